@@ -1,45 +1,36 @@
-use chrono::Utc;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use fukura::index::SearchSort;
-use fukura::models::{Author, Note, Privacy};
+use fukura::models::Author;
+use fukura::note_service::NoteBuilder;
 use fukura::repo::FukuraRepo;
-use std::collections::BTreeMap;
 use tempfile::TempDir;
 
 fn create_benchmark_data(repo: &FukuraRepo, count: usize) -> Vec<String> {
     let mut notes = Vec::new();
-    let now = Utc::now();
-
     for i in 0..count {
-        let mut meta = BTreeMap::new();
-        meta.insert("benchmark".into(), "true".into());
-        meta.insert("index".into(), i.to_string());
-
-        let note = Note {
-            title: format!("Benchmark Note {}", i),
-            body: format!(
+        let note = NoteBuilder::new(
+            format!("Benchmark Note {}", i),
+            format!(
                 "This is benchmark note number {} with some content about various topics like programming, \
                 troubleshooting, debugging, and software development. It contains keywords like error, \
                 fix, solution, problem, issue, and resolution. The content is designed to test search \
                 performance with realistic text patterns and vocabulary.",
                 i
             ),
-            tags: vec![
-                "benchmark".to_string(),
-                "test".to_string(),
-                format!("tag{}", i % 10),
-            ],
-            links: vec![format!("https://example.com/note/{}", i)],
-            meta,
-            solutions: vec![],
-            privacy: Privacy::Private,
-            created_at: now,
-            updated_at: now,
-            author: Author {
-                name: "Benchmark Author".into(),
-                email: Some("benchmark@test.com".into()),
-            },
-        };
+        )
+        .tags([
+            "benchmark".to_string(),
+            "test".to_string(),
+            format!("tag{}", i % 10),
+        ])
+        .links([format!("https://example.com/note/{}", i)])
+        .meta("benchmark", "true")
+        .meta("index", i.to_string())
+        .author(Author {
+            name: "Benchmark Author".into(),
+            email: Some("benchmark@test.com".into()),
+        })
+        .build();
 
         notes.push(note);
     }
@@ -120,25 +111,18 @@ fn bench_store_note(c: &mut Criterion) {
 
     c.bench_function("store_note", |b| {
         b.iter(|| {
-            let now = Utc::now();
-            let mut meta = BTreeMap::new();
-            meta.insert("benchmark".into(), "true".into());
-
-            let note = Note {
-                title: "Benchmark Store Note".into(),
-                body: "This is a benchmark note for testing store performance.".into(),
-                tags: vec!["benchmark".to_string(), "store".to_string()],
-                links: vec![],
-                meta,
-                solutions: vec![],
-                privacy: Privacy::Private,
-                created_at: now,
-                updated_at: now,
-                author: Author {
-                    name: "Benchmark Author".into(),
-                    email: Some("benchmark@test.com".into()),
-                },
-            };
+            let note = NoteBuilder::new(
+                "Benchmark Store Note",
+                "This is a benchmark note for testing store performance.",
+            )
+            .tag("benchmark")
+            .tag("store")
+            .meta("benchmark", "true")
+            .author(Author {
+                name: "Benchmark Author".into(),
+                email: Some("benchmark@test.com".into()),
+            })
+            .build();
 
             let record = repo.store_note(note).expect("Failed to store note");
             black_box(record)
@@ -0,0 +1,101 @@
+//! Example fukura WASM plugin: tags notes whose title or body mention a
+//! keyword. Configure it with a keyword-to-tag table:
+//!
+//! ```toml
+//! [[wasm_plugins]]
+//! path = ".fukura/plugins/keyword_tagger.wasm"
+//! [wasm_plugins.settings.keywords]
+//! stripe = "payments"
+//! OOMKilled = "memory"
+//! ```
+//!
+//! The note is handled as plain JSON, so fields this plugin doesn't know
+//! about pass through untouched.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+struct Input {
+    note: Value,
+    #[serde(default)]
+    settings: Settings,
+}
+
+#[derive(Deserialize, Default)]
+struct Settings {
+    #[serde(default)]
+    keywords: serde_json::Map<String, Value>,
+}
+
+#[link(wasm_import_module = "fukura")]
+extern "C" {
+    #[link_name = "log"]
+    fn fukura_log(ptr: *const u8, len: usize);
+}
+
+#[no_mangle]
+pub extern "C" fn fukura_api_version() -> i32 {
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn fukura_alloc(len: i32) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len as usize);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+#[no_mangle]
+pub extern "C" fn fukura_process(ptr: *mut u8, len: i32) -> i64 {
+    // fuku wrote `len` bytes into the buffer it got from `fukura_alloc`
+    let input = unsafe { Vec::from_raw_parts(ptr, len as usize, len as usize) };
+    let Ok(Input { mut note, settings }) = serde_json::from_slice::<Input>(&input) else {
+        log("input isn't the guest API's JSON");
+        return 0;
+    };
+    if !tag(&mut note, &settings) {
+        return 0;
+    }
+    let output = serde_json::to_vec(&note).expect("a note serializes");
+    let packed = ((output.as_ptr() as i64) << 32) | output.len() as i64;
+    std::mem::forget(output);
+    packed
+}
+
+/// Add the tag of every keyword the note mentions, returning whether any
+/// was new
+fn tag(note: &mut Value, settings: &Settings) -> bool {
+    let text = ["title", "body"]
+        .iter()
+        .filter_map(|field| note[*field].as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase();
+    let mut tags: Vec<String> = note["tags"]
+        .as_array()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut changed = false;
+    for (keyword, tag) in &settings.keywords {
+        let Some(tag) = tag.as_str() else { continue };
+        if text.contains(&keyword.to_lowercase()) && !tags.iter().any(|t| t == tag) {
+            log(&format!("tagging '{}' for '{}'", tag, keyword));
+            tags.push(tag.to_string());
+            changed = true;
+        }
+    }
+    if changed {
+        note["tags"] = tags.into();
+    }
+    changed
+}
+
+fn log(message: &str) {
+    unsafe { fukura_log(message.as_ptr(), message.len()) }
+}
@@ -0,0 +1,365 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::daemon::SolutionHit;
+use crate::ingest::{ErrorBlock, LogScanner};
+use crate::models::{Author, Note};
+use crate::note_service::NoteBuilder;
+
+/// Matched solutions shown per error
+pub const MAX_SOLUTIONS: usize = 3;
+
+/// Characters of a solution snippet put in an annotation
+const SNIPPET_CHARS: usize = 300;
+
+/// A source position mentioned by an error, for the annotation to point at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+/// An error found in a build log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CiError {
+    pub message: String,
+    pub context: Vec<String>,
+    pub location: Option<SourceLocation>,
+}
+
+/// An error and the notes that solved it before
+#[derive(Debug, Clone, Serialize)]
+pub struct CiFinding {
+    #[serde(flatten)]
+    pub error: CiError,
+    pub solutions: Vec<SolutionHit>,
+}
+
+/// The GitHub Actions run being reported on, from its environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunInfo {
+    pub server_url: Option<String>,
+    pub repository: Option<String>,
+    pub run_id: Option<String>,
+    pub workflow: Option<String>,
+    pub sha: Option<String>,
+    /// Checkout directory; stripped from absolute paths in annotations
+    pub workspace: Option<String>,
+}
+
+impl RunInfo {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            server_url: var("GITHUB_SERVER_URL"),
+            repository: var("GITHUB_REPOSITORY"),
+            run_id: var("GITHUB_RUN_ID"),
+            workflow: var("GITHUB_WORKFLOW"),
+            sha: var("GITHUB_SHA"),
+            workspace: var("GITHUB_WORKSPACE"),
+        }
+    }
+
+    pub fn url(&self) -> Option<String> {
+        Some(format!(
+            "{}/{}/actions/runs/{}",
+            self.server_url.as_deref().unwrap_or("https://github.com"),
+            self.repository.as_ref()?,
+            self.run_id.as_ref()?
+        ))
+    }
+}
+
+/// Terminal colors and the timestamps of downloaded Actions logs
+fn clean_line(line: &str) -> String {
+    static ANSI: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").expect("valid ANSI pattern"));
+    static TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?Z ").expect("valid timestamp")
+    });
+    let line = ANSI.replace_all(line, "");
+    TIMESTAMP.replace(&line, "").into_owned()
+}
+
+/// First `file:line[:col]`, `file(line,col)` or Python `File "...", line N`
+/// in the lines of an error
+pub fn location_of<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<SourceLocation> {
+    static COLON: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?:^|[\s(\[])(/?(?:[\w.-]+/)*[\w-][\w.-]*\.[A-Za-z][A-Za-z0-9]*):(\d+)(?::(\d+))?",
+        )
+        .expect("valid location pattern")
+    });
+    static PAREN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(/?(?:[\w.-]+/)*[\w-][\w.-]*\.[A-Za-z][A-Za-z0-9]*)\((\d+),(\d+)\)")
+            .expect("valid location pattern")
+    });
+    static PYTHON: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"File "([^"]+)", line (\d+)"#).expect("valid location pattern"));
+    lines.into_iter().find_map(|line| {
+        let captures = COLON
+            .captures(line)
+            .or_else(|| PAREN.captures(line))
+            .or_else(|| PYTHON.captures(line))?;
+        Some(SourceLocation {
+            file: captures[1].to_string(),
+            line: captures[2].parse().ok()?,
+            column: captures
+                .get(3)
+                .and_then(|column| column.as_str().parse().ok()),
+        })
+    })
+}
+
+/// Distinct errors of a build log, at most `max`
+pub fn scan_log(log: &str, max: usize) -> Vec<CiError> {
+    let mut scanner = LogScanner::new();
+    let mut blocks: Vec<ErrorBlock> = log
+        .lines()
+        .filter_map(|line| scanner.push(&clean_line(line)))
+        .collect();
+    blocks.extend(scanner.finish());
+
+    let mut seen = HashSet::new();
+    blocks
+        .into_iter()
+        // Our own annotations, when a log is scanned twice
+        .filter(|block| !block.message.starts_with("::"))
+        .filter(|block| seen.insert(block.message.clone()))
+        .take(max)
+        .map(|block| CiError {
+            location: location_of(
+                std::iter::once(block.message.as_str())
+                    .chain(block.context.iter().map(String::as_str)),
+            ),
+            message: block.message,
+            context: block.context,
+        })
+        .collect()
+}
+
+/// Escaping for workflow command data
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escaping for workflow command properties
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn note_link(link_base: Option<&str>, note_id: &str) -> Option<String> {
+    link_base.map(|base| format!("{}/notes/{}", base.trim_end_matches('/'), note_id))
+}
+
+fn solution_line(solution: &SolutionHit, link_base: Option<&str>) -> String {
+    let short_id: String = solution.note_id.chars().take(8).collect();
+    let mut line = format!(
+        "Known fix ({:.0}%): {} (fuku view {})",
+        solution.confidence * 100.0,
+        solution.title,
+        short_id
+    );
+    if let Some(link) = note_link(link_base, &solution.note_id) {
+        line.push_str(&format!(" {}", link));
+    }
+    line
+}
+
+/// A `::error` workflow command for a finding
+pub fn annotation(finding: &CiFinding, run: &RunInfo, link_base: Option<&str>) -> String {
+    let mut properties = Vec::new();
+    if let Some(location) = &finding.error.location {
+        let file = match &run.workspace {
+            Some(workspace) => location
+                .file
+                .strip_prefix(workspace.as_str())
+                .map(|file| file.trim_start_matches('/'))
+                .unwrap_or(&location.file),
+            None => &location.file,
+        };
+        properties.push(format!("file={}", escape_property(file)));
+        properties.push(format!("line={}", location.line));
+        if let Some(column) = location.column {
+            properties.push(format!("col={}", column));
+        }
+    }
+    let title = match finding.solutions.len() {
+        0 => "Build error".to_string(),
+        1 => "fuku: 1 known fix".to_string(),
+        n => format!("fuku: {} known fixes", n),
+    };
+    properties.push(format!("title={}", escape_property(&title)));
+
+    let mut message = finding.error.message.clone();
+    for solution in &finding.solutions {
+        message.push_str("\n\n");
+        message.push_str(&solution_line(solution, link_base));
+        let snippet: String = solution.snippet.chars().take(SNIPPET_CHARS).collect();
+        if !snippet.trim().is_empty() {
+            message.push('\n');
+            message.push_str(snippet.trim());
+        }
+    }
+    format!(
+        "::error {}::{}",
+        properties.join(","),
+        escape_data(&message)
+    )
+}
+
+/// Markdown for the job summary (`$GITHUB_STEP_SUMMARY`)
+pub fn step_summary(findings: &[CiFinding], link_base: Option<&str>) -> String {
+    let known = findings.iter().filter(|f| !f.solutions.is_empty()).count();
+    let mut summary = format!(
+        "### fuku: {} errors, {} with known fixes\n\n",
+        findings.len(),
+        known
+    );
+    for finding in findings {
+        summary.push_str(&format!(
+            "- `{}`\n",
+            finding.error.message.replace('`', "'")
+        ));
+        for solution in &finding.solutions {
+            let short_id: String = solution.note_id.chars().take(8).collect();
+            let title = match note_link(link_base, &solution.note_id) {
+                Some(link) => format!("[{}]({})", solution.title, link),
+                None => solution.title.clone(),
+            };
+            summary.push_str(&format!(
+                "  - {} ({:.0}%, `fuku view {}`)\n",
+                title,
+                solution.confidence * 100.0,
+                short_id
+            ));
+        }
+    }
+    summary
+}
+
+/// A note recording a failed run, its errors and the fixes suggested
+pub fn failure_note(findings: &[CiFinding], run: &RunInfo) -> Note {
+    let now = Utc::now();
+    let first = findings
+        .first()
+        .map(|finding| finding.error.message.chars().take(80).collect::<String>())
+        .unwrap_or_else(|| "no errors recognized".to_string());
+    let title = match &run.workflow {
+        Some(workflow) => format!("CI failure in {}: {}", workflow, first),
+        None => format!("CI failure: {}", first),
+    };
+
+    let mut body = String::from("## CI failure\n\n");
+    if let Some(url) = run.url() {
+        body.push_str(&format!("**Run**: {}\n\n", url));
+    }
+    if let Some(sha) = &run.sha {
+        body.push_str(&format!("**Commit**: `{}`\n\n", sha));
+    }
+    for finding in findings {
+        body.push_str("```\n");
+        body.push_str(&finding.error.message);
+        body.push('\n');
+        for line in &finding.error.context {
+            body.push_str(line);
+            body.push('\n');
+        }
+        body.push_str("```\n\n");
+        for solution in &finding.solutions {
+            body.push_str(&format!("- {}\n", solution_line(solution, None)));
+        }
+        if !finding.solutions.is_empty() {
+            body.push('\n');
+        }
+    }
+    body.push_str(&format!("**Time**: {}", now.format("%Y-%m-%d %H:%M:%S")));
+
+    let mut meta = BTreeMap::new();
+    meta.insert("source".to_string(), "ci".to_string());
+    if let Some(url) = run.url() {
+        meta.insert("ci_run".to_string(), url);
+    }
+    if let Some(sha) = &run.sha {
+        meta.insert("commit".to_string(), sha.clone());
+    }
+
+    NoteBuilder::new(title, body)
+        .tag("auto-captured")
+        .tag("ci")
+        .tag("error")
+        .links(run.url())
+        .meta_map(meta)
+        .author(Author {
+            name: std::env::var("GITHUB_ACTOR")
+                .or_else(|_| std::env::var("USER"))
+                .unwrap_or_else(|_| "ci".to_string()),
+            email: None,
+        })
+        .created_at(now)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_log_finds_errors_and_locations() {
+        let log = "\
+2024-06-01T10:00:00.0000000Z    Compiling app v0.1.0
+2024-06-01T10:00:01.0000000Z \x1b[31merror[E0425]\x1b[0m: cannot find value `count` in this scope
+2024-06-01T10:00:01.0000000Z   --> src/main.rs:10:5
+2024-06-01T10:00:02.0000000Z error: could not compile `app` due to 1 previous error
+2024-06-01T10:00:02.0000000Z error: could not compile `app` due to 1 previous error
+";
+        let errors = scan_log(log, 10);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].message,
+            "error[E0425]: cannot find value `count` in this scope"
+        );
+        assert_eq!(
+            errors[0].location,
+            Some(SourceLocation {
+                file: "src/main.rs".to_string(),
+                line: 10,
+                column: Some(5),
+            })
+        );
+        assert_eq!(errors[1].location, None);
+        assert_eq!(scan_log(log, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_annotation_escapes_and_links() {
+        let finding = CiFinding {
+            error: CiError {
+                message: "error TS2322: Type 'string' is not assignable, 100% sure".to_string(),
+                context: vec![],
+                location: location_of(["/home/runner/work/app/src/a.ts(3,7): error TS2322"]),
+            },
+            solutions: vec![SolutionHit {
+                note_id: "abcdef1234567890".to_string(),
+                title: "Narrow the union first".to_string(),
+                snippet: "Use a type guard".to_string(),
+                confidence: 0.9,
+            }],
+        };
+        let run = RunInfo {
+            workspace: Some("/home/runner/work/app".to_string()),
+            ..RunInfo::default()
+        };
+        let line = annotation(&finding, &run, Some("https://fuku.example.com/"));
+        assert!(line.starts_with("::error file=src/a.ts,line=3,col=7,title=fuku%3A 1 known fix::"));
+        assert!(line.contains("100%25 sure%0A%0AKnown fix (90%25): Narrow the union first"));
+        assert!(line.contains("https://fuku.example.com/notes/abcdef1234567890"));
+        assert!(!line.contains('\n'));
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 
 use anyhow::Result;
 
+use crate::analyzer::Analyzer;
 use crate::repo::FukuraRepo;
 
 #[derive(Debug, Default)]
@@ -11,18 +12,46 @@ pub struct RedactionUpdateReport {
 }
 
 pub fn update_remote(repo: &FukuraRepo, remote: Option<&str>) -> Result<Option<String>> {
-    let mut cfg = repo.config()?;
+    let mut cfg = repo.local_config()?;
     cfg.set_default_remote(remote.map(|s| s.to_string()));
+    repo.ensure_writable()?;
     cfg.save(&repo.config_path())?;
     Ok(cfg.default_remote.clone())
 }
 
+/// Set the search analyzer; returns whether it changed. The index is rebuilt
+/// the next time the repository is opened.
+pub fn update_analyzer(repo: &FukuraRepo, analyzer: Analyzer) -> Result<bool> {
+    let mut cfg = repo.local_config()?;
+    if cfg.search.analyzer == analyzer {
+        return Ok(false);
+    }
+    cfg.search.analyzer = analyzer;
+    repo.ensure_writable()?;
+    cfg.save(&repo.config_path())?;
+    Ok(true)
+}
+
+/// Trust `public_key` to sign notes as `name`; returns whether it was new
+pub fn trust_signing_key(repo: &FukuraRepo, name: &str, public_key: &str) -> Result<bool> {
+    let public_key = crate::signing::parse_public_key(public_key)?;
+    let mut cfg = repo.local_config()?;
+    let keys = cfg.signing.trusted.entry(name.to_string()).or_default();
+    if keys.contains(&public_key) {
+        return Ok(false);
+    }
+    keys.push(public_key);
+    repo.ensure_writable()?;
+    cfg.save(&repo.config_path())?;
+    Ok(true)
+}
+
 pub fn update_redaction(
     repo: &FukuraRepo,
     additions: Vec<(String, String)>,
     removals: Vec<String>,
 ) -> Result<RedactionUpdateReport> {
-    let mut cfg = repo.config()?;
+    let mut cfg = repo.local_config()?;
     let mut report = RedactionUpdateReport::default();
     for (key, pattern) in additions {
         cfg.set_redaction_override(&key, &pattern);
@@ -33,6 +62,7 @@ pub fn update_redaction(
             report.removed.push(key);
         }
     }
+    repo.ensure_writable()?;
     cfg.save(&repo.config_path())?;
     Ok(report)
 }
@@ -1,24 +1,67 @@
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tokio::time;
-use tracing::{debug, info};
-
-use crate::models::{Author, Note, Privacy};
+use tracing::{debug, info, warn};
+
+use crate::activity::{Activity, ActivitySession};
+use crate::activity_storage::{ActivityStorage, CommandLog, CommandRecord};
+use crate::ai::Summarizer;
+use crate::async_repo::{AsyncRepo, AsyncRepos};
+use crate::config::{CaptureConfig, IpcConfig, SmtpConfig};
+use crate::dedupe::DEFAULT_THRESHOLD;
+use crate::feedback::{FeedbackEvent, FeedbackKind, FeedbackStats, FeedbackStore};
+use crate::file_watcher::FileWatcher;
+use crate::git::{self, GitContext};
+use crate::ipc::{read_message, HookMessage};
+use crate::issues::{IssueLinker, IssueReason, ISSUE_URL_META};
+use crate::mail::{DigestSchedule, Mailer};
+use crate::models::{Author, Note, NoteRecord};
+use crate::note_service::{NoteBuilder, NoteService};
 use crate::notification::NotificationManager;
+use crate::patterns::{failure_message, normalize_message, PatternStore};
+use crate::prompt_state::HEARTBEAT_INTERVAL;
+use crate::recording::{
+    build_steps, render_steps, slow_duration, ActiveRecording, ErrorStreak, RecordingStore,
+    StreakEvent,
+};
+use crate::redaction::Redactor;
 use crate::repo::FukuraRepo;
+use crate::report::Report;
+use crate::titles::{render_title, TitleContext};
+
+pub use crate::patterns::ErrorPattern;
 
 /// Daemon for monitoring and capturing error patterns
 pub struct FukuraDaemon {
-    pub repo: Arc<FukuraRepo>,
+    pub repo: AsyncRepo,
+    /// `repo` and the repositories of the directories commands ran in
+    repos: AsyncRepos,
     pub sessions: Arc<RwLock<HashMap<String, ActiveSession>>>,
     pub error_patterns: Arc<RwLock<HashMap<String, ErrorPattern>>>,
     pub repo_path: std::path::PathBuf,
     config: DaemonConfig,
     notification_manager: Option<Arc<NotificationManager>>,
+    command_log: Option<Arc<CommandLog>>,
+    capture: Arc<CaptureConfig>,
+    /// The repository's redaction rules, for captured environment values
+    redactor: Redactor,
+    pattern_store: Arc<PatternStore>,
+    feedback: Arc<FeedbackStore>,
+    pending_feedback: Arc<RwLock<HashMap<String, PendingSuggestion>>>,
+    issues: Option<Arc<IssueLinker>>,
+    /// Failures in a row that start a recording (`recording.auto_start_after`)
+    auto_record: Option<usize>,
+    streaks: Arc<RwLock<HashMap<String, ErrorStreak>>>,
+    /// Quiet time after which loose objects are packed (`gc.idle_minutes`)
+    gc_idle: Option<Duration>,
+    ipc: IpcConfig,
+    /// Mail server of the weekly digest (`smtp.weekly`)
+    digest: Option<SmtpConfig>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +109,8 @@ pub struct CommandEntry {
     pub exit_code: Option<i32>,
     pub timestamp: SystemTime,
     pub working_directory: String,
+    /// How long the command ran, when the shell hook timed it
+    pub duration: Option<Duration>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,21 +125,18 @@ pub struct ErrorEntry {
 #[derive(Debug, Clone)]
 pub struct SessionContext {
     pub working_directory: String,
-    pub git_branch: Option<String>,
-    pub git_status: Option<String>,
+    /// `None` outside a git repository
+    pub git: Option<GitContext>,
     pub environment: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct ErrorPattern {
-    pub normalized_message: String,
-    pub fingerprint: String,
-    pub occurrences: u32,
-    pub last_seen: SystemTime,
-    pub solutions: Vec<String>,
+impl SessionContext {
+    pub fn git_branch(&self) -> Option<&str> {
+        self.git.as_ref().and_then(|git| git.branch.as_deref())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct SolutionHit {
     pub note_id: String,
     pub title: String,
@@ -102,24 +144,115 @@ pub struct SolutionHit {
     pub confidence: f64,
 }
 
+/// Shared state the IPC servers hand to every hook message
+#[derive(Clone)]
+struct HookContext {
+    repos: AsyncRepos,
+    sessions: Arc<RwLock<HashMap<String, ActiveSession>>>,
+    notification_manager: Option<Arc<NotificationManager>>,
+    command_log: Option<Arc<CommandLog>>,
+    capture: Arc<CaptureConfig>,
+    error_patterns: Arc<RwLock<HashMap<String, ErrorPattern>>>,
+    pattern_store: Arc<PatternStore>,
+    feedback: Arc<FeedbackStore>,
+    pending_feedback: Arc<RwLock<HashMap<String, PendingSuggestion>>>,
+    issues: Option<Arc<IssueLinker>>,
+    auto_record: Option<usize>,
+    /// Error streak of each shell session
+    streaks: Arc<RwLock<HashMap<String, ErrorStreak>>>,
+}
+
+/// Notes suggested to a session after its last failure, waiting for the
+/// outcome of the next command
+#[derive(Debug, Clone)]
+struct PendingSuggestion {
+    note_ids: Vec<String>,
+    suggested_at: SystemTime,
+}
+
+/// File watcher state for an active `fuku rec` recording
+struct RecordingWatch {
+    session: ActivitySession,
+    rx: mpsc::Receiver<Activity>,
+    _watcher: Option<FileWatcher>,
+}
+
+impl RecordingWatch {
+    /// Move pending file changes into the session, returning how many arrived
+    fn drain(&mut self) -> usize {
+        let mut received = 0;
+        while let Ok(activity) = self.rx.try_recv() {
+            self.session.add_activity(activity);
+            received += 1;
+        }
+        received
+    }
+}
+
 impl FukuraDaemon {
     /// Create a new daemon instance
     pub fn new(repo_path: &Path, config: DaemonConfig) -> Result<Self> {
-        let repo = Arc::new(FukuraRepo::discover(Some(repo_path))?);
+        let repo = AsyncRepo::new(Arc::new(FukuraRepo::discover(Some(repo_path))?))?;
         let notification_manager = NotificationManager::new(repo_path).ok().map(Arc::new);
+        let repo_config = repo.repo().config().unwrap_or_default();
+        let redactor = Redactor::default_with_overrides(&repo_config.redaction_overrides);
+        let command_log = CommandLog::new(repo.repo().root())
+            .ok()
+            .map(|log| Arc::new(log.with_redactor(redactor.clone())));
+        let pattern_store =
+            Arc::new(PatternStore::new(repo.repo().root()).with_redactor(redactor.clone()));
+        let issues = match IssueLinker::from_config(&repo_config.integrations) {
+            Ok(issues) => issues.map(Arc::new),
+            Err(e) => {
+                warn!("Issue tracker integration disabled: {}", e);
+                None
+            }
+        };
+
+        let feedback = Arc::new(FeedbackStore::new(repo.repo().root()));
 
         Ok(Self {
+            repos: AsyncRepos::with(repo.clone()),
             repo,
             sessions: Arc::new(RwLock::new(HashMap::new())),
             error_patterns: Arc::new(RwLock::new(HashMap::new())),
             repo_path: repo_path.to_path_buf(),
             config,
             notification_manager,
+            command_log,
+            capture: Arc::new(repo_config.capture),
+            redactor,
+            pattern_store,
+            feedback,
+            pending_feedback: Arc::new(RwLock::new(HashMap::new())),
+            issues,
+            auto_record: repo_config.recording.auto_start_after,
+            streaks: Arc::new(RwLock::new(HashMap::new())),
+            gc_idle: (repo_config.gc.idle_minutes > 0)
+                .then(|| Duration::from_secs(repo_config.gc.idle_minutes * 60)),
+            ipc: repo_config.ipc,
+            digest: repo_config.smtp.filter(|smtp| smtp.weekly),
         })
     }
 
-    /// Get commands from all sessions since a specific time
+    /// Get commands from all sessions since a specific time.
+    /// Reads the persisted command log so history survives daemon restarts.
     pub async fn get_commands_since(&self, since: SystemTime) -> Vec<CommandEntry> {
+        if let Some(log) = &self.command_log {
+            if let Ok(records) = log.read_since(since) {
+                return records
+                    .into_iter()
+                    .map(|record| CommandEntry {
+                        duration: record.duration(),
+                        command: record.command,
+                        exit_code: record.exit_code,
+                        timestamp: record.timestamp,
+                        working_directory: record.working_directory,
+                    })
+                    .collect();
+            }
+        }
+
         let sessions = self.sessions.read().await;
         let mut commands = Vec::new();
 
@@ -175,17 +308,18 @@ impl FukuraDaemon {
 
         // Load existing error patterns
         self.load_error_patterns().await?;
+        self.update_hooks();
 
         // Start monitoring tasks
         let sessions1 = self.sessions.clone();
         let sessions2 = self.sessions.clone();
-        let sessions3 = self.sessions.clone();
         let error_patterns = self.error_patterns.clone();
         let config1 = self.config.clone();
         let config2 = self.config.clone();
         let repo = self.repo.clone();
         let repo_path = self.repo_path.clone();
-        let notif_mgr = self.notification_manager.clone();
+        let capture = self.capture.clone();
+        let hook_context = self.hook_context();
 
         // Session cleanup task
         tokio::spawn(async move {
@@ -210,16 +344,96 @@ impl FukuraDaemon {
             let mut interval = time::interval(Duration::from_secs(30));
             loop {
                 interval.tick().await;
-                Self::auto_generate_notes(&sessions2, &repo, &repo_path).await;
+                Self::auto_generate_notes(&sessions2, &repo, &repo_path, &capture).await;
+            }
+        });
+
+        // Archive expired notes
+        let archive_repo = self.repo.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                match archive_repo
+                    .write(|repo| repo.archive_expired(chrono::Utc::now()))
+                    .await
+                {
+                    Ok(archived) if !archived.is_empty() => {
+                        info!("Archived {} expired notes", archived.len())
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to archive expired notes: {}", e),
+                }
+            }
+        });
+
+        // `fuku gc --auto` once no commands have come in for a while
+        if let Some(idle) = self.gc_idle {
+            let gc_repo = self.repo.clone();
+            let gc_sessions = self.sessions.clone();
+            tokio::spawn(async move {
+                let mut interval = time::interval(idle.min(Duration::from_secs(300)));
+                loop {
+                    interval.tick().await;
+                    if !Self::is_idle(&gc_sessions, idle).await {
+                        continue;
+                    }
+                    match gc_repo.write(|repo| repo.auto_pack()).await {
+                        Ok(Some(report)) => info!(
+                            "Packed {} loose objects, reclaiming {} bytes",
+                            report.object_count, report.reclaimed_bytes
+                        ),
+                        Ok(None) => {}
+                        Err(e) => warn!("Automatic gc failed: {}", e),
+                    }
+                }
+            });
+        }
+
+        // Weekly digest by email
+        if let Some(smtp) = self.digest.clone() {
+            let digest_repo = self.repo.clone();
+            tokio::spawn(async move {
+                let mut interval = time::interval(Duration::from_secs(15 * 60));
+                loop {
+                    interval.tick().await;
+                    let smtp = smtp.clone();
+                    match digest_repo
+                        .read(move |repo| Self::send_weekly_digest(repo, &smtp))
+                        .await
+                    {
+                        Ok(true) => info!("Sent the weekly digest"),
+                        Ok(false) => {}
+                        Err(e) => warn!("Failed to send the weekly digest: {}", e),
+                    }
+                }
+            });
+        }
+
+        // Heartbeat read by `fuku prompt-status` instead of a socket round-trip
+        let prompt_state = self.repo.repo().prompt_state();
+        tokio::spawn(async move {
+            let mut interval = time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = prompt_state.beat(std::process::id()) {
+                    debug!("Failed to write daemon heartbeat: {}", e);
+                }
             }
         });
 
+        // File change tracking for `fuku rec` recordings
+        let recording_root = self.repo.repo().root().to_path_buf();
+        tokio::spawn(async move {
+            Self::watch_recordings(recording_root).await;
+        });
+
         // Start Unix Domain Socket server for IPC (best practice)
-        let sessions_for_server = sessions3.clone();
         let socket_path = self.get_socket_path();
+        let allow_other_users = self.ipc.allow_other_users;
         tokio::spawn(async move {
             if let Err(e) =
-                Self::start_socket_server(sessions_for_server, notif_mgr, socket_path).await
+                Self::start_socket_server(hook_context, socket_path, allow_other_users).await
             {
                 tracing::error!("Socket server error: {}", e);
             }
@@ -229,274 +443,261 @@ impl FukuraDaemon {
         Ok(())
     }
 
+    /// Watch the project while `fuku rec` recordings are active and store
+    /// the file changes as activity sessions keyed by each recording's session
+    /// id, so `fuku done` can attach them to the generated note. Named
+    /// recordings only watch the directory they were started in.
+    async fn watch_recordings(root: PathBuf) {
+        let recordings = RecordingStore::new(&root);
+        let storage = match ActivityStorage::new(&root) {
+            Ok(storage) => storage,
+            Err(e) => {
+                warn!("Recording file tracking disabled: {}", e);
+                return;
+            }
+        };
+
+        let mut active: HashMap<String, RecordingWatch> = HashMap::new();
+        let mut interval = time::interval(Duration::from_secs(2));
+        loop {
+            interval.tick().await;
+
+            let current = match recordings.list() {
+                Ok(current) => current,
+                Err(e) => {
+                    debug!("Failed to read recordings: {}", e);
+                    continue;
+                }
+            };
+
+            let finished: Vec<String> = active
+                .keys()
+                .filter(|id| !current.iter().any(|r| &r.session_id == *id))
+                .cloned()
+                .collect();
+            for session_id in finished {
+                if let Some(mut watch) = active.remove(&session_id) {
+                    watch.drain();
+                    watch.session.finish();
+                    if let Err(e) = storage.store_session(&watch.session) {
+                        warn!("Failed to store recording activity: {}", e);
+                    }
+                    debug!("Stopped watching files for {}", session_id);
+                }
+            }
+
+            for recording in current {
+                if active.contains_key(&recording.session_id) {
+                    continue;
+                }
+                let session_id = recording.session_id;
+                let watched = recording.directory.unwrap_or_else(|| root.clone());
+                let (tx, rx) = mpsc::channel(256);
+                let mut watcher = FileWatcher::new(tx, vec![watched]);
+                let watcher = match watcher.start_watching(session_id.clone()).await {
+                    Ok(()) => Some(watcher),
+                    Err(e) => {
+                        warn!("Failed to watch files for recording: {}", e);
+                        None
+                    }
+                };
+                let session = storage.load_session(&session_id).unwrap_or_else(|_| {
+                    let mut session = ActivitySession::new(recording.title);
+                    session.id = session_id.clone();
+                    session
+                });
+                debug!("Watching files for recording {}", session_id);
+                active.insert(
+                    session_id,
+                    RecordingWatch {
+                        session,
+                        rx,
+                        _watcher: watcher,
+                    },
+                );
+            }
+
+            for watch in active.values_mut() {
+                if watch.drain() > 0 {
+                    if let Err(e) = storage.store_session(&watch.session) {
+                        warn!("Failed to store recording activity: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Mail the last seven days to `[smtp] to` when the weekly digest is due.
+    /// Returns whether it was sent.
+    fn send_weekly_digest(repo: &FukuraRepo, smtp: &SmtpConfig) -> Result<bool> {
+        let schedule = DigestSchedule::new(repo.root());
+        let now = chrono::Local::now();
+        if !schedule.due(now) {
+            return Ok(false);
+        }
+        let until = SystemTime::now();
+        let since = until - Duration::from_secs(7 * 24 * 60 * 60);
+        let report = Report::for_repo(repo, since, until)?;
+        Mailer::new(smtp)?.send_report(&report, &smtp.to)?;
+        schedule.mark_sent(now)?;
+        Ok(true)
+    }
+
+    /// Hooks installed by an older version may send messages this daemon
+    /// no longer understands, or write to a socket it doesn't listen on
+    fn update_hooks(&self) {
+        let hooks = crate::hooks::HookManager::new(&self.repo_path);
+        if self.ipc.keep_hooks {
+            for outdated in hooks.installed().iter().filter(|h| !h.is_current()) {
+                warn!(
+                    "{} hooks in {} are outdated; run 'fuku daemon --update-hooks'",
+                    outdated.shell,
+                    outdated.path.display()
+                );
+            }
+            return;
+        }
+        match hooks.regenerate() {
+            Ok(replaced) => {
+                for hooks in replaced {
+                    info!(
+                        "Regenerated {} hooks in {} (version {} to {})",
+                        hooks.shell,
+                        hooks.path.display(),
+                        hooks.version,
+                        crate::hooks::HOOK_VERSION
+                    );
+                }
+            }
+            Err(e) => warn!("Failed to regenerate shell hooks: {}", e),
+        }
+    }
+
     /// Get socket path for IPC
     fn get_socket_path(&self) -> std::path::PathBuf {
-        self.repo_path.join(".fukura").join("daemon.sock")
+        crate::ipc::socket_path(&self.repo_path, &self.ipc)
+    }
+
+    fn hook_context(&self) -> HookContext {
+        HookContext {
+            repos: self.repos.clone(),
+            sessions: self.sessions.clone(),
+            notification_manager: self.notification_manager.clone(),
+            command_log: self.command_log.clone(),
+            capture: self.capture.clone(),
+            error_patterns: self.error_patterns.clone(),
+            pattern_store: self.pattern_store.clone(),
+            feedback: self.feedback.clone(),
+            pending_feedback: self.pending_feedback.clone(),
+            issues: self.issues.clone(),
+            auto_record: self.auto_record,
+            streaks: self.streaks.clone(),
+        }
     }
 
     /// Start IPC server for shell hook communication (BEST PRACTICE: Unix Socket / Named Pipe)
     async fn start_socket_server(
-        sessions: Arc<RwLock<HashMap<String, ActiveSession>>>,
-        notif_mgr: Option<Arc<NotificationManager>>,
+        ctx: HookContext,
         socket_path: std::path::PathBuf,
+        allow_other_users: bool,
     ) -> Result<()> {
         #[cfg(unix)]
         {
-            Self::start_unix_socket_server(sessions, notif_mgr, socket_path).await
+            Self::start_unix_socket_server(ctx, socket_path, allow_other_users).await
         }
 
         #[cfg(windows)]
         {
-            Self::start_named_pipe_server(sessions, notif_mgr, socket_path).await
+            let _ = allow_other_users;
+            Self::start_named_pipe_server(ctx, socket_path).await
+        }
+    }
+
+    /// Persist a captured command to the append-only history log
+    fn persist_command(
+        command_log: &Option<Arc<CommandLog>>,
+        session_id: &str,
+        command: &str,
+        exit_code: Option<i32>,
+        working_dir: &str,
+        stderr: Option<&str>,
+        duration_ms: Option<u64>,
+    ) {
+        if let Some(log) = command_log {
+            let record = CommandRecord {
+                session_id: session_id.to_string(),
+                command: command.to_string(),
+                exit_code,
+                timestamp: SystemTime::now(),
+                working_directory: working_dir.to_string(),
+                stderr: stderr.filter(|s| !s.is_empty()).map(|s| s.to_string()),
+                duration_ms,
+            };
+            if let Err(e) = log.append(&record) {
+                warn!("Failed to persist command history: {}", e);
+            }
         }
     }
 
+    /// Listen for hook messages on `socket_path`, which only the current
+    /// user can connect to. Peers running as another user are turned away
+    /// unless `allow_other_users` is set.
     #[cfg(unix)]
     async fn start_unix_socket_server(
-        sessions: Arc<RwLock<HashMap<String, ActiveSession>>>,
-        notif_mgr: Option<Arc<NotificationManager>>,
+        ctx: HookContext,
         socket_path: std::path::PathBuf,
+        allow_other_users: bool,
     ) -> Result<()> {
-        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::AsyncWriteExt;
         use tokio::net::UnixListener;
 
+        crate::ipc::prepare_socket_dir(&socket_path)?;
         // Remove old socket if exists
         let _ = std::fs::remove_file(&socket_path);
 
-        // Create Unix socket
-        let listener = UnixListener::bind(&socket_path)?;
+        // Create the socket under a 077 umask, so it is never open to other
+        // users, not even before the chmod. This is what guarantees access
+        // control: a custom [ipc] socket_path may be in a shared directory,
+        // where the 0700 runtime-dir check doesn't apply. The umask is process
+        // wide, but 077 only narrows what other threads create meanwhile.
+        // SAFETY: umask has no preconditions and cannot fail
+        let previous_umask = unsafe { libc::umask(0o077) };
+        let bound = UnixListener::bind(&socket_path);
+        // SAFETY: as above
+        unsafe { libc::umask(previous_umask) };
+        let listener = bound?;
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
         info!("IPC socket server listening on {:?}", socket_path);
+        let uid = crate::ipc::current_uid();
 
         loop {
             match listener.accept().await {
                 Ok((mut stream, _)) => {
-                    let sessions = sessions.clone();
-                    let notif_mgr = notif_mgr.clone();
+                    if !allow_other_users {
+                        match stream.peer_cred() {
+                            Ok(peer) if peer.uid() == uid => {}
+                            Ok(peer) => {
+                                warn!("Ignoring hook message from user {}", peer.uid());
+                                continue;
+                            }
+                            Err(e) => {
+                                warn!("Ignoring hook message without credentials: {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    let ctx = ctx.clone();
 
                     tokio::spawn(async move {
-                        let mut buffer = vec![0u8; 4096];
-                        match stream.read(&mut buffer).await {
-                            Ok(n) if n > 0 => {
-                                let data = &buffer[..n];
-                                if let Ok(msg) = String::from_utf8(data.to_vec()) {
-                                    // Parse message: "session_id|command|exit_code|working_dir|stderr"
-                                    let parts: Vec<&str> = msg.trim().split('|').collect();
-                                    if parts.len() >= 4 {
-                                        let session_id = parts[0];
-                                        let command = parts[1];
-                                        let exit_code = parts[2].parse::<i32>().unwrap_or(0);
-                                        let working_dir = parts[3];
-                                        let stderr_content =
-                                            if parts.len() >= 5 { parts[4] } else { "" };
-
-                                        // Record command
-                                        let mut sessions = sessions.write().await;
-
-                                        if !sessions.contains_key(session_id) {
-                                            sessions.insert(
-                                                session_id.to_string(),
-                                                ActiveSession {
-                                                    id: session_id.to_string(),
-                                                    start_time: SystemTime::now(),
-                                                    last_activity: SystemTime::now(),
-                                                    commands: Vec::new(),
-                                                    errors: Vec::new(),
-                                                    context: SessionContext {
-                                                        working_directory: working_dir.to_string(),
-                                                        git_branch: None,
-                                                        git_status: None,
-                                                        environment: HashMap::new(),
-                                                    },
-                                                    last_error_command: None,
-                                                    resolution_in_progress: false,
-                                                },
-                                            );
-                                        }
-
-                                        if let Some(session) = sessions.get_mut(session_id) {
-                                            session.commands.push(CommandEntry {
-                                                command: command.to_string(),
-                                                exit_code: Some(exit_code),
-                                                timestamp: SystemTime::now(),
-                                                working_directory: working_dir.to_string(),
-                                            });
-                                            session.last_activity = SystemTime::now();
-
-                                            // Check if error and send notification
-                                            if exit_code != 0 {
-                                                let error_message = if !stderr_content.is_empty() {
-                                                    format!(
-                                                        "Command '{}' failed: {}",
-                                                        command, stderr_content
-                                                    )
-                                                } else {
-                                                    format!(
-                                                        "Command '{}' failed with exit code {}",
-                                                        command, exit_code
-                                                    )
-                                                };
-
-                                                session.errors.push(ErrorEntry {
-                                                    message: error_message.clone(),
-                                                    normalized: error_message.clone(),
-                                                    source: "shell".to_string(),
-                                                    timestamp: SystemTime::now(),
-                                                    stderr_output: if !stderr_content.is_empty() {
-                                                        Some(stderr_content.to_string())
-                                                    } else {
-                                                        None
-                                                    },
-                                                });
-
-                                                // BEST PRACTICE: Create note immediately (like Git commit)
-                                                // Users can access via: fuku search, fuku view @latest
-                                                drop(sessions);
-                                                let wd_path = std::path::PathBuf::from(working_dir);
-                                                let repo_clone = Arc::new(
-                                                    FukuraRepo::discover(Some(&wd_path))
-                                                        .unwrap_or_else(|_| {
-                                                            FukuraRepo::discover(None)
-                                                                .expect("Failed to discover repo")
-                                                        }),
-                                                );
-
-                                                let body_text = if !stderr_content.is_empty() {
-                                                    format!(
-                                                        "## Command Failed\n\n```bash\n$ {}\n\n# Error output:\n{}\n```\n\n**Exit Code**: {}\n\n**Working Directory**: `{}`\n\n**Time**: {}",
-                                                        command,
-                                                        stderr_content,
-                                                        exit_code,
-                                                        working_dir,
-                                                        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")
-                                                    )
-                                                } else {
-                                                    format!(
-                                                        "## Command Failed\n\n```bash\n$ {}\n```\n\n**Exit Code**: {}\n\n**Error**: {}\n\n**Working Directory**: `{}`\n\n**Time**: {}",
-                                                        command,
-                                                        exit_code,
-                                                        error_message,
-                                                        working_dir,
-                                                        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")
-                                                    )
-                                                };
-
-                                                let note = Note {
-                                                    title: format!("Error: {}", command),
-                                                    body: body_text,
-                                                    tags: vec![
-                                                        "error".to_string(),
-                                                        "auto-captured".to_string(),
-                                                    ],
-                                                    links: vec![],
-                                                    meta: std::collections::BTreeMap::from([
-                                                        (
-                                                            "exit_code".to_string(),
-                                                            exit_code.to_string(),
-                                                        ),
-                                                        (
-                                                            "working_dir".to_string(),
-                                                            working_dir.to_string(),
-                                                        ),
-                                                    ]),
-                                                    solutions: vec![],
-                                                    privacy: Privacy::Private,
-                                                    created_at: chrono::Utc::now(),
-                                                    updated_at: chrono::Utc::now(),
-                                                    author: Author {
-                                                        name: std::env::var("USER").unwrap_or_else(
-                                                            |_| "unknown".to_string(),
-                                                        ),
-                                                        email: None,
-                                                    },
-                                                };
-
-                                                if let Ok(record) = repo_clone.store_note(note) {
-                                                    tracing::info!(
-                                                        "Note created: {} for error: {}",
-                                                        &record.object_id[..8],
-                                                        command
-                                                    );
-
-                                                    // WORLD-CLASS: Search for similar errors and solutions
-                                                    let similar_solutions =
-                                                        Self::find_similar_solutions(
-                                                            &repo_clone,
-                                                            command,
-                                                            exit_code,
-                                                        );
-
-                                                    // Send intelligent notification
-                                                    if let Some(ref nm) = notif_mgr {
-                                                        tracing::info!(
-                                                            "Sending notification for error: {}",
-                                                            command
-                                                        );
-                                                        if let Ok(solutions) = similar_solutions {
-                                                            if !solutions.is_empty() {
-                                                                tracing::info!(
-                                                                    "Found {} solutions",
-                                                                    solutions.len()
-                                                                );
-                                                                if let Err(e) = nm
-                                                                    .notify_error_with_solutions(
-                                                                        command,
-                                                                        &error_message,
-                                                                        &record.object_id,
-                                                                        &solutions,
-                                                                    )
-                                                                {
-                                                                    tracing::error!(
-                                                                        "Notification failed: {}",
-                                                                        e
-                                                                    );
-                                                                } else {
-                                                                    tracing::info!("Notification sent successfully with solutions");
-                                                                }
-                                                            } else if let Err(e) = nm
-                                                                .notify_error_with_id(
-                                                                    command,
-                                                                    &error_message,
-                                                                    &record.object_id,
-                                                                )
-                                                            {
-                                                                tracing::error!(
-                                                                    "Notification failed: {}",
-                                                                    e
-                                                                );
-                                                            } else {
-                                                                tracing::info!("Notification sent successfully");
-                                                            }
-                                                        } else if let Err(e) = nm
-                                                            .notify_error_with_id(
-                                                                command,
-                                                                &error_message,
-                                                                &record.object_id,
-                                                            )
-                                                        {
-                                                            tracing::error!(
-                                                                "Notification failed: {}",
-                                                                e
-                                                            );
-                                                        } else {
-                                                            tracing::info!(
-                                                                "Notification sent successfully"
-                                                            );
-                                                        }
-                                                    } else {
-                                                        tracing::warn!(
-                                                            "Notification manager not available"
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
-
-                                        // Send response
-                                        let _ = stream.write_all(b"OK\n").await;
-                                    }
+                        match read_message(&mut stream).await {
+                            Ok(raw) => {
+                                if let Some(msg) = HookMessage::parse(&raw) {
+                                    Self::handle_hook_message(&msg, &ctx).await;
+                                    let _ = stream.write_all(b"OK\n").await;
                                 }
                             }
-                            _ => {}
+                            Err(e) => debug!("Failed to read hook message: {}", e),
                         }
                     });
                 }
@@ -509,204 +710,630 @@ impl FukuraDaemon {
 
     #[cfg(windows)]
     async fn start_named_pipe_server(
-        sessions: Arc<RwLock<HashMap<String, ActiveSession>>>,
-        notif_mgr: Option<Arc<NotificationManager>>,
+        ctx: HookContext,
         _socket_path: std::path::PathBuf,
     ) -> Result<()> {
-        use tokio::io::{AsyncReadExt, AsyncWriteExt};
-        use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::windows::named_pipe::ServerOptions;
 
         let pipe_name = r"\\.\pipe\fukura_daemon";
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(pipe_name)?;
+        info!("IPC named pipe server listening on {}", pipe_name);
 
         loop {
-            let server = ServerOptions::new()
-                .first_pipe_instance(true)
-                .create(pipe_name)?;
+            // Wait for a client, then immediately create the next instance so
+            // there is always one pipe available to connect to
+            server.connect().await?;
+            let mut connected = server;
+            server = ServerOptions::new().create(pipe_name)?;
 
-            let sessions = sessions.clone();
-            let notif_mgr = notif_mgr.clone();
+            let ctx = ctx.clone();
 
             tokio::spawn(async move {
-                let mut server = server;
-                server.connect().await.ok();
-
-                let mut buffer = vec![0u8; 4096];
-                match server.read(&mut buffer).await {
-                    Ok(n) if n > 0 => {
-                        let data = &buffer[..n];
-                        if let Ok(msg) = String::from_utf8(data.to_vec()) {
-                            let parts: Vec<&str> = msg.trim().split('|').collect();
-                            if parts.len() >= 4 {
-                                let session_id = parts[0];
-                                let command = parts[1];
-                                let exit_code = parts[2].parse::<i32>().unwrap_or(0);
-                                let working_dir = parts[3];
-
-                                let mut sessions = sessions.write().await;
-
-                                if !sessions.contains_key(session_id) {
-                                    sessions.insert(
-                                        session_id.to_string(),
-                                        ActiveSession {
-                                            id: session_id.to_string(),
-                                            start_time: SystemTime::now(),
-                                            last_activity: SystemTime::now(),
-                                            commands: Vec::new(),
-                                            errors: Vec::new(),
-                                            context: SessionContext {
-                                                working_directory: working_dir.to_string(),
-                                                git_branch: None,
-                                                git_status: None,
-                                                environment: HashMap::new(),
-                                            },
-                                            last_error_command: None,
-                                            resolution_in_progress: false,
-                                        },
-                                    );
-                                }
+                match read_message(&mut connected).await {
+                    Ok(raw) => {
+                        if let Some(msg) = HookMessage::parse(&raw) {
+                            Self::handle_hook_message(&msg, &ctx).await;
+                            let _ = connected.write_all(b"OK\n").await;
+                        }
+                    }
+                    Err(e) => debug!("Failed to read hook message: {}", e),
+                }
+            });
+        }
+    }
 
-                                if let Some(session) = sessions.get_mut(session_id) {
-                                    session.commands.push(CommandEntry {
-                                        command: command.to_string(),
-                                        exit_code: Some(exit_code),
-                                        timestamp: SystemTime::now(),
-                                        working_directory: working_dir.to_string(),
-                                    });
-                                    session.last_activity = SystemTime::now();
-
-                                    if exit_code != 0 {
-                                        let error_message = format!(
-                                            "Command '{}' failed with exit code {}",
-                                            command, exit_code
-                                        );
-                                        session.errors.push(ErrorEntry {
-                                            message: error_message.clone(),
-                                            normalized: error_message.clone(),
-                                            source: "shell".to_string(),
-                                            timestamp: SystemTime::now(),
-                                            stderr_output: None,
-                                        });
-
-                                        drop(sessions);
-                                        let wd_path = std::path::PathBuf::from(working_dir);
-                                        let repo_clone = Arc::new(
-                                            FukuraRepo::discover(Some(&wd_path)).unwrap_or_else(
-                                                |_| {
-                                                    FukuraRepo::discover(None)
-                                                        .expect("Failed to discover repo")
-                                                },
-                                            ),
-                                        );
-
-                                        let note = Note {
-                                            title: format!("Error: {}", command),
-                                            body: format!(
-                                                "## Command Failed\n\n```\n{}\n```\n\n**Exit Code**: {}\n\n**Error**: {}\n\n**Working Directory**: {}\n\n**Time**: {}",
-                                                command,
-                                                exit_code,
-                                                error_message,
-                                                working_dir,
-                                                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")
-                                            ),
-                                            tags: vec!["error".to_string(), "auto-captured".to_string()],
-                                            links: vec![],
-                                            meta: std::collections::BTreeMap::from([
-                                                ("exit_code".to_string(), exit_code.to_string()),
-                                                ("working_dir".to_string(), working_dir.to_string()),
-                                            ]),
-                                            solutions: vec![],
-                                            privacy: Privacy::Private,
-                                            created_at: chrono::Utc::now(),
-                                            updated_at: chrono::Utc::now(),
-                                            author: Author {
-                                                name: std::env::var("USERNAME").unwrap_or_else(|_| "unknown".to_string()),
-                                                email: None,
-                                            },
-                                        };
-
-                                        if let Ok(record) = repo_clone.store_note(note) {
-                                            tracing::info!(
-                                                "Note created: {} for error: {}",
-                                                &record.object_id[..8],
-                                                command
-                                            );
-
-                                            // WORLD-CLASS: Search for similar errors and solutions
-                                            let similar_solutions = Self::find_similar_solutions(
-                                                &repo_clone,
-                                                command,
-                                                exit_code,
-                                            );
-
-                                            // Send intelligent notification
-                                            if let Some(ref nm) = notif_mgr {
-                                                tracing::info!(
-                                                    "Sending notification for error: {}",
-                                                    command
-                                                );
-                                                if let Ok(solutions) = similar_solutions {
-                                                    if !solutions.is_empty() {
-                                                        tracing::info!(
-                                                            "Found {} solutions",
-                                                            solutions.len()
-                                                        );
-                                                        if let Err(e) = nm
-                                                            .notify_error_with_solutions(
-                                                                command,
-                                                                &error_message,
-                                                                &record.object_id,
-                                                                &solutions,
-                                                            )
-                                                        {
-                                                            tracing::error!(
-                                                                "Notification failed: {}",
-                                                                e
-                                                            );
-                                                        } else {
-                                                            tracing::info!("Notification sent successfully with solutions");
-                                                        }
-                                                    } else if let Err(e) = nm.notify_error_with_id(
-                                                        command,
-                                                        &error_message,
-                                                        &record.object_id,
-                                                    ) {
-                                                        tracing::error!(
-                                                            "Notification failed: {}",
-                                                            e
-                                                        );
-                                                    } else {
-                                                        tracing::info!(
-                                                            "Notification sent successfully"
-                                                        );
-                                                    }
-                                                } else if let Err(e) = nm.notify_error_with_id(
-                                                    command,
-                                                    &error_message,
-                                                    &record.object_id,
-                                                ) {
-                                                    tracing::error!("Notification failed: {}", e);
-                                                } else {
-                                                    tracing::info!(
-                                                        "Notification sent successfully"
-                                                    );
-                                                }
-                                            } else {
-                                                tracing::warn!(
-                                                    "Notification manager not available"
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
+    /// Record a command reported by a shell hook. Shared by the unix socket and
+    /// named pipe servers so both platforms capture the same context.
+    async fn handle_hook_message(msg: &HookMessage, ctx: &HookContext) {
+        debug!(
+            "Hook message: session={} exit={} dir={} command={}",
+            msg.session_id,
+            msg.exit_code,
+            msg.working_dir,
+            msg.command_summary()
+        );
+        if !ctx.capture.captures_in(Path::new(&msg.working_dir)) {
+            debug!("Not capturing in a never-capture zone: {}", msg.working_dir);
+            return;
+        }
+        Self::persist_command(
+            &ctx.command_log,
+            &msg.session_id,
+            &msg.command,
+            Some(msg.exit_code),
+            &msg.working_dir,
+            Some(&msg.stderr),
+            msg.duration_ms,
+        );
+        Self::resolve_pending_feedback(ctx, msg).await;
+        if let Some(threshold) = ctx.auto_record {
+            Self::track_streak(ctx, msg, threshold).await;
+        }
+
+        let key = Self::session_key(msg);
+        let is_new_session = !ctx.sessions.read().await.contains_key(&key);
+        let context = if is_new_session {
+            Some(SessionContext {
+                working_directory: msg.working_dir.clone(),
+                git: git::context(Path::new(&msg.working_dir)),
+                environment: HashMap::new(),
+            })
+        } else {
+            None
+        };
+
+        let error_message = Self::error_message(msg);
+        let failure = failure_message(&msg.command, msg.exit_code, &msg.stderr);
+
+        {
+            let mut sessions = ctx.sessions.write().await;
+            let session = sessions
+                .entry(key.clone())
+                .or_insert_with(|| ActiveSession {
+                    id: key.clone(),
+                    start_time: SystemTime::now(),
+                    last_activity: SystemTime::now(),
+                    commands: Vec::new(),
+                    errors: Vec::new(),
+                    context: context.unwrap_or_else(|| SessionContext {
+                        working_directory: msg.working_dir.clone(),
+                        git: None,
+                        environment: HashMap::new(),
+                    }),
+                    last_error_command: None,
+                    resolution_in_progress: false,
+                });
+
+            session.commands.push(CommandEntry {
+                command: msg.command.clone(),
+                exit_code: Some(msg.exit_code),
+                timestamp: SystemTime::now(),
+                working_directory: msg.working_dir.clone(),
+                duration: msg.duration_ms.map(Duration::from_millis),
+            });
+            session.last_activity = SystemTime::now();
+
+            if msg.exit_code == 0 {
+                return;
+            }
+            if !ctx
+                .capture
+                .should_capture(&msg.command, msg.exit_code, &msg.stderr)
+            {
+                debug!(
+                    "Capture policy skipped failed command: {}",
+                    msg.command_summary()
+                );
+                return;
+            }
+
+            session.errors.push(ErrorEntry {
+                message: error_message.clone(),
+                normalized: normalize_message(&failure),
+                source: "shell".to_string(),
+                timestamp: SystemTime::now(),
+                stderr_output: (!msg.stderr.is_empty()).then(|| msg.stderr.clone()),
+            });
+        }
 
-                                let _ = server.write_all(b"OK\n").await;
+        // BEST PRACTICE: Create note immediately (like Git commit)
+        // Users can access via: fuku search, fuku view @latest
+        let repo = match Self::repo_for(ctx, &msg.working_dir).await {
+            Ok(repo) => repo,
+            Err(e) => {
+                warn!("No repository for captured error: {}", e);
+                Self::track_pattern(ctx, &failure, Some(&msg.command), None).await;
+                return;
+            }
+        };
+
+        let mut note = Self::failure_note(msg, &ctx.capture);
+        Self::assign_scope(&repo, &mut note, Path::new(&msg.working_dir));
+        let created = repo
+            .write(move |repo| {
+                NoteService::new(repo)?
+                    .with_auto_tags()
+                    .with_dedup(DEFAULT_THRESHOLD)
+                    .create(note)
+            })
+            .await;
+        let record = match created {
+            Ok(created) => created.record,
+            Err(e) => {
+                tracing::error!("Failed to store error note: {}", e);
+                Self::track_pattern(ctx, &failure, Some(&msg.command), None).await;
+                return;
+            }
+        };
+        Self::track_pattern(ctx, &failure, Some(&msg.command), Some(&record.object_id)).await;
+        if let Some(linker) = ctx.issues.as_deref() {
+            Self::open_recurring_issue(ctx, linker, repo.repo(), &failure, &record).await;
+        }
+        info!(
+            "Note created: {} for error: {}",
+            &record.object_id[..8],
+            msg.command_summary()
+        );
+
+        let Some(nm) = ctx.notification_manager.as_deref() else {
+            warn!("Notification manager not available");
+            return;
+        };
+
+        // WORLD-CLASS: Search for similar errors and solutions
+        let feedback = ctx.feedback.stats().unwrap_or_default();
+        let (command, exit_code) = (msg.command.clone(), msg.exit_code);
+        let solutions = repo
+            .read(move |repo| Self::find_similar_solutions(repo, &command, exit_code, &feedback))
+            .await
+            .unwrap_or_default();
+        let command = msg.command_summary();
+        let result = if solutions.is_empty() {
+            nm.notify_error_with_id(&command, &error_message, &record.object_id)
+        } else {
+            info!("Found {} solutions", solutions.len());
+            nm.notify_error_with_solutions(&command, &error_message, &record.object_id, &solutions)
+        };
+        match result {
+            Ok(()) => {
+                info!("Notification sent for error: {}", command);
+                Self::track_suggestions(ctx, &msg.session_id, &solutions).await;
+            }
+            Err(e) => tracing::error!("Notification failed: {}", e),
+        }
+    }
+
+    /// Key of the in-memory session a hook message belongs to. A shell sends
+    /// one session id wherever it is, so the session is split per repository
+    /// root: changing into another project starts a new session and notes
+    /// generated from it never interleave commands from unrelated projects.
+    fn session_key(msg: &HookMessage) -> String {
+        match git::project_root(Path::new(&msg.working_dir)) {
+            Some(root) => {
+                let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+                format!("{}@{}", msg.session_id, &hex::encode(digest)[..8])
+            }
+            None => msg.session_id.clone(),
+        }
+    }
+
+    /// Remember which notes were suggested so the next command in the session
+    /// can tell whether they helped
+    async fn track_suggestions(ctx: &HookContext, session_id: &str, solutions: &[SolutionHit]) {
+        if solutions.is_empty() {
+            return;
+        }
+        for hit in solutions {
+            if let Err(e) = ctx
+                .feedback
+                .append(&FeedbackEvent::new(&hit.note_id, FeedbackKind::Suggested))
+            {
+                warn!("Failed to record suggestion: {}", e);
+            }
+        }
+        ctx.pending_feedback.write().await.insert(
+            session_id.to_string(),
+            PendingSuggestion {
+                note_ids: solutions.iter().map(|hit| hit.note_id.clone()).collect(),
+                suggested_at: SystemTime::now(),
+            },
+        );
+    }
+
+    /// After a suggestion, the next command in the session decides whether the
+    /// notes that were opened in the meantime worked. `fuku` commands (such as
+    /// opening the note) don't count as an attempt.
+    async fn resolve_pending_feedback(ctx: &HookContext, msg: &HookMessage) {
+        let program = msg.command.split_whitespace().next().unwrap_or_default();
+        if program == "fuku" || program.ends_with("/fuku") {
+            return;
+        }
+        let Some(pending) = ctx.pending_feedback.write().await.remove(&msg.session_id) else {
+            return;
+        };
+        let opened = match ctx
+            .feedback
+            .opened_since(&pending.note_ids, pending.suggested_at)
+        {
+            Ok(opened) => opened,
+            Err(e) => {
+                warn!("Failed to read solution feedback: {}", e);
+                return;
+            }
+        };
+        let kind = if msg.exit_code == 0 {
+            FeedbackKind::Worked
+        } else {
+            FeedbackKind::DidntWork
+        };
+        for note_id in opened {
+            debug!("Suggested note {} {:?} (inferred)", note_id, kind);
+            let event = FeedbackEvent::new(&note_id, kind)
+                .implicit()
+                .in_dir(&msg.working_dir);
+            if let Err(e) = ctx.feedback.append(&event) {
+                warn!("Failed to record solution feedback: {}", e);
+            }
+        }
+    }
+
+    /// Start a recording when the same command keeps failing, and save it as a
+    /// resolution note when that command finally succeeds
+    async fn track_streak(ctx: &HookContext, msg: &HookMessage, threshold: usize) {
+        let failed = msg.exit_code != 0;
+        if failed
+            && !ctx
+                .capture
+                .should_capture(&msg.command, msg.exit_code, &msg.stderr)
+        {
+            return;
+        }
+        // The command was logged a moment ago; start early enough to include it
+        let at = SystemTime::now() - Duration::from_secs(1);
+        let (event, streak) = {
+            let mut streaks = ctx.streaks.write().await;
+            let streak = streaks.entry(msg.session_id.clone()).or_default();
+            let event = streak.observe(&msg.command, failed, at, threshold);
+            (event, streak.clone())
+        };
+        let Some(event) = event else {
+            return;
+        };
+        let repo = match Self::repo_for(ctx, &msg.working_dir).await {
+            Ok(repo) => repo,
+            Err(e) => {
+                warn!("No repository for automatic recording: {}", e);
+                return;
+            }
+        };
+        let store = RecordingStore::new(repo.repo().root());
+
+        match event {
+            StreakEvent::Start => {
+                let name = streak.recording_name();
+                let recording = ActiveRecording::new(
+                    &name,
+                    &format!("Fix `{}`", streak.family),
+                    streak.since.unwrap_or(at),
+                    Some(PathBuf::from(&msg.working_dir)),
+                );
+                // Another shell may be recording the same command already
+                let started = match store.load(&name) {
+                    Ok(Some(_)) => Ok(()),
+                    Ok(None) => store.save(&recording),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = started {
+                    warn!("Failed to start automatic recording: {}", e);
+                    return;
+                }
+                info!(
+                    "Recording {} after {} failures of {}",
+                    name, streak.failures, streak.family
+                );
+                if let Some(streak) = ctx.streaks.write().await.get_mut(&msg.session_id) {
+                    streak.recording = Some(name);
+                }
+                if let Some(nm) = ctx.notification_manager.as_deref() {
+                    if let Err(e) = nm.notify_recording_started(&streak.family, streak.failures) {
+                        tracing::error!("Notification failed: {}", e);
+                    }
+                }
+            }
+            StreakEvent::Resolved(name) => {
+                match Self::save_auto_recording(ctx, &repo, &store, &name).await {
+                    Ok(Some(record)) => {
+                        info!(
+                            "Automatic recording saved: {} ({})",
+                            record.note.title,
+                            &record.object_id[..8]
+                        );
+                        if let Some(nm) = ctx.notification_manager.as_deref() {
+                            if let Err(e) =
+                                nm.notify_recording_saved(&record.note.title, &record.object_id)
+                            {
+                                tracing::error!("Notification failed: {}", e);
                             }
                         }
                     }
-                    _ => {}
+                    Ok(None) => debug!("Recording {} was finished by hand", name),
+                    Err(e) => warn!("Failed to save automatic recording: {}", e),
                 }
+            }
+        }
+    }
+
+    /// Turn a finished automatic recording into a resolution note linked to
+    /// the error pattern of its last failure
+    async fn save_auto_recording(
+        ctx: &HookContext,
+        repo: &AsyncRepo,
+        store: &RecordingStore,
+        name: &str,
+    ) -> Result<Option<NoteRecord>> {
+        // `fuku done --name` may have finished it already
+        let Some(recording) = store.load(name)? else {
+            return Ok(None);
+        };
+        let since = recording.start_time().unwrap_or_else(SystemTime::now);
+        let mut commands = match &ctx.command_log {
+            Some(log) => log.read_since(since)?,
+            None => Vec::new(),
+        };
+        commands.retain(|command| recording.includes(command));
+
+        let mut note = Self::auto_recording_note(&recording, since, &commands);
+        if let Some(dir) = &recording.directory {
+            ctx.capture.confine(&mut note, dir);
+            Self::assign_scope(repo, &mut note, dir);
+        }
+        let record = repo
+            .write(move |repo| NoteService::new(repo)?.with_auto_tags().create(note))
+            .await?
+            .record;
+        store.remove(name)?;
+
+        if let Some(last) = commands.iter().rev().find(|command| command.failed()) {
+            let failure = failure_message(
+                &last.command,
+                last.exit_code.unwrap_or(1),
+                last.stderr.as_deref().unwrap_or_default(),
+            );
+            let (_, fingerprint) = ctx.pattern_store.pattern_key(&failure);
+            match ctx
+                .pattern_store
+                .record_resolution(&fingerprint, &record.object_id)
+            {
+                Ok(updated) => *ctx.error_patterns.write().await = updated,
+                Err(e) => warn!("Failed to link resolution: {}", e),
+            }
+        }
+        Ok(Some(record))
+    }
+
+    /// Note for an automatic recording, in the `fuku done` format so
+    /// `fuku replay` can re-run it
+    fn auto_recording_note(
+        recording: &ActiveRecording,
+        since: SystemTime,
+        commands: &[CommandRecord],
+    ) -> Note {
+        let steps = build_steps(since, commands);
+        let mut body = String::from("## 🎯 Automatic Recording\n\n");
+        body.push_str("Started after repeated failures and saved when the command succeeded.\n\n");
+        if let Some(dir) = &recording.directory {
+            body.push_str(&format!("**Working Directory**: `{}`\n\n", dir.display()));
+        }
+        body.push_str("## 📋 What I Did\n\n");
+        body.push_str(&render_steps(&steps));
+
+        NoteBuilder::new(recording.title.clone(), body)
+            .tag("recording")
+            .tag("resolution")
+            .tag("auto-recorded")
+            .meta("session_id", recording.session_id.clone())
+            .meta("recording", "true")
+            .meta("recording_name", recording.name.clone())
+            .meta("command_count", steps.len().to_string())
+            .author(Author {
+                name: std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .unwrap_or_else(|_| "unknown".to_string()),
+                email: None,
+            })
+            .build()
+    }
+
+    /// Repository of the directory a command ran in, else the daemon's own
+    async fn repo_for(ctx: &HookContext, working_dir: &str) -> Result<AsyncRepo> {
+        let wd_path = PathBuf::from(working_dir);
+        match ctx.repos.discover(Some(&wd_path)).await {
+            Ok(repo) => Ok(repo),
+            Err(_) => ctx.repos.discover(None).await,
+        }
+    }
+
+    /// Count a failure in `.fukura/patterns.json` and refresh the in-memory patterns
+    async fn track_pattern(
+        ctx: &HookContext,
+        message: &str,
+        command: Option<&str>,
+        note_id: Option<&str>,
+    ) {
+        Self::record_pattern(
+            &ctx.pattern_store,
+            &ctx.error_patterns,
+            message,
+            command,
+            note_id,
+        )
+        .await;
+    }
+
+    /// Open an issue when this failure brought its pattern to the threshold
+    async fn open_recurring_issue(
+        ctx: &HookContext,
+        linker: &IssueLinker,
+        repo: &FukuraRepo,
+        failure: &str,
+        record: &NoteRecord,
+    ) {
+        let (_, key) = ctx.pattern_store.pattern_key(failure);
+        let occurrences = match ctx.error_patterns.read().await.get(&key) {
+            Some(pattern) if linker.crossed_threshold(pattern) => pattern.occurrences,
+            _ => return,
+        };
+        match linker
+            .open_issue(repo, record, IssueReason::Recurring(occurrences))
+            .await
+        {
+            Ok(linked) => info!(
+                "Opened {} issue for recurring error: {}",
+                linker.tracker_name(),
+                linked
+                    .note
+                    .meta
+                    .get(ISSUE_URL_META)
+                    .map_or("", String::as_str)
+            ),
+            Err(e) => warn!("Failed to open issue for recurring error: {}", e),
+        }
+    }
+
+    async fn record_pattern(
+        store: &PatternStore,
+        error_patterns: &Arc<RwLock<HashMap<String, ErrorPattern>>>,
+        message: &str,
+        command: Option<&str>,
+        note_id: Option<&str>,
+    ) {
+        let now = SystemTime::now();
+        match store.record_failure(message, command, note_id, now) {
+            Ok((_, updated)) => *error_patterns.write().await = updated,
+            Err(e) => {
+                warn!("Failed to persist error pattern: {}", e);
+                let (normalized, key) = store.pattern_key(message);
+                error_patterns
+                    .write()
+                    .await
+                    .entry(key.clone())
+                    .or_insert_with(|| ErrorPattern::new(normalized, key, now))
+                    .record(now, command, note_id);
+            }
+        }
+    }
+
+    /// Auto-captured note for a failed command
+    /// One-line description of a failed command for notifications
+    fn error_message(msg: &HookMessage) -> String {
+        if msg.stderr.is_empty() {
+            format!(
+                "Command '{}' failed with exit code {}",
+                msg.command_summary(),
+                msg.exit_code
+            )
+        } else {
+            format!("Command '{}' failed: {}", msg.command_summary(), msg.stderr)
+        }
+    }
+
+    /// The note captured for a failed command, with the `[capture]` title
+    /// template, expiry and zone applied; `fuku last-error --note` makes the
+    /// same note from the command log
+    pub fn failure_note(msg: &HookMessage, capture: &CaptureConfig) -> Note {
+        // Read per failure rather than at session start: the branch may have
+        // changed since. Cached, so repeated failures don't rescan the repo.
+        let git = git::context(Path::new(&msg.working_dir));
+        let mut note = Self::error_note(
+            msg,
+            &Self::error_message(msg),
+            git.as_ref(),
+            &capture.title_template,
+        );
+        if let Some(ttl) = capture.expiry() {
+            note.set_expires_at(note.created_at + ttl);
+        }
+        capture.confine(&mut note, Path::new(&msg.working_dir));
+        note
+    }
+
+    /// Scope `note` to the package of `repo` it was made in. Each repository
+    /// the daemon serves has its own `[scope]`, unlike `[capture]`.
+    fn assign_scope(repo: &AsyncRepo, note: &mut Note, dir: &Path) {
+        if let Ok(config) = repo.repo().config() {
+            config.scope.assign(note, repo.repo().root(), dir);
+        }
+    }
+
+    fn error_note(
+        msg: &HookMessage,
+        error_message: &str,
+        git: Option<&GitContext>,
+        title_template: &str,
+    ) -> Note {
+        let git_branch = git.and_then(|git| git.branch.as_deref());
+        let mut body = format!("## Command Failed\n\n```bash\n$ {}\n", msg.command);
+        if !msg.stderr.is_empty() {
+            body.push_str(&format!("\n# Error output:\n{}\n", msg.stderr));
+        }
+        body.push_str("```\n\n");
+        body.push_str(&format!("**Exit Code**: {}\n\n", msg.exit_code));
+        let took = slow_duration(msg.duration_ms.map(Duration::from_millis));
+        if let Some(took) = &took {
+            body.push_str(&format!("**Duration**: failed after {}\n\n", took));
+        }
+        if msg.stderr.is_empty() {
+            body.push_str(&format!("**Error**: {}\n\n", error_message));
+        }
+        body.push_str(&format!("**Working Directory**: `{}`\n\n", msg.working_dir));
+        if let Some(branch) = git_branch.filter(|b| !b.is_empty()) {
+            body.push_str(&format!("**Git Branch**: `{}`\n\n", branch));
+        }
+        if let Some(commit) = git.and_then(|git| git.last_commit.as_deref()) {
+            body.push_str(&format!("**Last Commit**: {}\n\n", commit));
+        }
+        body.push_str(&format!(
+            "**Time**: {}",
+            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")
+        ));
+
+        let title = render_title(
+            title_template,
+            &TitleContext {
+                command: msg.command.clone(),
+                exit_code: msg.exit_code,
+                stderr: msg.stderr.clone(),
+            },
+        );
+        let mut builder = NoteBuilder::new(title, body)
+            .tag("error")
+            .tag("auto-captured")
+            .meta("exit_code", msg.exit_code.to_string())
+            .meta("working_dir", msg.working_dir.clone())
+            .author(Author {
+                name: std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .unwrap_or_else(|_| "unknown".to_string()),
+                email: None,
             });
+        if let Some(ms) = msg.duration_ms {
+            builder = builder.meta("duration_ms", ms.to_string());
+        }
+        if let Some(branch) = git_branch.filter(|b| !b.is_empty()) {
+            builder = builder.meta("git_branch", branch);
+        }
+        if let Some(git) = git {
+            if let Some(remote) = &git.remote_url {
+                builder = builder.meta("git_remote", remote.clone());
+            }
+            if git.dirty {
+                builder = builder.meta("git_dirty", "true");
+            }
         }
+        builder.build()
     }
 
     /// Stop the daemon
@@ -718,6 +1345,7 @@ impl FukuraDaemon {
 
         // Clean up active sessions
         self.sessions.write().await.clear();
+        self.repo.repo().prompt_state().clear_heartbeat()?;
 
         info!("Fukura daemon stopped");
         Ok(())
@@ -731,6 +1359,20 @@ impl FukuraDaemon {
         exit_code: Option<i32>,
         working_dir: &str,
     ) -> Result<()> {
+        if !self.capture.captures_in(Path::new(working_dir)) {
+            debug!("Not capturing in a never-capture zone: {}", working_dir);
+            return Ok(());
+        }
+        Self::persist_command(
+            &self.command_log,
+            session_id,
+            command,
+            exit_code,
+            working_dir,
+            None,
+            None,
+        );
+
         let mut sessions = self.sessions.write().await;
 
         if !sessions.contains_key(session_id) {
@@ -744,8 +1386,7 @@ impl FukuraDaemon {
                     errors: Vec::new(),
                     context: SessionContext {
                         working_directory: working_dir.to_string(),
-                        git_branch: self.get_git_branch(working_dir).await.ok(),
-                        git_status: self.get_git_status(working_dir).await.ok(),
+                        git: git::context(Path::new(working_dir)),
                         environment: self.get_environment_context().await,
                     },
                     last_error_command: None,
@@ -760,25 +1401,46 @@ impl FukuraDaemon {
                 exit_code,
                 timestamp: SystemTime::now(),
                 working_directory: working_dir.to_string(),
+                duration: None,
             });
             session.last_activity = SystemTime::now();
 
             // INSTANT RESOLUTION DETECTION
             if let Some(code) = exit_code {
-                if code != 0 {
+                if code != 0 && !self.capture.captures_command(command, code) {
+                    debug!("Capture policy skipped failed command: {}", command);
+                } else if code != 0 {
                     // Error - start tracking
                     session.last_error_command = Some(command.to_string());
                     session.resolution_in_progress = true;
                     self.analyze_command_error(session, command, code).await;
-                } else if session.resolution_in_progress {
+                } else if session.resolution_in_progress && self.capture.instant_resolution {
                     // Success after error - INSTANT note creation!
                     let session_clone = session.clone();
                     drop(sessions);
 
-                    // Create resolution note immediately
+                    // Create resolution note immediately and link it to the error pattern
+                    let store = self.pattern_store.clone();
+                    let error_patterns = self.error_patterns.clone();
+                    let repos = self.repos.clone();
+                    let fingerprint = session_clone
+                        .errors
+                        .last()
+                        .map(|error| store.pattern_key(&error.normalized).1);
                     tokio::spawn(async move {
-                        if let Err(e) = Self::create_instant_resolution_note(session_clone).await {
-                            tracing::error!("Failed to create resolution note: {}", e);
+                        match Self::create_instant_resolution_note(&repos, session_clone).await {
+                            Ok(Some(note_id)) => {
+                                if let Some(fingerprint) = fingerprint {
+                                    match store.record_resolution(&fingerprint, &note_id) {
+                                        Ok(updated) => *error_patterns.write().await = updated,
+                                        Err(e) => warn!("Failed to link resolution: {}", e),
+                                    }
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                tracing::error!("Failed to create resolution note: {}", e);
+                            }
                         }
                     });
 
@@ -799,37 +1461,29 @@ impl FukuraDaemon {
     /// Record an error message
     pub async fn record_error(&self, session_id: &str, message: &str, source: &str) -> Result<()> {
         let normalized = self.normalize_error_message(message);
-        let fingerprint = self.create_error_fingerprint(&normalized);
 
         let mut sessions = self.sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
             session.errors.push(ErrorEntry {
                 message: message.to_string(),
-                normalized: normalized.clone(),
+                normalized,
                 source: source.to_string(),
                 timestamp: SystemTime::now(),
                 stderr_output: None,
             });
             session.last_activity = SystemTime::now();
         }
+        drop(sessions);
 
         // Update error patterns
-        let mut patterns = self.error_patterns.write().await;
-        if let Some(pattern) = patterns.get_mut(&fingerprint) {
-            pattern.occurrences += 1;
-            pattern.last_seen = SystemTime::now();
-        } else {
-            patterns.insert(
-                fingerprint.clone(),
-                ErrorPattern {
-                    normalized_message: normalized,
-                    fingerprint,
-                    occurrences: 1,
-                    last_seen: SystemTime::now(),
-                    solutions: Vec::new(),
-                },
-            );
-        }
+        Self::record_pattern(
+            &self.pattern_store,
+            &self.error_patterns,
+            message,
+            None,
+            None,
+        )
+        .await;
 
         Ok(())
     }
@@ -841,20 +1495,21 @@ impl FukuraDaemon {
 
         if let Some(session) = sessions.get(session_id) {
             let mut solutions = Vec::new();
+            let feedback = self.feedback.stats().unwrap_or_default();
 
             for error in &session.errors {
-                if let Some(pattern) =
-                    patterns.get(&self.create_error_fingerprint(&error.normalized))
-                {
+                let (_, fingerprint) = self.pattern_store.pattern_key(&error.normalized);
+                if let Some(pattern) = patterns.get(&fingerprint) {
                     for solution in &pattern.solutions {
                         solutions.push(Solution {
                             error_pattern: pattern.normalized_message.clone(),
                             solution: solution.clone(),
-                            confidence: self.calculate_confidence(pattern),
+                            confidence: Self::calculate_confidence(pattern, &feedback),
                         });
                     }
                 }
             }
+            solutions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
 
             return Ok(solutions);
         }
@@ -874,8 +1529,7 @@ impl FukuraDaemon {
             errors: Vec::new(),
             context: SessionContext {
                 working_directory: working_dir.to_string(),
-                git_branch: self.get_git_branch(working_dir).await.ok(),
-                git_status: self.get_git_status(working_dir).await.ok(),
+                git: git::context(Path::new(working_dir)),
                 environment: self.get_environment_context().await,
             },
             last_error_command: None,
@@ -911,6 +1565,18 @@ impl FukuraDaemon {
 
     // Private helper methods
 
+    /// Whether no session has seen a command for `idle`
+    async fn is_idle(
+        sessions: &Arc<RwLock<HashMap<String, ActiveSession>>>,
+        idle: Duration,
+    ) -> bool {
+        let now = SystemTime::now();
+        sessions.read().await.values().all(|session| {
+            now.duration_since(session.last_activity)
+                .is_ok_and(|quiet| quiet >= idle)
+        })
+    }
+
     async fn cleanup_sessions(
         sessions: &Arc<RwLock<HashMap<String, ActiveSession>>>,
         config: &DaemonConfig,
@@ -962,16 +1628,21 @@ impl FukuraDaemon {
         }
     }
 
-    fn create_error_fingerprint(&self, normalized: &str) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(normalized.as_bytes());
-        format!("{:x}", hasher.finalize())
-    }
-
-    fn calculate_confidence(&self, pattern: &ErrorPattern) -> f64 {
-        // Simple confidence calculation based on occurrence count
-        (pattern.occurrences as f64).min(10.0) / 10.0
+    /// How likely the known fixes for a pattern are to work: starts from how
+    /// often the error was seen and follows the feedback on its resolution notes
+    fn calculate_confidence(
+        pattern: &ErrorPattern,
+        feedback: &HashMap<String, FeedbackStats>,
+    ) -> f64 {
+        let prior = (pattern.occurrences as f64).min(10.0) / 10.0;
+        let mut combined = FeedbackStats::default();
+        for stats in pattern.resolved_by.iter().filter_map(|id| feedback.get(id)) {
+            combined.worked += stats.worked;
+            combined.didnt_work += stats.didnt_work;
+            combined.implicit_worked += stats.implicit_worked;
+            combined.implicit_didnt_work += stats.implicit_didnt_work;
+        }
+        combined.confidence(prior)
     }
 
     async fn analyze_command_error(
@@ -981,16 +1652,24 @@ impl FukuraDaemon {
         exit_code: i32,
     ) {
         // Analyze if this command failure is part of a known error pattern
-        let error_message = format!("Command '{}' failed with exit code {}", command, exit_code);
+        let error_message = failure_message(command, exit_code, "");
         let error_entry = ErrorEntry {
             message: error_message.clone(),
-            normalized: self.normalize_error_message(&format!("Command failed: {}", command)),
+            normalized: self.normalize_error_message(&error_message),
             source: "command".to_string(),
             timestamp: SystemTime::now(),
             stderr_output: None,
         };
 
         session.errors.push(error_entry);
+        Self::record_pattern(
+            &self.pattern_store,
+            &self.error_patterns,
+            &error_message,
+            Some(command),
+            None,
+        )
+        .await;
 
         // Send notification
         if let Some(ref notif_mgr) = self.notification_manager {
@@ -998,50 +1677,9 @@ impl FukuraDaemon {
         }
     }
 
-    async fn get_git_branch(&self, working_dir: &str) -> Result<String> {
-        let output = tokio::process::Command::new("git")
-            .args(["branch", "--show-current"])
-            .current_dir(working_dir)
-            .output()
-            .await?;
-
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-        } else {
-            Err(anyhow::anyhow!("Failed to get git branch"))
-        }
-    }
-
-    async fn get_git_status(&self, working_dir: &str) -> Result<String> {
-        let output = tokio::process::Command::new("git")
-            .args(["status", "--porcelain"])
-            .current_dir(working_dir)
-            .output()
-            .await?;
-
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-        } else {
-            Err(anyhow::anyhow!("Failed to get git status"))
-        }
-    }
-
+    /// Variables allowed by `capture.env_allow`, redacted
     async fn get_environment_context(&self) -> HashMap<String, String> {
-        let mut env = HashMap::new();
-
-        // Capture relevant environment variables
-        for (key, value) in std::env::vars() {
-            if key.starts_with("FUKURA_")
-                || key == "PATH"
-                || key == "SHELL"
-                || key == "USER"
-                || key == "HOME"
-            {
-                env.insert(key, value);
-            }
-        }
-
-        env
+        self.capture.environment(std::env::vars(), &self.redactor)
     }
 
     fn generate_session_id(&self) -> String {
@@ -1060,24 +1698,13 @@ impl FukuraDaemon {
         let body = self.generate_session_body(session);
         let tags = self.generate_session_tags(session);
 
-        let now = chrono::Utc::now();
-        let author = Author {
-            name: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
-            email: Some(std::env::var("EMAIL").unwrap_or_default()),
-        };
-
-        Ok(Note {
-            title,
-            body,
-            tags,
-            links: Vec::new(),
-            meta: std::collections::BTreeMap::new(),
-            solutions: Vec::new(),
-            privacy: Privacy::Private,
-            created_at: now,
-            updated_at: now,
-            author,
-        })
+        Ok(NoteBuilder::new(title, body)
+            .tags(tags)
+            .author(Author {
+                name: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
+                email: Some(std::env::var("EMAIL").unwrap_or_default()),
+            })
+            .build())
     }
 
     fn generate_session_title(&self, session: &ActiveSession) -> String {
@@ -1114,7 +1741,7 @@ impl FukuraDaemon {
             "\n# Context\n\n- Working Directory: {}\n",
             session.context.working_directory
         ));
-        if let Some(branch) = &session.context.git_branch {
+        if let Some(branch) = session.context.git_branch() {
             body.push_str(&format!("- Git Branch: {}\n", branch));
         }
 
@@ -1142,38 +1769,42 @@ impl FukuraDaemon {
         tags
     }
 
+    /// Load error patterns persisted in `.fukura/patterns.json`
     async fn load_error_patterns(&self) -> Result<()> {
-        // Load error patterns from the repository
-        // This would typically read from a patterns file in .fukura/
-        debug!("Loading error patterns...");
+        match self.pattern_store.load() {
+            Ok(patterns) => {
+                debug!("Loaded {} error patterns", patterns.len());
+                *self.error_patterns.write().await = patterns;
+            }
+            Err(e) => warn!("Ignoring unreadable error patterns: {}", e),
+        }
         Ok(())
     }
 
+    /// Write back patterns that only exist in memory (every update is already
+    /// persisted as it happens, so this only matters if a write failed)
     async fn save_error_patterns(&self) -> Result<()> {
-        // Save error patterns to the repository
-        debug!("Saving error patterns...");
+        let patterns = self.error_patterns.read().await.clone();
+        self.pattern_store.update(|stored| {
+            for (key, pattern) in patterns {
+                stored.entry(key).or_insert(pattern);
+            }
+        })?;
+        debug!("Saved error patterns");
         Ok(())
     }
 
     /// Normalize error messages by replacing paths and line numbers
     pub fn normalize_error_message(&self, error: &str) -> String {
-        let mut normalized = error.to_string();
-
-        // Replace file paths with generic paths
-        let path_regex = regex::Regex::new(r"/[^\s:]+\.rs").unwrap();
-        normalized = path_regex
-            .replace_all(&normalized, "/path/to/file")
-            .to_string();
-
-        // Keep line numbers as they are (don't normalize them)
-        normalized
+        normalize_message(error)
     }
 
     /// Auto-generate notes from completed sessions with errors
     async fn auto_generate_notes(
         sessions: &Arc<RwLock<HashMap<String, ActiveSession>>>,
-        repo: &Arc<FukuraRepo>,
+        repo: &AsyncRepo,
         repo_path: &std::path::Path,
+        capture: &CaptureConfig,
     ) {
         let sessions_guard = sessions.read().await;
         let now = SystemTime::now();
@@ -1186,7 +1817,10 @@ impl FukuraDaemon {
                 .unwrap_or_default()
                 > timeout
             {
-                let has_errors = session.commands.iter().any(|cmd| cmd.exit_code != Some(0));
+                let has_errors = session.commands.iter().any(|cmd| {
+                    cmd.exit_code
+                        .is_some_and(|code| capture.captures_command(&cmd.command, code))
+                });
 
                 if has_errors {
                     info!(
@@ -1195,10 +1829,16 @@ impl FukuraDaemon {
                     );
 
                     // Create note from session
-                    let note = Self::create_note_from_session_data(session, repo_path);
+                    let mut note = Self::create_note_from_session_data(session, repo_path);
+                    if let Some(ttl) = capture.expiry() {
+                        note.set_expires_at(note.created_at + ttl);
+                    }
 
                     // Store the note
-                    if let Ok(_record) = repo.store_note(note) {
+                    let created = repo
+                        .write(move |repo| NoteService::new(repo)?.with_auto_tags().create(note))
+                        .await;
+                    if created.is_ok() {
                         info!("Auto-generated note for session {}", session_id);
                     }
                 }
@@ -1206,14 +1846,19 @@ impl FukuraDaemon {
         }
     }
 
-    /// Create instant resolution note when error is solved (WORLD-CLASS)
-    async fn create_instant_resolution_note(session: ActiveSession) -> Result<()> {
+    /// Create instant resolution note when error is solved (WORLD-CLASS).
+    /// Returns the id of the created note.
+    async fn create_instant_resolution_note(
+        repos: &AsyncRepos,
+        session: ActiveSession,
+    ) -> Result<Option<String>> {
         // Discover repo
-        let repo = match FukuraRepo::discover(Some(std::path::Path::new(
-            &session.context.working_directory,
-        ))) {
+        let repo = match repos
+            .discover(Some(Path::new(&session.context.working_directory)))
+            .await
+        {
             Ok(r) => r,
-            Err(_) => return Ok(()),
+            Err(_) => return Ok(None),
         };
 
         // Get recent commands (last 10 or until error)
@@ -1235,7 +1880,7 @@ impl FukuraDaemon {
 
         let error = match error_cmd {
             Some(e) => e,
-            None => return Ok(()), // No error found
+            None => return Ok(None), // No error found
         };
 
         // Generate title
@@ -1255,7 +1900,14 @@ impl FukuraDaemon {
         }
 
         body.push_str("```\n");
-        body.push_str(&format!("Exit code: {}\n\n", error.exit_code.unwrap_or(1)));
+        match slow_duration(error.duration) {
+            Some(took) => body.push_str(&format!(
+                "Exit code: {} (failed after {})\n\n",
+                error.exit_code.unwrap_or(1),
+                took
+            )),
+            None => body.push_str(&format!("Exit code: {}\n\n", error.exit_code.unwrap_or(1))),
+        }
 
         if !solution_steps.is_empty() {
             body.push_str("### ✅ Solution Steps (Auto-detected)\n\n");
@@ -1272,67 +1924,51 @@ impl FukuraDaemon {
                 Some(_) => "❌",
                 None => "⏳",
             };
-            body.push_str(&format!("{} `{}`\n", status, cmd.command));
+            match slow_duration(cmd.duration) {
+                Some(took) => body.push_str(&format!("{} `{}` ({})\n", status, cmd.command, took)),
+                None => body.push_str(&format!("{} `{}`\n", status, cmd.command)),
+            }
         }
 
-        if let Some(ref branch) = session.context.git_branch {
+        if let Some(branch) = session.context.git_branch() {
             body.push_str(&format!("\n**Git Branch**: `{}`\n", branch));
         }
 
-        // Extract tags
-        let mut tags = vec!["auto-solved".to_string(), "resolution".to_string()];
-        let cmd_lower = error.command.to_lowercase();
-        if cmd_lower.contains("cargo") || cmd_lower.contains("rust") {
-            tags.push("rust".to_string());
-        }
-        if cmd_lower.contains("npm") || cmd_lower.contains("node") {
-            tags.push("nodejs".to_string());
-        }
-        if cmd_lower.contains("docker") {
-            tags.push("docker".to_string());
-        }
-        if cmd_lower.contains("git") {
-            tags.push("git".to_string());
-        }
-        if cmd_lower.contains("python") || cmd_lower.contains("pip") {
-            tags.push("python".to_string());
-        }
-        tags.sort();
-        tags.dedup();
-
-        let note = Note {
-            title: title.clone(),
-            body,
-            tags,
-            links: vec![],
-            meta: std::collections::BTreeMap::from([
-                ("auto-resolution".to_string(), "true".to_string()),
-                ("error_command".to_string(), error.command.clone()),
-                (
-                    "solution_steps".to_string(),
-                    solution_steps.len().to_string(),
-                ),
-            ]),
-            solutions: vec![],
-            privacy: Privacy::Private,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            author: Author {
+        // Toolchain tags come from the error command in the meta
+        let mut note = NoteBuilder::new(title, body)
+            .tag("auto-solved")
+            .tag("resolution")
+            .meta("auto-resolution", "true")
+            .meta("error_command", error.command.clone())
+            .meta("solution_steps", solution_steps.len().to_string())
+            .author(Author {
                 name: std::env::var("USER").unwrap_or_else(|_| "auto".to_string()),
                 email: None,
-            },
-        };
+            })
+            .build();
+        let config = repo.repo().config().unwrap_or_default();
+        let dir = Path::new(&session.context.working_directory);
+        config.capture.confine(&mut note, dir);
+        config.scope.assign(&mut note, repo.repo().root(), dir);
+        if let Some(summarizer) = Summarizer::from_config(&config) {
+            if let Err(e) = summarizer.enrich(&mut note).await {
+                tracing::warn!("Failed to summarize resolution note: {}", e);
+            }
+        }
 
-        match repo.store_note(note) {
+        let created = repo
+            .write(move |repo| NoteService::new(repo)?.with_auto_tags().create(note))
+            .await;
+        match created.map(|created| created.record) {
             Ok(record) => {
                 tracing::info!(
                     "✨ Auto-resolution note created: {} ({})",
-                    title,
+                    record.note.title,
                     &record.object_id[..8]
                 );
 
                 // Send success notification
-                if let Ok(notif) = NotificationManager::new(repo.root()) {
+                if let Ok(notif) = NotificationManager::new(repo.repo().root()) {
                     let _summary = "Fukura: Problem Solved! 🎉";
                     let body_text = format!(
                         "Error: {}\n\nSolved with {} step(s)\n\nView: fuku view @latest",
@@ -1342,7 +1978,7 @@ impl FukuraDaemon {
                     let _ = notif.notify_solution_found(&body_text, solution_steps.len());
                 }
 
-                Ok(())
+                Ok(Some(record.object_id))
             }
             Err(e) => {
                 tracing::error!("Failed to create resolution note: {}", e);
@@ -1353,14 +1989,34 @@ impl FukuraDaemon {
 
     /// Find similar solutions for an error (WORLD-CLASS)
     fn find_similar_solutions(
-        repo: &Arc<FukuraRepo>,
+        repo: &FukuraRepo,
         command: &str,
         _exit_code: i32,
+        feedback: &HashMap<String, FeedbackStats>,
     ) -> Result<Vec<SolutionHit>> {
         // Search for similar commands in past notes
-        let query = Self::extract_search_terms(command);
+        Self::solutions_for_query(repo, &Self::extract_search_terms(command), feedback)
+    }
 
-        match repo.search(&query, 10, crate::index::SearchSort::Relevance) {
+    /// Past notes with a solution for an error message, most trusted first
+    pub fn solutions_for_error(
+        repo: &FukuraRepo,
+        message: &str,
+        feedback: &HashMap<String, FeedbackStats>,
+    ) -> Result<Vec<SolutionHit>> {
+        let query = Self::error_search_terms(message);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        Self::solutions_for_query(repo, &query, feedback)
+    }
+
+    fn solutions_for_query(
+        repo: &FukuraRepo,
+        query: &str,
+        feedback: &HashMap<String, FeedbackStats>,
+    ) -> Result<Vec<SolutionHit>> {
+        match repo.search(query, 10, crate::index::SearchSort::Relevance) {
             Ok(hits) => {
                 let mut solutions = Vec::new();
                 for hit in hits {
@@ -1375,15 +2031,24 @@ impl FukuraDaemon {
                             || !record.note.solutions.is_empty();
 
                         if has_solution {
+                            // Likes raise the starting point; reported outcomes decide the rest
+                            let prior = (0.5 + hit.likes as f64 / 20.0).min(1.0);
+                            let confidence = feedback
+                                .get(&record.object_id)
+                                .copied()
+                                .unwrap_or_default()
+                                .confidence(prior);
                             solutions.push(SolutionHit {
                                 note_id: record.object_id.clone(),
                                 title: record.note.title.clone(),
                                 snippet: Self::extract_solution_snippet(&record.note.body),
-                                confidence: hit.likes as f64 / 10.0, // Use likes as confidence indicator
+                                confidence,
                             });
                         }
                     }
                 }
+                // Stable sort: equally trusted notes keep their search order
+                solutions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
                 Ok(solutions)
             }
             Err(e) => Err(e),
@@ -1401,6 +2066,31 @@ impl FukuraDaemon {
         words.join(" ")
     }
 
+    /// Distinctive words of an error message, free of query syntax. Paths
+    /// and numbers say little about the kind of error and are skipped.
+    pub fn error_search_terms(message: &str) -> String {
+        let noise_words = ["error", "the", "and", "for", "not", "with", "from"];
+        let mut terms: Vec<&str> = Vec::new();
+        let words = message
+            .split_whitespace()
+            .filter(|token| !token.contains('/') && !token.contains('\\'))
+            .flat_map(|token| token.split(|c: char| !c.is_alphanumeric() && c != '_'));
+        for word in words {
+            if word.chars().count() < 3
+                || word.chars().all(|c| c.is_ascii_digit())
+                || noise_words.contains(&word.to_lowercase().as_str())
+                || terms.contains(&word)
+            {
+                continue;
+            }
+            terms.push(word);
+            if terms.len() == 8 {
+                break;
+            }
+        }
+        terms.join(" ")
+    }
+
     /// Extract solution snippet from note body
     fn extract_solution_snippet(body: &str) -> String {
         // Look for solution markers
@@ -1443,21 +2133,13 @@ impl FukuraDaemon {
             "This note was automatically generated from a development session with errors.\n",
         );
 
-        Note {
-            title: format!("Auto-generated: Session {}", session.id),
-            body,
-            tags: vec!["auto-generated".into(), "session".into(), "error".into()],
-            links: vec![],
-            meta: std::collections::BTreeMap::new(),
-            solutions: vec![],
-            privacy: Privacy::Private,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            author: Author {
+        NoteBuilder::new(format!("Auto-generated: Session {}", session.id), body)
+            .tags(["auto-generated".into(), "session".into(), "error".into()])
+            .author(Author {
                 name: "Fukura Daemon".into(),
                 email: None,
-            },
-        }
+            })
+            .build()
     }
 }
 
@@ -1471,6 +2153,8 @@ pub struct Solution {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{PrivacyZone, ZoneMode};
+    use crate::models::Privacy;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -1498,4 +2182,271 @@ mod tests {
         assert!(normalized.contains("/path/to/file"));
         assert!(normalized.contains("42:5"));
     }
+
+    /// Hook context without notifications, so tests never hit the desktop
+    fn test_context(root: &Path, capture: CaptureConfig) -> HookContext {
+        HookContext {
+            repos: AsyncRepos::default(),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            notification_manager: None,
+            command_log: Some(Arc::new(CommandLog::new(root).unwrap())),
+            capture: Arc::new(capture),
+            error_patterns: Arc::new(RwLock::new(HashMap::new())),
+            pattern_store: Arc::new(PatternStore::new(root)),
+            feedback: Arc::new(FeedbackStore::new(root)),
+            pending_feedback: Arc::new(RwLock::new(HashMap::new())),
+            issues: None,
+            auto_record: None,
+            streaks: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hook_message_creates_error_note_with_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ctx = test_context(temp_dir.path(), CaptureConfig::default());
+
+        let msg = HookMessage {
+            session_id: "s1".to_string(),
+            command: "cargo build |\n  tee out.log".to_string(),
+            exit_code: 101,
+            working_dir: temp_dir.path().display().to_string(),
+            stderr: "error[E0425]: cannot find value `x`".to_string(),
+            duration_ms: Some(750_000),
+        };
+        FukuraDaemon::handle_hook_message(&msg, &ctx).await;
+
+        let sessions = ctx.sessions.read().await;
+        let session = &sessions["s1"];
+        assert_eq!(session.commands.len(), 1);
+        assert_eq!(session.commands[0].duration, Some(Duration::from_secs(750)));
+        assert_eq!(
+            session.errors[0].stderr_output.as_deref(),
+            Some("error[E0425]: cannot find value `x`")
+        );
+
+        let latest = repo.resolve_object_id("@latest").unwrap();
+        let note = repo.load_note(&latest).unwrap().note;
+        assert_eq!(note.title, "Error: cargo build | …");
+        assert!(note.body.contains("error[E0425]"));
+        assert!(note.body.contains("failed after 12m 30s"));
+        let commands = ctx.command_log.unwrap().read_all().unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].duration_ms, Some(750_000));
+    }
+
+    #[tokio::test]
+    async fn test_capture_policy_skips_denied_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let capture = CaptureConfig {
+            deny: vec!["ls".to_string()],
+            ..CaptureConfig::default()
+        };
+        let ctx = test_context(temp_dir.path(), capture);
+
+        let msg = HookMessage {
+            session_id: "s1".to_string(),
+            command: "ls missing-dir".to_string(),
+            exit_code: 2,
+            working_dir: temp_dir.path().display().to_string(),
+            stderr: "ls: cannot access 'missing-dir'".to_string(),
+            duration_ms: None,
+        };
+        FukuraDaemon::handle_hook_message(&msg, &ctx).await;
+
+        // Still part of the history, but no error, note or pattern
+        assert_eq!(ctx.sessions.read().await["s1"].commands.len(), 1);
+        assert!(ctx.sessions.read().await["s1"].errors.is_empty());
+        assert!(repo.resolve_object_id("@latest").is_err());
+        assert!(ctx.pattern_store.load().unwrap().is_empty());
+        assert_eq!(ctx.command_log.unwrap().read_all().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_privacy_zones_limit_capture() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let zone = |dir: &str, mode: ZoneMode| PrivacyZone {
+            path: temp_dir.path().join(dir).display().to_string(),
+            mode,
+        };
+        let capture = CaptureConfig {
+            zones: vec![
+                zone("acme", ZoneMode::NeverCapture),
+                zone("globex", ZoneMode::Private),
+            ],
+            ..CaptureConfig::default()
+        };
+        let ctx = test_context(temp_dir.path(), capture);
+        let failure = |dir: &str| HookMessage {
+            session_id: dir.to_string(),
+            command: "terraform apply".to_string(),
+            exit_code: 1,
+            working_dir: temp_dir.path().join(dir).display().to_string(),
+            stderr: "Error: invalid credentials".to_string(),
+            duration_ms: None,
+        };
+
+        FukuraDaemon::handle_hook_message(&failure("acme/infra"), &ctx).await;
+        assert!(ctx.sessions.read().await.is_empty());
+        assert!(repo.resolve_object_id("@latest").is_err());
+        assert!(ctx
+            .command_log
+            .as_ref()
+            .unwrap()
+            .read_all()
+            .unwrap()
+            .is_empty());
+
+        FukuraDaemon::handle_hook_message(&failure("globex/infra"), &ctx).await;
+        let latest = repo.resolve_object_id("@latest").unwrap();
+        let note = repo.load_note(&latest).unwrap().note;
+        assert!(note.is_local_only());
+        assert_eq!(note.privacy, Privacy::Private);
+    }
+
+    #[tokio::test]
+    async fn test_error_streak_records_until_fixed() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ctx = HookContext {
+            auto_record: Some(2),
+            ..test_context(temp_dir.path(), CaptureConfig::default())
+        };
+        let run = |command: &str, exit_code: i32| HookMessage {
+            session_id: "s1".to_string(),
+            command: command.to_string(),
+            exit_code,
+            working_dir: temp_dir.path().display().to_string(),
+            stderr: if exit_code == 0 {
+                String::new()
+            } else {
+                "error[E0425]: cannot find value `x`".to_string()
+            },
+            duration_ms: None,
+        };
+        let store = RecordingStore::new(temp_dir.path());
+
+        FukuraDaemon::handle_hook_message(&run("cargo build", 101), &ctx).await;
+        assert!(store.list().unwrap().is_empty());
+        FukuraDaemon::handle_hook_message(&run("cargo build", 101), &ctx).await;
+        let recording = store.load("auto-cargo-build").unwrap().unwrap();
+        assert_eq!(recording.title, "Fix `cargo build`");
+
+        FukuraDaemon::handle_hook_message(&run("vim src/main.rs", 0), &ctx).await;
+        FukuraDaemon::handle_hook_message(&run("cargo build", 0), &ctx).await;
+        assert!(store.list().unwrap().is_empty());
+
+        let latest = repo.resolve_object_id("@latest").unwrap();
+        let note = repo.load_note(&latest).unwrap().note;
+        assert_eq!(note.title, "Fix `cargo build`");
+        assert!(note.tags.contains(&"resolution".to_string()));
+        let steps = crate::recording::parse_steps(&note.body);
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[3].fixes, Some(1));
+        let patterns = ctx.pattern_store.load().unwrap();
+        assert!(patterns
+            .values()
+            .any(|pattern| pattern.resolved_by.contains(&latest)));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_split_by_repository_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let _repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ctx = test_context(temp_dir.path(), CaptureConfig::default());
+        for project in ["api", "web"] {
+            std::fs::create_dir_all(temp_dir.path().join(project).join(".git")).unwrap();
+            std::fs::create_dir_all(temp_dir.path().join(project).join("src")).unwrap();
+        }
+        let command = |dir: &str, command: &str| HookMessage {
+            session_id: "s1".to_string(),
+            command: command.to_string(),
+            exit_code: 0,
+            working_dir: temp_dir.path().join(dir).display().to_string(),
+            stderr: String::new(),
+            duration_ms: None,
+        };
+
+        FukuraDaemon::handle_hook_message(&command("api", "cargo build"), &ctx).await;
+        FukuraDaemon::handle_hook_message(&command("web", "npm install"), &ctx).await;
+        FukuraDaemon::handle_hook_message(&command("api/src", "cargo test"), &ctx).await;
+
+        let sessions = ctx.sessions.read().await;
+        assert_eq!(sessions.len(), 2);
+        let api = sessions
+            .values()
+            .find(|session| session.context.working_directory.ends_with("api"))
+            .unwrap();
+        assert!(api.id.starts_with("s1@"));
+        let commands: Vec<&str> = api.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo build", "cargo test"]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_errors_persist_pattern() {
+        let temp_dir = TempDir::new().unwrap();
+        let _repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ctx = test_context(temp_dir.path(), CaptureConfig::default());
+
+        for session_id in ["s1", "s2"] {
+            let msg = HookMessage {
+                session_id: session_id.to_string(),
+                command: "cargo test".to_string(),
+                exit_code: 101,
+                working_dir: temp_dir.path().display().to_string(),
+                stderr: "   Compiling app\nerror[E0308]: mismatched types".to_string(),
+                duration_ms: None,
+            };
+            FukuraDaemon::handle_hook_message(&msg, &ctx).await;
+        }
+
+        // A restarted daemon sees the same pattern
+        let daemon = FukuraDaemon::new(temp_dir.path(), DaemonConfig::default()).unwrap();
+        daemon.load_error_patterns().await.unwrap();
+        let patterns = daemon.error_patterns.read().await;
+        assert_eq!(patterns.len(), 1);
+        let pattern = patterns.values().next().unwrap();
+        assert_eq!(pattern.normalized_message, "error[E0308]: mismatched types");
+        assert_eq!(pattern.occurrences, 2);
+        assert_eq!(pattern.notes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_next_command_reports_opened_suggestion() {
+        let temp_dir = TempDir::new().unwrap();
+        let _repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ctx = test_context(temp_dir.path(), CaptureConfig::default());
+        let hit = |id: &str| SolutionHit {
+            note_id: id.to_string(),
+            title: String::new(),
+            snippet: String::new(),
+            confidence: 0.5,
+        };
+        FukuraDaemon::track_suggestions(&ctx, "s1", &[hit("opened"), hit("ignored")]).await;
+        ctx.feedback
+            .append(&FeedbackEvent::new("opened", FeedbackKind::Opened))
+            .unwrap();
+
+        let command = |command: &str| HookMessage {
+            session_id: "s1".to_string(),
+            command: command.to_string(),
+            exit_code: 0,
+            working_dir: temp_dir.path().display().to_string(),
+            stderr: String::new(),
+            duration_ms: None,
+        };
+        // Viewing the note is not an attempt at the fix
+        FukuraDaemon::handle_hook_message(&command("fuku view opened"), &ctx).await;
+        assert!(ctx.pending_feedback.read().await.contains_key("s1"));
+        FukuraDaemon::handle_hook_message(&command("cargo build"), &ctx).await;
+        assert!(ctx.pending_feedback.read().await.is_empty());
+
+        let stats = ctx.feedback.stats().unwrap();
+        assert_eq!(stats["opened"].implicit_worked, 1);
+        assert_eq!(stats["ignored"].suggested, 1);
+        assert_eq!(stats["ignored"].implicit_worked, 0);
+    }
 }
@@ -6,6 +6,7 @@ use std::time::Duration;
 use tokio::fs;
 
 use crate::daemon::{DaemonConfig, FukuraDaemon};
+use crate::prompt_state::PromptState;
 use crate::repo::FukuraRepo;
 
 /// Background daemon service management
@@ -26,6 +27,11 @@ impl DaemonService {
         if self.is_running_sync() {
             return Err(anyhow::anyhow!("Daemon already running"));
         }
+        if crate::repo::is_read_only_at(&self.repo_path) {
+            return Err(anyhow::anyhow!(
+                "The repository is read-only, so there is no daemon to capture into it"
+            ));
+        }
 
         if cfg!(target_os = "windows") {
             self.start_windows_service()
@@ -35,7 +41,7 @@ impl DaemonService {
     }
 
     /// Synchronous version of is_running for use in non-async contexts
-    fn is_running_sync(&self) -> bool {
+    pub fn is_running_sync(&self) -> bool {
         let pid_file = self.get_pid_file_path();
 
         if !pid_file.exists() {
@@ -65,6 +71,21 @@ impl DaemonService {
         }
     }
 
+    /// Process id of the daemon, if it is running
+    pub fn running_pid(&self) -> Option<u32> {
+        let pid = std::fs::read_to_string(self.get_pid_file_path()).ok()?;
+        let pid = pid.trim().parse().ok()?;
+        self.is_running_sync().then_some(pid)
+    }
+
+    /// How long the daemon has run, going by when its PID file was written
+    pub fn uptime(&self) -> Option<Duration> {
+        let started = std::fs::metadata(self.get_pid_file_path())
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        std::time::SystemTime::now().duration_since(started).ok()
+    }
+
     /// Stop the background daemon
     pub async fn stop_background(&self) -> Result<()> {
         let pid_file = self.get_pid_file_path();
@@ -84,6 +105,7 @@ impl DaemonService {
         }
 
         fs::remove_file(&pid_file).await?;
+        PromptState::new(&self.repo_path.join(".fukura")).clear_heartbeat()?;
         Ok(())
     }
 
@@ -126,18 +148,22 @@ impl DaemonService {
         let exe_path = std::env::current_exe()?;
         let daemon_dir = self.repo_path.join(".fukura");
         let pid_file = self.get_pid_file_path();
-        let log_file = daemon_dir.join("daemon.log");
+        let log_dir = daemon_dir.join("logs");
 
         // Create daemon directory if it doesn't exist
-        std::fs::create_dir_all(&daemon_dir)?;
+        std::fs::create_dir_all(&log_dir)?;
+
+        // Tracing output goes to logs/daemon.log; raw stdout/stderr (e.g. panics)
+        // land next to it so nothing from the detached process is lost
+        let output = std::fs::File::create(log_dir.join("daemon.out"))?;
 
         // Start daemon in background using nohup with proper process detachment
         let mut cmd = Command::new("nohup");
         cmd.arg(&exe_path)
             .args(["daemon", "--foreground"])
             .current_dir(&self.repo_path)
-            .stdout(Stdio::from(std::fs::File::create(&log_file)?))
-            .stderr(Stdio::from(std::fs::File::create(&log_file)?))
+            .stdout(Stdio::from(output.try_clone()?))
+            .stderr(Stdio::from(output))
             .stdin(Stdio::null());
 
         let child = cmd.spawn()?;
@@ -158,7 +184,8 @@ impl DaemonService {
             return Err(anyhow::anyhow!("Failed to start daemon process"));
         }
 
-        println!("Daemon started successfully with PID: {}", pid);
+        // Callers report the start; stdout may be machine-readable output
+        tracing::debug!("Daemon started with PID {}", pid);
 
         Ok(())
     }
@@ -182,7 +209,6 @@ impl DaemonService {
         let exe_path = std::env::current_exe()?;
         let daemon_dir = self.repo_path.join(".fukura");
         let pid_file = self.get_pid_file_path();
-        let _log_file = daemon_dir.join("daemon.log");
 
         // Create daemon directory if it doesn't exist
         std::fs::create_dir_all(&daemon_dir)?;
@@ -288,7 +314,9 @@ impl AutoNoteDaemon {
         for session_id in completed_sessions {
             if let Some(session) = sessions_guard.remove(&session_id) {
                 if let Ok(note) = Self::create_note_from_session(&session).await {
-                    if let Err(e) = repo.store_note(note) {
+                    let created = crate::note_service::NoteService::new(repo)
+                        .and_then(|service| service.with_auto_tags().create(note));
+                    if let Err(e) = created {
                         eprintln!("Failed to store auto-generated note: {}", e);
                     }
                 }
@@ -304,24 +332,13 @@ impl AutoNoteDaemon {
         let body = Self::generate_body_from_session(session);
         let tags = Self::generate_tags_from_session(session);
 
-        let now = chrono::Utc::now();
-        let author = crate::models::Author {
-            name: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
-            email: Some(std::env::var("EMAIL").unwrap_or_default()),
-        };
-
-        Ok(crate::models::Note {
-            title,
-            body,
-            tags,
-            links: Vec::new(),
-            meta: std::collections::BTreeMap::new(),
-            solutions: Vec::new(),
-            privacy: crate::models::Privacy::Private,
-            created_at: now,
-            updated_at: now,
-            author,
-        })
+        Ok(crate::note_service::NoteBuilder::new(title, body)
+            .tags(tags)
+            .author(crate::models::Author {
+                name: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
+                email: Some(std::env::var("EMAIL").unwrap_or_default()),
+            })
+            .build())
     }
 
     fn generate_title_from_session(session: &crate::daemon::ActiveSession) -> String {
@@ -358,7 +375,7 @@ impl AutoNoteDaemon {
             session.context.working_directory
         ));
 
-        if let Some(branch) = &session.context.git_branch {
+        if let Some(branch) = session.context.git_branch() {
             body.push_str(&format!("**Git Branch:** {}\n\n", branch));
         }
 
@@ -0,0 +1,455 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use crate::daemon::FukuraDaemon;
+use crate::feedback::{FeedbackEvent, FeedbackKind, FeedbackStore};
+use crate::index::SearchSort;
+use crate::models::{Author, Note};
+use crate::note_service::{NoteBuilder, NoteService};
+use crate::repo::FukuraRepo;
+
+/// Methods answered by [`EditorServer`]
+pub const METHODS: &[&str] = &[
+    "initialize",
+    "search",
+    "view",
+    "createFromSelection",
+    "explainError",
+    "shutdown",
+    "exit",
+];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Longest title derived from a selection
+const TITLE_CHARS: usize = 72;
+
+/// How a message was framed on the wire. Replies use the framing of the
+/// request, so clients can speak either LSP-style `Content-Length` headers
+/// (vscode-jsonrpc, `vim.lsp.rpc`) or one JSON object per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    ContentLength,
+    Line,
+}
+
+/// Long-running JSON-RPC 2.0 server for editor extensions. Keeps the
+/// repository and its search index open between requests, so a search per
+/// keystroke costs a query rather than a process start.
+pub struct EditorServer {
+    repo: FukuraRepo,
+    feedback: FeedbackStore,
+    shutdown: bool,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    query: String,
+    #[serde(default = "SearchParams::default_limit")]
+    limit: usize,
+    #[serde(default)]
+    sort: Option<SearchSort>,
+}
+
+impl SearchParams {
+    fn default_limit() -> usize {
+        20
+    }
+}
+
+#[derive(Deserialize)]
+struct ViewParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct SelectionParams {
+    text: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// File the selection came from
+    #[serde(default)]
+    file: Option<String>,
+    /// First selected line, 1-based
+    #[serde(default)]
+    line: Option<u32>,
+    /// Editor language id, used for the code fence
+    #[serde(default)]
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExplainParams {
+    message: String,
+    #[serde(default = "ExplainParams::default_limit")]
+    limit: usize,
+}
+
+impl ExplainParams {
+    fn default_limit() -> usize {
+        5
+    }
+}
+
+impl EditorServer {
+    pub fn new(repo: FukuraRepo) -> Self {
+        let feedback = FeedbackStore::new(repo.root());
+        Self {
+            repo,
+            feedback,
+            shutdown: false,
+        }
+    }
+
+    /// Serve requests from `input` until `exit` or end of input
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> Result<()> {
+        while let Some((payload, framing)) = read_message(&mut input)? {
+            let reply = match serde_json::from_str::<Value>(&payload) {
+                Ok(request) => self.handle(&request),
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+            if let Some(reply) = reply {
+                write_message(&mut output, &reply, framing)?;
+            }
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer one request. Notifications (requests without an id) get no reply.
+    pub fn handle(&mut self, request: &Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "Missing method",
+            ));
+        };
+        let params = match request.get("params") {
+            None | Some(Value::Null) => json!({}),
+            Some(params) => params.clone(),
+        };
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "name": "fukura",
+                "version": env!("CARGO_PKG_VERSION"),
+                "root": self.repo.root().display().to_string(),
+                "methods": METHODS,
+            })),
+            "search" => parse_params(params).and_then(|p| self.search(p)),
+            "view" => parse_params(params).and_then(|p| self.view(p)),
+            "createFromSelection" => parse_params(params).and_then(|p| self.create(p)),
+            "explainError" => parse_params(params).and_then(|p| self.explain(p)),
+            "shutdown" => Ok(Value::Null),
+            "exit" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            )),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e.code, &e.message),
+        })
+    }
+
+    fn search(&self, params: SearchParams) -> Result<Value, RpcError> {
+        let index = self.repo.index()?;
+        let sort = params.sort.unwrap_or(if params.query.trim().is_empty() {
+            SearchSort::Updated
+        } else {
+            SearchSort::Relevance
+        });
+        let mut hits = index.search(&params.query, params.limit, sort)?;
+        self.repo.mark_pinned(&mut hits)?;
+        Ok(serde_json::to_value(hits)?)
+    }
+
+    fn view(&self, params: ViewParams) -> Result<Value, RpcError> {
+        let object_id = self
+            .repo
+            .resolve_object_id(&params.id)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+        let record = self.repo.load_note(&object_id)?;
+        // Counts towards solution feedback like `fuku view`
+        let _ = self
+            .feedback
+            .append(&FeedbackEvent::new(&object_id, FeedbackKind::Opened));
+        Ok(serde_json::to_value(record)?)
+    }
+
+    fn create(&self, params: SelectionParams) -> Result<Value, RpcError> {
+        let text = params.text.trim_end();
+        if text.trim().is_empty() {
+            return Err(RpcError::new(INVALID_PARAMS, "Selection is empty"));
+        }
+        let note = selection_note(&params, text);
+        let record = NoteService::new(&self.repo)?.create(note)?.record;
+        Ok(json!({ "object_id": record.object_id, "title": record.note.title }))
+    }
+
+    fn explain(&self, params: ExplainParams) -> Result<Value, RpcError> {
+        let feedback = self.feedback.stats().unwrap_or_default();
+        let mut solutions =
+            FukuraDaemon::solutions_for_error(&self.repo, &params.message, &feedback)?;
+        solutions.truncate(params.limit);
+        Ok(serde_json::to_value(solutions)?)
+    }
+}
+
+/// Note for an editor selection, fenced as code and tagged with its source
+fn selection_note(params: &SelectionParams, text: &str) -> Note {
+    let title = params
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| {
+            let first_line = text
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default();
+            first_line.chars().take(TITLE_CHARS).collect()
+        });
+
+    let mut body = String::new();
+    if let Some(file) = &params.file {
+        match params.line {
+            Some(line) => body.push_str(&format!("From `{}:{}`\n\n", file, line)),
+            None => body.push_str(&format!("From `{}`\n\n", file)),
+        }
+    }
+    body.push_str(&format!(
+        "```{}\n{}\n```\n",
+        params.language.as_deref().unwrap_or_default(),
+        text
+    ));
+
+    let mut meta = BTreeMap::from([("source".to_string(), "editor".to_string())]);
+    if let Some(file) = &params.file {
+        meta.insert("file".to_string(), file.clone());
+    }
+    NoteBuilder::new(title, body)
+        .tags(params.tags.iter().cloned())
+        .meta_map(meta)
+        .author(Author {
+            name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            email: None,
+        })
+        .build()
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(INTERNAL_ERROR, e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(INTERNAL_ERROR, e.to_string())
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Next message and its framing, or `None` at end of input. Blank lines
+/// between messages are skipped.
+pub fn read_message<R: BufRead>(input: &mut R) -> Result<Option<(String, Framing)>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+    if !line.to_ascii_lowercase().starts_with("content-length:") {
+        return Ok(Some((line.trim().to_string(), Framing::Line)));
+    }
+
+    let mut length: Option<usize> = None;
+    loop {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse().context("Invalid Content-Length")?);
+            }
+        }
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            bail!("Unexpected end of input in message headers");
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut payload = vec![0; length.context("Missing Content-Length")?];
+    input.read_exact(&mut payload)?;
+    Ok(Some((String::from_utf8(payload)?, Framing::ContentLength)))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Value, framing: Framing) -> Result<()> {
+    let payload = serde_json::to_string(message)?;
+    match framing {
+        Framing::ContentLength => write!(
+            output,
+            "Content-Length: {}\r\n\r\n{}",
+            payload.len(),
+            payload
+        )?,
+        Framing::Line => writeln!(output, "{}", payload)?,
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    #[test]
+    fn test_create_search_view_and_explain() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let mut server = EditorServer::new(repo);
+
+        let created = server
+            .handle(&request(
+                1,
+                "createFromSelection",
+                json!({
+                    "text": "Solution: export SSL_CERT_FILE=/etc/ssl/cert.pem\n",
+                    "title": "Fix certificate verify failed",
+                    "file": "scripts/setup.sh",
+                    "line": 12,
+                    "language": "bash",
+                    "tags": ["TLS"],
+                }),
+            ))
+            .unwrap();
+        let object_id = created["result"]["object_id"].as_str().unwrap().to_string();
+
+        let hits = server
+            .handle(&request(2, "search", json!({ "query": "certificate" })))
+            .unwrap();
+        assert_eq!(hits["result"][0]["object_id"], object_id.as_str());
+
+        let note = server
+            .handle(&request(3, "view", json!({ "id": &object_id[..8] })))
+            .unwrap();
+        let body = note["result"]["note"]["body"].as_str().unwrap();
+        assert!(body.starts_with("From `scripts/setup.sh:12`\n\n```bash\n"));
+        assert_eq!(note["result"]["note"]["tags"], json!(["tls"]));
+
+        let explained = server
+            .handle(&request(
+                4,
+                "explainError",
+                json!({ "message": "ssl: certificate verify failed (/usr/lib/python3/ssl.py:1006)" }),
+            ))
+            .unwrap();
+        assert_eq!(explained["result"][0]["note_id"], object_id.as_str());
+    }
+
+    #[test]
+    fn test_errors_and_notifications() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let mut server = EditorServer::new(repo);
+
+        let reply = server.handle(&request(1, "format", Value::Null)).unwrap();
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+        let reply = server.handle(&request(2, "view", json!({}))).unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+        let reply = server
+            .handle(&request(3, "createFromSelection", json!({ "text": "  " })))
+            .unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+        assert!(server
+            .handle(&json!({ "jsonrpc": "2.0", "method": "initialized" }))
+            .is_none());
+    }
+
+    #[test]
+    fn test_run_answers_in_request_framing() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let mut server = EditorServer::new(repo);
+
+        let framed = request(1, "initialize", json!({})).to_string();
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{}{}\n{}\n{}\n",
+            framed.len(),
+            framed,
+            request(2, "shutdown", Value::Null),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            request(3, "search", json!({})),
+        );
+        let mut output = Vec::new();
+        server.run(Cursor::new(input), &mut output).unwrap();
+
+        let mut replies = Cursor::new(output);
+        let (first, framing) = read_message(&mut replies).unwrap().unwrap();
+        assert_eq!(framing, Framing::ContentLength);
+        let first: Value = serde_json::from_str(&first).unwrap();
+        assert_eq!(first["result"]["name"], "fukura");
+        let (second, framing) = read_message(&mut replies).unwrap().unwrap();
+        assert_eq!(framing, Framing::Line);
+        assert_eq!(serde_json::from_str::<Value>(&second).unwrap()["id"], 2);
+        // Nothing is answered after exit
+        assert!(read_message(&mut replies).unwrap().is_none());
+    }
+}
@@ -0,0 +1,361 @@
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use crate::config::CaptureConfig;
+use crate::issues::{block_on, IssueLinker, IssueReason};
+use crate::models::{Author, Note};
+use crate::note_service::{NoteBuilder, NoteService};
+use crate::patterns::PatternStore;
+use crate::redaction::Redactor;
+use crate::repo::FukuraRepo;
+
+/// Lines kept after an error line, e.g. a stack trace
+const MAX_CONTEXT_LINES: usize = 20;
+
+/// Characters of the error message used in a note title
+const TITLE_CHARS: usize = 80;
+
+/// An error seen by a source without shell hooks (container logs, ...)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestEvent {
+    /// Source name, also added as a tag ("docker")
+    pub source: String,
+    /// What produced the error, e.g. a container name. Occurrences of the
+    /// same error from the same subject update one note.
+    pub subject: String,
+    pub message: String,
+    /// Lines that followed the error
+    pub context: Vec<String>,
+    pub tags: Vec<String>,
+    pub meta: BTreeMap<String, String>,
+    /// Markdown added below the error in a new note, e.g. a manifest snippet
+    pub details: Option<String>,
+    pub timestamp: SystemTime,
+}
+
+impl IngestEvent {
+    pub fn new(source: &str, subject: &str, block: ErrorBlock) -> Self {
+        Self {
+            source: source.to_string(),
+            subject: subject.to_string(),
+            message: block.message,
+            context: block.context,
+            tags: Vec::new(),
+            meta: BTreeMap::new(),
+            details: None,
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IngestOutcome {
+    Created(String),
+    /// The error had been seen before; the note was rewritten with the new
+    /// occurrence count and has this new id
+    Updated(String),
+}
+
+impl IngestOutcome {
+    pub fn note_id(&self) -> &str {
+        match self {
+            Self::Created(id) | Self::Updated(id) => id,
+        }
+    }
+}
+
+/// An error line and the lines that belong to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorBlock {
+    pub message: String,
+    pub context: Vec<String>,
+}
+
+/// Whether a log line reports an error
+pub fn is_error_line(line: &str) -> bool {
+    static ERROR: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?i)\b(error|err|exception|fatal|panic(ked)?|traceback|failed|failure|oomkilled|crashloopbackoff|segmentation fault)\b",
+        )
+        .expect("valid error pattern")
+    });
+    static NOT_AN_ERROR: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)\b(0|no) (errors?|failures?)\b").expect("valid error pattern")
+    });
+    ERROR.is_match(line) && !NOT_AN_ERROR.is_match(line)
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with(char::is_whitespace)
+        || line.starts_with("Caused by")
+        || line.starts_with("at ")
+        || line.starts_with("...")
+}
+
+/// Splits a stream of log lines into error blocks: an error line followed
+/// by its indented continuation lines
+#[derive(Debug, Default)]
+pub struct LogScanner {
+    current: Option<ErrorBlock>,
+}
+
+impl LogScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one line; returns the previous block once it is complete
+    pub fn push(&mut self, line: &str) -> Option<ErrorBlock> {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            return None;
+        }
+        if let Some(block) = &mut self.current {
+            if is_continuation(line) && !is_error_line(line.trim_start()) {
+                if block.context.len() < MAX_CONTEXT_LINES {
+                    block.context.push(line.to_string());
+                }
+                return None;
+            }
+        }
+        let finished = self.current.take();
+        if is_error_line(line) {
+            self.current = Some(ErrorBlock {
+                message: line.trim().to_string(),
+                context: Vec::new(),
+            });
+        }
+        finished
+    }
+
+    /// The block in progress, at end of input
+    pub fn finish(&mut self) -> Option<ErrorBlock> {
+        self.current.take()
+    }
+}
+
+/// Turns ingested errors into notes with the daemon's normalization, so a
+/// container error and the same error in a shell share a pattern
+pub struct Ingestor {
+    repo: FukuraRepo,
+    patterns: PatternStore,
+    capture: CaptureConfig,
+    issues: Option<IssueLinker>,
+}
+
+impl Ingestor {
+    pub fn new(repo: FukuraRepo) -> Result<Self> {
+        let config = repo.config()?;
+        let redactor = Redactor::default_with_overrides(&config.redaction_overrides);
+        let patterns = PatternStore::new(repo.root()).with_redactor(redactor);
+        let issues = IssueLinker::from_config(&config.integrations).unwrap_or_else(|e| {
+            tracing::warn!("Issue tracker integration disabled: {}", e);
+            None
+        });
+        Ok(Self {
+            repo,
+            patterns,
+            capture: config.capture,
+            issues,
+        })
+    }
+
+    pub fn ingest(&self, event: &IngestEvent) -> Result<IngestOutcome> {
+        let (normalized, key) = self.patterns.pattern_key(&event.message);
+        let known = self.patterns.load()?.remove(&key);
+        let previous = known.as_ref().and_then(|pattern| {
+            pattern
+                .notes
+                .iter()
+                .rev()
+                .filter_map(|id| self.repo.load_note(id).ok())
+                .find(|record| {
+                    record.note.meta.get("source") == Some(&event.source)
+                        && record.note.meta.get("subject") == Some(&event.subject)
+                })
+        });
+        let seen = DateTime::<Utc>::from(event.timestamp);
+
+        let mut outcome = match previous {
+            Some(previous) => {
+                let mut note = previous.note.clone();
+                let occurrences = note
+                    .meta
+                    .get("occurrences")
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .unwrap_or(1);
+                note.meta
+                    .insert("occurrences".to_string(), (occurrences + 1).to_string());
+                note.meta.insert(
+                    "last_seen".to_string(),
+                    seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+                );
+                note.updated_at = Utc::now();
+                let record = self.repo.replace_note(&previous.object_id, note)?;
+                IngestOutcome::Updated(record.object_id)
+            }
+            None => {
+                let mut note = self.note(event, &normalized, seen);
+                if let Some(ttl) = self.capture.expiry() {
+                    note.set_expires_at(note.created_at + ttl);
+                }
+                // Repeats are folded by pattern above, not by the service
+                let record = NoteService::new(&self.repo)?.create(note)?.record;
+                IngestOutcome::Created(record.object_id)
+            }
+        };
+        let origin = format!("{}:{}", event.source, event.subject);
+        let (key, patterns) = self.patterns.record_failure(
+            &event.message,
+            Some(&origin),
+            Some(outcome.note_id()),
+            event.timestamp,
+        )?;
+
+        let recurring = patterns.get(&key).and_then(|pattern| {
+            let linker = self.issues.as_ref()?;
+            linker
+                .crossed_threshold(pattern)
+                .then_some((linker, pattern.occurrences))
+        });
+        if let Some((linker, occurrences)) = recurring {
+            let record = self.repo.load_note(outcome.note_id())?;
+            let reason = IssueReason::Recurring(occurrences);
+            match block_on(linker.open_issue(&self.repo, &record, reason))? {
+                Ok(linked) => {
+                    outcome = match outcome {
+                        IngestOutcome::Created(_) => IngestOutcome::Created(linked.object_id),
+                        IngestOutcome::Updated(_) => IngestOutcome::Updated(linked.object_id),
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to open issue for recurring error: {}", e),
+            }
+        }
+        Ok(outcome)
+    }
+
+    fn note(&self, event: &IngestEvent, normalized: &str, seen: DateTime<Utc>) -> Note {
+        let message: String = event.message.chars().take(TITLE_CHARS).collect();
+        let mut body = format!(
+            "## Error in {} `{}`\n\n```\n{}\n",
+            event.source, event.subject, event.message
+        );
+        for line in &event.context {
+            body.push_str(line);
+            body.push('\n');
+        }
+        body.push_str("```\n\n");
+        if let Some(details) = &event.details {
+            body.push_str(details.trim_end());
+            body.push_str("\n\n");
+        }
+        body.push_str(&format!("**Normalized**: `{}`\n\n", normalized));
+        body.push_str(&format!("**Time**: {}", seen.format("%Y-%m-%d %H:%M:%S")));
+
+        let mut meta = event.meta.clone();
+        meta.insert("source".to_string(), event.source.clone());
+        meta.insert("subject".to_string(), event.subject.clone());
+        meta.insert("occurrences".to_string(), "1".to_string());
+        meta.insert(
+            "last_seen".to_string(),
+            seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+        );
+
+        NoteBuilder::new(format!("Error in {}: {}", event.subject, message), body)
+            .tag("error")
+            .tag("auto-captured")
+            .tag(&event.source)
+            .tags(event.tags.iter().cloned())
+            .meta_map(meta)
+            .author(Author {
+                name: std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .unwrap_or_else(|_| "unknown".to_string()),
+                email: None,
+            })
+            .created_at(seen)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scanner_groups_stack_traces() {
+        let mut scanner = LogScanner::new();
+        let lines = [
+            "INFO server started",
+            "ERROR Unhandled exception: connection refused",
+            "    at db.connect (db.js:10)",
+            "    at main (index.js:3)",
+            "INFO retrying",
+            "panic: runtime error: index out of range",
+        ];
+        let mut blocks: Vec<ErrorBlock> =
+            lines.iter().filter_map(|line| scanner.push(line)).collect();
+        blocks.extend(scanner.finish());
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            blocks[0].message,
+            "ERROR Unhandled exception: connection refused"
+        );
+        assert_eq!(blocks[0].context.len(), 2);
+        assert!(blocks[1].message.starts_with("panic:"));
+        assert!(!is_error_line("Build finished with 0 errors"));
+    }
+
+    #[test]
+    fn test_repeated_error_updates_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let ingestor = Ingestor::new(repo.clone()).unwrap();
+        let block = ErrorBlock {
+            message: "ERROR could not connect to postgres:5432".to_string(),
+            context: vec![],
+        };
+        let mut event = IngestEvent::new("docker", "api", block);
+        event.tags.push("image:api-server".to_string());
+
+        let created = ingestor.ingest(&event).unwrap();
+        assert!(matches!(created, IngestOutcome::Created(_)));
+        let updated = ingestor.ingest(&event).unwrap();
+        assert!(matches!(updated, IngestOutcome::Updated(_)));
+
+        let notes = repo.list_all_notes().unwrap();
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0].note;
+        assert_eq!(note.meta["occurrences"], "2");
+        assert!(note.tags.contains(&"image:api-server".to_string()));
+        assert!(note
+            .title
+            .starts_with("Error in api: ERROR could not connect"));
+
+        // The same error elsewhere gets its own note
+        let other = IngestEvent::new(
+            "docker",
+            "worker",
+            ErrorBlock {
+                message: event.message.clone(),
+                context: vec![],
+            },
+        );
+        assert!(matches!(
+            ingestor.ingest(&other).unwrap(),
+            IngestOutcome::Created(_)
+        ));
+        let pattern = PatternStore::new(temp_dir.path())
+            .load()
+            .unwrap()
+            .into_values()
+            .next()
+            .unwrap();
+        assert_eq!(pattern.occurrences, 3);
+    }
+}
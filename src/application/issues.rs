@@ -0,0 +1,202 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::future::Future;
+
+use crate::config::IntegrationsConfig;
+use crate::integrations::{self, Integration, IssueRequest};
+use crate::models::{Note, NoteRecord};
+use crate::patterns::ErrorPattern;
+use crate::redaction::Redactor;
+use crate::repo::FukuraRepo;
+
+/// Note meta key holding the URL of the issue opened for it
+pub const ISSUE_URL_META: &str = "issue_url";
+
+/// Label on issues opened for recurring errors
+const RECURRING_LABEL: &str = "recurring-error";
+
+/// Why an issue is opened for a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueReason {
+    /// The note carries the incident tag
+    Incident,
+    /// Its error pattern reached the occurrence threshold
+    Recurring(u32),
+}
+
+/// Opens issues for incident notes and recurring errors, and links them
+/// back from the note
+pub struct IssueLinker {
+    integration: Box<dyn Integration>,
+    incident_tag: String,
+    threshold: Option<u32>,
+}
+
+impl IssueLinker {
+    /// `None` when no tracker is configured
+    pub fn from_config(config: &IntegrationsConfig) -> Result<Option<Self>> {
+        let Some(tracker) = &config.issues else {
+            return Ok(None);
+        };
+        Ok(Some(Self::new(integrations::from_config(tracker)?, config)))
+    }
+
+    pub fn new(integration: Box<dyn Integration>, config: &IntegrationsConfig) -> Self {
+        Self {
+            integration,
+            incident_tag: config.incident_tag.to_lowercase(),
+            threshold: config.occurrence_threshold,
+        }
+    }
+
+    pub fn tracker_name(&self) -> &'static str {
+        self.integration.name()
+    }
+
+    /// Tagged as an incident and not linked to an issue yet
+    pub fn needs_incident_issue(&self, note: &Note) -> bool {
+        note.tags.contains(&self.incident_tag) && !note.meta.contains_key(ISSUE_URL_META)
+    }
+
+    /// Whether the last occurrence brought a pattern to the threshold. Only
+    /// the crossing counts, so a pattern gets one issue.
+    pub fn crossed_threshold(&self, pattern: &ErrorPattern) -> bool {
+        self.threshold == Some(pattern.occurrences)
+    }
+
+    /// Open an issue for a note and store its URL in the note's links.
+    /// Returns the updated record, or the note as is when it already has one.
+    pub async fn open_issue(
+        &self,
+        repo: &FukuraRepo,
+        record: &NoteRecord,
+        reason: IssueReason,
+    ) -> Result<NoteRecord> {
+        if record.note.meta.contains_key(ISSUE_URL_META) {
+            return Ok(record.clone());
+        }
+        let issue = self.issue_for(repo, record, reason)?;
+        let url = self.integration.create_issue(&issue).await?;
+
+        let mut note = record.note.clone();
+        if !note.links.contains(&url) {
+            note.links.push(url.clone());
+        }
+        note.meta.insert(ISSUE_URL_META.to_string(), url);
+        note.updated_at = Utc::now();
+        repo.replace_note(&record.object_id, note)
+    }
+
+    fn issue_for(
+        &self,
+        repo: &FukuraRepo,
+        record: &NoteRecord,
+        reason: IssueReason,
+    ) -> Result<IssueRequest> {
+        let note = &record.note;
+        let (title, labels) = match reason {
+            IssueReason::Incident => (note.title.clone(), vec![self.incident_tag.clone()]),
+            IssueReason::Recurring(occurrences) => (
+                format!(
+                    "Recurring error ({} occurrences): {}",
+                    occurrences, note.title
+                ),
+                vec![RECURRING_LABEL.to_string()],
+            ),
+        };
+        // The issue leaves the machine; apply the same redaction as captures
+        let config = repo.config()?;
+        let redactor = Redactor::default_with_overrides(&config.redaction_overrides);
+        let short_id: String = record.object_id.chars().take(8).collect();
+        let body = format!(
+            "{}\n\n---\nOpened by fuku from note `{}` (`fuku view {}`)",
+            note.body.trim_end(),
+            short_id,
+            short_id
+        );
+        Ok(IssueRequest {
+            title: redactor.redact(&title),
+            body: redactor.redact(&body),
+            labels,
+        })
+    }
+}
+
+/// Run an issue request from synchronous code, inside the CLI's runtime or
+/// without one
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => Ok(tokio::task::block_in_place(|| handle.block_on(future))),
+        Err(_) => Ok(tokio::runtime::Runtime::new()?.block_on(future)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrations::BoxFuture;
+    use crate::models::Author;
+    use crate::note_service::NoteBuilder;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// Records issues instead of calling a tracker
+    struct Recorder(Arc<Mutex<Vec<IssueRequest>>>);
+
+    impl Integration for Recorder {
+        fn name(&self) -> &'static str {
+            "Recorder"
+        }
+
+        fn create_issue<'a>(&'a self, issue: &'a IssueRequest) -> BoxFuture<'a, Result<String>> {
+            Box::pin(async move {
+                let mut issues = self.0.lock().unwrap();
+                issues.push(issue.clone());
+                Ok(format!(
+                    "https://tracker.example.com/issues/{}",
+                    issues.len()
+                ))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_incident_note_gets_one_linked_issue() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let issues = Arc::new(Mutex::new(Vec::new()));
+        let linker = IssueLinker::new(
+            Box::new(Recorder(issues.clone())),
+            &IntegrationsConfig::default(),
+        );
+
+        let note = NoteBuilder::new("Checkout is down", "Payments return 502 since the deploy")
+            .tag("incident")
+            .tag("payments")
+            .author(Author {
+                name: "Tester".into(),
+                email: None,
+            })
+            .build();
+        let record = repo.store_note(note).unwrap();
+        assert!(linker.needs_incident_issue(&record.note));
+        let linked = linker
+            .open_issue(&repo, &record, IssueReason::Incident)
+            .await
+            .unwrap();
+        let url = "https://tracker.example.com/issues/1";
+        assert_eq!(linked.note.links, vec![url.to_string()]);
+        assert_eq!(linked.note.meta[ISSUE_URL_META], url);
+        assert!(!linker.needs_incident_issue(&linked.note));
+
+        // Already linked: nothing new is opened
+        linker
+            .open_issue(&repo, &linked, IssueReason::Incident)
+            .await
+            .unwrap();
+        let issues = issues.lock().unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].title, "Checkout is down");
+        assert_eq!(issues[0].labels, vec!["incident".to_string()]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::activity_storage::CommandLog;
+use crate::daemon_service::DaemonService;
+use crate::hooks::{HookManager, InstalledHooks};
+use crate::notification::{ClickAction, NotificationManager};
+use crate::prompt_state::PromptStatus;
+use crate::repo::FukuraRepo;
+
+/// Everything `fuku status` reports about a repository, for editors and
+/// scripts reading `fuku status --json`. `recording` and `unsynced` match
+/// `fuku prompt-status --output json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    pub root: PathBuf,
+    pub daemon: DaemonStatus,
+    /// Hooks installed per shell
+    pub hooks: Vec<ShellHooks>,
+    /// Whether hooks are installed for the current shell
+    pub hooks_installed: bool,
+    pub notifications: NotificationStatus,
+    /// When the daemon last captured a command
+    pub last_capture: Option<DateTime<Utc>>,
+    /// Names of the `fuku rec` recordings in progress
+    pub recording: Vec<String>,
+    /// Private notes not yet pushed
+    pub unsynced: usize,
+    /// Notes in the search index; unset when it can't be opened
+    pub indexed_notes: Option<u64>,
+    pub remote: Option<String>,
+    pub auto_sync: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DaemonStatus {
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub uptime_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellHooks {
+    #[serde(flatten)]
+    pub hooks: InstalledHooks,
+    /// Installed by this version; see [`crate::hooks::HOOK_VERSION`]
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationStatus {
+    pub enabled: bool,
+    pub click: ClickAction,
+}
+
+impl StatusSnapshot {
+    /// Gather the status of `repo`. Only reads files and checks the daemon
+    /// process; nothing here talks to the daemon or the remote.
+    pub fn collect(repo: &FukuraRepo) -> Result<Self> {
+        let root = repo.root().to_path_buf();
+        let config = repo.config()?;
+        let daemon = DaemonService::new(&root);
+        let hook_manager = HookManager::new(&root);
+        let notifications = NotificationManager::new(&root)?;
+        let prompt = PromptStatus::read(&root);
+
+        let pid = daemon.running_pid();
+        Ok(Self {
+            daemon: DaemonStatus {
+                running: pid.is_some(),
+                pid,
+                uptime_secs: pid
+                    .and_then(|_| daemon.uptime())
+                    .map(|uptime| uptime.as_secs()),
+            },
+            hooks: hook_manager
+                .installed()
+                .into_iter()
+                .map(|hooks| ShellHooks {
+                    current: hooks.is_current(),
+                    hooks,
+                })
+                .collect(),
+            hooks_installed: hook_manager.are_hooks_installed().unwrap_or(false),
+            notifications: NotificationStatus {
+                enabled: notifications.is_enabled(),
+                click: notifications.click_action(),
+            },
+            last_capture: last_capture(&root),
+            recording: prompt.recording,
+            unsynced: prompt.unsynced,
+            indexed_notes: repo.index().and_then(|index| index.num_docs()).ok(),
+            remote: config.default_remote.clone(),
+            auto_sync: config.auto_sync.unwrap_or(false),
+            root,
+        })
+    }
+
+    /// Hooks installed by an older version
+    pub fn outdated_hooks(&self) -> impl Iterator<Item = &InstalledHooks> {
+        self.hooks
+            .iter()
+            .filter(|shell| !shell.current)
+            .map(|shell| &shell.hooks)
+    }
+}
+
+/// The command log only grows as the daemon captures commands, so its
+/// modification time is the last capture without reading it
+fn last_capture(root: &std::path::Path) -> Option<DateTime<Utc>> {
+    let log = CommandLog::new(root).ok()?;
+    let modified = std::fs::metadata(log.path()).ok()?.modified().ok()?;
+    Some(modified.into())
+}
+
+/// How long ago `time` was, e.g. `3m 12s`
+pub fn age(time: SystemTime) -> String {
+    crate::recording::format_duration(
+        SystemTime::now()
+            .duration_since(time)
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_of_a_fresh_repository() {
+        let dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(dir.path(), true).unwrap();
+        let snapshot = StatusSnapshot::collect(&repo).unwrap();
+        assert!(!snapshot.daemon.running);
+        assert_eq!(snapshot.daemon.uptime_secs, None);
+        assert_eq!(snapshot.last_capture, None);
+        assert_eq!(snapshot.unsynced, 0);
+        assert_eq!(snapshot.indexed_notes, Some(0));
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["daemon"]["running"], false);
+        assert!(json["hooks"].is_array());
+        assert!(json["notifications"]["enabled"].is_boolean());
+    }
+}
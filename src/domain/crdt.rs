@@ -0,0 +1,275 @@
+//! Replicated state of a note's tags and likes, so machines that tag or like
+//! the same note offline merge their changes instead of the last sync
+//! winning. Tags are an observed-remove set (a concurrent add wins over a
+//! remove) and each solution's likes a grow-only counter per replica.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Contributor, Note, NoteRecord};
+
+/// Counter only ever incremented, by replica. The value is the sum; merging
+/// keeps each replica's highest count.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GCounter {
+    counts: BTreeMap<String, u64>,
+}
+
+impl GCounter {
+    pub fn increment(&mut self, replica: &str, by: u64) {
+        *self.counts.entry(replica.to_string()).or_insert(0) += by;
+    }
+
+    pub fn value(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    pub fn merge(&mut self, other: &GCounter) {
+        for (replica, count) in &other.counts {
+            let entry = self.counts.entry(replica.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
+        }
+    }
+}
+
+/// Observed-remove set of strings. Every add gets a unique dot; a remove
+/// drops only the dots it has seen, so an add made concurrently elsewhere
+/// survives the merge.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrSet {
+    /// Element to the dots of its adds
+    adds: BTreeMap<String, BTreeSet<String>>,
+    /// Dots of removed adds
+    removed: BTreeSet<String>,
+}
+
+impl OrSet {
+    /// Add `element` under `dot`, which must be unique to this add
+    pub fn add(&mut self, element: &str, dot: String) {
+        self.adds
+            .entry(element.to_string())
+            .or_default()
+            .insert(dot);
+    }
+
+    /// Remove `element` as far as this replica has seen it added
+    pub fn remove(&mut self, element: &str) {
+        if let Some(dots) = self.adds.get(element) {
+            self.removed.extend(dots.iter().cloned());
+        }
+    }
+
+    pub fn contains(&self, element: &str) -> bool {
+        self.adds
+            .get(element)
+            .is_some_and(|dots| dots.iter().any(|dot| !self.removed.contains(dot)))
+    }
+
+    /// Elements present, sorted
+    pub fn elements(&self) -> Vec<String> {
+        self.adds
+            .keys()
+            .filter(|element| self.contains(element))
+            .cloned()
+            .collect()
+    }
+
+    pub fn merge(&mut self, other: &OrSet) {
+        for (element, dots) in &other.adds {
+            self.adds
+                .entry(element.clone())
+                .or_default()
+                .extend(dots.iter().cloned());
+        }
+        self.removed.extend(other.removed.iter().cloned());
+    }
+}
+
+/// Replicated tags and likes of a note, carried in the note itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteCrdt {
+    /// Object id of the revision the state started from; revisions with
+    /// the same origin are versions of one note
+    pub origin: String,
+    pub tags: OrSet,
+    /// Likes by solution index
+    #[serde(default)]
+    pub likes: BTreeMap<usize, GCounter>,
+}
+
+impl NoteCrdt {
+    /// State of a note that had none, starting at `origin`. Dots are
+    /// derived from the origin, so replicas that start from the same
+    /// revision agree on them.
+    pub fn start(note: &Note, origin: &str) -> Self {
+        let mut state = NoteCrdt {
+            origin: origin.to_string(),
+            ..Default::default()
+        };
+        for tag in &note.tags {
+            state.tags.add(tag, format!("{}:{}", origin, tag));
+        }
+        for (index, solution) in note.solutions.iter().enumerate() {
+            if solution.likes > 0 {
+                let mut counter = GCounter::default();
+                counter.increment(origin, u64::from(solution.likes));
+                state.likes.insert(index, counter);
+            }
+        }
+        state
+    }
+}
+
+/// Object id of the first revision of the note `record` is a version of
+pub fn origin(record: &NoteRecord) -> &str {
+    record
+        .note
+        .crdt
+        .as_ref()
+        .map_or(&record.object_id, |state| &state.origin)
+}
+
+/// Bring the replicated state of `note` in line with its tags and likes
+/// after `replica` changed them from `base`, the revision it was edited
+/// from. A note without state starts from `base` at `origin`, so what
+/// `base` had counts as seen by every replica and only the edit is
+/// `replica`'s. Likes only grow; a lower count is ignored.
+pub fn record_changes(note: &mut Note, base: &Note, origin: &str, replica: &str) {
+    let mut state = note
+        .crdt
+        .take()
+        .or_else(|| base.crdt.clone())
+        .unwrap_or_else(|| NoteCrdt::start(base, origin));
+    for element in state.tags.elements() {
+        if !note.tags.contains(&element) {
+            state.tags.remove(&element);
+        }
+    }
+    for tag in &note.tags {
+        if !state.tags.contains(tag) {
+            let dot = format!("{}:{}", replica, uuid::Uuid::new_v4());
+            state.tags.add(tag, dot);
+        }
+    }
+    for (index, solution) in note.solutions.iter().enumerate() {
+        let counter = state.likes.entry(index).or_default();
+        let likes = u64::from(solution.likes);
+        if likes > counter.value() {
+            counter.increment(replica, likes - counter.value());
+        }
+    }
+    note.crdt = Some(state);
+}
+
+/// Merge two versions of a note with the same origin. Tags and likes merge
+/// from the replicated state; everything else comes from the version
+/// updated last. The result doesn't depend on the order of the arguments.
+pub fn merge_notes(a: &Note, b: &Note) -> Note {
+    let a_wins = (a.updated_at, &a.title, &a.body) >= (b.updated_at, &b.title, &b.body);
+    let (winner, other) = if a_wins { (a, b) } else { (b, a) };
+    let mut merged = winner.clone();
+
+    let mut contributors: Vec<Contributor> = a.contributors.clone();
+    for contributor in &b.contributors {
+        if !contributors.contains(contributor) {
+            contributors.push(contributor.clone());
+        }
+    }
+    contributors.sort_by(|x, y| (x.at, &x.name).cmp(&(y.at, &y.name)));
+    merged.contributors = contributors;
+
+    let (Some(mine), Some(theirs)) = (&winner.crdt, &other.crdt) else {
+        return merged;
+    };
+    let mut state = mine.clone();
+    state.tags.merge(&theirs.tags);
+    for (index, counter) in &theirs.likes {
+        state.likes.entry(*index).or_default().merge(counter);
+    }
+    merged.tags = state.tags.elements();
+    for (index, solution) in merged.solutions.iter_mut().enumerate() {
+        if let Some(counter) = state.likes.get(&index) {
+            solution.likes = u32::try_from(counter.value()).unwrap_or(u32::MAX);
+        }
+    }
+    merged.crdt = Some(state);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Solution;
+    use crate::note_service::NoteBuilder;
+    use chrono::Duration;
+
+    fn base() -> Note {
+        NoteBuilder::new("Proxy", "Set HTTPS_PROXY")
+            .tags(["network".to_string(), "proxy".to_string()])
+            .solutions([Solution {
+                steps: vec!["export HTTPS_PROXY=...".to_string()],
+                likes: 2,
+                ..Default::default()
+            }])
+            .build()
+    }
+
+    #[test]
+    fn test_counters_and_sets_merge_both_ways() {
+        let mut a = GCounter::default();
+        let mut b = GCounter::default();
+        a.increment("a", 2);
+        b.increment("b", 3);
+        b.merge(&a);
+        a.merge(&b);
+        a.merge(&b);
+        assert_eq!((a.value(), b.value()), (5, 5));
+
+        let mut x = OrSet::default();
+        x.add("rust", "x:1".to_string());
+        let mut y = x.clone();
+        // Removed on one side while added again on the other: the add wins
+        x.remove("rust");
+        y.add("rust", "y:1".to_string());
+        x.merge(&y);
+        assert!(x.contains("rust"));
+        let mut z = OrSet::default();
+        z.merge(&x);
+        z.remove("rust");
+        assert!(z.elements().is_empty());
+    }
+
+    #[test]
+    fn test_offline_tags_and_likes_merge() {
+        let base = base();
+        let origin = "a".repeat(64);
+
+        let mut laptop = base.clone();
+        laptop.tags.retain(|tag| tag != "proxy");
+        laptop.tags.push("corp".to_string());
+        laptop.solutions[0].likes += 1;
+        laptop.updated_at = base.updated_at + Duration::minutes(5);
+        record_changes(&mut laptop, &base, &origin, "laptop");
+
+        let mut desktop = base.clone();
+        desktop.tags.push("vpn".to_string());
+        desktop.solutions[0].likes += 2;
+        desktop.updated_at = base.updated_at + Duration::minutes(1);
+        record_changes(&mut desktop, &base, &origin, "desktop");
+
+        let merged = merge_notes(&laptop, &desktop);
+        assert_eq!(merged.tags, ["corp", "network", "vpn"]);
+        assert_eq!(merged.solutions[0].likes, 5);
+        // Same result whichever side merges
+        let other_way = merge_notes(&desktop, &laptop);
+        assert_eq!(other_way.tags, merged.tags);
+        assert_eq!(other_way.solutions[0].likes, 5);
+        assert_eq!(other_way.crdt, merged.crdt);
+        // Merging again changes nothing
+        let again = merge_notes(&merged, &desktop);
+        assert_eq!(again.tags, merged.tags);
+        assert_eq!(again.solutions[0].likes, 5);
+        assert_eq!(merged.updated_at, laptop.updated_at);
+    }
+}
@@ -0,0 +1,317 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use crate::models::{Note, NoteRecord};
+
+/// Default similarity at or above which two notes are considered duplicates
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// Bodies are compared on at most this many characters
+const MAX_COMPARE_CHARS: usize = 2000;
+
+/// Group of near-identical notes; `members[0]` is the note to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    /// Indices into the slice passed to [`cluster_notes`]
+    pub members: Vec<usize>,
+    /// Lowest pairwise link similarity that joined the cluster
+    pub similarity: f64,
+}
+
+/// Prepared text of one note for comparisons
+struct Fingerprint {
+    text: Vec<char>,
+    tokens: HashSet<String>,
+}
+
+impl Fingerprint {
+    fn new(note: &Note) -> Self {
+        let normalized = normalize_body(&format!("{}\n{}", note.title, note.body));
+        let tokens = normalized
+            .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '<' && c != '>')
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self {
+            text: normalized.chars().take(MAX_COMPARE_CHARS).collect(),
+            tokens,
+        }
+    }
+}
+
+/// Strip what varies between occurrences of the same error: timestamps,
+/// paths, hashes and numbers
+pub fn normalize_body(body: &str) -> String {
+    static TIME_LINE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?mi)^\*\*time\*\*:.*$").expect("valid time pattern"));
+    static PATH: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?:[A-Za-z]:)?[/\\][^\s`'\x22:]+").expect("valid path pattern"));
+    static HEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\b[0-9a-f]{8,}\b").expect("valid hex pattern"));
+    static NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").expect("valid number pattern"));
+
+    let body = body.to_lowercase();
+    let body = TIME_LINE.replace_all(&body, "");
+    let body = PATH.replace_all(&body, "<path>");
+    let body = HEX.replace_all(&body, "<hex>");
+    let body = NUMBER.replace_all(&body, "0");
+    body.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    let union = a.len() + b.len() - intersection;
+    intersection as f64 / union as f64
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn similarity_of(a: &Fingerprint, b: &Fingerprint, threshold: f64) -> f64 {
+    let token_score = jaccard(&a.tokens, &b.tokens);
+    // Edit distance is expensive; only refine pairs that share most of their words
+    if token_score >= threshold || token_score < threshold - 0.3 {
+        return token_score;
+    }
+    let longest = a.text.len().max(b.text.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let edit_score = 1.0 - levenshtein(&a.text, &b.text) as f64 / longest as f64;
+    token_score.max(edit_score)
+}
+
+/// Similarity between two notes in `[0, 1]` on their normalized title and body
+pub fn similarity(a: &Note, b: &Note) -> f64 {
+    similarity_of(&Fingerprint::new(a), &Fingerprint::new(b), 1.0)
+}
+
+/// Cluster notes whose similarity reaches `threshold`. Only clusters with
+/// more than one note are returned, largest first; the note to keep comes first
+/// (the one with solutions, then the oldest).
+pub fn cluster_notes(records: &[NoteRecord], threshold: f64) -> Vec<Cluster> {
+    let fingerprints: Vec<Fingerprint> =
+        records.iter().map(|r| Fingerprint::new(&r.note)).collect();
+
+    let mut parent: Vec<usize> = (0..records.len()).collect();
+    let mut link_score = vec![1.0_f64; records.len()];
+    fn find(parent: &mut [usize], mut idx: usize) -> usize {
+        while parent[idx] != idx {
+            parent[idx] = parent[parent[idx]];
+            idx = parent[idx];
+        }
+        idx
+    }
+
+    for (i, a) in fingerprints.iter().enumerate() {
+        for (j, b) in fingerprints.iter().enumerate().skip(i + 1) {
+            let score = similarity_of(a, b, threshold);
+            if score < threshold {
+                continue;
+            }
+            let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+            if root_i != root_j {
+                parent[root_j] = root_i;
+                link_score[root_i] = link_score[root_i].min(link_score[root_j]).min(score);
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for idx in 0..records.len() {
+        let root = find(&mut parent, idx);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(idx),
+            None => groups.push((root, vec![idx])),
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, mut members)| {
+            members.sort_by_key(|&idx| {
+                let note = &records[idx].note;
+                (note.solutions.is_empty(), note.created_at)
+            });
+            Cluster {
+                members,
+                similarity: link_score[root],
+            }
+        })
+        .collect();
+    clusters.sort_by_key(|cluster| Reverse(cluster.members.len()));
+    clusters
+}
+
+fn occurrences(note: &Note) -> u32 {
+    note.meta
+        .get("occurrences")
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Fold duplicates into the canonical note: occurrence counts add up, and
+/// tags, links and solutions are combined
+pub fn merge_notes(canonical: &Note, duplicates: &[&Note]) -> Note {
+    let mut merged = canonical.clone();
+    let mut total = occurrences(canonical);
+    let mut first_seen = canonical.created_at;
+    let mut last_seen = canonical.created_at;
+
+    for duplicate in duplicates {
+        total += occurrences(duplicate);
+        first_seen = first_seen.min(duplicate.created_at);
+        last_seen = last_seen.max(duplicate.created_at);
+        for tag in &duplicate.tags {
+            if !merged.tags.contains(tag) {
+                merged.tags.push(tag.clone());
+            }
+        }
+        for link in &duplicate.links {
+            if !merged.links.contains(link) {
+                merged.links.push(link.clone());
+            }
+        }
+        for solution in &duplicate.solutions {
+            if !merged.solutions.iter().any(|s| s.steps == solution.steps) {
+                merged.solutions.push(solution.clone());
+            }
+        }
+    }
+
+    merged
+        .meta
+        .insert("occurrences".to_string(), total.to_string());
+    merged
+        .meta
+        .insert("first_seen".to_string(), first_seen.to_rfc3339());
+    merged
+        .meta
+        .insert("last_seen".to_string(), last_seen.to_rfc3339());
+    merged.created_at = first_seen;
+    merged.updated_at = chrono::Utc::now();
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Solution;
+    use crate::note_service::NoteBuilder;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    /// `minutes` into the test period
+    fn minute(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 10, 9, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn error_body(path: &str, line: u32, time: &str) -> String {
+        format!(
+            "## Command Failed\n\n```bash\n$ cargo build\n\n# Error output:\nerror[E0425]: cannot find value `x` in {}:{}\n```\n\n**Exit Code**: 101\n\n**Working Directory**: `{}`\n\n**Time**: {}",
+            path, line, path, time
+        )
+    }
+
+    #[test]
+    fn test_normalize_body_strips_volatile_parts() {
+        let a = normalize_body(&error_body(
+            "/home/a/app/src/main.rs",
+            4,
+            "2024-06-10 09:00:00",
+        ));
+        let b = normalize_body(&error_body(
+            "/Users/b/app/src/lib.rs",
+            12,
+            "2024-06-11 18:30:12",
+        ));
+        assert_eq!(a, b);
+        assert!(!a.contains("2024"));
+    }
+
+    #[test]
+    fn test_clusters_near_identical_notes() {
+        let records = vec![
+            NoteBuilder::new("Error: cargo build", error_body("/x/main.rs", 1, "t1"))
+                .created_at(minute(5))
+                .record("a"),
+            NoteBuilder::new("Error: npm test", "npm ERR! missing script: test")
+                .created_at(minute(0))
+                .record("b"),
+            NoteBuilder::new("Error: cargo build", error_body("/y/lib.rs", 2, "t2"))
+                .created_at(minute(1))
+                .record("c"),
+            NoteBuilder::new("Error: cargo build", error_body("/z/mod.rs", 3, "t3"))
+                .created_at(minute(9))
+                .record("d"),
+        ];
+        let clusters = cluster_notes(&records, DEFAULT_THRESHOLD);
+        assert_eq!(clusters.len(), 1);
+        // Oldest note is kept
+        assert_eq!(clusters[0].members, vec![2, 0, 3]);
+        assert!(clusters[0].similarity >= DEFAULT_THRESHOLD);
+        assert!(similarity(&records[0].note, &records[1].note) < 0.5);
+    }
+
+    #[test]
+    fn test_note_with_solution_is_kept() {
+        let mut records = vec![
+            NoteBuilder::new("Error: make", "make: *** no rule to make target")
+                .created_at(minute(0))
+                .record("a"),
+            NoteBuilder::new("Error: make", "make: *** no rule to make target")
+                .created_at(minute(5))
+                .record("b"),
+        ];
+        records[1].note.solutions.push(Solution {
+            steps: vec!["touch Makefile".to_string()],
+            ..Solution::default()
+        });
+        let clusters = cluster_notes(&records, DEFAULT_THRESHOLD);
+        assert_eq!(clusters[0].members, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_merge_counts_occurrences() {
+        let canonical = NoteBuilder::new("Error: make", "body")
+            .tag("error")
+            .tag("auto-captured")
+            .meta("occurrences", "3")
+            .created_at(minute(10))
+            .build();
+        let duplicate = NoteBuilder::new("Error: make", "body")
+            .tag("error")
+            .tag("rust")
+            .created_at(minute(0))
+            .build();
+
+        let merged = merge_notes(&canonical, &[&duplicate]);
+        assert_eq!(merged.meta["occurrences"], "4");
+        assert_eq!(merged.created_at, duplicate.created_at);
+        assert!(merged.tags.contains(&"rust".to_string()));
+        assert_eq!(merged.tags.len(), 3);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+    }
+}
@@ -0,0 +1,243 @@
+//! Line diffs between notes, for `fuku diff`: their bodies as a unified
+//! diff, plus what changed in their titles, tags and meta.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::models::Note;
+
+/// Unchanged lines shown around each change
+pub const CONTEXT_LINES: usize = 3;
+
+/// Bodies whose differing middles would take more comparisons than this
+/// are shown as replaced wholesale rather than diffed line by line
+const MAX_COMPARISONS: usize = 4_000_000;
+
+/// One line of a diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Changes between two notes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoteDiff {
+    /// `[old, new]` when the titles differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<[String; 2]>,
+    pub tags_removed: Vec<String>,
+    pub tags_added: Vec<String>,
+    /// Keys with `[old, new]` values; a missing side is `None`
+    pub meta: BTreeMap<String, [Option<String>; 2]>,
+    /// Unified diff of the bodies, without file headers
+    pub body: String,
+}
+
+impl NoteDiff {
+    pub fn new(old: &Note, new: &Note) -> Self {
+        let meta = old
+            .meta
+            .keys()
+            .chain(new.meta.keys())
+            .filter(|key| old.meta.get(*key) != new.meta.get(*key))
+            .map(|key| {
+                (
+                    key.clone(),
+                    [old.meta.get(key).cloned(), new.meta.get(key).cloned()],
+                )
+            })
+            .collect();
+        Self {
+            title: (old.title != new.title).then(|| [old.title.clone(), new.title.clone()]),
+            tags_removed: old
+                .tags
+                .iter()
+                .filter(|tag| !new.tags.contains(tag))
+                .cloned()
+                .collect(),
+            tags_added: new
+                .tags
+                .iter()
+                .filter(|tag| !old.tags.contains(tag))
+                .cloned()
+                .collect(),
+            meta,
+            body: unified(&old.body, &new.body, CONTEXT_LINES),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.tags_removed.is_empty()
+            && self.tags_added.is_empty()
+            && self.meta.is_empty()
+            && self.body.is_empty()
+    }
+}
+
+/// Lines of `old` and `new` as kept, removed and added lines
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_COMPARISONS {
+        lines.extend(old_mid.iter().map(|l| DiffLine::Removed(l)));
+        lines.extend(new_mid.iter().map(|l| DiffLine::Added(l)));
+    } else {
+        lines.extend(lcs_diff(old_mid, new_mid));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    lines
+}
+
+/// Diff by longest common subsequence; quadratic, so only for the part of
+/// two texts that differs
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lengths[i][j]: LCS length of old[i..] and new[j..]
+    let mut lengths = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
+
+/// Unified diff of `old` and `new` with `context` lines around changes;
+/// empty when they're the same
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let lines = diff_lines(old, new);
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    let mut out = String::new();
+    let mut idx = 0;
+    while idx < changed.len() {
+        // Changes closer than twice the context share a hunk
+        let start = changed[idx].saturating_sub(context);
+        let mut end = changed[idx];
+        while idx < changed.len() && changed[idx] <= end + 2 * context {
+            end = changed[idx];
+            idx += 1;
+        }
+        let end = (end + context + 1).min(lines.len());
+
+        let (old_before, new_before) = counts(&lines[..start]);
+        let (old_len, new_len) = counts(&lines[start..end]);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_before, old_len),
+            range(new_before, new_len)
+        ));
+        for line in &lines[start..end] {
+            let (sign, text) = match line {
+                DiffLine::Same(text) => (' ', text),
+                DiffLine::Removed(text) => ('-', text),
+                DiffLine::Added(text) => ('+', text),
+            };
+            out.push(sign);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Lines of the old and new text among `lines`
+fn counts(lines: &[DiffLine]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(old, new), line| match line {
+        DiffLine::Same(_) => (old + 1, new + 1),
+        DiffLine::Removed(_) => (old + 1, new),
+        DiffLine::Added(_) => (old, new + 1),
+    })
+}
+
+/// A hunk range as in `diff -u`: `start,len`, where an empty range starts
+/// at the line before it
+fn range(before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+
+    #[test]
+    fn test_unified_diff_of_bodies() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            unified(old, new, 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -10 +10,2 @@\n j\n+k\n"
+        );
+        assert_eq!(unified(old, old, 3), "");
+    }
+
+    #[test]
+    fn test_note_diff_covers_tags_and_meta() {
+        let old = NoteBuilder::new("Port in use", "kill the process")
+            .tag("node")
+            .tag("auto-captured")
+            .build();
+        let mut new = NoteBuilder::new("Port 3000 in use", "kill the process")
+            .tag("node")
+            .tag("network")
+            .build();
+        new.meta.insert("exit_code".to_string(), "1".to_string());
+
+        let diff = NoteDiff::new(&old, &new);
+        assert_eq!(
+            diff.title,
+            Some(["Port in use".to_string(), "Port 3000 in use".to_string()])
+        );
+        assert_eq!(diff.tags_removed, ["auto-captured"]);
+        assert_eq!(diff.tags_added, ["network"]);
+        assert_eq!(diff.meta["exit_code"], [None, Some("1".to_string())]);
+        assert!(diff.body.is_empty());
+        assert!(NoteDiff::new(&old, &old).is_empty());
+    }
+}
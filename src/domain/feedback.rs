@@ -0,0 +1,213 @@
+use anyhow::Result;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What happened to a note suggested as a solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackKind {
+    /// The daemon suggested the note after an error
+    Suggested,
+    /// The note was viewed or opened
+    Opened,
+    Worked,
+    DidntWork,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackEvent {
+    pub note_id: String,
+    pub kind: FeedbackKind,
+    /// Inferred by the daemon from the next command's exit code rather than
+    /// reported with `fuku feedback`
+    #[serde(default)]
+    pub implicit: bool,
+    pub timestamp: SystemTime,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+impl FeedbackEvent {
+    pub fn new(note_id: &str, kind: FeedbackKind) -> Self {
+        Self {
+            note_id: note_id.to_string(),
+            kind,
+            implicit: false,
+            timestamp: SystemTime::now(),
+            working_dir: None,
+        }
+    }
+
+    pub fn implicit(mut self) -> Self {
+        self.implicit = true;
+        self
+    }
+
+    pub fn in_dir(mut self, working_dir: &str) -> Self {
+        self.working_dir = Some(working_dir.to_string());
+        self
+    }
+}
+
+/// Aggregated feedback for one note
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeedbackStats {
+    pub suggested: u32,
+    pub opened: u32,
+    pub worked: u32,
+    pub didnt_work: u32,
+    pub implicit_worked: u32,
+    pub implicit_didnt_work: u32,
+}
+
+impl FeedbackStats {
+    fn add(&mut self, event: &FeedbackEvent) {
+        match (event.kind, event.implicit) {
+            (FeedbackKind::Suggested, _) => self.suggested += 1,
+            (FeedbackKind::Opened, _) => self.opened += 1,
+            (FeedbackKind::Worked, false) => self.worked += 1,
+            (FeedbackKind::Worked, true) => self.implicit_worked += 1,
+            (FeedbackKind::DidntWork, false) => self.didnt_work += 1,
+            (FeedbackKind::DidntWork, true) => self.implicit_didnt_work += 1,
+        }
+    }
+
+    /// Success rate in `[0, 1]`, starting from `prior` (worth two observations)
+    /// and moving towards the observed outcomes. Explicit feedback counts twice
+    /// as much as outcomes inferred from the next command.
+    pub fn confidence(&self, prior: f64) -> f64 {
+        let positive = 2.0 * self.worked as f64 + self.implicit_worked as f64;
+        let negative = 2.0 * self.didnt_work as f64 + self.implicit_didnt_work as f64;
+        (positive + 2.0 * prior.clamp(0.0, 1.0)) / (positive + negative + 2.0)
+    }
+}
+
+/// Solution feedback under `.fukura/feedback.jsonl`
+pub struct FeedbackStore {
+    path: PathBuf,
+}
+
+impl FeedbackStore {
+    pub fn new(repo_path: &Path) -> Self {
+        Self {
+            path: repo_path.join(".fukura").join("feedback.jsonl"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, event: &FeedbackEvent) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.lock_exclusive()?;
+        let result = file.write_all(line.as_bytes());
+        let _ = FileExt::unlock(&file);
+        result?;
+        Ok(())
+    }
+
+    /// All events in the order they were written; corrupt lines are skipped
+    pub fn read_all(&self) -> Result<Vec<FeedbackEvent>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(reader
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+
+    pub fn stats(&self) -> Result<HashMap<String, FeedbackStats>> {
+        let mut stats: HashMap<String, FeedbackStats> = HashMap::new();
+        for event in self.read_all()? {
+            stats.entry(event.note_id.clone()).or_default().add(&event);
+        }
+        Ok(stats)
+    }
+
+    /// Which of `note_ids` were opened at or after `since`
+    pub fn opened_since(&self, note_ids: &[String], since: SystemTime) -> Result<Vec<String>> {
+        let mut opened: Vec<String> = Vec::new();
+        for event in self.read_all()? {
+            if event.kind == FeedbackKind::Opened
+                && event.timestamp >= since
+                && note_ids.contains(&event.note_id)
+                && !opened.contains(&event.note_id)
+            {
+                opened.push(event.note_id);
+            }
+        }
+        Ok(opened)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stats_and_confidence() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FeedbackStore::new(temp_dir.path());
+        store
+            .append(&FeedbackEvent::new("a", FeedbackKind::Suggested))
+            .unwrap();
+        store
+            .append(&FeedbackEvent::new("a", FeedbackKind::Worked))
+            .unwrap();
+        store
+            .append(&FeedbackEvent::new("a", FeedbackKind::Worked).implicit())
+            .unwrap();
+        store
+            .append(&FeedbackEvent::new("b", FeedbackKind::DidntWork))
+            .unwrap();
+
+        let stats = store.stats().unwrap();
+        assert_eq!(stats["a"].suggested, 1);
+        assert_eq!(stats["a"].worked, 1);
+        assert_eq!(stats["a"].implicit_worked, 1);
+
+        let prior = 0.5;
+        assert!(stats["a"].confidence(prior) > prior);
+        assert!(stats["b"].confidence(prior) < prior);
+        assert_eq!(FeedbackStats::default().confidence(prior), prior);
+    }
+
+    #[test]
+    fn test_opened_since() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FeedbackStore::new(temp_dir.path());
+        let start = SystemTime::now() - Duration::from_secs(60);
+        let mut old = FeedbackEvent::new("a", FeedbackKind::Opened);
+        old.timestamp = start - Duration::from_secs(60);
+        store.append(&old).unwrap();
+        store
+            .append(&FeedbackEvent::new("b", FeedbackKind::Opened))
+            .unwrap();
+        store
+            .append(&FeedbackEvent::new("c", FeedbackKind::Opened))
+            .unwrap();
+
+        let ids = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(store.opened_since(&ids, start).unwrap(), vec!["b"]);
+    }
+}
@@ -0,0 +1,276 @@
+use anyhow::Result;
+use chrono::Utc;
+
+use crate::models::Note;
+
+/// Meta key listing the ids of the notes merged into a note
+pub const MERGED_FROM_META: &str = "merged_from";
+
+/// Part of a note body: the text before the first heading, or a heading and
+/// everything up to the next heading of any level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Heading text without the `#` markers; `None` for the preamble
+    pub heading: Option<String>,
+    /// The section as written, heading line included
+    pub text: String,
+}
+
+impl Section {
+    fn key(&self) -> Option<String> {
+        self.heading.as_ref().map(|heading| heading.to_lowercase())
+    }
+}
+
+/// Split a Markdown body at its ATX headings. Fenced code blocks are not
+/// split, so `# comments` in shell snippets stay put.
+pub fn sections(body: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current = Section {
+        heading: None,
+        text: String::new(),
+    };
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let heading = (!in_fence).then(|| heading_text(trimmed)).flatten();
+        if let Some(heading) = heading {
+            if current.heading.is_some() || !current.text.trim().is_empty() {
+                sections.push(current);
+            }
+            current = Section {
+                heading: Some(heading),
+                text: String::new(),
+            };
+        }
+        current.text.push_str(line);
+        current.text.push('\n');
+    }
+    if current.heading.is_some() || !current.text.trim().is_empty() {
+        sections.push(current);
+    }
+    sections
+}
+
+fn heading_text(line: &str) -> Option<String> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    let rest = &line[hashes..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some(rest.trim().trim_end_matches('#').trim().to_string())
+}
+
+/// A section of the kept note and the matching section of the absorbed one.
+/// Sections match on their heading, case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionPair {
+    pub heading: Option<String>,
+    pub kept: Option<Section>,
+    pub absorbed: Option<Section>,
+}
+
+impl SectionPair {
+    /// Both notes have the section, with different text
+    pub fn is_conflict(&self) -> bool {
+        match (&self.kept, &self.absorbed) {
+            (Some(kept), Some(absorbed)) => kept.text.trim() != absorbed.text.trim(),
+            _ => false,
+        }
+    }
+}
+
+/// Pair up the sections of two bodies: the kept note's sections in order,
+/// then the sections only the absorbed note has
+pub fn align_sections(kept: &str, absorbed: &str) -> Vec<SectionPair> {
+    let mut remaining = sections(absorbed);
+    let mut pairs: Vec<SectionPair> = sections(kept)
+        .into_iter()
+        .map(|section| {
+            let matching = remaining
+                .iter()
+                .position(|other| other.key() == section.key())
+                .map(|pos| remaining.remove(pos));
+            SectionPair {
+                heading: section.heading.clone(),
+                kept: Some(section),
+                absorbed: matching,
+            }
+        })
+        .collect();
+    pairs.extend(remaining.into_iter().map(|section| SectionPair {
+        heading: section.heading.clone(),
+        kept: None,
+        absorbed: Some(section),
+    }));
+    pairs
+}
+
+/// How to resolve a section both notes have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SectionChoice {
+    /// Keep the section of the note merged into
+    Kept,
+    /// Take the section of the absorbed note
+    Absorbed,
+    /// Keep both, the absorbed text after the kept one
+    Both,
+}
+
+/// Merge two bodies section by section; `choose` is asked for every section
+/// the notes disagree on
+pub fn merge_bodies(
+    kept: &str,
+    absorbed: &str,
+    mut choose: impl FnMut(&SectionPair) -> Result<SectionChoice>,
+) -> Result<String> {
+    let mut merged = String::new();
+    for pair in align_sections(kept, absorbed) {
+        let choice = if pair.is_conflict() {
+            choose(&pair)?
+        } else {
+            SectionChoice::Kept
+        };
+        let parts: Vec<&Section> = match choice {
+            SectionChoice::Kept => pair.kept.iter().chain(&pair.absorbed).take(1).collect(),
+            SectionChoice::Absorbed => pair.absorbed.iter().collect(),
+            SectionChoice::Both => pair.kept.iter().chain(&pair.absorbed).collect(),
+        };
+        for (idx, section) in parts.into_iter().enumerate() {
+            // The heading is already there when both are kept
+            let text = if idx > 0 && section.heading.is_some() {
+                section.text.split_once('\n').map_or("", |(_, rest)| rest)
+            } else {
+                &section.text
+            };
+            if !merged.is_empty() && !merged.ends_with("\n\n") {
+                merged.push('\n');
+            }
+            merged.push_str(text.trim_end());
+            merged.push('\n');
+        }
+    }
+    Ok(merged.trim_end().to_string())
+}
+
+/// The kept note with the absorbed one folded in: the given body, tags,
+/// links, solutions and contributors combined, and meta of the absorbed note
+/// added where the kept one has no value. `absorbed_id` is recorded in
+/// [`MERGED_FROM_META`].
+pub fn merge_into(kept: &Note, absorbed: &Note, absorbed_id: &str, body: String) -> Note {
+    let mut merged = kept.clone();
+    merged.body = body;
+    for tag in &absorbed.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    merged.tags.sort();
+    for link in &absorbed.links {
+        if !merged.links.contains(link) {
+            merged.links.push(link.clone());
+        }
+    }
+    for solution in &absorbed.solutions {
+        if !merged.solutions.iter().any(|s| s.steps == solution.steps) {
+            merged.solutions.push(solution.clone());
+        }
+    }
+    for (key, value) in &absorbed.meta {
+        if key != MERGED_FROM_META {
+            merged
+                .meta
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    let mut merged_from: Vec<String> = kept
+        .meta
+        .get(MERGED_FROM_META)
+        .into_iter()
+        .chain(absorbed.meta.get(MERGED_FROM_META))
+        .flat_map(|ids| ids.split(','))
+        .map(str::to_string)
+        .collect();
+    merged_from.push(absorbed_id.to_string());
+    merged
+        .meta
+        .insert(MERGED_FROM_META.to_string(), merged_from.join(","));
+
+    for contributor in &absorbed.contributors {
+        if !merged.contributors.contains(contributor) {
+            merged.contributors.push(contributor.clone());
+        }
+    }
+    merged
+        .contributors
+        .sort_by_key(|contributor| contributor.at);
+    merged.created_at = kept.created_at.min(absorbed.created_at);
+    merged.updated_at = Utc::now();
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+
+    #[test]
+    fn test_sections_ignore_headings_in_code() {
+        let body = "Intro\n## Fix\n```sh\n# not a heading\nexport X=1\n```\n## Cause\nDNS";
+        let sections = sections(body);
+        let headings: Vec<_> = sections.iter().map(|s| s.heading.as_deref()).collect();
+        assert_eq!(headings, vec![None, Some("Fix"), Some("Cause")]);
+        assert!(sections[1].text.contains("# not a heading"));
+    }
+
+    #[test]
+    fn test_merge_bodies_by_section() {
+        let kept = "Seen on macOS.\n\n## Fix\nRestart the proxy.\n\n## Cause\nStale credentials";
+        let absorbed = "Seen on macOS.\n\n## fix\nRotate the token.\n\n## Logs\nerror: 407";
+        let mut asked = Vec::new();
+        let body = merge_bodies(kept, absorbed, |pair| {
+            asked.push(pair.heading.clone());
+            Ok(SectionChoice::Both)
+        })
+        .unwrap();
+        assert_eq!(asked, vec![Some("Fix".to_string())]);
+        assert_eq!(
+            body,
+            "Seen on macOS.\n\n## Fix\nRestart the proxy.\n\nRotate the token.\n\n## Cause\nStale credentials\n\n## Logs\nerror: 407"
+        );
+
+        let body = merge_bodies(kept, absorbed, |_| Ok(SectionChoice::Absorbed)).unwrap();
+        assert!(body.contains("## fix\nRotate the token.") && !body.contains("Restart"));
+    }
+
+    #[test]
+    fn test_merge_into_unions_and_records_provenance() {
+        let kept = NoteBuilder::new("Proxy install fails", "Manual")
+            .tag("proxy")
+            .meta("os", "macos")
+            .build();
+        let absorbed = NoteBuilder::new("Proxy install fails", "Captured")
+            .tag("auto-captured")
+            .tag("proxy")
+            .meta("os", "linux")
+            .meta("exit_code", "1")
+            .meta(MERGED_FROM_META, "aaa")
+            .links(["https://example.com/runbook".to_string()])
+            .build();
+
+        let merged = merge_into(&kept, &absorbed, "bbb", "Manual".into());
+        assert_eq!(merged.tags, vec!["auto-captured", "proxy"]);
+        assert_eq!(merged.meta["os"], "macos");
+        assert_eq!(merged.meta["exit_code"], "1");
+        assert_eq!(merged.meta[MERGED_FROM_META], "aaa,bbb");
+        assert_eq!(merged.links.len(), 1);
+    }
+}
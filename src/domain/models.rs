@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, clap::ValueEnum, Default)]
@@ -37,9 +37,110 @@ pub struct Note {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub author: Author,
+    /// Who edited the note after `author` created it, one entry per revision.
+    /// Left out when empty so notes without edits keep their object id.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contributors: Vec<Contributor>,
+    /// Replicated tags and likes, so versions edited on different machines
+    /// merge on sync. Left out until the note is first edited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crdt: Option<crate::crdt::NoteCrdt>,
+    /// Ed25519 signature of the rest of the note by whoever stored this
+    /// revision, if they have a signing key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<NoteSignature>,
 }
 
+/// Signature over a note's canonical bytes with the signature left out
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NoteSignature {
+    /// Who the signer says they are, as `name <email>`
+    pub signer: String,
+    /// Hex Ed25519 public key
+    pub public_key: String,
+    /// Hex Ed25519 signature
+    pub signature: String,
+}
+
+/// The author of one revision of a note
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Contributor {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Meta key holding the RFC 3339 time after which a note is archived
+pub const EXPIRES_AT_META: &str = "expires_at";
+/// Meta key holding the attachment object with the full body of a note
+/// whose body was truncated
+pub const BODY_ATTACHMENT_META: &str = "body_attachment";
+/// Meta key holding the attachment object with the asciicast of a
+/// recording made with `fuku rec --cast`
+pub const CAST_ATTACHMENT_META: &str = "cast_attachment";
+/// Meta key holding the size in bytes of a truncated body before truncation
+pub const BODY_BYTES_META: &str = "body_bytes";
+/// Meta key set on notes made in a private zone, which are never synced
+pub const LOCAL_ONLY_META: &str = "local_only";
+
 impl Note {
+    /// When the note expires, if it was stored with `--expires` or captured
+    /// while `capture.expires_after` was set
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        let value = self.meta.get(EXPIRES_AT_META)?;
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+    }
+
+    pub fn set_expires_at(&mut self, at: DateTime<Utc>) {
+        self.meta.insert(
+            EXPIRES_AT_META.to_string(),
+            at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        );
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at().is_some_and(|at| at <= now)
+    }
+
+    /// Make the note private and keep it off remotes
+    pub fn keep_local(&mut self) {
+        self.privacy = Privacy::Private;
+        self.meta
+            .insert(LOCAL_ONLY_META.to_string(), "true".to_string());
+    }
+
+    /// Whether the note must never be pushed, served or synced
+    pub fn is_local_only(&self) -> bool {
+        self.meta
+            .get(LOCAL_ONLY_META)
+            .is_some_and(|value| value == "true")
+    }
+
+    /// Mark the note as edited by `editor`. The original author is kept and
+    /// the editor is added to the contributors.
+    pub fn record_edit(&mut self, editor: &Author, at: DateTime<Utc>) {
+        self.updated_at = at;
+        self.contributors.push(Contributor {
+            name: editor.name.clone(),
+            email: editor.email.clone(),
+            at,
+        });
+    }
+
+    /// Distinct people who worked on the note, the original author first
+    pub fn contributor_names(&self) -> Vec<&str> {
+        let mut names = vec![self.author.name.as_str()];
+        for contributor in &self.contributors {
+            if !names.contains(&contributor.name.as_str()) {
+                names.push(&contributor.name);
+            }
+        }
+        names
+    }
+
     pub fn canonical_bytes(&self) -> Result<Vec<u8>> {
         let envelope = NoteEnvelope {
             schema: "fuku.note".to_owned(),
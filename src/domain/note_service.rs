@@ -0,0 +1,542 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::dedupe;
+use crate::index::SearchSort;
+use crate::models::{
+    Author, Note, NoteRecord, Privacy, Solution, BODY_ATTACHMENT_META, BODY_BYTES_META,
+};
+use crate::plugins::PluginHook;
+use crate::redaction::Redactor;
+use crate::repo::FukuraRepo;
+
+/// Notes compared against a new one when looking for a duplicate
+const DEDUP_CANDIDATES: usize = 5;
+
+/// Words in commands that say which toolchain a note is about
+const TOOL_TAGS: &[(&[&str], &str)] = &[
+    (&["cargo", "rustc", "rustup"], "rust"),
+    (&["npm", "npx", "yarn", "pnpm", "node"], "javascript"),
+    (&["python", "python3", "pip", "pip3", "pytest"], "python"),
+    (&["docker", "podman"], "docker"),
+    (&["kubectl", "helm"], "kubernetes"),
+    (&["git"], "git"),
+];
+
+/// Share of a truncated body's budget kept from its start; the rest comes
+/// from its end, where errors usually are
+const HEAD_SHARE: f64 = 0.6;
+
+/// Cut `body` to about `max_bytes` by dropping whole lines from its middle,
+/// leaving a marker that names the attachment with the full text. `None`
+/// when it already fits.
+pub fn truncate_body(body: &str, max_bytes: usize, attachment: &str) -> Option<String> {
+    if body.len() <= max_bytes {
+        return None;
+    }
+    let head_budget = (max_bytes as f64 * HEAD_SHARE) as usize;
+    let mut head_end = floor_boundary(body, head_budget);
+    if let Some(newline) = body[..head_end].rfind('\n').filter(|&at| at > 0) {
+        head_end = newline;
+    }
+    let mut tail_start = body.len() - (max_bytes - head_budget);
+    while !body.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    if let Some(newline) = body[tail_start..].find('\n') {
+        if tail_start + newline + 1 < body.len() {
+            tail_start += newline + 1;
+        }
+    }
+    let (head, tail) = (&body[..head_end], &body[tail_start..]);
+    Some(format!(
+        "{}\n\n… {} bytes truncated; `fuku view --full` shows all of it (attachment {}) …\n\n{}",
+        head,
+        body.len() - head.len() - tail.len(),
+        attachment,
+        tail
+    ))
+}
+
+fn floor_boundary(text: &str, mut at: usize) -> usize {
+    at = at.min(text.len());
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+/// Tags lowercased with spaces as dashes, sorted and without duplicates
+pub fn normalize_tags(raw: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut tags = raw
+        .into_iter()
+        .map(|t| t.trim().to_lowercase().replace(' ', "-"))
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Assembles a new [`Note`]: private, authored by nobody in particular and
+/// created now unless told otherwise
+pub struct NoteBuilder {
+    note: Note,
+}
+
+impl NoteBuilder {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            note: Note {
+                title: title.into().trim().to_string(),
+                body: body.into().trim().to_string(),
+                tags: vec![],
+                links: vec![],
+                meta: BTreeMap::new(),
+                solutions: vec![],
+                privacy: Privacy::Private,
+                created_at: now,
+                updated_at: now,
+                author: Author::default(),
+                contributors: vec![],
+                crdt: None,
+                signature: None,
+            },
+        }
+    }
+
+    pub fn tags(mut self, tags: impl IntoIterator<Item = String>) -> Self {
+        self.note.tags.extend(tags);
+        self
+    }
+
+    pub fn tag(self, tag: &str) -> Self {
+        self.tags([tag.to_string()])
+    }
+
+    pub fn links(mut self, links: impl IntoIterator<Item = String>) -> Self {
+        self.note.links.extend(links);
+        self
+    }
+
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.note.meta.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn meta_map(mut self, meta: BTreeMap<String, String>) -> Self {
+        self.note.meta.extend(meta);
+        self
+    }
+
+    pub fn privacy(mut self, privacy: Privacy) -> Self {
+        self.note.privacy = privacy;
+        self
+    }
+
+    pub fn author(mut self, author: Author) -> Self {
+        self.note.author = author;
+        self
+    }
+
+    pub fn created_at(mut self, at: DateTime<Utc>) -> Self {
+        self.note.created_at = at;
+        self.note.updated_at = at;
+        self
+    }
+
+    /// When last changed, if not when created
+    pub fn updated_at(mut self, at: DateTime<Utc>) -> Self {
+        self.note.updated_at = at;
+        self
+    }
+
+    pub fn solutions(mut self, solutions: impl IntoIterator<Item = Solution>) -> Self {
+        self.note.solutions.extend(solutions);
+        self
+    }
+
+    pub fn expires_at(mut self, at: Option<DateTime<Utc>>) -> Self {
+        if let Some(at) = at {
+            self.note.set_expires_at(at);
+        }
+        self
+    }
+
+    pub fn build(self) -> Note {
+        let mut note = self.note;
+        note.tags = normalize_tags(note.tags);
+        note
+    }
+
+    /// The note as if stored under `object_id`, for tests that need records
+    /// but no repository
+    #[cfg(test)]
+    pub fn record(self, object_id: &str) -> NoteRecord {
+        NoteRecord {
+            object_id: object_id.to_string(),
+            note: self.build(),
+        }
+    }
+}
+
+/// Step run on every new note before it is stored
+pub trait NoteHook: Send + Sync {
+    fn apply(&self, note: &mut Note);
+}
+
+/// Masks secrets in the title, body and meta with the repository's rules
+pub struct RedactionHook(pub Redactor);
+
+impl NoteHook for RedactionHook {
+    fn apply(&self, note: &mut Note) {
+        note.title = self.0.redact(&note.title);
+        note.body = self.0.redact(&note.body);
+        for value in note.meta.values_mut() {
+            *value = self.0.redact(value);
+        }
+    }
+}
+
+/// Tags notes with the toolchain of the commands in them: the title and
+/// `$ command` lines of the body
+pub struct AutoTagHook;
+
+impl NoteHook for AutoTagHook {
+    fn apply(&self, note: &mut Note) {
+        let commands = std::iter::once(note.title.as_str())
+            .chain(
+                note.body
+                    .lines()
+                    .filter_map(|line| line.trim_start().strip_prefix("$ ")),
+            )
+            .chain(note.meta.get("error_command").map(String::as_str));
+        let mut tags = note.tags.clone();
+        for text in commands {
+            let lower = text.to_lowercase();
+            let words: Vec<&str> = lower
+                .split(|c: char| !c.is_alphanumeric() && c != '-')
+                .collect();
+            for (tools, tag) in TOOL_TAGS {
+                if tools.iter().any(|tool| words.contains(tool)) {
+                    tags.push(tag.to_string());
+                }
+            }
+        }
+        note.tags = normalize_tags(tags);
+    }
+}
+
+/// Who is making changes here: `[author]` in the global config, else the
+/// git author or login name
+pub fn default_author() -> Author {
+    // Per-user, so the global config and its profiles decide
+    let configured = crate::config::FukuraConfig::load_global_resolved()
+        .ok()
+        .and_then(|config| config.author)
+        .unwrap_or_default();
+    let name = configured
+        .name
+        .or_else(|| std::env::var("GIT_AUTHOR_NAME").ok())
+        .unwrap_or_else(|| {
+            std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string())
+        });
+    let email = configured
+        .email
+        .or_else(|| std::env::var("GIT_AUTHOR_EMAIL").ok())
+        .or_else(|| std::env::var("EMAIL").ok());
+    Author { name, email }
+}
+
+/// A note created through [`NoteService::create`]
+#[derive(Debug, Clone)]
+pub struct Created {
+    pub record: NoteRecord,
+    /// Existing note the new one was folded into as a duplicate; `record`
+    /// replaces it
+    pub merged_into: Option<String>,
+}
+
+/// A note past [`NoteService::admit`]
+enum Admitted {
+    /// Still to be stored
+    New(Note),
+    /// Folded into an existing note, which is already replaced
+    Merged(Created),
+}
+
+/// The one way new notes get into a repository, shared by `fuku add`,
+/// `fuku import`, the daemon and `fuku serve`, so they are redacted, tagged
+/// and deduplicated the same way
+pub struct NoteService<'a> {
+    repo: &'a FukuraRepo,
+    hooks: Vec<Box<dyn NoteHook>>,
+    dedup_threshold: Option<f64>,
+    /// `[notes] max_body_bytes`
+    max_body_bytes: usize,
+}
+
+impl<'a> NoteService<'a> {
+    /// Service that redacts with the repository's rules, then runs its
+    /// `[[wasm_plugins]]`
+    pub fn new(repo: &'a FukuraRepo) -> Result<Self> {
+        let config = repo.config()?;
+        let redactor = Redactor::default_with_overrides(&config.redaction_overrides);
+        let mut hooks: Vec<Box<dyn NoteHook>> = vec![Box::new(RedactionHook(redactor))];
+        for processor in crate::wasm_plugins::load_all(repo.root(), &config.wasm_plugins) {
+            hooks.push(Box::new(processor));
+        }
+        Ok(Self {
+            repo,
+            hooks,
+            dedup_threshold: None,
+            max_body_bytes: config.notes.max_body_bytes,
+        })
+    }
+
+    /// Also tag notes by the tools in their commands, for captured notes
+    pub fn with_auto_tags(self) -> Self {
+        self.with_hook(AutoTagHook)
+    }
+
+    /// Run `hook` after the ones already added
+    pub fn with_hook(mut self, hook: impl NoteHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Fold a new note into an existing one at least this similar (see
+    /// [`dedupe::similarity`]) instead of storing it separately
+    pub fn with_dedup(mut self, threshold: f64) -> Self {
+        self.dedup_threshold = Some(threshold);
+        self
+    }
+
+    /// Run the hooks over `note`
+    pub fn prepare(&self, mut note: Note) -> Note {
+        for hook in &self.hooks {
+            hook.apply(&mut note);
+        }
+        note
+    }
+
+    /// Store a new note. The `pre-add` plugin sees it after the hooks and
+    /// may change or reject it; `post-add` hears about the stored result.
+    pub fn create(&self, note: Note) -> Result<Created> {
+        let created = match self.admit(note)? {
+            Admitted::New(note) => Created {
+                record: self.repo.store_note(note)?,
+                merged_into: None,
+            },
+            Admitted::Merged(created) => created,
+        };
+        self.repo
+            .plugins()
+            .notify(PluginHook::PostAdd, &created.record);
+        Ok(created)
+    }
+
+    /// Store new notes like [`create`](Self::create), indexing the ones that
+    /// aren't duplicates together, for imports. A note the `pre-add` plugin
+    /// rejects gets its error in its place; failing to store the rest fails
+    /// them all. Duplicates are only looked for among notes stored before.
+    pub fn create_batch(&self, notes: Vec<Note>) -> Result<Vec<Result<Created>>> {
+        let mut admitted = Vec::with_capacity(notes.len());
+        let mut new_notes = Vec::new();
+        for note in notes {
+            match self.admit(note) {
+                Ok(Admitted::New(note)) => {
+                    new_notes.push(note);
+                    admitted.push(None);
+                }
+                Ok(Admitted::Merged(created)) => admitted.push(Some(Ok(created))),
+                Err(e) => admitted.push(Some(Err(e))),
+            }
+        }
+        let mut stored = self.repo.store_notes_batch(new_notes)?.into_iter();
+        let plugins = self.repo.plugins();
+        Ok(admitted
+            .into_iter()
+            .map(|result| {
+                let result = result.unwrap_or_else(|| {
+                    Ok(Created {
+                        record: stored.next().expect("a record per stored note"),
+                        merged_into: None,
+                    })
+                });
+                if let Ok(created) = &result {
+                    plugins.notify(PluginHook::PostAdd, &created.record);
+                }
+                result
+            })
+            .collect())
+    }
+
+    /// Take `note` through the hooks, the `pre-add` plugin and the body
+    /// limit, folding it into a duplicate when there is one
+    fn admit(&self, note: Note) -> Result<Admitted> {
+        let mut note = self
+            .repo
+            .plugins()
+            .filter(PluginHook::PreAdd, self.prepare(note), None)?;
+        self.limit_body(&mut note)?;
+        let duplicate = match self.dedup_threshold {
+            Some(threshold) => self.find_duplicate(&note, threshold)?,
+            None => None,
+        };
+        let Some(existing) = duplicate else {
+            return Ok(Admitted::New(note));
+        };
+        let merged = dedupe::merge_notes(&existing.note, &[&note]);
+        Ok(Admitted::Merged(Created {
+            record: self.repo.replace_note(&existing.object_id, merged)?,
+            merged_into: Some(existing.object_id),
+        }))
+    }
+
+    /// Truncate a body over `max_bytes`, keeping the redacted original as an
+    /// attachment
+    fn limit_body(&self, note: &mut Note) -> Result<()> {
+        if self.max_body_bytes == 0 || note.body.len() <= self.max_body_bytes {
+            return Ok(());
+        }
+        let attachment = self.repo.store_attachment(note.body.as_bytes())?;
+        if let Some(body) = truncate_body(&note.body, self.max_body_bytes, &attachment[..8]) {
+            note.meta
+                .insert(BODY_BYTES_META.to_string(), note.body.len().to_string());
+            note.meta
+                .insert(BODY_ATTACHMENT_META.to_string(), attachment);
+            note.body = body;
+        }
+        Ok(())
+    }
+
+    fn find_duplicate(&self, note: &Note, threshold: f64) -> Result<Option<NoteRecord>> {
+        // Plain words only; titles like `Error: cargo build` would otherwise
+        // parse as field queries
+        let query = note
+            .title
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if query.is_empty() {
+            return Ok(None);
+        }
+        let hits = self
+            .repo
+            .search(&query, DEDUP_CANDIDATES, SearchSort::Relevance)?;
+        Ok(hits
+            .iter()
+            .filter_map(|hit| self.repo.load_note(&hit.object_id).ok())
+            .find(|candidate| dedupe::similarity(&candidate.note, note) >= threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedupe::DEFAULT_THRESHOLD;
+    use tempfile::TempDir;
+
+    fn captured(dir: &str) -> Note {
+        NoteBuilder::new(
+            "Error: cargo build",
+            format!(
+                "```bash\n$ cargo build\nerror[E0425]: cannot find value `x`\n```\n\n**Working Directory**: `{}`",
+                dir
+            ),
+        )
+        .tags(["error".to_string(), "Auto Captured".to_string()])
+        .build()
+    }
+
+    #[test]
+    fn test_builder_normalizes_tags() {
+        let note = captured("/tmp/a");
+        assert_eq!(note.tags, vec!["auto-captured", "error"]);
+        assert_eq!(note.privacy, Privacy::Private);
+    }
+
+    #[test]
+    fn test_hooks_redact_and_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let service = NoteService::new(&repo).unwrap().with_auto_tags();
+
+        let mut note = captured("/tmp/a");
+        note.title = "Error: mail ops@example.com".to_string();
+        let note = service.prepare(note);
+        assert!(!note.title.contains("ops@example.com"));
+        assert!(note.tags.contains(&"rust".to_string()));
+        assert!(!note.tags.contains(&"git".to_string()));
+    }
+
+    #[test]
+    fn test_truncate_keeps_head_and_tail_lines() {
+        let body = (1..=100)
+            .map(|n| format!("line {:03}", n))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(truncate_body(&body, body.len(), "abc"), None);
+
+        let cut = truncate_body(&body, 200, "abc").unwrap();
+        assert!(cut.starts_with("line 001\n"));
+        assert!(cut.ends_with("line 100"));
+        assert!(cut.contains("(attachment abc)"));
+        assert!(!cut.contains("line 050"));
+        assert!(cut
+            .lines()
+            .all(|line| !line.starts_with("line") || line.len() == 8));
+
+        // No line breaks to cut at: still on a char boundary
+        let wide = "é".repeat(100);
+        assert!(truncate_body(&wide, 51, "abc").is_some());
+    }
+
+    #[test]
+    fn test_long_bodies_keep_full_text_as_attachment() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let mut config = repo.local_config().unwrap();
+        config.notes.max_body_bytes = 1024;
+        config.save(&repo.config_path()).unwrap();
+        let service = NoteService::new(&repo).unwrap();
+
+        let stderr = "error: linker failed\n".repeat(500);
+        let created = service
+            .create(NoteBuilder::new("Link failure", stderr.clone()).build())
+            .unwrap();
+        let note = &created.record.note;
+        assert!(note.body.len() < 1200);
+        assert_eq!(note.meta[BODY_BYTES_META], stderr.trim().len().to_string());
+        let full = repo
+            .load_attachment(&note.meta[BODY_ATTACHMENT_META])
+            .unwrap();
+        assert_eq!(String::from_utf8(full).unwrap(), stderr.trim());
+    }
+
+    #[test]
+    fn test_dedup_folds_repeats_into_one_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let service = NoteService::new(&repo)
+            .unwrap()
+            .with_dedup(DEFAULT_THRESHOLD);
+
+        let first = service.create(captured("/home/a/app")).unwrap();
+        assert!(first.merged_into.is_none());
+        let second = service.create(captured("/home/b/app")).unwrap();
+        assert_eq!(
+            second.merged_into.as_deref(),
+            Some(first.record.object_id.as_str())
+        );
+        assert_eq!(second.record.note.meta["occurrences"], "2");
+        assert_eq!(repo.list_all_notes().unwrap().len(), 1);
+    }
+}
@@ -5,10 +5,9 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context, Result};
 use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 
-use crate::repo::FukuraRepo;
-
 #[derive(Debug, Serialize, Deserialize)]
 struct PackIndex {
     pack_file: String,
@@ -29,16 +28,41 @@ pub struct PackReport {
     pub index_file: PathBuf,
     pub object_count: usize,
     pub pruned: usize,
+    /// Disk space freed by pruning, less the space the new pack takes
+    pub reclaimed_bytes: u64,
+    /// Stopped by Ctrl-C; the objects not packed yet stay loose
+    pub interrupted: bool,
+}
+
+/// Loose objects waiting to be packed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LooseUsage {
+    pub objects: usize,
+    /// Disk space they take
+    pub bytes: u64,
+}
+
+/// Count the loose objects under `objects_dir` and the space they take
+pub fn loose_usage(objects_dir: &Path) -> Result<LooseUsage> {
+    let mut usage = LooseUsage::default();
+    for (_object_id, path) in collect_loose_objects(objects_dir)? {
+        usage.objects += 1;
+        usage.bytes += disk_usage(&fs::metadata(&path)?);
+    }
+    Ok(usage)
 }
 
-pub fn pack_objects(repo: &FukuraRepo, prune: bool) -> Result<PackReport> {
-    let objects = collect_loose_objects(repo)?;
+/// Pack the loose objects under `objects_dir` into a new pack in `pack_dir`,
+/// removing them afterwards when `prune` is set. On Ctrl-C the pack is
+/// closed after the current object and only what it holds is pruned.
+pub fn pack_objects(objects_dir: &Path, pack_dir: &Path, prune: bool) -> Result<PackReport> {
+    let objects = collect_loose_objects(objects_dir)?;
     if objects.is_empty() {
         bail!("No loose objects to pack");
     }
     let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ");
     let pack_name = format!("pack-{}.fop", timestamp);
-    let pack_path = repo.pack_dir().join(&pack_name);
+    let pack_path = pack_dir.join(&pack_name);
     let mut pack_file = File::create(&pack_path).with_context(|| "Failed to create pack file")?;
     let mut index = PackIndex {
         pack_file: pack_name.clone(),
@@ -50,10 +74,24 @@ pub fn pack_objects(repo: &FukuraRepo, prune: bool) -> Result<PackReport> {
     pack_file.write_all(&1u32.to_le_bytes())?;
     pack_file.write_all(&(objects.len() as u32).to_le_bytes())?;
 
+    let progress = if objects.len() < 1000 {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(objects.len() as u64)
+    };
+    progress.set_style(
+        ProgressStyle::with_template("  Packing {bar:40.cyan/blue} {pos}/{len}")
+            .expect("valid progress template")
+            .progress_chars(crate::output::progress_chars()),
+    );
+
     // Pre-allocate buffer for better performance
     let mut buffer = Vec::with_capacity(1024 * 1024); // 1MB buffer
 
     for (object_id, path) in &objects {
+        if crate::interrupt::requested() {
+            break;
+        }
         buffer.clear();
         File::open(path)
             .with_context(|| format!("Failed to open {}", path.display()))?
@@ -71,41 +109,55 @@ pub fn pack_objects(repo: &FukuraRepo, prune: bool) -> Result<PackReport> {
             offset: offset + 64 + 4,
             length: length as u32,
         });
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    let packed_count = index.objects.len();
+    if packed_count < objects.len() {
+        pack_file.seek(SeekFrom::Start(8))?;
+        pack_file.write_all(&(packed_count as u32).to_le_bytes())?;
     }
     pack_file.flush()?;
     pack_file.sync_all()?;
 
-    let index_path = repo.pack_dir().join(format!("pack-{}.fop.idx", timestamp));
+    let index_path = pack_dir.join(format!("pack-{}.fop.idx", timestamp));
     let index_json = serde_json::to_string_pretty(&index)?;
-    fs::write(&index_path, index_json)?;
+    // Readers load every index in the directory; never let them see a partial one
+    let index_tmp = index_path.with_extension("idx.tmp");
+    fs::write(&index_tmp, index_json)?;
+    fs::rename(&index_tmp, &index_path)?;
 
     let mut pruned = 0usize;
+    let mut freed = 0u64;
     if prune {
-        for (_object_id, path) in &objects {
-            if path.exists() {
+        for (_object_id, path) in &objects[..packed_count] {
+            if let Ok(metadata) = fs::metadata(path) {
                 fs::remove_file(path)?;
+                freed += disk_usage(&metadata);
                 pruned += 1;
             }
             if let Some(parent) = path.parent() {
-                cleanup_empty_dirs(parent, repo.objects_dir())?;
+                cleanup_empty_dirs(parent, objects_dir)?;
             }
         }
     }
 
+    let packed = disk_usage(&fs::metadata(&pack_path)?) + disk_usage(&fs::metadata(&index_path)?);
     Ok(PackReport {
         pack_file: pack_path,
         index_file: index_path,
-        object_count: objects.len(),
+        object_count: packed_count,
         pruned,
+        reclaimed_bytes: freed.saturating_sub(packed),
+        interrupted: packed_count < objects.len(),
     })
 }
 
-pub fn load_object_from_pack(repo: &FukuraRepo, object_id: &str) -> Result<Option<Vec<u8>>> {
-    let pack_dir = repo.pack_dir();
+pub fn load_object_from_pack(pack_dir: &Path, object_id: &str) -> Result<Option<Vec<u8>>> {
     if !pack_dir.exists() {
         return Ok(None);
     }
-    for entry in fs::read_dir(&pack_dir)? {
+    for entry in fs::read_dir(pack_dir)? {
         let entry = entry?;
         if entry.path().extension().and_then(|s| s.to_str()) != Some("idx") {
             continue;
@@ -132,12 +184,12 @@ pub fn load_object_from_pack(repo: &FukuraRepo, object_id: &str) -> Result<Optio
     Ok(None)
 }
 
-fn collect_loose_objects(repo: &FukuraRepo) -> Result<Vec<(String, PathBuf)>> {
+fn collect_loose_objects(objects_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
     let mut objects = Vec::new();
-    if !repo.objects_dir().exists() {
+    if !objects_dir.exists() {
         return Ok(objects);
     }
-    for prefix_entry in fs::read_dir(repo.objects_dir())? {
+    for prefix_entry in fs::read_dir(objects_dir)? {
         let prefix_entry = prefix_entry?;
         if !prefix_entry.file_type()?.is_dir() {
             continue;
@@ -158,7 +210,21 @@ fn collect_loose_objects(repo: &FukuraRepo) -> Result<Vec<(String, PathBuf)>> {
     Ok(objects)
 }
 
-fn cleanup_empty_dirs(path: &Path, root: PathBuf) -> Result<()> {
+/// Space a file takes on disk: whole blocks where the platform reports them,
+/// since a small loose object still occupies a full block
+fn disk_usage(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+fn cleanup_empty_dirs(path: &Path, root: &Path) -> Result<()> {
     if path == root {
         return Ok(());
     }
@@ -172,20 +238,20 @@ fn cleanup_empty_dirs(path: &Path, root: PathBuf) -> Result<()> {
 }
 
 pub(crate) fn load_pack_indices(
-    repo: &FukuraRepo,
+    pack_dir: &Path,
 ) -> Result<BTreeMap<String, (PathBuf, PackIndexEntry)>> {
     let mut map = BTreeMap::new();
-    if !repo.pack_dir().exists() {
+    if !pack_dir.exists() {
         return Ok(map);
     }
-    for entry in fs::read_dir(repo.pack_dir())? {
+    for entry in fs::read_dir(pack_dir)? {
         let entry = entry?;
         if entry.path().extension().and_then(|s| s.to_str()) != Some("idx") {
             continue;
         }
         let index: PackIndex = serde_json::from_str(&fs::read_to_string(entry.path())?)
             .with_context(|| format!("Failed to parse {}", entry.path().display()))?;
-        let pack_file = repo.pack_dir().join(index.pack_file);
+        let pack_file = pack_dir.join(index.pack_file);
         for object in index.objects {
             map.insert(object.id.clone(), (pack_file.clone(), object));
         }
@@ -0,0 +1,350 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::redaction::Redactor;
+
+/// Occurrence timestamps kept per pattern for trend statistics
+pub const MAX_TRACKED_OCCURRENCES: usize = 500;
+
+/// A recurring error, keyed by the fingerprint of its normalized message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorPattern {
+    pub normalized_message: String,
+    pub fingerprint: String,
+    pub occurrences: u32,
+    pub last_seen: SystemTime,
+    #[serde(default)]
+    pub solutions: Vec<String>,
+    #[serde(default = "SystemTime::now")]
+    pub first_seen: SystemTime,
+    /// Most recent command that produced this error
+    #[serde(default)]
+    pub last_command: Option<String>,
+    /// Recent occurrence times, oldest first
+    #[serde(default)]
+    pub seen_at: Vec<SystemTime>,
+    /// Error notes captured for this pattern
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Notes created when this error was resolved
+    #[serde(default)]
+    pub resolved_by: Vec<String>,
+}
+
+/// Occurrences in the current window compared to the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trend {
+    pub current: usize,
+    pub previous: usize,
+}
+
+impl Trend {
+    pub fn arrow(&self) -> &'static str {
+        match self.current.cmp(&self.previous) {
+            std::cmp::Ordering::Greater => "↑",
+            std::cmp::Ordering::Less => "↓",
+            std::cmp::Ordering::Equal => "→",
+        }
+    }
+}
+
+impl ErrorPattern {
+    /// A pattern with no occurrences yet; call [`ErrorPattern::record`] to count one
+    pub fn new(normalized_message: String, fingerprint: String, at: SystemTime) -> Self {
+        Self {
+            normalized_message,
+            fingerprint,
+            occurrences: 0,
+            last_seen: at,
+            solutions: Vec::new(),
+            first_seen: at,
+            last_command: None,
+            seen_at: Vec::new(),
+            notes: Vec::new(),
+            resolved_by: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, at: SystemTime, command: Option<&str>, note_id: Option<&str>) {
+        if self.occurrences == 0 {
+            self.first_seen = at;
+            self.last_seen = at;
+        } else {
+            self.first_seen = self.first_seen.min(at);
+            self.last_seen = self.last_seen.max(at);
+        }
+        self.occurrences += 1;
+        self.seen_at.push(at);
+        if self.seen_at.len() > MAX_TRACKED_OCCURRENCES {
+            let excess = self.seen_at.len() - MAX_TRACKED_OCCURRENCES;
+            self.seen_at.drain(..excess);
+        }
+        if let Some(command) = command {
+            self.last_command = Some(command.to_string());
+        }
+        if let Some(note_id) = note_id {
+            push_unique(&mut self.notes, note_id);
+        }
+    }
+
+    pub fn resolve(&mut self, note_id: &str) {
+        push_unique(&mut self.resolved_by, note_id);
+    }
+
+    /// Occurrences in `[start, end)`
+    pub fn count_between(&self, start: SystemTime, end: SystemTime) -> usize {
+        self.seen_at
+            .iter()
+            .filter(|at| **at >= start && **at < end)
+            .count()
+    }
+
+    /// Occurrences in the last `window` versus the `window` before that
+    pub fn trend(&self, now: SystemTime, window: Duration) -> Trend {
+        let start = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+        let before = start.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+        Trend {
+            current: self.count_between(start, now + Duration::from_secs(1)),
+            previous: self.count_between(before, start),
+        }
+    }
+}
+
+fn push_unique(ids: &mut Vec<String>, id: &str) {
+    if !ids.iter().any(|existing| existing == id) {
+        ids.push(id.to_string());
+    }
+}
+
+/// Normalize an error message so recurrences share a fingerprint
+pub fn normalize_message(message: &str) -> String {
+    static RUST_PATH: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"/[^\s:]+\.rs").expect("valid path pattern"));
+
+    // Line numbers are kept: the same message at another location is a different error
+    RUST_PATH.replace_all(message, "/path/to/file").to_string()
+}
+
+pub fn fingerprint(normalized: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(normalized.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// The message a failed command is grouped by: the first stderr line that
+/// mentions an error, else the first stderr line, else the command itself
+pub fn failure_message(command: &str, exit_code: i32, stderr: &str) -> String {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines
+        .iter()
+        .find(|line| line.to_lowercase().contains("error"))
+        .or_else(|| lines.first())
+        .map(|line| line.to_string())
+        .unwrap_or_else(|| {
+            let command = command.lines().next().unwrap_or_default().trim();
+            format!("Command '{}' failed with exit code {}", command, exit_code)
+        })
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PatternFile {
+    version: u32,
+    #[serde(default)]
+    patterns: BTreeMap<String, ErrorPattern>,
+}
+
+/// Error patterns persisted in `.fukura/patterns.json`
+pub struct PatternStore {
+    path: PathBuf,
+    redactor: Option<Redactor>,
+}
+
+impl PatternStore {
+    pub fn new(repo_path: &Path) -> Self {
+        Self {
+            path: repo_path.join(".fukura").join("patterns.json"),
+            redactor: None,
+        }
+    }
+
+    /// Redact messages and commands before they hit the disk
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<HashMap<String, ErrorPattern>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let file: PatternFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
+        Ok(file.patterns.into_iter().collect())
+    }
+
+    /// Apply `f` to the stored patterns under an exclusive lock, so the daemon
+    /// and CLI can update the file concurrently. Returns the updated map.
+    pub fn update<F>(&self, f: F) -> Result<HashMap<String, ErrorPattern>>
+    where
+        F: FnOnce(&mut HashMap<String, ErrorPattern>),
+    {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("lock"))?;
+        lock.lock_exclusive()?;
+        let result = self.load().and_then(|mut patterns| {
+            f(&mut patterns);
+            self.write(&patterns)?;
+            Ok(patterns)
+        });
+        let _ = FileExt::unlock(&lock);
+        result
+    }
+
+    /// Normalized message and fingerprint a failure is stored under
+    pub fn pattern_key(&self, message: &str) -> (String, String) {
+        let normalized = normalize_message(&self.redact(message));
+        let key = fingerprint(&normalized);
+        (normalized, key)
+    }
+
+    fn redact(&self, text: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Count one occurrence of a failure and return its fingerprint
+    pub fn record_failure(
+        &self,
+        message: &str,
+        command: Option<&str>,
+        note_id: Option<&str>,
+        at: SystemTime,
+    ) -> Result<(String, HashMap<String, ErrorPattern>)> {
+        let (normalized, key) = self.pattern_key(message);
+        let command = command.map(|command| self.redact(command));
+        let patterns = self.update(|patterns| {
+            patterns
+                .entry(key.clone())
+                .or_insert_with(|| ErrorPattern::new(normalized, key.clone(), at))
+                .record(at, command.as_deref(), note_id);
+        })?;
+        Ok((key, patterns))
+    }
+
+    /// Link a resolution note to a pattern
+    pub fn record_resolution(
+        &self,
+        fingerprint: &str,
+        note_id: &str,
+    ) -> Result<HashMap<String, ErrorPattern>> {
+        self.update(|patterns| {
+            if let Some(pattern) = patterns.get_mut(fingerprint) {
+                pattern.resolve(note_id);
+            }
+        })
+    }
+
+    fn write(&self, patterns: &HashMap<String, ErrorPattern>) -> Result<()> {
+        let file = PatternFile {
+            version: 1,
+            patterns: patterns
+                .iter()
+                .map(|(key, pattern)| (key.clone(), pattern.clone()))
+                .collect(),
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DAY: Duration = Duration::from_secs(86_400);
+
+    #[test]
+    fn test_failure_message_prefers_error_lines() {
+        let stderr = "   Compiling app v0.1.0\nerror[E0425]: cannot find value `x`\n";
+        assert_eq!(
+            failure_message("cargo build", 101, stderr),
+            "error[E0425]: cannot find value `x`"
+        );
+        assert_eq!(
+            failure_message("make\n  all", 2, ""),
+            "Command 'make' failed with exit code 2"
+        );
+    }
+
+    #[test]
+    fn test_patterns_survive_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PatternStore::new(temp_dir.path());
+        let now = SystemTime::now();
+
+        let message = "error: /home/me/app/src/main.rs:4:5 mismatched types";
+        let (key, _) = store
+            .record_failure(message, Some("cargo build"), Some("note1"), now)
+            .unwrap();
+        store
+            .record_failure(message, Some("cargo test"), Some("note2"), now)
+            .unwrap();
+        store.record_resolution(&key, "fix1").unwrap();
+
+        let reloaded = PatternStore::new(temp_dir.path()).load().unwrap();
+        let pattern = &reloaded[&key];
+        assert_eq!(pattern.occurrences, 2);
+        assert!(pattern.normalized_message.contains("/path/to/file"));
+        assert_eq!(pattern.last_command.as_deref(), Some("cargo test"));
+        assert_eq!(pattern.notes, vec!["note1", "note2"]);
+        assert_eq!(pattern.resolved_by, vec!["fix1"]);
+    }
+
+    #[test]
+    fn test_trend() {
+        let now = SystemTime::UNIX_EPOCH + DAY * 30;
+        let mut pattern = ErrorPattern::new("e".to_string(), "f".to_string(), now);
+        for days_ago in [1, 2, 3, 9] {
+            pattern.record(now - DAY * days_ago, None, None);
+        }
+        let trend = pattern.trend(now, DAY * 7);
+        assert_eq!(
+            trend,
+            Trend {
+                current: 3,
+                previous: 1
+            }
+        );
+        assert_eq!(trend.arrow(), "↑");
+        assert_eq!(pattern.first_seen, now - DAY * 9);
+        assert_eq!(pattern.last_seen, now - DAY);
+    }
+}
@@ -0,0 +1,785 @@
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::activity_storage::CommandRecord;
+
+/// Name of the recording started without `--name`
+pub const DEFAULT_RECORDING: &str = "default";
+
+/// A `fuku rec` recording in progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveRecording {
+    pub name: String,
+    pub session_id: String,
+    pub title: String,
+    /// Start of the recording; backfilled recordings (`fuku rec "x" 2h`)
+    /// start before they were created
+    pub since: Option<SystemTime>,
+    /// Where a named recording was started. Only commands run in this
+    /// directory or below it belong to the recording.
+    pub directory: Option<PathBuf>,
+}
+
+impl ActiveRecording {
+    pub fn new(name: &str, title: &str, since: SystemTime, directory: Option<PathBuf>) -> Self {
+        let secs = unix_secs(since);
+        let session_id = if name == DEFAULT_RECORDING {
+            format!("rec_{}", secs)
+        } else {
+            format!("rec_{}_{}", name, secs)
+        };
+        Self {
+            name: name.to_string(),
+            session_id,
+            title: title.to_string(),
+            since: Some(since),
+            directory,
+        }
+    }
+
+    /// Read a state file: `session_id|title` followed by optional
+    /// `|since:<unix secs>` and `|dir:<path>` fields
+    pub fn parse(name: &str, content: &str) -> Option<Self> {
+        let mut parts = content.trim_end_matches('\n').split('|');
+        let session_id = parts.next()?.trim().to_string();
+        let title = parts.next()?.to_string();
+        if session_id.is_empty() {
+            return None;
+        }
+        let mut recording = Self {
+            name: name.to_string(),
+            session_id,
+            title,
+            since: None,
+            directory: None,
+        };
+        for field in parts {
+            if let Some(secs) = field.trim().strip_prefix("since:") {
+                recording.since = secs
+                    .parse::<u64>()
+                    .ok()
+                    .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            } else if let Some(dir) = field.strip_prefix("dir:") {
+                recording.directory = Some(PathBuf::from(dir.trim_end()));
+            }
+        }
+        Some(recording)
+    }
+
+    /// The state file contents, read back by [`ActiveRecording::parse`].
+    /// The session id comes first so shell hooks can `cut -d'|' -f1` it.
+    pub fn to_state(&self) -> String {
+        let mut state = format!("{}|{}", self.session_id, self.title.replace('|', "/"));
+        if let Some(since) = self.since {
+            state.push_str(&format!("|since:{}", unix_secs(since)));
+        }
+        if let Some(dir) = &self.directory {
+            state.push_str(&format!("|dir:{}", dir.display()));
+        }
+        state
+    }
+
+    /// When the recording started, falling back to the timestamp in
+    /// `rec_<secs>` session ids written before `since:` was stored
+    pub fn start_time(&self) -> Option<SystemTime> {
+        self.since.or_else(|| {
+            let secs = self.session_id.rsplit('_').next()?.parse::<u64>().ok()?;
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        })
+    }
+
+    /// Whether a captured command was run inside this recording's directory
+    pub fn includes(&self, record: &CommandRecord) -> bool {
+        self.directory.as_ref().map_or(true, |dir| {
+            Path::new(&record.working_directory).starts_with(dir)
+        })
+    }
+}
+
+/// State files of the recordings in progress: `.fukura/recording` for the
+/// default one and `.fukura/recordings/<name>` for named ones
+#[derive(Debug, Clone)]
+pub struct RecordingStore {
+    root: PathBuf,
+}
+
+impl RecordingStore {
+    pub fn new(repo_path: &Path) -> Self {
+        Self {
+            root: repo_path.join(".fukura"),
+        }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_RECORDING {
+            self.root.join("recording")
+        } else {
+            self.root.join("recordings").join(name)
+        }
+    }
+
+    pub fn load(&self, name: &str) -> Result<Option<ActiveRecording>> {
+        validate_recording_name(name)?;
+        match fs::read_to_string(self.path(name)) {
+            Ok(content) => Ok(ActiveRecording::parse(name, &content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// All recordings in progress, the default one first
+    pub fn list(&self) -> Result<Vec<ActiveRecording>> {
+        let mut recordings: Vec<ActiveRecording> =
+            self.load(DEFAULT_RECORDING)?.into_iter().collect();
+        let mut names = Vec::new();
+        match fs::read_dir(self.root.join("recordings")) {
+            Ok(entries) => {
+                for entry in entries {
+                    let name = entry?.file_name().to_string_lossy().to_string();
+                    if validate_recording_name(&name).is_ok() && name != DEFAULT_RECORDING {
+                        names.push(name);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        names.sort();
+        for name in names {
+            recordings.extend(self.load(&name)?);
+        }
+        Ok(recordings)
+    }
+
+    pub fn save(&self, recording: &ActiveRecording) -> Result<()> {
+        validate_recording_name(&recording.name)?;
+        let path = self.path(&recording.name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, recording.to_state())?;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.path(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Where files attached to a recording in progress are kept until
+    /// `fuku done` folds them into its note
+    pub fn attachments_dir(&self, recording: &ActiveRecording) -> PathBuf {
+        self.root.join("attachments").join(&recording.session_id)
+    }
+
+    /// Attach a file, e.g. a terminal scrollback from `fuku capture`
+    pub fn attach(
+        &self,
+        recording: &ActiveRecording,
+        file_name: &str,
+        content: &str,
+    ) -> Result<PathBuf> {
+        let dir = self.attachments_dir(recording);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(file_name);
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// Attached files, oldest name first
+    pub fn attachments(&self, recording: &ActiveRecording) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        match fs::read_dir(self.attachments_dir(recording)) {
+            Ok(entries) => {
+                for entry in entries {
+                    paths.push(entry?.path());
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    pub fn discard_attachments(&self, recording: &ActiveRecording) -> Result<()> {
+        match fs::remove_dir_all(self.attachments_dir(recording)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The recording `fuku done` should finish: the one named, else the only
+    /// one in progress, else the named recording whose directory holds `cwd`
+    /// (the innermost), else the default one
+    pub fn find(&self, name: Option<&str>, cwd: &Path) -> Result<Option<ActiveRecording>> {
+        if let Some(name) = name {
+            return self.load(name);
+        }
+        let mut recordings = self.list()?;
+        if recordings.len() <= 1 {
+            return Ok(recordings.pop());
+        }
+        let workspace = recordings
+            .iter()
+            .filter(|r| r.directory.as_ref().is_some_and(|dir| cwd.starts_with(dir)))
+            .max_by_key(|r| {
+                r.directory
+                    .as_ref()
+                    .map_or(0, |dir| dir.components().count())
+            });
+        if let Some(recording) = workspace {
+            return Ok(Some(recording.clone()));
+        }
+        if let Some(default) = recordings.iter().find(|r| r.name == DEFAULT_RECORDING) {
+            return Ok(Some(default.clone()));
+        }
+        let names: Vec<&str> = recordings.iter().map(|r| r.name.as_str()).collect();
+        bail!(
+            "Several recordings in progress ({}). Pick one with --name.",
+            names.join(", ")
+        );
+    }
+}
+
+/// What a shell session's latest command means for its error streak
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreakEvent {
+    /// The streak reached the threshold; start recording
+    Start,
+    /// A command of the streak's family succeeded while this recording ran
+    Resolved(String),
+}
+
+/// Consecutive failures of one command family (see [`command_key`]) in a
+/// shell session, for starting recordings without `fuku rec`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorStreak {
+    pub family: String,
+    pub failures: usize,
+    /// When the first failure of the streak happened
+    pub since: Option<SystemTime>,
+    /// Recording started for this streak
+    pub recording: Option<String>,
+}
+
+impl ErrorStreak {
+    /// Count a command. A failure of another family starts a new streak,
+    /// unless a recording is already running for this one; Fukura's own
+    /// commands and successes of other families don't count.
+    pub fn observe(
+        &mut self,
+        command: &str,
+        failed: bool,
+        at: SystemTime,
+        threshold: usize,
+    ) -> Option<StreakEvent> {
+        let program = command.split_whitespace().next().unwrap_or_default();
+        if program == "fuku" || program == "fukura" {
+            return None;
+        }
+        let family = command_key(command);
+        if family.is_empty() {
+            return None;
+        }
+        if !failed {
+            if family != self.family {
+                return None;
+            }
+            let recording = std::mem::take(self).recording;
+            return recording.map(StreakEvent::Resolved);
+        }
+        if family != self.family {
+            if self.recording.is_some() {
+                return None;
+            }
+            *self = Self {
+                family,
+                failures: 0,
+                since: Some(at),
+                recording: None,
+            };
+        }
+        self.failures += 1;
+        (self.recording.is_none() && self.failures == threshold).then_some(StreakEvent::Start)
+    }
+
+    /// Name of the recording for this streak, e.g. `auto-cargo-build`
+    pub fn recording_name(&self) -> String {
+        let slug: String = self
+            .family
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let slug: Vec<&str> = slug.split('-').filter(|part| !part.is_empty()).collect();
+        format!("auto-{}", slug.join("-"))
+    }
+}
+
+/// Recording names become file names: letters, digits, `-` and `_`
+pub fn validate_recording_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Invalid recording name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// One command in a `fuku rec` recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingStep {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// Time since the recording started
+    pub offset: Duration,
+    /// First non-empty stderr line for failed commands
+    pub error: Option<String>,
+    /// Index of the step that failed before this one succeeded
+    pub fixes: Option<usize>,
+}
+
+impl RecordingStep {
+    pub fn failed(&self) -> bool {
+        self.exit_code.is_some_and(|code| code != 0)
+    }
+}
+
+/// A command that failed and later succeeded within the same recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedFix {
+    pub failed: usize,
+    pub fixed: usize,
+    pub elapsed: Duration,
+}
+
+/// Turn captured commands into ordered steps, linking failures to their fixes.
+/// Fukura's own commands (`fuku rec`, `fuku done`) are left out.
+pub fn build_steps(start: SystemTime, records: &[CommandRecord]) -> Vec<RecordingStep> {
+    let mut steps: Vec<RecordingStep> = Vec::new();
+    for record in records {
+        let program = record.command.split_whitespace().next().unwrap_or_default();
+        if program.is_empty() || program == "fuku" || program == "fukura" {
+            continue;
+        }
+
+        let mut step = RecordingStep {
+            command: record.command.clone(),
+            exit_code: record.exit_code,
+            offset: record.timestamp.duration_since(start).unwrap_or_default(),
+            error: None,
+            fixes: None,
+        };
+        if step.failed() {
+            step.error = record
+                .stderr
+                .as_deref()
+                .and_then(|stderr| stderr.lines().map(str::trim).find(|l| !l.is_empty()))
+                .map(|line| line.to_string());
+        } else {
+            // The most recent unresolved failure of the same command is considered fixed
+            let key = command_key(&step.command);
+            step.fixes = steps.iter().enumerate().rev().find_map(|(idx, prev)| {
+                (prev.failed()
+                    && command_key(&prev.command) == key
+                    && !steps.iter().any(|s| s.fixes == Some(idx)))
+                .then_some(idx)
+            });
+        }
+        steps.push(step);
+    }
+    steps
+}
+
+/// Failure → fix pairs in the order the fixes happened
+pub fn failed_fixes(steps: &[RecordingStep]) -> Vec<FailedFix> {
+    steps
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, step)| {
+            let failed = step.fixes?;
+            Some(FailedFix {
+                failed,
+                fixed: idx,
+                elapsed: step.offset.saturating_sub(steps[failed].offset),
+            })
+        })
+        .collect()
+}
+
+/// Markdown for the "What I Did" and "Failed → Fixed" sections of a recording note
+pub fn render_steps(steps: &[RecordingStep]) -> String {
+    let mut body = String::new();
+    for (idx, step) in steps.iter().enumerate() {
+        let status = match step.exit_code {
+            Some(0) => "✅".to_string(),
+            Some(code) => format!("❌ exit {}", code),
+            None => "❔".to_string(),
+        };
+        body.push_str(&format!(
+            "{}. `{}` {} (+{})",
+            idx + 1,
+            step.command,
+            status,
+            format_duration(step.offset)
+        ));
+        if let Some(failed) = step.fixes {
+            body.push_str(&format!(" — fixes step {}", failed + 1));
+        }
+        body.push('\n');
+        if let Some(error) = &step.error {
+            body.push_str(&format!("   > {}\n", error));
+        }
+    }
+
+    let fixes = failed_fixes(steps);
+    if !fixes.is_empty() {
+        body.push_str("\n## 🔧 Failed → Fixed\n\n");
+        for fix in fixes {
+            body.push_str(&format!(
+                "- `{}` failed at step {}, fixed at step {} after {}\n",
+                steps[fix.failed].command,
+                fix.failed + 1,
+                fix.fixed + 1,
+                format_duration(fix.elapsed)
+            ));
+            for step in &steps[fix.failed + 1..fix.fixed] {
+                body.push_str(&format!("  - `{}`\n", step.command));
+            }
+        }
+    }
+    body
+}
+
+/// Recover steps from a recording note body written by [`render_steps`]
+pub fn parse_steps(body: &str) -> Vec<RecordingStep> {
+    static STEP: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\d+\. `(.*)` (✅|❌ exit (-?\d+)|❔) \(\+([^)]*)\)(?: — fixes step (\d+))?$")
+            .expect("valid step pattern")
+    });
+
+    let mut steps: Vec<RecordingStep> = Vec::new();
+    for line in body.lines() {
+        if let Some(error) = line.strip_prefix("   > ") {
+            if let Some(step) = steps.last_mut() {
+                step.error = Some(error.to_string());
+            }
+            continue;
+        }
+        let Some(caps) = STEP.captures(line) else {
+            continue;
+        };
+        let exit_code = match &caps[2] {
+            "✅" => Some(0),
+            "❔" => None,
+            _ => caps.get(3).and_then(|code| code.as_str().parse().ok()),
+        };
+        steps.push(RecordingStep {
+            command: caps[1].to_string(),
+            exit_code,
+            offset: parse_duration(&caps[4]),
+            error: None,
+            fixes: caps
+                .get(5)
+                .and_then(|step| step.as_str().parse::<usize>().ok())
+                .and_then(|step| step.checked_sub(1)),
+        });
+    }
+    steps
+}
+
+/// Bash script that re-runs the successful commands of a recording.
+/// Failed attempts stay in the script as comments for context.
+pub fn replay_script(title: &str, note_id: &str, steps: &[RecordingStep]) -> String {
+    let mut script = String::from("#!/usr/bin/env bash\n");
+    script.push_str(&format!("# Replay of: {}\n", title));
+    script.push_str(&format!(
+        "# Generated by `fuku replay {} --script`\n",
+        note_id
+    ));
+    script.push_str("# Failed attempts are commented out; review before running.\n");
+    script.push_str("set -euo pipefail\n");
+
+    for (idx, step) in steps.iter().enumerate() {
+        script.push('\n');
+        let mut header = format!("# Step {} (+{})", idx + 1, format_duration(step.offset));
+        if let Some(failed) = step.fixes {
+            header.push_str(&format!(", fixes step {}", failed + 1));
+        }
+        if step.failed() {
+            header.push_str(&format!(
+                ": failed with exit {}",
+                step.exit_code.unwrap_or_default()
+            ));
+        }
+        script.push_str(&header);
+        script.push('\n');
+        if let Some(error) = &step.error {
+            script.push_str(&format!("#   {}\n", error));
+        }
+        if step.failed() {
+            script.push_str(&format!("# {}\n", step.command));
+        } else {
+            script.push_str(&format!("{}\n", step.command));
+        }
+    }
+    script
+}
+
+/// Commands count as "the same" when program and subcommand match,
+/// so `cargo build --release` fixes `cargo build`
+pub fn command_key(command: &str) -> String {
+    command
+        .split_whitespace()
+        .filter(|part| !part.starts_with('-'))
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_duration(text: &str) -> Duration {
+    let secs = text
+        .split_whitespace()
+        .filter_map(|part| {
+            let (split, _) = part.char_indices().last()?;
+            let (value, unit) = part.split_at(split);
+            let value: u64 = value.parse().ok()?;
+            Some(match unit {
+                "h" => value * 3600,
+                "m" => value * 60,
+                _ => value,
+            })
+        })
+        .sum();
+    Duration::from_secs(secs)
+}
+
+/// Commands that ran at least this long have their duration in notes
+pub const SLOW_COMMAND: Duration = Duration::from_secs(10);
+
+/// How long a command ran, e.g. `12m 3s`, when it is at least
+/// [`SLOW_COMMAND`]
+pub fn slow_duration(duration: Option<Duration>) -> Option<String> {
+    duration
+        .filter(|duration| *duration >= SLOW_COMMAND)
+        .map(format_duration)
+}
+
+/// `45s`, `12m 3s` or `2h 5m`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(command: &str, exit_code: i32, secs: u64) -> CommandRecord {
+        CommandRecord {
+            session_id: "s1".to_string(),
+            command: command.to_string(),
+            exit_code: Some(exit_code),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            working_directory: "/app".to_string(),
+            stderr: (exit_code != 0).then(|| "error[E0425]: cannot find value".to_string()),
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_failed_then_fixed() {
+        let records = vec![
+            record("fuku rec \"Fix build\"", 0, 0),
+            record("cargo build", 101, 10),
+            record("vim src/main.rs", 0, 40),
+            record("cargo build --release", 0, 190),
+        ];
+        let steps = build_steps(SystemTime::UNIX_EPOCH, &records);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(
+            steps[0].error.as_deref(),
+            Some("error[E0425]: cannot find value")
+        );
+        assert_eq!(steps[2].fixes, Some(0));
+
+        let fixes = failed_fixes(&steps);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].elapsed, Duration::from_secs(180));
+
+        let body = render_steps(&steps);
+        assert!(body.contains("1. `cargo build` ❌ exit 101 (+10s)"));
+        assert!(body.contains("fixes step 1"));
+        assert!(body.contains("fixed at step 3 after 3m 0s"));
+        assert!(body.contains("  - `vim src/main.rs`"));
+    }
+
+    #[test]
+    fn test_success_does_not_fix_other_commands() {
+        let records = vec![record("npm test", 1, 5), record("cargo test", 0, 20)];
+        let steps = build_steps(SystemTime::UNIX_EPOCH, &records);
+        assert!(steps.iter().all(|s| s.fixes.is_none()));
+        assert!(!render_steps(&steps).contains("Failed → Fixed"));
+    }
+
+    #[test]
+    fn test_parse_steps_round_trip_and_script() {
+        let records = vec![
+            record("cargo build", 101, 10),
+            record("echo 'a | b'", 0, 40),
+            record("cargo build", 0, 3780),
+        ];
+        let steps = build_steps(SystemTime::UNIX_EPOCH, &records);
+        let parsed = parse_steps(&format!("## 📋 What I Did\n\n{}", render_steps(&steps)));
+        assert_eq!(parsed, steps);
+
+        let script = replay_script("Fix build", "abc123", &parsed);
+        assert!(script.starts_with("#!/usr/bin/env bash\n"));
+        assert!(script.contains("# Step 1 (+10s): failed with exit 101\n"));
+        assert!(script.contains("\n# cargo build\n"));
+        assert!(script.contains("\necho 'a | b'\n"));
+        assert!(script.contains("# Step 3 (+1h 3m), fixes step 1\ncargo build\n"));
+    }
+
+    #[test]
+    fn test_state_round_trip_and_legacy_format() {
+        let recording = ActiveRecording::new(
+            "infra",
+            "Fix ingress",
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            Some(PathBuf::from("/work/infra")),
+        );
+        assert_eq!(recording.session_id, "rec_infra_1700000000");
+        assert_eq!(
+            ActiveRecording::parse("infra", &recording.to_state()),
+            Some(recording.clone())
+        );
+
+        // Files written before recordings had names
+        let legacy = ActiveRecording::parse(DEFAULT_RECORDING, "rec_1700000000|Deploy").unwrap();
+        assert_eq!(legacy.since, None);
+        assert_eq!(legacy.start_time(), recording.since);
+        assert!(legacy.includes(&record("ls", 0, 0)));
+
+        let mut outside = record("terraform plan", 0, 0);
+        assert!(!recording.includes(&outside));
+        outside.working_directory = "/work/infra/modules".to_string();
+        assert!(recording.includes(&outside));
+    }
+
+    #[test]
+    fn test_find_by_name_and_workspace() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = RecordingStore::new(temp.path());
+        let since = SystemTime::now();
+        let cwd = Path::new("/work/app/src");
+        assert_eq!(store.find(None, cwd).unwrap(), None);
+
+        let infra = ActiveRecording::new("infra", "Infra", since, Some("/work/infra".into()));
+        let app = ActiveRecording::new("app", "App", since, Some("/work/app".into()));
+        store.save(&infra).unwrap();
+        assert_eq!(store.find(None, cwd).unwrap().unwrap().name, "infra");
+
+        store.save(&app).unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+        assert_eq!(store.find(None, cwd).unwrap().unwrap().name, "app");
+        assert_eq!(
+            store.find(Some("infra"), cwd).unwrap().unwrap().title,
+            "Infra"
+        );
+        assert!(store.find(None, Path::new("/elsewhere")).is_err());
+
+        store.remove("app").unwrap();
+        store.remove("app").unwrap();
+        assert!(store.load("../escape").is_err());
+    }
+
+    #[test]
+    fn test_attachments_are_kept_per_recording() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = RecordingStore::new(temp.path());
+        let since = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let default = ActiveRecording::new(DEFAULT_RECORDING, "Fix", since, None);
+        let deploy = ActiveRecording::new("deploy", "Deploy", since, None);
+        assert!(store.attachments(&default).unwrap().is_empty());
+
+        store
+            .attach(&default, "scrollback-2.txt", "second")
+            .unwrap();
+        store.attach(&default, "scrollback-1.txt", "first").unwrap();
+        store.attach(&deploy, "scrollback-1.txt", "other").unwrap();
+        let attached = store.attachments(&default).unwrap();
+        assert_eq!(attached.len(), 2);
+        assert_eq!(fs::read_to_string(&attached[0]).unwrap(), "first");
+
+        store.discard_attachments(&default).unwrap();
+        store.discard_attachments(&default).unwrap();
+        assert!(store.attachments(&default).unwrap().is_empty());
+        assert_eq!(store.attachments(&deploy).unwrap().len(), 1);
+        // Attachments never show up as recordings
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_error_streak_starts_and_resolves() {
+        let at = SystemTime::UNIX_EPOCH;
+        let mut streak = ErrorStreak::default();
+        assert_eq!(streak.observe("cargo build", true, at, 3), None);
+        assert_eq!(streak.observe("ls", false, at, 3), None);
+        assert_eq!(streak.observe("fuku search E0425", true, at, 3), None);
+        assert_eq!(streak.observe("cargo build --release", true, at, 3), None);
+        assert_eq!(
+            streak.observe("cargo build", true, at, 3),
+            Some(StreakEvent::Start)
+        );
+        assert_eq!(streak.recording_name(), "auto-cargo-build");
+        streak.recording = Some(streak.recording_name());
+
+        // Other failures are part of the fix while recording
+        assert_eq!(streak.observe("npm test", true, at, 3), None);
+        assert_eq!(streak.failures, 3);
+        assert_eq!(
+            streak.observe("cargo build", false, at, 3),
+            Some(StreakEvent::Resolved("auto-cargo-build".to_string()))
+        );
+        assert_eq!(streak, ErrorStreak::default());
+
+        // A different failing command restarts the count
+        streak.observe("cargo build", true, at, 2);
+        assert_eq!(streak.observe("npm test", true, at, 2), None);
+        assert_eq!(streak.observe("cargo build", false, at, 2), None);
+        assert_eq!(
+            streak.observe("npm test", true, at, 2),
+            Some(StreakEvent::Start)
+        );
+    }
+}
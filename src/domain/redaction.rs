@@ -41,6 +41,17 @@ static DEFAULT_PATTERNS: Lazy<Vec<(&str, &str)>> = Lazy::new(|| {
     ]
 });
 
+/// Rules whose matches are credentials that work wherever they were
+/// copied, not just sensitive text. Finding one means the secret has most
+/// likely leaked somewhere besides the note.
+pub const LEAK_RULES: &[&str] = &[
+    "aws_access_key",
+    "aws_secret_key",
+    "github_token",
+    "github_oauth",
+    "private_key",
+];
+
 #[derive(Clone)]
 pub struct Redactor {
     rules: Vec<Rule>,
@@ -48,10 +59,17 @@ pub struct Redactor {
 
 #[derive(Clone)]
 struct Rule {
+    pub name: &'static str,
     pub regex: Regex,
     pub replacement: String,
 }
 
+/// Names of the built-in rules, the ones `redaction_overrides` can replace
+/// or (with an empty pattern) turn off
+pub fn builtin_rule_names() -> Vec<&'static str> {
+    DEFAULT_PATTERNS.iter().map(|(name, _)| *name).collect()
+}
+
 impl Redactor {
     pub fn default_with_overrides(overrides: &BTreeMap<String, String>) -> Self {
         let mut rules = Vec::new();
@@ -63,11 +81,13 @@ impl Redactor {
                 }
                 let regex = Regex::new(&value).unwrap_or_else(|_| Regex::new(pattern).unwrap());
                 rules.push(Rule {
+                    name,
                     regex,
                     replacement: format!("__{}_REDACTED__", name.to_uppercase()),
                 });
             } else {
                 rules.push(Rule {
+                    name,
                     regex: Regex::new(pattern).expect("valid default regex"),
                     replacement: format!("__{}_REDACTED__", name.to_uppercase()),
                 });
@@ -86,4 +106,13 @@ impl Redactor {
         }
         redacted
     }
+
+    /// Names of the [`LEAK_RULES`] that match `input`
+    pub fn leaks(&self, input: &str) -> Vec<&'static str> {
+        self.rules
+            .iter()
+            .filter(|rule| LEAK_RULES.contains(&rule.name) && rule.regex.is_match(input))
+            .map(|rule| rule.name)
+            .collect()
+    }
 }
@@ -0,0 +1,190 @@
+//! "Related notes" for a note: ones sharing its tags, with a similar title,
+//! or that the index finds for a more-like-this query built from its words.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::index::SearchSort;
+use crate::models::NoteRecord;
+use crate::repo::FukuraRepo;
+
+/// Related notes shown by `fuku view`, the HTML page and the TUI
+pub const DEFAULT_LIMIT: usize = 5;
+
+/// Index hits considered before ranking
+const CANDIDATES: usize = 20;
+
+/// Body words added to the more-like-this query
+const BODY_TERMS: usize = 8;
+
+/// Notes scoring lower than this aren't shown
+const MIN_SCORE: f64 = 0.15;
+
+/// Words too common to say what a note is about
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "not", "are", "was", "were", "but", "you",
+    "your", "have", "has", "had", "can", "cannot", "when", "then", "into", "out", "all", "any",
+    "use", "used", "using", "run", "ran", "error", "failed", "command", "exit", "code", "time",
+    "note", "notes", "http", "https", "www",
+];
+
+/// A note related to the one being viewed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelatedNote {
+    pub object_id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    /// Between 0 and 1; higher is more related
+    pub score: f64,
+    /// Tags it shares with the viewed note
+    pub shared_tags: Vec<String>,
+}
+
+/// Up to `limit` notes related to `record`, most related first
+pub fn related_notes(
+    repo: &FukuraRepo,
+    record: &NoteRecord,
+    limit: usize,
+) -> Result<Vec<RelatedNote>> {
+    let query = more_like_this(record);
+    if query.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+    let hits = repo.search(&query, CANDIDATES, SearchSort::Relevance)?;
+    let top = hits
+        .iter()
+        .filter(|hit| hit.object_id != record.object_id)
+        .map(|hit| hit.score)
+        .fold(0.0_f32, f32::max);
+    let tags: HashSet<&str> = record.note.tags.iter().map(String::as_str).collect();
+    let title = words(&record.note.title);
+
+    let mut related: Vec<RelatedNote> = hits
+        .into_iter()
+        .filter(|hit| hit.object_id != record.object_id)
+        .map(|hit| {
+            let shared_tags: Vec<String> = hit
+                .tags
+                .iter()
+                .filter(|tag| tags.contains(tag.as_str()))
+                .cloned()
+                .collect();
+            let hit_tags: HashSet<&str> = hit.tags.iter().map(String::as_str).collect();
+            let index = if top > 0.0 {
+                f64::from(hit.score / top)
+            } else {
+                0.0
+            };
+            let score = 0.5 * index
+                + 0.3 * jaccard(&tags, &hit_tags)
+                + 0.2 * jaccard(&title, &words(&hit.title));
+            RelatedNote {
+                object_id: hit.object_id,
+                title: hit.title,
+                tags: hit.tags,
+                score,
+                shared_tags,
+            }
+        })
+        .filter(|related| related.score >= MIN_SCORE)
+        .collect();
+    related.sort_by(|a, b| b.score.total_cmp(&a.score));
+    related.truncate(limit);
+    Ok(related)
+}
+
+/// Plain-word query for notes like `record`: its tags, its title's words
+/// and the words its body uses most
+fn more_like_this(record: &NoteRecord) -> String {
+    let mut terms: Vec<String> = Vec::new();
+    let mut push = |term: String| {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    };
+    for tag in &record.note.tags {
+        for word in tag.split(|c: char| !c.is_alphanumeric()) {
+            if !word.is_empty() {
+                push(word.to_lowercase());
+            }
+        }
+    }
+    let mut title: Vec<String> = words(&record.note.title).into_iter().collect();
+    title.sort();
+    title.into_iter().for_each(&mut push);
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for word in tokens(&record.note.body) {
+        *counts.entry(word).or_default() += 1;
+    }
+    let mut frequent: Vec<(String, usize)> = counts.into_iter().filter(|(_, n)| *n > 1).collect();
+    frequent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequent
+        .into_iter()
+        .take(BODY_TERMS)
+        .for_each(|(word, _)| push(word));
+    terms.join(" ")
+}
+
+/// Lowercase words of `text` worth matching on
+fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 3 && !word.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+fn words(text: &str) -> HashSet<String> {
+    tokens(text).collect()
+}
+
+fn jaccard<T: Eq + std::hash::Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_related_notes_share_tags_and_words() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let store = |title: &str, body: &str, tags: &[&str]| {
+            let mut builder = NoteBuilder::new(title, body);
+            for tag in tags {
+                builder = builder.tag(tag);
+            }
+            repo.store_note(builder.build()).unwrap()
+        };
+        let viewed = store(
+            "Postgres connection refused",
+            "psql: connection refused on port 5432. Start postgres with brew services.",
+            &["postgres", "database"],
+        );
+        let similar = store(
+            "Postgres refuses connections after upgrade",
+            "After brew upgrade postgres refused every connection until the data dir was migrated.",
+            &["postgres"],
+        );
+        store(
+            "Rename a git branch",
+            "git branch -m old new, then push the new branch.",
+            &["git"],
+        );
+
+        let related = related_notes(&repo, &viewed, DEFAULT_LIMIT).unwrap();
+        assert_eq!(related[0].object_id, similar.object_id);
+        assert_eq!(related[0].shared_tags, ["postgres"]);
+        assert!(related.iter().all(|r| r.object_id != viewed.object_id));
+        assert!(related.iter().all(|r| r.title != "Rename a git branch"));
+    }
+}
@@ -0,0 +1,309 @@
+use chrono::{DateTime, Local, Utc};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::activity_storage::{CommandLog, CommandRecord};
+use crate::models::NoteRecord;
+use crate::patterns::{ErrorPattern, PatternStore};
+use crate::repo::FukuraRepo;
+
+/// Recurring errors listed in a report
+const TOP_ERRORS: usize = 5;
+
+/// A note counted in a report section
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportNote {
+    pub object_id: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What happened in a repository between `since` and `until`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub since: SystemTime,
+    pub until: SystemTime,
+    pub commands: usize,
+    pub failed_commands: usize,
+    /// Error notes captured by the daemon
+    pub errors_captured: Vec<ReportNote>,
+    /// Resolution notes, and notes that came with a solution
+    pub problems_solved: Vec<ReportNote>,
+    /// Notes saved from `fuku rec`
+    pub recordings: Vec<ReportNote>,
+    /// Everything else written in the period
+    pub new_notes: Vec<ReportNote>,
+    /// Most frequent error messages in the period with their counts
+    pub top_errors: Vec<(String, usize)>,
+}
+
+impl Report {
+    /// Sort the notes created in `[since, until)` into sections and count
+    /// commands and error occurrences in the same window
+    pub fn build(
+        notes: impl IntoIterator<Item = NoteRecord>,
+        commands: &[CommandRecord],
+        patterns: &HashMap<String, ErrorPattern>,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> Self {
+        let (start, end): (DateTime<Utc>, DateTime<Utc>) = (since.into(), until.into());
+        let mut report = Self {
+            since,
+            until,
+            commands: 0,
+            failed_commands: 0,
+            errors_captured: Vec::new(),
+            problems_solved: Vec::new(),
+            recordings: Vec::new(),
+            new_notes: Vec::new(),
+            top_errors: Vec::new(),
+        };
+
+        for record in notes {
+            let note = &record.note;
+            if note.created_at < start || note.created_at >= end {
+                continue;
+            }
+            let has_tag = |names: &[&str]| note.tags.iter().any(|t| names.contains(&t.as_str()));
+            let section = if has_tag(&["recording"]) {
+                &mut report.recordings
+            } else if has_tag(&["resolution", "auto-solved", "solved"])
+                || !note.solutions.is_empty()
+            {
+                &mut report.problems_solved
+            } else if has_tag(&["auto-captured", "error"]) {
+                &mut report.errors_captured
+            } else {
+                &mut report.new_notes
+            };
+            section.push(ReportNote {
+                object_id: record.object_id.clone(),
+                title: note.title.clone(),
+                created_at: note.created_at,
+            });
+        }
+        for section in [
+            &mut report.errors_captured,
+            &mut report.problems_solved,
+            &mut report.recordings,
+            &mut report.new_notes,
+        ] {
+            section.sort_by_key(|note| note.created_at);
+        }
+
+        for record in commands {
+            if record.timestamp >= since && record.timestamp < until {
+                report.commands += 1;
+                if record.failed() {
+                    report.failed_commands += 1;
+                }
+            }
+        }
+
+        let mut top_errors: Vec<(String, usize)> = patterns
+            .values()
+            .map(|p| (p.normalized_message.clone(), p.count_between(since, until)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        top_errors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_errors.truncate(TOP_ERRORS);
+        report.top_errors = top_errors;
+        report
+    }
+
+    /// The report of `repo` for `[since, until)`, from its notes, command
+    /// history and error patterns
+    pub fn for_repo(repo: &FukuraRepo, since: SystemTime, until: SystemTime) -> Result<Self> {
+        let commands = CommandLog::new(repo.root())?.read_since(since)?;
+        let patterns = PatternStore::new(repo.root()).load().unwrap_or_default();
+        Ok(Self::build(
+            repo.iter_notes()?.flatten(),
+            &commands,
+            &patterns,
+            since,
+            until,
+        ))
+    }
+
+    /// Period covered, as local dates
+    pub fn period(&self) -> String {
+        let day = |time: SystemTime| DateTime::<Local>::from(time).format("%Y-%m-%d").to_string();
+        format!("{} – {}", day(self.since), day(self.until))
+    }
+
+    fn sections(&self) -> [(&'static str, &[ReportNote]); 4] {
+        [
+            ("Problems solved", &self.problems_solved),
+            ("Errors captured", &self.errors_captured),
+            ("Recordings", &self.recordings),
+            ("New notes", &self.new_notes),
+        ]
+    }
+
+    /// Markdown for a standup doc, chat message or webhook payload
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Fukura report: {}\n", self.period());
+        let _ = writeln!(
+            out,
+            "- **{}** problems solved\n- **{}** errors captured\n- **{}** recordings\n- **{}** new notes\n- **{}** commands run ({} failed)",
+            self.problems_solved.len(),
+            self.errors_captured.len(),
+            self.recordings.len(),
+            self.new_notes.len(),
+            self.commands,
+            self.failed_commands
+        );
+        for (heading, notes) in self.sections() {
+            if notes.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n## {}\n", heading);
+            for note in notes {
+                let _ = writeln!(
+                    out,
+                    "- {} (`{}`)",
+                    note.title,
+                    &note.object_id[..8.min(note.object_id.len())]
+                );
+            }
+        }
+        if !self.top_errors.is_empty() {
+            let _ = writeln!(out, "\n## Most frequent errors\n");
+            for (message, count) in &self.top_errors {
+                let _ = writeln!(out, "- {}× `{}`", count, message.replace('`', "'"));
+            }
+        }
+        out
+    }
+
+    /// Standalone HTML page with the same content as [`Report::to_markdown`]
+    pub fn to_html(&self) -> String {
+        let escape = |text: &str| html_escape::encode_text(text).to_string();
+        let mut body = String::new();
+        let _ = writeln!(body, "<h1>Fukura report: {}</h1>", escape(&self.period()));
+        let _ = writeln!(
+            body,
+            "<ul class=\"summary\">\n<li><strong>{}</strong> problems solved</li>\n<li><strong>{}</strong> errors captured</li>\n<li><strong>{}</strong> recordings</li>\n<li><strong>{}</strong> new notes</li>\n<li><strong>{}</strong> commands run ({} failed)</li>\n</ul>",
+            self.problems_solved.len(),
+            self.errors_captured.len(),
+            self.recordings.len(),
+            self.new_notes.len(),
+            self.commands,
+            self.failed_commands
+        );
+        for (heading, notes) in self.sections() {
+            if notes.is_empty() {
+                continue;
+            }
+            let _ = writeln!(body, "<h2>{}</h2>\n<ul>", heading);
+            for note in notes {
+                let _ = writeln!(
+                    body,
+                    "<li>{} <code>{}</code></li>",
+                    escape(&note.title),
+                    &note.object_id[..8.min(note.object_id.len())]
+                );
+            }
+            let _ = writeln!(body, "</ul>");
+        }
+        if !self.top_errors.is_empty() {
+            let _ = writeln!(body, "<h2>Most frequent errors</h2>\n<ul>");
+            for (message, count) in &self.top_errors {
+                let _ = writeln!(body, "<li>{}× <code>{}</code></li>", count, escape(message));
+            }
+            let _ = writeln!(body, "</ul>");
+        }
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Fukura report</title>\n<style>body{{font-family:system-ui,sans-serif;max-width:720px;margin:2rem auto;line-height:1.5}}code{{background:#f3f3f3;padding:0 .25rem}}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use chrono::{Duration, TimeZone};
+
+    fn command(exit_code: i32, timestamp: SystemTime) -> CommandRecord {
+        CommandRecord {
+            session_id: "s1".to_string(),
+            command: "cargo build".to_string(),
+            exit_code: Some(exit_code),
+            timestamp,
+            working_directory: "/app".to_string(),
+            stderr: None,
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_build_sorts_notes_into_sections() {
+        let until = Utc.with_ymd_and_hms(2024, 6, 17, 0, 0, 0).unwrap();
+        let since = until - Duration::days(7);
+        let day = |n: i64| since + Duration::days(n);
+        let notes = vec![
+            NoteBuilder::new("Error: cargo build", "")
+                .tag("error")
+                .tag("auto-captured")
+                .created_at(day(1))
+                .record("a0000000"),
+            NoteBuilder::new("Solved: cargo build", "")
+                .tag("auto-solved")
+                .tag("resolution")
+                .created_at(day(2))
+                .record("b0000000"),
+            NoteBuilder::new("Deploy steps", "")
+                .tag("recording")
+                .tag("workflow")
+                .created_at(day(3))
+                .record("c0000000"),
+            NoteBuilder::new("Proxy runbook", "")
+                .tag("proxy")
+                .created_at(day(4))
+                .record("d0000000"),
+            NoteBuilder::new("Old note", "")
+                .tag("proxy")
+                .created_at(since - Duration::days(1))
+                .record("e0000000"),
+        ];
+        let commands = vec![
+            command(0, day(1).into()),
+            command(101, day(1).into()),
+            command(1, (since - Duration::hours(1)).into()),
+        ];
+        let mut pattern = ErrorPattern::new(
+            "error[E0308]: mismatched types".to_string(),
+            "f".to_string(),
+            day(1).into(),
+        );
+        pattern.record(day(1).into(), None, None);
+        pattern.record(day(2).into(), None, None);
+        let patterns = HashMap::from([("f".to_string(), pattern)]);
+
+        let report = Report::build(notes, &commands, &patterns, since.into(), until.into());
+        assert_eq!(report.errors_captured.len(), 1);
+        assert_eq!(report.problems_solved[0].title, "Solved: cargo build");
+        assert_eq!(report.recordings[0].title, "Deploy steps");
+        assert_eq!(report.new_notes.len(), 1);
+        assert_eq!((report.commands, report.failed_commands), (2, 1));
+        assert_eq!(
+            report.top_errors,
+            vec![("error[E0308]: mismatched types".to_string(), 2)]
+        );
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("- **1** problems solved"));
+        assert!(markdown.contains("## Recordings\n\n- Deploy steps (`c0000000`)"));
+        assert!(!markdown.contains("Old note"));
+
+        let html = report.to_html();
+        assert!(html.contains("<li>2× <code>error[E0308]: mismatched types</code></li>"));
+    }
+}
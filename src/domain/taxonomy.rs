@@ -0,0 +1,328 @@
+use serde::Serialize;
+
+use crate::models::Note;
+use crate::titles::TitleContext;
+
+/// Meta keys that override what [`ErrorTaxonomy::from_note`] works out, so a
+/// misclassified note can be corrected with `fuku edit --meta`
+pub const BINARY_META: &str = "binary";
+pub const LANGUAGE_META: &str = "language";
+pub const ERROR_CLASS_META: &str = "error_class";
+
+/// Commands that say which language a failure is about
+const LANGUAGES: &[(&[&str], &str)] = &[
+    (&["cargo", "rustc", "rustup", "clippy-driver"], "rust"),
+    (
+        &["npm", "npx", "yarn", "pnpm", "node", "tsc", "jest", "vite"],
+        "javascript",
+    ),
+    (
+        &["python", "python3", "pip", "pip3", "pytest", "poetry", "uv"],
+        "python",
+    ),
+    (&["go", "gofmt"], "go"),
+    (&["java", "javac", "mvn", "gradle", "gradlew"], "java"),
+    (&["ruby", "bundle", "rake", "rails", "gem"], "ruby"),
+    (&["terraform", "tofu"], "hcl"),
+    (&["gcc", "g++", "clang", "clang++", "make", "cmake"], "c"),
+];
+
+/// Error output that gives the language away when the command doesn't
+const LANGUAGE_MARKERS: &[(&str, &str)] = &[
+    ("error[E", "rust"),
+    ("Traceback (most recent call last)", "python"),
+    ("npm ERR!", "javascript"),
+    ("panic: ", "go"),
+    ("Exception in thread", "java"),
+];
+
+/// Error output patterns, checked in order; the first match classifies
+const ERROR_CLASSES: &[(&[&str], &str)] = &[
+    (
+        &[
+            "command not found",
+            "No such file or directory",
+            "not recognized as",
+        ],
+        "not_found",
+    ),
+    (
+        &["Permission denied", "EACCES", "Operation not permitted"],
+        "permission",
+    ),
+    (
+        &[
+            "401 Unauthorized",
+            "403 Forbidden",
+            "authentication failed",
+            "Authentication failed",
+            "invalid credentials",
+        ],
+        "auth",
+    ),
+    (
+        &[
+            "Connection refused",
+            "ECONNREFUSED",
+            "Could not resolve host",
+            "Name or service not known",
+            "Network is unreachable",
+        ],
+        "network",
+    ),
+    (&["timed out", "deadline exceeded", "ETIMEDOUT"], "timeout"),
+    (
+        &[
+            "out of memory",
+            "OOMKilled",
+            "MemoryError",
+            "heap out of memory",
+        ],
+        "out_of_memory",
+    ),
+    (
+        &[
+            "ModuleNotFoundError",
+            "Cannot find module",
+            "unresolved import",
+            "no matching package",
+            "Could not find a version",
+            "failed to select a version",
+            "ERESOLVE",
+        ],
+        "dependency",
+    ),
+    (
+        &[
+            "test result: FAILED",
+            "AssertionError",
+            "assertion failed",
+            "Tests failed",
+            "FAILED tests",
+            "--- FAIL:",
+        ],
+        "test_failure",
+    ),
+    (
+        &[
+            "SyntaxError",
+            "syntax error",
+            "expected one of",
+            "ParseError",
+        ],
+        "syntax",
+    ),
+    (
+        &[
+            "error[E",
+            "could not compile",
+            "compilation failed",
+            "error TS",
+            "cannot find symbol",
+            "undefined reference",
+        ],
+        "compile",
+    ),
+];
+
+/// What kind of failure a note records, indexed as typed fields so searches
+/// like `binary:terraform exit_code:1` work
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ErrorTaxonomy {
+    pub exit_code: Option<i64>,
+    /// Program that failed, e.g. `cargo` for `RUST_LOG=debug cargo test`
+    pub binary: Option<String>,
+    pub language: Option<String>,
+    pub error_class: Option<String>,
+}
+
+impl ErrorTaxonomy {
+    /// Classify a failure from its command line, exit code and error output
+    pub fn classify(command: &str, exit_code: Option<i64>, stderr: &str) -> Self {
+        let binary = command_binary(command);
+        let language = binary
+            .as_deref()
+            .and_then(language_of)
+            .or_else(|| {
+                LANGUAGE_MARKERS
+                    .iter()
+                    .find(|(marker, _)| stderr.contains(marker))
+                    .map(|(_, language)| *language)
+            })
+            .map(str::to_string);
+        Self {
+            exit_code,
+            binary,
+            language,
+            error_class: error_class(exit_code, stderr).map(str::to_string),
+        }
+    }
+
+    /// Taxonomy of a stored note: captured failures are classified from
+    /// their command and error output, other notes from the `exit_code` and
+    /// `error_command` meta. The `binary`, `language` and `error_class` meta
+    /// win when set.
+    pub fn from_note(note: &Note) -> Self {
+        let exit_code = note
+            .meta
+            .get("exit_code")
+            .and_then(|code| code.trim().parse().ok());
+        let mut taxonomy = match TitleContext::from_note(note) {
+            Some(context) => Self::classify(&context.command, exit_code, &context.stderr),
+            None => match note.meta.get("error_command") {
+                Some(command) => Self::classify(command, exit_code, ""),
+                None => Self {
+                    exit_code,
+                    ..Self::default()
+                },
+            },
+        };
+        let set = |key: &str| {
+            note.meta
+                .get(key)
+                .map(|value| value.trim().to_lowercase())
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(binary) = set(BINARY_META) {
+            taxonomy.language = taxonomy
+                .language
+                .or_else(|| language_of(&binary).map(str::to_string));
+            taxonomy.binary = Some(binary);
+        }
+        taxonomy.language = set(LANGUAGE_META).or(taxonomy.language);
+        taxonomy.error_class = set(ERROR_CLASS_META).or(taxonomy.error_class);
+        taxonomy
+    }
+
+    /// Whether the note records a failure at all
+    pub fn is_error(&self) -> bool {
+        self.exit_code.is_some_and(|code| code != 0) || self.error_class.is_some()
+    }
+}
+
+/// Program run by `command`, past env assignments, `sudo`/`env`-style
+/// wrappers and directories, lowercased
+pub fn command_binary(command: &str) -> Option<String> {
+    let first_line = command.lines().next().unwrap_or_default();
+    let mut words = first_line.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        if word.contains('=') && !word.starts_with('=') {
+            continue;
+        }
+        if matches!(word, "sudo" | "env" | "time" | "nohup" | "exec" | "command") {
+            continue;
+        }
+        let name = word.rsplit(['/', '\\']).next().unwrap_or(word);
+        let name = name.trim_end_matches(".exe").to_lowercase();
+        // `npx jest` and `python -m pytest` fail in the tool they run
+        if matches!(name.as_str(), "npx" | "bunx" | "pnpx") {
+            if let Some(tool) = words.peek().filter(|w| !w.starts_with('-')) {
+                return Some(tool.to_lowercase());
+            }
+        }
+        if name.starts_with("python") && words.peek() == Some(&"-m") {
+            words.next();
+            if let Some(module) = words.next() {
+                return Some(module.to_lowercase());
+            }
+        }
+        return (!name.is_empty()).then_some(name);
+    }
+    None
+}
+
+fn language_of(binary: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(binaries, _)| binaries.contains(&binary))
+        .map(|(_, language)| *language)
+}
+
+fn error_class(exit_code: Option<i64>, stderr: &str) -> Option<&'static str> {
+    if let Some((_, class)) = ERROR_CLASSES
+        .iter()
+        .find(|(patterns, _)| patterns.iter().any(|pattern| stderr.contains(pattern)))
+    {
+        return Some(class);
+    }
+    // Shells report these without saying anything
+    match exit_code? {
+        126 => Some("permission"),
+        127 => Some("not_found"),
+        130 => Some("interrupted"),
+        137 => Some("out_of_memory"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+
+    #[test]
+    fn test_binary_skips_wrappers() {
+        assert_eq!(
+            command_binary("RUST_LOG=debug sudo /usr/bin/cargo test").as_deref(),
+            Some("cargo")
+        );
+        assert_eq!(command_binary("npx jest --ci").as_deref(), Some("jest"));
+        assert_eq!(
+            command_binary("python3 -m pytest -x").as_deref(),
+            Some("pytest")
+        );
+        assert_eq!(command_binary("  "), None);
+    }
+
+    #[test]
+    fn test_classify_from_stderr_and_exit_code() {
+        let taxonomy = ErrorTaxonomy::classify(
+            "cargo build",
+            Some(101),
+            "error[E0425]: cannot find value `x`",
+        );
+        assert_eq!(taxonomy.language.as_deref(), Some("rust"));
+        assert_eq!(taxonomy.error_class.as_deref(), Some("compile"));
+
+        let taxonomy = ErrorTaxonomy::classify(
+            "./deploy.sh",
+            Some(1),
+            "Traceback (most recent call last):\nModuleNotFoundError: No module named 'yaml'",
+        );
+        assert_eq!(taxonomy.binary.as_deref(), Some("deploy.sh"));
+        assert_eq!(taxonomy.language.as_deref(), Some("python"));
+        assert_eq!(taxonomy.error_class.as_deref(), Some("dependency"));
+
+        let taxonomy = ErrorTaxonomy::classify("terrafrom plan", Some(127), "");
+        assert_eq!(taxonomy.error_class.as_deref(), Some("not_found"));
+        assert!(taxonomy.is_error());
+    }
+
+    #[test]
+    fn test_from_captured_note_with_meta_override() {
+        let note = NoteBuilder::new(
+            "Error: terraform plan",
+            "## Command Failed\n\n```bash\n$ terraform plan\n\n# Error output:\nError: Could not resolve host: registry.terraform.io\n```\n\n**Exit Code**: 1",
+        )
+        .meta("exit_code", "1")
+        .build();
+        let taxonomy = ErrorTaxonomy::from_note(&note);
+        assert_eq!(taxonomy.exit_code, Some(1));
+        assert_eq!(taxonomy.binary.as_deref(), Some("terraform"));
+        assert_eq!(taxonomy.language.as_deref(), Some("hcl"));
+        assert_eq!(taxonomy.error_class.as_deref(), Some("network"));
+
+        let mut corrected = note.clone();
+        corrected
+            .meta
+            .insert(ERROR_CLASS_META.to_string(), "Auth".to_string());
+        assert_eq!(
+            ErrorTaxonomy::from_note(&corrected).error_class.as_deref(),
+            Some("auth")
+        );
+
+        let manual = NoteBuilder::new("Proxy setup", "Use the corporate proxy").build();
+        assert_eq!(ErrorTaxonomy::from_note(&manual), ErrorTaxonomy::default());
+        assert!(!ErrorTaxonomy::from_note(&manual).is_error());
+    }
+}
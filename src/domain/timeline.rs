@@ -0,0 +1,270 @@
+use serde::Serialize;
+use std::time::SystemTime;
+
+use crate::activity::{Activity, ActivityType, FileChangeType};
+use crate::activity_storage::CommandRecord;
+
+/// What happened at a point on the timeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimelineKind {
+    Command {
+        command: String,
+        exit_code: Option<i32>,
+    },
+    Error {
+        message: String,
+    },
+    FileChange {
+        path: String,
+        change: String,
+    },
+    Note {
+        object_id: String,
+        title: String,
+    },
+}
+
+/// A single entry in the activity timeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineEvent {
+    pub timestamp: SystemTime,
+    /// Working directory the event belongs to, when known
+    pub directory: Option<String>,
+    pub session_id: Option<String>,
+    pub kind: TimelineKind,
+}
+
+impl TimelineEvent {
+    /// Convert a stored activity; activity types without a timeline view are skipped
+    pub fn from_activity(activity: &Activity) -> Option<Self> {
+        let directory = activity.metadata.get("working_directory").cloned();
+        let (directory, kind) = match &activity.activity_type {
+            ActivityType::Command(cmd) => (
+                Some(cmd.working_directory.clone()),
+                TimelineKind::Command {
+                    command: cmd.command.clone(),
+                    exit_code: cmd.exit_code,
+                },
+            ),
+            ActivityType::Error(err) => (
+                directory,
+                TimelineKind::Error {
+                    message: err.message.clone(),
+                },
+            ),
+            ActivityType::FileChange(file) => {
+                let change = match &file.change_type {
+                    FileChangeType::Created => "created".to_string(),
+                    FileChangeType::Modified => "modified".to_string(),
+                    FileChangeType::Deleted => "deleted".to_string(),
+                    FileChangeType::Renamed { from } => format!("renamed from {}", from.display()),
+                };
+                let directory = directory.or_else(|| {
+                    file.path
+                        .parent()
+                        .map(|parent| parent.display().to_string())
+                });
+                (
+                    directory,
+                    TimelineKind::FileChange {
+                        path: file.path.display().to_string(),
+                        change,
+                    },
+                )
+            }
+            _ => return None,
+        };
+        Some(Self {
+            timestamp: activity.timestamp,
+            directory,
+            session_id: Some(activity.session_id.clone()),
+            kind,
+        })
+    }
+
+    /// A daemon-captured command, plus an error entry when it failed with stderr
+    pub fn from_command(record: &CommandRecord) -> Vec<Self> {
+        let mut events = vec![Self {
+            timestamp: record.timestamp,
+            directory: Some(record.working_directory.clone()),
+            session_id: Some(record.session_id.clone()),
+            kind: TimelineKind::Command {
+                command: record.command.clone(),
+                exit_code: record.exit_code,
+            },
+        }];
+        if record.failed() {
+            if let Some(message) = record
+                .stderr
+                .as_deref()
+                .and_then(|stderr| stderr.lines().find(|line| !line.trim().is_empty()))
+            {
+                events.push(Self {
+                    timestamp: record.timestamp,
+                    directory: Some(record.working_directory.clone()),
+                    session_id: Some(record.session_id.clone()),
+                    kind: TimelineKind::Error {
+                        message: message.trim().to_string(),
+                    },
+                });
+            }
+        }
+        events
+    }
+
+    pub fn note(
+        timestamp: SystemTime,
+        directory: Option<String>,
+        object_id: &str,
+        title: &str,
+    ) -> Self {
+        Self {
+            timestamp,
+            directory,
+            session_id: None,
+            kind: TimelineKind::Note {
+                object_id: object_id.to_string(),
+                title: title.to_string(),
+            },
+        }
+    }
+}
+
+/// Chronological view over commands, errors, file changes and notes
+#[derive(Debug, Default)]
+pub struct Timeline {
+    events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+    pub fn new(mut events: Vec<TimelineEvent>) -> Self {
+        events.sort_by_key(|event| event.timestamp);
+        Self { events }
+    }
+
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Keep events in `[start, end)`
+    pub fn within(self, start: SystemTime, end: SystemTime) -> Self {
+        Self {
+            events: self
+                .events
+                .into_iter()
+                .filter(|event| event.timestamp >= start && event.timestamp < end)
+                .collect(),
+        }
+    }
+
+    /// Keep events from one session (matched by id prefix)
+    pub fn for_session(self, session_id: &str) -> Self {
+        Self {
+            events: self
+                .events
+                .into_iter()
+                .filter(|event| {
+                    event
+                        .session_id
+                        .as_deref()
+                        .is_some_and(|id| id.starts_with(session_id))
+                })
+                .collect(),
+        }
+    }
+
+    /// Group events by working directory, ordered by each directory's first event
+    pub fn group_by_directory(&self) -> Vec<(Option<&str>, Vec<&TimelineEvent>)> {
+        let mut groups: Vec<(Option<&str>, Vec<&TimelineEvent>)> = Vec::new();
+        for event in &self.events {
+            let directory = event.directory.as_deref();
+            match groups.iter_mut().find(|(dir, _)| *dir == directory) {
+                Some((_, events)) => events.push(event),
+                None => groups.push((directory, vec![event])),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::{CommandActivity, FileChangeActivity};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn command(cmd: &str, dir: &str, exit_code: i32, secs: u64) -> CommandRecord {
+        CommandRecord {
+            session_id: "s1".to_string(),
+            command: cmd.to_string(),
+            exit_code: Some(exit_code),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            working_directory: dir.to_string(),
+            stderr: (exit_code != 0).then(|| "\nerror: build failed\n".to_string()),
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_failed_command_adds_error_event() {
+        let events = TimelineEvent::from_command(&command("cargo build", "/app", 101, 10));
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].kind,
+            TimelineKind::Error {
+                message: "error: build failed".to_string()
+            }
+        );
+        assert_eq!(
+            TimelineEvent::from_command(&command("ls", "/app", 0, 10)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_from_activity() {
+        let activity = Activity::command(
+            "s2".to_string(),
+            CommandActivity::new("make".to_string(), "/srv".to_string()).with_exit_code(0),
+        );
+        let event = TimelineEvent::from_activity(&activity).unwrap();
+        assert_eq!(event.directory.as_deref(), Some("/srv"));
+
+        let activity = Activity::file_change(
+            "s2".to_string(),
+            FileChangeActivity::new(PathBuf::from("/srv/src/main.rs"), FileChangeType::Created),
+        );
+        let event = TimelineEvent::from_activity(&activity).unwrap();
+        assert_eq!(event.directory.as_deref(), Some("/srv/src"));
+        assert!(
+            matches!(event.kind, TimelineKind::FileChange { ref change, .. } if change == "created")
+        );
+    }
+
+    #[test]
+    fn test_window_and_grouping() {
+        let mut events = Vec::new();
+        events.extend(TimelineEvent::from_command(&command("b", "/two", 0, 30)));
+        events.extend(TimelineEvent::from_command(&command("a", "/one", 0, 20)));
+        events.extend(TimelineEvent::from_command(&command("c", "/one", 0, 40)));
+        events.extend(TimelineEvent::from_command(&command("old", "/one", 0, 1)));
+
+        let timeline = Timeline::new(events).within(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(100),
+        );
+        assert_eq!(timeline.events().len(), 3);
+
+        let groups = timeline.group_by_directory();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, Some("/one"));
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, Some("/two"));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::models::Note;
+use crate::patterns::{failure_message, normalize_message};
+
+/// Title of captured errors unless `capture.title_template` says otherwise
+pub const DEFAULT_TITLE_TEMPLATE: &str = "Error: {command}";
+
+/// Longest generated title, in characters
+const MAX_TITLE_CHARS: usize = 80;
+
+/// What a captured failure's title can be built from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleContext {
+    pub command: String,
+    pub exit_code: i32,
+    pub stderr: String,
+}
+
+impl TitleContext {
+    /// Recover the failure from a note written by the daemon: the `$ command`
+    /// line and error output of its bash block, and the `exit_code` meta.
+    /// `None` for notes that don't look captured.
+    pub fn from_note(note: &Note) -> Option<Self> {
+        let mut command: Option<Vec<&str>> = None;
+        let mut stderr = Vec::new();
+        let mut in_block = false;
+        let mut in_command = false;
+        let mut in_output = false;
+        for line in note.body.lines() {
+            if line.trim_start().starts_with("```") {
+                if in_block {
+                    break;
+                }
+                in_block = true;
+                continue;
+            }
+            if !in_block {
+                continue;
+            }
+            if in_output {
+                stderr.push(line);
+            } else if line.trim() == "# Error output:" {
+                in_output = true;
+            } else if in_command && !line.trim().is_empty() {
+                // Continuation of a multi-line command
+                command.get_or_insert_with(Vec::new).push(line);
+            } else if let Some(rest) = line.strip_prefix("$ ").filter(|_| command.is_none()) {
+                command = Some(vec![rest]);
+                in_command = true;
+                continue;
+            }
+            in_command = false;
+        }
+        Some(Self {
+            command: command?.join("\n"),
+            exit_code: note.meta.get("exit_code")?.parse().ok()?,
+            stderr: stderr.join("\n"),
+        })
+    }
+
+    /// First line of the command, marked when more lines follow
+    fn command_summary(&self) -> String {
+        let mut lines = self.command.lines();
+        let first = lines.next().unwrap_or_default().trim();
+        if lines.next().is_some() {
+            format!("{} …", first)
+        } else {
+            first.to_string()
+        }
+    }
+}
+
+/// Fill a title template. Placeholders: `{command}` (first line of the
+/// command), `{program}`, `{exit_code}`, `{error}` (the most telling stderr
+/// line, normalized) and `{stderr}` (first stderr line). Unknown placeholders
+/// stay as written; a template that renders empty falls back to
+/// [`DEFAULT_TITLE_TEMPLATE`].
+pub fn render_title(template: &str, context: &TitleContext) -> String {
+    let command = context.command_summary();
+    let program = command.split_whitespace().next().unwrap_or_default();
+    let error = normalize_message(&failure_message(
+        &context.command,
+        context.exit_code,
+        &context.stderr,
+    ));
+    let stderr = context
+        .stderr
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+
+    let rendered = template
+        .replace("{command}", &command)
+        .replace("{program}", program)
+        .replace("{exit_code}", &context.exit_code.to_string())
+        .replace("{error}", &error)
+        .replace("{stderr}", stderr);
+    let title = rendered.split_whitespace().collect::<Vec<_>>().join(" ");
+    let title = title.trim_matches(|c: char| c == ':' || c == '-' || c.is_whitespace());
+    if title.is_empty() && template != DEFAULT_TITLE_TEMPLATE {
+        return render_title(DEFAULT_TITLE_TEMPLATE, context);
+    }
+    truncate(title)
+}
+
+fn truncate(title: &str) -> String {
+    if title.chars().count() <= MAX_TITLE_CHARS {
+        return title.to_string();
+    }
+    let cut: String = title.chars().take(MAX_TITLE_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+
+    fn context() -> TitleContext {
+        TitleContext {
+            command: "cargo build --release --target x86_64-unknown-linux-musl".to_string(),
+            exit_code: 101,
+            stderr: "   Compiling app v0.1.0\nerror[E0425]: cannot find value `x` in /src/main.rs"
+                .to_string(),
+        }
+    }
+
+    #[test]
+    fn test_default_template_keeps_old_titles() {
+        assert_eq!(
+            render_title(DEFAULT_TITLE_TEMPLATE, &context()),
+            "Error: cargo build --release --target x86_64-unknown-linux-musl"
+        );
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            render_title("{program} ({exit_code}): {error}", &context()),
+            "cargo (101): error[E0425]: cannot find value `x` in /path/to/file"
+        );
+        assert_eq!(render_title("{stderr}", &context()), "Compiling app v0.1.0");
+        // Nothing to show falls back to the default
+        let quiet = TitleContext {
+            stderr: String::new(),
+            ..context()
+        };
+        assert!(render_title("{stderr}", &quiet).starts_with("Error: cargo build"));
+        assert!(render_title("{error} {error} {error}", &context()).ends_with('…'));
+    }
+
+    #[test]
+    fn test_context_from_captured_note() {
+        let note = NoteBuilder::new(
+            "Error: npm test",
+            "## Command Failed\n\n```bash\n$ npm test\n\n# Error output:\nnpm ERR! missing script: test\n```\n\n**Exit Code**: 1",
+        )
+        .meta("exit_code", "1")
+        .build();
+        let context = TitleContext::from_note(&note).unwrap();
+        assert_eq!(context.command, "npm test");
+        assert_eq!(context.stderr, "npm ERR! missing script: test");
+        assert_eq!(
+            render_title("{error}", &context),
+            "npm ERR! missing script: test"
+        );
+
+        let manual = NoteBuilder::new("Proxy setup", "Set HTTPS_PROXY first").build();
+        assert!(TitleContext::from_note(&manual).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{
+    LowerCaser, RemoveLongFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
+};
+use tantivy::Index;
+
+/// Tokens longer than this many bytes are dropped, as in tantivy's default
+/// analyzer
+const MAX_TOKEN_BYTES: usize = 40;
+
+/// How note titles and bodies are split into searchable terms, set with
+/// `analyzer` under `[search]` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Analyzer {
+    /// Split on whitespace and punctuation; suits space-separated languages
+    #[default]
+    Default,
+    /// Index Chinese, Japanese and Korean text as overlapping character
+    /// pairs, and everything else like `default`
+    Cjk,
+}
+
+impl Analyzer {
+    /// Name of the tokenizer in the index schema
+    pub fn tokenizer_name(self) -> &'static str {
+        match self {
+            Analyzer::Default => "default",
+            Analyzer::Cjk => "fukura_cjk",
+        }
+    }
+
+    pub fn from_tokenizer_name(name: &str) -> Option<Self> {
+        [Analyzer::Default, Analyzer::Cjk]
+            .into_iter()
+            .find(|analyzer| analyzer.tokenizer_name() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Analyzer::Default => "default",
+            Analyzer::Cjk => "cjk",
+        }
+    }
+}
+
+/// Make the tokenizers of every analyzer available to `index`. Tantivy
+/// doesn't store custom tokenizers, so this runs whenever an index is opened.
+pub fn register_tokenizers(index: &Index) {
+    index.tokenizers().register(
+        Analyzer::Cjk.tokenizer_name(),
+        TextAnalyzer::builder(CjkTokenizer)
+            .filter(RemoveLongFilter::limit(MAX_TOKEN_BYTES))
+            .filter(LowerCaser)
+            .build(),
+    );
+}
+
+/// Splits runs of CJK characters into overlapping bigrams (`東京都` becomes
+/// `東京`, `京都`) and other text into alphanumeric words. Queries are split
+/// the same way and matched as phrases, so any two or more consecutive
+/// characters of a note can be found without a dictionary.
+#[derive(Debug, Clone, Default)]
+pub struct CjkTokenizer;
+
+pub struct CjkTokenStream {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Tokenizer for CjkTokenizer {
+    type TokenStream<'a> = CjkTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        CjkTokenStream {
+            tokens: cjk_tokens(text),
+            next: 0,
+        }
+    }
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.next < self.tokens.len() {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.next - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.next - 1]
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF     // Hiragana, Katakana
+            | 0x3400..=0x4DBF // CJK Extension A
+            | 0x4E00..=0x9FFF // CJK Unified Ideographs
+            | 0xAC00..=0xD7AF // Hangul syllables
+            | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+            | 0xFF66..=0xFF9F // Halfwidth Katakana
+            | 0x20000..=0x2FA1F // Extensions B and later
+    )
+}
+
+fn cjk_tokens(text: &str) -> Vec<Token> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let end_of = |idx: usize| chars.get(idx).map_or(text.len(), |(offset, _)| *offset);
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut push = |from: usize, to: usize| {
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position: tokens.len(),
+            text: text[from..to].to_string(),
+            position_length: 1,
+        })
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (start, c) = chars[idx];
+        if is_cjk(c) {
+            let run = idx;
+            while idx < chars.len() && is_cjk(chars[idx].1) {
+                idx += 1;
+            }
+            if idx - run == 1 {
+                push(start, end_of(idx));
+            }
+            for pair in chars[run..idx].windows(2) {
+                let (last, c) = pair[1];
+                push(pair[0].0, last + c.len_utf8());
+            }
+        } else if c.is_alphanumeric() {
+            while idx < chars.len() && chars[idx].1.is_alphanumeric() && !is_cjk(chars[idx].1) {
+                idx += 1;
+            }
+            push(start, end_of(idx));
+        } else {
+            idx += 1;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(text: &str) -> Vec<String> {
+        cjk_tokens(text)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_cjk_runs_become_bigrams() {
+        assert_eq!(
+            terms("プロキシ設定 proxy_v2 失敗"),
+            vec!["プロ", "ロキ", "キシ", "シ設", "設定", "proxy", "v2", "失敗"]
+        );
+        assert_eq!(terms("猫"), vec!["猫"]);
+    }
+
+    #[test]
+    fn test_offsets_and_positions_line_up() {
+        let text = "npm 東京都";
+        let tokens = cjk_tokens(text);
+        assert_eq!(tokens.len(), 3);
+        for (position, token) in tokens.iter().enumerate() {
+            assert_eq!(token.position, position);
+            assert_eq!(&text[token.offset_from..token.offset_to], token.text);
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_names_round_trip() {
+        for analyzer in [Analyzer::Default, Analyzer::Cjk] {
+            assert_eq!(
+                Analyzer::from_tokenizer_name(analyzer.tokenizer_name()),
+                Some(analyzer)
+            );
+        }
+    }
+}
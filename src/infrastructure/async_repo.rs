@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::{mpsc, oneshot, RwLock};
+
+use crate::models::{Note, NoteRecord};
+use crate::repo::FukuraRepo;
+
+/// Writes waiting for the writer before callers are made to wait
+const WRITE_QUEUE: usize = 64;
+
+type WriteJob = Box<dyn FnOnce(&FukuraRepo) + Send>;
+
+/// Async handle to a [`FukuraRepo`] for code running on the tokio runtime.
+/// Repository calls block on disk (objects are fsynced, index commits wait for
+/// the writer lock), so reads run on the blocking pool and writes go to one
+/// writer thread per repository. A burst of captures queues there instead of
+/// tying up runtime workers or a blocking thread each.
+#[derive(Clone)]
+pub struct AsyncRepo {
+    repo: Arc<FukuraRepo>,
+    writes: mpsc::Sender<WriteJob>,
+}
+
+impl AsyncRepo {
+    pub fn new(repo: Arc<FukuraRepo>) -> Result<Self> {
+        let (writes, mut jobs) = mpsc::channel::<WriteJob>(WRITE_QUEUE);
+        let writer = repo.clone();
+        std::thread::Builder::new()
+            .name("fukura-writer".to_string())
+            .spawn(move || {
+                // Ends once every handle is dropped
+                while let Some(job) = jobs.blocking_recv() {
+                    job(&writer);
+                }
+            })
+            .context("Failed to start repository writer")?;
+        Ok(Self { repo, writes })
+    }
+
+    /// Open the repository at `root` without blocking the runtime
+    pub async fn open(root: PathBuf) -> Result<Self> {
+        let repo = tokio::task::spawn_blocking(move || FukuraRepo::open(&root))
+            .await
+            .context("Repository open task failed")??;
+        Self::new(Arc::new(repo))
+    }
+
+    /// The wrapped repository, for calls that don't touch the disk such as
+    /// [`FukuraRepo::root`]
+    pub fn repo(&self) -> &FukuraRepo {
+        &self.repo
+    }
+
+    /// Run a read on the blocking pool
+    pub async fn read<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&FukuraRepo) -> Result<T> + Send + 'static,
+    {
+        let repo = self.repo.clone();
+        tokio::task::spawn_blocking(move || f(&repo))
+            .await
+            .context("Repository task failed")?
+    }
+
+    /// Run a write on the repository's writer thread, after the writes queued
+    /// before it
+    pub async fn write<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&FukuraRepo) -> Result<T> + Send + 'static,
+    {
+        let (done, result) = oneshot::channel();
+        self.writes
+            .send(Box::new(move |repo| {
+                let _ = done.send(f(repo));
+            }))
+            .await
+            .map_err(|_| anyhow!("Repository writer stopped"))?;
+        result
+            .await
+            .map_err(|_| anyhow!("Repository writer stopped"))?
+    }
+
+    pub async fn store_note(&self, note: Note) -> Result<NoteRecord> {
+        self.write(move |repo| repo.store_note(note)).await
+    }
+}
+
+/// Repositories opened so far, one [`AsyncRepo`] per root so each repository
+/// keeps a single writer
+#[derive(Clone, Default)]
+pub struct AsyncRepos {
+    repos: Arc<RwLock<HashMap<PathBuf, AsyncRepo>>>,
+}
+
+impl AsyncRepos {
+    /// Start with an already opened repository
+    pub fn with(repo: AsyncRepo) -> Self {
+        let root = repo.repo().root().to_path_buf();
+        Self {
+            repos: Arc::new(RwLock::new(HashMap::from([(root, repo)]))),
+        }
+    }
+
+    /// The repository containing `start`, as [`FukuraRepo::discover`] finds
+    /// it, opened on first use
+    pub async fn discover(&self, start: Option<&Path>) -> Result<AsyncRepo> {
+        let root = FukuraRepo::find_root(start)?;
+        if let Some(repo) = self.repos.read().await.get(&root) {
+            return Ok(repo.clone());
+        }
+        let opened = AsyncRepo::open(root.clone()).await?;
+        Ok(self
+            .repos
+            .write()
+            .await
+            .entry(root)
+            .or_insert(opened)
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_burst_of_writes_is_serialized() {
+        let temp_dir = TempDir::new().unwrap();
+        FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let repos = AsyncRepos::default();
+        let repo = repos.discover(Some(temp_dir.path())).await.unwrap();
+
+        let writes: Vec<_> = (0..20)
+            .map(|i| {
+                let repo = repo.clone();
+                let note =
+                    NoteBuilder::new(format!("Error {}", i), "npm ERR! code ECONNRESET").build();
+                tokio::spawn(async move { repo.store_note(note).await })
+            })
+            .collect();
+        for write in writes {
+            write.await.unwrap().unwrap();
+        }
+
+        let same = repos.discover(Some(temp_dir.path())).await.unwrap();
+        assert!(Arc::ptr_eq(&repo.repo, &same.repo));
+        let count = same
+            .read(|repo| Ok(repo.list_all_notes()?.len()))
+            .await
+            .unwrap();
+        assert_eq!(count, 20);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// File under `.fukura` holding the audit log
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+/// A kind of change recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    /// New notes stored, including imports and captures
+    Add,
+    /// A note replaced by an edited version
+    Edit,
+    Delete,
+    /// A note retired into another by `fuku merge` or `fuku dedupe`
+    Merge,
+    /// Notes moved to the archive, by `fuku archive` or once expired
+    Archive,
+    /// Archived notes brought back by `fuku unarchive`
+    Unarchive,
+    Push,
+    Pull,
+    /// A public copy of a note published by `fuku share`
+    Share,
+    /// A shared copy taken down by `fuku share --revoke`
+    Unshare,
+    Config,
+    /// Redaction rules changed
+    Redaction,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Add => "add",
+            AuditAction::Edit => "edit",
+            AuditAction::Delete => "delete",
+            AuditAction::Merge => "merge",
+            AuditAction::Archive => "archive",
+            AuditAction::Unarchive => "unarchive",
+            AuditAction::Push => "push",
+            AuditAction::Pull => "pull",
+            AuditAction::Share => "share",
+            AuditAction::Unshare => "unshare",
+            AuditAction::Config => "config",
+            AuditAction::Redaction => "redaction",
+        }
+    }
+}
+
+/// One change: who made it, when, and to which objects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub action: AuditAction,
+    /// Objects the change touched; for edits the previous id comes first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry for a change made now by this user
+    pub fn new(action: AuditAction, object_ids: Vec<String>) -> Self {
+        Self {
+            at: Utc::now(),
+            actor: actor().to_string(),
+            action,
+            object_ids,
+            detail: None,
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Name changes are recorded under, resolved once per process
+pub fn actor() -> &'static str {
+    static ACTOR: Lazy<String> = Lazy::new(|| {
+        let author = crate::note_service::default_author();
+        match author.email {
+            Some(email) => format!("{} <{}>", author.name, email),
+            None => author.name,
+        }
+    });
+    &ACTOR
+}
+
+/// Append-only record of every change to a repository, one JSON object
+/// per line in `.fukura/audit.jsonl`. Entries are only ever appended;
+/// nothing in fukura rewrites or truncates the file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// The log of the repository whose `.fukura` directory is `dot_dir`
+    pub fn new(dot_dir: &Path) -> Self {
+        Self {
+            path: dot_dir.join(AUDIT_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry, holding an exclusive lock so the daemon, the server
+    /// and the CLI can write at once
+    pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock_exclusive()?;
+        let result = file.write_all(line.as_bytes());
+        let _ = FileExt::unlock(&file);
+        result.with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Entries at or after `since`, oldest first; corrupt lines are skipped
+    pub fn read_since(&self, since: Option<DateTime<Utc>>) -> Result<Vec<AuditEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(reader
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+            .filter(|entry| since.map_or(true, |since| entry.at >= since))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_appends_and_reads_since() {
+        let temp = TempDir::new().unwrap();
+        let log = AuditLog::new(temp.path());
+        let mut old = AuditEntry::new(AuditAction::Add, vec!["a".repeat(64)]);
+        old.at -= Duration::days(2);
+        log.append(&old).unwrap();
+        log.append(
+            &AuditEntry::new(AuditAction::Edit, vec!["a".repeat(64), "b".repeat(64)])
+                .detail("title"),
+        )
+        .unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(log.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let all = log.read_since(None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], old);
+        let recent = log
+            .read_since(Some(Utc::now() - Duration::days(1)))
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].action, AuditAction::Edit);
+        assert_eq!(recent[0].actor, actor());
+        assert_eq!(recent[0].detail.as_deref(), Some("title"));
+    }
+}
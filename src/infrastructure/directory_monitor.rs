@@ -79,9 +79,7 @@ impl DirectoryMonitor {
         }
 
         // Add common development directories
-        if let Ok(home) = std::env::var("HOME") {
-            let home_path = std::path::PathBuf::from(home);
-
+        if let Ok(home_path) = crate::paths::home_dir() {
             // Common development directories
             let dev_dirs = [
                 "projects",
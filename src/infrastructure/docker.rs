@@ -0,0 +1,147 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::process::Command;
+
+use crate::log_process::LogProcess;
+
+/// A container that stopped, from `docker events`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerExit {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub exit_code: i32,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "Action", default)]
+    action: String,
+    #[serde(rename = "Actor")]
+    actor: Actor,
+}
+
+#[derive(Deserialize)]
+struct Actor {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "Attributes", default)]
+    attributes: HashMap<String, String>,
+}
+
+/// Parse a `docker events --format '{{json .}}'` line, keeping only
+/// containers that died
+pub fn parse_die_event(line: &str) -> Option<ContainerExit> {
+    let event: Event = serde_json::from_str(line).ok()?;
+    if event.action != "die" {
+        return None;
+    }
+    let attributes = &event.actor.attributes;
+    Some(ContainerExit {
+        name: attributes
+            .get("name")
+            .cloned()
+            .unwrap_or_else(|| event.actor.id.chars().take(12).collect()),
+        image: attributes.get("image").cloned().unwrap_or_default(),
+        exit_code: attributes
+            .get("exitCode")
+            .and_then(|code| code.parse().ok())
+            .unwrap_or_default(),
+        id: event.actor.id,
+    })
+}
+
+/// The `docker` CLI. Talking to the CLI rather than the engine API keeps
+/// contexts, remote hosts and credentials working as configured.
+pub struct Docker {
+    program: String,
+}
+
+impl Default for Docker {
+    fn default() -> Self {
+        Self {
+            program: "docker".to_string(),
+        }
+    }
+}
+
+impl Docker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Image a container was created from
+    pub fn image_of(&self, container: &str) -> Result<String> {
+        let output = Command::new(&self.program)
+            .args(["inspect", "--format", "{{.Config.Image}}", container])
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "docker inspect {} failed: {}",
+                container,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Log lines of a container; with `follow`, keeps streaming new lines
+    pub fn logs(
+        &self,
+        container: &str,
+        follow: bool,
+        since: Option<&str>,
+        tail: Option<usize>,
+    ) -> Result<LogProcess> {
+        let mut command = Command::new(&self.program);
+        command.arg("logs");
+        if follow {
+            command.arg("--follow");
+        }
+        if let Some(since) = since {
+            command.args(["--since", since]);
+        }
+        if let Some(tail) = tail {
+            command.args(["--tail", &tail.to_string()]);
+        }
+        command.arg(container);
+        LogProcess::spawn(command)
+    }
+
+    /// Containers as they die, until interrupted
+    pub fn exits(&self) -> Result<impl Iterator<Item = ContainerExit>> {
+        let mut command = Command::new(&self.program);
+        command.args([
+            "events",
+            "--filter",
+            "type=container",
+            "--filter",
+            "event=die",
+            "--format",
+            "{{json .}}",
+        ]);
+        Ok(LogProcess::spawn(command)?.filter_map(|line| parse_die_event(&line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_die_event() {
+        let line = r#"{"status":"die","id":"4f2a9c1e7b3d","from":"api-server:1.4","Type":"container","Action":"die","Actor":{"ID":"4f2a9c1e7b3d","Attributes":{"exitCode":"137","image":"api-server:1.4","name":"api"}},"scope":"local","time":1718000000}"#;
+        assert_eq!(
+            parse_die_event(line),
+            Some(ContainerExit {
+                id: "4f2a9c1e7b3d".to_string(),
+                name: "api".to_string(),
+                image: "api-server:1.4".to_string(),
+                exit_code: 137,
+            })
+        );
+        assert!(parse_die_event(&line.replace("\"die\"", "\"start\"")).is_none());
+        assert!(parse_die_event("not json").is_none());
+    }
+}
@@ -166,7 +166,7 @@ impl FileWatcher {
     /// Calculate diff for a file change (if possible)
     pub async fn calculate_diff(
         &self,
-        _path: &Path,
+        path: &Path,
         change_type: &FileChangeType,
     ) -> Option<(String, usize, usize)> {
         // Only calculate diff for modifications
@@ -174,12 +174,44 @@ impl FileWatcher {
             return None;
         }
 
-        // TODO: Implement diff calculation
-        // This would integrate with git diff or implement a simple line-based diff
-        // For now, return None
+        git_diff(path.parent()?, path)
+    }
+}
+
+/// Working-tree diff of `path` against git's index, with added/removed line counts.
+/// Returns `None` outside a git repository or when the file is unchanged.
+pub fn git_diff(repo_root: &Path, path: &Path) -> Option<(String, usize, usize)> {
+    let output = std::process::Command::new("git")
+        .args(["diff", "--no-color", "--no-ext-diff", "--"])
+        .arg(path)
+        .current_dir(repo_root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
 
-        None
+    let diff = String::from_utf8_lossy(&output.stdout).to_string();
+    if diff.trim().is_empty() {
+        return None;
     }
+    let (added, removed) = count_diff_lines(&diff);
+    Some((diff, added, removed))
+}
+
+/// Count added and removed lines in a unified diff, ignoring file headers
+fn count_diff_lines(diff: &str) -> (usize, usize) {
+    diff.lines()
+        .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+        .fold((0, 0), |(added, removed), line| {
+            if line.starts_with('+') {
+                (added + 1, removed)
+            } else if line.starts_with('-') {
+                (added, removed + 1)
+            } else {
+                (added, removed)
+            }
+        })
 }
 
 impl Drop for FileWatcher {
@@ -219,6 +251,13 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_count_diff_lines() {
+        let diff =
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,2 +1,2 @@\n-old\n+new\n+extra\n context\n";
+        assert_eq!(count_diff_lines(diff), (2, 1));
+    }
+
     #[test]
     fn test_hidden_file_exclusion() {
         assert!(FileWatcher::should_exclude(Path::new("/tmp/.hidden"), &[]));
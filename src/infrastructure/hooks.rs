@@ -1,8 +1,29 @@
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Bumped whenever the generated hooks change how they talk to the daemon,
+/// so hooks installed by an older version can be found and regenerated.
+/// Hooks from before stamps count as version 1.
+pub const HOOK_VERSION: u32 = 2;
+
+const VERSION_STAMP: &str = "# fukura-hooks-version:";
+const REPO_STAMP: &str = "# fukura-hooks-repo:";
+
+/// Last two lines of the bash and zsh hooks from before end markers
+const LEGACY_ENDINGS: [(&str, [&str; 2]); 2] = [
+    (
+        "bash",
+        [
+            "    PROMPT_COMMAND=\"${PROMPT_COMMAND}; _fukura_record_command\"",
+            "fi",
+        ],
+    ),
+    ("zsh", ["#     tee -a \"$_fukura_stderr_file\" >&2", "# }"]),
+];
+
 /// Shell hook installation and management
 pub struct HookManager {
     repo_path: PathBuf,
@@ -16,6 +37,23 @@ pub enum ShellType {
     PowerShell,
 }
 
+/// Hooks found in the startup file of one shell
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstalledHooks {
+    pub shell: String,
+    pub path: PathBuf,
+    pub version: u32,
+    /// Repository whose daemon the hooks report to; fish hooks work with any
+    pub repo: Option<PathBuf>,
+}
+
+impl InstalledHooks {
+    /// Whether these hooks speak the protocol of this version
+    pub fn is_current(&self) -> bool {
+        self.version == HOOK_VERSION
+    }
+}
+
 impl HookManager {
     pub fn new(repo_path: &Path) -> Self {
         Self {
@@ -63,6 +101,64 @@ impl HookManager {
         }
     }
 
+    /// Hooks installed for bash, zsh and fish, whatever the current shell
+    pub fn installed(&self) -> Vec<InstalledHooks> {
+        let files = [
+            ("bash", self.get_bashrc_path().ok()),
+            ("zsh", self.get_zshrc_path().ok()),
+            (
+                "fish",
+                self.get_fish_config_dir()
+                    .ok()
+                    .map(|dir| dir.join("fukura_hooks.fish")),
+            ),
+        ];
+        files
+            .into_iter()
+            .filter_map(|(shell, path)| {
+                let path = path?;
+                let content = fs::read_to_string(&path).ok()?;
+                let (version, repo) = read_hooks(&content, shell)?;
+                Some(InstalledHooks {
+                    shell: shell.to_string(),
+                    path,
+                    version,
+                    repo,
+                })
+            })
+            .collect()
+    }
+
+    /// Rewrite installed hooks made by another version with the current
+    /// ones, leaving hooks that report to another repository to its daemon.
+    /// Returns the hooks that were replaced.
+    pub fn regenerate(&self) -> Result<Vec<InstalledHooks>> {
+        let mut replaced = Vec::new();
+        for hooks in self.installed() {
+            if hooks.is_current()
+                || hooks
+                    .repo
+                    .as_ref()
+                    .is_some_and(|repo| *repo != self.repo_path)
+            {
+                continue;
+            }
+            match hooks.shell.as_str() {
+                "bash" => {
+                    self.remove_hook_from_file(&hooks.path, "bash")?;
+                    self.append_to_file(&hooks.path, &self.generate_bash_hook())?;
+                }
+                "zsh" => {
+                    self.remove_hook_from_file(&hooks.path, "zsh")?;
+                    self.append_to_file(&hooks.path, &self.generate_zsh_hook())?;
+                }
+                _ => fs::write(&hooks.path, self.generate_fish_hook())?,
+            }
+            replaced.push(hooks);
+        }
+        Ok(replaced)
+    }
+
     fn detect_shell(&self) -> Result<ShellType> {
         let shell = std::env::var("SHELL")
             .or_else(|_| std::env::var("SHELL_NAME"))
@@ -140,14 +236,14 @@ impl HookManager {
 
     fn uninstall_bash_hooks(&self) -> Result<()> {
         let bashrc_path = self.get_bashrc_path()?;
-        self.remove_hook_from_file(&bashrc_path, "fukura")?;
+        self.remove_hook_from_file(&bashrc_path, "bash")?;
         println!(" Uninstalled Fukura hooks for bash");
         Ok(())
     }
 
     fn uninstall_zsh_hooks(&self) -> Result<()> {
         let zshrc_path = self.get_zshrc_path()?;
-        self.remove_hook_from_file(&zshrc_path, "fukura")?;
+        self.remove_hook_from_file(&zshrc_path, "zsh")?;
         println!(" Uninstalled Fukura hooks for zsh");
         Ok(())
     }
@@ -195,24 +291,21 @@ impl HookManager {
     // Helper methods for file paths
 
     fn get_bashrc_path(&self) -> Result<std::path::PathBuf> {
-        let home = std::env::var("HOME").context("HOME environment variable not set")?;
-        Ok(std::path::PathBuf::from(home).join(".bashrc"))
+        Ok(crate::paths::home_dir()?.join(".bashrc"))
     }
 
     fn get_zshrc_path(&self) -> Result<std::path::PathBuf> {
-        let home = std::env::var("HOME").context("HOME environment variable not set")?;
-        Ok(std::path::PathBuf::from(home).join(".zshrc"))
+        Ok(crate::paths::home_dir()?.join(".zshrc"))
     }
 
     fn get_fish_config_dir(&self) -> Result<std::path::PathBuf> {
         let config_dir = if cfg!(target_os = "windows") {
-            std::env::var("APPDATA")?
+            std::path::PathBuf::from(std::env::var("APPDATA")?)
         } else {
-            std::env::var("XDG_CONFIG_HOME")
-                .or_else(|_| std::env::var("HOME").map(|h| format!("{}/.config", h)))?
+            crate::paths::user_config_home()?
         };
 
-        Ok(std::path::PathBuf::from(config_dir).join("fish"))
+        Ok(config_dir.join("fish"))
     }
 
     fn get_powershell_profile_path(&self) -> Result<std::path::PathBuf> {
@@ -253,26 +346,7 @@ impl HookManager {
         }
 
         let content = fs::read_to_string(file_path)?;
-        let lines: Vec<&str> = content.lines().collect();
-        let mut new_lines = Vec::new();
-        let mut in_hook_section = false;
-
-        for line in lines {
-            if line.contains(&format!("# Fukura hooks - {}", hook_name)) {
-                in_hook_section = true;
-                continue;
-            }
-
-            if in_hook_section && line.trim().is_empty() && !line.contains("# Fukura hooks") {
-                in_hook_section = false;
-            }
-
-            if !in_hook_section {
-                new_lines.push(line);
-            }
-        }
-
-        fs::write(file_path, new_lines.join("\n"))?;
+        fs::write(file_path, strip_hook(&content, hook_name))?;
         Ok(())
     }
 
@@ -282,8 +356,16 @@ impl HookManager {
         format!(
             r#"
 # Fukura hooks - bash (World-class IPC via Unix Domain Socket)
+{stamp} {version}
+{repo_stamp} {repo}
 _fukura_socket_path="{socket_path}"
 _fukura_last_command=""
+_fukura_started=""
+
+# Fields are base64 encoded so newlines, unicode and '|' survive the trip
+_fukura_b64() {{
+    printf '%s' "$1" | base64 | tr -d '\n'
+}}
 
 _fukura_record_command() {{
     local exit_code=$?
@@ -291,13 +373,25 @@ _fukura_record_command() {{
     local working_dir="$PWD"
     local session_id=$(echo "$PWD" | md5sum 2>/dev/null | cut -d' ' -f1 || echo "default")
     
+    # Wall-clock duration in milliseconds (EPOCHREALTIME needs bash 5)
+    local duration_ms=""
+    if [ -n "$_fukura_started" ] && [ -n "$EPOCHREALTIME" ]; then
+        duration_ms=$(( (${{EPOCHREALTIME/[.,]/}} - _fukura_started) / 1000 ))
+    fi
+    _fukura_started=""
+    
     if [ -n "$command" ] && [ -S "$_fukura_socket_path" ]; then
-        echo "$session_id|$command|$exit_code|$working_dir" | nc -U -w 1 "$_fukura_socket_path" 2>/dev/null || true
+        printf 'b64|%s|%s|%s|%s||%s\n' "$(_fukura_b64 "$session_id")" "$(_fukura_b64 "$command")" "$exit_code" "$(_fukura_b64 "$working_dir")" "$duration_ms" | nc -U -w 1 "$_fukura_socket_path" 2>/dev/null || true
     fi
 }}
 
 _fukura_preexec() {{
+    [ "$BASH_COMMAND" = "_fukura_record_command" ] && return
     _fukura_last_command="$BASH_COMMAND"
+    # The DEBUG trap fires for every simple command; time from the first one
+    if [ -z "$_fukura_started" ] && [ -n "$EPOCHREALTIME" ]; then
+        _fukura_started="${{EPOCHREALTIME/[.,]/}}"
+    fi
 }}
 
 # Hook into command execution
@@ -309,8 +403,13 @@ if [[ -z "$PROMPT_COMMAND" ]]; then
 else
     PROMPT_COMMAND="${{PROMPT_COMMAND}}; _fukura_record_command"
 fi
+# End of Fukura hooks - bash
 "#,
-            socket_path = self.repo_path.join(".fukura").join("daemon.sock").display()
+            stamp = VERSION_STAMP,
+            version = HOOK_VERSION,
+            repo_stamp = REPO_STAMP,
+            repo = self.repo_path.display(),
+            socket_path = crate::ipc::repo_socket_path(&self.repo_path).display()
         )
     }
 
@@ -318,8 +417,17 @@ fi
         format!(
             r#"
 # Fukura hooks - zsh (World-class: captures commands, errors, and stderr)
+{stamp} {version}
+{repo_stamp} {repo}
 _fukura_socket_path="{socket_path}"
 _fukura_stderr_file="/tmp/fukura_stderr_$$"
+_fukura_started=""
+zmodload zsh/datetime 2>/dev/null
+
+# Fields are base64 encoded so newlines, unicode and '|' survive the trip
+_fukura_b64() {{
+    printf '%s' "$1" | base64 | tr -d '\n'
+}}
 
 _fukura_record_command() {{
     local exit_code=$?
@@ -343,11 +451,24 @@ _fukura_record_command() {{
         rm -f "$_fukura_stderr_file"
     fi
     
+    # Wall-clock duration in milliseconds
+    local duration_ms=""
+    if [ -n "$_fukura_started" ] && [ -n "$EPOCHREALTIME" ]; then
+        duration_ms=$(( int((EPOCHREALTIME - _fukura_started) * 1000) ))
+    fi
+    _fukura_started=""
+    
     # Send to daemon via Unix socket (fast & secure)
     if [ -S "$_fukura_socket_path" ]; then
-        # Format: session_id|command|exit_code|working_dir|stderr
-        local message="$session_id|$command|$exit_code|$working_dir|$stderr_content"
-        echo "$message" | nc -U -w 1 "$_fukura_socket_path" 2>/dev/null || true
+        # Format: b64|session_id|command|exit_code|working_dir|stderr|duration_ms (see ipc.rs)
+        printf 'b64|%s|%s|%s|%s|%s|%s\n' \
+            "$(_fukura_b64 "$session_id")" \
+            "$(_fukura_b64 "$command")" \
+            "$exit_code" \
+            "$(_fukura_b64 "$working_dir")" \
+            "$(_fukura_b64 "$stderr_content")" \
+            "$duration_ms" \
+            | nc -U -w 1 "$_fukura_socket_path" 2>/dev/null || true
     fi
 }}
 
@@ -357,6 +478,7 @@ precmd_functions+=(_fukura_precmd_hook)
 
 _fukura_preexec_hook() {{
     _fukura_last_command="$1"
+    _fukura_started="$EPOCHREALTIME"
     # Prepare stderr capture for next command
     rm -f "$_fukura_stderr_file"
 }}
@@ -377,14 +499,21 @@ _fukura_precmd_hook() {{
 # _fukura_tee_stderr() {{
 #     tee -a "$_fukura_stderr_file" >&2
 # }}
+# End of Fukura hooks - zsh
 "#,
-            socket_path = self.repo_path.join(".fukura").join("daemon.sock").display()
+            stamp = VERSION_STAMP,
+            version = HOOK_VERSION,
+            repo_stamp = REPO_STAMP,
+            repo = self.repo_path.display(),
+            socket_path = crate::ipc::repo_socket_path(&self.repo_path).display()
         )
     }
 
     fn generate_fish_hook(&self) -> String {
-        r#"
+        format!(
+            r#"
 # Fukura hooks - fish
+{VERSION_STAMP} {HOOK_VERSION}
 function _fukura_record_command --on-event fish_prompt
     set -l exit_code $status
     set -l command (history | head -n1)
@@ -401,7 +530,8 @@ function _fukura_record_error --on-event fish_postexec
         fukura daemon record-error (pwd | tr '/' '_') "Command failed with exit code $exit_code" "fish" 2>/dev/null || true
     end
 end
-"#.to_string()
+"#
+        )
     }
 
     fn generate_powershell_hook(&self) -> String {
@@ -432,6 +562,88 @@ function Invoke-Expression {{
     }
 }
 
+/// Version and repository of the `shell` hooks in `content`, if they are
+/// there. Hooks from before stamps only name the repository in the path of
+/// the socket they wrote to.
+fn read_hooks(content: &str, shell: &str) -> Option<(u32, Option<PathBuf>)> {
+    let header = format!("# Fukura hooks - {}", shell);
+    let mut lines = content.lines().skip_while(|line| !line.contains(&header));
+    lines.next()?;
+    let head: Vec<&str> = lines.take(3).collect();
+    let stamp = |prefix: &str| {
+        head.iter()
+            .find_map(|line| line.strip_prefix(prefix))
+            .map(str::trim)
+    };
+    match stamp(VERSION_STAMP) {
+        Some(version) => Some((
+            version.parse().unwrap_or(1),
+            stamp(REPO_STAMP).map(PathBuf::from),
+        )),
+        None => Some((
+            1,
+            stamp("_fukura_socket_path=")
+                .and_then(|path| path.trim_matches('"').strip_suffix("/.fukura/daemon.sock"))
+                .map(PathBuf::from),
+        )),
+    }
+}
+
+/// `content` without the `shell` hooks: from their header to their end
+/// marker, or for hooks from before end markers, to the last line those
+/// hooks had
+fn strip_hook(content: &str, shell: &str) -> String {
+    let header = format!("# Fukura hooks - {}", shell);
+    let end = format!("# End of Fukura hooks - {}", shell);
+    let legacy_ending = LEGACY_ENDINGS
+        .iter()
+        .find(|(name, _)| *name == shell)
+        .map(|(_, ending)| ending);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut kept = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        if !lines[index].contains(&header) {
+            kept.push(lines[index]);
+            index += 1;
+            continue;
+        }
+        // The blank lines `append_to_file` put before the hooks go too
+        while kept.last().is_some_and(|line| line.trim().is_empty()) {
+            kept.pop();
+        }
+        let next = lines[index + 1..]
+            .iter()
+            .position(|line| line.contains(&header))
+            .map_or(lines.len(), |offset| index + 1 + offset);
+        let block = &lines[index..next];
+        let len = match block.iter().position(|line| *line == end) {
+            // The hook ends with a newline, which leaves a blank line after
+            // the end marker
+            Some(last)
+                if block
+                    .get(last + 1)
+                    .is_some_and(|line| line.trim().is_empty()) =>
+            {
+                last + 2
+            }
+            Some(last) => last + 1,
+            None => legacy_ending
+                .and_then(|ending| block.windows(2).position(|pair| pair == ending))
+                .map(|last| last + 2)
+                // Hooks we don't know the end of stop at the first blank line
+                .or_else(|| block.iter().position(|line| line.trim().is_empty()))
+                .unwrap_or(block.len()),
+        };
+        index += len;
+    }
+    let mut stripped = kept.join("\n");
+    if content.ends_with('\n') && !stripped.is_empty() {
+        stripped.push('\n');
+    }
+    stripped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -468,5 +680,57 @@ mod tests {
         // Check that hook uses IPC (Unix socket)
         assert!(zsh_hook.contains("nc -U"));
         assert!(zsh_hook.contains("zsh"));
+
+        // Payload fields are encoded so commands can contain '|' and newlines
+        assert!(bash_hook.contains("printf 'b64|"));
+        assert!(zsh_hook.contains("printf 'b64|"));
+
+        // Both report how long the command took
+        assert!(bash_hook.contains("${EPOCHREALTIME/[.,]/}"));
+        assert!(zsh_hook.contains("zmodload zsh/datetime"));
+    }
+
+    #[test]
+    fn test_stamped_hooks_are_stripped_whole() {
+        let manager = HookManager::new(Path::new("/work/app"));
+        let rc = format!(
+            "export A=1\n\n{}\nalias ll=ls\n",
+            manager.generate_bash_hook()
+        );
+        assert_eq!(
+            read_hooks(&rc, "bash"),
+            Some((HOOK_VERSION, Some(PathBuf::from("/work/app"))))
+        );
+        assert_eq!(read_hooks(&rc, "zsh"), None);
+        assert_eq!(strip_hook(&rc, "bash"), "export A=1\nalias ll=ls\n");
+    }
+
+    #[test]
+    fn test_hooks_from_before_stamps_are_version_one() {
+        let manager = HookManager::new(Path::new("/work/app"));
+        for (shell, hook) in [
+            ("bash", manager.generate_bash_hook()),
+            ("zsh", manager.generate_zsh_hook()),
+        ] {
+            let legacy: Vec<String> = hook
+                .lines()
+                .filter(|line| {
+                    !line.starts_with("# fukura-hooks-") && !line.starts_with("# End of")
+                })
+                .map(|line| {
+                    if line.starts_with("_fukura_socket_path=") {
+                        "_fukura_socket_path=\"/work/app/.fukura/daemon.sock\"".to_string()
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect();
+            let rc = format!("export A=1\n{}\nalias ll=ls\n", legacy.join("\n"));
+            assert_eq!(
+                read_hooks(&rc, shell),
+                Some((1, Some(PathBuf::from("/work/app"))))
+            );
+            assert_eq!(strip_hook(&rc, shell), "export A=1\nalias ll=ls\n");
+        }
     }
 }
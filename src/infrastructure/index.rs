@@ -1,17 +1,32 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use tantivy::collector::TopDocs;
-use tantivy::query::{AllQuery, QueryParser};
-use tantivy::schema::{Field, Schema, SchemaBuilder, Value, FAST, STORED, STRING, TEXT};
-use tantivy::{DocAddress, Index, TantivyDocument};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, RegexQuery, TermQuery,
+};
+use tantivy::schema::{
+    Facet, FacetOptions, Field, FieldType, IndexRecordOption, OwnedValue, Schema, SchemaBuilder,
+    TextFieldIndexing, TextOptions, Value, FAST, INDEXED, STORED, STRING, TEXT,
+};
+use tantivy::snippet::{Snippet, SnippetGenerator};
+use tantivy::{
+    DocAddress, Index, IndexReader, IndexWriter, Order, ReloadPolicy, Searcher, TantivyDocument,
+    Term,
+};
 
+use crate::analyzer::{self, Analyzer};
+use crate::config::{FukuraConfig, SCOPE_META};
+use crate::locks::RepoLock;
 use crate::models::NoteRecord;
 use crate::repo::FukuraRepo;
+use crate::taxonomy::ErrorTaxonomy;
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum, Serialize, Deserialize)]
 pub enum SearchSort {
@@ -34,12 +49,168 @@ pub struct SearchHit {
     pub likes: u32,
     pub score: f32,
     pub privacy: String,
+    /// Pinned with `fuku pin`; set by [`FukuraRepo`] rather than the index
+    #[serde(default)]
+    pub pinned: bool,
+    /// Signed by a trusted key; set by [`FukuraRepo::mark_verified`]
+    #[serde(default)]
+    pub verified: bool,
+    /// Body text around the matched terms, for searches with a query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<HitSnippet>,
+    /// Package of a monorepo the note was made in; see
+    /// [`ScopeConfig`](crate::config::ScopeConfig)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
 
+/// Longest body excerpt shown with a hit
+const SNIPPET_CHARS: usize = 160;
+
+/// Excerpt of a note body with the matched terms marked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitSnippet {
+    pub text: String,
+    /// Byte ranges of the matched terms in `text`, in order
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl HitSnippet {
+    /// `text` split into runs, each flagged whether it is a matched term
+    pub fn segments(&self) -> Vec<(&str, bool)> {
+        let mut segments = Vec::new();
+        let mut position = 0;
+        for &(start, end) in &self.highlights {
+            if start > position {
+                segments.push((&self.text[position..start], false));
+            }
+            segments.push((&self.text[start..end], true));
+            position = end;
+        }
+        if position < self.text.len() {
+            segments.push((&self.text[position..], false));
+        }
+        segments
+    }
+}
+
+impl From<Snippet> for HitSnippet {
+    fn from(snippet: Snippet) -> Self {
+        Self {
+            highlights: snippet
+                .highlighted()
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect(),
+            // Fragments keep the body's line breaks; a space is as wide, so the
+            // ranges still hold
+            text: snippet.fragment().replace(['\n', '\r'], " "),
+        }
+    }
+}
+
+impl SearchHit {
+    /// Hit for a note loaded outside of a search
+    pub fn from_record(record: &NoteRecord) -> Self {
+        Self {
+            object_id: record.object_id.clone(),
+            title: record.note.title.clone(),
+            tags: record.note.tags.clone(),
+            summary: make_summary(&record.note.body),
+            updated_at: record.note.updated_at,
+            author: record.note.author.name.clone(),
+            likes: total_likes(&record.note),
+            score: 0.0,
+            privacy: format_privacy(&record.note.privacy),
+            pinned: false,
+            verified: false,
+            snippet: None,
+            scope: record.note.meta.get(SCOPE_META).cloned(),
+        }
+    }
+}
+
+/// One page of search results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    /// Matches across all pages
+    pub total: usize,
+    /// Position of the first hit among all matches
+    pub offset: usize,
+    /// Offset of the next page; `None` on the last page
+    pub next_offset: Option<usize>,
+}
+
+/// Restrictions on a search, applied by the index alongside the query
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    /// Exact meta values, all of which must match
+    pub meta: Vec<(String, String)>,
+    /// Exact meta values, none of which may match
+    pub exclude_meta: Vec<(String, String)>,
+    /// Package of a monorepo, matching notes scoped to it or to a package
+    /// inside it
+    pub scope: Option<String>,
+}
+
+impl SearchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.created_after.is_none()
+            && self.created_before.is_none()
+            && self.updated_after.is_none()
+            && self.updated_before.is_none()
+            && self.meta.is_empty()
+            && self.exclude_meta.is_empty()
+            && self.scope.is_none()
+    }
+}
+
+/// Move pinned hits to the front, keeping the order within each group
+pub fn pinned_first(hits: &mut [SearchHit]) {
+    hits.sort_by_key(|hit| !hit.pinned);
+}
+
+/// Memory budget for an index writer
+const WRITER_HEAP_BYTES: usize = 50_000_000;
+
+/// Version of [`build_schema`]. Bump it with every schema change: indexes
+/// built with another version are rebuilt when the repository is opened.
+pub const SCHEMA_VERSION: u32 = 2;
+/// File in an index directory holding the [`SCHEMA_VERSION`] it was built with
+const SCHEMA_VERSION_FILE: &str = "fukura-schema";
+
+/// Name of the repository lock held while writing the index
+const INDEX_LOCK: &str = "index";
+/// Lock held while writing the archive of expired notes
+const ARCHIVE_LOCK: &str = "archive";
+
+/// Handle to the tantivy index of a repository. Cheap to clone; clones share
+/// the reader and the writer lock.
 #[derive(Clone)]
 pub struct SearchIndex {
     index: Index,
     fields: Fields,
+    reader: IndexReader,
+    /// Directory of the repository locks; writers across processes (CLI and
+    /// daemon) take the `index` (or `archive`) lock
+    locks_dir: PathBuf,
+    lock: &'static str,
+    /// Serializes writers within this process
+    write_guard: Arc<Mutex<()>>,
+}
+
+impl std::fmt::Debug for SearchIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchIndex")
+            .field("locks_dir", &self.locks_dir)
+            .field("lock", &self.lock)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(Clone)]
@@ -53,29 +224,216 @@ struct Fields {
     likes: Field,
     author: Field,
     privacy: Field,
+    /// Note meta as a JSON object, for `meta.<key>:<value>` queries. Missing
+    /// from indexes created before format v3 until they are rebuilt.
+    meta: Option<Field>,
+    /// Creation time for date filters; missing before format v5
+    created_at: Option<Field>,
+    /// Meta as `/<key>/<value>` facets for exact filters; missing before
+    /// format v5
+    meta_facets: Option<Field>,
+    /// [`ErrorTaxonomy`] fields for `exit_code:1` or `binary:terraform`
+    /// searches; missing before format v6
+    taxonomy: Option<TaxonomyFields>,
+    /// Meta `scope` as a term of its own, for prefix filters and hits;
+    /// missing before schema version 2
+    scope: Option<Field>,
+}
+
+#[derive(Clone)]
+struct TaxonomyFields {
+    exit_code: Field,
+    binary: Field,
+    language: Field,
+    error_class: Field,
 }
 
 impl SearchIndex {
+    /// Open the index of `repo`. Prefer [`FukuraRepo::index`], which keeps one
+    /// handle per repository instead of reopening the index on every call.
     pub fn open_or_create(repo: &FukuraRepo) -> Result<Self> {
-        let path = repo.index_dir();
-        Self::open_or_create_in(path)
+        Self::open_or_create_in(
+            repo.index_dir(),
+            repo.locks_dir(),
+            INDEX_LOCK,
+            configured_analyzer(repo)?,
+        )
+    }
+
+    /// Open the index of archived (expired) notes. Prefer
+    /// [`FukuraRepo::archive`].
+    pub fn open_or_create_archive(repo: &FukuraRepo) -> Result<Self> {
+        Self::open_or_create_in(
+            repo.archive_dir(),
+            repo.locks_dir(),
+            ARCHIVE_LOCK,
+            configured_analyzer(repo)?,
+        )
     }
 
-    fn open_or_create_in(path: PathBuf) -> Result<Self> {
+    /// Replace the index of `repo` with an empty one using the current
+    /// schema and configured analyzer. Handles to the old index must be
+    /// dropped first.
+    pub fn recreate(repo: &FukuraRepo) -> Result<Self> {
+        Self::recreate_in(
+            repo.index_dir(),
+            repo.locks_dir(),
+            INDEX_LOCK,
+            configured_analyzer(repo)?,
+        )
+    }
+
+    /// [`SearchIndex::recreate`] for the archive
+    pub fn recreate_archive(repo: &FukuraRepo) -> Result<Self> {
+        Self::recreate_in(
+            repo.archive_dir(),
+            repo.locks_dir(),
+            ARCHIVE_LOCK,
+            configured_analyzer(repo)?,
+        )
+    }
+
+    fn recreate_in(
+        path: PathBuf,
+        locks_dir: PathBuf,
+        lock: &'static str,
+        analyzer: Analyzer,
+    ) -> Result<Self> {
+        {
+            let _lock = RepoLock::exclusive(&locks_dir, lock)?;
+            if path.exists() {
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Self::open_or_create_in(path, locks_dir, lock, analyzer)
+    }
+
+    /// Open the index at `path`; `analyzer` only applies when it is created
+    fn open_or_create_in(
+        path: PathBuf,
+        locks_dir: PathBuf,
+        lock: &'static str,
+        analyzer: Analyzer,
+    ) -> Result<Self> {
         fs::create_dir_all(&path)?;
-        let schema = build_schema();
-        let index = if path.read_dir()?.next().is_some() {
-            Index::open_in_dir(&path).context("Failed to open search index")?
-        } else {
-            Index::create_in_dir(&path, schema.clone()).context("Failed to create search index")?
+        // Creation happens under the writer lock so two processes can't both
+        // initialize an empty directory
+        let index = {
+            let _lock = RepoLock::exclusive(&locks_dir, lock)?;
+            if Self::exists_at(&path) {
+                Index::open_in_dir(&path).context("Failed to open search index")?
+            } else {
+                let index = Index::create_in_dir(&path, build_schema(analyzer))
+                    .context("Failed to create search index")?;
+                fs::write(path.join(SCHEMA_VERSION_FILE), SCHEMA_VERSION.to_string())?;
+                index
+            }
         };
+        analyzer::register_tokenizers(&index);
         let actual_schema = index.schema();
         let fields = Fields::new(&actual_schema)?;
-        Ok(Self { index, fields })
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .context("Failed to open search index reader")?;
+        Ok(Self {
+            index,
+            fields,
+            reader,
+            locks_dir,
+            lock,
+            write_guard: Arc::new(Mutex::new(())),
+        })
     }
 
-    pub fn add_note(&self, record: &NoteRecord) -> Result<()> {
-        let mut writer = self.index.writer(50_000_000)?;
+    /// Whether an index has been created in `path`
+    pub fn exists_at(path: &Path) -> bool {
+        path.join("meta.json").exists()
+    }
+
+    /// [`SCHEMA_VERSION`] of the index in `path`. Indexes from before the
+    /// version was recorded have the first one: older layouts were rebuilt
+    /// by format migrations.
+    pub fn schema_version_at(path: &Path) -> Result<u32> {
+        match fs::read_to_string(path.join(SCHEMA_VERSION_FILE)) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid {} in {}", SCHEMA_VERSION_FILE, path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(1),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Object ids in the index in `path`, whatever schema it was built with,
+    /// so an index this build can't open can still be rebuilt
+    pub fn object_ids_at(path: &Path) -> Result<Vec<String>> {
+        if !Self::exists_at(path) {
+            return Ok(Vec::new());
+        }
+        let index = Index::open_in_dir(path).context("Failed to open search index")?;
+        let object_id = index
+            .schema()
+            .get_field("object_id")
+            .context("object_id field missing")?;
+        let reader: IndexReader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let mut ids = Vec::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let address = DocAddress::new(segment_ord as u32, doc_id);
+                let retrieved: TantivyDocument = searcher.doc(address)?;
+                if let Some(id) = retrieved.get_first(object_id).and_then(|v| v.as_str()) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Analyzer the index was built with; `None` for a tokenizer this build
+    /// doesn't know
+    pub fn analyzer(&self) -> Option<Analyzer> {
+        match self
+            .index
+            .schema()
+            .get_field_entry(self.fields.title)
+            .field_type()
+        {
+            FieldType::Str(options) => options
+                .get_indexing_options()
+                .and_then(|indexing| Analyzer::from_tokenizer_name(indexing.tokenizer())),
+            _ => None,
+        }
+    }
+
+    /// Run `f` with an index writer and commit. The writer is not kept between
+    /// calls: tantivy allows one writer per index across all processes, so a
+    /// long-lived writer in the daemon would lock the CLI out.
+    fn write<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut IndexWriter) -> Result<()>,
+    {
+        let _guard = self
+            .write_guard
+            .lock()
+            .map_err(|_| anyhow!("Search index writer lock poisoned"))?;
+        {
+            let _lock = RepoLock::exclusive(&self.locks_dir, self.lock)?;
+            let mut writer: IndexWriter = self.index.writer(WRITER_HEAP_BYTES)?;
+            f(&mut writer)?;
+            writer.commit()?;
+        }
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    fn document(&self, record: &NoteRecord) -> TantivyDocument {
         let mut document = TantivyDocument::new();
         document.add_text(self.fields.object_id, &record.object_id);
         document.add_text(self.fields.title, &record.note.title);
@@ -88,40 +446,112 @@ impl SearchIndex {
         document.add_text(self.fields.privacy, format_privacy(&record.note.privacy));
         document.add_i64(self.fields.updated_at, record.note.updated_at.timestamp());
         document.add_i64(self.fields.likes, total_likes(&record.note) as i64);
-        writer.add_document(document)?;
-        writer.commit()?;
-        Ok(())
+        if let Some(meta) = self.fields.meta {
+            let object: BTreeMap<String, OwnedValue> = record
+                .note
+                .meta
+                .iter()
+                .map(|(key, value)| (key.clone(), OwnedValue::Str(value.clone())))
+                .collect();
+            document.add_object(meta, object);
+        }
+        if let Some(created_at) = self.fields.created_at {
+            document.add_i64(created_at, record.note.created_at.timestamp());
+        }
+        if let Some(meta_facets) = self.fields.meta_facets {
+            for (key, value) in &record.note.meta {
+                document.add_facet(meta_facets, Facet::from_path([key, value]));
+            }
+        }
+        if let (Some(field), Some(scope)) = (self.fields.scope, record.note.meta.get(SCOPE_META)) {
+            document.add_text(field, scope);
+        }
+        if let Some(fields) = &self.fields.taxonomy {
+            let taxonomy = ErrorTaxonomy::from_note(&record.note);
+            if let Some(code) = taxonomy.exit_code {
+                document.add_i64(fields.exit_code, code);
+            }
+            for (field, value) in [
+                (fields.binary, &taxonomy.binary),
+                (fields.language, &taxonomy.language),
+                (fields.error_class, &taxonomy.error_class),
+            ] {
+                if let Some(value) = value {
+                    document.add_text(field, value);
+                }
+            }
+        }
+        document
+    }
+
+    /// Index a note, replacing any document already indexed for its object id
+    pub fn add_note(&self, record: &NoteRecord) -> Result<()> {
+        self.add_notes_batch(std::slice::from_ref(record))
     }
 
     /// Add multiple notes efficiently in batch
     pub fn add_notes_batch(&self, records: &[NoteRecord]) -> Result<()> {
-        let mut writer = self.index.writer(50_000_000)?;
-        for record in records {
-            let mut document = TantivyDocument::new();
-            document.add_text(self.fields.object_id, &record.object_id);
-            document.add_text(self.fields.title, &record.note.title);
-            document.add_text(self.fields.body, &record.note.body);
-            for tag in &record.note.tags {
-                document.add_text(self.fields.tags, tag);
+        self.write(|writer| {
+            for record in records {
+                writer.delete_term(self.object_id_term(&record.object_id));
+                writer.add_document(self.document(record))?;
             }
-            document.add_text(self.fields.summary, make_summary(&record.note.body));
-            document.add_text(self.fields.author, &record.note.author.name);
-            document.add_text(self.fields.privacy, format_privacy(&record.note.privacy));
-            document.add_i64(self.fields.updated_at, record.note.updated_at.timestamp());
-            document.add_i64(self.fields.likes, total_likes(&record.note) as i64);
-            writer.add_document(document)?;
-        }
-        writer.commit()?;
-        Ok(())
+            Ok(())
+        })
+    }
+
+    /// Add a document without replacing existing ones, as v1 repositories did
+    #[cfg(test)]
+    pub(crate) fn append_unchecked(&self, record: &NoteRecord) -> Result<()> {
+        self.write(|writer| {
+            writer.add_document(self.document(record))?;
+            Ok(())
+        })
+    }
+
+    fn object_id_term(&self, object_id: &str) -> Term {
+        Term::from_field_text(self.fields.object_id, object_id)
+    }
+
+    /// Remove every indexed document for an object id
+    pub fn remove_note(&self, object_id: &str) -> Result<()> {
+        self.remove_notes(std::slice::from_ref(&object_id.to_string()))
+    }
+
+    /// Remove several notes in a single commit
+    pub fn remove_notes(&self, object_ids: &[String]) -> Result<()> {
+        self.write(|writer| {
+            for object_id in object_ids {
+                writer.delete_term(self.object_id_term(object_id));
+            }
+            Ok(())
+        })
     }
 
     pub fn search(&self, query: &str, limit: usize, sort: SearchSort) -> Result<Vec<SearchHit>> {
+        Ok(self
+            .search_page(query, &SearchFilter::default(), 0, limit, sort)?
+            .hits)
+    }
+
+    /// Hits `offset..offset + limit` of a search, with the total number of
+    /// matches. Sorting and filtering happen in the index, so pages line up
+    /// for every sort.
+    pub fn search_page(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        offset: usize,
+        limit: usize,
+        sort: SearchSort,
+    ) -> Result<SearchPage> {
         let limit = limit.max(1);
-        let reader = self.index.reader()?;
-        reader.reload()?;
-        let searcher = reader.searcher();
-        let query_text = query.trim();
-        let query: Box<dyn tantivy::query::Query> = if query_text.is_empty() {
+        // Pick up commits from other processes
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let (query_text, typed) = split_typed_terms(query.trim());
+        let query_text = query_text.as_str();
+        let query: Box<dyn Query> = if query_text.is_empty() {
             Box::new(AllQuery)
         } else {
             let parser = QueryParser::for_index(
@@ -130,83 +560,281 @@ impl SearchIndex {
             );
             parser.parse_query(query_text)?
         };
-        let top_docs = searcher.search(query.as_ref(), &TopDocs::with_limit(limit))?;
-        let mut hits = Vec::new();
-        for (score, doc_address) in top_docs {
-            let retrieved: TantivyDocument = searcher.doc(doc_address)?;
-            let object_id = retrieved
-                .get_first(self.fields.object_id)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let title = retrieved
-                .get_first(self.fields.title)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let tags = retrieved
-                .get_all(self.fields.tags)
-                .filter_map(|v| v.as_str())
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-            let summary = retrieved
-                .get_first(self.fields.summary)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let author = retrieved
-                .get_first(self.fields.author)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let likes = retrieved
-                .get_first(self.fields.likes)
-                .and_then(|v| v.as_i64())
-                .unwrap_or_default() as u32;
-            let updated_at_ts = retrieved
-                .get_first(self.fields.updated_at)
-                .and_then(|v| v.as_i64())
-                .unwrap_or_default();
-            let privacy = retrieved
-                .get_first(self.fields.privacy)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let updated_at = Utc
-                .timestamp_opt(updated_at_ts, 0)
-                .single()
-                .unwrap_or_else(Utc::now);
-            hits.push(SearchHit {
-                object_id,
-                title,
-                tags,
-                summary,
-                updated_at,
-                author,
-                likes,
-                score,
-                privacy,
-            });
+        let query = if typed.is_empty() {
+            query
+        } else {
+            self.with_typed_terms(query, &typed)?
+        };
+        let query = if filter.is_empty() {
+            query
+        } else {
+            self.filtered(query, filter)?
+        };
+        let top_docs = TopDocs::with_limit(limit).and_offset(offset);
+        let (total, docs): (usize, Vec<(f32, DocAddress)>) = match sort {
+            SearchSort::Relevance => searcher.search(query.as_ref(), &(Count, top_docs))?,
+            SearchSort::Updated | SearchSort::Likes => {
+                let field = match sort {
+                    SearchSort::Updated => "updated_at",
+                    _ => "likes",
+                };
+                let (total, docs) = searcher.search(
+                    query.as_ref(),
+                    &(
+                        Count,
+                        top_docs.order_by_fast_field::<i64>(field, Order::Desc),
+                    ),
+                )?;
+                let docs = docs
+                    .into_iter()
+                    .map(|(_, address)| (0.0, address))
+                    .collect();
+                (total, docs)
+            }
+        };
+        // Only terms of an actual query can be highlighted
+        let snippets = if query_text.is_empty() {
+            None
+        } else {
+            let mut generator =
+                SnippetGenerator::create(&searcher, query.as_ref(), self.fields.body)?;
+            generator.set_max_num_chars(SNIPPET_CHARS);
+            Some(generator)
+        };
+        let hits = docs
+            .into_iter()
+            .map(|(score, address)| self.hit(&searcher, address, score, snippets.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let next_offset = offset + hits.len();
+        Ok(SearchPage {
+            next_offset: (next_offset < total).then_some(next_offset),
+            hits,
+            total,
+            offset,
+        })
+    }
+
+    /// `query` restricted to notes matching every typed `field:value` term
+    fn with_typed_terms(
+        &self,
+        query: Box<dyn Query>,
+        typed: &[(&str, &str)],
+    ) -> Result<Box<dyn Query>> {
+        let Some(fields) = &self.fields.taxonomy else {
+            bail!(
+                "The search index predates {} searches\n💡 Tip: Run 'fuku migrate' to rebuild it",
+                typed[0].0
+            );
+        };
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        for &(name, value) in typed {
+            let term = match name {
+                "exit_code" => Term::from_field_i64(
+                    fields.exit_code,
+                    value
+                        .parse()
+                        .with_context(|| format!("exit_code must be a number, got '{}'", value))?,
+                ),
+                "binary" => Term::from_field_text(fields.binary, &value.to_lowercase()),
+                "language" => Term::from_field_text(fields.language, &value.to_lowercase()),
+                _ => Term::from_field_text(fields.error_class, &value.to_lowercase()),
+            };
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
         }
-        // Optimize sorting for large result sets
-        match sort {
-            SearchSort::Relevance => {
-                // Already sorted by relevance score from tantivy
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// `query` restricted to the notes `filter` lets through
+    fn filtered(&self, query: Box<dyn Query>, filter: &SearchFilter) -> Result<Box<dyn Query>> {
+        let (Some(created_at), Some(meta_facets)) =
+            (self.fields.created_at, self.fields.meta_facets)
+        else {
+            bail!("The search index predates search filters\n💡 Tip: Run 'fuku migrate' to rebuild it");
+        };
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        for (field, after, before) in [
+            (created_at, filter.created_after, filter.created_before),
+            (
+                self.fields.updated_at,
+                filter.updated_after,
+                filter.updated_before,
+            ),
+        ] {
+            if after.is_none() && before.is_none() {
+                continue;
             }
-            SearchSort::Updated => {
-                // Use unstable sort for better performance with large datasets
-                hits.sort_unstable_by(|a, b| b.updated_at.cmp(&a.updated_at));
+            let bound = |at: Option<DateTime<Utc>>, bound: fn(Term) -> Bound<Term>| {
+                at.map_or(Bound::Unbounded, |at| {
+                    bound(Term::from_field_i64(field, at.timestamp()))
+                })
+            };
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new(
+                    bound(after, Bound::Excluded),
+                    bound(before, Bound::Excluded),
+                )),
+            ));
+        }
+        let meta = filter.meta.iter().map(|pair| (Occur::Must, pair)).chain(
+            filter
+                .exclude_meta
+                .iter()
+                .map(|pair| (Occur::MustNot, pair)),
+        );
+        for (occur, (key, value)) in meta {
+            clauses.push((
+                occur,
+                Box::new(TermQuery::new(
+                    Term::from_facet(meta_facets, &Facet::from_path([key, value])),
+                    IndexRecordOption::Basic,
+                )),
+            ));
+        }
+        if let Some(scope) = &filter.scope {
+            let Some(field) = self.fields.scope else {
+                bail!("The search index predates scopes\n💡 Tip: Run 'fuku migrate' to rebuild it");
+            };
+            let scope = scope.trim_matches('/');
+            let pattern = format!("{}(/.*)?", regex::escape(scope));
+            clauses.push((
+                Occur::Must,
+                Box::new(RegexQuery::from_pattern(&pattern, field)?),
+            ));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    fn hit(
+        &self,
+        searcher: &Searcher,
+        address: DocAddress,
+        score: f32,
+        snippets: Option<&SnippetGenerator>,
+    ) -> Result<SearchHit> {
+        let retrieved: TantivyDocument = searcher.doc(address)?;
+        let snippet = snippets
+            .map(|generator| HitSnippet::from(generator.snippet_from_doc(&retrieved)))
+            .filter(|snippet| !snippet.highlights.is_empty());
+        let object_id = retrieved
+            .get_first(self.fields.object_id)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let title = retrieved
+            .get_first(self.fields.title)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let tags = retrieved
+            .get_all(self.fields.tags)
+            .filter_map(|v| v.as_str())
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let summary = retrieved
+            .get_first(self.fields.summary)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let author = retrieved
+            .get_first(self.fields.author)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let likes = retrieved
+            .get_first(self.fields.likes)
+            .and_then(|v| v.as_i64())
+            .unwrap_or_default() as u32;
+        let updated_at_ts = retrieved
+            .get_first(self.fields.updated_at)
+            .and_then(|v| v.as_i64())
+            .unwrap_or_default();
+        let privacy = retrieved
+            .get_first(self.fields.privacy)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let scope = self
+            .fields
+            .scope
+            .and_then(|field| retrieved.get_first(field))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let updated_at = Utc
+            .timestamp_opt(updated_at_ts, 0)
+            .single()
+            .unwrap_or_else(Utc::now);
+        Ok(SearchHit {
+            object_id,
+            title,
+            tags,
+            summary,
+            updated_at,
+            author,
+            likes,
+            score,
+            privacy,
+            pinned: false,
+            verified: false,
+            snippet,
+            scope,
+        })
+    }
+
+    /// Number of live (non-deleted) notes in the index
+    pub fn num_docs(&self) -> Result<u64> {
+        // Pick up commits from other processes
+        self.reader.reload()?;
+        let reader = &self.reader;
+        Ok(reader.searcher().num_docs())
+    }
+
+    /// Object ids of all live notes, most recently updated first. Only the ids
+    /// are held in memory, so callers can load notes one at a time.
+    pub fn object_ids(&self) -> Result<Vec<String>> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let mut ids = Vec::with_capacity(searcher.num_docs() as usize);
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let address = DocAddress::new(segment_ord as u32, doc_id);
+                let retrieved: TantivyDocument = searcher.doc(address)?;
+                let Some(object_id) = retrieved
+                    .get_first(self.fields.object_id)
+                    .and_then(|v| v.as_str())
+                else {
+                    continue;
+                };
+                let updated_at = retrieved
+                    .get_first(self.fields.updated_at)
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or_default();
+                ids.push((updated_at, object_id.to_string()));
             }
-            SearchSort::Likes => {
-                hits.sort_unstable_by(|a, b| b.likes.cmp(&a.likes));
+        }
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(ids.into_iter().map(|(_, id)| id).collect())
+    }
+
+    /// Object ids indexed more than once
+    pub fn duplicate_ids(&self) -> Result<Vec<String>> {
+        let mut seen = BTreeSet::new();
+        let mut duplicates = BTreeSet::new();
+        for object_id in self.object_ids()? {
+            if !seen.insert(object_id.clone()) {
+                duplicates.insert(object_id);
             }
         }
-        Ok(hits)
+        Ok(duplicates.into_iter().collect())
     }
 
     pub fn collect_tags(&self) -> Result<Vec<String>> {
-        let reader = self.index.reader()?;
-        reader.reload()?;
+        // Pick up commits from other processes
+        self.reader.reload()?;
+        let reader = &self.reader;
         let searcher = reader.searcher();
         let mut unique = BTreeSet::new();
         for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
@@ -245,24 +873,102 @@ impl Fields {
             privacy: schema
                 .get_field("privacy")
                 .context("privacy field missing")?,
+            meta: schema.get_field("meta").ok(),
+            created_at: schema.get_field("created_at").ok(),
+            meta_facets: schema.get_field("meta_facets").ok(),
+            taxonomy: TaxonomyFields::new(schema),
+            scope: schema.get_field("scope").ok(),
         })
     }
 }
 
-fn build_schema() -> Schema {
+impl TaxonomyFields {
+    fn new(schema: &Schema) -> Option<Self> {
+        Some(Self {
+            exit_code: schema.get_field("exit_code").ok()?,
+            binary: schema.get_field("binary").ok()?,
+            language: schema.get_field("language").ok()?,
+            error_class: schema.get_field("error_class").ok()?,
+        })
+    }
+}
+
+/// Analyzer set in the repository's own config; the global config doesn't
+/// apply since the index lives with the repository
+fn configured_analyzer(repo: &FukuraRepo) -> Result<Analyzer> {
+    Ok(FukuraConfig::load(&repo.config_path())?.search.analyzer)
+}
+
+fn build_schema(analyzer: Analyzer) -> Schema {
+    let text = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(analyzer.tokenizer_name())
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        )
+        .set_stored();
     let mut builder = SchemaBuilder::default();
     builder.add_text_field("object_id", STRING | STORED);
-    builder.add_text_field("title", TEXT | STORED);
-    builder.add_text_field("body", TEXT);
+    builder.add_text_field("title", text.clone());
+    builder.add_text_field("body", text);
     builder.add_text_field("tags", TEXT | STORED);
     builder.add_text_field("summary", STORED);
     builder.add_text_field("author", STRING | STORED);
     builder.add_text_field("privacy", STRING | STORED);
-    builder.add_i64_field("updated_at", FAST | STORED);
+    builder.add_i64_field("updated_at", INDEXED | FAST | STORED);
     builder.add_i64_field("likes", FAST | STORED);
+    builder.add_json_field("meta", TEXT);
+    builder.add_i64_field("created_at", INDEXED | FAST);
+    builder.add_facet_field("meta_facets", FacetOptions::default());
+    builder.add_i64_field("exit_code", INDEXED | FAST);
+    builder.add_text_field("binary", STRING);
+    builder.add_text_field("language", STRING);
+    builder.add_text_field("error_class", STRING);
+    builder.add_text_field("scope", STRING | STORED);
     builder.build()
 }
 
+/// Fields from [`ErrorTaxonomy`] that act as filters in a query
+const TYPED_FIELDS: &[&str] = &["exit_code", "binary", "language", "error_class"];
+
+/// Pull `field:value` terms on [`TYPED_FIELDS`] out of `query` so they must
+/// all match; the query parser would only OR them with the rest. Queries
+/// using `OR` or parentheses are left to the parser as written.
+fn split_typed_terms(query: &str) -> (String, Vec<(&str, &str)>) {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if words
+        .iter()
+        .any(|word| *word == "OR" || word.contains(['(', ')']))
+    {
+        return (query.to_string(), vec![]);
+    }
+    let mut rest: Vec<&str> = Vec::new();
+    let mut typed = Vec::new();
+    let mut skip_and = false;
+    for word in words {
+        let term = word
+            .split_once(':')
+            .filter(|(name, value)| TYPED_FIELDS.contains(name) && !value.is_empty());
+        match term {
+            Some(term) if !word.contains('"') => {
+                // Drop the AND that joined this term to the rest, once
+                if rest.last() == Some(&"AND") {
+                    rest.pop();
+                } else {
+                    skip_and = true;
+                }
+                typed.push(term);
+            }
+            _ if skip_and && word == "AND" => skip_and = false,
+            _ => {
+                skip_and = false;
+                rest.push(word);
+            }
+        }
+    }
+    (rest.join(" "), typed)
+}
+
 pub fn make_summary(body: &str) -> String {
     let mut lines = body.lines().filter(|line| !line.trim().is_empty());
     let preview: Vec<&str> = lines.by_ref().take(3).collect();
@@ -0,0 +1,227 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::config::{IssueProvider, IssueTrackerConfig};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An issue to open in a tracker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRequest {
+    pub title: String,
+    /// Markdown
+    pub body: String,
+    pub labels: Vec<String>,
+}
+
+/// An outbound integration that can open issues. Implementations own their
+/// HTTP client and credentials.
+pub trait Integration: Send + Sync {
+    /// Name shown to the user, e.g. "GitHub"
+    fn name(&self) -> &'static str;
+
+    /// Opens an issue and returns its web URL
+    fn create_issue<'a>(&'a self, issue: &'a IssueRequest) -> BoxFuture<'a, Result<String>>;
+}
+
+/// The integration for a configured tracker, with credentials from the environment
+pub fn from_config(config: &IssueTrackerConfig) -> Result<Box<dyn Integration>> {
+    let env = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .with_context(|| format!("{} is not set", name))
+    };
+    let labels = config.labels.clone();
+    Ok(match config.provider {
+        IssueProvider::Github => Box::new(GitHub {
+            client: Client::new(),
+            api: config
+                .url
+                .clone()
+                .unwrap_or_else(|| "https://api.github.com".to_string()),
+            repository: config.project.clone(),
+            token: env("GITHUB_TOKEN")?,
+            labels,
+        }),
+        IssueProvider::Gitlab => Box::new(GitLab {
+            client: Client::new(),
+            url: config
+                .url
+                .clone()
+                .unwrap_or_else(|| "https://gitlab.com".to_string()),
+            project: config.project.clone(),
+            token: env("GITLAB_TOKEN")?,
+            labels,
+        }),
+        IssueProvider::Jira => Box::new(Jira {
+            client: Client::new(),
+            url: config
+                .url
+                .clone()
+                .context("integrations.issues.url must be set to the Jira site")?,
+            project: config.project.clone(),
+            issue_type: config
+                .issue_type
+                .clone()
+                .unwrap_or_else(|| "Bug".to_string()),
+            email: env("JIRA_EMAIL")?,
+            token: env("JIRA_API_TOKEN")?,
+            labels,
+        }),
+    })
+}
+
+fn merged_labels(configured: &[String], issue: &IssueRequest) -> Vec<String> {
+    let mut labels: Vec<String> = configured.iter().chain(&issue.labels).cloned().collect();
+    labels.sort();
+    labels.dedup();
+    labels
+}
+
+/// Send a request and decode the JSON reply, with the tracker's error
+/// message on failure
+async fn send(name: &str, request: RequestBuilder) -> Result<Value> {
+    let response = request
+        .header("User-Agent", concat!("fukura/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await
+        .with_context(|| format!("Failed to contact {}", name))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "<unavailable>".to_string());
+        bail!("{} returned {}: {}", name, status, body);
+    }
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to decode {} response", name))
+}
+
+fn string_field(value: &Value, field: &str, name: &str) -> Result<String> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .with_context(|| format!("{} response has no {}", name, field))
+}
+
+pub struct GitHub {
+    client: Client,
+    api: String,
+    repository: String,
+    token: String,
+    labels: Vec<String>,
+}
+
+impl Integration for GitHub {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    fn create_issue<'a>(&'a self, issue: &'a IssueRequest) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let url = format!(
+                "{}/repos/{}/issues",
+                self.api.trim_end_matches('/'),
+                self.repository
+            );
+            let request = self
+                .client
+                .post(url)
+                .bearer_auth(&self.token)
+                .header("Accept", "application/vnd.github+json")
+                .json(&json!({
+                    "title": issue.title,
+                    "body": issue.body,
+                    "labels": merged_labels(&self.labels, issue),
+                }));
+            let created = send(self.name(), request).await?;
+            string_field(&created, "html_url", self.name())
+        })
+    }
+}
+
+pub struct GitLab {
+    client: Client,
+    url: String,
+    project: String,
+    token: String,
+    labels: Vec<String>,
+}
+
+impl Integration for GitLab {
+    fn name(&self) -> &'static str {
+        "GitLab"
+    }
+
+    fn create_issue<'a>(&'a self, issue: &'a IssueRequest) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let url = format!(
+                "{}/api/v4/projects/{}/issues",
+                self.url.trim_end_matches('/'),
+                urlencoding::encode(&self.project)
+            );
+            let request = self
+                .client
+                .post(url)
+                .header("PRIVATE-TOKEN", &self.token)
+                .json(&json!({
+                    "title": issue.title,
+                    "description": issue.body,
+                    "labels": merged_labels(&self.labels, issue).join(","),
+                }));
+            let created = send(self.name(), request).await?;
+            string_field(&created, "web_url", self.name())
+        })
+    }
+}
+
+pub struct Jira {
+    client: Client,
+    url: String,
+    project: String,
+    issue_type: String,
+    email: String,
+    token: String,
+    labels: Vec<String>,
+}
+
+impl Integration for Jira {
+    fn name(&self) -> &'static str {
+        "Jira"
+    }
+
+    fn create_issue<'a>(&'a self, issue: &'a IssueRequest) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let site = self.url.trim_end_matches('/');
+            // Jira labels can't contain spaces
+            let labels: Vec<String> = merged_labels(&self.labels, issue)
+                .into_iter()
+                .map(|label| label.replace(' ', "-"))
+                .collect();
+            let request = self
+                .client
+                .post(format!("{}/rest/api/2/issue", site))
+                .basic_auth(&self.email, Some(&self.token))
+                .json(&json!({
+                    "fields": {
+                        "project": { "key": self.project },
+                        "summary": issue.title,
+                        "description": issue.body,
+                        "issuetype": { "name": self.issue_type },
+                        "labels": labels,
+                    }
+                }));
+            let created = send(self.name(), request).await?;
+            let key = string_field(&created, "key", self.name())?;
+            Ok(format!("{}/browse/{}", site, key))
+        })
+    }
+}
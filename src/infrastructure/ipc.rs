@@ -0,0 +1,305 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::config::IpcConfig;
+use crate::repo::FukuraRepo;
+
+/// Marker for the encoded message format. Every text field is base64 encoded so
+/// newlines, unicode and `|` inside commands or stderr survive the trip from the
+/// shell hooks to the daemon.
+pub const ENCODED_PREFIX: &str = "b64";
+
+/// Upper bound for a single hook message
+pub const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+/// A command reported by a shell hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookMessage {
+    pub session_id: String,
+    pub command: String,
+    pub exit_code: i32,
+    pub working_dir: String,
+    pub stderr: String,
+    /// How long the command ran, from hooks that time it
+    pub duration_ms: Option<u64>,
+}
+
+impl HookMessage {
+    /// `b64|session|command|exit_code|working_dir|stderr|duration_ms`, without
+    /// a trailing newline. The duration is a plain number, empty when unknown.
+    pub fn encode(&self) -> String {
+        [
+            ENCODED_PREFIX.to_string(),
+            STANDARD.encode(&self.session_id),
+            STANDARD.encode(&self.command),
+            self.exit_code.to_string(),
+            STANDARD.encode(&self.working_dir),
+            STANDARD.encode(&self.stderr),
+            self.duration_ms
+                .map(|ms| ms.to_string())
+                .unwrap_or_default(),
+        ]
+        .join("|")
+    }
+
+    /// Parse an encoded message, falling back to the legacy plain
+    /// `session|command|exit_code|working_dir[|stderr]` format used by older
+    /// hooks. Hooks from before durations were timed leave the field out.
+    pub fn parse(message: &str) -> Option<Self> {
+        let message = message.trim_end_matches(['\r', '\n']);
+        let parts: Vec<&str> = message.split('|').collect();
+
+        if parts.first() == Some(&ENCODED_PREFIX) {
+            if !(5..=7).contains(&parts.len()) {
+                return None;
+            }
+            return Some(Self {
+                session_id: decode(parts[1])?,
+                command: decode(parts[2])?,
+                exit_code: parts[3].trim().parse().ok()?,
+                working_dir: decode(parts[4])?,
+                stderr: match parts.get(5) {
+                    Some(field) => decode(field)?,
+                    None => String::new(),
+                },
+                duration_ms: parts.get(6).and_then(|field| field.trim().parse().ok()),
+            });
+        }
+
+        let parts: Vec<&str> = message.trim().split('|').collect();
+        if parts.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            session_id: parts[0].to_string(),
+            command: parts[1].to_string(),
+            exit_code: parts[2].parse().unwrap_or(0),
+            working_dir: parts[3].to_string(),
+            stderr: parts.get(4).map(|s| s.to_string()).unwrap_or_default(),
+            duration_ms: None,
+        })
+    }
+
+    /// Single-line summary of the command for note titles and notifications
+    pub fn command_summary(&self) -> String {
+        let mut lines = self.command.lines();
+        let first = lines.next().unwrap_or_default().trim();
+        if lines.next().is_some() {
+            format!("{} …", first)
+        } else {
+            first.to_string()
+        }
+    }
+}
+
+fn decode(field: &str) -> Option<String> {
+    let bytes = STANDARD.decode(field.trim()).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Socket the daemon of the repository at `root` listens on: `[ipc]
+/// socket_path` when set, otherwise one in the runtime directory named
+/// after a hash of the root, so daemons of different repositories never
+/// share a socket and nothing lands in a synced or shared `.fukura`
+pub fn socket_path(root: &Path, config: &IpcConfig) -> PathBuf {
+    if let Some(path) = &config.socket_path {
+        return root.join(path);
+    }
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = hex::encode(Sha256::digest(root.to_string_lossy().as_bytes()));
+    crate::paths::runtime_dir().join(format!("{}.sock", &digest[..16]))
+}
+
+/// [`socket_path`] per the repository's own config, for the hooks
+pub fn repo_socket_path(root: &Path) -> PathBuf {
+    // The same config the daemon reads, global defaults included
+    let config = FukuraRepo::open_without_upgrade(root)
+        .and_then(|repo| repo.config())
+        .unwrap_or_default();
+    socket_path(root, &config.ipc)
+}
+
+/// Create the directory a socket goes in, private to the current user.
+/// The default runtime directory must belong to us and is narrowed to
+/// `0700` if it was opened up, so nobody else can swap the socket.
+#[cfg(unix)]
+pub fn prepare_socket_dir(socket: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    let Some(dir) = socket.parent() else {
+        return Ok(());
+    };
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    if dir != crate::paths::runtime_dir() {
+        return Ok(());
+    }
+    let meta = std::fs::metadata(dir)?;
+    if meta.uid() != current_uid() {
+        anyhow::bail!(
+            "{} belongs to another user; set [ipc] socket_path to use another socket",
+            dir.display()
+        );
+    }
+    if meta.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Read one message: up to the first newline, EOF, or [`MAX_MESSAGE_BYTES`].
+/// Hooks keep the connection open waiting for the reply, so EOF can't be relied on.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<String> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let n = reader.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..n]);
+        if buffer[..n].contains(&b'\n') || data.len() >= MAX_MESSAGE_BYTES {
+            break;
+        }
+    }
+    let end = data
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(data.len())
+        .min(MAX_MESSAGE_BYTES);
+    Ok(String::from_utf8_lossy(&data[..end]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn message(command: &str, stderr: &str) -> HookMessage {
+        HookMessage {
+            session_id: "abc123".to_string(),
+            command: command.to_string(),
+            exit_code: 1,
+            working_dir: "/home/dev/プロジェクト".to_string(),
+            stderr: stderr.to_string(),
+            duration_ms: Some(1500),
+        }
+    }
+
+    #[test]
+    fn test_special_characters_round_trip() {
+        let msg = message(
+            "cat log | grep 'a|b'\necho \"done\" 🎉",
+            "error: bad\nline two|three",
+        );
+        let encoded = msg.encode();
+        assert!(!encoded.contains('\n'));
+        assert_eq!(HookMessage::parse(&format!("{}\n", encoded)), Some(msg));
+    }
+
+    #[test]
+    fn test_parses_shell_base64_output() {
+        // printf '%s' 'ls | wc' | base64
+        let line = "b64|czE=|bHMgfCB3Yw==|0|L3RtcA==|\n";
+        let msg = HookMessage::parse(line).unwrap();
+        assert_eq!(msg.command, "ls | wc");
+        assert_eq!(msg.working_dir, "/tmp");
+        assert_eq!(msg.stderr, "");
+        assert_eq!(msg.duration_ms, None);
+
+        let timed = HookMessage::parse("b64|czE=|bHMgfCB3Yw==|0|L3RtcA==||2350\n").unwrap();
+        assert_eq!(timed.duration_ms, Some(2350));
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let msg = HookMessage::parse("s1|cargo build|101|/app|error[E0425]\n").unwrap();
+        assert_eq!(msg.command, "cargo build");
+        assert_eq!(msg.exit_code, 101);
+        assert_eq!(msg.stderr, "error[E0425]");
+        assert!(HookMessage::parse("garbage").is_none());
+        assert!(HookMessage::parse("b64|not base64!|x|1|y|z").is_none());
+    }
+
+    #[test]
+    fn test_command_summary() {
+        assert_eq!(message("make", "").command_summary(), "make");
+        assert_eq!(
+            message("for f in *; do\n  echo $f\ndone", "").command_summary(),
+            "for f in *; do …"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_message_stops_at_newline() {
+        let mut input: &[u8] = b"b64|czE=|bHM=|0|Lw==|\nleftover";
+        let msg = read_message(&mut input).await.unwrap();
+        assert_eq!(msg, "b64|czE=|bHM=|0|Lw==|");
+        assert_eq!(HookMessage::parse(&msg).unwrap().command, "ls");
+    }
+
+    #[test]
+    fn test_socket_path_is_per_repository() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (one, two) = (dir.path().join("one"), dir.path().join("two"));
+        let config = IpcConfig::default();
+        let path = socket_path(&one, &config);
+        assert_eq!(path.parent().unwrap(), crate::paths::runtime_dir());
+        assert_eq!(path, socket_path(&one, &config));
+        assert_ne!(path, socket_path(&two, &config));
+
+        let custom = IpcConfig {
+            socket_path: Some(PathBuf::from(".fukura/daemon.sock")),
+            ..IpcConfig::default()
+        };
+        assert_eq!(socket_path(&one, &custom), one.join(".fukura/daemon.sock"));
+
+        // Hooks read the repository's config file, like the daemon
+        let repo = FukuraRepo::init(&one, true).unwrap();
+        let mut local = repo.local_config().unwrap();
+        local.ipc = custom;
+        local.save(&repo.config_path()).unwrap();
+        assert_eq!(repo_socket_path(&one), one.join(".fukura/daemon.sock"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prepare_socket_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let socket = dir.path().join("sockets").join("daemon.sock");
+        prepare_socket_dir(&socket).unwrap();
+        let mode = std::fs::metadata(dir.path().join("sockets"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip_arbitrary_commands(
+            session_id in ".*",
+            command in any::<String>(),
+            exit_code in any::<i32>(),
+            working_dir in ".*",
+            stderr in any::<String>(),
+            duration_ms in any::<Option<u64>>(),
+        ) {
+            let msg = HookMessage { session_id, command, exit_code, working_dir, stderr, duration_ms };
+            prop_assert_eq!(HookMessage::parse(&msg.encode()), Some(msg));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+use crate::log_process::LogProcess;
+
+/// Syslog priority of `err`; lower numbers are more severe
+pub const PRIORITY_ERR: u8 = 3;
+
+/// A line from journald or a syslog file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// systemd unit, or the syslog identifier when there is none
+    pub unit: String,
+    pub message: String,
+    pub priority: Option<u8>,
+}
+
+impl LogEntry {
+    /// Logged at `err` or worse, whatever the message says
+    pub fn is_severe(&self) -> bool {
+        self.priority
+            .is_some_and(|priority| priority <= PRIORITY_ERR)
+    }
+}
+
+/// journald fields are strings, or byte arrays when not valid UTF-8
+fn field(entry: &Value, name: &str) -> Option<String> {
+    match entry.get(name)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Parse a `journalctl --output json` line
+pub fn parse_journal_line(line: &str) -> Option<LogEntry> {
+    let entry: Value = serde_json::from_str(line).ok()?;
+    let unit = field(&entry, "_SYSTEMD_UNIT")
+        .or_else(|| field(&entry, "SYSLOG_IDENTIFIER"))
+        .unwrap_or_else(|| "journal".to_string());
+    Some(LogEntry {
+        unit: unit.trim_end_matches(".service").to_string(),
+        message: field(&entry, "MESSAGE")?,
+        priority: field(&entry, "PRIORITY").and_then(|priority| priority.parse().ok()),
+    })
+}
+
+/// Parse a traditional (`Jun  1 10:00:00 host app[42]: ...`), ISO-timestamped
+/// or RFC 5424 syslog line
+pub fn parse_syslog_line(line: &str) -> Option<LogEntry> {
+    static BSD: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(?:<(\d+)>)?\w{3} +\d+ [\d:]+ \S+ ([^\s:\[]+)(?:\[\d+\])?: ?(.*)$")
+            .expect("valid syslog pattern")
+    });
+    static ISO: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(?:<(\d+)>)?\d{4}-\d{2}-\d{2}T\S+ \S+ ([^\s:\[]+)(?:\[\d+\])?: ?(.*)$")
+            .expect("valid syslog pattern")
+    });
+    static RFC5424: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^<(\d+)>\d+ \S+ \S+ (\S+) \S+ \S+ (?:-|(?:\[[^\]]*\])+) ?(.*)$")
+            .expect("valid syslog pattern")
+    });
+    let captures = BSD
+        .captures(line)
+        .or_else(|| RFC5424.captures(line))
+        .or_else(|| ISO.captures(line))?;
+    Some(LogEntry {
+        unit: captures[2].to_string(),
+        message: captures[3].to_string(),
+        // PRI is facility * 8 + severity
+        priority: captures
+            .get(1)
+            .and_then(|pri| pri.as_str().parse::<u16>().ok())
+            .map(|pri| (pri % 8) as u8),
+    })
+}
+
+/// journald through `journalctl`, which handles journal files, rotation and
+/// permissions the way the system is configured
+pub struct Journal {
+    program: String,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            program: "journalctl".to_string(),
+        }
+    }
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries of `units` (all units when empty); with `follow`, keeps
+    /// streaming new entries
+    pub fn entries(
+        &self,
+        units: &[String],
+        follow: bool,
+        since: Option<&str>,
+    ) -> Result<impl Iterator<Item = LogEntry>> {
+        let mut command = Command::new(&self.program);
+        command.args(["--output", "json", "--no-pager"]);
+        for unit in units {
+            command.args(["--unit", unit]);
+        }
+        if follow {
+            command.arg("--follow");
+        }
+        if let Some(since) = since {
+            command.args(["--since", since]);
+        }
+        Ok(LogProcess::spawn(command)?.filter_map(|line| parse_journal_line(&line)))
+    }
+
+    /// Entries of a syslog file; with `follow`, new lines as they are
+    /// written, across rotation
+    pub fn syslog_file(&self, path: &Path, follow: bool) -> Result<impl Iterator<Item = LogEntry>> {
+        let mut command = Command::new("tail");
+        command.args(if follow {
+            ["-F", "-n", "0"]
+        } else {
+            ["-n", "+1", "--"]
+        });
+        command.arg(path);
+        Ok(LogProcess::spawn(command)?.filter_map(|line| parse_syslog_line(&line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_journal_and_syslog_lines() {
+        let line = r#"{"_SYSTEMD_UNIT":"payments.service","SYSLOG_IDENTIFIER":"payments","PRIORITY":"3","MESSAGE":"upstream timed out"}"#;
+        let entry = parse_journal_line(line).unwrap();
+        assert_eq!(entry.unit, "payments");
+        assert_eq!(entry.message, "upstream timed out");
+        assert!(entry.is_severe());
+
+        // Non-UTF-8 messages come as byte arrays
+        let line = r#"{"SYSLOG_IDENTIFIER":"cron","PRIORITY":"6","MESSAGE":[104,105]}"#;
+        let entry = parse_journal_line(line).unwrap();
+        assert_eq!(
+            (entry.unit.as_str(), entry.message.as_str()),
+            ("cron", "hi")
+        );
+        assert!(!entry.is_severe());
+
+        let entry =
+            parse_syslog_line("Jun  1 10:00:00 web-1 nginx[812]: connect() failed (111)").unwrap();
+        assert_eq!(entry.unit, "nginx");
+        assert_eq!(entry.message, "connect() failed (111)");
+        assert_eq!(entry.priority, None);
+
+        let entry =
+            parse_syslog_line("<27>1 2024-06-01T10:00:00Z web-1 app 42 - - disk full").unwrap();
+        assert_eq!(entry.unit, "app");
+        assert_eq!(entry.message, "disk full");
+        assert_eq!(entry.priority, Some(3));
+        assert!(parse_syslog_line("not a syslog line").is_none());
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::{Context, Result};
+use futures::stream::{BoxStream, StreamExt};
+use k8s_openapi::api::core::v1::{Event, Pod};
+use kube::api::{Api, DynamicObject, ListParams, LogParams};
+use kube::core::{GroupVersionKind, TypeMeta};
+use kube::discovery::{pinned_kind, Scope};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::Client;
+use serde_json::Value;
+use std::fmt;
+
+use crate::issues::block_on;
+
+/// Lines of a manifest kept in a note
+const MAX_SNIPPET_LINES: usize = 40;
+
+/// Container fields worth showing next to a failure
+const CONTAINER_FIELDS: [&str; 7] = [
+    "name",
+    "image",
+    "command",
+    "args",
+    "resources",
+    "livenessProbe",
+    "readinessProbe",
+];
+
+/// A Kubernetes Event, as served by the API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KubeEvent {
+    pub namespace: String,
+    /// API version of the object the event is about, e.g. "apps/v1"
+    pub api_version: String,
+    /// Kind of the object the event is about, e.g. "Pod"
+    pub kind: String,
+    pub name: String,
+    pub reason: String,
+    pub message: String,
+    pub warning: bool,
+}
+
+pub fn parse_event(value: &Value) -> Option<KubeEvent> {
+    let object = value.get("involvedObject")?;
+    let text = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let namespace = match text(object, "namespace") {
+        namespace if namespace.is_empty() => text(&value["metadata"], "namespace"),
+        namespace => namespace,
+    };
+    Some(KubeEvent {
+        namespace,
+        api_version: text(object, "apiVersion"),
+        kind: text(object, "kind"),
+        name: text(object, "name"),
+        reason: text(value, "reason"),
+        message: text(value, "message").trim().to_string(),
+        warning: value.get("type").and_then(Value::as_str) == Some("Warning"),
+    })
+}
+
+/// The controller that owns a pod, so crashes of its replicas are grouped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workload {
+    pub kind: String,
+    pub name: String,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind.to_lowercase(), self.name)
+    }
+}
+
+/// Workload of a pod manifest, following ReplicaSets up to their Deployment
+pub fn workload_of(pod: &Value) -> Option<Workload> {
+    let metadata = pod.get("metadata")?;
+    let name = metadata.get("name")?.as_str()?;
+    let owner = metadata
+        .get("ownerReferences")
+        .and_then(Value::as_array)
+        .and_then(|owners| {
+            owners
+                .iter()
+                .find(|owner| owner.get("controller").and_then(Value::as_bool) == Some(true))
+                .or_else(|| owners.first())
+        });
+    let Some(owner) = owner else {
+        return Some(Workload {
+            kind: "Pod".to_string(),
+            name: name.to_string(),
+        });
+    };
+    let kind = owner.get("kind")?.as_str()?;
+    let owner_name = owner.get("name")?.as_str()?;
+    let hash = metadata
+        .get("labels")
+        .and_then(|labels| labels.get("pod-template-hash"))
+        .and_then(Value::as_str);
+    if let (Some(hash), "ReplicaSet") = (hash, kind) {
+        if let Some(deployment) = owner_name.strip_suffix(&format!("-{}", hash)) {
+            return Some(Workload {
+                kind: "Deployment".to_string(),
+                name: deployment.to_string(),
+            });
+        }
+    }
+    Some(Workload {
+        kind: kind.to_string(),
+        name: owner_name.to_string(),
+    })
+}
+
+/// A container that is crash looping or was killed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerFailure {
+    pub container: String,
+    /// Why it is waiting (CrashLoopBackOff) or last terminated (OOMKilled, Error)
+    pub reason: String,
+    pub exit_code: Option<i32>,
+    pub restart_count: u64,
+}
+
+/// Failing containers of a pod manifest
+pub fn container_failures(pod: &Value) -> Vec<ContainerFailure> {
+    let Some(statuses) = pod
+        .get("status")
+        .and_then(|status| status.get("containerStatuses"))
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    statuses
+        .iter()
+        .filter_map(|status| {
+            let terminated = status
+                .pointer("/state/terminated")
+                .or_else(|| status.pointer("/lastState/terminated"));
+            let exit_code = terminated
+                .and_then(|terminated| terminated.get("exitCode"))
+                .and_then(Value::as_i64)
+                .map(|code| code as i32);
+            let waiting = status
+                .pointer("/state/waiting/reason")
+                .and_then(Value::as_str)
+                .filter(|reason| *reason != "ContainerCreating" && *reason != "PodInitializing");
+            let terminated_reason = terminated
+                .and_then(|terminated| terminated.get("reason"))
+                .and_then(Value::as_str)
+                .filter(|_| exit_code != Some(0));
+            let reason = match (waiting, terminated_reason) {
+                (Some("CrashLoopBackOff"), Some(terminated)) => {
+                    format!("CrashLoopBackOff ({})", terminated)
+                }
+                (Some(waiting), _) => waiting.to_string(),
+                (None, Some(terminated)) => terminated.to_string(),
+                (None, None) => return None,
+            };
+            Some(ContainerFailure {
+                container: status.get("name")?.as_str()?.to_string(),
+                reason,
+                exit_code,
+                restart_count: status
+                    .get("restartCount")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// The relevant part of a manifest as pretty JSON: a pod's container
+/// specs (only `container` when given), or any other object's spec
+pub fn manifest_snippet(object: &Value, container: Option<&str>) -> Option<String> {
+    let snippet = if object.get("kind").and_then(Value::as_str) == Some("Pod") {
+        let containers: Vec<Value> = object
+            .pointer("/spec/containers")?
+            .as_array()?
+            .iter()
+            .filter(|spec| {
+                container.is_none() || spec.get("name").and_then(Value::as_str) == container
+            })
+            .map(|spec| {
+                let fields = CONTAINER_FIELDS
+                    .iter()
+                    .filter_map(|field| Some((field.to_string(), spec.get(*field)?.clone())))
+                    .collect();
+                Value::Object(fields)
+            })
+            .collect();
+        if containers.is_empty() {
+            return None;
+        }
+        Value::Array(containers)
+    } else {
+        object.get("spec")?.clone()
+    };
+    let pretty = serde_json::to_string_pretty(&snippet).ok()?;
+    let mut lines: Vec<&str> = pretty.lines().collect();
+    if lines.len() > MAX_SNIPPET_LINES {
+        lines.truncate(MAX_SNIPPET_LINES);
+        lines.push("...");
+    }
+    Some(lines.join("\n"))
+}
+
+/// Kubernetes API client for the kubeconfig's current context, so its
+/// namespace and auth plugins apply as they do for `kubectl`
+pub struct KubeClient {
+    client: Client,
+    namespace: String,
+}
+
+impl KubeClient {
+    /// Namespace of `None` means the current context's namespace
+    pub fn connect(namespace: Option<String>) -> Result<Self> {
+        let client = block_on(Client::try_default())?
+            .context("Failed to load the Kubernetes client configuration")?;
+        let namespace = namespace.unwrap_or_else(|| client.default_namespace().to_string());
+        Ok(Self { client, namespace })
+    }
+
+    /// Current events, then (with `watch`) new and updated ones as they happen
+    pub fn events(&self, watch: bool) -> Result<Box<dyn Iterator<Item = KubeEvent>>> {
+        let api: Api<Event> = Api::namespaced(self.client.clone(), &self.namespace);
+        if !watch {
+            let list = block_on(api.list(&ListParams::default()))?
+                .with_context(|| format!("Failed to list events in {}", self.namespace))?;
+            let current: Vec<KubeEvent> = list.items.iter().filter_map(event_of).collect();
+            return Ok(Box::new(current.into_iter()));
+        }
+        // The watcher lists current events first, then watches, relisting
+        // (with backoff) when the watch breaks
+        let stream = watcher(api, watcher::Config::default())
+            .default_backoff()
+            .boxed();
+        Ok(Box::new(WatchedEvents { stream }))
+    }
+
+    /// Manifest of the object an event is about
+    pub fn get(&self, event: &KubeEvent) -> Result<Value> {
+        let gvk = GroupVersionKind::try_from(TypeMeta {
+            api_version: event.api_version.clone(),
+            kind: event.kind.clone(),
+        })?;
+        let object = block_on(async {
+            let (resource, capabilities) = pinned_kind(&self.client, &gvk).await?;
+            let api: Api<DynamicObject> = match capabilities.scope {
+                Scope::Namespaced => {
+                    Api::namespaced_with(self.client.clone(), &event.namespace, &resource)
+                }
+                Scope::Cluster => Api::all_with(self.client.clone(), &resource),
+            };
+            api.get(&event.name).await
+        })?
+        .with_context(|| format!("Failed to get {}/{}", event.kind, event.name))?;
+        Ok(serde_json::to_value(object)?)
+    }
+
+    /// Last lines logged by a container before it last terminated
+    pub fn previous_logs(
+        &self,
+        namespace: &str,
+        pod: &str,
+        container: &str,
+        tail: usize,
+    ) -> Result<Vec<String>> {
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        let params = LogParams {
+            container: Some(container.to_string()),
+            previous: true,
+            tail_lines: Some(tail as i64),
+            ..LogParams::default()
+        };
+        let logs = block_on(api.logs(pod, &params))?
+            .with_context(|| format!("Failed to read the logs of {}/{}", pod, container))?;
+        Ok(logs.lines().map(str::to_string).collect())
+    }
+}
+
+fn event_of(event: &Event) -> Option<KubeEvent> {
+    parse_event(&serde_json::to_value(event).ok()?)
+}
+
+/// Events from a watcher, waited for one at a time
+struct WatchedEvents {
+    stream: BoxStream<'static, Result<watcher::Event<Event>, watcher::Error>>,
+}
+
+impl Iterator for WatchedEvents {
+    type Item = KubeEvent;
+
+    fn next(&mut self) -> Option<KubeEvent> {
+        loop {
+            // Errors are retried by the backoff, so only the end of the stream stops
+            match block_on(self.stream.next()).ok()?? {
+                Ok(watcher::Event::Apply(event) | watcher::Event::InitApply(event)) => {
+                    if let Some(event) = event_of(&event) {
+                        return Some(event);
+                    }
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn crashing_pod() -> Value {
+        json!({
+            "kind": "Pod",
+            "metadata": {
+                "name": "api-7d9f8c6b5-x2x7q",
+                "namespace": "prod",
+                "labels": {"pod-template-hash": "7d9f8c6b5"},
+                "ownerReferences": [
+                    {"kind": "ReplicaSet", "name": "api-7d9f8c6b5", "controller": true}
+                ]
+            },
+            "spec": {
+                "containers": [
+                    {
+                        "name": "api",
+                        "image": "api-server:1.4",
+                        "resources": {"limits": {"memory": "128Mi"}},
+                        "ports": [{"containerPort": 8080}]
+                    },
+                    {"name": "proxy", "image": "envoy:1.30"}
+                ]
+            },
+            "status": {
+                "containerStatuses": [
+                    {
+                        "name": "api",
+                        "restartCount": 4,
+                        "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+                        "lastState": {"terminated": {"reason": "OOMKilled", "exitCode": 137}}
+                    },
+                    {"name": "proxy", "restartCount": 0, "state": {"running": {}}}
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_crashing_pod_is_grouped_by_deployment() {
+        let pod = crashing_pod();
+        let workload = workload_of(&pod).unwrap();
+        assert_eq!(workload.to_string(), "deployment/api");
+
+        assert_eq!(
+            container_failures(&pod),
+            vec![ContainerFailure {
+                container: "api".to_string(),
+                reason: "CrashLoopBackOff (OOMKilled)".to_string(),
+                exit_code: Some(137),
+                restart_count: 4,
+            }]
+        );
+
+        let snippet = manifest_snippet(&pod, Some("api")).unwrap();
+        assert!(snippet.contains("128Mi"));
+        assert!(!snippet.contains("containerPort"));
+        assert!(!snippet.contains("envoy"));
+    }
+
+    #[test]
+    fn test_api_events_are_parsed() {
+        let events: Vec<Event> = serde_json::from_value(json!([
+            {
+                "metadata": {"name": "api.17f3", "namespace": "prod"},
+                "involvedObject": {
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "name": "api-7d9f8c6b5-x2x7q",
+                    "namespace": "prod"
+                },
+                "reason": "BackOff",
+                "message": "Back-off restarting failed container api\n",
+                "type": "Warning"
+            },
+            {
+                "metadata": {"name": "node-1.17f4", "namespace": "default"},
+                "involvedObject": {"kind": "Node", "name": "node-1"},
+                "reason": "NodeReady",
+                "type": "Normal"
+            }
+        ]))
+        .unwrap();
+        let events: Vec<KubeEvent> = events.iter().filter_map(event_of).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].reason, "BackOff");
+        assert_eq!(events[0].namespace, "prod");
+        assert_eq!(events[0].api_version, "v1");
+        assert_eq!(
+            events[0].message,
+            "Back-off restarting failed container api"
+        );
+        assert!(events[0].warning);
+        assert_eq!(events[1].namespace, "default");
+        assert!(!events[1].warning);
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long writers wait for a lock before giving up
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Process that holds (or last held without releasing) an exclusive lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl LockHolder {
+    fn parse(content: &str) -> Option<Self> {
+        let mut parts = content.split_whitespace();
+        let pid = parts.next()?.parse().ok()?;
+        let acquired_at = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+        Some(Self {
+            pid,
+            acquired_at: acquired_at.with_timezone(&Utc),
+        })
+    }
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} since {}",
+            self.pid,
+            self.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Any number of holders, excluded by an exclusive holder
+    Shared,
+    Exclusive,
+}
+
+/// Advisory lock on `.fukura/locks/<name>.lock`, shared by the CLI and the
+/// daemon. The OS releases it when the holding process exits, so a crash
+/// never blocks other writers; exclusive holders also record their pid in the
+/// file and clear it on release, which makes a crashed holder detectable.
+#[derive(Debug)]
+pub struct RepoLock {
+    file: File,
+    path: PathBuf,
+    mode: LockMode,
+    recovered: Option<LockHolder>,
+}
+
+impl RepoLock {
+    pub fn exclusive(locks_dir: &Path, name: &str) -> Result<Self> {
+        Self::acquire(locks_dir, name, LockMode::Exclusive, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    pub fn shared(locks_dir: &Path, name: &str) -> Result<Self> {
+        Self::acquire(locks_dir, name, LockMode::Shared, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    pub fn acquire(
+        locks_dir: &Path,
+        name: &str,
+        mode: LockMode,
+        timeout: Duration,
+    ) -> Result<Self> {
+        fs::create_dir_all(locks_dir)?;
+        let path = locks_dir.join(format!("{}.lock", name));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let attempt = match mode {
+                LockMode::Shared => FileExt::try_lock_shared(&file),
+                LockMode::Exclusive => FileExt::try_lock_exclusive(&file),
+            };
+            match attempt {
+                Ok(()) => break,
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if Instant::now() >= deadline {
+                        let holder = read_holder(&mut file)
+                            .map(|holder| format!(" (held by {})", holder))
+                            .unwrap_or_default();
+                        bail!(
+                            "Timed out waiting for the {} lock{}\n💡 Tip: Another fuku process is writing; retry once it finishes",
+                            name,
+                            holder
+                        );
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+                }
+            }
+        }
+
+        let mut lock = Self {
+            file,
+            path,
+            mode,
+            recovered: None,
+        };
+        if mode == LockMode::Exclusive {
+            // A holder record we could lock over belongs to a process that died
+            // before releasing
+            lock.recovered = read_holder(&mut lock.file);
+            if let Some(stale) = &lock.recovered {
+                warn!("Recovered stale {} lock left by {}", name, stale);
+            }
+            lock.write_holder()?;
+        }
+        Ok(lock)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Holder of a lock that was never released, if this acquisition found one
+    pub fn recovered(&self) -> Option<&LockHolder> {
+        self.recovered.as_ref()
+    }
+
+    fn write_holder(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        writeln!(
+            self.file,
+            "{} {}",
+            std::process::id(),
+            Utc::now().to_rfc3339()
+        )?;
+        self.file.flush()
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        if self.mode == LockMode::Exclusive {
+            let _ = self.file.set_len(0);
+        }
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+fn read_holder(file: &mut File) -> Option<LockHolder> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    LockHolder::parse(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SHORT: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_exclusive_lock_times_out_with_holder() {
+        let temp_dir = TempDir::new().unwrap();
+        let held = RepoLock::exclusive(temp_dir.path(), "refs").unwrap();
+
+        let err = RepoLock::acquire(temp_dir.path(), "refs", LockMode::Shared, SHORT).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("refs lock"));
+        assert!(message.contains(&format!("pid {}", std::process::id())));
+
+        drop(held);
+        let lock = RepoLock::acquire(temp_dir.path(), "refs", LockMode::Exclusive, SHORT).unwrap();
+        assert!(lock.recovered().is_none());
+        assert!(lock.path().ends_with("refs.lock"));
+    }
+
+    #[test]
+    fn test_shared_locks_coexist() {
+        let temp_dir = TempDir::new().unwrap();
+        let _a = RepoLock::shared(temp_dir.path(), "objects").unwrap();
+        let _b = RepoLock::shared(temp_dir.path(), "objects").unwrap();
+        assert!(RepoLock::acquire(temp_dir.path(), "objects", LockMode::Exclusive, SHORT).is_err());
+    }
+
+    #[test]
+    fn test_detects_stale_holder() {
+        let temp_dir = TempDir::new().unwrap();
+        // What a process that crashed while holding the lock leaves behind
+        fs::write(
+            temp_dir.path().join("pack.lock"),
+            "4242 2024-06-10T09:00:00+00:00\n",
+        )
+        .unwrap();
+
+        let lock = RepoLock::exclusive(temp_dir.path(), "pack").unwrap();
+        assert_eq!(lock.recovered().map(|holder| holder.pid), Some(4242));
+        drop(lock);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("pack.lock")).unwrap(),
+            ""
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Lines printed by a long-running child process (`docker logs -f`,
+/// `journalctl -f`, ...) on stdout and stderr, in the order they arrive.
+/// The process is killed when this is dropped.
+pub struct LogProcess {
+    child: Child,
+    lines: Receiver<String>,
+}
+
+impl LogProcess {
+    pub fn spawn(mut command: Command) -> Result<Self> {
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run {}; is it installed?", program))?;
+
+        let (tx, lines) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, tx.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, tx);
+        }
+        Ok(Self { child, lines })
+    }
+}
+
+fn forward_lines<R: Read + Send + 'static>(reader: R, tx: Sender<String>) {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+impl Iterator for LogProcess {
+    type Item = String;
+
+    /// Blocks until the next line; ends when both streams are closed
+    fn next(&mut self) -> Option<String> {
+        self.lines.recv().ok()
+    }
+}
+
+impl Drop for LogProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
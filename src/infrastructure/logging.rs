@@ -0,0 +1,217 @@
+use anyhow::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Rotate once the active log grows past this size
+pub const DEFAULT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Number of rotated files kept next to the active log (`daemon.log.1` ...)
+pub const DEFAULT_MAX_FILES: usize = 3;
+
+/// Location of the daemon log for a repository
+pub fn daemon_log_path(repo_root: &Path) -> PathBuf {
+    repo_root.join(".fukura").join("logs").join("daemon.log")
+}
+
+/// Send daemon tracing output to `.fukura/logs/daemon.log`, and to stderr as
+/// well when running attached to a terminal. `FUKURA_LOG` overrides the level
+/// filter (e.g. `FUKURA_LOG=debug`).
+pub fn init_daemon_logging(repo_root: &Path) -> Result<PathBuf> {
+    let path = daemon_log_path(repo_root);
+    let writer = RotatingLog::open(&path, DEFAULT_MAX_BYTES, DEFAULT_MAX_FILES)?;
+
+    let filter = EnvFilter::try_from_env("FUKURA_LOG").unwrap_or_else(|_| EnvFilter::new("info"));
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_target(true)
+        .with_writer(writer);
+    let stderr_layer = is_terminal::is_terminal(io::stderr())
+        .then(|| tracing_subscriber::fmt::layer().with_writer(io::stderr));
+
+    // A subscriber may already be installed (e.g. in tests); keep it
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init();
+    Ok(path)
+}
+
+/// Append-only log file with size-based rotation
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    state: Mutex<LogState>,
+}
+
+struct LogState {
+    file: File,
+    size: u64,
+}
+
+impl RotatingLog {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            state: Mutex::new(LogState { file, size }),
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// Shift `daemon.log.N` → `daemon.log.N+1`, dropping the oldest, and start a new file
+    fn rotate(&self, state: &mut LogState) -> io::Result<()> {
+        state.file.flush()?;
+        if self.max_files == 0 {
+            state.file = File::create(&self.path)?;
+            state.size = 0;
+            return Ok(());
+        }
+        let _ = fs::remove_file(self.rotated_path(self.max_files));
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        state.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        state.size = 0;
+        Ok(())
+    }
+}
+
+/// Writer handed out per log event; holds the lock for the whole event
+pub struct RotatingLogWriter<'a> {
+    log: &'a RotatingLog,
+    state: MutexGuard<'a, LogState>,
+}
+
+impl Write for RotatingLogWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.state.size > 0 && self.state.size + buf.len() as u64 > self.log.max_bytes {
+            self.log.rotate(&mut self.state)?;
+        }
+        let written = self.state.file.write(buf)?;
+        self.state.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state.file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingLog {
+    type Writer = RotatingLogWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        RotatingLogWriter { log: self, state }
+    }
+}
+
+/// Last `count` lines of a log file
+pub fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    let lines: Vec<String> = reader.lines().map_while(|line| line.ok()).collect();
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.into_iter().skip(skip).collect())
+}
+
+/// Print lines appended to `path` as they arrive, like `tail -f`.
+/// Follows the new file when the log rotates.
+pub fn follow(path: &Path, mut emit: impl FnMut(&str)) -> Result<()> {
+    let mut position = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut pending = String::new();
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        let Ok(len) = fs::metadata(path).map(|m| m.len()) else {
+            continue;
+        };
+        if len < position {
+            // Rotated or truncated: start from the top of the new file
+            position = 0;
+        }
+        if len == position {
+            continue;
+        }
+
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(position))?;
+        let mut reader = BufReader::new(file);
+        loop {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            position += read as u64;
+            pending.push_str(&line);
+            if pending.ends_with('\n') {
+                emit(pending.trim_end_matches(['\n', '\r']));
+                pending.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotation_keeps_limited_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs").join("daemon.log");
+        let log = RotatingLog::open(&path, 64, 2).unwrap();
+
+        for i in 0..20 {
+            let mut writer = log.make_writer();
+            writeln!(writer, "line {:02} padding padding padding", i).unwrap();
+        }
+
+        assert!(fs::metadata(&path).unwrap().len() <= 64);
+        assert!(log.rotated_path(1).exists());
+        assert!(log.rotated_path(2).exists());
+        assert!(!log.rotated_path(3).exists());
+
+        let last = tail_lines(&path, 1).unwrap();
+        assert_eq!(last, vec!["line 19 padding padding padding".to_string()]);
+    }
+
+    #[test]
+    fn test_tail_lines_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(tail_lines(&temp_dir.path().join("nope.log"), 10)
+            .unwrap()
+            .is_empty());
+    }
+}
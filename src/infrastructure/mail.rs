@@ -0,0 +1,176 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDate, Timelike, Weekday};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::report::Report;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Weekly digests go out on Mondays from this hour, local time
+const WEEKLY_DIGEST_HOUR: u32 = 9;
+
+/// Whether `address` is an address, or a mailbox like `Team <team@example.com>`
+pub fn is_address(address: &str) -> bool {
+    address.trim().parse::<Mailbox>().is_ok()
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .trim()
+        .parse()
+        .with_context(|| format!("'{}' is not an email address", address))
+}
+
+/// Sends mail through the `[smtp]` server
+pub struct Mailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::Starttls => SmtpTransport::starttls_relay(&config.host)?,
+            SmtpTls::Tls => SmtpTransport::relay(&config.host)?,
+            SmtpTls::None => SmtpTransport::builder_dangerous(&config.host),
+        }
+        .port(config.port())
+        .timeout(Some(SEND_TIMEOUT));
+        let builder = match &config.username {
+            Some(username) => {
+                let password = std::env::var(&config.password_env).with_context(|| {
+                    format!(
+                        "Set {} to the SMTP password of {}",
+                        config.password_env, username
+                    )
+                })?;
+                builder.credentials(Credentials::new(username.clone(), password))
+            }
+            None => builder,
+        };
+        Ok(Self {
+            transport: builder.build(),
+            from: mailbox(&config.from)?,
+        })
+    }
+
+    /// Send `report` to `to` as Markdown with an HTML alternative. Returns
+    /// the recipients.
+    pub fn send_report(&self, report: &Report, to: &[String]) -> Result<Vec<String>> {
+        if to.is_empty() {
+            bail!("No recipients; set to = [...] under [smtp] or pass --to");
+        }
+        let message = report_message(&self.from, report, to)?;
+        self.transport
+            .send(&message)
+            .context("Failed to send the report")?;
+        Ok(to.to_vec())
+    }
+}
+
+fn report_message(from: &Mailbox, report: &Report, to: &[String]) -> Result<Message> {
+    let mut builder = Message::builder()
+        .from(from.clone())
+        .subject(format!("Fukura report: {}", report.period()));
+    for address in to {
+        builder = builder.to(mailbox(address)?);
+    }
+    Ok(builder.multipart(MultiPart::alternative_plain_html(
+        report.to_markdown(),
+        report.to_html(),
+    ))?)
+}
+
+/// When the daemon last sent the weekly digest, in `.fukura/state/digest`
+pub struct DigestSchedule {
+    path: PathBuf,
+}
+
+impl DigestSchedule {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            path: root.join(".fukura").join("state").join("digest"),
+        }
+    }
+
+    fn last_sent(&self) -> Option<NaiveDate> {
+        std::fs::read_to_string(&self.path)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Whether a digest is due: Monday from 9:00, once
+    pub fn due(&self, now: DateTime<Local>) -> bool {
+        now.weekday() == Weekday::Mon
+            && now.hour() >= WEEKLY_DIGEST_HOUR
+            && self.last_sent() != Some(now.date_naive())
+    }
+
+    pub fn mark_sent(&self, now: DateTime<Local>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, now.date_naive().to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_message_has_text_and_html() {
+        let now = SystemTime::now();
+        let report = Report::build(
+            Vec::new(),
+            &[],
+            &HashMap::new(),
+            now - Duration::from_secs(7 * 24 * 3600),
+            now,
+        );
+        let from = mailbox("Fukura <fukura@example.com>").unwrap();
+        let to = vec![
+            "team@example.com".to_string(),
+            "ana@example.com".to_string(),
+        ];
+        let message = report_message(&from, &report, &to).unwrap();
+        let text = String::from_utf8(message.formatted()).unwrap();
+        assert!(text.contains("Subject: Fukura report:"));
+        assert!(text.contains("team@example.com"));
+        assert!(text.contains("text/plain"));
+        assert!(text.contains("text/html"));
+
+        assert!(report_message(&from, &report, &["not an address".to_string()]).is_err());
+        assert!(is_address("Team <team@example.com>"));
+        assert!(!is_address("team"));
+    }
+
+    #[test]
+    fn test_digest_is_due_once_on_monday_morning() {
+        let dir = TempDir::new().unwrap();
+        let schedule = DigestSchedule::new(dir.path());
+        // 2024-05-06 was a Monday
+        let early = Local.with_ymd_and_hms(2024, 5, 6, 8, 0, 0).unwrap();
+        let morning = Local.with_ymd_and_hms(2024, 5, 6, 9, 30, 0).unwrap();
+        let tuesday = Local.with_ymd_and_hms(2024, 5, 7, 10, 0, 0).unwrap();
+        assert!(!schedule.due(early));
+        assert!(schedule.due(morning));
+        assert!(!schedule.due(tuesday));
+
+        schedule.mark_sent(morning).unwrap();
+        assert!(!schedule.due(morning + chrono::Duration::hours(1)));
+        assert!(schedule.due(morning + chrono::Duration::weeks(1)));
+    }
+}
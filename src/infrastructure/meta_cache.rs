@@ -0,0 +1,392 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::index::SearchHit;
+use crate::models::NoteRecord;
+use crate::taxonomy::ErrorTaxonomy;
+
+/// Bumped when the tables change; an older cache is dropped and rebuilt
+const SCHEMA_VERSION: i64 = 2;
+
+/// Metadata of every indexed note in `.fukura/meta.sqlite`, kept next to the
+/// search index so counts and listings don't load every note. The index and
+/// objects stay the source of truth: [`crate::repo::FukuraRepo`] rebuilds the
+/// cache whenever it disagrees with the index.
+#[derive(Debug)]
+pub struct MetaCache {
+    conn: Mutex<Connection>,
+}
+
+/// How much a tag is used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagStat {
+    pub tag: String,
+    /// Notes carrying the tag
+    pub notes: usize,
+    /// When a note carrying the tag was last updated
+    pub last_used: DateTime<Utc>,
+}
+
+/// Totals answered from the cache alone
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub notes: usize,
+    /// Distinct tags across all notes
+    pub tags: usize,
+    pub last_updated: Option<DateTime<Utc>>,
+    /// Error notes by the tool that failed, `unknown` when not detected
+    pub errors_by_tool: BTreeMap<String, usize>,
+    /// Error notes by class, `other` when not detected
+    pub errors_by_class: BTreeMap<String, usize>,
+}
+
+impl MetaCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        // The daemon and the CLI write at the same time
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS notes;
+                 DROP TABLE IF EXISTS note_tags;",
+            )?;
+        }
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS notes (
+                 object_id TEXT PRIMARY KEY,
+                 title TEXT NOT NULL,
+                 summary TEXT NOT NULL,
+                 author TEXT NOT NULL,
+                 privacy TEXT NOT NULL,
+                 likes INTEGER NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL,
+                 size INTEGER NOT NULL,
+                 error_tool TEXT,
+                 error_class TEXT,
+                 scope TEXT
+             );
+             CREATE INDEX IF NOT EXISTS notes_updated ON notes (updated_at DESC, object_id DESC);
+             CREATE TABLE IF NOT EXISTS note_tags (
+                 object_id TEXT NOT NULL,
+                 tag TEXT NOT NULL,
+                 PRIMARY KEY (object_id, tag)
+             );
+             CREATE INDEX IF NOT EXISTS note_tags_tag ON note_tags (tag);",
+        )?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Number of cached notes
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn()
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Add or refresh notes, in one transaction
+    pub fn upsert(&self, records: &[NoteRecord]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO notes (object_id, title, summary, author, privacy,
+                     likes, created_at, updated_at, size, error_tool, error_class, scope)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+            let mut clear_tags = tx.prepare_cached("DELETE FROM note_tags WHERE object_id = ?1")?;
+            let mut insert_tag = tx.prepare_cached(
+                "INSERT OR IGNORE INTO note_tags (object_id, tag) VALUES (?1, ?2)",
+            )?;
+            for record in records {
+                let hit = SearchHit::from_record(record);
+                let size = record.note.canonical_bytes()?.len() as i64;
+                let taxonomy = ErrorTaxonomy::from_note(&record.note);
+                let (error_tool, error_class) = if taxonomy.is_error() {
+                    (
+                        Some(taxonomy.binary.unwrap_or_else(|| "unknown".to_string())),
+                        Some(taxonomy.error_class.unwrap_or_else(|| "other".to_string())),
+                    )
+                } else {
+                    (None, None)
+                };
+                insert.execute(params![
+                    hit.object_id,
+                    hit.title,
+                    hit.summary,
+                    hit.author,
+                    hit.privacy,
+                    hit.likes,
+                    record.note.created_at.timestamp_millis(),
+                    record.note.updated_at.timestamp_millis(),
+                    size,
+                    error_tool,
+                    error_class,
+                    hit.scope,
+                ])?;
+                clear_tags.execute(params![hit.object_id])?;
+                for tag in &hit.tags {
+                    insert_tag.execute(params![hit.object_id, tag])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Forget notes that left the index
+    pub fn remove(&self, object_ids: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for object_id in object_ids {
+            tx.execute("DELETE FROM notes WHERE object_id = ?1", params![object_id])?;
+            tx.execute(
+                "DELETE FROM note_tags WHERE object_id = ?1",
+                params![object_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replace the whole cache with `records`
+    pub fn rebuild(&self, records: &[NoteRecord]) -> Result<()> {
+        self.conn()
+            .execute_batch("DELETE FROM notes; DELETE FROM note_tags;")?;
+        self.upsert(records)
+    }
+
+    /// `limit` notes starting at `offset`, most recently updated first, as
+    /// the index would return them for an empty query
+    pub fn page(&self, offset: usize, limit: usize) -> Result<Vec<SearchHit>> {
+        let conn = self.conn();
+        let mut select = conn.prepare_cached(
+            "SELECT object_id, title, summary, author, privacy, likes, updated_at, scope
+             FROM notes ORDER BY updated_at DESC, object_id DESC LIMIT ?1 OFFSET ?2",
+        )?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = select.query_map(params![limit, offset as i64], |row| {
+            Ok(SearchHit {
+                object_id: row.get(0)?,
+                title: row.get(1)?,
+                tags: Vec::new(),
+                summary: row.get(2)?,
+                updated_at: from_millis(row.get(6)?),
+                author: row.get(3)?,
+                likes: row.get(5)?,
+                score: 0.0,
+                privacy: row.get(4)?,
+                pinned: false,
+                verified: false,
+                snippet: None,
+                scope: row.get(7)?,
+            })
+        })?;
+        let mut hits = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut tags =
+            conn.prepare_cached("SELECT tag FROM note_tags WHERE object_id = ?1 ORDER BY rowid")?;
+        for hit in &mut hits {
+            hit.tags = tags
+                .query_map(params![hit.object_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+        }
+        Ok(hits)
+    }
+
+    /// Every tag with the number of notes carrying it and when one of them
+    /// was last updated, most used first
+    pub fn tag_stats(&self) -> Result<Vec<TagStat>> {
+        let conn = self.conn();
+        let mut select = conn.prepare_cached(
+            "SELECT note_tags.tag, COUNT(*) AS uses, MAX(notes.updated_at)
+             FROM note_tags JOIN notes ON notes.object_id = note_tags.object_id
+             GROUP BY note_tags.tag ORDER BY uses DESC, note_tags.tag",
+        )?;
+        let rows = select.query_map([], |row| {
+            Ok(TagStat {
+                tag: row.get(0)?,
+                notes: row.get::<_, i64>(1)? as usize,
+                last_used: from_millis(row.get(2)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Notes last updated on each UTC day since `since`, as `YYYY-MM-DD`
+    pub fn updated_per_day(&self, since: DateTime<Utc>) -> Result<BTreeMap<String, usize>> {
+        let conn = self.conn();
+        let mut select = conn.prepare_cached(
+            "SELECT date(updated_at / 1000, 'unixepoch') AS day, COUNT(*)
+             FROM notes WHERE updated_at >= ?1 GROUP BY day",
+        )?;
+        let rows = select.query_map(params![since.timestamp_millis()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let conn = self.conn();
+        let (notes, last_updated): (i64, Option<i64>) =
+            conn.query_row("SELECT COUNT(*), MAX(updated_at) FROM notes", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+        let tags: i64 = conn.query_row("SELECT COUNT(DISTINCT tag) FROM note_tags", [], |row| {
+            row.get(0)
+        })?;
+        let count_by = |column: &str| -> Result<BTreeMap<String, usize>> {
+            let mut select = conn.prepare(&format!(
+                "SELECT {column}, COUNT(*) FROM notes WHERE {column} IS NOT NULL GROUP BY {column}"
+            ))?;
+            let rows = select.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        };
+        Ok(CacheStats {
+            notes: notes as usize,
+            tags: tags as usize,
+            last_updated: last_updated.map(from_millis),
+            errors_by_tool: count_by("error_tool")?,
+            errors_by_class: count_by("error_class")?,
+        })
+    }
+}
+
+fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or_else(Utc::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use crate::repo::FukuraRepo;
+    use tempfile::TempDir;
+
+    fn minute(minutes: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + minutes * 60, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cache_pages_and_counts() {
+        let temp = TempDir::new().unwrap();
+        let cache = MetaCache::open(&temp.path().join("meta.sqlite")).unwrap();
+        cache
+            .upsert(&[
+                NoteBuilder::new("Oldest", "body")
+                    .tag("rust")
+                    .updated_at(minute(0))
+                    .record("a"),
+                NoteBuilder::new("Newest", "body")
+                    .tag("rust")
+                    .tag("cargo")
+                    .updated_at(minute(10))
+                    .record("b"),
+                NoteBuilder::new("Middle", "body")
+                    .updated_at(minute(5))
+                    .record("c"),
+            ])
+            .unwrap();
+
+        let titles: Vec<String> = cache
+            .page(0, 10)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.title)
+            .collect();
+        assert_eq!(titles, ["Newest", "Middle", "Oldest"]);
+        let second = cache.page(1, 1).unwrap();
+        assert_eq!(second[0].object_id, "c");
+        assert_eq!(cache.page(0, 1).unwrap()[0].tags, ["cargo", "rust"]);
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.notes, 3);
+        assert_eq!(stats.tags, 2);
+        assert_eq!(
+            stats.last_updated,
+            Some(Utc.timestamp_opt(1_700_000_600, 0).unwrap())
+        );
+
+        let newest = Utc.timestamp_opt(1_700_000_600, 0).unwrap();
+        let tags = cache.tag_stats().unwrap();
+        assert_eq!(
+            tags.iter()
+                .map(|stat| (stat.tag.as_str(), stat.notes))
+                .collect::<Vec<_>>(),
+            [("rust", 2), ("cargo", 1)]
+        );
+        assert!(tags.iter().all(|stat| stat.last_used == newest));
+        let since = Utc.timestamp_opt(1_700_000_000 + 300, 0).unwrap();
+        let days = cache.updated_per_day(since).unwrap();
+        assert_eq!(
+            days.into_iter().collect::<Vec<_>>(),
+            [("2023-11-14".to_string(), 2)]
+        );
+
+        cache.remove(&["b".to_string()]).unwrap();
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.notes, stats.tags), (2, 1));
+    }
+
+    #[test]
+    fn test_repo_keeps_cache_in_step() {
+        let temp = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp.path(), true).unwrap();
+        let first = repo
+            .store_note(NoteBuilder::new("Proxy", "Set HTTPS_PROXY").build())
+            .unwrap();
+        let second = repo
+            .store_note(NoteBuilder::new("Docker", "Restart the daemon").build())
+            .unwrap();
+        assert_eq!(repo.meta_cache().unwrap().len().unwrap(), 2);
+
+        repo.delete_note(&first.object_id).unwrap();
+        let edited = repo
+            .replace_note(
+                &second.object_id,
+                NoteBuilder::new("Docker", "Restart dockerd").build(),
+            )
+            .unwrap();
+        let hits = repo.meta_cache().unwrap().page(0, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].object_id, edited.object_id);
+
+        // A cache out of step with the index is rebuilt on open
+        fs_remove(&repo.meta_cache_path());
+        let reopened = FukuraRepo::open(temp.path()).unwrap();
+        assert_eq!(reopened.meta_cache().unwrap().len().unwrap(), 1);
+    }
+
+    fn fs_remove(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
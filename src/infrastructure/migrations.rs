@@ -0,0 +1,302 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::index::SearchIndex;
+use crate::locks::RepoLock;
+use crate::repo::FukuraRepo;
+
+/// On-disk format written by this build
+pub const CURRENT_VERSION: u32 = 6;
+
+/// Upgrade of the on-disk format from `from` to `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    apply: fn(&FukuraRepo) -> Result<()>,
+}
+
+/// Every migration, in order. Add new ones at the end and bump
+/// [`CURRENT_VERSION`]; migrations only ever move forward.
+pub fn migrations() -> &'static [Migration] {
+    &[
+        Migration {
+            from: 1,
+            description: "Keep a single search index document per note",
+            apply: dedupe_index,
+        },
+        Migration {
+            from: 2,
+            description: "Index note meta for meta.<field> searches",
+            apply: rebuild_indexes,
+        },
+        Migration {
+            from: 3,
+            description: "Store note bodies in the index for search snippets",
+            apply: rebuild_indexes,
+        },
+        Migration {
+            from: 4,
+            description: "Index creation dates and meta facets for search filters",
+            apply: rebuild_indexes,
+        },
+        Migration {
+            from: 5,
+            description: "Index exit codes, binaries, languages and error classes",
+            apply: rebuild_indexes,
+        },
+    ]
+}
+
+/// What `migrate_to` did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    /// Descriptions of the migrations that ran
+    pub applied: Vec<&'static str>,
+    pub backup: Option<PathBuf>,
+}
+
+/// Format version recorded in the repository config. Repositories written
+/// before the field was set count as version 1.
+pub fn repo_version(repo: &FukuraRepo) -> Result<u32> {
+    let table = read_config_table(&repo.config_path())?;
+    let version = table
+        .get("version")
+        .and_then(|value| value.as_integer())
+        .unwrap_or(1);
+    Ok(u32::try_from(version).unwrap_or(1).max(1))
+}
+
+/// Migrations needed to bring `version` up to `target`
+pub fn pending(version: u32, target: u32) -> Vec<&'static Migration> {
+    migrations()
+        .iter()
+        .filter(|m| m.from >= version && m.from < target)
+        .collect()
+}
+
+/// Bring a repository opened by an older build up to [`CURRENT_VERSION`].
+/// Called whenever a repository is opened; a no-op once it is current.
+pub fn upgrade(repo: &FukuraRepo) -> Result<Option<MigrationReport>> {
+    let version = repo_version(repo)?;
+    if version > CURRENT_VERSION {
+        bail!(
+            "Repository format v{} is newer than this fuku supports (v{})\n💡 Tip: Upgrade fuku to open this repository",
+            version,
+            CURRENT_VERSION
+        );
+    }
+    if version == CURRENT_VERSION {
+        return Ok(None);
+    }
+    if repo.is_read_only() {
+        bail!(
+            "Repository format v{} needs upgrading to v{}, but the repository is read-only\n💡 Tip: Run fuku migrate where the repository is writable",
+            version,
+            CURRENT_VERSION
+        );
+    }
+    let report = migrate_to(repo, CURRENT_VERSION, true)?;
+    if !report.applied.is_empty() {
+        eprintln!(
+            "Info: Upgraded repository format v{} → v{}{}",
+            report.from,
+            report.to,
+            report
+                .backup
+                .as_ref()
+                .map(|path| format!(" (backup in {})", path.display()))
+                .unwrap_or_default()
+        );
+    }
+    Ok(Some(report))
+}
+
+/// Run the migrations from the repository's version up to `target`, after
+/// backing up the mutable state (config, refs and search indexes) when `backup`
+/// is set. Objects are content-addressed and never rewritten in place.
+pub fn migrate_to(repo: &FukuraRepo, target: u32, backup: bool) -> Result<MigrationReport> {
+    if target > CURRENT_VERSION {
+        bail!(
+            "Unknown repository format v{} (latest is v{})",
+            target,
+            CURRENT_VERSION
+        );
+    }
+    let _lock = RepoLock::exclusive(&repo.locks_dir(), "migrate")?;
+    // Another process may have migrated while we waited for the lock
+    let from = repo_version(repo)?;
+    if target < from {
+        bail!(
+            "Cannot downgrade repository format v{} to v{}\n💡 Tip: Restore a backup from .fukura/backups instead",
+            from,
+            target
+        );
+    }
+
+    let steps = pending(from, target);
+    let mut report = MigrationReport {
+        from,
+        to: from,
+        applied: Vec::new(),
+        backup: None,
+    };
+    if steps.is_empty() {
+        return Ok(report);
+    }
+    if backup {
+        report.backup = Some(backup_state(repo, from)?);
+    }
+    for step in steps {
+        (step.apply)(repo)
+            .with_context(|| format!("Migration v{} → v{} failed", step.from, step.from + 1))?;
+        // Record progress after every step so a failure resumes where it stopped
+        write_version(&repo.config_path(), step.from + 1)?;
+        report.to = step.from + 1;
+        report.applied.push(step.description);
+    }
+    Ok(report)
+}
+
+fn read_config_table(path: &Path) -> Result<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Update only the version key, leaving keys unknown to this build intact
+fn write_version(path: &Path, version: u32) -> Result<()> {
+    let mut table = read_config_table(path)?;
+    table.insert("version".to_string(), toml::Value::Integer(version.into()));
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, toml::to_string_pretty(&table)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn backup_state(repo: &FukuraRepo, version: u32) -> Result<PathBuf> {
+    let dot_dir = repo.root().join(".fukura");
+    let backup = dot_dir.join("backups").join(format!(
+        "v{}-{}",
+        version,
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    fs::create_dir_all(&backup)?;
+    let config = repo.config_path();
+    if config.exists() {
+        fs::copy(&config, backup.join("config"))?;
+    }
+    copy_dir(&repo.refs_dir(), &backup.join("refs"))?;
+    copy_dir(&repo.index_dir(), &backup.join("index"))?;
+    copy_dir(&repo.archive_dir(), &backup.join("archive"))?;
+    Ok(backup)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to back up {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// v1 → v2: storing an existing note again used to add a second index
+/// document, so it showed up twice in listings
+fn dedupe_index(repo: &FukuraRepo) -> Result<()> {
+    // Not `repo.index()`: a later migration may replace the index, and the
+    // repository would keep this handle
+    let index = SearchIndex::open_or_create(repo)?;
+    let records = index
+        .duplicate_ids()?
+        .iter()
+        .filter_map(|id| repo.load_note(id).ok())
+        .collect::<Vec<_>>();
+    index.add_notes_batch(&records)
+}
+
+/// For schema changes (v2 → v3 through v4 → v5): the schema of an existing index
+/// can't change, so both indexes are rebuilt with the current one
+fn rebuild_indexes(repo: &FukuraRepo) -> Result<()> {
+    repo.rebuild_indexes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NoteRecord;
+    use crate::note_service::NoteBuilder;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_registry_is_contiguous() {
+        for (i, migration) in migrations().iter().enumerate() {
+            assert_eq!(migration.from, i as u32 + 1);
+        }
+        assert_eq!(migrations().len() as u32 + 1, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_upgrade_v1_dedupes_index_and_keeps_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        let record = repo
+            .store_note(
+                NoteBuilder::new("Proxy install fails", "Update the proxy credentials").build(),
+            )
+            .unwrap();
+
+        // Simulate a v1 repository whose index holds the note twice
+        write_version(&repo.config_path(), 1).unwrap();
+        let index = SearchIndex::open_or_create(&repo).unwrap();
+        index.remove_note(&record.object_id).unwrap();
+        let raw = NoteRecord {
+            object_id: record.object_id.clone(),
+            note: record.note.clone(),
+        };
+        for _ in 0..2 {
+            // v1 appended without replacing the existing document
+            index.append_unchecked(&raw).unwrap();
+        }
+        assert_eq!(
+            index.duplicate_ids().unwrap(),
+            vec![record.object_id.clone()]
+        );
+        drop(index);
+
+        let report = upgrade(&repo).unwrap().unwrap();
+        assert_eq!((report.from, report.to), (1, CURRENT_VERSION));
+        assert_eq!(report.applied.len(), CURRENT_VERSION as usize - 1);
+        assert!(report.backup.unwrap().join("config").exists());
+        let index = SearchIndex::open_or_create(&repo).unwrap();
+        assert!(index.duplicate_ids().unwrap().is_empty());
+        assert_eq!(index.object_ids().unwrap(), vec![record.object_id.clone()]);
+        assert_eq!(repo.list_all_notes().unwrap().len(), 1);
+        assert_eq!(repo_version(&repo).unwrap(), CURRENT_VERSION);
+        assert!(upgrade(&repo).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_refuses_downgrade_and_newer_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = FukuraRepo::init(temp_dir.path(), true).unwrap();
+        assert!(migrate_to(&repo, 1, true).is_err());
+
+        write_version(&repo.config_path(), CURRENT_VERSION + 1).unwrap();
+        assert!(FukuraRepo::open(temp_dir.path()).is_err());
+    }
+}
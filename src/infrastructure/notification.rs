@@ -10,6 +10,12 @@ pub struct NotificationConfig {
     pub enabled: bool,
     pub show_on_error: bool,
     pub show_on_solution_found: bool,
+    /// What the "View note" action button does when clicked
+    #[serde(default)]
+    pub click_action: ClickAction,
+    /// Base URL of `fuku serve`, used when `click_action = "serve"`
+    #[serde(default = "NotificationConfig::default_serve_url")]
+    pub serve_url: String,
 }
 
 impl Default for NotificationConfig {
@@ -18,14 +24,53 @@ impl Default for NotificationConfig {
             enabled: true,
             show_on_error: true,
             show_on_solution_found: true,
+            click_action: ClickAction::default(),
+            serve_url: Self::default_serve_url(),
         }
     }
 }
 
+impl NotificationConfig {
+    fn default_serve_url() -> String {
+        "http://127.0.0.1:8765".to_string()
+    }
+}
+
+/// Behaviour of the notification action button
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ClickAction {
+    /// Run `fuku open <id>` to render the note in the browser
+    #[default]
+    Open,
+    /// Open `<serve_url>/notes/<id>` from a running `fuku serve`
+    Serve,
+    /// Plain notification without an action button
+    None,
+}
+
+/// Resolved target for a notification click
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClickTarget {
+    Command { program: PathBuf, args: Vec<String> },
+    Url(String),
+}
+
 /// Notification manager for OS-native notifications
 pub struct NotificationManager {
     config: NotificationConfig,
     config_path: PathBuf,
+    repo_path: PathBuf,
 }
 
 impl NotificationManager {
@@ -48,9 +93,50 @@ impl NotificationManager {
         Ok(Self {
             config,
             config_path,
+            repo_path: repo_path.to_path_buf(),
         })
     }
 
+    /// Resolve what clicking "View note" should do for a note
+    pub fn click_target(&self, note_id: &str) -> Option<ClickTarget> {
+        match self.config.click_action {
+            ClickAction::Open => {
+                let program = std::env::current_exe().ok()?;
+                Some(ClickTarget::Command {
+                    program,
+                    args: vec![
+                        "--repo".to_string(),
+                        self.repo_path.display().to_string(),
+                        "open".to_string(),
+                        note_id.to_string(),
+                    ],
+                })
+            }
+            ClickAction::Serve => Some(ClickTarget::Url(format!(
+                "{}/notes/{}",
+                self.config.serve_url.trim_end_matches('/'),
+                note_id
+            ))),
+            ClickAction::None => None,
+        }
+    }
+
+    /// Execute a click target (spawned detached, never blocks the caller)
+    pub fn run_click_target(target: &ClickTarget) -> Result<()> {
+        match target {
+            ClickTarget::Command { program, args } => {
+                std::process::Command::new(program)
+                    .args(args)
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn()?;
+            }
+            ClickTarget::Url(url) => open::that_detached(url)?,
+        }
+        Ok(())
+    }
+
     /// Show error notification
     pub fn notify_error(&self, command: &str, error_message: &str) -> Result<()> {
         if !self.config.enabled || !self.config.show_on_error {
@@ -99,10 +185,10 @@ impl NotificationManager {
         );
 
         #[cfg(target_os = "macos")]
-        self.show_notification_detailed_macos(summary, &body)?;
+        self.show_notification_detailed_macos(summary, &body, note_id)?;
 
         #[cfg(target_os = "linux")]
-        self.show_notification_detailed_linux(summary, &body, short_id)?;
+        self.show_notification_detailed_linux(summary, &body, note_id)?;
 
         #[cfg(target_os = "windows")]
         self.show_notification_detailed_windows(summary, &body)?;
@@ -147,10 +233,10 @@ impl NotificationManager {
         );
 
         #[cfg(target_os = "macos")]
-        self.show_notification_detailed_macos(&summary, &body)?;
+        self.show_notification_detailed_macos(&summary, &body, note_id)?;
 
         #[cfg(target_os = "linux")]
-        self.show_notification_detailed_linux(&summary, &body, short_id)?;
+        self.show_notification_detailed_linux(&summary, &body, note_id)?;
 
         #[cfg(target_os = "windows")]
         self.show_notification_detailed_windows(&summary, &body)?;
@@ -183,6 +269,57 @@ impl NotificationManager {
         Ok(())
     }
 
+    /// Tell the user a recording started on its own after repeated failures
+    pub fn notify_recording_started(&self, command: &str, failures: usize) -> Result<()> {
+        if !self.config.enabled || !self.config.show_on_error {
+            return Ok(());
+        }
+
+        let summary = "Fukura: Recording Started";
+        let body = format!(
+            "{} failed {} times in a row.\nRecording until it succeeds.\n\n  fuku rec --status",
+            Self::truncate(command, 40),
+            failures
+        );
+
+        #[cfg(target_os = "macos")]
+        self.show_notification_macos(summary, &body, None)?;
+
+        #[cfg(target_os = "linux")]
+        self.show_notification_linux(summary, &body)?;
+
+        #[cfg(target_os = "windows")]
+        self.show_notification_windows(summary, &body)?;
+
+        Ok(())
+    }
+
+    /// Tell the user an automatic recording was saved as a resolution note
+    pub fn notify_recording_saved(&self, title: &str, note_id: &str) -> Result<()> {
+        if !self.config.enabled || !self.config.show_on_solution_found {
+            return Ok(());
+        }
+
+        let short_id = &note_id[..8.min(note_id.len())];
+        let summary = "Fukura: Fix Recorded";
+        let body = format!(
+            "{}\n\nView the steps:\n  fuku view {}",
+            Self::truncate(title, 60),
+            short_id
+        );
+
+        #[cfg(target_os = "macos")]
+        self.show_notification_detailed_macos(summary, &body, note_id)?;
+
+        #[cfg(target_os = "linux")]
+        self.show_notification_detailed_linux(summary, &body, note_id)?;
+
+        #[cfg(target_os = "windows")]
+        self.show_notification_detailed_windows(summary, &body)?;
+
+        Ok(())
+    }
+
     #[cfg(target_os = "macos")]
     fn show_notification_macos(
         &self,
@@ -207,10 +344,44 @@ impl NotificationManager {
     }
 
     #[cfg(target_os = "macos")]
-    fn show_notification_detailed_macos(&self, summary: &str, body: &str) -> Result<()> {
-        // macOS: Use osascript for guaranteed notifications (BEST PRACTICE)
+    fn show_notification_detailed_macos(
+        &self,
+        summary: &str,
+        body: &str,
+        note_id: &str,
+    ) -> Result<()> {
         use std::process::Command;
 
+        // macOS: terminal-notifier supports click actions, osascript does not
+        if let (Some(target), Ok(notifier)) = (
+            self.click_target(note_id),
+            which::which("terminal-notifier"),
+        ) {
+            let mut cmd = Command::new(notifier);
+            cmd.args(["-title", summary])
+                .args(["-message", &body.replace('\n', " ")])
+                .args(["-actions", "View note"])
+                .args(["-sound", "Submarine"])
+                .args(["-group", "fukura"]);
+            match target {
+                ClickTarget::Command { program, args } => {
+                    let command_line = std::iter::once(program.display().to_string())
+                        .chain(args)
+                        .map(|part| format!("'{}'", part.replace('\'', r"'\''")))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    cmd.args(["-execute", &command_line]);
+                }
+                ClickTarget::Url(url) => {
+                    cmd.args(["-open", &url]);
+                }
+            }
+            if cmd.output().map(|o| o.status.success()).unwrap_or(false) {
+                return Ok(());
+            }
+        }
+
+        // Fallback: osascript for guaranteed notifications (no actions)
         let escaped_title = summary.replace('"', r#"\""#);
         let escaped_body = body.replace('"', r#"\""#).replace('\n', " ");
 
@@ -240,16 +411,35 @@ impl NotificationManager {
         &self,
         summary: &str,
         body: &str,
-        _short_id: &str,
+        note_id: &str,
     ) -> Result<()> {
-        // Linux: Rich notification with action hint (NO auto-open)
-        Notification::new()
+        // Linux: Rich notification with a "View note" action (NO auto-open)
+        let target = self.click_target(note_id);
+        let mut notification = Notification::new();
+        notification
             .summary(summary)
             .body(body)
             .appname("Fukura")
             .timeout(Timeout::Milliseconds(15000))
-            .urgency(notify_rust::Urgency::Normal)
-            .show()?;
+            .urgency(notify_rust::Urgency::Normal);
+        if target.is_some() {
+            notification.action("default", "View note");
+            notification.action("view", "View note");
+        }
+        let handle = notification.show()?;
+
+        // Waiting for the click blocks, so do it off the caller's thread
+        if let Some(target) = target {
+            std::thread::spawn(move || {
+                handle.wait_for_action(|action| {
+                    if action == "default" || action == "view" {
+                        if let Err(e) = Self::run_click_target(&target) {
+                            tracing::warn!("Notification action failed: {}", e);
+                        }
+                    }
+                });
+            });
+        }
         Ok(())
     }
 
@@ -266,7 +456,8 @@ impl NotificationManager {
 
     #[cfg(target_os = "windows")]
     fn show_notification_detailed_windows(&self, summary: &str, body: &str) -> Result<()> {
-        // Windows: Rich notification with command instructions (NO auto-open)
+        // Windows: toast actions need a registered COM activator, so keep the
+        // command instructions in the body instead (NO auto-open)
         Notification::new()
             .summary(summary)
             .body(body)
@@ -293,6 +484,17 @@ impl NotificationManager {
         self.config.enabled
     }
 
+    /// Get the configured click action
+    pub fn click_action(&self) -> ClickAction {
+        self.config.click_action
+    }
+
+    /// Set what the "View note" action does
+    pub fn set_click_action(&mut self, action: ClickAction) -> Result<()> {
+        self.config.click_action = action;
+        self.save_config()
+    }
+
     /// Send test notification (for debugging)
     pub fn send_test_notification(&self) -> Result<()> {
         let summary = "Fukura: Test Notification";
@@ -347,3 +549,55 @@ impl NotificationManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_config_defaults_for_older_files() {
+        let config: NotificationConfig = toml::from_str(
+            "enabled = true\nshow_on_error = true\nshow_on_solution_found = false\n",
+        )
+        .unwrap();
+        assert_eq!(config.click_action, ClickAction::Open);
+        assert_eq!(config.serve_url, "http://127.0.0.1:8765");
+        assert!(!config.show_on_solution_found);
+    }
+
+    #[test]
+    fn test_click_target_open_runs_fuku_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = NotificationManager::new(temp_dir.path()).unwrap();
+
+        match manager.click_target("abc123").unwrap() {
+            ClickTarget::Command { args, .. } => {
+                assert_eq!(args[0], "--repo");
+                assert_eq!(&args[2..], ["open", "abc123"]);
+            }
+            other => panic!("unexpected target: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_click_target_serve_and_none() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = NotificationManager::new(temp_dir.path()).unwrap();
+
+        manager.set_click_action(ClickAction::Serve).unwrap();
+        assert_eq!(
+            manager.click_target("abc123"),
+            Some(ClickTarget::Url(
+                "http://127.0.0.1:8765/notes/abc123".to_string()
+            ))
+        );
+
+        manager.set_click_action(ClickAction::None).unwrap();
+        assert!(manager.click_target("abc123").is_none());
+
+        // Persisted across reloads
+        let reloaded = NotificationManager::new(temp_dir.path()).unwrap();
+        assert_eq!(reloaded.click_action(), ClickAction::None);
+    }
+}
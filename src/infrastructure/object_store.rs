@@ -0,0 +1,263 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
+
+use crate::pack::{
+    load_object_from_pack, load_pack_indices, loose_usage, pack_objects, LooseUsage, PackReport,
+};
+
+/// Storage for a repository's objects: zlib-compressed `<type> <len>\0<payload>`
+/// blobs named by the hex SHA-256 of their uncompressed contents. The
+/// repository hashes, compresses and locks; a store only keeps the bytes.
+pub trait ObjectStore: Send + Sync + std::fmt::Debug {
+    /// Whether an object with this full id is stored
+    fn contains(&self, object_id: &str) -> Result<bool>;
+
+    /// The stored (compressed) bytes of an object
+    fn read(&self, object_id: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Store an object. Objects are immutable, so writing an id that is
+    /// already stored does nothing.
+    fn write(&self, object_id: &str, bytes: &[u8]) -> Result<()>;
+
+    /// Remove an object if the store can, returning whether it did
+    fn remove(&self, object_id: &str) -> Result<bool>;
+
+    /// Ids of stored objects starting with `prefix`, sorted
+    fn ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Compact the store, e.g. by packing loose objects. `prune` drops what
+    /// was compacted from its old location.
+    fn pack(&self, prune: bool) -> Result<PackReport>;
+
+    /// Objects [`ObjectStore::pack`] would compact; none for stores that
+    /// don't keep loose objects
+    fn loose_usage(&self) -> Result<LooseUsage> {
+        Ok(LooseUsage::default())
+    }
+}
+
+/// Loose files under `.fukura/objects/<2 hex>/<62 hex>`, packed by
+/// `fuku gc` into `.fukura/packs`
+#[derive(Debug, Clone)]
+pub struct FileObjectStore {
+    objects_dir: PathBuf,
+    pack_dir: PathBuf,
+}
+
+impl FileObjectStore {
+    pub fn new(objects_dir: PathBuf, pack_dir: PathBuf) -> Self {
+        Self {
+            objects_dir,
+            pack_dir,
+        }
+    }
+
+    fn loose_path(&self, object_id: &str) -> PathBuf {
+        match (object_id.get(..2), object_id.get(2..)) {
+            (Some(prefix), Some(rest)) => self.objects_dir.join(prefix).join(rest),
+            _ => self.objects_dir.join(object_id),
+        }
+    }
+
+    fn loose_ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        if !self.objects_dir.exists() {
+            return Ok(ids);
+        }
+        for dir in fs::read_dir(&self.objects_dir)? {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            let dir_name = dir.file_name().to_string_lossy().to_string();
+            // `prefix` may be shorter or longer than the directory name
+            if !dir_name.starts_with(prefix.get(..2).unwrap_or(prefix)) {
+                continue;
+            }
+            for entry in fs::read_dir(dir.path())? {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                let id = format!("{}{}", dir_name, entry.file_name().to_string_lossy());
+                if id.starts_with(prefix) {
+                    ids.push(id);
+                }
+            }
+        }
+        Ok(ids)
+    }
+}
+
+impl ObjectStore for FileObjectStore {
+    fn contains(&self, object_id: &str) -> Result<bool> {
+        if self.loose_path(object_id).exists() {
+            return Ok(true);
+        }
+        Ok(load_pack_indices(&self.pack_dir)?.contains_key(object_id))
+    }
+
+    fn read(&self, object_id: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.loose_path(object_id);
+        if path.exists() {
+            let bytes =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            return Ok(Some(bytes));
+        }
+        load_object_from_pack(&self.pack_dir, object_id)
+    }
+
+    fn write(&self, object_id: &str, bytes: &[u8]) -> Result<()> {
+        let object_path = self.loose_path(object_id);
+        let dir_path = object_path
+            .parent()
+            .context("Object path has no directory")?;
+        fs::create_dir_all(dir_path)?;
+        if object_path.exists() {
+            return Ok(());
+        }
+        let mut temp = NamedTempFile::new_in(dir_path)?;
+        temp.write_all(bytes)?;
+        temp.flush()?;
+        temp.as_file().sync_all()?;
+        let persisted = temp.persist(&object_path)?;
+        let _ = persisted.sync_all();
+        if let Ok(dir_file) = File::open(dir_path) {
+            let _ = dir_file.sync_all();
+        }
+        if let Ok(objects_dir) = File::open(&self.objects_dir) {
+            let _ = objects_dir.sync_all();
+        }
+        Ok(())
+    }
+
+    /// Only loose objects can be removed; packed ones stay in their pack
+    fn remove(&self, object_id: &str) -> Result<bool> {
+        let path = self.loose_path(object_id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        Ok(true)
+    }
+
+    fn ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut ids = self.loose_ids_with_prefix(prefix)?;
+        ids.extend(
+            load_pack_indices(&self.pack_dir)?
+                .into_keys()
+                .filter(|id| id.starts_with(prefix)),
+        );
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    fn pack(&self, prune: bool) -> Result<PackReport> {
+        pack_objects(&self.objects_dir, &self.pack_dir, prune)
+    }
+
+    fn loose_usage(&self) -> Result<LooseUsage> {
+        loose_usage(&self.objects_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use crate::repo::FukuraRepo;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// Keeps objects in memory, like a remote-only thin clone would
+    #[derive(Debug, Default)]
+    struct MemoryStore(Mutex<BTreeMap<String, Vec<u8>>>);
+
+    impl ObjectStore for MemoryStore {
+        fn contains(&self, object_id: &str) -> Result<bool> {
+            Ok(self.0.lock().unwrap().contains_key(object_id))
+        }
+
+        fn read(&self, object_id: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(object_id).cloned())
+        }
+
+        fn write(&self, object_id: &str, bytes: &[u8]) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .entry(object_id.to_string())
+                .or_insert_with(|| bytes.to_vec());
+            Ok(())
+        }
+
+        fn remove(&self, object_id: &str) -> Result<bool> {
+            Ok(self.0.lock().unwrap().remove(object_id).is_some())
+        }
+
+        fn ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+            let objects = self.0.lock().unwrap();
+            Ok(objects
+                .keys()
+                .filter(|id| id.starts_with(prefix))
+                .cloned()
+                .collect())
+        }
+
+        fn pack(&self, _prune: bool) -> Result<PackReport> {
+            anyhow::bail!("Nothing to pack in memory")
+        }
+    }
+
+    #[test]
+    fn test_file_store_finds_loose_and_packed_objects() {
+        let temp = TempDir::new().unwrap();
+        let store = FileObjectStore::new(temp.path().join("objects"), temp.path().join("packs"));
+        fs::create_dir_all(temp.path().join("packs")).unwrap();
+        let packed = format!("ab{}", "1".repeat(62));
+        let loose = format!("ab{}", "2".repeat(62));
+
+        store.write(&packed, b"first").unwrap();
+        store.pack(true).unwrap();
+        store.write(&loose, b"second").unwrap();
+        store.write(&loose, b"ignored").unwrap();
+
+        assert_eq!(store.read(&packed).unwrap().as_deref(), Some(&b"first"[..]));
+        assert_eq!(store.read(&loose).unwrap().as_deref(), Some(&b"second"[..]));
+        assert!(store.contains(&packed).unwrap());
+        assert_eq!(
+            store.ids_with_prefix("a").unwrap(),
+            vec![packed.clone(), loose.clone()]
+        );
+        assert_eq!(store.ids_with_prefix("ab2").unwrap(), vec![loose.clone()]);
+
+        assert!(!store.remove(&packed).unwrap());
+        assert!(store.remove(&loose).unwrap());
+        assert_eq!(store.read(&loose).unwrap(), None);
+    }
+
+    #[test]
+    fn test_repo_on_another_backend() {
+        let temp = TempDir::new().unwrap();
+        let store = Arc::new(MemoryStore::default());
+        let repo = FukuraRepo::init(temp.path(), true)
+            .unwrap()
+            .with_object_store(store.clone());
+
+        let record = repo
+            .store_note(NoteBuilder::new("Proxy", "Set HTTPS_PROXY").build())
+            .unwrap();
+        assert!(store.contains(&record.object_id).unwrap());
+        assert!(fs::read_dir(repo.objects_dir()).unwrap().next().is_none());
+
+        let object_id = repo.resolve_object_id(&record.object_id[..8]).unwrap();
+        assert_eq!(object_id, record.object_id);
+        assert_eq!(repo.load_note(&object_id).unwrap().note.title, "Proxy");
+    }
+}
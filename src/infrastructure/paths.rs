@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+/// Overrides every per-user location below with one directory
+pub const FUKURA_HOME_ENV: &str = "FUKURA_HOME";
+
+/// Where per-user files live. `FUKURA_HOME` wins when set; otherwise the XDG
+/// base directories are used, keeping a global config that already exists in
+/// the old `~/.fukura` location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    home: PathBuf,
+    fukura_home: Option<PathBuf>,
+    xdg_config_home: Option<PathBuf>,
+    xdg_data_home: Option<PathBuf>,
+    xdg_runtime_dir: Option<PathBuf>,
+    temp: PathBuf,
+}
+
+impl Paths {
+    /// Locations from the process environment
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(
+            |key| std::env::var_os(key).map(PathBuf::from),
+            std::env::temp_dir(),
+        )
+    }
+
+    /// Locations from `lookup`, which maps environment variables to values.
+    /// Empty and relative XDG values are ignored as the spec asks.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<PathBuf>, temp: PathBuf) -> Result<Self> {
+        let set = |key: &str| lookup(key).filter(|path| !path.as_os_str().is_empty());
+        let home = set("HOME")
+            .or_else(|| set("USERPROFILE"))
+            .ok_or_else(|| anyhow!("HOME environment variable not set"))?;
+        let xdg = |key: &str| set(key).filter(|path| path.is_absolute());
+        Ok(Self {
+            home,
+            fukura_home: set(FUKURA_HOME_ENV),
+            xdg_config_home: xdg("XDG_CONFIG_HOME"),
+            xdg_data_home: xdg("XDG_DATA_HOME"),
+            xdg_runtime_dir: xdg("XDG_RUNTIME_DIR"),
+            temp,
+        })
+    }
+
+    /// The user's home directory, for shell rc files and the like
+    pub fn home(&self) -> PathBuf {
+        self.home.clone()
+    }
+
+    /// Base directory for other programs' configuration (`~/.config`)
+    pub fn user_config_home(&self) -> PathBuf {
+        self.xdg_config_home
+            .clone()
+            .unwrap_or_else(|| self.home.join(".config"))
+    }
+
+    /// Base directory for other programs' data (`~/.local/share`)
+    pub fn user_data_home(&self) -> PathBuf {
+        self.xdg_data_home
+            .clone()
+            .unwrap_or_else(|| self.home.join(".local").join("share"))
+    }
+
+    /// Directory of the global `config.toml`
+    pub fn config_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.fukura_home {
+            return dir.clone();
+        }
+        if self.xdg_config_home.is_none() {
+            let legacy = self.home.join(".fukura");
+            if legacy.join("config.toml").is_file() {
+                return legacy;
+            }
+        }
+        self.user_config_home().join("fukura")
+    }
+
+    /// Directory of per-user state such as the repository registry
+    pub fn data_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.fukura_home {
+            return dir.clone();
+        }
+        self.user_data_home().join("fukura")
+    }
+
+    /// Directory for sockets and other files that must not outlive the
+    /// login or leave the machine; without `XDG_RUNTIME_DIR` a per-user
+    /// directory under the temp directory
+    pub fn runtime_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.fukura_home {
+            return dir.join("run");
+        }
+        match &self.xdg_runtime_dir {
+            Some(dir) => dir.join("fukura"),
+            None => self.temp.join(format!("fukura-{}", user_id())),
+        }
+    }
+
+    /// Directory for throwaway files such as rendered HTML notes
+    pub fn temp_dir(&self) -> PathBuf {
+        match &self.fukura_home {
+            Some(dir) => dir.join("tmp"),
+            None => self.temp.clone(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn user_id() -> String {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }.to_string()
+}
+
+#[cfg(not(unix))]
+fn user_id() -> String {
+    std::env::var("USERNAME").unwrap_or_else(|_| "user".to_string())
+}
+
+/// See [`Paths::home`]
+pub fn home_dir() -> Result<PathBuf> {
+    Ok(Paths::from_env()?.home())
+}
+
+/// See [`Paths::user_config_home`]
+pub fn user_config_home() -> Result<PathBuf> {
+    Ok(Paths::from_env()?.user_config_home())
+}
+
+/// See [`Paths::user_data_home`]
+pub fn user_data_home() -> Result<PathBuf> {
+    Ok(Paths::from_env()?.user_data_home())
+}
+
+/// See [`Paths::config_dir`]
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(Paths::from_env()?.config_dir())
+}
+
+/// See [`Paths::data_dir`]
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(Paths::from_env()?.data_dir())
+}
+
+/// See [`Paths::runtime_dir`]. Falls back to the system temp directory
+/// when `HOME` is unset.
+pub fn runtime_dir() -> PathBuf {
+    match Paths::from_env() {
+        Ok(paths) => paths.runtime_dir(),
+        Err(_) => std::env::temp_dir().join(format!("fukura-{}", user_id())),
+    }
+}
+
+/// See [`Paths::temp_dir`]; created if missing. Falls back to the system
+/// temp directory when `HOME` is unset.
+pub fn temp_dir() -> Result<PathBuf> {
+    let dir = match Paths::from_env() {
+        Ok(paths) => paths.temp_dir(),
+        Err(_) => std::env::temp_dir(),
+    };
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn paths(vars: &[(&str, &str)]) -> Paths {
+        let vars: HashMap<String, PathBuf> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), PathBuf::from(value)))
+            .collect();
+        Paths::from_lookup(|key| vars.get(key).cloned(), PathBuf::from("/tmp")).unwrap()
+    }
+
+    #[test]
+    fn test_xdg_defaults() {
+        let defaults = paths(&[("HOME", "/home/ana")]);
+        assert_eq!(
+            defaults.config_dir(),
+            PathBuf::from("/home/ana/.config/fukura")
+        );
+        assert_eq!(
+            defaults.data_dir(),
+            PathBuf::from("/home/ana/.local/share/fukura")
+        );
+        assert_eq!(defaults.temp_dir(), PathBuf::from("/tmp"));
+        assert_eq!(
+            defaults.runtime_dir(),
+            PathBuf::from(format!("/tmp/fukura-{}", user_id()))
+        );
+        let runtime = paths(&[("HOME", "/home/ana"), ("XDG_RUNTIME_DIR", "/run/user/1000")]);
+        assert_eq!(
+            runtime.runtime_dir(),
+            PathBuf::from("/run/user/1000/fukura")
+        );
+
+        let xdg = paths(&[
+            ("HOME", "/home/ana"),
+            ("XDG_CONFIG_HOME", "/cfg"),
+            ("XDG_DATA_HOME", "relative/ignored"),
+        ]);
+        assert_eq!(xdg.config_dir(), PathBuf::from("/cfg/fukura"));
+        assert_eq!(xdg.user_config_home(), PathBuf::from("/cfg"));
+        assert_eq!(
+            xdg.user_data_home(),
+            PathBuf::from("/home/ana/.local/share")
+        );
+        assert_eq!(
+            xdg.data_dir(),
+            PathBuf::from("/home/ana/.local/share/fukura")
+        );
+    }
+
+    #[test]
+    fn test_fukura_home_overrides_everything() {
+        let custom = paths(&[
+            ("HOME", "/home/ana"),
+            ("XDG_CONFIG_HOME", "/cfg"),
+            ("FUKURA_HOME", "/opt/fukura"),
+        ]);
+        assert_eq!(custom.config_dir(), PathBuf::from("/opt/fukura"));
+        assert_eq!(custom.data_dir(), PathBuf::from("/opt/fukura"));
+        assert_eq!(custom.temp_dir(), PathBuf::from("/opt/fukura/tmp"));
+        assert_eq!(custom.runtime_dir(), PathBuf::from("/opt/fukura/run"));
+        // Shell files stay where the shell looks for them
+        assert_eq!(custom.home(), PathBuf::from("/home/ana"));
+    }
+
+    #[test]
+    fn test_existing_legacy_config_is_kept() {
+        let home = TempDir::new().unwrap();
+        std::fs::create_dir_all(home.path().join(".fukura")).unwrap();
+        std::fs::write(home.path().join(".fukura/config.toml"), "").unwrap();
+        let legacy = paths(&[("HOME", home.path().to_str().unwrap())]);
+        assert_eq!(legacy.config_dir(), home.path().join(".fukura"));
+    }
+}
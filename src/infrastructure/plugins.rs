@@ -0,0 +1,209 @@
+//! Executables in `.fukura/hooks/` run at points in a note's life, so a team
+//! can classify notes, open tickets or enforce policy without forking fukura.
+//! Each gets the note as JSON on stdin. Exiting non-zero rejects the note,
+//! with stderr as the reason; printing a note as JSON replaces it; printing
+//! nothing leaves it as it was.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::models::{Note, NoteRecord};
+
+/// Directory under `.fukura` holding plugin executables
+pub const PLUGIN_DIR: &str = "hooks";
+
+/// A point in a note's life where a plugin runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginHook {
+    /// Before a new note is stored; may change or reject it
+    PreAdd,
+    /// After a new note is stored; its output is ignored
+    PostAdd,
+    /// Before a note goes to a hub; may change what is sent or reject it
+    PrePush,
+}
+
+impl PluginHook {
+    /// Name of the executable run for this hook
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            PluginHook::PreAdd => "pre-add",
+            PluginHook::PostAdd => "post-add",
+            PluginHook::PrePush => "pre-push",
+        }
+    }
+}
+
+/// The plugins of one repository
+#[derive(Debug, Clone)]
+pub struct Plugins {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl Plugins {
+    /// Plugins of the repository at `root` whose `.fukura` is `dot_dir`
+    pub fn new(root: &Path, dot_dir: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dir: dot_dir.join(PLUGIN_DIR),
+        }
+    }
+
+    pub fn path(&self, hook: PluginHook) -> PathBuf {
+        self.dir.join(hook.file_name())
+    }
+
+    /// The executable for `hook`, if one is installed. A file that isn't
+    /// executable is skipped, as git does with its hooks.
+    fn executable(&self, hook: PluginHook) -> Option<PathBuf> {
+        let path = self.path(hook);
+        let metadata = path.metadata().ok().filter(|metadata| metadata.is_file())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o111 == 0 {
+                tracing::warn!(path = %path.display(), "plugin is not executable; skipped");
+                return None;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+        Some(path)
+    }
+
+    /// Pass `note` through the plugin for `hook`, returning the note it
+    /// printed or `note` unchanged. Errors when the plugin rejects it.
+    pub fn filter(&self, hook: PluginHook, note: Note, object_id: Option<&str>) -> Result<Note> {
+        let Some(path) = self.executable(hook) else {
+            return Ok(note);
+        };
+        let output = self.run(&path, hook, &note, object_id)?;
+        if !output.status.success() {
+            let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if reason.is_empty() {
+                bail!(
+                    "The {} plugin rejected '{}' ({})",
+                    hook.file_name(),
+                    note.title,
+                    output.status
+                );
+            }
+            bail!(
+                "The {} plugin rejected '{}': {}",
+                hook.file_name(),
+                note.title,
+                reason
+            );
+        }
+        if output.stdout.iter().all(u8::is_ascii_whitespace) {
+            return Ok(note);
+        }
+        serde_json::from_slice(&output.stdout).with_context(|| {
+            format!(
+                "The {} plugin printed something that isn't a note",
+                hook.file_name()
+            )
+        })
+    }
+
+    /// Tell the plugin for `hook` about a stored note. Failures are only
+    /// logged; the note is already stored.
+    pub fn notify(&self, hook: PluginHook, record: &NoteRecord) {
+        let Some(path) = self.executable(hook) else {
+            return;
+        };
+        match self.run(&path, hook, &record.note, Some(&record.object_id)) {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => tracing::warn!(
+                plugin = hook.file_name(),
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "plugin failed"
+            ),
+            Err(err) => tracing::warn!(plugin = hook.file_name(), error = %err, "plugin failed"),
+        }
+    }
+
+    fn run(
+        &self,
+        path: &Path,
+        hook: PluginHook,
+        note: &Note,
+        object_id: Option<&str>,
+    ) -> Result<Output> {
+        let input = serde_json::to_vec(note)?;
+        let mut command = Command::new(path);
+        command
+            .current_dir(&self.root)
+            .env("FUKURA_HOOK", hook.file_name())
+            .env("FUKURA_REPO", &self.root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(object_id) = object_id {
+            command.env("FUKURA_OBJECT_ID", object_id);
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run {}", path.display()))?;
+        // Written from another thread so a plugin that prints before
+        // reading all of its input can't deadlock; one that never reads
+        // it just closes the pipe
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("Failed to run {}", path.display()))?;
+        let _ = writer.join();
+        Ok(output)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn install(plugins: &Plugins, hook: PluginHook, script: &str) {
+        let path = plugins.path(hook);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_plugins_change_and_reject_notes() {
+        let temp = TempDir::new().unwrap();
+        let plugins = Plugins::new(temp.path(), &temp.path().join(".fukura"));
+        let note = NoteBuilder::new("Proxy", "Set HTTPS_PROXY").build();
+        // Nothing installed: the note passes through
+        let same = plugins
+            .filter(PluginHook::PreAdd, note.clone(), None)
+            .unwrap();
+        assert_eq!(same.title, "Proxy");
+
+        install(
+            &plugins,
+            PluginHook::PreAdd,
+            "#!/bin/sh\nsed 's/\"tags\":\\[\\]/\"tags\":[\"network\"]/'\n",
+        );
+        let tagged = plugins
+            .filter(PluginHook::PreAdd, note.clone(), None)
+            .unwrap();
+        assert_eq!(tagged.tags, ["network"]);
+
+        install(
+            &plugins,
+            PluginHook::PrePush,
+            "#!/bin/sh\ncat >/dev/null\necho \"no pushes from $FUKURA_HOOK\" >&2\nexit 1\n",
+        );
+        let err = plugins.filter(PluginHook::PrePush, note, None).unwrap_err();
+        assert!(err.to_string().contains("no pushes from pre-push"));
+    }
+}
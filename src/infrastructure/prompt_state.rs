@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::locks::RepoLock;
+use crate::recording::RecordingStore;
+
+/// How often a running daemon refreshes its heartbeat
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A heartbeat older than this was left by a daemon that died without
+/// removing it
+const HEARTBEAT_STALE: Duration = Duration::from_secs(90);
+
+const STATE_LOCK: &str = "state";
+const UNSYNCED_FILE: &str = "unsynced";
+const HEARTBEAT_FILE: &str = "daemon";
+
+/// Small files under `.fukura/state` kept current as notes are stored and
+/// pushed and while the daemon runs, so `fuku prompt-status` answers from a
+/// few reads instead of opening the index or asking the daemon
+#[derive(Debug, Clone)]
+pub struct PromptState {
+    dot_dir: PathBuf,
+}
+
+impl PromptState {
+    /// The state of the repository whose `.fukura` directory is `dot_dir`
+    pub fn new(dot_dir: &Path) -> Self {
+        Self {
+            dot_dir: dot_dir.to_path_buf(),
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.dot_dir.join("state")
+    }
+
+    /// Ids of private notes stored since they were last pushed, one per line
+    pub fn unsynced(&self) -> Vec<String> {
+        fs::read_to_string(self.dir().join(UNSYNCED_FILE))
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Notes that still have to be pushed
+    pub fn mark_unsynced(&self, object_ids: &[String]) -> Result<()> {
+        self.update_unsynced(|ids| {
+            let mut known: HashSet<String> = ids.iter().cloned().collect();
+            for id in object_ids {
+                if known.insert(id.clone()) {
+                    ids.push(id.clone());
+                }
+            }
+        })
+    }
+
+    /// Notes pushed, deleted or replaced by a newer revision
+    pub fn mark_settled(&self, object_ids: &[String]) -> Result<()> {
+        let settled: HashSet<&String> = object_ids.iter().collect();
+        self.update_unsynced(|ids| ids.retain(|id| !settled.contains(id)))
+    }
+
+    fn update_unsynced(&self, change: impl FnOnce(&mut Vec<String>)) -> Result<()> {
+        let _lock = RepoLock::exclusive(&self.dot_dir.join("locks"), STATE_LOCK)?;
+        let mut ids = self.unsynced();
+        let before = ids.clone();
+        change(&mut ids);
+        if ids == before {
+            return Ok(());
+        }
+        let content: String = ids.iter().map(|id| format!("{}\n", id)).collect();
+        self.write(UNSYNCED_FILE, content.as_bytes())
+    }
+
+    /// Record that the daemon with `pid` is alive; see [`HEARTBEAT_INTERVAL`]
+    pub fn beat(&self, pid: u32) -> Result<()> {
+        self.write(HEARTBEAT_FILE, pid.to_string().as_bytes())
+    }
+
+    /// Remove the heartbeat of a daemon that stopped
+    pub fn clear_heartbeat(&self) -> Result<()> {
+        match fs::remove_file(self.dir().join(HEARTBEAT_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the daemon's heartbeat is recent as of `now`
+    pub fn daemon_alive(&self, now: SystemTime) -> bool {
+        fs::metadata(self.dir().join(HEARTBEAT_FILE))
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|beat| now.duration_since(beat).unwrap_or_default() < HEARTBEAT_STALE)
+    }
+
+    /// Write through a temporary file, so readers never see half a file
+    fn write(&self, name: &str, content: &[u8]) -> Result<()> {
+        let dir = self.dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(name);
+        let tmp = dir.join(format!(".{}.tmp", name));
+        fs::write(&tmp, content)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// What `fuku prompt-status` shows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PromptStatus {
+    /// Names of the `fuku rec` recordings in progress
+    pub recording: Vec<String>,
+    pub unsynced: usize,
+    pub daemon_running: bool,
+}
+
+impl PromptStatus {
+    /// Read the status of the repository at `root` from its state files
+    pub fn read(root: &Path) -> Self {
+        let dot_dir = root.join(".fukura");
+        let state = PromptState::new(&dot_dir);
+        Self {
+            recording: RecordingStore::new(root)
+                .list()
+                .map(|recordings| recordings.into_iter().map(|rec| rec.name).collect())
+                .unwrap_or_default(),
+            unsynced: state.unsynced().len(),
+            daemon_running: state.daemon_alive(SystemTime::now()),
+        }
+    }
+
+    /// The compact segment: the daemon as `◉` (running) or `○`, `●rec` while
+    /// recording and `↑N` for unsynced notes; ASCII when `plain`
+    pub fn segment(&self, plain: bool) -> String {
+        let mut parts = vec![match (self.daemon_running, plain) {
+            (true, false) => "◉".to_string(),
+            (false, false) => "○".to_string(),
+            (true, true) => "on".to_string(),
+            (false, true) => "off".to_string(),
+        }];
+        if !self.recording.is_empty() {
+            let mut rec = if plain { "REC" } else { "●rec" }.to_string();
+            let named: Vec<&str> = self
+                .recording
+                .iter()
+                .map(String::as_str)
+                .filter(|name| *name != crate::recording::DEFAULT_RECORDING)
+                .collect();
+            if !named.is_empty() {
+                rec.push(':');
+                rec.push_str(&named.join(","));
+            }
+            parts.push(rec);
+        }
+        if self.unsynced > 0 {
+            parts.push(format!(
+                "{}{}",
+                if plain { "^" } else { "↑" },
+                self.unsynced
+            ));
+        }
+        parts.join(" ")
+    }
+
+    /// Fill `{daemon}` (`up` or `down`), `{recording}` (names, comma
+    /// separated) and `{unsynced}` (a count) in a user template
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{daemon}", if self.daemon_running { "up" } else { "down" })
+            .replace("{recording}", &self.recording.join(","))
+            .replace("{unsynced}", &self.unsynced.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unsynced_ids_are_added_once_and_settled() {
+        let dir = TempDir::new().unwrap();
+        let state = PromptState::new(dir.path());
+        assert!(state.unsynced().is_empty());
+
+        state
+            .mark_unsynced(&["a".to_string(), "b".to_string()])
+            .unwrap();
+        state.mark_unsynced(&["b".to_string()]).unwrap();
+        assert_eq!(state.unsynced(), vec!["a", "b"]);
+
+        state.mark_settled(&["a".to_string()]).unwrap();
+        assert_eq!(state.unsynced(), vec!["b"]);
+    }
+
+    #[test]
+    fn test_heartbeat_goes_stale() {
+        let dir = TempDir::new().unwrap();
+        let state = PromptState::new(dir.path());
+        assert!(!state.daemon_alive(SystemTime::now()));
+
+        state.beat(42).unwrap();
+        assert!(state.daemon_alive(SystemTime::now()));
+        assert!(!state.daemon_alive(SystemTime::now() + HEARTBEAT_STALE * 2));
+
+        state.clear_heartbeat().unwrap();
+        assert!(!state.daemon_alive(SystemTime::now()));
+    }
+
+    #[test]
+    fn test_segment_and_template() {
+        let status = PromptStatus {
+            recording: vec!["default".to_string(), "deploy".to_string()],
+            unsynced: 3,
+            daemon_running: true,
+        };
+        assert_eq!(status.segment(false), "◉ ●rec:deploy ↑3");
+        assert_eq!(status.segment(true), "on REC:deploy ^3");
+        assert_eq!(
+            status.render("{daemon}/{recording}/{unsynced}"),
+            "up/default,deploy/3"
+        );
+
+        let idle = PromptStatus {
+            recording: vec![],
+            unsynced: 0,
+            daemon_running: false,
+        };
+        assert_eq!(idle.segment(false), "○");
+    }
+}
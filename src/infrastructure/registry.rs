@@ -0,0 +1,292 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::index::{SearchHit, SearchSort};
+use crate::repo::FukuraRepo;
+
+/// Registry file name inside [`crate::paths::data_dir`]
+const REGISTRY_FILE: &str = "repos.toml";
+
+/// Repositories on this machine, recorded as they are initialized or opened
+/// so `--all-repos` searches them without walking the disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoRegistry {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    repos: BTreeSet<PathBuf>,
+}
+
+impl RepoRegistry {
+    /// The per-user registry
+    pub fn load_default() -> Result<Self> {
+        Self::load(&crate::paths::data_dir()?.join(REGISTRY_FILE))
+    }
+
+    /// Registry stored at `path`; empty if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut registry = if path.exists() {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            toml::from_str::<Self>(&raw)
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        registry.path = path.to_path_buf();
+        Ok(registry)
+    }
+
+    /// Write the registry, replacing the file in one step so concurrent
+    /// readers never see half of it
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self
+            .path
+            .with_extension(format!("toml.{}.tmp", std::process::id()));
+        fs::write(&tmp, toml::to_string_pretty(self)?)?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Registered repository roots
+    pub fn repos(&self) -> impl Iterator<Item = &Path> {
+        self.repos.iter().map(PathBuf::as_path)
+    }
+
+    /// Registered roots that still hold a repository
+    pub fn existing(&self) -> Vec<PathBuf> {
+        self.repos
+            .iter()
+            .filter(|root| is_repo(root))
+            .cloned()
+            .collect()
+    }
+
+    /// Record `root`; `false` if it was already registered. Roots whose
+    /// repository is gone (deleted checkouts, temp dirs) are dropped too.
+    pub fn add(&mut self, root: &Path) -> bool {
+        self.repos.retain(|root| is_repo(root));
+        self.repos.insert(normalize(root))
+    }
+
+    /// Forget `root`; `false` if it wasn't registered
+    pub fn remove(&mut self, root: &Path) -> bool {
+        self.repos.remove(&normalize(root)) || self.repos.remove(root)
+    }
+}
+
+/// Record `root` in the per-user registry unless it's already there
+pub fn register(root: &Path) -> Result<()> {
+    let mut registry = RepoRegistry::load_default()?;
+    if registry.repos.contains(&normalize(root)) {
+        return Ok(());
+    }
+    registry.add(root);
+    registry.save()
+}
+
+/// A hit from a search across repositories
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoHit {
+    #[serde(flatten)]
+    pub hit: SearchHit,
+    /// Repository the hit was read from
+    pub repo: PathBuf,
+    /// Other repositories holding the same note, e.g. synced copies
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<PathBuf>,
+}
+
+/// Search every root at once and merge the results into one ranking.
+/// Repositories that fail to open or search are skipped.
+pub fn search_repos(
+    roots: &[PathBuf],
+    query: &str,
+    limit: usize,
+    sort: SearchSort,
+) -> Vec<RepoHit> {
+    let results: Vec<(PathBuf, Vec<SearchHit>)> = roots
+        .par_iter()
+        .filter_map(|root| {
+            let hits = FukuraRepo::open(root)
+                .ok()?
+                .search(query, limit, sort)
+                .ok()?;
+            Some((root.clone(), hits))
+        })
+        .collect();
+    merge_hits(results, limit, sort)
+}
+
+/// One ranking from per-repository results: by score for relevance searches,
+/// with a note found in several repositories (same object id) listed once
+pub fn merge_hits(
+    results: Vec<(PathBuf, Vec<SearchHit>)>,
+    limit: usize,
+    sort: SearchSort,
+) -> Vec<RepoHit> {
+    let mut merged: Vec<RepoHit> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (repo, hits) in results {
+        for hit in hits {
+            match seen.get(&hit.object_id) {
+                Some(&idx) => {
+                    let existing = &mut merged[idx];
+                    if hit.score > existing.hit.score {
+                        existing.hit = hit;
+                        let previous = std::mem::replace(&mut existing.repo, repo.clone());
+                        existing.also_in.push(previous);
+                    } else {
+                        existing.also_in.push(repo.clone());
+                    }
+                }
+                None => {
+                    seen.insert(hit.object_id.clone(), merged.len());
+                    merged.push(RepoHit {
+                        hit,
+                        repo: repo.clone(),
+                        also_in: vec![],
+                    });
+                }
+            }
+        }
+    }
+
+    match sort {
+        SearchSort::Relevance => merged.sort_by(|a, b| {
+            b.hit
+                .score
+                .total_cmp(&a.hit.score)
+                .then(b.hit.likes.cmp(&a.hit.likes))
+                .then(b.hit.updated_at.cmp(&a.hit.updated_at))
+        }),
+        SearchSort::Updated => merged.sort_by_key(|entry| Reverse(entry.hit.updated_at)),
+        SearchSort::Likes => merged.sort_by(|a, b| {
+            b.hit
+                .likes
+                .cmp(&a.hit.likes)
+                .then(b.hit.score.total_cmp(&a.hit.score))
+        }),
+    }
+    merged.truncate(limit);
+    merged
+}
+
+fn is_repo(root: &Path) -> bool {
+    root.join(".fukura").is_dir()
+}
+
+fn normalize(root: &Path) -> PathBuf {
+    root.canonicalize().unwrap_or_else(|_| root.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn hit(object_id: &str, score: f32) -> SearchHit {
+        SearchHit {
+            object_id: object_id.to_string(),
+            title: object_id.to_string(),
+            tags: vec![],
+            summary: String::new(),
+            updated_at: Utc::now(),
+            author: "ana".to_string(),
+            likes: 0,
+            score,
+            privacy: "private".to_string(),
+            pinned: false,
+            verified: false,
+            snippet: None,
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_ranks_by_score_and_dedups_synced_notes() {
+        let merged = merge_hits(
+            vec![
+                (
+                    PathBuf::from("/a"),
+                    vec![hit("shared", 1.0), hit("only-a", 0.5)],
+                ),
+                (
+                    PathBuf::from("/b"),
+                    vec![hit("only-b", 3.0), hit("shared", 2.0)],
+                ),
+            ],
+            10,
+            SearchSort::Relevance,
+        );
+        let ids: Vec<&str> = merged.iter().map(|h| h.hit.object_id.as_str()).collect();
+        assert_eq!(ids, vec!["only-b", "shared", "only-a"]);
+        assert_eq!(merged[1].repo, PathBuf::from("/b"));
+        assert_eq!(merged[1].also_in, vec![PathBuf::from("/a")]);
+
+        let json = serde_json::to_value(&merged[0]).unwrap();
+        assert_eq!(json["repo"], "/b");
+        assert_eq!(json["object_id"], "only-b");
+        assert!(json.get("also_in").is_none());
+
+        assert_eq!(
+            merge_hits(
+                vec![(PathBuf::from("/a"), vec![hit("x", 1.0), hit("y", 2.0)])],
+                1,
+                SearchSort::Relevance
+            )
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_add_remove_roundtrip() {
+        let data = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        FukuraRepo::init(project.path(), true).unwrap();
+        let path = data.path().join(REGISTRY_FILE);
+
+        let mut registry = RepoRegistry::load(&path).unwrap();
+        assert!(registry.add(project.path()));
+        assert!(!registry.add(project.path()));
+        registry.save().unwrap();
+
+        let mut reloaded = RepoRegistry::load(&path).unwrap();
+        assert_eq!(reloaded.existing(), vec![normalize(project.path())]);
+        assert!(reloaded.remove(project.path()));
+        assert!(!reloaded.remove(project.path()));
+    }
+
+    #[test]
+    fn test_missing_repositories_are_pruned() {
+        let data = TempDir::new().unwrap();
+        let kept = TempDir::new().unwrap();
+        FukuraRepo::init(kept.path(), true).unwrap();
+        let mut registry = RepoRegistry::load(&data.path().join(REGISTRY_FILE)).unwrap();
+        {
+            let gone = TempDir::new().unwrap();
+            FukuraRepo::init(gone.path(), true).unwrap();
+            registry.add(gone.path());
+        }
+        assert_eq!(registry.repos().count(), 1);
+        assert!(registry.existing().is_empty());
+        registry.add(kept.path());
+        assert_eq!(registry.repos().count(), 1);
+    }
+}
@@ -1,24 +1,80 @@
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
+use indicatif::{ProgressBar, ProgressStyle};
+use once_cell::sync::OnceCell;
 use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
 
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
 use crate::config::FukuraConfig;
-use crate::index::{SearchHit, SearchIndex, SearchSort};
-use crate::models::{Note, NoteEnvelope, NoteRecord};
-use crate::pack::{load_object_from_pack, load_pack_indices, pack_objects, PackReport};
+use crate::crdt;
+use crate::index::{SearchFilter, SearchHit, SearchIndex, SearchPage, SearchSort, SCHEMA_VERSION};
+use crate::interrupt;
+use crate::locks::RepoLock;
+use crate::meta_cache::{MetaCache, TagStat};
+use crate::models::{Note, NoteEnvelope, NoteRecord, Privacy};
+use crate::object_store::{FileObjectStore, ObjectStore};
+use crate::pack::{LooseUsage, PackReport};
+use crate::plugins::Plugins;
+use crate::prompt_state::PromptState;
 use crate::redaction::Redactor;
+use crate::share::ShareStore;
+use crate::signing;
+use crate::thin_store::ThinObjectStore;
+
+/// Set to keep [`FukuraRepo::open`] from rebuilding an index built with
+/// another schema version (`fuku --no-auto-reindex`)
+pub const NO_AUTO_REINDEX_ENV: &str = "FUKURA_NO_AUTO_REINDEX";
+
+/// Set to open repositories read-only, like `read_only = true` in their
+/// config (`fuku --read-only`)
+pub const READ_ONLY_ENV: &str = "FUKURA_READ_ONLY";
+
+/// Lock taken exclusively while updating refs
+const REFS_LOCK: &str = "refs";
+/// Lock shared by object writers and taken exclusively while packing, so
+/// pruning never races a new loose object
+const OBJECTS_LOCK: &str = "objects";
+/// Ref listing pinned note ids, one per line, in the order they were pinned
+const PINS_REF: &str = "pins";
+/// Ref mapping merged-away note ids to the note they were merged into, one
+/// `<absorbed> <merged>` pair per line
+const MERGED_REF: &str = "merged";
 
 #[derive(Clone, Debug)]
 pub struct FukuraRepo {
     root: PathBuf,
     dot_dir: PathBuf,
+    /// Search index, opened on first use and shared by clones of this repo
+    index: Arc<OnceCell<SearchIndex>>,
+    /// Index of expired notes, kept out of regular searches
+    archive: Arc<OnceCell<SearchIndex>>,
+    /// Note metadata for counts and listings, opened on first use
+    meta: Arc<OnceCell<MetaCache>>,
+    /// Where note objects live; loose files and packs unless replaced with
+    /// [`FukuraRepo::with_object_store`]
+    objects: Arc<dyn ObjectStore>,
+    /// Searching and viewing only; see [`FukuraRepo::ensure_writable`]
+    read_only: bool,
+}
+
+/// Whether the repository at `root` is opened read-only, by its config or
+/// [`READ_ONLY_ENV`]
+pub fn is_read_only_at(root: &Path) -> bool {
+    let from_env = std::env::var(READ_ONLY_ENV)
+        .is_ok_and(|value| !matches!(value.trim().to_lowercase().as_str(), "" | "0" | "false"));
+    from_env
+        || FukuraConfig::load(&root.join(".fukura").join("config"))
+            .is_ok_and(|config| config.read_only)
 }
 
 impl FukuraRepo {
@@ -31,11 +87,19 @@ impl FukuraRepo {
         fs::create_dir_all(path)?;
         let repo = Self {
             root: path.to_path_buf(),
+            objects: Arc::new(FileObjectStore::new(
+                dot_dir.join("objects"),
+                dot_dir.join("packs"),
+            )),
             dot_dir,
+            index: Arc::new(OnceCell::new()),
+            archive: Arc::new(OnceCell::new()),
+            meta: Arc::new(OnceCell::new()),
+            read_only: false,
         };
         repo.ensure_layout()?;
         let cfg = FukuraConfig {
-            version: 1,
+            version: crate::migrations::CURRENT_VERSION,
             ..Default::default()
         };
         cfg.save(&repo.config_path())?;
@@ -43,26 +107,85 @@ impl FukuraRepo {
     }
 
     pub fn open(path: &Path) -> Result<Self> {
+        let repo = Self::open_without_upgrade(path)?;
+        crate::migrations::upgrade(&repo)?;
+        repo.sync_index()?;
+        Ok(repo)
+    }
+
+    /// Open without migrating an older on-disk format, for `fuku migrate`
+    pub fn open_without_upgrade(path: &Path) -> Result<Self> {
         let dot_dir = path.join(".fukura");
         if !dot_dir.exists() {
             bail!("No .fukura directory at {}", path.display());
         }
         let repo = Self {
             root: path.to_path_buf(),
+            objects: Self::object_store_at(&dot_dir),
             dot_dir,
+            index: Arc::new(OnceCell::new()),
+            archive: Arc::new(OnceCell::new()),
+            meta: Arc::new(OnceCell::new()),
+            read_only: is_read_only_at(path),
         };
-        repo.ensure_layout()?;
+        if !repo.read_only {
+            repo.ensure_layout()?;
+        }
         Ok(repo)
     }
 
+    /// Loose files and packs under `dot_dir`, which a thin clone backs with
+    /// its remote
+    fn object_store_at(dot_dir: &Path) -> Arc<dyn ObjectStore> {
+        let local = FileObjectStore::new(dot_dir.join("objects"), dot_dir.join("packs"));
+        let thin = FukuraConfig::load(&dot_dir.join("config"))
+            .ok()
+            .and_then(|config| config.thin);
+        match thin {
+            Some(thin) => Arc::new(ThinObjectStore::new(local, &thin, dot_dir.join("thin"))),
+            None => Arc::new(local),
+        }
+    }
+
+    /// Keep objects in another backend, e.g. a database or a remote
+    pub fn with_object_store(mut self, objects: Arc<dyn ObjectStore>) -> Self {
+        self.objects = objects;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail before anything is written when the repository is read-only
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            bail!(
+                "The repository at {} is read-only: notes can be searched and viewed but not changed\n💡 Tip: It is set by --read-only, {} or read_only = true in .fukura/config",
+                self.root.display(),
+                READ_ONLY_ENV
+            );
+        }
+        Ok(())
+    }
+
+    pub fn object_store(&self) -> &dyn ObjectStore {
+        self.objects.as_ref()
+    }
+
     pub fn discover(start: Option<&Path>) -> Result<Self> {
+        Self::open(&Self::find_root(start)?)
+    }
+
+    /// Closest directory at or above `start` (default: the current directory)
+    /// that contains a `.fukura` repository
+    pub fn find_root(start: Option<&Path>) -> Result<PathBuf> {
         let mut current = start
             .map(|p| p.to_path_buf())
             .unwrap_or(std::env::current_dir()?);
         loop {
-            let candidate = current.join(".fukura");
-            if candidate.exists() {
-                return Self::open(&current);
+            if current.join(".fukura").exists() {
+                return Ok(current);
             }
             if !current.pop() {
                 bail!("No fuku repository found. Run `fuku init` first.");
@@ -93,6 +216,147 @@ impl FukuraRepo {
         self.dot_dir.join("index")
     }
 
+    /// Bookkeeping of a thin clone: the remote's note ids and what was
+    /// fetched
+    pub fn thin_dir(&self) -> PathBuf {
+        self.dot_dir.join("thin")
+    }
+
+    pub fn archive_dir(&self) -> PathBuf {
+        self.dot_dir.join("archive")
+    }
+
+    pub fn locks_dir(&self) -> PathBuf {
+        self.dot_dir.join("locks")
+    }
+
+    /// The repository's search index, opened once and reused
+    pub fn index(&self) -> Result<&SearchIndex> {
+        self.index
+            .get_or_try_init(|| SearchIndex::open_or_create(self))
+    }
+
+    /// Index of archived notes, opened once and reused
+    pub fn archive(&self) -> Result<&SearchIndex> {
+        self.archive
+            .get_or_try_init(|| SearchIndex::open_or_create_archive(self))
+    }
+
+    pub fn meta_cache_path(&self) -> PathBuf {
+        self.dot_dir.join("meta.sqlite")
+    }
+
+    /// Metadata cache of the indexed notes, opened once and reused. Rebuilt
+    /// from the stored notes when its count disagrees with the index, e.g.
+    /// after an upgrade or when the file was removed.
+    pub fn meta_cache(&self) -> Result<&MetaCache> {
+        self.meta.get_or_try_init(|| {
+            let cache = MetaCache::open(&self.meta_cache_path())?;
+            if !self.read_only && cache.len()? as u64 != self.index()?.num_docs()? {
+                cache.rebuild(&self.list_all_notes()?)?;
+            }
+            Ok(cache)
+        })
+    }
+
+    /// Rebuild both search indexes from the stored notes with the current
+    /// schema and configured analyzer, showing progress on a terminal.
+    /// Handles from [`FukuraRepo::index`] and [`FukuraRepo::archive`] must
+    /// not have been opened yet. Ctrl-C while notes are loading leaves the
+    /// existing indexes untouched.
+    pub fn rebuild_indexes(&self) -> Result<()> {
+        self.ensure_writable()?;
+        let ids = SearchIndex::object_ids_at(&self.index_dir())?;
+        let archived_ids = SearchIndex::object_ids_at(&self.archive_dir())?;
+        let progress = if ids.len() + archived_ids.len() < 1000 {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new((ids.len() + archived_ids.len()) as u64)
+        };
+        progress.set_style(
+            ProgressStyle::with_template("  Reindexing {bar:40.cyan/blue} {pos}/{len}")
+                .expect("valid progress template")
+                .progress_chars(crate::output::progress_chars()),
+        );
+        let load = |ids: Vec<String>| -> Result<Vec<NoteRecord>> {
+            let mut records = Vec::with_capacity(ids.len());
+            for id in &ids {
+                if interrupt::requested() {
+                    bail!("Interrupted; the search index was left as it was");
+                }
+                records.extend(self.load_note(id).ok());
+                progress.inc(1);
+            }
+            Ok(records)
+        };
+        let loaded = load(ids).and_then(|notes| Ok((notes, load(archived_ids)?)));
+        progress.finish_and_clear();
+        let (notes, archived) = loaded?;
+        SearchIndex::recreate(self)?.add_notes_batch(&notes)?;
+        SearchIndex::recreate_archive(self)?.add_notes_batch(&archived)?;
+        MetaCache::open(&self.meta_cache_path())?.rebuild(&notes)?;
+        Ok(())
+    }
+
+    /// Rebuild the indexes when they were built with another schema version
+    /// or `[search] analyzer` no longer matches the one they were built with,
+    /// otherwise keep the opened index for reuse
+    fn sync_index(&self) -> Result<()> {
+        for dir in [self.index_dir(), self.archive_dir()] {
+            if !SearchIndex::exists_at(&dir) {
+                continue;
+            }
+            let version = SearchIndex::schema_version_at(&dir)?;
+            if version == SCHEMA_VERSION {
+                continue;
+            }
+            if self.read_only {
+                bail!(
+                    "The search index at {} has schema v{} but this fuku uses v{}, and the repository is read-only.\n💡 Tip: Rebuild the index where the repository is writable",
+                    dir.display(),
+                    version,
+                    SCHEMA_VERSION
+                );
+            }
+            if std::env::var_os(NO_AUTO_REINDEX_ENV).is_some() {
+                bail!(
+                    "The search index at {} has schema v{} but this fuku uses v{}. Run fuku without --no-auto-reindex to rebuild it.",
+                    dir.display(),
+                    version,
+                    SCHEMA_VERSION
+                );
+            }
+            eprintln!(
+                "Info: Rebuilding the search index (schema v{} → v{})",
+                version, SCHEMA_VERSION
+            );
+            return self.rebuild_indexes();
+        }
+        if self.read_only {
+            // Searched as built, whatever the analyzer is now
+            if !SearchIndex::exists_at(&self.index_dir()) {
+                bail!(
+                    "The read-only repository at {} has no search index yet.\n💡 Tip: Add or index notes where the repository is writable first",
+                    self.root.display()
+                );
+            }
+            return Ok(());
+        }
+        let configured = FukuraConfig::load(&self.config_path())?.search.analyzer;
+        let index = SearchIndex::open_or_create(self)?;
+        if index.analyzer() == Some(configured) {
+            let _ = self.index.set(index);
+            return Ok(());
+        }
+        drop(index);
+        self.rebuild_indexes()?;
+        eprintln!(
+            "Info: Rebuilt the search index for the '{}' analyzer",
+            configured.as_str()
+        );
+        Ok(())
+    }
+
     pub fn pack_dir(&self) -> PathBuf {
         self.dot_dir.join("packs")
     }
@@ -101,45 +365,320 @@ impl FukuraRepo {
         &self.root
     }
 
-    pub fn store_note(&self, mut note: Note) -> Result<NoteRecord> {
-        let cfg = FukuraConfig::load(&self.config_path())?;
-        let redactor = Redactor::default_with_overrides(&cfg.redaction_overrides);
-        note.body = redactor.redact(&note.body);
+    pub fn store_note(&self, note: Note) -> Result<NoteRecord> {
+        self.ensure_writable()?;
+        let record = self.write_note(note)?;
+        self.audit(AuditEntry::new(
+            AuditAction::Add,
+            vec![record.object_id.clone()],
+        ))?;
+        Ok(record)
+    }
 
-        // Also redact meta fields
-        let mut redacted_meta = std::collections::BTreeMap::new();
-        for (key, value) in note.meta {
-            redacted_meta.insert(key, redactor.redact(&value));
-        }
-        note.meta = redacted_meta;
+    /// Redact, persist and index a note
+    fn write_note(&self, mut note: Note) -> Result<NoteRecord> {
+        let cfg = self.config()?;
+        let redactor = Redactor::default_with_overrides(&cfg.redaction_overrides);
+        seal_redacted(&mut note, &redactor, signing::load_key()?.as_ref())?;
 
+        // Open the cache before the index changes, so it isn't mistaken
+        // for stale and rebuilt
+        let meta = self.meta_cache()?;
         let object_id = self.persist_object("note", &note.canonical_bytes()?)?;
         let record = NoteRecord {
             object_id: object_id.clone(),
             note: note.clone(),
         };
-        let index = SearchIndex::open_or_create(self)?;
-        index.add_note(&record)?;
+        self.index()?.add_note(&record)?;
+        meta.upsert(std::slice::from_ref(&record))?;
         self.update_latest_ref(&object_id)?;
+        self.track_unsynced(std::slice::from_ref(&record))?;
         Ok(record)
     }
 
+    /// Store an edited note and drop the previous revision from the index.
+    /// Tag and like changes are recorded in the note's replicated state, so
+    /// they merge with edits made elsewhere on sync.
+    pub fn replace_note(&self, previous_id: &str, mut note: Note) -> Result<NoteRecord> {
+        self.ensure_writable()?;
+        // The state starts from the stored revision, not the edited note,
+        // or changes made in this edit would count as already seen everywhere
+        let base = self
+            .load_note(previous_id)
+            .map_or_else(|_| note.clone(), |previous| previous.note);
+        crdt::record_changes(&mut note, &base, previous_id, &self.replica_id()?);
+        let record = self.write_note(note)?;
+        if record.object_id != previous_id {
+            self.index()?.remove_note(previous_id)?;
+            self.meta_cache()?.remove(&[previous_id.to_string()])?;
+        }
+        self.audit(AuditEntry::new(
+            AuditAction::Edit,
+            vec![previous_id.to_string(), record.object_id.clone()],
+        ))?;
+        Ok(record)
+    }
+
+    /// The note that `record` is a version of: the one with the same
+    /// origin, i.e. the same first revision
+    pub fn find_version(&self, record: &NoteRecord) -> Result<Option<NoteRecord>> {
+        let origin = crdt::origin(record);
+        for local in self.iter_notes()? {
+            let local = local?;
+            if crdt::origin(&local) == origin {
+                return Ok(Some(local));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Append-only log of the changes made to this repository
+    pub fn audit_log(&self) -> AuditLog {
+        AuditLog::new(&self.dot_dir)
+    }
+
+    /// Record a change in the audit log. Notes pushed or gone, including
+    /// the previous revision of an edit, no longer count as unsynced.
+    pub fn audit(&self, entry: AuditEntry) -> Result<()> {
+        self.ensure_writable()?;
+        let settled: &[String] = match entry.action {
+            AuditAction::Push | AuditAction::Delete => &entry.object_ids[..],
+            AuditAction::Edit | AuditAction::Merge => {
+                &entry.object_ids[..entry.object_ids.len().min(1)]
+            }
+            _ => &[],
+        };
+        if !settled.is_empty() {
+            self.prompt_state().mark_settled(settled)?;
+        }
+        self.audit_log().append(&entry)
+    }
+
+    /// State files read by `fuku prompt-status`
+    pub fn prompt_state(&self) -> PromptState {
+        PromptState::new(&self.dot_dir)
+    }
+
+    /// Public copies of notes made by `fuku share`
+    pub fn shares(&self) -> ShareStore {
+        ShareStore::new(&self.dot_dir)
+    }
+
+    /// Count new private notes as unsynced until they are pushed
+    fn track_unsynced(&self, records: &[NoteRecord]) -> Result<()> {
+        let ids: Vec<String> = records
+            .iter()
+            .filter(|record| {
+                record.note.privacy == Privacy::Private && !record.note.is_local_only()
+            })
+            .map(|record| record.object_id.clone())
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.prompt_state().mark_unsynced(&ids)
+    }
+
+    /// Executables in `.fukura/hooks/` run as notes are added and pushed
+    pub fn plugins(&self) -> Plugins {
+        Plugins::new(&self.root, &self.dot_dir)
+    }
+
+    /// Id of this copy of the repository, naming its changes in the
+    /// replicated state of notes. Created on first use.
+    pub fn replica_id(&self) -> Result<String> {
+        let path = self.dot_dir.join("replica");
+        if let Ok(id) = fs::read_to_string(&path) {
+            if !id.trim().is_empty() {
+                return Ok(id.trim().to_string());
+            }
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        fs::write(&path, format!("{}\n", id))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(id)
+    }
+
+    /// Delete a note from the index and remove its object where the store
+    /// allows. Objects already packed stay in the pack but are no longer
+    /// searchable.
+    pub fn delete_note(&self, object_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let meta = self.meta_cache()?;
+        self.index()?.remove_note(object_id)?;
+        meta.remove(&[object_id.to_string()])?;
+        if self.archive_dir().exists() {
+            self.archive()?.remove_note(object_id)?;
+        }
+        self.objects.remove(object_id)?;
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        if self.latest()?.as_deref() == Some(object_id) {
+            self.write_ref("latest", "")?;
+        }
+        let mut pins = self.pins()?;
+        if let Some(pos) = pins.iter().position(|id| id == object_id) {
+            pins.remove(pos);
+            self.write_ref(PINS_REF, &pins.join("\n"))?;
+        }
+        self.audit(AuditEntry::new(
+            AuditAction::Delete,
+            vec![object_id.to_string()],
+        ))
+    }
+
+    /// Retire a note merged into another. Unlike [`FukuraRepo::delete_note`]
+    /// the object is kept, so the merged note's provenance can be looked up;
+    /// the note leaves the indexes and its pin moves to `merged_into`.
+    pub fn tombstone(&self, object_id: &str, merged_into: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let meta = self.meta_cache()?;
+        self.index()?.remove_note(object_id)?;
+        meta.remove(&[object_id.to_string()])?;
+        if self.archive_dir().exists() {
+            self.archive()?.remove_note(object_id)?;
+        }
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        let mut merged = self.read_ref_lines(MERGED_REF)?;
+        merged.push(format!("{} {}", object_id, merged_into));
+        self.write_ref(MERGED_REF, &merged.join("\n"))?;
+        let mut pins = self.pins()?;
+        if let Some(pos) = pins.iter().position(|id| id == object_id) {
+            if pins.iter().any(|id| id == merged_into) {
+                pins.remove(pos);
+            } else {
+                pins[pos] = merged_into.to_string();
+            }
+            self.write_ref(PINS_REF, &pins.join("\n"))?;
+        }
+        self.audit(AuditEntry::new(
+            AuditAction::Merge,
+            vec![object_id.to_string(), merged_into.to_string()],
+        ))
+    }
+
+    /// The note a tombstoned note ended up in, following later merges
+    pub fn merged_into(&self, object_id: &str) -> Result<Option<String>> {
+        let merges: BTreeMap<String, String> = self
+            .read_ref_lines(MERGED_REF)?
+            .iter()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect();
+        let mut current = object_id;
+        let mut hops = 0;
+        while let Some(next) = merges.get(current) {
+            current = next;
+            hops += 1;
+            if hops > merges.len() {
+                bail!("Merge history of {} has a cycle", object_id);
+            }
+        }
+        Ok((hops > 0).then(|| current.to_string()))
+    }
+
+    /// Ids of pinned notes, in the order they were pinned
+    pub fn pins(&self) -> Result<Vec<String>> {
+        self.read_ref_lines(PINS_REF)
+    }
+
+    fn read_ref_lines(&self, name: &str) -> Result<Vec<String>> {
+        let path = self.refs_dir().join(name);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Pin a note; returns false if it was already pinned
+    pub fn pin(&self, object_id: &str) -> Result<bool> {
+        self.ensure_writable()?;
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        let mut pins = self.pins()?;
+        if pins.iter().any(|id| id == object_id) {
+            return Ok(false);
+        }
+        pins.push(object_id.to_string());
+        self.write_ref(PINS_REF, &pins.join("\n"))?;
+        Ok(true)
+    }
+
+    /// Unpin a note; returns false if it wasn't pinned
+    pub fn unpin(&self, object_id: &str) -> Result<bool> {
+        self.ensure_writable()?;
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        let mut pins = self.pins()?;
+        let Some(pos) = pins.iter().position(|id| id == object_id) else {
+            return Ok(false);
+        };
+        pins.remove(pos);
+        self.write_ref(PINS_REF, &pins.join("\n"))?;
+        Ok(true)
+    }
+
+    /// Pinned notes, most recently pinned first. Cached for @N references.
+    pub fn pinned_notes(&self) -> Result<Vec<SearchHit>> {
+        let hits = self.load_pinned()?;
+        self.save_search_cache(&hits)?;
+        Ok(hits)
+    }
+
+    fn load_pinned(&self) -> Result<Vec<SearchHit>> {
+        Ok(self
+            .pins()?
+            .iter()
+            .rev()
+            .filter_map(|id| self.load_note(id).ok())
+            .map(|record| SearchHit {
+                pinned: true,
+                ..SearchHit::from_record(&record)
+            })
+            .collect())
+    }
+
+    /// Set the `pinned` flag of hits from another source, such as the index
+    pub fn mark_pinned(&self, hits: &mut [SearchHit]) -> Result<()> {
+        let pins = self.pins()?;
+        for hit in hits {
+            hit.pinned = pins.contains(&hit.object_id);
+        }
+        Ok(())
+    }
+
+    /// Set the `verified` flag of hits whose notes are signed by a trusted
+    /// key. Hits of a thin clone are left alone, as checking them would
+    /// fetch every one from the remote.
+    pub fn mark_verified(&self, hits: &mut [SearchHit]) -> Result<()> {
+        let config = self.config()?;
+        if config.thin.is_some() {
+            return Ok(());
+        }
+        let trust = signing::TrustStore::load(&config.signing)?;
+        for hit in hits {
+            hit.verified = self
+                .load_note(&hit.object_id)
+                .is_ok_and(|record| trust.check(&record.note).is_verified());
+        }
+        Ok(())
+    }
+
     /// Store multiple notes efficiently in batch
     pub fn store_notes_batch(&self, notes: Vec<Note>) -> Result<Vec<NoteRecord>> {
-        let cfg = FukuraConfig::load(&self.config_path())?;
+        self.ensure_writable()?;
+        let cfg = self.config()?;
         let redactor = Redactor::default_with_overrides(&cfg.redaction_overrides);
 
+        let meta = self.meta_cache()?;
+        let key = signing::load_key()?;
         let mut records = Vec::new();
 
         // Process all notes and create records
         for mut note in notes {
-            // Redact content
-            note.body = redactor.redact(&note.body);
-            let mut redacted_meta = std::collections::BTreeMap::new();
-            for (key, value) in note.meta {
-                redacted_meta.insert(key, redactor.redact(&value));
-            }
-            note.meta = redacted_meta;
+            seal_redacted(&mut note, &redactor, key.as_ref())?;
 
             // Persist object
             let object_id = self.persist_object("note", &note.canonical_bytes()?)?;
@@ -151,31 +690,35 @@ impl FukuraRepo {
         }
 
         // Add all records to index in batch
-        let index = SearchIndex::open_or_create(self)?;
-        index.add_notes_batch(&records)?;
+        self.index()?.add_notes_batch(&records)?;
+        meta.upsert(&records)?;
+        self.track_unsynced(&records)?;
 
         // Update latest ref with the last note
         if let Some(last_record) = records.last() {
             self.update_latest_ref(&last_record.object_id)?;
         }
+        if !records.is_empty() {
+            self.audit(AuditEntry::new(
+                AuditAction::Add,
+                records.iter().map(|r| r.object_id.clone()).collect(),
+            ))?;
+        }
 
         Ok(records)
     }
 
+    /// Index notes whose objects are stored elsewhere, like on the remote
+    /// of a thin clone
+    pub fn index_records(&self, records: &[NoteRecord]) -> Result<()> {
+        self.ensure_writable()?;
+        let meta = self.meta_cache()?;
+        self.index()?.add_notes_batch(records)?;
+        meta.upsert(records)
+    }
+
     pub fn load_note(&self, object_id: &str) -> Result<NoteRecord> {
-        let object_bytes = self.load_object_bytes(object_id)?;
-        let mut decoder = ZlibDecoder::new(std::io::Cursor::new(object_bytes));
-        let mut buf = Vec::new();
-        decoder.read_to_end(&mut buf)?;
-        let mut parts = buf.splitn(2, |b| *b == 0);
-        let header = parts.next().context("Missing object header")?;
-        let payload = parts.next().context("Missing object payload")?;
-        let header_str = std::str::from_utf8(header)?;
-        let mut header_parts = header_str.split_whitespace();
-        let object_type = header_parts.next().context("Invalid header")?;
-        if object_type != "note" {
-            bail!("Object {} is not a note", object_id);
-        }
+        let payload = self.load_typed_object(object_id, "note")?;
         let envelope: NoteEnvelope = ciborium::de::from_reader(std::io::Cursor::new(payload))?;
         let record = NoteRecord {
             object_id: object_id.to_string(),
@@ -184,23 +727,179 @@ impl FukuraRepo {
         Ok(record)
     }
 
-    pub fn load_object_bytes(&self, object_id: &str) -> Result<Vec<u8>> {
-        let path = self.object_path(object_id);
-        if path.exists() {
-            return fs::read(&path).with_context(|| format!("Failed to read {}", path.display()));
+    /// Store raw bytes kept alongside a note, such as the full output of a
+    /// truncated body, returning the attachment's object id
+    pub fn store_attachment(&self, bytes: &[u8]) -> Result<String> {
+        self.ensure_writable()?;
+        self.persist_object("blob", bytes)
+    }
+
+    pub fn load_attachment(&self, object_id: &str) -> Result<Vec<u8>> {
+        self.load_typed_object(object_id, "blob")
+    }
+
+    /// Payload of an object, checking its type
+    fn load_typed_object(&self, object_id: &str, expected: &str) -> Result<Vec<u8>> {
+        let object_bytes = self.load_object_bytes(object_id)?;
+        let mut decoder = ZlibDecoder::new(std::io::Cursor::new(object_bytes));
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf)?;
+        let split = buf
+            .iter()
+            .position(|b| *b == 0)
+            .context("Missing object payload")?;
+        let header_str = std::str::from_utf8(&buf[..split])?;
+        let object_type = header_str
+            .split_whitespace()
+            .next()
+            .context("Invalid header")?;
+        if object_type != expected {
+            bail!("Object {} is not a {}", object_id, expected);
         }
-        if let Some(bytes) = load_object_from_pack(self, object_id)? {
-            return Ok(bytes);
+        buf.drain(..=split);
+        Ok(buf)
+    }
+
+    pub fn load_object_bytes(&self, object_id: &str) -> Result<Vec<u8>> {
+        match self.objects.read(object_id)? {
+            Some(bytes) => Ok(bytes),
+            None => bail!("Object {} not found", object_id),
         }
-        bail!("Object {} not found", object_id)
     }
 
     pub fn search(&self, query: &str, limit: usize, sort: SearchSort) -> Result<Vec<SearchHit>> {
-        let index = SearchIndex::open_or_create(self)?;
-        let hits = index.search(query, limit, sort)?;
+        Ok(self
+            .search_page(query, &SearchFilter::default(), 0, limit, sort)?
+            .hits)
+    }
+
+    /// One page of search results
+    pub fn search_page(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        offset: usize,
+        limit: usize,
+        sort: SearchSort,
+    ) -> Result<SearchPage> {
+        let mut page = self
+            .index()?
+            .search_page(query, filter, offset, limit, sort)?;
+        self.mark_pinned(&mut page.hits)?;
         // Cache search results for @N references
-        self.save_search_cache(&hits)?;
-        Ok(hits)
+        self.save_search_cache(&page.hits)?;
+        Ok(page)
+    }
+
+    /// The `limit` most recently updated notes, after every pinned note
+    pub fn list_notes(&self, limit: usize) -> Result<Vec<SearchHit>> {
+        Ok(self.list_notes_page(0, limit)?.hits)
+    }
+
+    /// A page of notes, most recently updated first. Pinned notes lead the
+    /// first page on top of `limit`.
+    pub fn list_notes_page(&self, offset: usize, limit: usize) -> Result<SearchPage> {
+        let mut hits = if offset == 0 {
+            self.load_pinned()?
+        } else {
+            Vec::new()
+        };
+        let pins = self.pins()?;
+        let cache = self.meta_cache()?;
+        let total = cache.len()?;
+        let page = cache.page(offset, limit)?;
+        let end = offset + page.len();
+        hits.extend(
+            page.into_iter()
+                .filter(|hit| !pins.contains(&hit.object_id)),
+        );
+        let page = SearchPage {
+            hits,
+            total,
+            offset,
+            next_offset: (end < total).then_some(end),
+        };
+        self.save_search_cache(&page.hits)?;
+        Ok(page)
+    }
+
+    /// Search notes archived by [`FukuraRepo::archive_expired`]
+    pub fn search_archived(
+        &self,
+        query: &str,
+        limit: usize,
+        sort: SearchSort,
+    ) -> Result<Vec<SearchHit>> {
+        Ok(self
+            .search_archived_page(query, &SearchFilter::default(), 0, limit, sort)?
+            .hits)
+    }
+
+    /// One page of [`FukuraRepo::search_archived`]
+    pub fn search_archived_page(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        offset: usize,
+        limit: usize,
+        sort: SearchSort,
+    ) -> Result<SearchPage> {
+        let page = self
+            .archive()?
+            .search_page(query, filter, offset, limit, sort)?;
+        self.save_search_cache(&page.hits)?;
+        Ok(page)
+    }
+
+    /// Move notes whose expiry is at or before `now` from the main index to
+    /// the archive. Their objects are kept, so they can still be viewed by id.
+    pub fn archive_expired(&self, now: DateTime<Utc>) -> Result<Vec<NoteRecord>> {
+        let expired: Vec<NoteRecord> = self
+            .iter_notes()?
+            .filter_map(Result::ok)
+            .filter(|record| record.note.is_expired(now))
+            .collect();
+        self.archive_notes(&expired)?;
+        Ok(expired)
+    }
+
+    /// Move notes from the main index to the archive, out of listings and
+    /// searches but not deleted; `fuku search --archived` still finds them
+    pub fn archive_notes(&self, records: &[NoteRecord]) -> Result<()> {
+        self.ensure_writable()?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        let meta = self.meta_cache()?;
+        // Archive first: a crash in between leaves a note in both indexes
+        // rather than in neither
+        self.archive()?.add_notes_batch(records)?;
+        let ids: Vec<String> = records.iter().map(|r| r.object_id.clone()).collect();
+        self.index()?.remove_notes(&ids)?;
+        meta.remove(&ids)?;
+        self.audit(AuditEntry::new(AuditAction::Archive, ids))
+    }
+
+    /// Move archived notes back to the main index
+    pub fn unarchive_notes(&self, records: &[NoteRecord]) -> Result<()> {
+        self.ensure_writable()?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.index()?.add_notes_batch(records)?;
+        self.meta_cache()?.upsert(records)?;
+        let ids: Vec<String> = records.iter().map(|r| r.object_id.clone()).collect();
+        self.archive()?.remove_notes(&ids)?;
+        self.audit(AuditEntry::new(AuditAction::Unarchive, ids))
+    }
+
+    /// Whether a note is in the archive rather than the main index
+    pub fn is_archived(&self, object_id: &str) -> Result<bool> {
+        Ok(self
+            .archive()?
+            .object_ids()?
+            .iter()
+            .any(|id| id == object_id))
     }
 
     fn search_cache_path(&self) -> PathBuf {
@@ -209,8 +908,12 @@ impl FukuraRepo {
 
     fn save_search_cache(&self, hits: &[SearchHit]) -> Result<()> {
         let json = serde_json::to_string_pretty(hits)?;
-        fs::write(self.search_cache_path(), json)?;
-        Ok(())
+        match fs::write(self.search_cache_path(), json) {
+            // Only `@1` style references miss it, so a read-only mount
+            // doesn't fail searches
+            Err(_) if self.read_only => Ok(()),
+            result => Ok(result?),
+        }
     }
 
     pub fn load_search_cache(&self) -> Result<Vec<SearchHit>> {
@@ -224,7 +927,27 @@ impl FukuraRepo {
     }
 
     pub fn pack_loose_objects(&self, prune: bool) -> Result<PackReport> {
-        pack_objects(self, prune)
+        self.ensure_writable()?;
+        let _lock = RepoLock::exclusive(&self.locks_dir(), OBJECTS_LOCK)?;
+        self.objects.pack(prune)
+    }
+
+    /// Loose objects waiting for `fuku gc`
+    pub fn loose_usage(&self) -> Result<LooseUsage> {
+        self.objects.loose_usage()
+    }
+
+    /// Pack and prune loose objects once they exceed the `[gc]` thresholds.
+    /// `None` when they don't, so frequent calls are cheap.
+    pub fn auto_pack(&self) -> Result<Option<PackReport>> {
+        if self.read_only {
+            return Ok(None);
+        }
+        let usage = self.loose_usage()?;
+        if usage.objects == 0 || !self.config()?.gc.is_due(&usage) {
+            return Ok(None);
+        }
+        self.pack_loose_objects(true).map(Some)
     }
 
     fn persist_object(&self, object_type: &str, payload: &[u8]) -> Result<String> {
@@ -234,48 +957,62 @@ impl FukuraRepo {
         hasher.update(&header);
         let digest = hasher.finalize();
         let object_id = hex::encode(digest);
-        let (prefix, rest) = object_id.split_at(2);
-        let dir_path = self.objects_dir().join(prefix);
-        fs::create_dir_all(&dir_path)?;
-        let object_path = dir_path.join(rest);
-        if object_path.exists() {
-            return Ok(object_id);
-        }
         let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
         encoder.write_all(&header)?;
         let compressed = encoder.finish()?;
-        let mut temp = NamedTempFile::new_in(&dir_path)?;
-        temp.write_all(&compressed)?;
-        temp.flush()?;
-        temp.as_file().sync_all()?;
-        let persisted = temp.persist(&object_path)?;
-        let _ = persisted.sync_all();
-        if let Ok(dir_file) = File::open(&dir_path) {
-            let _ = dir_file.sync_all();
-        }
-        if let Ok(objects_dir) = File::open(self.objects_dir()) {
-            let _ = objects_dir.sync_all();
-        }
+        let _lock = RepoLock::shared(&self.locks_dir(), OBJECTS_LOCK)?;
+        self.objects.write(&object_id, &compressed)?;
         Ok(object_id)
     }
 
-    fn update_latest_ref(&self, object_id: &str) -> Result<()> {
-        let latest_path = self.refs_dir().join("latest");
-        fs::write(&latest_path, object_id.as_bytes())?;
-        if let Ok(file) = File::open(&latest_path) {
-            let _ = file.sync_all();
+    /// Every ref by name, e.g. `latest` and `pins`
+    pub fn refs(&self) -> Result<BTreeMap<String, String>> {
+        let mut refs = BTreeMap::new();
+        for entry in fs::read_dir(self.refs_dir())? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Skip temp files of a write in progress
+            if !entry.file_type()?.is_file() || name.starts_with('.') {
+                continue;
+            }
+            refs.insert(name, fs::read_to_string(entry.path())?);
         }
-        if let Ok(dir_file) = File::open(self.refs_dir()) {
+        Ok(refs)
+    }
+
+    /// Replace a ref with refs from another repository, e.g. when cloning
+    pub fn set_ref(&self, name: &str, value: &str) -> Result<()> {
+        self.ensure_writable()?;
+        ensure!(
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "Invalid ref name '{}'",
+            name
+        );
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        self.write_ref(name, value)
+    }
+
+    fn update_latest_ref(&self, object_id: &str) -> Result<()> {
+        let _lock = RepoLock::exclusive(&self.locks_dir(), REFS_LOCK)?;
+        self.write_ref("latest", object_id)
+    }
+
+    /// Atomically replace a ref; callers hold the refs lock
+    fn write_ref(&self, name: &str, value: &str) -> Result<()> {
+        let refs_dir = self.refs_dir();
+        let mut temp = NamedTempFile::new_in(&refs_dir)?;
+        temp.write_all(value.as_bytes())?;
+        temp.as_file().sync_all()?;
+        temp.persist(refs_dir.join(name))?;
+        if let Ok(dir_file) = File::open(&refs_dir) {
             let _ = dir_file.sync_all();
         }
         Ok(())
     }
 
-    fn object_path(&self, object_id: &str) -> PathBuf {
-        let (prefix, rest) = object_id.split_at(2);
-        self.objects_dir().join(prefix).join(rest)
-    }
-
     pub fn latest(&self) -> Result<Option<String>> {
         let latest_path = self.refs_dir().join("latest");
         if !latest_path.exists() {
@@ -290,28 +1027,53 @@ impl FukuraRepo {
         }
     }
 
+    /// Effective config: the repository's, global defaults, the active
+    /// profile and `FUKURA_*` overrides
     pub fn config(&self) -> Result<FukuraConfig> {
         FukuraConfig::load_with_global_fallback(&self.config_path())
     }
 
+    /// The repository's own config file, to change and save
+    pub fn local_config(&self) -> Result<FukuraConfig> {
+        FukuraConfig::load(&self.config_path())
+    }
+
     pub fn collect_tags(&self) -> Result<Vec<String>> {
-        let index = SearchIndex::open_or_create(self)?;
-        index.collect_tags()
+        self.index()?.collect_tags()
     }
 
+    /// Tags with how many notes carry each and when one was last updated,
+    /// most used first. Answered from the meta cache, without loading notes.
+    pub fn tag_stats(&self) -> Result<Vec<TagStat>> {
+        self.meta_cache()?.tag_stats()
+    }
+
+    /// Every note, most recently updated first. Loads everything into memory;
+    /// prefer [`FukuraRepo::iter_notes`] for large repositories.
     pub fn list_all_notes(&self) -> Result<Vec<NoteRecord>> {
-        // Use search with empty query and large limit to get all notes
-        let hits = self.search("", 10000, SearchSort::Updated)?;
-        let mut records = Vec::new();
+        Ok(self.iter_notes()?.filter_map(Result::ok).collect())
+    }
 
-        for hit in hits {
-            match self.load_note(&hit.object_id) {
-                Ok(record) => records.push(record),
-                Err(_) => continue, // Skip if note can't be loaded
-            }
-        }
+    /// Iterate over every note, most recently updated first, loading each
+    /// note only when it is reached
+    pub fn iter_notes(&self) -> Result<NoteIter<'_>> {
+        self.notes_page(0, usize::MAX)
+    }
 
-        Ok(records)
+    /// Iterate over `limit` notes starting at `offset` in the order of
+    /// [`FukuraRepo::iter_notes`]
+    pub fn notes_page(&self, offset: usize, limit: usize) -> Result<NoteIter<'_>> {
+        let ids: Vec<String> = self
+            .index()?
+            .object_ids()?
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect();
+        Ok(NoteIter {
+            repo: self,
+            ids: ids.into_iter(),
+        })
     }
 
     pub fn resolve_object_id(&self, input: &str) -> Result<String> {
@@ -347,71 +1109,13 @@ impl FukuraRepo {
             }
         }
 
-        if candidate.len() >= 64 {
-            let object_id = candidate[..64].to_lowercase();
-            if self.object_path(&object_id).exists() {
+        if let Some(object_id) = candidate.get(..64) {
+            let object_id = object_id.to_lowercase();
+            if self.objects.contains(&object_id)? {
                 return Ok(object_id);
             }
-            if let Ok(map) = load_pack_indices(self) {
-                if map.contains_key(&object_id) {
-                    return Ok(object_id);
-                }
-            }
-        }
-        let mut matches = Vec::new();
-        if candidate.len() >= 2 {
-            let (dir_prefix, rest) = candidate.split_at(2);
-            let dir_path = self.objects_dir().join(dir_prefix);
-            if dir_path.exists() {
-                for entry in fs::read_dir(dir_path)? {
-                    let entry = entry?;
-                    if !entry.file_type()?.is_file() {
-                        continue;
-                    }
-                    let name = entry.file_name();
-                    let name = name.to_string_lossy();
-                    if name.starts_with(rest) {
-                        matches.push(format!("{}{}", dir_prefix, name));
-                    }
-                }
-            }
-        } else {
-            let objects_dir = self.objects_dir();
-            if objects_dir.exists() {
-                for dir in fs::read_dir(objects_dir)? {
-                    let dir = dir?;
-                    if !dir.file_type()?.is_dir() {
-                        continue;
-                    }
-                    let dir_name = dir.file_name().to_string_lossy().to_string();
-                    if !dir_name.starts_with(candidate) {
-                        continue;
-                    }
-                    for entry in fs::read_dir(dir.path())? {
-                        let entry = entry?;
-                        if !entry.file_type()?.is_file() {
-                            continue;
-                        }
-                        let file_name = entry.file_name().to_string_lossy().to_string();
-                        matches.push(format!("{}{}", dir_name, file_name));
-                    }
-                }
-            }
-        }
-        matches.sort();
-        matches.dedup();
-        if matches.is_empty() {
-            if let Ok(map) = load_pack_indices(self) {
-                matches.extend(
-                    map.keys()
-                        .filter(|id| id.starts_with(candidate))
-                        .cloned()
-                        .collect::<Vec<_>>(),
-                );
-                matches.sort();
-                matches.dedup();
-            }
         }
+        let mut matches = self.objects.ids_with_prefix(candidate)?;
         ensure!(
             !matches.is_empty(),
             "No object matching '{}'\n💡 Tip: Use 'fuku search' to list available notes, or '@latest' for the most recent",
@@ -426,3 +1130,61 @@ impl FukuraRepo {
         Ok(matches.remove(0))
     }
 }
+
+/// Notes of a repository, loaded lazily from objects and packs.
+/// Yields an error for notes whose object can't be read.
+pub struct NoteIter<'a> {
+    repo: &'a FukuraRepo,
+    ids: std::vec::IntoIter<String>,
+}
+
+impl Iterator for NoteIter<'_> {
+    type Item = Result<NoteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let object_id = self.ids.next()?;
+        Some(self.repo.load_note(&object_id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl ExactSizeIterator for NoteIter<'_> {}
+
+/// Warn that `note` holds what looks like a live credential. Redaction
+/// masks it here, but a secret pasted into a note has most likely leaked
+/// wherever it was copied from too, so it should be rotated.
+fn warn_leaks(note: &Note, redactor: &Redactor) {
+    let mut leaks = redactor.leaks(&note.body);
+    for value in note.meta.values() {
+        leaks.extend(redactor.leaks(value));
+    }
+    if leaks.is_empty() {
+        return;
+    }
+    leaks.sort_unstable();
+    leaks.dedup();
+    tracing::warn!(title = %note.title, rules = ?leaks, "live-looking secret redacted");
+    eprintln!(
+        "⚠️  Warning: '{}' contained what looks like a live secret ({}). It was redacted, but rotate it now: it has most likely leaked elsewhere too.",
+        note.title,
+        leaks.join(", ")
+    );
+}
+
+/// Mask secrets in the body and meta of a note about to be stored, then
+/// sign it with `key`
+fn seal_redacted(
+    note: &mut Note,
+    redactor: &Redactor,
+    key: Option<&ed25519_dalek::SigningKey>,
+) -> Result<()> {
+    warn_leaks(note, redactor);
+    note.body = redactor.redact(&note.body);
+    for value in note.meta.values_mut() {
+        *value = redactor.redact(value);
+    }
+    signing::seal(note, key)
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::Serialize;
+
+use crate::config::{FukuraConfig, SigningConfig};
+use crate::models::{Note, NoteSignature};
+
+/// File next to the global config holding this user's hex signing key
+pub const SIGNING_KEY_FILE: &str = "signing_key";
+
+pub fn key_path() -> Result<PathBuf> {
+    Ok(FukuraConfig::global_config_dir()?.join(SIGNING_KEY_FILE))
+}
+
+/// This user's signing key, if they made one with `fuku keys generate`
+pub fn load_key() -> Result<Option<SigningKey>> {
+    let path = key_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let bytes: [u8; 32] = hex::decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .with_context(|| format!("{} doesn't hold a signing key", path.display()))?;
+    Ok(Some(SigningKey::from_bytes(&bytes)))
+}
+
+/// Make and store a new signing key, readable only by this user. An
+/// existing key is only replaced with `force`.
+pub fn generate_key(force: bool) -> Result<SigningKey> {
+    let path = key_path()?;
+    if path.exists() && !force {
+        bail!(
+            "A signing key already exists at {}; pass --force to replace it",
+            path.display()
+        );
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let key = SigningKey::generate(&mut rand_core::OsRng);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    writeln!(file, "{}", hex::encode(key.to_bytes()))?;
+    Ok(key)
+}
+
+/// Hex public key of `key`, as teammates add it to `[signing.trusted]`
+pub fn public_key(key: &SigningKey) -> String {
+    hex::encode(key.verifying_key().to_bytes())
+}
+
+/// `text` as a normalized hex public key, or an error if it isn't one
+pub fn parse_public_key(text: &str) -> Result<String> {
+    let text = text.trim().to_lowercase();
+    let valid = hex::decode(&text)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .is_some_and(|bytes| VerifyingKey::from_bytes(&bytes).is_ok());
+    if !valid {
+        bail!(
+            "'{}' is not an Ed25519 public key (64 hex characters)",
+            text
+        );
+    }
+    Ok(text)
+}
+
+/// The bytes a signature covers: the note's canonical bytes without the
+/// signature itself
+fn signed_bytes(note: &Note) -> Result<Vec<u8>> {
+    let mut unsigned = note.clone();
+    unsigned.signature = None;
+    unsigned.canonical_bytes()
+}
+
+/// Sign `note` as this user
+pub fn sign(note: &mut Note, key: &SigningKey) -> Result<()> {
+    let signature = key.sign(&signed_bytes(note)?);
+    note.signature = Some(NoteSignature {
+        signer: crate::audit::actor().to_string(),
+        public_key: public_key(key),
+        signature: hex::encode(signature.to_bytes()),
+    });
+    Ok(())
+}
+
+/// Whether `note` is signed and the signature matches its content
+pub fn verify(note: &Note) -> bool {
+    let Some(signed) = &note.signature else {
+        return false;
+    };
+    let key = hex::decode(&signed.public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+    let signature = hex::decode(&signed.signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|bytes| Signature::from_bytes(&bytes));
+    let (Some(key), Some(signature)) = (key, signature) else {
+        return false;
+    };
+    signed_bytes(note).is_ok_and(|bytes| key.verify_strict(&bytes, &signature).is_ok())
+}
+
+/// Sign a note about to be stored. A signature that still matches, like
+/// that of a pulled note, is kept; otherwise the note is signed with `key`,
+/// or left unsigned without one.
+pub fn seal(note: &mut Note, key: Option<&SigningKey>) -> Result<()> {
+    if note.signature.is_some() && verify(note) {
+        return Ok(());
+    }
+    note.signature = None;
+    match key {
+        Some(key) => sign(note, key),
+        None => Ok(()),
+    }
+}
+
+/// How far a note's signature can be trusted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Trust {
+    /// Signed by a trusted key; `name` is who the key is trusted as
+    Verified {
+        name: String,
+    },
+    /// Signed, but by a key nobody trusted
+    Untrusted {
+        signer: String,
+    },
+    /// The signature doesn't match the note
+    Invalid,
+    Unsigned,
+}
+
+impl Trust {
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Trust::Verified { .. })
+    }
+}
+
+/// Public keys trusted to sign notes: `[signing.trusted]` and this user's
+/// own key
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    /// Hex public key to the name it is trusted as
+    keys: BTreeMap<String, String>,
+}
+
+impl TrustStore {
+    pub fn new(config: &SigningConfig, own: Option<&SigningKey>) -> Self {
+        let mut keys = BTreeMap::new();
+        for (name, public_keys) in &config.trusted {
+            for key in public_keys {
+                keys.insert(key.trim().to_lowercase(), name.clone());
+            }
+        }
+        if let Some(own) = own {
+            keys.insert(public_key(own), crate::note_service::default_author().name);
+        }
+        Self { keys }
+    }
+
+    /// Trust of the repository's `[signing]` config and the local key
+    pub fn load(config: &SigningConfig) -> Result<Self> {
+        Ok(Self::new(config, load_key()?.as_ref()))
+    }
+
+    pub fn check(&self, note: &Note) -> Trust {
+        let Some(signed) = &note.signature else {
+            return Trust::Unsigned;
+        };
+        if !verify(note) {
+            return Trust::Invalid;
+        }
+        match self.keys.get(&signed.public_key.to_lowercase()) {
+            Some(name) => Trust::Verified { name: name.clone() },
+            None => Trust::Untrusted {
+                signer: signed.signer.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+
+    #[test]
+    fn test_signatures_verify_until_the_note_changes() {
+        let alice = SigningKey::from_bytes(&[7; 32]);
+        let mallory = SigningKey::from_bytes(&[9; 32]);
+        let mut config = SigningConfig::default();
+        config
+            .trusted
+            .insert("alice".to_string(), vec![public_key(&alice)]);
+        let trust = TrustStore::new(&config, None);
+
+        let mut note = NoteBuilder::new("Rotate certs", "certbot renew").build();
+        assert_eq!(trust.check(&note), Trust::Unsigned);
+        seal(&mut note, Some(&alice)).unwrap();
+        assert_eq!(
+            trust.check(&note),
+            Trust::Verified {
+                name: "alice".to_string()
+            }
+        );
+
+        // A matching signature survives being stored again
+        let signed = note.signature.clone();
+        seal(&mut note, Some(&mallory)).unwrap();
+        assert_eq!(note.signature, signed);
+
+        note.body.push_str(" --force");
+        assert_eq!(trust.check(&note), Trust::Invalid);
+        seal(&mut note, Some(&mallory)).unwrap();
+        assert!(matches!(trust.check(&note), Trust::Untrusted { .. }));
+        // Without a key a matching signature is still kept
+        seal(&mut note, None).unwrap();
+        assert!(verify(&note));
+
+        note.title.push('!');
+        seal(&mut note, None).unwrap();
+        assert_eq!(trust.check(&note), Trust::Unsigned);
+    }
+}
@@ -1,37 +1,345 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::time::Duration;
 
-use crate::models::NoteRecord;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::audit::{AuditAction, AuditEntry};
+use crate::config::SyncConfig;
+use crate::crdt::{merge_notes, NoteCrdt};
+use crate::models::{Note, NoteRecord};
+use crate::plugins::PluginHook;
+use crate::redaction::Redactor;
 use crate::repo::FukuraRepo;
+use crate::signing::{Trust, TrustStore};
+
+/// Retries of a request the hub rate-limited or couldn't serve
+const MAX_RETRIES: u32 = 4;
+
+/// Longest wait before a retry, whatever `Retry-After` asks for
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Object ids asked about per `POST /v1/notes/missing`
+const NEGOTIATE_CHUNK: usize = 1000;
 
 fn normalize_remote(remote: &str) -> String {
     remote.trim_end_matches('/').to_string()
 }
 
-pub async fn push_note(repo: &FukuraRepo, object_id: &str, remote: &str) -> Result<String> {
-    let record = repo
-        .load_note(object_id)
-        .with_context(|| format!("Failed to load note {}", object_id))?;
+/// A hub at `remote`. Cheap to clone; clones share the connection pool.
+#[derive(Debug, Clone)]
+pub struct Hub {
+    client: Client,
+    base: String,
+    token: Option<String>,
+}
+
+/// The hub's answer for one note of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushResult {
+    pub object_id: String,
+    #[serde(default)]
+    pub remote_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// One pushed note: the hub's id for it, or why it failed
+#[derive(Debug)]
+pub struct PushOutcome {
+    pub object_id: String,
+    pub title: String,
+    pub result: Result<String>,
+}
+
+/// What [`push_notes`] got through
+#[derive(Debug, Default)]
+pub struct PushReport {
+    pub outcomes: Vec<PushOutcome>,
+    /// Stopped by Ctrl-C before every note was sent
+    pub interrupted: bool,
+}
+
+impl PushReport {
+    /// Record the notes that reached `remote` in the audit log of `repo`
+    pub fn audit(&self, repo: &FukuraRepo, remote: &str) -> Result<()> {
+        let pushed: Vec<String> = self
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_ok())
+            .map(|outcome| outcome.object_id.clone())
+            .collect();
+        if pushed.is_empty() {
+            return Ok(());
+        }
+        repo.audit(AuditEntry::new(AuditAction::Push, pushed).detail(remote))
+    }
+}
+
+/// `GET /v1/clone`: what a clone starts from
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CloneManifest {
+    /// Contents of each ref, e.g. `latest` or `pins`
+    #[serde(default)]
+    pub refs: BTreeMap<String, String>,
+    /// Every note, with bodies cut down to their summary; the full note is
+    /// the object of the same id
+    pub notes: Vec<NoteRecord>,
+}
+
+/// What a hub answers to `POST /v1/shares`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SharedCopy {
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct ShareRequest<'a> {
+    note: &'a NoteRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct MissingRequest<'a> {
+    object_ids: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct MissingResponse {
+    missing: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchRequest<'a> {
+    notes: &'a [NoteRecord],
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    results: Vec<PushResult>,
+}
+
+impl Hub {
+    pub fn new(remote: &str) -> Self {
+        // Get authentication token from environment or config
+        let token = std::env::var("FUKURA_TOKEN")
+            .or_else(|_| std::env::var("FUKURA_API_TOKEN"))
+            .ok()
+            .filter(|token| !token.is_empty());
+        Self {
+            client: Client::new(),
+            base: normalize_remote(remote),
+            token,
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.base, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
 
-    // Get authentication token from environment or config
-    let token = std::env::var("FUKURA_TOKEN")
-        .or_else(|_| std::env::var("FUKURA_API_TOKEN"))
-        .unwrap_or_default();
+    /// Send `request`, waiting out 429 and 503 responses for as long as the
+    /// hub's `Retry-After` asks, or with exponential backoff without one
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let response = request
+                .try_clone()
+                .context("Request can't be retried")?
+                .send()
+                .await
+                .with_context(|| "Failed to contact remote hub")?;
+            let status = response.status();
+            let throttled = status == StatusCode::TOO_MANY_REQUESTS
+                || status == StatusCode::SERVICE_UNAVAILABLE;
+            if !throttled || attempt == MAX_RETRIES {
+                return Ok(response);
+            }
+            let delay = retry_after(&response)
+                .unwrap_or_else(|| Duration::from_millis(500 << attempt))
+                .min(MAX_RETRY_DELAY);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
 
-    let client = Client::new();
-    let url = format!("{}/v1/notes", normalize_remote(remote));
+    /// Push one note, returning the hub's id for it
+    pub async fn push(&self, record: &NoteRecord) -> Result<String> {
+        let response = self
+            .send(self.request(Method::POST, "/v1/notes").json(record))
+            .await?;
+        let remote_record: NoteRecord = ensure_success(response)
+            .await?
+            .json()
+            .await
+            .with_context(|| "Failed to decode hub response")?;
+        Ok(remote_record.object_id)
+    }
 
-    let mut request = client.post(url).json(&record);
+    /// Push `records` in one request. `None` when the hub has no batch
+    /// endpoint, so notes have to go one at a time.
+    pub async fn push_batch(&self, records: &[NoteRecord]) -> Result<Option<Vec<PushResult>>> {
+        let response = self
+            .send(
+                self.request(Method::POST, "/v1/notes/batch")
+                    .json(&BatchRequest { notes: records }),
+            )
+            .await?;
+        if unsupported(response.status()) {
+            return Ok(None);
+        }
+        let batch: BatchResponse = ensure_success(response)
+            .await?
+            .json()
+            .await
+            .with_context(|| "Failed to decode hub response")?;
+        Ok(Some(batch.results))
+    }
 
-    // Add authorization header if token is available
-    if !token.is_empty() {
-        request = request.header("Authorization", format!("Bearer {}", token));
+    pub async fn fetch(&self, object_id: &str) -> Result<NoteRecord> {
+        let response = self
+            .send(self.request(Method::GET, &format!("/v1/notes/{}", object_id)))
+            .await?;
+        ensure_success(response)
+            .await?
+            .json()
+            .await
+            .with_context(|| "Failed to decode hub response")
     }
 
-    let response = request
-        .send()
-        .await
-        .with_context(|| "Failed to contact remote hub")?;
+    /// Stored (compressed) bytes of an object, `None` when the hub lacks it
+    pub async fn fetch_object(&self, object_id: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .send(self.request(Method::GET, &format!("/v1/objects/{}", object_id)))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let bytes = ensure_success(response)
+            .await?
+            .bytes()
+            .await
+            .with_context(|| "Failed to read hub response")?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    pub async fn clone_manifest(&self) -> Result<CloneManifest> {
+        let response = self.send(self.request(Method::GET, "/v1/clone")).await?;
+        ensure_success(response)
+            .await?
+            .json()
+            .await
+            .with_context(|| "Failed to decode hub response")
+    }
+
+    /// Publish a public copy of a note at a URL anyone can open, until
+    /// `expires_at` if set
+    pub async fn share(
+        &self,
+        record: &NoteRecord,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<SharedCopy> {
+        let response = self
+            .send(
+                self.request(Method::POST, "/v1/shares")
+                    .json(&ShareRequest {
+                        note: record,
+                        expires_at,
+                    }),
+            )
+            .await?;
+        if unsupported(response.status()) {
+            bail!("{} doesn't support sharing notes", self.base);
+        }
+        ensure_success(response)
+            .await?
+            .json()
+            .await
+            .with_context(|| "Failed to decode hub response")
+    }
+
+    /// Take down a shared copy. One the hub no longer has (it expired, or
+    /// was revoked elsewhere) counts as revoked.
+    pub async fn revoke_share(&self, id: &str) -> Result<()> {
+        let response = self
+            .send(self.request(Method::DELETE, &format!("/v1/shares/{}", id)))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        ensure_success(response).await?;
+        Ok(())
+    }
+
+    /// Split `records` into the ones the hub still lacks and the ones it
+    /// already has. Object ids are content hashes, so a note the hub knows
+    /// is unchanged there. Hubs that can't tell get everything.
+    pub async fn partition_synced(
+        &self,
+        records: Vec<NoteRecord>,
+    ) -> Result<(Vec<NoteRecord>, Vec<NoteRecord>)> {
+        let ids: Vec<String> = records
+            .iter()
+            .map(|record| record.object_id.clone())
+            .collect();
+        let mut missing = HashSet::new();
+        for chunk in ids.chunks(NEGOTIATE_CHUNK) {
+            let response = self
+                .send(
+                    self.request(Method::POST, "/v1/notes/missing")
+                        .json(&MissingRequest { object_ids: chunk }),
+                )
+                .await?;
+            if unsupported(response.status()) {
+                return Ok((records, Vec::new()));
+            }
+            let answer: MissingResponse = ensure_success(response)
+                .await?
+                .json()
+                .await
+                .with_context(|| "Failed to decode hub response")?;
+            missing.extend(answer.missing);
+        }
+        Ok(records
+            .into_iter()
+            .partition(|record| missing.contains(&record.object_id)))
+    }
+}
+
+/// Whether the hub predates an endpoint, so the older protocol applies
+fn unsupported(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+async fn ensure_success(response: Response) -> Result<Response> {
     let status = response.status();
     if !status.is_success() {
         let body = response
@@ -40,45 +348,439 @@ pub async fn push_note(repo: &FukuraRepo, object_id: &str, remote: &str) -> Resu
             .unwrap_or_else(|_| "<unavailable>".to_string());
         anyhow::bail!("Remote returned {}: {}", status, body);
     }
-    let remote_record: NoteRecord = response
-        .json()
-        .await
-        .with_context(|| "Failed to decode hub response")?;
-    Ok(remote_record.object_id)
+    Ok(response)
+}
+
+fn outcome(record: &NoteRecord, result: Result<String>) -> PushOutcome {
+    PushOutcome {
+        object_id: record.object_id.clone(),
+        title: record.note.title.clone(),
+        result,
+    }
+}
+
+/// Match a batch answer back to its notes; a note the hub left out failed
+fn batch_outcomes(
+    chunk: &[NoteRecord],
+    answer: Result<Option<Vec<PushResult>>>,
+) -> Vec<PushOutcome> {
+    let mut results = match answer {
+        Ok(Some(results)) => results,
+        Ok(None) => {
+            return chunk
+                .iter()
+                .map(|record| outcome(record, Err(anyhow!("The hub stopped accepting batches"))))
+                .collect()
+        }
+        Err(e) => {
+            let message = format!("{:#}", e);
+            return chunk
+                .iter()
+                .map(|record| outcome(record, Err(anyhow!(message.clone()))))
+                .collect();
+        }
+    };
+    chunk
+        .iter()
+        .map(|record| {
+            let result = match results
+                .iter()
+                .position(|result| result.object_id == record.object_id)
+            {
+                Some(position) => {
+                    let result = results.swap_remove(position);
+                    match (result.remote_id, result.error) {
+                        (Some(remote_id), None) => Ok(remote_id),
+                        (_, Some(error)) => Err(anyhow!(error)),
+                        (None, None) => Err(anyhow!("The hub returned no id")),
+                    }
+                }
+                None => Err(anyhow!("The hub left the note out of its answer")),
+            };
+            outcome(record, result)
+        })
+        .collect()
+}
+
+async fn push_chunk(hub: Hub, chunk: Vec<NoteRecord>, batched: bool) -> Vec<PushOutcome> {
+    if batched {
+        let answer = hub.push_batch(&chunk).await;
+        return batch_outcomes(&chunk, answer);
+    }
+    let mut outcomes = Vec::with_capacity(chunk.len());
+    for record in &chunk {
+        outcomes.push(outcome(record, hub.push(record).await));
+    }
+    outcomes
+}
+
+/// Push `records` in batches of `[sync] batch_size`, or one request per note
+/// when the hub has no batch endpoint, with `[sync] concurrency` requests in
+/// flight. `on_done` sees every note as its request finishes. Ctrl-C stops
+/// sending new requests and waits for the ones in flight.
+pub async fn push_notes(
+    hub: &Hub,
+    records: Vec<NoteRecord>,
+    config: &SyncConfig,
+    mut on_done: impl FnMut(&PushOutcome),
+) -> Result<PushReport> {
+    let mut report = PushReport::default();
+    let mut queue: VecDeque<Vec<NoteRecord>> = VecDeque::new();
+    let mut records = records.into_iter().peekable();
+    while records.peek().is_some() {
+        queue.push_back(records.by_ref().take(config.batch_size.max(1)).collect());
+    }
+
+    // The first batch finds out whether the hub takes batches at all
+    let mut batched = true;
+    if let Some(first) = queue.pop_front() {
+        if crate::interrupt::requested() {
+            queue.push_front(first);
+        } else {
+            match hub.push_batch(&first).await {
+                Ok(None) => {
+                    batched = false;
+                    queue.push_front(first);
+                }
+                answer => {
+                    for outcome in batch_outcomes(&first, answer) {
+                        on_done(&outcome);
+                        report.outcomes.push(outcome);
+                    }
+                }
+            }
+        }
+    }
+    if !batched {
+        queue = queue
+            .into_iter()
+            .flatten()
+            .map(|record| vec![record])
+            .collect();
+    }
+
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < config.concurrency.max(1) && !crate::interrupt::requested() {
+            let Some(chunk) = queue.pop_front() else {
+                break;
+            };
+            tasks.spawn(push_chunk(hub.clone(), chunk, batched));
+        }
+        let Some(joined) = tasks.join_next().await else {
+            break;
+        };
+        for outcome in joined.context("Sync task failed")? {
+            on_done(&outcome);
+            report.outcomes.push(outcome);
+        }
+    }
+    report.interrupted = !queue.is_empty();
+    Ok(report)
+}
+
+/// Names of the [`LEAK_RULES`](crate::redaction::LEAK_RULES) a note matches
+/// anywhere it could carry a secret to a hub. The built-in rules are used,
+/// so one turned off in `redaction_overrides` still counts.
+pub fn leaked_secrets(note: &Note) -> Vec<&'static str> {
+    static REDACTOR: Lazy<Redactor> =
+        Lazy::new(|| Redactor::default_with_overrides(&BTreeMap::new()));
+    let mut texts = vec![&note.title, &note.body];
+    texts.extend(note.meta.values());
+    texts.extend(&note.links);
+    for solution in &note.solutions {
+        texts.extend(&solution.steps);
+    }
+    let mut leaks: Vec<&'static str> = texts.iter().flat_map(|text| REDACTOR.leaks(text)).collect();
+    leaks.sort_unstable();
+    leaks.dedup();
+    leaks
+}
+
+/// Run the `pre-push` plugin over `records`, returning the ones to push as
+/// the plugin left them and the ids of the ones it rejected, with why
+pub fn pre_push(
+    repo: &FukuraRepo,
+    records: Vec<NoteRecord>,
+) -> (Vec<NoteRecord>, Vec<(String, anyhow::Error)>) {
+    let plugins = repo.plugins();
+    let mut accepted = Vec::with_capacity(records.len());
+    let mut rejected = Vec::new();
+    for mut record in records {
+        match plugins.filter(PluginHook::PrePush, record.note, Some(&record.object_id)) {
+            Ok(note) => {
+                record.note = note;
+                accepted.push(record);
+            }
+            Err(err) => rejected.push((record.object_id, err)),
+        }
+    }
+    (accepted, rejected)
 }
 
+/// Push one note through the `pre-push` plugin. A note holding what looks
+/// like a live secret is refused unless `force` is set.
+pub async fn push_note(
+    repo: &FukuraRepo,
+    object_id: &str,
+    remote: &str,
+    force: bool,
+) -> Result<String> {
+    let mut record = repo
+        .load_note(object_id)
+        .with_context(|| format!("Failed to load note {}", object_id))?;
+    if record.note.is_local_only() {
+        bail!(
+            "Note {} was made in a private zone and is never synced",
+            object_id
+        );
+    }
+    record.note =
+        repo.plugins()
+            .filter(PluginHook::PrePush, record.note, Some(&record.object_id))?;
+    let leaks = leaked_secrets(&record.note);
+    if !force && !leaks.is_empty() {
+        bail!(
+            "Not pushing '{}': it contains what looks like a live secret ({})\n💡 Tip: Rotate the secret and remove it from the note, or push anyway with --force",
+            record.note.title,
+            leaks.join(", ")
+        );
+    }
+    let remote_id = Hub::new(remote).push(&record).await?;
+    repo.audit(AuditEntry::new(AuditAction::Push, vec![record.object_id]).detail(remote))?;
+    Ok(remote_id)
+}
+
+/// Fetch a note from `remote` and store it, merged with the local version
+/// if there is one. A note changed after it was signed is refused, as is
+/// one not signed by a trusted key when `signing.require_signed` is set.
 pub async fn pull_note(repo: &FukuraRepo, object_id: &str, remote: &str) -> Result<String> {
-    // Get authentication token from environment or config
-    let token = std::env::var("FUKURA_TOKEN")
-        .or_else(|_| std::env::var("FUKURA_API_TOKEN"))
-        .unwrap_or_default();
+    let mut remote_record = Hub::new(remote).fetch(object_id).await?;
+    let signing = repo.config()?.signing;
+    match TrustStore::load(&signing)?.check(&remote_record.note) {
+        Trust::Invalid => bail!(
+            "Note {} from {} was changed after it was signed",
+            object_id,
+            remote
+        ),
+        Trust::Verified { .. } => {}
+        _ if signing.require_signed => bail!(
+            "Note {} from {} isn't signed by a trusted key (signing.require_signed is set)",
+            object_id,
+            remote
+        ),
+        _ => {}
+    }
+    let pulled = |local_id: String| -> Result<String> {
+        repo.audit(
+            AuditEntry::new(AuditAction::Pull, vec![local_id.clone()])
+                .detail(format!("{} from {}", object_id, remote)),
+        )?;
+        Ok(local_id)
+    };
+    let Some(mut local) = repo.find_version(&remote_record)? else {
+        let stored = repo.store_note(remote_record.note)?;
+        // Already on the remote, so there is nothing to push
+        repo.prompt_state()
+            .mark_settled(std::slice::from_ref(&stored.object_id))?;
+        return pulled(stored.object_id);
+    };
+    if local.object_id == remote_record.object_id {
+        return Ok(local.object_id);
+    }
+    // Another version of a note we have: merge rather than keep both
+    for record in [&mut local, &mut remote_record] {
+        if record.note.crdt.is_none() {
+            record.note.crdt = Some(NoteCrdt::start(&record.note, &record.object_id));
+        }
+    }
+    let merged = merge_notes(&local.note, &remote_record.note);
+    pulled(repo.replace_note(&local.object_id, merged)?.object_id)
+}
 
-    let client = Client::new();
-    let url = format!("{}/v1/notes/{}", normalize_remote(remote), object_id);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::{Arc, Mutex};
 
-    let mut request = client.get(url);
+    /// Requests a mock hub saw, by path
+    type Seen = Arc<Mutex<Vec<(String, usize)>>>;
 
-    // Add authorization header if token is available
-    if !token.is_empty() {
-        request = request.header("Authorization", format!("Bearer {}", token));
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{}", addr)
     }
 
-    let response = request
-        .send()
-        .await
-        .with_context(|| "Failed to contact remote hub")?;
-    let status = response.status();
-    if !status.is_success() {
-        let body = response
-            .text()
+    #[tokio::test]
+    async fn test_batches_skip_what_the_hub_has() {
+        let seen: Seen = Arc::default();
+        let router = Router::new()
+            .route(
+                "/v1/notes/missing",
+                post(
+                    |State(seen): State<Seen>, Json(body): Json<serde_json::Value>| async move {
+                        let ids = body["object_ids"].as_array().unwrap().clone();
+                        seen.lock().unwrap().push(("missing".into(), ids.len()));
+                        let missing: Vec<_> = ids.into_iter().filter(|id| id != "id-0").collect();
+                        Json(serde_json::json!({ "missing": missing }))
+                    },
+                ),
+            )
+            .route(
+                "/v1/notes/batch",
+                post(
+                    |State(seen): State<Seen>, Json(body): Json<serde_json::Value>| async move {
+                        let notes = body["notes"].as_array().unwrap();
+                        seen.lock().unwrap().push(("batch".into(), notes.len()));
+                        let results: Vec<_> = notes
+                            .iter()
+                            .map(|note| {
+                                let id = note["object_id"].as_str().unwrap();
+                                if id == "id-3" {
+                                    serde_json::json!({ "object_id": id, "error": "too big" })
+                                } else {
+                                    serde_json::json!({ "object_id": id, "remote_id": format!("r-{}", id) })
+                                }
+                            })
+                            .collect();
+                        Json(serde_json::json!({ "results": results }))
+                    },
+                ),
+            )
+            .with_state(seen.clone());
+        let hub = Hub::new(&serve(router).await);
+
+        let records: Vec<_> = (0..6)
+            .map(|i| NoteBuilder::new(i.to_string(), "body").record(&format!("id-{}", i)))
+            .collect();
+        let (missing, synced) = hub.partition_synced(records).await.unwrap();
+        assert_eq!(synced.len(), 1);
+        assert_eq!(missing.len(), 5);
+
+        let config = SyncConfig {
+            batch_size: 2,
+            concurrency: 2,
+        };
+        let mut done = 0;
+        let report = push_notes(&hub, missing, &config, |_| done += 1)
             .await
-            .unwrap_or_else(|_| "<unavailable>".to_string());
-        anyhow::bail!("Remote returned {}: {}", status, body);
+            .unwrap();
+        assert_eq!(done, 5);
+        assert!(!report.interrupted);
+        let failed: Vec<_> = report
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+            .map(|outcome| outcome.object_id.as_str())
+            .collect();
+        assert_eq!(failed, ["id-3"]);
+        let mut batches: Vec<usize> = seen
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "batch")
+            .map(|(_, len)| *len)
+            .collect();
+        batches.sort();
+        assert_eq!(batches, [1, 2, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_single_pushes_and_retries_when_throttled() {
+        let seen: Seen = Arc::default();
+        let router = Router::new()
+            .route(
+                "/v1/notes",
+                post(
+                    |State(seen): State<Seen>, Json(record): Json<NoteRecord>| async move {
+                        let mut seen = seen.lock().unwrap();
+                        seen.push(("single".into(), 1));
+                        // Throttle every other request
+                        if seen.len() % 2 == 1 {
+                            return Err((
+                                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                                [(axum::http::header::RETRY_AFTER, "0")],
+                            ));
+                        }
+                        Ok(Json(record))
+                    },
+                ),
+            )
+            .with_state(seen.clone());
+        let hub = Hub::new(&serve(router).await);
+
+        let records: Vec<_> = (0..3)
+            .map(|i| NoteBuilder::new(i.to_string(), "body").record(&format!("id-{}", i)))
+            .collect();
+        let (missing, synced) = hub.partition_synced(records).await.unwrap();
+        assert_eq!((missing.len(), synced.len()), (3, 0));
+
+        let config = SyncConfig {
+            batch_size: 10,
+            concurrency: 1,
+        };
+        let report = push_notes(&hub, missing, &config, |_| {}).await.unwrap();
+        assert!(report.outcomes.iter().all(|outcome| outcome.result.is_ok()));
+        assert_eq!(seen.lock().unwrap().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_shares_are_published_and_revoked() {
+        let seen: Seen = Arc::default();
+        let router = Router::new()
+            .route(
+                "/v1/shares",
+                post(
+                    |State(seen): State<Seen>, Json(body): Json<serde_json::Value>| async move {
+                        assert_eq!(body["note"]["object_id"], "id-0");
+                        assert!(body["expires_at"].is_string());
+                        seen.lock().unwrap().push(("share".into(), 1));
+                        Json(serde_json::json!({
+                            "id": "s1",
+                            "url": "https://hub.example.com/s/s1",
+                            "expires_at": body["expires_at"],
+                        }))
+                    },
+                ),
+            )
+            .route(
+                "/v1/shares/{id}",
+                axum::routing::delete(
+                    |State(seen): State<Seen>,
+                     axum::extract::Path(id): axum::extract::Path<String>| async move {
+                        seen.lock().unwrap().push((id.clone(), 1));
+                        if id == "s1" {
+                            axum::http::StatusCode::NO_CONTENT
+                        } else {
+                            axum::http::StatusCode::NOT_FOUND
+                        }
+                    },
+                ),
+            )
+            .with_state(seen.clone());
+        let hub = Hub::new(&serve(router).await);
+
+        let expires_at = Utc::now() + chrono::Duration::days(1);
+        let shared = hub
+            .share(
+                &NoteBuilder::new("0", "body").record("id-0"),
+                Some(expires_at),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shared.id, "s1");
+        assert_eq!(shared.url, "https://hub.example.com/s/s1");
+        assert!(shared.expires_at.is_some());
+
+        hub.revoke_share("s1").await.unwrap();
+        // Already gone counts as revoked
+        hub.revoke_share("s2").await.unwrap();
+        assert_eq!(seen.lock().unwrap().len(), 3);
     }
-    let remote_record: NoteRecord = response
-        .json()
-        .await
-        .with_context(|| "Failed to decode hub response")?;
-    let local = repo.store_note(remote_record.note)?;
-    Ok(local.object_id)
 }
@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::config::ThinConfig;
+use crate::issues::block_on;
+use crate::object_store::{FileObjectStore, ObjectStore};
+use crate::pack::{LooseUsage, PackReport};
+use crate::sync::Hub;
+
+/// Ids of the notes a thin clone knows from the remote, one per line
+const REMOTE_IDS_FILE: &str = "remote-ids";
+
+/// Fetched objects as `<id> <bytes>`, least recently used first
+const CACHE_FILE: &str = "cache";
+
+/// Objects of a thin clone: what was written or fetched here lives in the
+/// local store, everything else is fetched from the hub on first read and
+/// kept until fetched objects exceed `[thin] cache_limit_mb`. Objects written
+/// locally are never evicted.
+#[derive(Debug)]
+pub struct ThinObjectStore {
+    local: FileObjectStore,
+    remote: String,
+    cache_limit: u64,
+    /// `.fukura/thin`
+    dir: PathBuf,
+    remote_ids: OnceCell<BTreeSet<String>>,
+    /// Serializes updates of the cache file within the process
+    cache: Mutex<()>,
+}
+
+impl ThinObjectStore {
+    pub fn new(local: FileObjectStore, config: &ThinConfig, dir: PathBuf) -> Self {
+        Self {
+            local,
+            remote: config.remote.clone(),
+            cache_limit: config.cache_limit_bytes(),
+            dir,
+            remote_ids: OnceCell::new(),
+            cache: Mutex::new(()),
+        }
+    }
+
+    /// Record the notes the remote has, for id lookups before they are
+    /// fetched
+    pub fn write_remote_ids<'a>(
+        dir: &std::path::Path,
+        ids: impl IntoIterator<Item = &'a str>,
+    ) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut contents = String::new();
+        for id in ids {
+            contents.push_str(id);
+            contents.push('\n');
+        }
+        write_atomic(&dir.join(REMOTE_IDS_FILE), &contents)
+    }
+
+    fn remote_ids(&self) -> Result<&BTreeSet<String>> {
+        self.remote_ids.get_or_try_init(|| {
+            let path = self.dir.join(REMOTE_IDS_FILE);
+            if !path.exists() {
+                return Ok(BTreeSet::new());
+            }
+            Ok(fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect())
+        })
+    }
+
+    /// Fetch an object from the hub. A fresh client each time, as `block_on`
+    /// may run each request on a runtime of its own.
+    fn fetch(&self, object_id: &str) -> Result<Option<Vec<u8>>> {
+        let fetched = block_on(Hub::new(&self.remote).fetch_object(object_id))?
+            .with_context(|| format!("Failed to fetch {} from {}", object_id, self.remote))?;
+        let Some(bytes) = fetched else {
+            return Ok(None);
+        };
+        verify(object_id, &bytes)?;
+        Ok(Some(bytes))
+    }
+
+    fn read_cache(&self) -> Result<Vec<(String, u64)>> {
+        let path = self.dir.join(CACHE_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| {
+                let (id, size) = line.split_once(' ')?;
+                Some((id.to_string(), size.trim().parse().ok()?))
+            })
+            .collect())
+    }
+
+    fn write_cache(&self, entries: &[(String, u64)]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut contents = String::new();
+        for (id, size) in entries {
+            contents.push_str(&format!("{} {}\n", id, size));
+        }
+        write_atomic(&self.dir.join(CACHE_FILE), &contents)
+    }
+
+    /// Mark a fetched object as just used, adding it when `size` is given,
+    /// then evict the least recently used ones past the limit
+    fn touch(&self, object_id: &str, size: Option<u64>) -> Result<()> {
+        let _guard = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.read_cache()?;
+        let position = entries.iter().position(|(id, _)| id == object_id);
+        let entry = match (position, size) {
+            (Some(position), _) => entries.remove(position),
+            (None, Some(size)) => (object_id.to_string(), size),
+            // Written locally, not fetched
+            (None, None) => return Ok(()),
+        };
+        entries.push(entry);
+
+        let mut total: u64 = entries.iter().map(|(_, size)| size).sum();
+        // The object being read stays, even when it alone is over the limit
+        while self.cache_limit > 0 && total > self.cache_limit && entries.len() > 1 {
+            let (id, size) = entries.remove(0);
+            self.local.remove(&id)?;
+            total -= size;
+        }
+        self.write_cache(&entries)
+    }
+}
+
+impl ObjectStore for ThinObjectStore {
+    fn contains(&self, object_id: &str) -> Result<bool> {
+        Ok(self.local.contains(object_id)? || self.remote_ids()?.contains(object_id))
+    }
+
+    fn read(&self, object_id: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(bytes) = self.local.read(object_id)? {
+            self.touch(object_id, None)?;
+            return Ok(Some(bytes));
+        }
+        let Some(bytes) = self.fetch(object_id)? else {
+            return Ok(None);
+        };
+        self.local.write(object_id, &bytes)?;
+        self.touch(object_id, Some(bytes.len() as u64))?;
+        Ok(Some(bytes))
+    }
+
+    fn write(&self, object_id: &str, bytes: &[u8]) -> Result<()> {
+        self.local.write(object_id, bytes)
+    }
+
+    fn remove(&self, object_id: &str) -> Result<bool> {
+        self.local.remove(object_id)
+    }
+
+    fn ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut ids: BTreeSet<String> = self.local.ids_with_prefix(prefix)?.into_iter().collect();
+        ids.extend(
+            self.remote_ids()?
+                .range(prefix.to_string()..)
+                .take_while(|id| id.starts_with(prefix))
+                .cloned(),
+        );
+        Ok(ids.into_iter().collect())
+    }
+
+    fn pack(&self, prune: bool) -> Result<PackReport> {
+        self.local.pack(prune)
+    }
+
+    fn loose_usage(&self) -> Result<LooseUsage> {
+        self.local.loose_usage()
+    }
+}
+
+/// Check fetched bytes hash to the id they were asked for
+fn verify(object_id: &str, bytes: &[u8]) -> Result<()> {
+    let mut decoded = Vec::new();
+    ZlibDecoder::new(bytes)
+        .read_to_end(&mut decoded)
+        .with_context(|| format!("Object {} from the remote is corrupt", object_id))?;
+    let digest = hex::encode(Sha256::digest(&decoded));
+    if digest != object_id {
+        bail!(
+            "Object {} from the remote hashes to {}; refusing it",
+            object_id,
+            digest
+        );
+    }
+    Ok(())
+}
+
+fn write_atomic(path: &std::path::Path, contents: &str) -> Result<()> {
+    let dir = path.parent().context("Path has no directory")?;
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(contents.as_bytes())?;
+    temp.persist(path)?;
+    Ok(())
+}
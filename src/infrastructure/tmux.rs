@@ -0,0 +1,137 @@
+use anyhow::{bail, Result};
+use std::process::Command;
+
+use crate::ingest::is_error_line;
+
+/// Lines kept before the last error in an excerpt, e.g. the command that
+/// failed and its first lines of output
+const LINES_BEFORE_ERROR: usize = 10;
+
+/// Whether this process runs inside a tmux client
+pub fn inside() -> bool {
+    std::env::var_os("TMUX").is_some_and(|value| !value.is_empty())
+}
+
+/// The `tmux` CLI, for reading the scrollback of a pane
+pub struct Tmux {
+    program: String,
+}
+
+impl Default for Tmux {
+    fn default() -> Self {
+        Self {
+            program: "tmux".to_string(),
+        }
+    }
+}
+
+impl Tmux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text of `pane` (default: the current one) with up to `history` lines
+    /// of scrollback above the visible part. Wrapped lines are joined.
+    pub fn capture_pane(&self, pane: Option<&str>, history: usize) -> Result<String> {
+        let start = format!("-{}", history);
+        let mut args = vec!["capture-pane", "-p", "-J", "-S", start.as_str()];
+        if let Some(pane) = pane {
+            args.extend(["-t", pane]);
+        }
+        self.run(&args)
+    }
+
+    /// `session:window.pane` of `pane` (default: the current one)
+    pub fn pane_name(&self, pane: Option<&str>) -> Result<String> {
+        let mut args = vec!["display-message", "-p"];
+        if let Some(pane) = pane {
+            args.extend(["-t", pane]);
+        }
+        args.push("#S:#I.#P");
+        Ok(self.run(&args)?.trim().to_string())
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let output = match Command::new(&self.program).args(args).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                bail!("tmux is not installed")
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !output.status.success() {
+            bail!(
+                "tmux {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// The part of a scrollback worth keeping in a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excerpt {
+    /// The last line that reports an error, if any
+    pub error: Option<String>,
+    pub lines: Vec<String>,
+}
+
+/// The lines around the last error of `scrollback`, or its last `max_lines`
+/// lines when nothing looks like an error. Trailing blank lines (the empty
+/// part of the pane below the prompt) are dropped.
+pub fn excerpt(scrollback: &str, max_lines: usize) -> Excerpt {
+    let mut lines: Vec<&str> = scrollback.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let last_error = lines.iter().rposition(|line| is_error_line(line));
+    let start = match last_error {
+        Some(index) => index
+            .saturating_sub(LINES_BEFORE_ERROR)
+            .max(lines.len().saturating_sub(max_lines)),
+        None => lines.len().saturating_sub(max_lines),
+    };
+    Excerpt {
+        error: last_error.map(|index| lines[index].trim().to_string()),
+        lines: lines[start..].iter().map(|line| line.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_keeps_the_last_error_and_what_follows() {
+        let mut scrollback: Vec<String> = (0..30).map(|i| format!("line {}", i)).collect();
+        scrollback.push("$ psql -h db".to_string());
+        scrollback.push("psql: error: connection refused".to_string());
+        scrollback.push("db=> ".to_string());
+        scrollback.push(String::new());
+        scrollback.push(String::new());
+
+        let excerpt = excerpt(&scrollback.join("\n"), 40);
+        assert_eq!(
+            excerpt.error.as_deref(),
+            Some("psql: error: connection refused")
+        );
+        assert_eq!(excerpt.lines.len(), LINES_BEFORE_ERROR + 2);
+        assert_eq!(excerpt.lines.last().unwrap(), "db=>");
+
+        // The cap wins over the lines kept before the error
+        let capped = super::excerpt(&scrollback.join("\n"), 2);
+        assert_eq!(
+            capped.lines,
+            vec!["psql: error: connection refused", "db=>"]
+        );
+    }
+
+    #[test]
+    fn test_excerpt_without_errors_is_the_tail() {
+        let excerpt = excerpt("one\ntwo\nthree\n", 2);
+        assert_eq!(excerpt.error, None);
+        assert_eq!(excerpt.lines, vec!["two", "three"]);
+    }
+}
@@ -0,0 +1,346 @@
+//! WASM modules that process new notes: tag them, summarize them, mask
+//! what the built-in rules miss. Unlike the executables in `.fukura/hooks/`
+//! they run in-process and sandboxed, so one module works on every
+//! platform. Needs the `wasm` feature.
+//!
+//! # Guest API, version 1
+//!
+//! A module exports:
+//!
+//! - `memory`
+//! - `fukura_api_version() -> i32`, returning [`GUEST_API_VERSION`]
+//! - `fukura_alloc(len: i32) -> i32`, returning a buffer of `len` bytes
+//! - `fukura_process(ptr: i32, len: i32) -> i64`
+//!
+//! `fukura_process` gets a buffer from `fukura_alloc` holding the JSON
+//! object `{"api_version": 1, "note": <note>, "settings": <settings>}`,
+//! where `settings` is the plugin's table from the config. It returns the
+//! changed note as JSON, packed as `ptr << 32 | len`, or `0` to leave the
+//! note as it was. It may import `fukura.log(ptr: i32, len: i32)` to log a
+//! UTF-8 message. See `examples/wasm-plugin` for a module in Rust.
+//!
+//! A module that traps, runs out of fuel or returns something that isn't a
+//! note is skipped with a warning; the note is stored without its changes.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::WasmPluginConfig;
+use crate::models::Note;
+use crate::note_service::NoteHook;
+
+/// Version of the guest API modules are written against
+pub const GUEST_API_VERSION: i32 = 1;
+
+/// Instructions, roughly, one note may take
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+const FUEL: u64 = 500_000_000;
+
+/// Memory one module instance may grow to
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+const MAX_MEMORY: usize = 64 << 20;
+
+/// What `fukura_process` is handed
+#[derive(Serialize)]
+struct GuestInput<'a> {
+    api_version: i32,
+    note: &'a Note,
+    settings: &'a toml::Table,
+}
+
+/// A loaded `[[wasm_plugins]]` module
+pub struct WasmProcessor {
+    path: PathBuf,
+    settings: toml::Table,
+    #[cfg(feature = "wasm")]
+    module: wasmtime::Module,
+}
+
+impl WasmProcessor {
+    /// Compile the module of `config` and check it speaks this guest API
+    pub fn load(root: &Path, config: &WasmPluginConfig) -> Result<Self> {
+        let path = root.join(&config.path);
+        #[cfg(feature = "wasm")]
+        {
+            let processor = Self {
+                module: runtime::compile(&path)?,
+                path,
+                settings: config.settings.clone(),
+            };
+            processor.instantiate()?;
+            Ok(processor)
+        }
+        #[cfg(not(feature = "wasm"))]
+        anyhow::bail!(
+            "Can't load {}: this fuku was built without WASM plugin support (the `wasm` feature)",
+            path.display()
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the module over `note`, returning the note it changed it into,
+    /// or `None` when it left it alone
+    pub fn process(&self, note: &Note) -> Result<Option<Note>> {
+        let input = serde_json::to_vec(&GuestInput {
+            api_version: GUEST_API_VERSION,
+            note,
+            settings: &self.settings,
+        })?;
+        #[cfg(feature = "wasm")]
+        {
+            let mut guest = self.instantiate()?;
+            let Some(output) = guest.process(&input)? else {
+                return Ok(None);
+            };
+            serde_json::from_slice(&output).map(Some).map_err(|err| {
+                anyhow::anyhow!(
+                    "{} returned something that isn't a note: {}",
+                    self.path.display(),
+                    err
+                )
+            })
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = input;
+            Ok(None)
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn instantiate(&self) -> Result<runtime::Guest> {
+        runtime::Guest::new(&self.module, &self.path)
+    }
+}
+
+impl NoteHook for WasmProcessor {
+    fn apply(&self, note: &mut Note) {
+        match self.process(note) {
+            Ok(Some(processed)) => *note = processed,
+            Ok(None) => {}
+            Err(err) => tracing::warn!(
+                plugin = %self.path.display(),
+                error = %err,
+                "WASM plugin failed; note stored without its changes"
+            ),
+        }
+    }
+}
+
+/// Load every `[[wasm_plugins]]` module; ones that fail to load are
+/// skipped with a warning
+pub fn load_all(root: &Path, configs: &[WasmPluginConfig]) -> Vec<WasmProcessor> {
+    configs
+        .iter()
+        .filter_map(|config| match WasmProcessor::load(root, config) {
+            Ok(processor) => Some(processor),
+            Err(err) => {
+                tracing::warn!(plugin = %config.path, error = %err, "WASM plugin not loaded");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "wasm")]
+mod runtime {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use anyhow::{bail, ensure, Context, Result};
+    use once_cell::sync::Lazy;
+    use wasmtime::{
+        Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+        StoreLimitsBuilder, TypedFunc,
+    };
+
+    use super::{FUEL, GUEST_API_VERSION, MAX_MEMORY};
+
+    static ENGINE: Lazy<Engine> = Lazy::new(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("valid wasmtime config")
+    });
+
+    /// Compiled modules by path and modification time, so the daemon
+    /// doesn't compile a module again for every note
+    static MODULES: Lazy<Mutex<HashMap<PathBuf, (SystemTime, Module)>>> =
+        Lazy::new(Default::default);
+
+    pub fn compile(path: &Path) -> Result<Module> {
+        let modified = path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut modules = MODULES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((at, module)) = modules.get(path) {
+            if *at == modified {
+                return Ok(module.clone());
+            }
+        }
+        let module = Module::from_file(&ENGINE, path)
+            .with_context(|| format!("Failed to compile {}", path.display()))?;
+        modules.insert(path.to_path_buf(), (modified, module.clone()));
+        Ok(module)
+    }
+
+    struct HostState {
+        limits: StoreLimits,
+        plugin: String,
+    }
+
+    /// One instance of a module, good for one note
+    pub struct Guest {
+        store: Store<HostState>,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        process: TypedFunc<(i32, i32), i64>,
+        plugin: String,
+    }
+
+    impl Guest {
+        pub fn new(module: &Module, path: &Path) -> Result<Self> {
+            let plugin = path.display().to_string();
+            let mut store = Store::new(
+                &ENGINE,
+                HostState {
+                    limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+                    plugin: plugin.clone(),
+                },
+            );
+            store.limiter(|state| &mut state.limits);
+            store.set_fuel(FUEL)?;
+
+            let mut linker = Linker::new(&ENGINE);
+            linker.func_wrap(
+                "fukura",
+                "log",
+                |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                        return;
+                    };
+                    let mut message = vec![0; usize::try_from(len).unwrap_or(0)];
+                    if memory
+                        .read(&caller, ptr as u32 as usize, &mut message)
+                        .is_ok()
+                    {
+                        tracing::info!(
+                            plugin = %caller.data().plugin,
+                            "{}",
+                            String::from_utf8_lossy(&message)
+                        );
+                    }
+                },
+            )?;
+            let instance: Instance = linker
+                .instantiate(&mut store, module)
+                .with_context(|| format!("Failed to start {}", plugin))?;
+
+            let export = |name: &str| format!("{} doesn't export `{}`", plugin, name);
+            let version = instance
+                .get_typed_func::<(), i32>(&mut store, "fukura_api_version")
+                .with_context(|| export("fukura_api_version"))?
+                .call(&mut store, ())?;
+            ensure!(
+                version == GUEST_API_VERSION,
+                "{} speaks guest API version {}, but fuku speaks {}",
+                plugin,
+                version,
+                GUEST_API_VERSION
+            );
+            Ok(Self {
+                memory: instance
+                    .get_memory(&mut store, "memory")
+                    .with_context(|| export("memory"))?,
+                alloc: instance
+                    .get_typed_func(&mut store, "fukura_alloc")
+                    .with_context(|| export("fukura_alloc"))?,
+                process: instance
+                    .get_typed_func(&mut store, "fukura_process")
+                    .with_context(|| export("fukura_process"))?,
+                store,
+                plugin,
+            })
+        }
+
+        /// Hand `input` to `fukura_process`, returning its output, if any
+        pub fn process(&mut self, input: &[u8]) -> Result<Option<Vec<u8>>> {
+            let len = i32::try_from(input.len()).context("Note too large for a WASM plugin")?;
+            let ptr = self.alloc.call(&mut self.store, len)?;
+            self.memory
+                .write(&mut self.store, ptr as u32 as usize, input)
+                .with_context(|| format!("{} returned a bad buffer", self.plugin))?;
+            let packed = self.process.call(&mut self.store, (ptr, len))? as u64;
+            let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+            if out_len == 0 {
+                return Ok(None);
+            }
+            if out_ptr + out_len > self.memory.data_size(&self.store) {
+                bail!("{} returned output outside its memory", self.plugin);
+            }
+            let mut output = vec![0; out_len];
+            self.memory.read(&self.store, out_ptr, &mut output)?;
+            Ok(Some(output))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wasm"))]
+mod tests {
+    use super::*;
+    use crate::note_service::NoteBuilder;
+    use tempfile::TempDir;
+
+    /// Guest that tags every note `wasm`: it answers a fixed note from a
+    /// data segment
+    const TAGGER: &str = r#"
+        (module
+          (import "fukura" "log" (func $log (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "tagging")
+          (data (i32.const 1024) "{\"title\":\"Proxy\",\"body\":\"Set HTTPS_PROXY\",\"tags\":[\"wasm\"],\"created_at\":\"2024-01-01T00:00:00Z\",\"updated_at\":\"2024-01-01T00:00:00Z\",\"author\":{\"name\":\"wasm\"}}")
+          (func (export "fukura_api_version") (result i32) (i32.const 1))
+          (func (export "fukura_alloc") (param i32) (result i32) (i32.const 4096))
+          (func (export "fukura_process") (param i32 i32) (result i64)
+            (call $log (i32.const 0) (i32.const 7))
+            (i64.or (i64.shl (i64.const 1024) (i64.const 32)) (i64.const 155))))
+    "#;
+
+    fn load(temp: &TempDir, name: &str, wat: &str) -> Result<WasmProcessor> {
+        std::fs::write(temp.path().join(name), wat).unwrap();
+        WasmProcessor::load(
+            temp.path(),
+            &WasmPluginConfig {
+                path: name.to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_processors_change_notes_through_the_guest_api() {
+        let temp = TempDir::new().unwrap();
+        let mut note = NoteBuilder::new("Proxy", "Set HTTPS_PROXY").build();
+        load(&temp, "tagger.wat", TAGGER).unwrap().apply(&mut note);
+        assert_eq!(note.tags, ["wasm"]);
+
+        // A trapping guest leaves the note alone
+        let trapping = TAGGER.replace("(call $log", "(unreachable) (call $log");
+        let mut note = NoteBuilder::new("Proxy", "Set HTTPS_PROXY").build();
+        load(&temp, "trapping.wat", &trapping)
+            .unwrap()
+            .apply(&mut note);
+        assert!(note.tags.is_empty());
+
+        let newer = TAGGER.replace("(i32.const 1))", "(i32.const 2))");
+        let err = load(&temp, "newer.wat", &newer).err().unwrap();
+        assert!(err.to_string().contains("guest API version 2"));
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::{NoteEvent, WebhookConfig};
+use crate::models::{Note, NoteRecord};
+
+/// Attempts per delivery before it is given up
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Body posted to a webhook
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    pub event: NoteEvent,
+    pub object_id: &'a str,
+    /// The note as stored, or as it was before a delete
+    pub note: &'a Note,
+    pub at: DateTime<Utc>,
+}
+
+impl WebhookConfig {
+    /// Whether this webhook wants `event` for `note`
+    pub fn wants(&self, event: NoteEvent, note: &Note) -> bool {
+        (self.events.is_empty() || self.events.contains(&event))
+            && self.privacy.as_ref().map_or(true, |p| *p == note.privacy)
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| note.tags.contains(tag)))
+    }
+}
+
+/// The `[[webhooks]]` of a repository, posted to as `fuku serve` creates,
+/// updates and deletes notes. Cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    hooks: Arc<Vec<WebhookConfig>>,
+    client: Client,
+}
+
+impl Webhooks {
+    pub fn new(hooks: Vec<WebhookConfig>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self {
+            hooks: Arc::new(hooks),
+            client,
+        }
+    }
+
+    /// Post `event` in the background, so the request that caused it
+    /// doesn't wait on receivers. Failed deliveries are logged.
+    pub fn fire(&self, event: NoteEvent, record: &NoteRecord) {
+        if !self
+            .hooks
+            .iter()
+            .any(|hook| hook.wants(event, &record.note))
+        {
+            return;
+        }
+        let webhooks = self.clone();
+        let record = record.clone();
+        tokio::spawn(async move { webhooks.deliver(event, &record).await });
+    }
+
+    /// Post `event` to every webhook that wants it, returning the number
+    /// delivered
+    pub async fn deliver(&self, event: NoteEvent, record: &NoteRecord) -> usize {
+        let payload = WebhookPayload {
+            event,
+            object_id: &record.object_id,
+            note: &record.note,
+            at: Utc::now(),
+        };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!(error = %err, "failed to encode webhook payload");
+                return 0;
+            }
+        };
+        let mut delivered = 0;
+        for hook in self
+            .hooks
+            .iter()
+            .filter(|hook| hook.wants(event, &record.note))
+        {
+            match self.post(hook, event, &body).await {
+                Ok(()) => delivered += 1,
+                Err(err) => tracing::warn!(url = %hook.url, error = %err, "webhook failed"),
+            }
+        }
+        delivered
+    }
+
+    async fn post(&self, hook: &WebhookConfig, event: NoteEvent, body: &[u8]) -> Result<()> {
+        let delivery = uuid::Uuid::new_v4().to_string();
+        let event_name = match event {
+            NoteEvent::Created => "created",
+            NoteEvent::Updated => "updated",
+            NoteEvent::Deleted => "deleted",
+        };
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .post(&hook.url)
+                .header("Content-Type", "application/json")
+                .header("X-Fukura-Event", event_name)
+                .header("X-Fukura-Delivery", &delivery)
+                .body(body.to_vec());
+            if let Some(secret) = &hook.secret {
+                request = request.header("X-Fukura-Signature", signature(secret, body));
+            }
+            let result = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                // The receiver refused it; sending again won't help
+                Ok(response) if response.status().is_client_error() => {
+                    bail!("{} answered {}", hook.url, response.status())
+                }
+                Ok(response) => Err(anyhow::anyhow!(
+                    "{} answered {}",
+                    hook.url,
+                    response.status()
+                )),
+                Err(err) => Err(err).with_context(|| format!("Failed to reach {}", hook.url)),
+            };
+            attempt += 1;
+            if attempt >= MAX_ATTEMPTS {
+                return result;
+            }
+            tokio::time::sleep(Duration::from_millis(500 << attempt)).await;
+        }
+    }
+}
+
+/// `X-Fukura-Signature` of `body`: `sha256=` and the hex HMAC-SHA256 under
+/// `secret`
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        hex::encode(hmac_sha256(secret.as_bytes(), body))
+    )
+}
+
+/// HMAC (RFC 2104) over SHA-256
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|k| k ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Privacy;
+    use crate::note_service::NoteBuilder;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_signature_matches_rfc_4231() {
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivers_signed_events_to_matching_hooks() {
+        type Seen = Arc<Mutex<Vec<(String, String, serde_json::Value)>>>;
+        let seen: Seen = Arc::default();
+        let router = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |axum::extract::State(seen): axum::extract::State<Seen>,
+                     headers: HeaderMap,
+                     body: axum::body::Bytes| async move {
+                        let header =
+                            |name: &str| headers[name].to_str().unwrap_or_default().to_string();
+                        assert_eq!(header("X-Fukura-Signature"), signature("s3cret", &body));
+                        let body = serde_json::from_slice(&body).unwrap();
+                        seen.lock().unwrap().push((
+                            header("X-Fukura-Event"),
+                            header("X-Fukura-Delivery"),
+                            body,
+                        ));
+                    },
+                ),
+            )
+            .with_state(seen.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let webhooks = Webhooks::new(vec![
+            WebhookConfig {
+                url: url.clone(),
+                secret: Some("s3cret".to_string()),
+                events: vec![NoteEvent::Created],
+                privacy: Some(Privacy::Public),
+                tags: vec!["runbook".to_string()],
+            },
+            WebhookConfig {
+                url: format!("{}-gone", url),
+                events: vec![NoteEvent::Deleted],
+                ..Default::default()
+            },
+        ]);
+        let runbook = NoteRecord {
+            object_id: "a".repeat(64),
+            note: NoteBuilder::new("Restart the queue", "kubectl rollout restart")
+                .tag("runbook")
+                .privacy(Privacy::Public)
+                .build(),
+        };
+        let private = NoteRecord {
+            object_id: "b".repeat(64),
+            note: NoteBuilder::new("Scratch", "notes").tag("runbook").build(),
+        };
+        assert_eq!(webhooks.deliver(NoteEvent::Created, &runbook).await, 1);
+        assert_eq!(webhooks.deliver(NoteEvent::Created, &private).await, 0);
+        assert_eq!(webhooks.deliver(NoteEvent::Updated, &runbook).await, 0);
+        // The receiver of deletes answers 404 and isn't retried
+        assert_eq!(webhooks.deliver(NoteEvent::Deleted, &runbook).await, 0);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        let (event, delivery, body) = &seen[0];
+        assert_eq!(event, "created");
+        assert!(!delivery.is_empty());
+        assert_eq!(body["event"], "created");
+        assert_eq!(body["object_id"], runbook.object_id);
+        assert_eq!(body["note"]["title"], "Restart the queue");
+    }
+}
@@ -1,61 +1,169 @@
 pub mod ui {
     pub mod browser;
     pub mod cli;
+    pub mod editor;
+    pub mod markdown;
+    pub mod output;
+    pub mod picker;
+    pub mod qr;
 }
 
 pub mod application {
     pub mod activity_monitor;
+    pub mod ci;
     pub mod config_cmd;
     pub mod daemon;
     pub mod daemon_service;
+    pub mod editor_server;
+    pub mod ingest;
+    pub mod issues;
+    pub mod status;
 }
 
 pub mod domain {
     pub mod activity;
     pub mod activity_storage;
+    pub mod crdt;
+    pub mod dedupe;
+    pub mod diff;
+    pub mod feedback;
+    pub mod merge;
     pub mod models;
+    pub mod note_service;
     pub mod pack;
+    pub mod patterns;
+    pub mod recording;
     pub mod redaction;
+    pub mod related;
+    pub mod report;
+    pub mod taxonomy;
+    pub mod timeline;
+    pub mod titles;
 }
 
 pub mod infrastructure {
+    pub mod ai;
+    pub mod analyzer;
+    pub mod asciicast;
+    pub mod async_repo;
+    pub mod audit;
     pub mod config;
     pub mod directory_monitor;
+    pub mod docker;
     pub mod file_watcher;
+    pub mod git;
     pub mod hooks;
     pub mod index;
+    pub mod integrations;
+    pub mod ipc;
+    pub mod journal;
+    pub mod kubernetes;
+    pub mod locks;
+    pub mod log_process;
+    pub mod logging;
+    pub mod mail;
+    pub mod meta_cache;
+    pub mod migrations;
     pub mod notification;
+    pub mod object_store;
+    pub mod paths;
+    pub mod plugins;
+    pub mod prompt_state;
+    pub mod registry;
     pub mod remote_search;
     pub mod repo;
+    pub mod share;
+    pub mod signing;
     pub mod sync;
+    pub mod thin_store;
+    pub mod tmux;
+    pub mod wasm_plugins;
+    pub mod webhooks;
 }
 
 pub mod shared {
+    pub mod fuzzy;
+    pub mod grep;
+    pub mod interrupt;
     pub mod performance;
     pub mod time_parser;
 }
 
 pub use application::activity_monitor;
+pub use application::ci;
 pub use application::config_cmd;
 pub use application::daemon;
 pub use application::daemon_service;
+pub use application::editor_server;
+pub use application::ingest;
+pub use application::issues;
+pub use application::status;
 pub use domain::activity;
 pub use domain::activity_storage;
+pub use domain::crdt;
+pub use domain::dedupe;
+pub use domain::diff;
+pub use domain::feedback;
+pub use domain::merge;
 pub use domain::models;
+pub use domain::note_service;
 pub use domain::pack;
+pub use domain::patterns;
+pub use domain::recording;
 pub use domain::redaction;
+pub use domain::related;
+pub use domain::report;
+pub use domain::taxonomy;
+pub use domain::timeline;
+pub use domain::titles;
+pub use infrastructure::ai;
+pub use infrastructure::analyzer;
+pub use infrastructure::asciicast;
+pub use infrastructure::async_repo;
+pub use infrastructure::audit;
 pub use infrastructure::config;
 pub use infrastructure::directory_monitor;
+pub use infrastructure::docker;
 pub use infrastructure::file_watcher;
+pub use infrastructure::git;
 pub use infrastructure::hooks;
 pub use infrastructure::index;
+pub use infrastructure::integrations;
+pub use infrastructure::ipc;
+pub use infrastructure::journal;
+pub use infrastructure::kubernetes;
+pub use infrastructure::locks;
+pub use infrastructure::log_process;
+pub use infrastructure::logging;
+pub use infrastructure::mail;
+pub use infrastructure::meta_cache;
+pub use infrastructure::migrations;
 pub use infrastructure::notification;
+pub use infrastructure::object_store;
+pub use infrastructure::paths;
+pub use infrastructure::plugins;
+pub use infrastructure::prompt_state;
+pub use infrastructure::registry;
 pub use infrastructure::remote_search;
 pub use infrastructure::repo;
+pub use infrastructure::share;
+pub use infrastructure::signing;
 pub use infrastructure::sync;
+pub use infrastructure::thin_store;
+pub use infrastructure::tmux;
+pub use infrastructure::wasm_plugins;
+pub use infrastructure::webhooks;
+pub use shared::fuzzy;
+pub use shared::grep;
+pub use shared::interrupt;
 pub use shared::performance;
 pub use shared::time_parser;
 pub use ui::browser;
 pub use ui::cli;
+pub use ui::editor;
+pub use ui::markdown;
+pub use ui::output;
+pub use ui::picker;
+pub use ui::qr;
 
 pub use ui::cli::run;
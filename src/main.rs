@@ -18,7 +18,12 @@ async fn main() {
     }
 
     if let Err(error) = fukura::run().await {
-        eprintln!("{} {}", "error:".red().bold(), error);
+        let message = error.to_string();
+        eprintln!(
+            "{} {}",
+            "error:".red().bold(),
+            fukura::output::plain_text(&message)
+        );
         process::exit(1);
     }
 }
@@ -0,0 +1,109 @@
+/// fzf-style subsequence matching used by the interactive picker.
+///
+/// Returns `None` when `pattern` is not a subsequence of `candidate`, otherwise
+/// a score where higher is better. Matching is case-insensitive unless the
+/// pattern contains an uppercase character (smart case). Whitespace separates
+/// terms that must all match.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let mut total = 0;
+    for term in pattern.split_whitespace() {
+        total += score_term(term, candidate)?;
+    }
+    Some(total)
+}
+
+/// Filter and rank candidates, best match first. Ties keep the input order.
+pub fn fuzzy_filter<'a, T, F>(pattern: &str, items: &'a [T], text: F) -> Vec<(usize, &'a T)>
+where
+    F: Fn(&T) -> String,
+{
+    let mut scored: Vec<(i64, usize, &T)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| fuzzy_score(pattern, &text(item)).map(|s| (s, idx, item)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored
+        .into_iter()
+        .map(|(_, idx, item)| (idx, item))
+        .collect()
+}
+
+fn score_term(term: &str, candidate: &str) -> Option<i64> {
+    let case_sensitive = term.chars().any(|c| c.is_uppercase());
+    let normalize = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut position = 0usize;
+    let mut previous_match: Option<usize> = None;
+
+    for wanted in term.chars().map(normalize) {
+        let found = (position..chars.len()).find(|&i| normalize(chars[i]) == wanted)?;
+
+        score += 16;
+        if previous_match == Some(found.wrapping_sub(1)) {
+            // Consecutive characters
+            score += 24;
+        }
+        if found == 0 || !chars[found - 1].is_alphanumeric() {
+            // Start of a word
+            score += 20;
+        }
+        if let Some(prev) = previous_match {
+            // Penalize gaps between matched characters
+            score -= (found - prev - 1).min(16) as i64;
+        } else {
+            score -= found.min(16) as i64;
+        }
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_matching() {
+        assert!(fuzzy_score("dkr", "docker build failed").is_some());
+        assert!(fuzzy_score("xyz", "docker build failed").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(fuzzy_score("docker", "Docker").is_some());
+        assert!(fuzzy_score("Docker", "docker").is_none());
+    }
+
+    #[test]
+    fn test_multiple_terms_must_all_match() {
+        assert!(fuzzy_score("proxy timeout", "Proxy deploy timeout").is_some());
+        assert!(fuzzy_score("proxy missing", "Proxy deploy timeout").is_none());
+    }
+
+    #[test]
+    fn test_ranking_prefers_contiguous_word_starts() {
+        let items = vec![
+            "npm install cache error".to_string(),
+            "cargo build error".to_string(),
+            "cannot open bundle".to_string(),
+        ];
+        let ranked = fuzzy_filter("cargo", &items, |s| s.clone());
+        assert_eq!(ranked[0].1, "cargo build error");
+
+        let ranked = fuzzy_filter("cb", &items, |s| s.clone());
+        assert_eq!(ranked[0].1, "cargo build error");
+    }
+}
@@ -0,0 +1,100 @@
+use regex::Regex;
+
+/// A line selected by [`grep_lines`], either a match or surrounding context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepLine {
+    /// 1-based line number
+    pub number: usize,
+    pub text: String,
+    pub is_match: bool,
+    /// True when this line starts a new group that isn't adjacent to the previous one
+    pub group_start: bool,
+}
+
+/// Select matching lines plus `before`/`after` context lines, merging overlapping windows
+pub fn grep_lines(body: &str, pattern: &Regex, before: usize, after: usize) -> Vec<GrepLine> {
+    let lines: Vec<&str> = body.lines().collect();
+    let matched: Vec<bool> = lines.iter().map(|line| pattern.is_match(line)).collect();
+
+    let mut selected = vec![false; lines.len()];
+    for (idx, _) in matched.iter().enumerate().filter(|(_, m)| **m) {
+        let start = idx.saturating_sub(before);
+        let end = (idx + after).min(lines.len().saturating_sub(1));
+        for flag in &mut selected[start..=end] {
+            *flag = true;
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut last: Option<usize> = None;
+    for (idx, line) in lines.iter().enumerate() {
+        if !selected[idx] {
+            continue;
+        }
+        result.push(GrepLine {
+            number: idx + 1,
+            text: line.to_string(),
+            is_match: matched[idx],
+            group_start: last.is_some_and(|prev| prev + 1 != idx),
+        });
+        last = Some(idx);
+    }
+    result
+}
+
+/// Byte ranges of every match in a line, for highlighting
+pub fn match_ranges(line: &str, pattern: &Regex) -> Vec<(usize, usize)> {
+    pattern
+        .find_iter(line)
+        .filter(|m| !m.is_empty())
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "one\ntwo\nE0425 unresolved\nfour\nfive\nsix\nseven\nE0425 again\nnine";
+
+    #[test]
+    fn test_matches_without_context() {
+        let re = Regex::new("E0425").unwrap();
+        let lines = grep_lines(BODY, &re, 0, 0);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].number, 3);
+        assert!(lines[0].is_match);
+        assert!(lines[1].group_start);
+    }
+
+    #[test]
+    fn test_context_windows_merge() {
+        let re = Regex::new("E0425").unwrap();
+        let lines = grep_lines(BODY, &re, 2, 2);
+        let numbers: Vec<usize> = lines.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert!(lines.iter().all(|l| !l.group_start));
+
+        let lines = grep_lines(BODY, &re, 1, 1);
+        let numbers: Vec<usize> = lines.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![2, 3, 4, 7, 8, 9]);
+        assert!(lines[3].group_start);
+        assert!(!lines[3].is_match);
+    }
+
+    #[test]
+    fn test_no_match() {
+        let re = Regex::new("panic").unwrap();
+        assert!(grep_lines(BODY, &re, 3, 3).is_empty());
+        assert!(grep_lines("", &re, 3, 3).is_empty());
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let re = Regex::new(r"\d+").unwrap();
+        assert_eq!(
+            match_ranges("at line 42 col 7", &re),
+            vec![(8, 10), (15, 16)]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Ctrl-C handling for long operations (import, sync, gc, index rebuilds).
+//!
+//! While an [`InterruptGuard`] is alive, the first Ctrl-C only asks the
+//! operation to stop: it checks [`requested`] between objects, finishes the
+//! current one, flushes what it has and returns. A second Ctrl-C, or one
+//! while nothing is watching, exits right away like the default handler.
+//! Only the CLI watches; the daemon and library code just check
+//! [`requested`], which stays false for them.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Once;
+
+/// Exit status of a process killed by SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static WATCHERS: AtomicUsize = AtomicUsize::new(0);
+static INSTALL: Once = Once::new();
+
+/// Keeps Ctrl-C from killing the process until dropped
+#[derive(Debug)]
+pub struct InterruptGuard(());
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        if WATCHERS.fetch_sub(1, Ordering::SeqCst) == 1 {
+            REQUESTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Start watching for Ctrl-C. The signal is handled on its own thread, so
+/// it is seen while the operation blocks every runtime worker.
+pub fn watch() -> InterruptGuard {
+    if WATCHERS.fetch_add(1, Ordering::SeqCst) == 0 {
+        REQUESTED.store(false, Ordering::SeqCst);
+    }
+    INSTALL.call_once(|| {
+        let spawned = std::thread::Builder::new()
+            .name("fuku-interrupt".to_string())
+            .spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?;
+                runtime.block_on(async {
+                    while tokio::signal::ctrl_c().await.is_ok() {
+                        if !request() {
+                            std::process::exit(INTERRUPTED_EXIT_CODE);
+                        }
+                        eprintln!(
+                            "\n{}",
+                            crate::output::plain_text(
+                                "Interrupted: finishing the current item… (Ctrl-C again to quit now)"
+                            )
+                        );
+                    }
+                });
+                std::io::Result::Ok(())
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Ctrl-C can't be handled gracefully: {}", e);
+        }
+    });
+    InterruptGuard(())
+}
+
+/// Ask the watched operation to stop. `false` when nothing is watching or
+/// a stop was already requested, i.e. when the process should exit instead.
+fn request() -> bool {
+    WATCHERS.load(Ordering::SeqCst) > 0 && !REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+/// Whether the operation should stop at its next safe point
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
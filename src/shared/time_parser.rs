@@ -1,49 +1,303 @@
 use anyhow::{bail, Result};
-use chrono::{Duration, Utc};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
 use regex::Regex;
 use std::time::SystemTime;
 
-/// Parse time expressions like "3m ago", "2h ago", "1h 30m ago"
+/// Shown when nothing understands a time
+const TIME_FORMATS: &str = "Use a date (2024-05-01, 2024-05-01 14:00, RFC 3339), a day \
+     ('today 9am', 'yesterday', 'monday'), or a span back from now ('7d', '6mo', '3 hours ago').";
+
+/// Parse time expressions like "3m ago", "2h ago", "1h 30m ago", "7d", "2w"
 pub fn parse_time_ago(input: &str) -> Result<SystemTime> {
     let input = input.trim().to_lowercase();
 
     // Remove "ago" suffix if present
     let time_part = input.strip_suffix(" ago").unwrap_or(&input);
+    let total_duration = parse_duration(time_part)?;
 
-    // Regex patterns for different time formats
-    let re = Regex::new(r"^(?:(\d+)h)?\s*(?:(\d+)m)?\s*(?:(\d+)s)?$")?;
+    let target_time = Utc::now() - total_duration;
 
-    if let Some(captures) = re.captures(time_part) {
-        let hours = captures
-            .get(1)
-            .and_then(|m| m.as_str().parse::<u32>().ok())
-            .unwrap_or(0);
-        let minutes = captures
-            .get(2)
-            .and_then(|m| m.as_str().parse::<u32>().ok())
-            .unwrap_or(0);
-        let seconds = captures
-            .get(3)
+    // Convert to SystemTime
+    let system_time =
+        SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(target_time.timestamp() as u64);
+
+    Ok(system_time)
+}
+
+/// Parse a span like "90d", "2w", "6mo" or "1h 30m". A month is 30 days
+/// and a year 365.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim().to_lowercase();
+
+    // Regex patterns for different time formats
+    let re = Regex::new(
+        r"^(?:(\d+)y)?\s*(?:(\d+)mo)?\s*(?:(\d+)w)?\s*(?:(\d+)d)?\s*(?:(\d+)h)?\s*(?:(\d+)m)?\s*(?:(\d+)s)?$",
+    )?;
+
+    let Some(captures) = re.captures(&input) else {
+        bail!("Invalid time format. Use formats like '3m ago', '2h ago', '1h 30m ago', '7d', etc.");
+    };
+    let unit = |group: usize| {
+        captures
+            .get(group)
             .and_then(|m| m.as_str().parse::<u32>().ok())
-            .unwrap_or(0);
+            .unwrap_or(0) as i64
+    };
+    let (years, months, weeks, days, hours, minutes, seconds) = (
+        unit(1),
+        unit(2),
+        unit(3),
+        unit(4),
+        unit(5),
+        unit(6),
+        unit(7),
+    );
+
+    if [years, months, weeks, days, hours, minutes, seconds]
+        .iter()
+        .all(|n| *n == 0)
+    {
+        bail!("Invalid time format. Use formats like '3m', '2h', '1h 30m', etc.");
+    }
+
+    Ok(Duration::days(365 * years + 30 * months)
+        + Duration::weeks(weeks)
+        + Duration::days(days)
+        + Duration::hours(hours)
+        + Duration::minutes(minutes)
+        + Duration::seconds(seconds))
+}
+
+/// Parse a day ("today", "yesterday", "monday", "YYYY-MM-DD") into
+/// local-time `[start, end)` bounds
+pub fn parse_day(input: &str) -> Result<(SystemTime, SystemTime)> {
+    let lower = normalize(input);
+    let words: Vec<&str> = lower.split(' ').collect();
+    let Some((date, _)) =
+        parse_date(&words, Local::now().date_naive()).filter(|(_, used)| *used == words.len())
+    else {
+        bail!("Invalid day. Use 'today', 'yesterday', a weekday or YYYY-MM-DD.");
+    };
+    Ok((
+        local_midnight(date)?.into(),
+        local_midnight(date + Duration::days(1))?.into(),
+    ))
+}
+
+/// Parse a point in time: RFC 3339 or ISO 8601 ("2024-05-01T14:00"), a
+/// local date and time ("2024-05-01 14:00", "today 9am", "yesterday",
+/// "last friday 17:30"; a day alone means its midnight), a time today
+/// ("9am", "14:00"), "now", or a span back from now ("7d", "2w ago",
+/// "3 hours ago")
+pub fn parse_instant(input: &str) -> Result<DateTime<Utc>> {
+    parse_instant_at(input, Local::now())
+}
+
+/// [`parse_instant`] relative to `now`
+pub fn parse_instant_at(input: &str, now: DateTime<Local>) -> Result<DateTime<Utc>> {
+    Ok(parse_point(input, now)?.0)
+}
+
+/// Parse a span of time into `[start, end)`: two points joined by `..` or
+/// `to` ("2024-05-01..2024-05-03", "monday 9am to today"), "this week",
+/// "last week", "this month" or "last month". A day alone covers the whole
+/// day, and a bare point runs until now.
+pub fn parse_range(input: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    parse_range_at(input, Local::now())
+}
 
-        if hours == 0 && minutes == 0 && seconds == 0 {
-            bail!("Invalid time format. Use formats like '3m', '2h', '1h 30m', etc.");
+/// [`parse_range`] relative to `now`
+pub fn parse_range_at(input: &str, now: DateTime<Local>) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let lower = input
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let lower = lower.strip_prefix("from ").unwrap_or(&lower);
+    let today = now.date_naive();
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let first_of_month = today.with_day(1).unwrap_or(today);
+    let named = match lower {
+        "this week" => Some((monday, today + Duration::days(1))),
+        "last week" => Some((monday - Duration::weeks(1), monday)),
+        "this month" => Some((first_of_month, today + Duration::days(1))),
+        "last month" => {
+            let previous = (first_of_month - Duration::days(1)).with_day(1);
+            previous.map(|start| (start, first_of_month))
         }
+        _ => None,
+    };
+    if let Some((start, end)) = named {
+        return Ok((
+            local_midnight(start)?.with_timezone(&Utc),
+            local_midnight(end)?.with_timezone(&Utc),
+        ));
+    }
+
+    let (start, end) = match lower.split_once("..").or_else(|| lower.split_once(" to ")) {
+        Some((from, to)) => {
+            let (start, _) = parse_point(from, now)?;
+            let (end, day) = parse_point(to, now)?;
+            // "..friday" includes friday
+            let end = day.map_or(Ok(end), |date| {
+                local_midnight(date + Duration::days(1)).map(|at| at.with_timezone(&Utc))
+            })?;
+            (start, end)
+        }
+        None => match parse_point(lower, now)? {
+            (start, Some(date)) => (
+                start,
+                local_midnight(date + Duration::days(1))?.with_timezone(&Utc),
+            ),
+            (start, None) => (start, now.with_timezone(&Utc)),
+        },
+    };
+    if end <= start {
+        bail!("Invalid range '{}': it ends before it starts", input.trim());
+    }
+    Ok((start, end))
+}
+
+/// A point in time, and its date when the input named a day without a time
+fn parse_point(input: &str, now: DateTime<Local>) -> Result<(DateTime<Utc>, Option<NaiveDate>)> {
+    let trimmed = input.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(&trimmed.to_uppercase()) {
+        return Ok((at.with_timezone(&Utc), None));
+    }
+    let lower = normalize(trimmed);
+    if lower == "now" {
+        return Ok((now.with_timezone(&Utc), None));
+    }
+    if let Some((at, day_only)) = parse_local(&lower, now.date_naive()) {
+        let Some(local) = Local.from_local_datetime(&at).earliest() else {
+            bail!("'{}' doesn't exist in the local time zone", trimmed);
+        };
+        return Ok((local.with_timezone(&Utc), day_only.then_some(at.date())));
+    }
+    match parse_span(&lower) {
+        Ok(span) => Ok(((now - span).with_timezone(&Utc), None)),
+        Err(_) => bail!("Invalid time '{}'. {}", trimmed, TIME_FORMATS),
+    }
+}
+
+/// Lowercase with single spaces, ISO 8601's `T` split off and filler words
+/// ("at", "on") dropped
+fn normalize(input: &str) -> String {
+    let lower = input.trim().to_lowercase();
+    let iso = Regex::new(r"^(\d{4}-\d{2}-\d{2})t(\d)").expect("valid regex");
+    let lower = iso.replace(&lower, "$1 $2");
+    lower
+        .split_whitespace()
+        .filter(|word| !matches!(*word, "at" | "on"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `<day> [<time>]` or `<time>` (meaning today), and whether no time was given
+fn parse_local(lower: &str, today: NaiveDate) -> Option<(NaiveDateTime, bool)> {
+    let words: Vec<&str> = lower.split(' ').collect();
+    let (date, used) = parse_date(&words, today).unwrap_or((today, 0));
+    let rest = words[used..].join(" ");
+    if rest.is_empty() {
+        return (used > 0).then(|| (date.and_time(NaiveTime::MIN), true));
+    }
+    parse_time_of_day(&rest).map(|time| (date.and_time(time), false))
+}
 
-        let total_duration = Duration::hours(hours as i64)
-            + Duration::minutes(minutes as i64)
-            + Duration::seconds(seconds as i64);
+/// A date from the first words, and how many words it took
+fn parse_date(words: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let first = *words.first()?;
+    match first {
+        "today" => return Some((today, 1)),
+        "yesterday" => return Some((today - Duration::days(1), 1)),
+        "tomorrow" => return Some((today + Duration::days(1), 1)),
+        _ => {}
+    }
+    for format in ["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(first, format) {
+            return Some((date, 1));
+        }
+    }
+    // "monday" is the latest monday up to today, "last monday" the one before
+    // today
+    let (last, weekday) = match (first, words.get(1)) {
+        ("last", Some(day)) => (true, day.parse::<Weekday>().ok()?),
+        (day, _) => (false, day.parse::<Weekday>().ok()?),
+    };
+    let mut back =
+        (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    if last && back == 0 {
+        back = 7;
+    }
+    Some((
+        today - Duration::days(back as i64),
+        if last { 2 } else { 1 },
+    ))
+}
 
-        let target_time = Utc::now() - total_duration;
+/// "9am", "9:30 pm", "14:00", "14:00:30", "noon" or "midnight"
+fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    match input {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+    let re = Regex::new(r"^(\d{1,2})(?::(\d{2}))?(?::(\d{2}))?\s*(am|pm)?$").expect("valid regex");
+    let captures = re.captures(input)?;
+    let number = |group: usize| {
+        captures
+            .get(group)
+            .map_or(Some(0), |m| m.as_str().parse::<u32>().ok())
+    };
+    let (mut hour, minute, second) = (number(1)?, number(2)?, number(3)?);
+    match captures.get(4).map(|m| m.as_str()) {
+        Some(meridiem) => {
+            if !(1..=12).contains(&hour) {
+                return None;
+            }
+            hour = hour % 12 + if meridiem == "pm" { 12 } else { 0 };
+        }
+        // A bare number is a count ("3" of what?), not an hour
+        None if captures.get(2).is_none() => return None,
+        None => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
 
-        // Convert to SystemTime
-        let system_time =
-            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(target_time.timestamp() as u64);
+/// A span back from now in compact ("1h 30m ago") or spelled-out ("3 days
+/// ago", "an hour") form
+fn parse_span(lower: &str) -> Result<Duration> {
+    let text = lower.strip_suffix(" ago").unwrap_or(lower);
+    let words =
+        Regex::new(r"\b(\d+|an?)\s*(years?|yrs?|months?|weeks?|days?|hours?|hrs?|minutes?|mins?|seconds?|secs?)\b")
+            .expect("valid regex");
+    let compact = words.replace_all(text, |captures: &regex::Captures| {
+        let count = match &captures[1] {
+            "a" | "an" => "1",
+            count => count,
+        };
+        // "mo" keeps months apart from minutes
+        let unit = if captures[2].starts_with("mo") {
+            "mo"
+        } else {
+            &captures[2][..1]
+        };
+        format!("{}{}", count, unit)
+    });
+    parse_duration(&compact)
+}
 
-        Ok(system_time)
-    } else {
-        bail!("Invalid time format. Use formats like '3m ago', '2h ago', '1h 30m ago', etc.");
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Local>> {
+    match date
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+    {
+        Some(at) => Ok(at),
+        None => bail!("Invalid day: {}", date),
     }
 }
 
@@ -81,6 +335,92 @@ mod tests {
     use super::*;
     use std::time::Duration as StdDuration;
 
+    #[test]
+    fn test_parse_instant_forms() {
+        let at = parse_instant("2024-06-01T10:00:00Z").unwrap();
+        assert_eq!(at.timestamp(), 1_717_236_000);
+
+        let midnight = parse_instant("2024-06-01").unwrap();
+        assert_eq!(
+            midnight.with_timezone(&Local).date_naive(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+        );
+
+        let week_ago = parse_instant("7d").unwrap();
+        let expected = Utc::now() - Duration::days(7);
+        assert!((week_ago - expected).num_seconds().abs() < 5);
+        assert!(parse_instant("when pigs fly").is_err());
+    }
+
+    /// Wednesday 2024-05-15 15:00 local time
+    fn wednesday() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, 15, 15, 0, 0).unwrap()
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_absolute_and_natural_instants() {
+        let now = wednesday();
+        let at = |input: &str| parse_instant_at(input, now).unwrap();
+        assert_eq!(at("2024-05-01 14:00"), local(2024, 5, 1, 14, 0));
+        assert_eq!(at("2024-05-01T14:00"), local(2024, 5, 1, 14, 0));
+        assert_eq!(at("2024/05/01"), local(2024, 5, 1, 0, 0));
+        assert_eq!(at("today 9am"), local(2024, 5, 15, 9, 0));
+        assert_eq!(at("Yesterday at 5:30 PM"), local(2024, 5, 14, 17, 30));
+        assert_eq!(at("yesterday"), local(2024, 5, 14, 0, 0));
+        assert_eq!(at("14:00"), local(2024, 5, 15, 14, 0));
+        assert_eq!(at("noon"), local(2024, 5, 15, 12, 0));
+        assert_eq!(at("monday"), local(2024, 5, 13, 0, 0));
+        assert_eq!(at("wednesday"), local(2024, 5, 15, 0, 0));
+        assert_eq!(at("last wednesday 9am"), local(2024, 5, 8, 9, 0));
+        assert_eq!(at("3 hours ago"), local(2024, 5, 15, 12, 0));
+        assert_eq!(at("an hour ago"), local(2024, 5, 15, 14, 0));
+        assert_eq!(at("2 days"), local(2024, 5, 13, 15, 0));
+        assert_eq!(at("1h 30m ago"), local(2024, 5, 15, 13, 30));
+        assert_eq!(at("now"), now.with_timezone(&Utc));
+
+        for invalid in ["13pm", "today 25:00", "someday", "3", "last", ""] {
+            assert!(parse_instant_at(invalid, now).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        let now = wednesday();
+        let range = |input: &str| parse_range_at(input, now).unwrap();
+        assert_eq!(
+            range("2024-05-01..2024-05-03"),
+            (local(2024, 5, 1, 0, 0), local(2024, 5, 4, 0, 0))
+        );
+        assert_eq!(
+            range("from monday 9am to yesterday 18:00"),
+            (local(2024, 5, 13, 9, 0), local(2024, 5, 14, 18, 0))
+        );
+        assert_eq!(
+            range("yesterday"),
+            (local(2024, 5, 14, 0, 0), local(2024, 5, 15, 0, 0))
+        );
+        assert_eq!(
+            range("2h ago"),
+            (local(2024, 5, 15, 13, 0), now.with_timezone(&Utc))
+        );
+        assert_eq!(
+            range("last week"),
+            (local(2024, 5, 6, 0, 0), local(2024, 5, 13, 0, 0))
+        );
+        assert_eq!(
+            range("last month"),
+            (local(2024, 4, 1, 0, 0), local(2024, 5, 1, 0, 0))
+        );
+        assert!(parse_range_at("today..yesterday", now).is_err());
+    }
+
     #[test]
     fn test_parse_minutes_only() {
         let result = parse_time_ago("5m ago");
@@ -133,6 +473,29 @@ mod tests {
         assert!((duration.as_secs() as i64 - 5445).abs() <= 1);
     }
 
+    #[test]
+    fn test_parse_days_and_weeks() {
+        let now = SystemTime::now();
+        let duration = now.duration_since(parse_time_ago("7d").unwrap()).unwrap();
+        assert!((duration.as_secs() as i64 - 7 * 86_400).abs() <= 1);
+
+        let duration = now
+            .duration_since(parse_time_ago("1w 2d ago").unwrap())
+            .unwrap();
+        assert!((duration.as_secs() as i64 - 9 * 86_400).abs() <= 1);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90d").unwrap(), Duration::days(90));
+        assert_eq!(parse_duration("1h 30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("6mo").unwrap(), Duration::days(180));
+        assert_eq!(parse_duration("1y").unwrap(), Duration::days(365));
+        assert_eq!(parse_span("2 months ago").unwrap(), Duration::days(60));
+        assert!(parse_duration("0d").is_err());
+        assert!(parse_duration("soon").is_err());
+    }
+
     #[test]
     fn test_parse_without_ago_suffix() {
         let result = parse_time_ago("3m");
@@ -308,4 +671,17 @@ mod tests {
         let five_minutes_ago = now - StdDuration::from_secs(5 * 60);
         assert!(validate_time_ago(five_minutes_ago, 6, 5).is_ok());
     }
+
+    #[test]
+    fn test_parse_day() {
+        let (start, end) = parse_day("2024-06-10").unwrap();
+        assert_eq!(end.duration_since(start).unwrap().as_secs() / 3600, 24);
+
+        let (today, _) = parse_day("today").unwrap();
+        let (yesterday, yesterday_end) = parse_day("Yesterday").unwrap();
+        assert!(yesterday < today);
+        assert_eq!(yesterday_end, today);
+
+        assert!(parse_day("someday").is_err());
+    }
 }
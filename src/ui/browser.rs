@@ -187,7 +187,7 @@ impl BrowserOpener {
 
     /// Start a local HTTP server and open the URL in browser
     pub fn open_with_server(html_content: &str, filename: &str) -> Result<()> {
-        let temp_dir = std::env::temp_dir();
+        let temp_dir = crate::paths::temp_dir()?;
         let file_path = temp_dir.join(filename);
 
         // Write HTML content to temporary file
@@ -221,7 +221,13 @@ impl BrowserOpener {
         // Try to open the URL in browser
         if Self::open_url(&url).is_err() {
             // If opening fails, print the URL for manual opening
-            println!("🌐 Please open this URL in your browser: {}", url);
+            println!(
+                "{}",
+                crate::output::plain_text(&format!(
+                    "🌐 Please open this URL in your browser: {}",
+                    url
+                ))
+            );
             println!(" Or open this file directly: {}", file_path.display());
         }
 
@@ -295,7 +301,7 @@ impl BrowserOpener {
     }
 
     /// Open a URL in the browser
-    fn open_url(url: &str) -> Result<()> {
+    pub fn open_url(url: &str) -> Result<()> {
         if cfg!(target_os = "windows") {
             Command::new("rundll32")
                 .args(["url.dll,FileProtocolHandler", url])
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
@@ -7,36 +7,72 @@ use std::time::{Duration as StdDuration, SystemTime};
 
 use anyhow::{bail, ensure, Context, Result};
 use axum::extract::{Path as AxumPath, Query as AxumQuery, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse};
 use axum::routing::get;
 use axum::{Json, Router};
 use chrono::{Duration, Utc};
-use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
 use colored::Colorize;
-use comfy_table::{presets::UTF8_HORIZONTAL_ONLY, Table};
+use comfy_table::Table;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::{execute, queue};
 use dialoguer::{theme::ColorfulTheme, Editor, Input};
+use indicatif::{ProgressBar, ProgressStyle};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use ratatui::Terminal;
-use serde::Deserialize;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
-use crate::config_cmd::{update_redaction, update_remote};
+use crate::activity_storage::{CommandLog, CommandRecord};
+use crate::ai::{Summarizer, Summary};
+use crate::analyzer::Analyzer;
+use crate::audit::{self, AuditAction};
+use crate::ci::{
+    annotation, failure_note, scan_log, step_summary, CiFinding, RunInfo, MAX_SOLUTIONS,
+};
+use crate::config::{MetaFieldType, MetaSchema, NoteEvent, ThinConfig};
+use crate::config_cmd::{trust_signing_key, update_analyzer, update_redaction, update_remote};
 use crate::daemon_service::DaemonService;
-use crate::index::{SearchHit, SearchIndex, SearchSort};
-use crate::models::{Author, Note, NoteRecord, Privacy};
+use crate::diff::NoteDiff;
+use crate::docker::Docker;
+use crate::editor::{EditorEvent, NoteForm, TextEditor};
+use crate::feedback::{FeedbackEvent, FeedbackKind, FeedbackStore};
+use crate::index::{
+    pinned_first, HitSnippet, SearchFilter, SearchHit, SearchIndex, SearchPage, SearchSort,
+};
+use crate::ingest::{is_error_line, ErrorBlock, IngestEvent, IngestOutcome, Ingestor, LogScanner};
+use crate::issues::{block_on, IssueLinker, IssueReason, ISSUE_URL_META};
+use crate::journal::{Journal, LogEntry};
+use crate::kubernetes::{
+    container_failures, manifest_snippet, workload_of, KubeClient, KubeEvent, Workload,
+};
+use crate::merge::{merge_bodies, merge_into, SectionChoice, SectionPair};
+use crate::meta_cache::TagStat;
+use crate::models::{Author, Note, NoteRecord, Privacy, LOCAL_ONLY_META};
+use crate::note_service::{normalize_tags, NoteBuilder, NoteService};
+use crate::output::{plain_text, OutputFormat};
+use crate::recording::{
+    format_duration, ActiveRecording, RecordingStore, DEFAULT_RECORDING, SLOW_COMMAND,
+};
+use crate::registry::{self, RepoHit, RepoRegistry};
+use crate::related::{self, RelatedNote};
 use crate::repo::FukuraRepo;
-use crate::sync::{pull_note, push_note};
-use crate::time_parser::{parse_time_ago, validate_time_ago};
+use crate::share::{self, Share, ShareKind};
+use crate::signing::{self, Trust, TrustStore};
+use crate::sync::{leaked_secrets, pre_push, pull_note, push_note, push_notes, CloneManifest, Hub};
+use crate::thin_store::ThinObjectStore;
+use crate::time_parser::{parse_instant, parse_range, parse_time_ago, validate_time_ago};
+use crate::titles::{render_title, TitleContext};
+use crate::webhooks::Webhooks;
 use clap::CommandFactory;
 use clap_complete::{generate, Shell};
 
@@ -51,37 +87,14 @@ fn format_object_id(id: &str) -> String {
 
 async fn handle_rec_time_based(cli: &Cli, cmd: &RecCommand) -> Result<()> {
     let repo = open_repo(cli)?;
-    let recording_file = repo.root().join(".fukura").join("recording");
+    let store = RecordingStore::new(repo.root());
 
     if cmd.status {
-        // Show recording status
-        if recording_file.exists() {
-            let content = fs::read_to_string(&recording_file)?;
-            let parts: Vec<&str> = content.split('|').collect();
-            if parts.len() >= 2 {
-                let title = parts[1];
-                if !cli.quiet {
-                    println!("{} Recording in progress", "🔴".red());
-                    println!();
-                    println!("  📝 Task: {}", title.bold());
-                    println!("  ⏱️  All commands are being recorded");
-                    println!();
-                    println!("💡 When done:");
-                    println!("  fuku done        # Save and stop recording");
-                }
-            }
-        } else if !cli.quiet {
-            println!("{} Not recording", "ℹ️".blue());
-            println!();
-            println!("💡 Start recording:");
-            println!("  fuku rec \"Task description\"");
-            println!("  fuku rec \"Task\" 3m ago      # Start from 3 minutes ago");
-        }
-        return Ok(());
+        return print_recording_status(cli, &store, cmd.name.as_deref());
     }
 
     if cmd.stop {
-        return handle_done(cli);
+        return handle_done(cli, cmd.name.as_deref(), false).await;
     }
 
     // Require title if not status/stop
@@ -92,52 +105,52 @@ async fn handle_rec_time_based(cli: &Cli, cmd: &RecCommand) -> Result<()> {
         }
     };
 
-    // Check if already recording
-    if recording_file.exists() {
-        let content = fs::read_to_string(&recording_file)?;
-        let parts: Vec<&str> = content.split('|').collect();
-        if parts.len() >= 2 {
-            if !cli.quiet {
-                println!("{} Already recording: {}", "⚠️".yellow(), parts[1]);
-                println!();
-                println!("💡 Options:");
-                println!("  fuku done          # Finish current recording");
-                println!("  fuku rec --stop    # Same as 'fuku done'");
-            }
-            return Ok(());
-        }
+    repo.ensure_writable()?;
+    let name = cmd.name.as_deref().unwrap_or(DEFAULT_RECORDING);
+    if already_recording(cli, &store, name)? {
+        return Ok(());
     }
 
     // Handle time-based recording
     if let Some(time_expr) = &cmd.from_time {
-        return handle_time_based_rec(cli, title, time_expr).await;
+        return handle_time_based_rec(cli, name, title, time_expr).await;
     }
 
     // Start new recording (normal case)
-    let session_id = format!("rec_{}", chrono::Utc::now().timestamp());
-    let recording_data = format!("{}|{}", session_id, title);
-    fs::write(&recording_file, recording_data)?;
+    let recording =
+        ActiveRecording::new(name, title, SystemTime::now(), recording_directory(name)?);
+    store.save(&recording)?;
 
     if !cli.quiet {
-        println!("{} Recording started", "🔴".red().bold());
+        println!("{}Recording started", plain_text("🔴 ").red().bold());
         println!();
-        println!("  📝 Task: {}", title.bold());
-        println!("  🎯 Session ID: {}", session_id);
+        println!("  {}Task: {}", plain_text("📝 "), title.bold());
+        println!(
+            "  {}Session ID: {}",
+            plain_text("🎯 "),
+            recording.session_id
+        );
+        if let Some(dir) = &recording.directory {
+            println!("  {}Commands in: {}", plain_text("📂 "), dir.display());
+        }
         println!();
-        println!("💡 All commands will be recorded automatically");
-        println!("   Run 'fuku done' when finished");
+        println!(
+            "{}All commands will be recorded automatically",
+            plain_text("💡 ")
+        );
+        println!("   Run '{}' when finished", done_command(name));
         println!();
         println!("Examples of what gets recorded:");
-        println!("  • Every command you run");
-        println!("  • Success/failure status");
-        println!("  • Working directory");
-        println!("  • Timestamps");
+        println!("  {} Every command you run", plain_text("•"));
+        println!("  {} Success/failure status", plain_text("•"));
+        println!("  {} Working directory", plain_text("•"));
+        println!("  {} Timestamps", plain_text("•"));
     }
 
     Ok(())
 }
 
-async fn handle_time_based_rec(cli: &Cli, title: &str, time_expr: &str) -> Result<()> {
+async fn handle_time_based_rec(cli: &Cli, name: &str, title: &str, time_expr: &str) -> Result<()> {
     let repo = open_repo(cli)?;
 
     // Load configuration
@@ -160,7 +173,10 @@ async fn handle_time_based_rec(cli: &Cli, title: &str, time_expr: &str) -> Resul
     let daemon_service = DaemonService::new(repo.root());
     if !daemon_service.is_running().await {
         if !cli.quiet {
-            println!("{} Daemon not running. Starting it now...", "ℹ️".yellow());
+            println!(
+                "{} Daemon not running. Starting it now...",
+                plain_text("ℹ️").yellow()
+            );
         }
         daemon_service.start_background()?;
 
@@ -175,37 +191,47 @@ async fn handle_time_based_rec(cli: &Cli, title: &str, time_expr: &str) -> Resul
     }
 
     if !cli.quiet {
-        println!("{} Searching for commands since {}", "🔍".cyan(), time_expr);
-    }
-
-    // Try to get historical commands from daemon
-    // Note: In a real implementation, you'd need to connect to the daemon via socket
-    // For now, we'll create a placeholder recording file
-    let session_id = format!("time_rec_{}", chrono::Utc::now().timestamp());
-    let recording_data = format!(
-        "{}|{}|since:{}",
-        session_id,
-        title,
-        target_time
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)?
-            .as_secs()
-    );
+        println!(
+            "{}Searching for commands since {}",
+            plain_text("🔍 ").cyan(),
+            time_expr
+        );
+    }
 
-    let recording_file = repo.root().join(".fukura").join("recording");
-    fs::write(&recording_file, recording_data)?;
+    // Commands are persisted by the daemon, so the backfill survives restarts
+    let backfilled = CommandLog::new(repo.root())?.read_since(target_time)?;
+
+    let recording = ActiveRecording::new(name, title, target_time, recording_directory(name)?);
+    RecordingStore::new(repo.root()).save(&recording)?;
 
     if !cli.quiet {
-        println!("{} Time-based recording started", "🔴".red().bold());
+        println!(
+            "{}Time-based recording started",
+            plain_text("🔴 ").red().bold()
+        );
         println!();
-        println!("  📝 Task: {}", title.bold());
-        println!("  ⏰ From: {}", time_expr);
-        println!("  🎯 Session ID: {}", session_id);
+        println!("  {}Task: {}", plain_text("📝 "), title.bold());
+        println!("  {}From: {}", plain_text("⏰ "), time_expr);
+        println!(
+            "  {}Backfilled: {} commands",
+            plain_text("📜 "),
+            backfilled.len()
+        );
+        println!(
+            "  {}Session ID: {}",
+            plain_text("🎯 "),
+            recording.session_id
+        );
         println!();
         println!(
-            "💡 Commands from {} ago are now included in recording",
+            "{}Commands from {} ago are now included in recording",
+            plain_text("💡 "),
             time_expr
         );
-        println!("   Continue working and run 'fuku done' when finished");
+        println!(
+            "   Continue working and run '{}' when finished",
+            done_command(name)
+        );
     }
 
     Ok(())
@@ -229,6 +255,44 @@ pub struct Cli {
     #[arg(long, global = true, action = ArgAction::SetTrue, help = "Suppress celebratory output")]
     quiet: bool,
 
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        help = "Print results as a table, JSON or YAML"
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Use a profile from [profiles] in the config (also FUKURA_PROFILE)"
+    )]
+    profile: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Fail instead of rebuilding a search index built by another fuku version"
+    )]
+    no_auto_reindex: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "No emoji, colors or box drawing (also FUKURA_PLAIN=1)"
+    )]
+    plain: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Only search and view; fail on any change (also FUKURA_READ_ONLY=1)"
+    )]
+    read_only: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -249,7 +313,33 @@ pub enum Commands {
 
     /// List all notes (alias for search)
     #[command(about = "List all notes (same as 'search \"\"')")]
-    List,
+    List(ListCommand),
+
+    /// Pin a note to the top of listings
+    #[command(about = "Pin a note so it always appears at the top of 'fuku list'")]
+    Pin(PinCommand),
+
+    /// List pinned notes
+    #[command(about = "List pinned notes")]
+    Pins,
+
+    /// Hide notes from listings and searches without deleting them
+    #[command(
+        about = "Archive notes out of 'fuku list' and 'fuku search' (find them with --archived)"
+    )]
+    Archive(ArchiveCommand),
+
+    /// Edit every note matching a query
+    #[command(about = "Add or remove tags, meta and privacy on every note matching a query")]
+    Bulk(BulkCommand),
+
+    /// Regex search over note bodies
+    #[command(about = "Search note bodies with a regular expression (exact codes, stack frames)")]
+    Grep(GrepCommand),
+
+    /// Pick a note interactively
+    #[command(about = "Fuzzy-pick a note and print its ID (e.g. fuku view $(fuku pick))")]
+    Pick(PickCommand),
 
     /// View a note
     #[command(about = "View a note's full content by ID or special ref (@latest, @1, etc.)")]
@@ -259,6 +349,14 @@ pub enum Commands {
     #[command(about = "Edit an existing note's content, tags, or metadata")]
     Edit(EditCommand),
 
+    /// Rename a note
+    #[command(about = "Retitle a note, or regenerate a captured error's title with --auto")]
+    Rename(RenameCommand),
+
+    /// Summarize a note with a language model
+    #[command(about = "Suggest a title, summary and tags for a note with the model in [ai]")]
+    Summarize(SummarizeCommand),
+
     /// Open note in browser
     #[command(about = "Open a note in your web browser with beautiful HTML rendering")]
     Open(OpenCommand),
@@ -267,6 +365,29 @@ pub enum Commands {
     #[command(about = "Start a local HTTP server to browse and manage notes via API")]
     Serve(ServeCommand),
 
+    /// JSON-RPC server for editor extensions
+    #[command(
+        about = "Serve search, view, create-from-selection and error lookup to editors over stdio (JSON-RPC)"
+    )]
+    Lsp,
+
+    /// Match a failed build log against known fixes
+    #[command(
+        about = "Scan a failed build log from stdin and annotate errors with known fixes (GitHub Actions)"
+    )]
+    Ci(CiCommand),
+
+    /// Look up known fixes for one error
+    #[command(about = "Find known fixes for an error from stdin, or the last captured failure")]
+    Explain(ExplainCommand),
+
+    /// Show the last failed command
+    #[command(
+        name = "last-error",
+        about = "Show the last failed command captured in this directory, or save it as a note"
+    )]
+    LastError(LastErrorCommand),
+
     /// Show repository statistics
     #[command(about = "Display repository statistics including note count, tags, and storage")]
     Stats,
@@ -275,10 +396,27 @@ pub enum Commands {
     #[command(about = "Generate shell completion scripts for bash/zsh/fish")]
     Completions(CompletionsCommand),
 
+    /// Candidates for the dynamic parts of shell completions
+    // Scripts call `__complete`; clap_complete's bash generator splits
+    // subcommand paths on `__`, so that can't be the name itself
+    #[command(name = "complete", alias = "__complete", hide = true)]
+    Complete(CompleteCommand),
+
     /// Manage shell aliases
     #[command(about = "Setup convenient shell aliases for fuku commands")]
     Alias(AliasCommand),
 
+    /// Capture errors from sources without shell hooks
+    #[command(about = "Capture errors from container logs and other sources into notes")]
+    Ingest {
+        #[command(subcommand)]
+        source: IngestSource,
+    },
+
+    /// Capture terminal output the shell hooks can't see
+    #[command(about = "Capture a tmux pane's scrollback into the active recording or a new note")]
+    Capture(CaptureCommand),
+
     /// Import notes from files
     #[command(about = "Import notes from markdown files or directories")]
     Import(ImportCommand),
@@ -289,12 +427,60 @@ pub enum Commands {
 
     /// Stop recording and create note
     #[command(about = "Stop recording and auto-generate note from session")]
-    Done,
+    Done(DoneCommand),
+
+    /// Replay a recorded session
+    #[command(about = "Show the command steps of a recording, or export them as a bash script")]
+    Replay(ReplayCommand),
+
+    /// Show captured command history
+    #[command(about = "Show shell commands captured by the daemon")]
+    History(HistoryCommand),
+
+    /// Show recurring error patterns
+    #[command(
+        about = "Show your most frequent errors with trends and the notes that resolved them"
+    )]
+    Patterns(PatternsCommand),
+
+    /// Summarize recent activity
+    #[command(
+        about = "Summarize errors captured, problems solved, recordings and new notes for a standup"
+    )]
+    Report(ReportCommand),
+
+    /// Show the audit log
+    #[command(about = "Show who added, edited, deleted, synced or reconfigured what, and when")]
+    Audit(AuditCommand),
 
     /// Optimize storage (garbage collection)
     #[command(about = "Pack loose objects to optimize storage and improve performance")]
     Gc(GcCommand),
 
+    /// Upgrade the repository format
+    #[command(about = "Migrate the repository's on-disk format to a newer version")]
+    Migrate(MigrateCommand),
+
+    /// Merge duplicate auto-captured notes
+    #[command(about = "Find near-identical notes and merge them into canonical entries")]
+    Dedupe(DedupeCommand),
+
+    /// Compare two notes
+    #[command(
+        about = "Show how two notes differ in title, tags, meta and body (or a note and its previous revision)"
+    )]
+    Diff(DiffCommand),
+
+    /// Merge notes about the same problem
+    #[command(about = "Combine notes section by section and retire the merged-away ones")]
+    Merge(MergeCommand),
+
+    /// Report whether a suggested note fixed the error
+    #[command(
+        about = "Tell fukura whether a suggested solution worked, to rank future suggestions"
+    )]
+    Feedback(FeedbackCommand),
+
     /// Push notes to remote
     #[command(about = "Push a specific note to remote server (one-way upload)")]
     Push(PushCommand),
@@ -303,10 +489,18 @@ pub enum Commands {
     #[command(about = "Pull a note from remote server by ID")]
     Pull(PullCommand),
 
+    /// Share a note by link
+    #[command(about = "Publish a redacted public copy of a note and print its URL, or revoke one")]
+    Share(ShareCommand),
+
     /// Sync notes with remote
     #[command(about = "Sync notes bidirectionally or manage auto-sync settings")]
     Sync(SyncCommand),
 
+    /// Clone notes from remote
+    #[command(about = "Clone a remote's notes; --thin fetches each note on first use")]
+    Clone(CloneCommand),
+
     /// Manage configuration
     #[command(about = "Manage configuration settings (remote URL, redaction rules)")]
     Config {
@@ -314,6 +508,27 @@ pub enum Commands {
         command: ConfigCommand,
     },
 
+    /// Manage the repositories searched by --all-repos
+    #[command(about = "List, add or remove the repositories 'search --all-repos' looks in")]
+    Repos {
+        #[command(subcommand)]
+        command: ReposCommand,
+    },
+
+    /// Manage the key notes are signed with
+    #[command(about = "Generate or show your signing key, or trust a teammate's")]
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+
+    /// Show the tags in use
+    #[command(about = "List tags with how many notes carry each and when they were last used")]
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+
     /// Start error capture daemon
     #[command(name = "start", about = "Start the error capture daemon in background")]
     Start,
@@ -327,7 +542,14 @@ pub enum Commands {
         name = "status",
         about = "Check daemon status, hooks, and notification settings"
     )]
-    Status,
+    Status(StatusCommand),
+
+    /// Compact status for shell prompts
+    #[command(
+        name = "prompt-status",
+        about = "Print a compact prompt segment: daemon up/down, recording, unsynced notes"
+    )]
+    PromptStatus(PromptStatusCommand),
 
     /// Restart daemon
     #[command(name = "restart", about = "Restart the error capture daemon")]
@@ -355,6 +577,12 @@ pub enum Commands {
     #[command(about = "Show detailed activity information")]
     Show(ShowCommand),
 
+    /// Reconstruct what happened on a day or in a session
+    #[command(
+        about = "Show a timeline of commands, errors, file changes and notes grouped by directory"
+    )]
+    Timeline(TimelineCommand),
+
     /// Track activities
     #[command(about = "Start/stop activity tracking")]
     Track(TrackCommand),
@@ -373,8 +601,8 @@ pub struct ActivityCommand {
 
     #[arg(
         long,
-        value_name = "TIME_AGO",
-        help = "Show activities since (e.g., '1h ago')"
+        value_name = "WHEN",
+        help = "Show activities since WHEN (e.g., '1h ago', 'today 9am')"
     )]
     since: Option<String>,
 }
@@ -389,7 +617,11 @@ pub struct LogCommand {
     )]
     limit: usize,
 
-    #[arg(long, value_name = "TIME_AGO", help = "Show since (e.g., '1h ago')")]
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Show since WHEN (e.g., '1h ago', 'yesterday')"
+    )]
     since: Option<String>,
 
     #[arg(long, help = "Show only file changes")]
@@ -402,6 +634,27 @@ pub struct LogCommand {
     oneline: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct TimelineCommand {
+    #[arg(
+        long,
+        value_name = "WHEN",
+        default_value = "today",
+        help = "Day or range to show (e.g., 'yesterday', 'monday', '2024-05-01..2024-05-03', 'last week')"
+    )]
+    day: String,
+
+    #[arg(
+        long,
+        value_name = "SESSION_ID",
+        help = "Show a single session instead of a day"
+    )]
+    session: Option<String>,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct ShowCommand {
     #[arg(value_name = "SESSION_ID", help = "Session ID or @latest")]
@@ -444,11 +697,37 @@ pub struct InitCommand {
     #[arg(long, help = "Reinitialize existing repository")]
     force: bool,
 
-    #[arg(long, help = "Skip daemon setup")]
+    #[arg(long, help = "Skip daemon setup (same as --daemon off)")]
     no_daemon: bool,
 
+    #[arg(
+        long,
+        value_enum,
+        value_name = "on|off",
+        conflicts_with = "no_daemon",
+        help = "Start the capture daemon or not, without asking"
+    )]
+    daemon: Option<DaemonSetting>,
+
     #[arg(long, help = "Skip shell hooks")]
     no_hooks: bool,
+
+    #[arg(short = 'y', long, help = "Accept the defaults instead of prompting")]
+    yes: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "force",
+        help = "Succeed without changes when the repository is already set up; never prompts"
+    )]
+    ensure: bool,
+}
+
+/// `fuku init --daemon`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DaemonSetting {
+    On,
+    Off,
 }
 
 #[derive(Debug, Args)]
@@ -496,8 +775,12 @@ pub struct AddCommand {
     )]
     links: Vec<String>,
 
-    #[arg(value_enum, long, help = "Privacy level (private/org/public)", default_value_t = Privacy::Private)]
-    privacy: Privacy,
+    #[arg(
+        value_enum,
+        long,
+        help = "Privacy level (private/org/public); defaults to default_privacy in the config, else private"
+    )]
+    privacy: Option<Privacy>,
 
     #[arg(long, value_name = "NAME", help = "Author name")]
     author: Option<String>,
@@ -505,6 +788,13 @@ pub struct AddCommand {
     #[arg(long, value_name = "EMAIL", help = "Author email")]
     email: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Archive the note after this long (e.g. 90d, 2w)"
+    )]
+    expires: Option<String>,
+
     #[arg(long, help = "Skip editor")]
     no_editor: bool,
 }
@@ -532,85 +822,408 @@ pub struct SearchCommand {
     #[arg(long, help = "Search remote hub only")]
     remote_only: bool,
 
+    #[arg(
+        long,
+        conflicts_with_all = ["tui", "all_repos", "remote", "remote_only"],
+        help = "Search archived notes: ones archived with 'fuku archive' or once expired"
+    )]
+    archived: bool,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Only notes created after WHEN (a date, 'today 9am', RFC 3339, or a span like 7d)"
+    )]
+    created_after: Option<String>,
+
+    #[arg(long, value_name = "WHEN", help = "Only notes created before WHEN")]
+    created_before: Option<String>,
+
+    #[arg(
+        long,
+        visible_alias = "since",
+        value_name = "WHEN",
+        help = "Only notes updated after WHEN (e.g., 'yesterday', '2024-05-01 14:00', '3 days ago')"
+    )]
+    updated_after: Option<String>,
+
+    #[arg(
+        long,
+        visible_alias = "until",
+        value_name = "WHEN",
+        help = "Only notes updated before WHEN"
+    )]
+    updated_before: Option<String>,
+
+    #[arg(
+        long = "meta",
+        value_name = "KEY=VALUE",
+        action = ArgAction::Append,
+        help = "Only notes with this exact metadata (can be used multiple times)"
+    )]
+    meta: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Only notes from this package of a monorepo or one inside it, e.g. services/payments"
+    )]
+    scope: Option<String>,
+
+    #[command(flatten)]
+    page: PageArgs,
+
     #[arg(value_name = "QUERY", help = "Search terms", trailing_var_arg = true)]
     query: Vec<String>,
 }
 
 #[derive(Debug, Args)]
-pub struct ViewCommand {
-    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
-    id: String,
+pub struct ListCommand {
+    #[arg(long, short = 'n', default_value_t = 100, help = "Max results")]
+    limit: usize,
 
-    #[arg(long, help = "Output as JSON")]
-    json: bool,
+    #[command(flatten)]
+    page: PageArgs,
 }
 
+/// Which page of results to show; pages are `--limit` long
 #[derive(Debug, Args)]
-pub struct EditCommand {
-    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
-    id: String,
-
-    #[arg(long, value_name = "TEXT", help = "Update title")]
-    title: Option<String>,
-
-    #[arg(long, value_name = "TEXT", help = "Update body")]
-    body: Option<String>,
-
+pub struct PageArgs {
     #[arg(
-        long = "add-tag",
-        value_name = "TAG",
-        action = ArgAction::Append,
-        help = "Add tag (can be used multiple times)"
+        long,
+        value_name = "N",
+        conflicts_with = "page",
+        help = "Skip the first N results"
     )]
-    add_tags: Vec<String>,
+    offset: Option<usize>,
 
     #[arg(
-        long = "remove-tag",
-        value_name = "TAG",
-        action = ArgAction::Append,
-        help = "Remove tag (can be used multiple times)"
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Page of results to show, starting at 1"
     )]
-    remove_tags: Vec<String>,
+    page: Option<u64>,
+}
 
-    #[arg(long, help = "Open in editor to edit body")]
-    editor: bool,
+impl PageArgs {
+    fn offset(&self, limit: usize) -> usize {
+        match (self.offset, self.page) {
+            (Some(offset), _) => offset,
+            (None, Some(page)) => (page as usize - 1) * limit,
+            (None, None) => 0,
+        }
+    }
 }
 
 #[derive(Debug, Args)]
-pub struct OpenCommand {
-    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
-    id: String,
+pub struct GrepCommand {
+    #[arg(value_name = "PATTERN", help = "Regular expression to search for")]
+    pattern: String,
+
+    #[arg(short = 'i', long, help = "Case-insensitive matching")]
+    ignore_case: bool,
+
+    #[arg(short = 'F', long, help = "Treat the pattern as a literal string")]
+    fixed_strings: bool,
+
+    #[arg(short = 'l', long, help = "Only print IDs of matching notes")]
+    files_with_matches: bool,
 
     #[arg(
-        long,
-        value_name = "THEME",
-        help = "Theme (light/dark)",
-        default_value = "dark"
+        long = "tag",
+        value_name = "TAG",
+        action = ArgAction::Append,
+        help = "Only search notes with this tag (can be used multiple times)"
     )]
-    theme: String,
+    tags: Vec<String>,
 
-    #[arg(long, help = "Open in browser directly")]
-    browser_only: bool,
+    #[arg(
+        short = 'C',
+        long,
+        value_name = "N",
+        help = "Lines of context around matches"
+    )]
+    context: Option<usize>,
 
-    #[arg(long, help = "Show URL only")]
-    url_only: bool,
+    #[arg(
+        short = 'A',
+        long,
+        value_name = "N",
+        help = "Lines of context after matches"
+    )]
+    after_context: Option<usize>,
 
     #[arg(
+        short = 'B',
         long,
-        value_name = "PORT",
-        help = "Local server port",
-        default_value = "8080"
+        value_name = "N",
+        help = "Lines of context before matches"
     )]
-    server_port: Option<u16>,
+    before_context: Option<usize>,
 }
 
 #[derive(Debug, Args)]
-pub struct ServeCommand {
+pub struct PickCommand {
     #[arg(
         long,
-        value_name = "HOST:PORT",
-        default_value = "127.0.0.1:8765",
-        help = "Server address"
+        help = "Open the picked note in the browser instead of printing its ID"
+    )]
+    open: bool,
+
+    #[arg(long, help = "Print the short ID")]
+    short: bool,
+
+    #[arg(long, default_value_t = 5000, help = "Max notes to load")]
+    limit: usize,
+
+    #[arg(
+        long,
+        value_name = "SHELL",
+        help = "Print a Ctrl+G key binding widget for bash, zsh, or fish"
+    )]
+    widget: Option<String>,
+
+    #[arg(value_name = "QUERY", help = "Initial filter", trailing_var_arg = true)]
+    query: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ViewCommand {
+    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
+    id: String,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with = "json",
+        help = "Export as a standalone document instead of terminal output"
+    )]
+    format: Option<ViewFormat>,
+
+    #[arg(
+        short = 'o',
+        long = "out",
+        value_name = "FILE",
+        requires = "format",
+        help = "Write the export to FILE (PDFs default to <id>.pdf)"
+    )]
+    out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "THEME",
+        default_value = "light",
+        help = "Theme of HTML and PDF exports (light/dark)"
+    )]
+    theme: String,
+
+    #[arg(long, help = "Show the whole body of a note that was truncated")]
+    full: bool,
+}
+
+/// Document formats of `fuku view --format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ViewFormat {
+    /// Markdown with meta, links and solutions sections
+    Md,
+    /// Standalone HTML page
+    Html,
+    /// PDF, printed by a headless browser or wkhtmltopdf
+    Pdf,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameCommand {
+    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
+    id: String,
+
+    #[arg(value_name = "TITLE", help = "New title", conflicts_with = "auto")]
+    title: Option<String>,
+
+    #[arg(
+        long,
+        help = "Regenerate the title of an auto-captured error from [capture] title_template"
+    )]
+    auto: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SummarizeCommand {
+    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
+    id: String,
+
+    #[arg(
+        long,
+        help = "Retitle, summarize and tag the note instead of only showing the suggestion"
+    )]
+    apply: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct EditCommand {
+    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
+    id: String,
+
+    #[arg(long, value_name = "TEXT", help = "Update title")]
+    title: Option<String>,
+
+    #[arg(long, value_name = "TEXT", help = "Update body")]
+    body: Option<String>,
+
+    #[command(flatten)]
+    edits: NoteEdits,
+
+    #[arg(long, help = "Open in editor to edit body")]
+    editor: bool,
+}
+
+/// Tag and metadata changes, shared by `fuku edit` and `fuku bulk`
+#[derive(Debug, Args)]
+pub struct NoteEdits {
+    #[arg(
+        long = "add-tag",
+        value_name = "TAG",
+        action = ArgAction::Append,
+        help = "Add tag (can be used multiple times)"
+    )]
+    add_tags: Vec<String>,
+
+    #[arg(
+        long = "remove-tag",
+        value_name = "TAG",
+        action = ArgAction::Append,
+        help = "Remove tag (can be used multiple times)"
+    )]
+    remove_tags: Vec<String>,
+
+    #[arg(
+        long = "meta",
+        value_name = "KEY=VALUE",
+        action = ArgAction::Append,
+        help = "Set metadata (can be used multiple times)"
+    )]
+    meta: Vec<String>,
+
+    #[arg(
+        long = "remove-meta",
+        value_name = "KEY",
+        action = ArgAction::Append,
+        help = "Remove metadata (can be used multiple times)"
+    )]
+    remove_meta: Vec<String>,
+}
+
+impl NoteEdits {
+    fn is_empty(&self) -> bool {
+        self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+            && self.meta.is_empty()
+            && self.remove_meta.is_empty()
+    }
+
+    /// Apply the changes to `note`, returning whether it changed
+    fn apply(&self, repo: &FukuraRepo, note: &mut Note) -> Result<bool> {
+        let mut modified = false;
+        // Add tags
+        if !self.add_tags.is_empty() {
+            let normalized = normalize_tags(self.add_tags.clone());
+            for tag in normalized {
+                if !note.tags.contains(&tag) {
+                    note.tags.push(tag);
+                    modified = true;
+                }
+            }
+            note.tags.sort();
+        }
+
+        // Remove tags
+        if !self.remove_tags.is_empty() {
+            let normalized = normalize_tags(self.remove_tags.clone());
+            for tag in normalized {
+                if let Some(pos) = note.tags.iter().position(|t| t == &tag) {
+                    note.tags.remove(pos);
+                    modified = true;
+                }
+            }
+        }
+
+        // Update metadata
+        if !self.meta.is_empty() || !self.remove_meta.is_empty() {
+            for key in &self.remove_meta {
+                modified |= note.meta.remove(key.trim()).is_some();
+            }
+            for (key, value) in parse_meta(self.meta.clone())? {
+                modified |= note.meta.get(&key) != Some(&value);
+                note.meta.insert(key, value);
+            }
+            validate_meta(repo, &note.meta)?;
+        }
+        Ok(modified)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct OpenCommand {
+    #[arg(value_name = "ID", help = "Note ID or @latest/@1")]
+    id: String,
+
+    #[arg(
+        long,
+        value_name = "THEME",
+        help = "Theme (light/dark)",
+        default_value = "dark"
+    )]
+    theme: String,
+
+    #[arg(long, help = "Open in browser directly")]
+    browser_only: bool,
+
+    #[arg(long, help = "Show URL only")]
+    url_only: bool,
+
+    #[arg(
+        long,
+        value_name = "PORT",
+        help = "Local server port",
+        default_value = "8080"
+    )]
+    server_port: Option<u16>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["browser_only", "url_only"],
+        help = "Open through 'fuku serve' (started if needed) so links between notes work"
+    )]
+    serve: bool,
+
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        default_value = "127.0.0.1:8765",
+        requires = "serve",
+        help = "Address of the serve server"
+    )]
+    addr: String,
+
+    #[arg(
+        long,
+        requires = "serve",
+        help = "Print a QR code of the note's serve URL to open it on a phone instead"
+    )]
+    qr: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeCommand {
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        default_value = "127.0.0.1:8765",
+        help = "Server address"
     )]
     addr: String,
 
@@ -622,14 +1235,29 @@ pub struct ServeCommand {
 pub struct CompletionsCommand {
     #[arg(
         value_name = "SHELL",
-        help = "Shell type (bash, zsh, fish, powershell)"
+        help = "Shell type (bash, zsh, fish, powershell, elvish, xonsh); detected when omitted"
     )]
-    shell: String,
+    shell: Option<String>,
 
     #[arg(long, help = "Output to stdout instead of installing")]
     stdout: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct CompleteCommand {
+    #[arg(value_enum)]
+    kind: CompleteKind,
+}
+
+/// What `fuku __complete` lists, one `value<TAB>description` per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompleteKind {
+    /// @latest, @N from the last search, and recent short IDs
+    Ids,
+    /// Tags in use, most used first
+    Tags,
+}
+
 #[derive(Debug, Args)]
 pub struct AliasCommand {
     #[arg(long, help = "Show current aliases")]
@@ -668,1643 +1296,5435 @@ pub struct RecCommand {
     )]
     from_time: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Run this recording alongside others; it only records commands in the current directory"
+    )]
+    name: Option<String>,
+
     #[arg(long, help = "Stop recording (same as 'fuku done')")]
     stop: bool,
 
     #[arg(long, help = "Show current recording status")]
     status: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["from_time", "stop", "status"],
+        help = "Also record the terminal as an asciicast, in a new shell (or COMMAND)"
+    )]
+    cast: bool,
+
+    #[arg(
+        last = true,
+        value_name = "COMMAND",
+        requires = "cast",
+        help = "Shell to run with --cast (default: $SHELL)"
+    )]
+    shell: Vec<String>,
 }
 
 #[derive(Debug, Args)]
-pub struct GcCommand {
-    #[arg(long, help = "Remove loose objects")]
-    prune: bool,
+pub struct DoneCommand {
+    #[arg(long, help = "Include git diffs of modified files in the note")]
+    diffs: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Recording to finish (default: the one for the current directory)"
+    )]
+    name: Option<String>,
 }
 
 #[derive(Debug, Args)]
-pub struct PushCommand {
-    #[arg(value_name = "ID", help = "Note ID")]
+pub struct ReplayCommand {
+    #[arg(value_name = "ID", help = "Recording note ID (or @latest, @1)")]
     id: String,
 
-    #[arg(long, value_name = "URL", help = "Remote URL")]
-    remote: Option<String>,
+    #[arg(
+        long,
+        help = "Print an annotated bash script (failed attempts commented out)"
+    )]
+    script: bool,
+
+    #[arg(
+        short = 'o',
+        long = "out",
+        value_name = "FILE",
+        requires = "script",
+        help = "Write the script to a file instead of stdout"
+    )]
+    out: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
-pub struct PullCommand {
-    #[arg(value_name = "ID", help = "Note ID")]
-    id: String,
+pub struct PatternsCommand {
+    #[arg(
+        short = 'n',
+        long,
+        value_name = "N",
+        default_value_t = 10,
+        help = "Number of patterns to show"
+    )]
+    limit: usize,
 
-    #[arg(long, value_name = "URL", help = "Remote URL")]
-    remote: Option<String>,
+    #[arg(
+        long,
+        value_name = "DAYS",
+        default_value_t = 7,
+        help = "Trend window: compare the last DAYS days with the DAYS before"
+    )]
+    days: u64,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
 }
 
 #[derive(Debug, Args)]
-pub struct SyncCommand {
-    #[arg(value_name = "ID", help = "Note ID (optional with --all)")]
-    id: Option<String>,
+pub struct ReportCommand {
+    #[arg(
+        long,
+        value_name = "WHEN",
+        default_value = "7d",
+        help = "Start of the period, or the whole period (e.g., '7d', 'monday', 'last week', '2024-05-01..2024-05-15')"
+    )]
+    since: String,
 
-    #[arg(long, value_name = "URL", help = "Remote URL")]
-    remote: Option<String>,
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "End of the period (default: now, or the end of the --since range)"
+    )]
+    until: Option<String>,
 
-    #[arg(long, help = "Sync all notes")]
-    all: bool,
+    #[arg(long, conflicts_with = "html", help = "Output as Markdown")]
+    markdown: bool,
 
-    #[arg(long, help = "Enable auto-sync")]
-    enable_auto: bool,
+    #[arg(long, help = "Output as a standalone HTML page")]
+    html: bool,
 
-    #[arg(long, help = "Disable auto-sync")]
-    disable_auto: bool,
-}
+    #[arg(
+        long,
+        conflicts_with_all = ["markdown", "html"],
+        help = "Send the report by email through the [smtp] config"
+    )]
+    email: bool,
 
-#[derive(Debug, Subcommand)]
-pub enum ConfigCommand {
-    /// Show current configuration
-    Show,
-    /// Configure remote URL
-    Remote(RemoteCommand),
-    /// Manage redaction rules
-    Redact(RedactCommand),
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        requires = "email",
+        help = "Send to ADDRESS instead of [smtp] to (repeatable)"
+    )]
+    to: Vec<String>,
 }
 
 #[derive(Debug, Args)]
-pub struct RemoteCommand {
-    #[arg(long, value_name = "URL", help = "Set remote URL")]
-    set: Option<String>,
+pub struct HistoryCommand {
+    #[arg(long, help = "Only show commands that exited with a non-zero status")]
+    failed: bool,
 
-    #[arg(long, help = "Clear remote URL")]
-    clear: bool,
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Only show commands since WHEN (e.g., '30m', '2h', 'today 9am')"
+    )]
+    since: Option<String>,
 
-    #[arg(long, help = "Apply globally")]
-    global: bool,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Only show commands that ran at least DURATION (e.g., '30s', '1m')"
+    )]
+    slower_than: Option<String>,
+
+    #[arg(
+        short = 'n',
+        long,
+        value_name = "N",
+        default_value_t = 50,
+        help = "Maximum number of commands to show (most recent)"
+    )]
+    limit: usize,
+
+    #[arg(long, help = "Output as JSON lines")]
+    json: bool,
 }
 
 #[derive(Debug, Args)]
-pub struct RedactCommand {
+pub struct AuditCommand {
     #[arg(
-        long = "set",
-        value_name = "NAME=REGEX",
-        action = ArgAction::Append,
-        help = "Add redaction rule"
+        long,
+        value_name = "WHEN",
+        help = "Only show changes since WHEN (e.g., '7d', '2024-05-01')"
     )]
-    set: Vec<String>,
+    since: Option<String>,
 
     #[arg(
-        long = "unset",
-        value_name = "NAME",
+        long,
+        value_enum,
+        value_name = "ACTION",
         action = ArgAction::Append,
-        help = "Remove redaction rule"
+        help = "Only show this kind of change (can be used multiple times)"
     )]
-    unset: Vec<String>,
+    action: Vec<AuditAction>,
+
+    #[arg(long, value_name = "ID", help = "Only show changes to this note")]
+    id: Option<String>,
+
+    #[arg(
+        short = 'n',
+        long,
+        value_name = "N",
+        default_value_t = 50,
+        help = "Maximum number of entries to show (most recent)"
+    )]
+    limit: usize,
 }
 
 #[derive(Debug, Args)]
-pub struct DaemonCommand {
-    #[arg(long, help = "Show daemon status and information")]
-    status: bool,
+pub struct GcCommand {
+    #[arg(long, help = "Remove loose objects")]
+    prune: bool,
 
-    #[arg(long, help = "Stop the daemon")]
-    stop: bool,
+    #[arg(
+        long,
+        help = "Only pack (and prune) when loose objects exceed the [gc] thresholds"
+    )]
+    auto: bool,
+}
 
-    #[arg(long, help = "Run daemon in foreground (for debugging)")]
-    foreground: bool,
+#[derive(Debug, Args)]
+pub struct MigrateCommand {
+    #[arg(
+        long,
+        value_name = "VERSION",
+        help = "Target format version (default: latest)"
+    )]
+    to: Option<u32>,
 
-    #[arg(long, help = "Install shell hooks")]
-    install_hooks: bool,
+    #[arg(long, help = "List pending migrations without running them")]
+    dry_run: bool,
 
-    #[arg(long, help = "Uninstall shell hooks")]
-    uninstall_hooks: bool,
+    #[arg(long, help = "Skip the backup under .fukura/backups")]
+    no_backup: bool,
+}
 
-    #[arg(long, help = "Check shell hooks status")]
-    hooks_status: bool,
+#[derive(Debug, Args)]
+pub struct DedupeCommand {
+    #[arg(
+        long,
+        help = "Merge the proposed clusters instead of only listing them"
+    )]
+    apply: bool,
 
-    #[arg(long, help = "Enable error notifications")]
-    notifications_enable: bool,
+    #[arg(
+        long,
+        value_name = "0.0-1.0",
+        default_value_t = crate::dedupe::DEFAULT_THRESHOLD,
+        help = "Minimum similarity for two notes to count as duplicates"
+    )]
+    threshold: f64,
 
-    #[arg(long, help = "Disable error notifications")]
-    notifications_disable: bool,
+    #[arg(long, help = "Consider all notes, not only auto-captured ones")]
+    all: bool,
+}
 
-    #[arg(long, help = "Check notification status")]
-    notifications_status: bool,
+#[derive(Debug, Args)]
+pub struct DiffCommand {
+    #[arg(value_name = "A", help = "Note ID or reference (@latest, @1, ...)")]
+    a: String,
 
-    #[arg(long, help = "Test notifications (send test notification)")]
-    test_notification: bool,
+    #[arg(
+        value_name = "B",
+        help = "Note to compare with; without it, A is compared with the revision it was edited from"
+    )]
+    b: Option<String>,
+}
 
-    #[arg(long, hide = true)]
-    background: bool,
+#[derive(Debug, Args)]
+pub struct MergeCommand {
+    #[arg(
+        value_name = "ID",
+        num_args = 2..,
+        required = true,
+        help = "Note to keep, then the notes to merge into it"
+    )]
+    ids: Vec<String>,
 
-    #[arg(long, hide = true)]
-    record_command: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CHOICE",
+        help = "Resolve differing sections without prompting (kept/absorbed/both)"
+    )]
+    conflicts: Option<SectionChoice>,
 
-    #[arg(long, hide = true)]
-    record_error: Option<String>,
+    #[arg(long, help = "Show the merged note without storing it")]
+    dry_run: bool,
+}
 
-    #[arg(long, hide = true)]
-    check_solutions: bool,
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("outcome").required(true).args(["worked", "didnt"])))]
+pub struct FeedbackCommand {
+    #[arg(value_name = "ID", help = "Note ID or reference (@latest, @1, ...)")]
+    id: String,
+
+    #[arg(long, help = "The note's solution fixed the error")]
+    worked: bool,
+
+    #[arg(long, help = "The note's solution did not help")]
+    didnt: bool,
 }
 
-pub async fn run() -> Result<()> {
-    let cli = Cli::parse();
-    match &cli.command {
-        Commands::Init(cmd) => handle_init(&cli, cmd)?,
-        Commands::Add(cmd) => handle_add(&cli, cmd).await?,
-        Commands::Search(cmd) => handle_search(&cli, cmd)?,
-        Commands::List => handle_list(&cli)?,
-        Commands::View(cmd) => handle_view(&cli, cmd)?,
-        Commands::Edit(cmd) => handle_edit(&cli, cmd)?,
-        Commands::Open(cmd) => handle_open(&cli, cmd)?,
-        Commands::Serve(cmd) => handle_serve(&cli, cmd).await?,
-        Commands::Stats => handle_stats(&cli)?,
-        Commands::Completions(cmd) => handle_completions(&cli, cmd)?,
-        Commands::Alias(cmd) => handle_alias(&cli, cmd)?,
-        Commands::Import(cmd) => handle_import(&cli, cmd).await?,
-        Commands::Rec(cmd) => {
-            if cmd.from_time.is_some() {
-                // Time-based recording requires async
-                handle_rec_time_based(&cli, cmd).await?;
-            } else {
-                handle_rec(&cli, cmd)?;
-            }
-        }
-        Commands::Done => handle_done(&cli)?,
-        Commands::Gc(cmd) => handle_gc(&cli, cmd)?,
-        Commands::Push(cmd) => handle_push(&cli, cmd).await?,
-        Commands::Pull(cmd) => handle_pull(&cli, cmd).await?,
-        Commands::Sync(cmd) => handle_sync(&cli, cmd).await?,
-        Commands::Config { command } => handle_config(&cli, command)?,
-        Commands::Start => handle_start(&cli).await?,
-        Commands::Stop => handle_stop(&cli).await?,
-        Commands::Status => handle_status(&cli).await?,
-        Commands::Restart => handle_restart(&cli).await?,
-        Commands::Daemon(cmd) => handle_daemon(&cli, cmd).await?,
-        Commands::Activity(cmd) => handle_activity(&cli, cmd).await?,
-        Commands::Log(cmd) => handle_log(&cli, cmd).await?,
-        Commands::Show(cmd) => handle_show_activity(&cli, cmd).await?,
-        Commands::Track(cmd) => handle_track(&cli, cmd).await?,
-    }
-    Ok(())
+#[derive(Debug, Args)]
+pub struct ExplainCommand {
+    #[arg(long, help = "Also search the remote hub")]
+    remote: bool,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 5,
+        value_name = "N",
+        help = "Show at most this many matches"
+    )]
+    limit: usize,
 }
 
-fn handle_init(cli: &Cli, cmd: &InitCommand) -> Result<()> {
-    let path = if cmd.path == Path::new(".") {
-        std::env::current_dir()?
-    } else {
-        cmd.path.clone()
-    };
-    let repo = FukuraRepo::init(&path, cmd.force)?;
+#[derive(Debug, Args)]
+pub struct PromptStatusCommand {
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Custom output with {daemon} (up/down), {recording} (names) and {unsynced} (count)"
+    )]
+    format: Option<String>,
+}
 
-    if !cli.quiet {
-        println!(
-            "{} Initialized Fukura vault at {}",
-            "✓".bold().cyan(),
-            repo.root().display()
-        );
-        println!();
-    }
+#[derive(Debug, Args)]
+pub struct CaptureCommand {
+    #[arg(long, required = true, help = "Read the scrollback of a tmux pane")]
+    tmux: bool,
 
-    // Interactive setup for daemon (unless --no-daemon is specified)
-    let start_daemon = if cmd.no_daemon {
-        false
-    } else if cli.quiet {
-        true // Default to true in quiet mode
-    } else {
-        // Ask user interactively
-        println!(
-            "{} Fukura can automatically capture errors and solutions in the background.",
-            "💡".cyan()
-        );
-        println!(
-            "{} This helps build your knowledge base without manual effort.",
-            "  ".dimmed()
-        );
-        dialoguer::Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enable automatic error capture daemon?")
-            .default(true)
-            .interact()?
-    };
+    #[arg(
+        long,
+        value_name = "TARGET",
+        help = "tmux pane to read, e.g. 'work:1.0' (default: the current pane)"
+    )]
+    pane: Option<String>,
 
-    if start_daemon && !cmd.no_daemon {
-        use crate::daemon_service::DaemonService;
-        let daemon_service = DaemonService::new(repo.root());
-        if let Err(e) = daemon_service.start_background() {
-            if !cli.quiet {
-                println!("{} Failed to start daemon: {}", "⚠️".yellow(), e);
-                println!("{} You can start it later with: fuku start", "💡".cyan());
-            }
-        } else if !cli.quiet {
-            println!("{} Background daemon started", "✓".green());
-        }
-    }
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2000,
+        help = "Lines of history above the visible part to read"
+    )]
+    lines: usize,
 
-    // Setup shell hooks (unless --no-hooks is specified)
-    if !cmd.no_hooks && !cli.quiet {
-        println!();
-        println!(
-            "{} For best experience, add to your shell profile:",
-            "💡".cyan()
-        );
-        println!();
-        println!("  # For Bash/Zsh:");
-        println!("  eval \"$(fuku alias --setup)\"");
-        println!();
-        println!("  # Or run: fuku alias --setup");
-    }
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "note",
+        help = "Recording to attach to (default: the one 'fuku done' would finish)"
+    )]
+    name: Option<String>,
 
-    Ok(())
+    #[arg(long, help = "Create a note even while a recording is in progress")]
+    note: bool,
+
+    #[arg(long, value_name = "TITLE", help = "Title of the note")]
+    title: Option<String>,
 }
 
-async fn handle_add(cli: &Cli, cmd: &AddCommand) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let now = chrono::Utc::now();
+#[derive(Debug, Args)]
+pub struct LastErrorCommand {
+    #[arg(long, help = "Save the failure as a note, as the daemon captures it")]
+    note: bool,
 
-    // Quick mode: interactive prompts
-    if cmd.quick {
-        let title: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("📝 Title")
-            .interact_text()?;
+    #[arg(long, help = "Look in every directory, not just the current one")]
+    anywhere: bool,
+}
 
-        let body: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("📄 Body (short description)")
-            .interact_text()?;
+#[derive(Debug, Args)]
+pub struct CiCommand {
+    #[arg(
+        long,
+        default_value_t = 10,
+        value_name = "N",
+        help = "Annotate at most this many distinct errors"
+    )]
+    max_errors: usize,
 
-        if title.trim().is_empty() {
-            bail!("Title cannot be empty");
-        }
-        if body.trim().is_empty() {
-            bail!("Body cannot be empty");
-        }
+    #[arg(
+        long,
+        help = "Save the failure, its errors and suggested fixes as a note"
+    )]
+    archive: bool,
 
-        let tags = normalize_tags(cmd.tags.clone());
-        let meta = parse_meta(cmd.meta.clone())?;
-        let author = resolve_author(cmd.author.as_deref(), cmd.email.as_deref());
-
-        let note = Note {
-            title: title.trim().to_string(),
-            body: body.trim().to_string(),
-            tags,
-            links: cmd.links.clone(),
-            meta,
-            solutions: vec![],
-            privacy: cmd.privacy.clone(),
-            created_at: now,
-            updated_at: now,
-            author,
-        };
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the findings as JSON, e.g. for upload as a build artifact"
+    )]
+    report: Option<PathBuf>,
 
-        let record = repo.store_note(note)?;
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Base URL of a fuku server to link notes to (default: the default remote)"
+    )]
+    link_base: Option<String>,
+}
 
-        if !cli.quiet {
-            let short_id = format_object_id(&record.object_id);
-            println!(
-                "{} Captured {} ({})",
-                "✓".green(),
-                record.note.title.bold(),
-                short_id
-            );
-            if !record.note.tags.is_empty() {
-                println!("  #{}", record.note.tags.join(" #"));
-            }
-            println!("💡 Quick view: fuku view @latest");
-        }
-        return Ok(());
-    }
+#[derive(Debug, Args)]
+pub struct PinCommand {
+    #[arg(value_name = "ID", help = "Note ID or reference (@latest, @1, ...)")]
+    id: String,
 
-    let title = match &cmd.title {
-        Some(t) => t.clone(),
-        None => Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Title")
+    #[arg(long, help = "Unpin the note instead")]
+    remove: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ArchiveCommand {
+    #[arg(
+        value_name = "ID",
+        required_unless_present = "query",
+        help = "Note IDs or references (@latest, @1, ...)"
+    )]
+    ids: Vec<String>,
+
+    #[arg(
+        long,
+        short = 'q',
+        value_name = "QUERY",
+        conflicts_with = "ids",
+        help = "Archive every note matching QUERY; tag:NAME, before:WHEN and after:WHEN narrow it (e.g. \"tag:auto-captured before:6mo\")"
+    )]
+    query: Option<String>,
+
+    #[arg(long, help = "Show what would be archived without archiving it")]
+    dry_run: bool,
+
+    #[arg(long, help = "Move the notes back out of the archive instead")]
+    restore: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BulkCommand {
+    #[arg(
+        long,
+        short = 'q',
+        value_name = "QUERY",
+        help = "Notes to edit; tag:NAME, before:WHEN and after:WHEN narrow it (e.g. \"tag:auto-captured exit_code:1\")"
+    )]
+    query: String,
+
+    #[command(flatten)]
+    edits: NoteEdits,
+
+    #[arg(long, value_enum, value_name = "PRIVACY", help = "Set privacy")]
+    set_privacy: Option<Privacy>,
+
+    #[arg(long, help = "Show what would change without changing it")]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PushCommand {
+    #[arg(value_name = "ID", help = "Note ID")]
+    id: String,
+
+    #[arg(long, value_name = "URL", help = "Remote URL")]
+    remote: Option<String>,
+
+    #[arg(
+        long,
+        help = "Push even if the note looks like it contains a live secret"
+    )]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PullCommand {
+    #[arg(value_name = "ID", help = "Note ID")]
+    id: String,
+
+    #[arg(long, value_name = "URL", help = "Remote URL")]
+    remote: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ShareCommand {
+    #[arg(
+        value_name = "ID",
+        required_unless_present = "list",
+        help = "Note ID; with --revoke, a share's ID or URL also works"
+    )]
+    id: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Take the copy down after this long, e.g. 1h or 7d (hubs only)"
+    )]
+    expires: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Share through this hub instead of [share] endpoint or the default remote"
+    )]
+    remote: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["expires", "remote", "list"],
+        help = "Take down the shared copies of a note, or one share"
+    )]
+    revoke: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["expires", "remote", "force"],
+        help = "List the notes shared from this repository"
+    )]
+    list: bool,
+
+    #[arg(
+        long,
+        help = "Share even if the note looks like it contains a live secret"
+    )]
+    force: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["revoke", "list"],
+        help = "Also print the link as a QR code, to open it on a phone"
+    )]
+    qr: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncCommand {
+    #[arg(value_name = "ID", help = "Note ID (optional with --all)")]
+    id: Option<String>,
+
+    #[arg(long, value_name = "URL", help = "Remote URL")]
+    remote: Option<String>,
+
+    #[arg(long, help = "Sync all notes")]
+    all: bool,
+
+    #[arg(long, help = "Enable auto-sync")]
+    enable_auto: bool,
+
+    #[arg(long, help = "Disable auto-sync")]
+    disable_auto: bool,
+
+    #[arg(
+        long,
+        help = "Push even if the note looks like it contains a live secret"
+    )]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CloneCommand {
+    #[arg(value_name = "URL", help = "Remote to clone")]
+    remote: String,
+
+    #[arg(
+        value_name = "DIR",
+        help = "Directory to clone into (default: current directory)"
+    )]
+    dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Clone refs and search metadata only; notes are fetched on first view"
+    )]
+    thin: bool,
+
+    #[arg(
+        long,
+        value_name = "MB",
+        requires = "thin",
+        help = "Fetched notes kept before the least recently used are dropped (default: 256)"
+    )]
+    cache_limit_mb: Option<u64>,
+
+    #[arg(
+        long = "tag",
+        value_name = "TAG",
+        action = ArgAction::Append,
+        help = "Only clone notes with this tag (can be used multiple times)"
+    )]
+    tags: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Only clone notes updated since WHEN (e.g., '30d', '2024-05-01')"
+    )]
+    since: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IngestSource {
+    /// Tail a container's logs, or watch for containers that exit with an error
+    Docker(DockerIngestCommand),
+    /// Watch warning events and crash looping pods, grouped by workload
+    K8s(K8sIngestCommand),
+    /// Follow journald, or a syslog file, for errors logged by services
+    Journal(JournalIngestCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct DockerIngestCommand {
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Container to tail (default: watch all containers for failed exits)"
+    )]
+    container: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SINCE",
+        requires = "container",
+        help = "Start from logs this recent (e.g. 10m, or a timestamp)"
+    )]
+    since: Option<String>,
+
+    #[arg(
+        long,
+        requires = "container",
+        help = "Read the existing logs and exit instead of following"
+    )]
+    no_follow: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct K8sIngestCommand {
+    #[arg(
+        long,
+        short = 'n',
+        value_name = "NAMESPACE",
+        help = "Namespace to watch (default: the current context's namespace)"
+    )]
+    namespace: Option<String>,
+
+    #[arg(long, help = "Capture the current events and exit instead of watching")]
+    no_watch: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct JournalIngestCommand {
+    #[arg(
+        long,
+        short = 'u',
+        value_name = "UNIT",
+        conflicts_with = "file",
+        help = "systemd unit to follow; repeat for several (default: all units)"
+    )]
+    unit: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Follow a syslog file (e.g. /var/log/syslog) instead of journald"
+    )]
+    file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "SINCE",
+        conflicts_with = "file",
+        help = "Start from entries this recent, in journalctl syntax (e.g. \"-1h\", \"today\")"
+    )]
+    since: Option<String>,
+
+    #[arg(long, help = "Read the existing entries and exit instead of following")]
+    no_follow: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReposCommand {
+    /// Show registered repositories
+    List {
+        #[arg(long, help = "Output as JSON")]
+        json: bool,
+    },
+    /// Register a repository
+    Add {
+        #[arg(
+            value_name = "PATH",
+            default_value = ".",
+            help = "Repository or a directory inside it"
+        )]
+        path: PathBuf,
+    },
+    /// Forget a repository (its notes are left alone)
+    Remove {
+        #[arg(value_name = "PATH", help = "Registered repository path")]
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// Make a signing key; notes you store from then on are signed
+    Generate {
+        #[arg(long, help = "Replace an existing key")]
+        force: bool,
+    },
+    /// Print your public key for teammates to trust
+    Show,
+    /// Trust a teammate's public key, so notes they signed show as verified
+    Trust {
+        #[arg(value_name = "NAME", help = "Who the key belongs to")]
+        name: String,
+        #[arg(
+            value_name = "PUBLIC_KEY",
+            help = "Hex public key from 'fuku keys show'"
+        )]
+        public_key: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TagCommand {
+    /// Show every tag with its note count and when a note carrying it was
+    /// last updated
+    List {
+        #[arg(long, short = 'n', help = "Max tags")]
+        limit: Option<usize>,
+        #[arg(value_enum, long, short = 's', default_value_t = TagSort::Count, help = "Sort by (count/recent/name)")]
+        sort: TagSort,
+    },
+}
+
+/// Order of `fuku tag list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TagSort {
+    /// Most used first
+    Count,
+    /// Most recently used first
+    Recent,
+    /// Alphabetical
+    Name,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Show current configuration
+    Show,
+    /// Configure remote URL
+    Remote(RemoteCommand),
+    /// Manage redaction rules
+    Redact(RedactCommand),
+    /// Show or set how notes are tokenized for search
+    Analyzer(AnalyzerCommand),
+    /// Open the config in $EDITOR and check it before saving
+    Edit(ConfigFileCommand),
+    /// Report unknown keys, bad regexes and invalid URLs in the config
+    Check(ConfigFileCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigFileCommand {
+    #[arg(long, help = "The global config instead of the repository's")]
+    global: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzerCommand {
+    #[arg(
+        value_enum,
+        help = "Analyzer to use; 'cjk' makes Chinese, Japanese and Korean notes searchable"
+    )]
+    analyzer: Option<Analyzer>,
+}
+
+#[derive(Debug, Args)]
+pub struct RemoteCommand {
+    #[arg(long, value_name = "URL", help = "Set remote URL")]
+    set: Option<String>,
+
+    #[arg(long, help = "Clear remote URL")]
+    clear: bool,
+
+    #[arg(long, help = "Apply globally")]
+    global: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RedactCommand {
+    #[arg(
+        long = "set",
+        value_name = "NAME=REGEX",
+        action = ArgAction::Append,
+        help = "Add redaction rule"
+    )]
+    set: Vec<String>,
+
+    #[arg(
+        long = "unset",
+        value_name = "NAME",
+        action = ArgAction::Append,
+        help = "Remove redaction rule"
+    )]
+    unset: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusCommand {
+    #[arg(long, help = "Print the full status snapshot as JSON")]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DaemonCommand {
+    #[arg(long, help = "Show daemon status and information")]
+    status: bool,
+
+    #[arg(long, help = "Stop the daemon")]
+    stop: bool,
+
+    #[arg(long, help = "Run daemon in foreground (for debugging)")]
+    foreground: bool,
+
+    #[arg(long, help = "Install shell hooks")]
+    install_hooks: bool,
+
+    #[arg(long, help = "Uninstall shell hooks")]
+    uninstall_hooks: bool,
+
+    #[arg(long, help = "Check shell hooks status")]
+    hooks_status: bool,
+
+    #[arg(long, help = "Regenerate shell hooks installed by an older version")]
+    update_hooks: bool,
+
+    #[arg(long, help = "Enable error notifications")]
+    notifications_enable: bool,
+
+    #[arg(long, help = "Disable error notifications")]
+    notifications_disable: bool,
+
+    #[arg(long, help = "Check notification status")]
+    notifications_status: bool,
+
+    #[arg(long, help = "Test notifications (send test notification)")]
+    test_notification: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Set what the notification \"View note\" button does (open, serve, none)"
+    )]
+    notification_click: Option<crate::notification::ClickAction>,
+
+    #[arg(long, help = "Show the daemon log (.fukura/logs/daemon.log)")]
+    logs: bool,
+
+    #[arg(
+        short = 'f',
+        long,
+        requires = "logs",
+        help = "Keep printing new log lines as they are written"
+    )]
+    follow: bool,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 50,
+        requires = "logs",
+        help = "Number of log lines to show"
+    )]
+    lines: usize,
+
+    #[arg(long, hide = true)]
+    background: bool,
+
+    #[arg(long, hide = true)]
+    record_command: Option<String>,
+
+    #[arg(long, hide = true)]
+    record_error: Option<String>,
+
+    #[arg(long, hide = true)]
+    check_solutions: bool,
+}
+
+pub async fn run() -> Result<()> {
+    let cli = Cli::parse();
+    if let Some(profile) = &cli.profile {
+        // Through the environment so config loads everywhere, and the
+        // daemon this starts, see it
+        std::env::set_var(crate::config::PROFILE_ENV, profile);
+    }
+    if cli.plain || crate::output::plain_from_env() {
+        crate::output::set_plain(true);
+    }
+    if cli.no_auto_reindex {
+        std::env::set_var(crate::repo::NO_AUTO_REINDEX_ENV, "1");
+    }
+    if cli.read_only {
+        std::env::set_var(crate::repo::READ_ONLY_ENV, "1");
+    }
+    match &cli.command {
+        Commands::Init(cmd) => handle_init(&cli, cmd)?,
+        Commands::Add(cmd) => handle_add(&cli, cmd).await?,
+        Commands::Search(cmd) => handle_search(&cli, cmd)?,
+        Commands::List(cmd) => handle_list(&cli, cmd)?,
+        Commands::Pin(cmd) => handle_pin(&cli, cmd)?,
+        Commands::Pins => handle_pins(&cli)?,
+        Commands::Archive(cmd) => handle_archive(&cli, cmd)?,
+        Commands::Bulk(cmd) => handle_bulk(&cli, cmd)?,
+        Commands::Grep(cmd) => handle_grep(&cli, cmd)?,
+        Commands::Pick(cmd) => handle_pick(&cli, cmd)?,
+        Commands::View(cmd) => handle_view(&cli, cmd)?,
+        Commands::Edit(cmd) => handle_edit(&cli, cmd)?,
+        Commands::Rename(cmd) => handle_rename(&cli, cmd)?,
+        Commands::Summarize(cmd) => handle_summarize(&cli, cmd).await?,
+        Commands::Open(cmd) => handle_open(&cli, cmd)?,
+        Commands::Serve(cmd) => handle_serve(&cli, cmd).await?,
+        Commands::Lsp => handle_lsp(&cli)?,
+        Commands::Ci(cmd) => handle_ci(&cli, cmd)?,
+        Commands::Explain(cmd) => handle_explain(&cli, cmd).await?,
+        Commands::LastError(cmd) => handle_last_error(&cli, cmd)?,
+        Commands::Capture(cmd) => handle_capture(&cli, cmd)?,
+        Commands::Stats => handle_stats(&cli)?,
+        Commands::Completions(cmd) => handle_completions(&cli, cmd)?,
+        Commands::Complete(cmd) => handle_complete(cmd),
+        Commands::Alias(cmd) => handle_alias(&cli, cmd)?,
+        Commands::Import(cmd) => handle_import(&cli, cmd).await?,
+        Commands::Rec(cmd) => {
+            if cmd.from_time.is_some() {
+                // Time-based recording requires async
+                handle_rec_time_based(&cli, cmd).await?;
+            } else {
+                handle_rec(&cli, cmd).await?;
+            }
+        }
+        Commands::Done(cmd) => handle_done(&cli, cmd.name.as_deref(), cmd.diffs).await?,
+        Commands::Replay(cmd) => handle_replay(&cli, cmd)?,
+        Commands::History(cmd) => handle_history(&cli, cmd)?,
+        Commands::Patterns(cmd) => handle_patterns(&cli, cmd)?,
+        Commands::Report(cmd) => handle_report(&cli, cmd)?,
+        Commands::Audit(cmd) => handle_audit(&cli, cmd)?,
+        Commands::Gc(cmd) => handle_gc(&cli, cmd)?,
+        Commands::Migrate(cmd) => handle_migrate(&cli, cmd)?,
+        Commands::Dedupe(cmd) => handle_dedupe(&cli, cmd)?,
+        Commands::Diff(cmd) => handle_diff(&cli, cmd)?,
+        Commands::Merge(cmd) => handle_merge(&cli, cmd)?,
+        Commands::Feedback(cmd) => handle_feedback(&cli, cmd)?,
+        Commands::Push(cmd) => handle_push(&cli, cmd).await?,
+        Commands::Pull(cmd) => handle_pull(&cli, cmd).await?,
+        Commands::Share(cmd) => handle_share(&cli, cmd).await?,
+        Commands::Sync(cmd) => handle_sync(&cli, cmd).await?,
+        Commands::Clone(cmd) => handle_clone(&cli, cmd).await?,
+        Commands::Config { command } => handle_config(&cli, command)?,
+        Commands::Repos { command } => handle_repos(&cli, command)?,
+        Commands::Keys { command } => handle_keys(&cli, command)?,
+        Commands::Tag { command } => handle_tag(&cli, command)?,
+        Commands::Ingest { source } => handle_ingest(&cli, source)?,
+        Commands::Start => handle_start(&cli).await?,
+        Commands::Stop => handle_stop(&cli).await?,
+        Commands::Status(cmd) => handle_status(&cli, cmd)?,
+        Commands::PromptStatus(cmd) => handle_prompt_status(&cli, cmd)?,
+        Commands::Restart => handle_restart(&cli).await?,
+        Commands::Daemon(cmd) => handle_daemon(&cli, cmd).await?,
+        Commands::Activity(cmd) => handle_activity(&cli, cmd).await?,
+        Commands::Log(cmd) => handle_log(&cli, cmd).await?,
+        Commands::Show(cmd) => handle_show_activity(&cli, cmd).await?,
+        Commands::Timeline(cmd) => handle_timeline(&cli, cmd)?,
+        Commands::Track(cmd) => handle_track(&cli, cmd).await?,
+    }
+    Ok(())
+}
+
+/// `fuku init --output json|yaml`: what was set up
+#[derive(Debug, Serialize)]
+struct InitOutput {
+    root: PathBuf,
+    /// False when the repository was already there
+    created: bool,
+    daemon: InitDaemon,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daemon_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum InitDaemon {
+    Started,
+    AlreadyRunning,
+    Off,
+    Failed,
+}
+
+fn handle_init(cli: &Cli, cmd: &InitCommand) -> Result<()> {
+    let path = if cmd.path == Path::new(".") {
+        std::env::current_dir()?
+    } else {
+        cmd.path.clone()
+    };
+    let structured = cli.output.is_structured();
+    // Provisioning scripts (Nix shells, devcontainers) can't answer prompts
+    let interactive = !(cmd.yes || cmd.ensure || cli.quiet || structured);
+    let exists = path.join(".fukura").exists();
+    let repo = if cmd.ensure && exists {
+        // Opening upgrades an older format and fills in missing directories
+        FukuraRepo::open(&path)?
+    } else {
+        FukuraRepo::init(&path, cmd.force)?
+    };
+    // A read-only data directory shouldn't fail the init
+    let _ = registry::register(repo.root());
+    let mut summary = InitOutput {
+        root: repo.root().to_path_buf(),
+        created: !exists || cmd.force,
+        daemon: InitDaemon::Off,
+        daemon_error: None,
+    };
+
+    if !cli.quiet && !structured {
+        if summary.created {
+            println!(
+                "{} Initialized Fukura vault at {}",
+                plain_text("✓").bold().cyan(),
+                repo.root().display()
+            );
+        } else {
+            println!(
+                "{} Fukura vault already set up at {}",
+                plain_text("✓").bold().cyan(),
+                repo.root().display()
+            );
+        }
+        println!();
+    }
+
+    let start_daemon = match (cmd.daemon, cmd.no_daemon) {
+        (Some(setting), _) => setting == DaemonSetting::On,
+        (None, true) => false,
+        (None, false) if !interactive => true,
+        (None, false) => {
+            println!(
+                "{}Fukura can automatically capture errors and solutions in the background.",
+                plain_text("💡 ").cyan()
+            );
+            println!(
+                "{} This helps build your knowledge base without manual effort.",
+                "  ".dimmed()
+            );
+            dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Enable automatic error capture daemon?")
+                .default(true)
+                .interact()?
+        }
+    };
+
+    if start_daemon {
+        use crate::daemon_service::DaemonService;
+        let daemon_service = DaemonService::new(repo.root());
+        summary.daemon = if daemon_service.is_running_sync() {
+            InitDaemon::AlreadyRunning
+        } else if let Err(e) = daemon_service.start_background() {
+            summary.daemon_error = Some(e.to_string());
+            InitDaemon::Failed
+        } else {
+            InitDaemon::Started
+        };
+        if !cli.quiet && !structured {
+            match summary.daemon {
+                InitDaemon::Started => {
+                    println!("{} Background daemon started", plain_text("✓").green())
+                }
+                InitDaemon::AlreadyRunning => {
+                    println!(
+                        "{} Background daemon already running",
+                        plain_text("✓").green()
+                    )
+                }
+                _ => {
+                    println!(
+                        "{} Failed to start daemon: {}",
+                        plain_text("⚠️").yellow(),
+                        summary.daemon_error.as_deref().unwrap_or_default()
+                    );
+                    println!(
+                        "{}You can start it later with: fuku start",
+                        plain_text("💡 ").cyan()
+                    );
+                }
+            }
+        }
+    }
+
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
+
+    // Setup shell hooks (unless --no-hooks is specified)
+    if !cmd.no_hooks && !cli.quiet && summary.created {
+        println!();
+        println!(
+            "{}For best experience, add to your shell profile:",
+            plain_text("💡 ").cyan()
+        );
+        println!();
+        println!("  # For Bash/Zsh:");
+        println!("  eval \"$(fuku alias --setup)\"");
+        println!();
+        println!("  # Or run: fuku alias --setup");
+    }
+
+    Ok(())
+}
+
+async fn handle_add(cli: &Cli, cmd: &AddCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let config = repo.config()?;
+    let privacy = match &cmd.privacy {
+        Some(privacy) => privacy.clone(),
+        None => config.default_privacy.clone().unwrap_or_default(),
+    };
+    let now = chrono::Utc::now();
+    let expires_at = cmd
+        .expires
+        .as_deref()
+        .map(crate::time_parser::parse_duration)
+        .transpose()?
+        .map(|ttl| now + ttl);
+
+    // Quick mode: interactive prompts
+    if cmd.quick {
+        let title: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(plain_text("📝 Title"))
+            .interact_text()?;
+
+        let body: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(plain_text("📄 Body (short description)"))
+            .interact_text()?;
+
+        if title.trim().is_empty() {
+            bail!("Title cannot be empty");
+        }
+        if body.trim().is_empty() {
+            bail!("Body cannot be empty");
+        }
+
+        let meta = parse_meta(cmd.meta.clone())?;
+        validate_meta(&repo, &meta)?;
+        let mut note = NoteBuilder::new(title, body)
+            .tags(cmd.tags.clone())
+            .links(cmd.links.clone())
+            .meta_map(meta)
+            .privacy(privacy.clone())
+            .author(resolve_author(cmd.author.as_deref(), cmd.email.as_deref()))
+            .created_at(now)
+            .expires_at(expires_at)
+            .build();
+        let cwd = std::env::current_dir()?;
+        config.capture.confine(&mut note, &cwd);
+        config.scope.assign(&mut note, repo.root(), &cwd);
+
+        let created = NoteService::new(&repo)?.create(note)?;
+        let record = link_incident_issue(cli, &repo, created.record);
+
+        if !cli.quiet {
+            let short_id = format_object_id(&record.object_id);
+            println!(
+                "{} Captured {} ({})",
+                plain_text("✓").green(),
+                record.note.title.bold(),
+                short_id
+            );
+            if !record.note.tags.is_empty() {
+                println!("  #{}", record.note.tags.join(" #"));
+            }
+            println!("{}Quick view: fuku view @latest", plain_text("💡 "));
+        }
+        return Ok(());
+    }
+
+    let title = match &cmd.title {
+        Some(t) => t.clone(),
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Title")
             .interact_text()?,
     };
 
-    let mut body = if let Some(explicit) = &cmd.body {
-        explicit.clone()
-    } else if let Some(path) = &cmd.file {
-        fs::read_to_string(path)
-            .with_context(|| format!("Failed to read body from {}", path.display()))?
-    } else if cmd.stdin || !is_terminal::is_terminal(std::io::stdin()) {
-        let mut buf = String::new();
-        io::stdin().read_to_string(&mut buf)?;
-        buf
-    } else if cmd.no_editor {
-        // Interactive inline input without external editor
-        get_interactive_body()?
+    let mut body = if let Some(explicit) = &cmd.body {
+        explicit.clone()
+    } else if let Some(path) = &cmd.file {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read body from {}", path.display()))?
+    } else if cmd.stdin || !is_terminal::is_terminal(std::io::stdin()) {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        buf
+    } else if cmd.no_editor {
+        // Interactive inline input without external editor
+        get_interactive_body()?
+    } else {
+        String::new()
+    };
+
+    if body.trim().is_empty() && !cmd.no_editor {
+        // Try interactive input first, fallback to editor if needed
+        if let Ok(interactive_body) = get_interactive_body() {
+            if !interactive_body.trim().is_empty() {
+                body = interactive_body;
+            } else if let Some(buffer) =
+                Editor::new().edit("# jot down the diagnosis, commands, or code snippets here\n")?
+            {
+                body = buffer;
+            }
+        } else if let Some(buffer) =
+            Editor::new().edit("# jot down the diagnosis, commands, or code snippets here\n")?
+        {
+            body = buffer;
+        }
+    }
+
+    if body.trim().is_empty() {
+        bail!("Note body cannot be empty");
+    }
+
+    let meta = parse_meta(cmd.meta.clone())?;
+    validate_meta(&repo, &meta)?;
+    let mut note = NoteBuilder::new(title, body)
+        .tags(cmd.tags.clone())
+        .links(cmd.links.clone())
+        .meta_map(meta)
+        .privacy(privacy.clone())
+        .author(resolve_author(cmd.author.as_deref(), cmd.email.as_deref()))
+        .created_at(now)
+        .expires_at(expires_at)
+        .build();
+    let cwd = std::env::current_dir()?;
+    config.capture.confine(&mut note, &cwd);
+    config.scope.assign(&mut note, repo.root(), &cwd);
+
+    let created = NoteService::new(&repo)?.create(note)?;
+    let record = link_incident_issue(cli, &repo, created.record);
+
+    if !cli.quiet {
+        let short_id = format_object_id(&record.object_id);
+        println!(
+            "{} Captured {} ({})",
+            "".green(),
+            record.note.title.bold(),
+            short_id
+        );
+        if !record.note.tags.is_empty() {
+            println!("{}  #{}", "".dimmed(), record.note.tags.join(" #"));
+        }
+        println!("{}Quick view: fuku view @latest", plain_text("💡 "));
+    }
+
+    // Auto-sync if enabled; notes from a private zone never leave
+    if config.auto_sync.unwrap_or(false) && !record.note.is_local_only() {
+        if let Some(remote) = &config.default_remote {
+            if !cli.quiet {
+                println!("{} Auto-syncing to remote...", "".blue());
+            }
+            match push_note(&repo, &record.object_id, remote, false).await {
+                Ok(remote_id) => {
+                    if !cli.quiet {
+                        println!(
+                            "{} Auto-synced {} {}",
+                            "".green(),
+                            plain_text("→"),
+                            remote_id
+                        );
+                    }
+                }
+                Err(e) => {
+                    if !cli.quiet {
+                        println!("{} Auto-sync failed: {}", "".yellow(), e);
+                        println!(
+                            "{} Use 'fuku sync {}' to retry",
+                            "".cyan(),
+                            record.object_id
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_search(cli: &Cli, cmd: &SearchCommand) -> Result<()> {
+    let query = if cmd.query.is_empty() {
+        String::new()
+    } else {
+        cmd.query.join(" ")
+    };
+
+    if cmd.all_repos {
+        // Search across all local Fukura repositories
+        return search_all_repos(cli, &query, cmd.limit, cmd.sort, cmd.json);
+    }
+
+    // Remote-only search
+    if cmd.remote_only {
+        return tokio::runtime::Runtime::new()?
+            .block_on(async { handle_remote_search(cli, &query, cmd.limit, cmd.json).await });
+    }
+
+    let repo = open_repo(cli)?;
+    if cmd.tui {
+        run_search_tui(&repo, &query, cmd.sort, cmd.limit)?;
+        return Ok(());
+    }
+
+    let instant = |value: &Option<String>| value.as_deref().map(parse_instant).transpose();
+    let filter = SearchFilter {
+        created_after: instant(&cmd.created_after)?,
+        created_before: instant(&cmd.created_before)?,
+        updated_after: instant(&cmd.updated_after)?,
+        updated_before: instant(&cmd.updated_before)?,
+        meta: parse_meta(cmd.meta.clone())?.into_iter().collect(),
+        scope: cmd.scope.clone(),
+        ..SearchFilter::default()
+    };
+    let offset = cmd.page.offset(cmd.limit);
+    let mut page = if cmd.archived {
+        repo.search_archived_page(&query, &filter, offset, cmd.limit, cmd.sort)?
+    } else {
+        repo.search_page(&query, &filter, offset, cmd.limit, cmd.sort)?
+    };
+    repo.mark_verified(&mut page.hits)?;
+    let hits = &page.hits;
+
+    // If --remote flag is set and no local results, search remote
+    if cmd.remote && hits.is_empty() && !query.is_empty() {
+        if !cli.quiet {
+            println!(
+                "{}No local results, searching remote hub...",
+                plain_text("🔍 ").cyan()
+            );
+            println!();
+        }
+        return tokio::runtime::Runtime::new()?
+            .block_on(async { handle_remote_search(cli, &query, cmd.limit, cmd.json).await });
+    }
+
+    if cli.output.or_json(cmd.json).emit(hits)? {
+        return Ok(());
+    }
+    render_search_table(hits);
+    if !cli.quiet {
+        render_page_footer(&page, cmd.limit);
+    }
+    if !hits.is_empty() {
+        let short_id = format_object_id(&hits[0].object_id);
+        println!(
+            "{}Next: fuku view @1 (or fuku open @1 to open in browser)",
+            plain_text("💡 ")
+        );
+        println!("   Copy ID: {}", short_id);
+    } else if !query.is_empty() && !cli.quiet {
+        println!("{} No results found locally", plain_text("ℹ️").blue());
+        println!();
+        println!("{}Try searching the remote hub:", plain_text("💡 "));
+        println!("   fuku search \"{}\" --remote", query);
+    }
+    Ok(())
+}
+
+async fn handle_remote_search(
+    cli: &Cli,
+    query: &str,
+    limit: usize,
+    json_output: bool,
+) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let config_path = repo.root().join(".fukura").join("config.toml");
+    let config = crate::config::FukuraConfig::load_with_global_fallback(&config_path)?;
+
+    let remote_url = config
+        .default_remote
+        .context("No remote configured. Set with: fuku config remote --set <URL>")?;
+
+    let hits = crate::remote_search::search_remote(&remote_url, query, limit).await?;
+
+    if cli.output.or_json(json_output).emit(&hits)? {
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        if !cli.quiet {
+            println!("{} No results found on remote hub", plain_text("ℹ️").blue());
+        }
+        return Ok(());
+    }
+
+    if !cli.quiet {
+        println!(
+            "{} Found {} results from remote hub",
+            plain_text("✓").green(),
+            hits.len()
+        );
+        println!();
+    }
+
+    // Display remote results in a table
+    let mut table = Table::new();
+    table.load_preset(crate::output::table_preset());
+    table.set_header(vec!["#", "Title", "Tags", "Author", "Privacy"]);
+
+    for (i, hit) in hits.iter().enumerate() {
+        let short_id = format_object_id(&hit.object_id);
+        let tags_display = if hit.tags.is_empty() {
+            String::new()
+        } else {
+            format!("#{}", hit.tags.join(" #"))
+        };
+
+        table.add_row(vec![
+            format!("@{}", i + 1),
+            format!("{} ({})", hit.title, short_id),
+            tags_display,
+            hit.author.clone(),
+            hit.privacy.clone(),
+        ]);
+    }
+
+    println!("{}", table);
+
+    if !hits.is_empty() && !cli.quiet {
+        println!();
+        println!("{}To pull a note from remote:", plain_text("💡 "));
+        println!("   fuku pull {}", format_object_id(&hits[0].object_id));
+    }
+
+    Ok(())
+}
+
+fn handle_list(cli: &Cli, cmd: &ListCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let page = repo.list_notes_page(cmd.page.offset(cmd.limit), cmd.limit)?;
+    if cli.output.emit(&page)? {
+        return Ok(());
+    }
+    let hits = &page.hits;
+
+    if hits.is_empty() && page.offset == 0 {
+        println!("{} No notes yet", plain_text("ℹ️").blue());
+        println!();
+        println!("{}Get started:", plain_text("💡 "));
+        println!("  {} Quick add: fuku add -q", plain_text("•"));
+        println!(
+            "  {} Full add:  fuku add --title 'My Note' --body 'Content'",
+            plain_text("•")
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}All Notes ({} total)",
+        plain_text("📋 ").cyan(),
+        page.total
+    );
+    println!();
+    render_search_table(hits);
+    render_page_footer(&page, cmd.limit);
+
+    if !hits.is_empty() {
+        let short_id = format_object_id(&hits[0].object_id);
+        println!();
+        println!("{}Quick access:", plain_text("💡 "));
+        println!("   fuku view @1     # View first note");
+        println!("   fuku edit @1     # Edit first note");
+        println!("   Copy ID: {}", short_id);
+    }
+    Ok(())
+}
+
+fn handle_ingest(cli: &Cli, source: &IngestSource) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let ingestor = Ingestor::new(repo)?;
+    match source {
+        IngestSource::Docker(cmd) => ingest_docker(cli, &ingestor, cmd),
+        IngestSource::K8s(cmd) => ingest_k8s(cli, &ingestor, cmd),
+        IngestSource::Journal(cmd) => ingest_journal(cli, &ingestor, cmd),
+    }
+}
+
+/// Log lines searched for the cause when a container dies
+const CRASH_LOG_TAIL: usize = 100;
+
+fn ingest_docker(cli: &Cli, ingestor: &Ingestor, cmd: &DockerIngestCommand) -> Result<()> {
+    let docker = Docker::new();
+
+    if let Some(container) = &cmd.container {
+        let image = docker.image_of(container)?;
+        if !cli.quiet {
+            println!(
+                "{}Watching logs of {} ({})",
+                plain_text("🐳 ").cyan(),
+                container.bold(),
+                if cmd.no_follow {
+                    "existing lines"
+                } else {
+                    "Ctrl+C to stop"
+                }
+            );
+        }
+        let lines = docker.logs(container, !cmd.no_follow, cmd.since.as_deref(), None)?;
+        let mut scanner = LogScanner::new();
+        let mut count = 0;
+        for line in lines {
+            if let Some(block) = scanner.push(&line) {
+                report_ingested(cli, ingestor, &docker_event(container, &image, block))?;
+                count += 1;
+            }
+        }
+        if let Some(block) = scanner.finish() {
+            report_ingested(cli, ingestor, &docker_event(container, &image, block))?;
+            count += 1;
+        }
+        if !cli.quiet {
+            println!(
+                "{} {} errors captured from {}",
+                plain_text("✓").green(),
+                count,
+                container
+            );
+        }
+        return Ok(());
+    }
+
+    if !cli.quiet {
+        println!(
+            "{}Watching for containers that exit with an error (Ctrl+C to stop)",
+            plain_text("🐳 ").cyan()
+        );
+    }
+    for exit in docker.exits()? {
+        if exit.exit_code == 0 {
+            continue;
+        }
+        // The last lines usually say why it died
+        let mut scanner = LogScanner::new();
+        let mut blocks: Vec<ErrorBlock> = docker
+            .logs(&exit.id, false, None, Some(CRASH_LOG_TAIL))?
+            .filter_map(|line| scanner.push(&line))
+            .collect();
+        blocks.extend(scanner.finish());
+        if blocks.is_empty() {
+            blocks.push(ErrorBlock {
+                message: format!("Container exited with code {}", exit.exit_code),
+                context: Vec::new(),
+            });
+        }
+        for block in blocks {
+            let mut event = docker_event(&exit.name, &exit.image, block);
+            event
+                .meta
+                .insert("exit_code".to_string(), exit.exit_code.to_string());
+            report_ingested(cli, ingestor, &event)?;
+        }
+    }
+    Ok(())
+}
+
+fn docker_event(container: &str, image: &str, block: ErrorBlock) -> IngestEvent {
+    let mut event = IngestEvent::new("docker", container, block);
+    event
+        .tags
+        .push(format!("container:{}", container.to_lowercase()));
+    if !image.is_empty() {
+        event.tags.push(format!("image:{}", image.to_lowercase()));
+        event.meta.insert("image".to_string(), image.to_string());
+    }
+    event
+}
+
+/// Log lines kept in a note about a crashed pod
+const K8S_CONTEXT_LINES: usize = 30;
+
+fn ingest_k8s(cli: &Cli, ingestor: &Ingestor, cmd: &K8sIngestCommand) -> Result<()> {
+    let kube = KubeClient::connect(cmd.namespace.clone())?;
+    let events = kube.events(!cmd.no_watch)?;
+    if !cli.quiet {
+        println!(
+            "{}Watching warning events in {} ({})",
+            plain_text("☸ ").cyan(),
+            cmd.namespace
+                .as_deref()
+                .unwrap_or("the current namespace")
+                .bold(),
+            if cmd.no_watch {
+                "current events"
+            } else {
+                "Ctrl+C to stop"
+            }
+        );
+    }
+
+    // Restart count last captured per container, so every BackOff event of
+    // one crash doesn't count as another occurrence
+    let mut captured_restarts: HashMap<(String, String), u64> = HashMap::new();
+    for event in events.filter(|event| event.warning) {
+        let object = kube.get(&event).ok();
+        let pod = object.as_ref().filter(|_| event.kind == "Pod");
+        let workload = pod.and_then(workload_of).unwrap_or_else(|| Workload {
+            kind: event.kind.clone(),
+            name: event.name.clone(),
+        });
+
+        if let (Some(pod), "BackOff") = (pod, event.reason.as_str()) {
+            for failure in container_failures(pod) {
+                let key = (
+                    format!("{}/{}", event.namespace, event.name),
+                    failure.container.clone(),
+                );
+                if captured_restarts.insert(key, failure.restart_count)
+                    == Some(failure.restart_count)
+                {
+                    continue;
+                }
+                let logs = kube
+                    .previous_logs(
+                        &event.namespace,
+                        &event.name,
+                        &failure.container,
+                        CRASH_LOG_TAIL,
+                    )
+                    .unwrap_or_default();
+                let mut message = format!("{} in container {}", failure.reason, failure.container);
+                if let Some(code) = failure.exit_code {
+                    message.push_str(&format!(" (exit code {})", code));
+                }
+                let block = ErrorBlock {
+                    message,
+                    context: crash_context(&logs),
+                };
+                let snippet = manifest_snippet(pod, Some(failure.container.as_str()));
+                let ingest = k8s_event(&event, &workload, &failure.reason, block, snippet);
+                report_ingested(cli, ingestor, &ingest)?;
+            }
+            continue;
+        }
+
+        let block = ErrorBlock {
+            message: format!(
+                "{}: {}",
+                event.reason,
+                event.message.replace(&event.name, &workload.name)
+            ),
+            context: Vec::new(),
+        };
+        let snippet = object
+            .as_ref()
+            .and_then(|object| manifest_snippet(object, None));
+        let ingest = k8s_event(&event, &workload, &event.reason, block, snippet);
+        report_ingested(cli, ingestor, &ingest)?;
+    }
+    Ok(())
+}
+
+/// Error lines from a crashed container's last logs, or its last lines
+/// when none look like errors
+fn crash_context(logs: &[String]) -> Vec<String> {
+    let mut scanner = LogScanner::new();
+    let mut blocks: Vec<ErrorBlock> = logs.iter().filter_map(|line| scanner.push(line)).collect();
+    blocks.extend(scanner.finish());
+    let lines: Vec<String> = if blocks.is_empty() {
+        logs.to_vec()
+    } else {
+        blocks
+            .into_iter()
+            .flat_map(|block| std::iter::once(block.message).chain(block.context))
+            .collect()
+    };
+    let skip = lines.len().saturating_sub(K8S_CONTEXT_LINES);
+    lines.into_iter().skip(skip).collect()
+}
+
+fn k8s_event(
+    event: &KubeEvent,
+    workload: &Workload,
+    reason: &str,
+    block: ErrorBlock,
+    snippet: Option<String>,
+) -> IngestEvent {
+    let subject = format!("{}/{}", event.namespace, workload);
+    let mut ingest = IngestEvent::new("k8s", &subject, block);
+    ingest.tags.push(format!("namespace:{}", event.namespace));
+    ingest.tags.push(format!(
+        "{}:{}",
+        workload.kind.to_lowercase(),
+        workload.name.to_lowercase()
+    ));
+    ingest.tags.extend(
+        reason
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase),
+    );
+    ingest
+        .meta
+        .insert("namespace".to_string(), event.namespace.clone());
+    ingest
+        .meta
+        .insert("workload".to_string(), workload.to_string());
+    ingest.meta.insert(
+        "object".to_string(),
+        format!("{}/{}", event.kind, event.name),
+    );
+    ingest.details = snippet.map(|snippet| {
+        format!(
+            "**Manifest** (`{}/{}`):\n\n```json\n{}\n```",
+            event.kind.to_lowercase(),
+            event.name,
+            snippet
+        )
+    });
+    ingest
+}
+
+fn ingest_journal(cli: &Cli, ingestor: &Ingestor, cmd: &JournalIngestCommand) -> Result<()> {
+    let journal = Journal::new();
+    let what = match (&cmd.file, cmd.unit.is_empty()) {
+        (Some(path), _) => path.display().to_string(),
+        (None, true) => "the journal".to_string(),
+        (None, false) => cmd.unit.join(", "),
+    };
+    if !cli.quiet {
+        println!(
+            "{}Following {} ({})",
+            plain_text("📜 ").cyan(),
+            what.bold(),
+            if cmd.no_follow {
+                "existing entries"
+            } else {
+                "Ctrl+C to stop"
+            }
+        );
+    }
+
+    let count = match &cmd.file {
+        Some(path) => ingest_log_entries(
+            cli,
+            ingestor,
+            "syslog",
+            journal.syslog_file(path, !cmd.no_follow)?,
+        )?,
+        None => ingest_log_entries(
+            cli,
+            ingestor,
+            "journal",
+            journal.entries(&cmd.unit, !cmd.no_follow, cmd.since.as_deref())?,
+        )?,
+    };
+    if !cli.quiet {
+        println!(
+            "{} {} errors captured from {}",
+            plain_text("✓").green(),
+            count,
+            what
+        );
+    }
+    Ok(())
+}
+
+/// Scans each unit's lines separately, so interleaved services don't end
+/// up in each other's stack traces
+fn ingest_log_entries(
+    cli: &Cli,
+    ingestor: &Ingestor,
+    source: &str,
+    entries: impl Iterator<Item = LogEntry>,
+) -> Result<usize> {
+    let mut scanners: HashMap<String, LogScanner> = HashMap::new();
+    let mut count = 0;
+    for entry in entries {
+        let scanner = scanners.entry(entry.unit.clone()).or_default();
+        let mut blocks: Vec<ErrorBlock> = scanner.push(&entry.message).into_iter().collect();
+        // Logged at error priority without saying so
+        if entry.is_severe() && !is_error_line(&entry.message) && !entry.message.trim().is_empty() {
+            blocks.extend(scanner.finish());
+            blocks.push(ErrorBlock {
+                message: entry.message.trim().to_string(),
+                context: Vec::new(),
+            });
+        }
+        for block in blocks {
+            report_ingested(cli, ingestor, &unit_event(source, &entry.unit, block))?;
+            count += 1;
+        }
+    }
+    for (unit, mut scanner) in scanners {
+        if let Some(block) = scanner.finish() {
+            report_ingested(cli, ingestor, &unit_event(source, &unit, block))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn unit_event(source: &str, unit: &str, block: ErrorBlock) -> IngestEvent {
+    let mut event = IngestEvent::new(source, unit, block);
+    event.tags.push(format!("unit:{}", unit.to_lowercase()));
+    event.meta.insert("unit".to_string(), unit.to_string());
+    event
+}
+
+fn report_ingested(cli: &Cli, ingestor: &Ingestor, event: &IngestEvent) -> Result<()> {
+    let outcome = ingestor.ingest(event)?;
+    if !cli.quiet {
+        let (marker, verb) = match outcome {
+            IngestOutcome::Created(_) => (plain_text("✓").green(), "Captured"),
+            IngestOutcome::Updated(_) => (plain_text("↻").yellow(), "Seen again"),
+        };
+        println!(
+            "  {} {} [{}] {} ({})",
+            marker,
+            verb,
+            event.subject.cyan(),
+            event.message,
+            format_object_id(outcome.note_id())
+        );
+    }
+    Ok(())
+}
+
+fn handle_pin(cli: &Cli, cmd: &PinCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let record = repo.load_note(&resolved)?;
+    let changed = if cmd.remove {
+        repo.unpin(&resolved)?
+    } else {
+        repo.pin(&resolved)?
+    };
+
+    if !cli.quiet {
+        let short_id = format_object_id(&resolved);
+        match (cmd.remove, changed) {
+            (false, true) => println!(
+                "{}Pinned {} ({})",
+                plain_text("📌 ").green(),
+                record.note.title.bold(),
+                short_id
+            ),
+            (false, false) => println!(
+                "{} {} ({}) is already pinned",
+                plain_text("ℹ️").blue(),
+                record.note.title.bold(),
+                short_id
+            ),
+            (true, true) => println!(
+                "{} Unpinned {} ({})",
+                plain_text("✓").green(),
+                record.note.title.bold(),
+                short_id
+            ),
+            (true, false) => println!(
+                "{} {} ({}) is not pinned",
+                plain_text("ℹ️").blue(),
+                record.note.title.bold(),
+                short_id
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn handle_pins(cli: &Cli) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let hits = repo.pinned_notes()?;
+    if cli.output.emit(&hits)? {
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        if !cli.quiet {
+            println!("{} No pinned notes", plain_text("ℹ️").blue());
+            println!();
+            println!("{}Pin a runbook: fuku pin @1", plain_text("💡 "));
+        }
+        return Ok(());
+    }
+
+    println!("{}Pinned Notes ({})", plain_text("📌 ").cyan(), hits.len());
+    println!();
+    render_search_table(&hits);
+    Ok(())
+}
+
+/// `fuku archive --output json|yaml`
+#[derive(Debug, Serialize)]
+struct ArchiveOutput {
+    /// Notes archived, or restored with `--restore`
+    notes: Vec<SearchHit>,
+    restored: bool,
+    dry_run: bool,
+}
+
+fn handle_archive(cli: &Cli, cmd: &ArchiveCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let mut records = match &cmd.query {
+        Some(query) => notes_matching(&repo, query, cmd.restore)?
+            .iter()
+            .map(|id| repo.load_note(id))
+            .collect::<Result<Vec<_>>>()?,
+        None => cmd
+            .ids
+            .iter()
+            .map(|id| repo.load_note(&repo.resolve_object_id(id)?))
+            .collect::<Result<Vec<_>>>()?,
+    };
+    // Skip notes already where they're going
+    let archived = repo.archive()?.object_ids()?;
+    records.retain(|record| archived.contains(&record.object_id) == cmd.restore);
+    if !cmd.dry_run {
+        if cmd.restore {
+            repo.unarchive_notes(&records)?;
+        } else {
+            repo.archive_notes(&records)?;
+        }
+    }
+    let summary = ArchiveOutput {
+        notes: records.iter().map(SearchHit::from_record).collect(),
+        restored: cmd.restore,
+        dry_run: cmd.dry_run,
+    };
+    if cli.output.emit(&summary)? || cli.quiet {
+        return Ok(());
+    }
+    if summary.notes.is_empty() {
+        println!(
+            "{} Nothing to {}",
+            plain_text("ℹ️").blue(),
+            if cmd.restore { "restore" } else { "archive" }
+        );
+        return Ok(());
+    }
+    let verb = match (cmd.restore, cmd.dry_run) {
+        (false, false) => "Archived",
+        (false, true) => "Would archive",
+        (true, false) => "Restored",
+        (true, true) => "Would restore",
+    };
+    println!(
+        "{}{} {} notes",
+        plain_text("📦 ").blue(),
+        verb,
+        summary.notes.len()
+    );
+    for hit in &summary.notes {
+        println!(
+            "  {} {}",
+            format_object_id(&hit.object_id).cyan(),
+            hit.title
+        );
+    }
+    if !cmd.restore && !cmd.dry_run {
+        println!();
+        println!("{}Find them with: fuku search --archived; bring one back with: fuku archive <id> --restore", plain_text("💡 "));
+    }
+    Ok(())
+}
+
+/// Ids of the notes matching a bulk query, most recently updated first:
+/// `tag:NAME` terms must all be tags of the note, `before:WHEN` and
+/// `after:WHEN` bound when it was last updated, and the rest is a search
+/// query. With `archived`, the archive is searched.
+fn notes_matching(repo: &FukuraRepo, query: &str, archived: bool) -> Result<Vec<String>> {
+    let mut text = Vec::new();
+    let mut tags = Vec::new();
+    let mut filter = SearchFilter::default();
+    for word in query.split_whitespace() {
+        match word.split_once(':') {
+            Some(("tag", tag)) if !tag.is_empty() => tags.push(tag.to_lowercase()),
+            Some(("before", when)) => filter.updated_before = Some(parse_instant(when)?),
+            Some(("after", when)) => filter.updated_after = Some(parse_instant(when)?),
+            _ => text.push(word),
+        }
+    }
+    let text = text.join(" ");
+    let index = if archived {
+        repo.archive()?
+    } else {
+        repo.index()?
+    };
+    let limit = usize::try_from(index.num_docs()?).unwrap_or(usize::MAX);
+    let page = index.search_page(&text, &filter, 0, limit, SearchSort::Updated)?;
+    Ok(page
+        .hits
+        .into_iter()
+        .filter(|hit| tags.iter().all(|tag| hit.tags.contains(tag)))
+        .map(|hit| hit.object_id)
+        .collect())
+}
+
+/// `fuku bulk --output json|yaml`
+#[derive(Debug, Serialize)]
+struct BulkOutput {
+    matched: usize,
+    /// Notes edited, as the id they had and the id of the edited version
+    changed: Vec<BulkChange>,
+    /// Matching notes the edits didn't change
+    unchanged: usize,
+    failed: Vec<SyncedNote>,
+    dry_run: bool,
+    /// Stopped by Ctrl-C before every note was edited
+    interrupted: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct BulkChange {
+    object_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_id: Option<String>,
+    title: String,
+}
+
+fn handle_bulk(cli: &Cli, cmd: &BulkCommand) -> Result<()> {
+    if cmd.edits.is_empty() && cmd.set_privacy.is_none() {
+        bail!("Nothing to change\n💡 Tip: Use --add-tag, --remove-tag, --meta, --remove-meta or --set-privacy");
+    }
+    let repo = open_repo(cli)?;
+    let ids = notes_matching(&repo, &cmd.query, false)?;
+    let _interrupt = crate::interrupt::watch();
+    let mut summary = BulkOutput {
+        matched: ids.len(),
+        changed: Vec::new(),
+        unchanged: 0,
+        failed: Vec::new(),
+        dry_run: cmd.dry_run,
+        interrupted: false,
+    };
+    // One note at a time, so memory stays flat however many match
+    for id in ids {
+        if crate::interrupt::requested() {
+            summary.interrupted = true;
+            break;
+        }
+        let edited = repo.load_note(&id).and_then(|mut record| {
+            let mut changed = cmd.edits.apply(&repo, &mut record.note)?;
+            if let Some(privacy) = &cmd.set_privacy {
+                if record.note.privacy != *privacy {
+                    if record.note.is_local_only() {
+                        bail!("Made in a private zone; it stays private");
+                    }
+                    record.note.privacy = privacy.clone();
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(None);
+            }
+            let title = record.note.title.clone();
+            let new_id = if cmd.dry_run {
+                None
+            } else {
+                Some(store_edit(cli, &repo, &id, record.note)?.object_id)
+            };
+            Ok(Some(BulkChange {
+                object_id: id.clone(),
+                new_id,
+                title,
+            }))
+        });
+        match edited {
+            Ok(Some(change)) => summary.changed.push(change),
+            Ok(None) => summary.unchanged += 1,
+            Err(err) => summary.failed.push(SyncedNote {
+                object_id: id,
+                remote_id: None,
+                error: Some(format!("{:#}", err)),
+            }),
+        }
+    }
+    if cli.output.emit(&summary)? || cli.quiet {
+        return Ok(());
+    }
+    if summary.matched == 0 {
+        println!("{} No notes match '{}'", plain_text("ℹ️").blue(), cmd.query);
+        return Ok(());
+    }
+    let verb = if cmd.dry_run {
+        "Would change"
+    } else {
+        "Changed"
+    };
+    for change in &summary.changed {
+        println!(
+            "  {} {}",
+            format_object_id(&change.object_id).cyan(),
+            change.title
+        );
+    }
+    for failed in &summary.failed {
+        println!(
+            "  {} {}: {}",
+            plain_text("✗").red(),
+            format_object_id(&failed.object_id),
+            failed.error.as_deref().unwrap_or_default()
+        );
+    }
+    println!(
+        "{} {} {} of {} matching notes ({} unchanged, {} failed)",
+        if summary.failed.is_empty() {
+            plain_text("✓").green()
+        } else {
+            plain_text("⚠️").yellow()
+        },
+        verb,
+        summary.changed.len(),
+        summary.matched,
+        summary.unchanged,
+        summary.failed.len()
+    );
+    if summary.interrupted {
+        println!(
+            "{} Interrupted; the remaining notes were left as they were",
+            plain_text("⚠️").yellow()
+        );
+    }
+    Ok(())
+}
+
+fn search_all_repos(
+    cli: &Cli,
+    query: &str,
+    limit: usize,
+    sort: SearchSort,
+    json: bool,
+) -> Result<()> {
+    let format = cli.output.or_json(json);
+    let roots = RepoRegistry::load_default()?.existing();
+    if roots.is_empty() && !format.is_structured() {
+        println!("No registered repositories.");
+        println!("{}Repositories register on 'fuku init' and when used; add others with: fuku repos add <path>", plain_text("💡 "));
+        return Ok(());
+    }
+
+    let hits = registry::search_repos(&roots, query, limit, sort);
+
+    if format.emit(&hits)? {
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No notes found across all repositories.");
+        return Ok(());
+    }
+
+    println!("Search Results (across {} repositories)", roots.len());
+    render_repo_hits_table(&hits);
+
+    if let Some(first) = hits.first() {
+        let short_id = format_object_id(&first.hit.object_id);
+        println!(
+            "{}View: fuku view {} --repo {}",
+            plain_text("💡 "),
+            short_id,
+            first.repo.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Search results with the repository each came from
+fn render_repo_hits_table(hits: &[RepoHit]) {
+    let mut table = Table::new();
+    table
+        .load_preset(crate::output::table_preset())
+        .set_header(vec!["#", "Title", "Repo", "Likes", "Updated", "Tags"]);
+    for (idx, repo_hit) in hits.iter().enumerate() {
+        let hit = &repo_hit.hit;
+        let mut title = hit.title.clone();
+        if let Some(snippet) = &hit.snippet {
+            title.push_str("\n  ");
+            title.push_str(&marked_snippet(snippet));
+        }
+        let mut repo = repo_hit
+            .repo
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| repo_hit.repo.display().to_string());
+        if !repo_hit.also_in.is_empty() {
+            repo.push_str(&format!(" (+{})", repo_hit.also_in.len()));
+        }
+        table.add_row(vec![
+            format!("{:>2}", idx + 1),
+            title,
+            repo,
+            hit.likes.to_string(),
+            hit.updated_at.format("%Y-%m-%d").to_string(),
+            hit.tags.join(", "),
+        ]);
+    }
+    println!("{}", " Results".bold());
+    println!("{}", table);
+}
+
+fn handle_repos(cli: &Cli, cmd: &ReposCommand) -> Result<()> {
+    let mut registry = RepoRegistry::load_default()?;
+    match cmd {
+        ReposCommand::List { json } => {
+            let roots: Vec<&Path> = registry.repos().collect();
+            let format = cli.output.or_json(*json);
+            if format.is_structured() {
+                let entries: Vec<serde_json::Value> = roots
+                    .iter()
+                    .map(|root| {
+                        serde_json::json!({
+                            "path": root.display().to_string(),
+                            "exists": root.join(".fukura").is_dir(),
+                        })
+                    })
+                    .collect();
+                format.emit(&entries)?;
+                return Ok(());
+            }
+            if roots.is_empty() {
+                if !cli.quiet {
+                    println!("{} No registered repositories", plain_text("ℹ️").blue());
+                    println!("{}Add one: fuku repos add <path>", plain_text("💡 "));
+                }
+                return Ok(());
+            }
+            for root in roots {
+                if root.join(".fukura").is_dir() {
+                    println!("{}", root.display());
+                } else {
+                    println!("{} {}", root.display(), "(missing)".dimmed());
+                }
+            }
+        }
+        ReposCommand::Add { path } => {
+            let root = FukuraRepo::find_root(Some(path.as_path()))?;
+            let added = registry.add(&root);
+            registry.save()?;
+            if !cli.quiet {
+                if added {
+                    println!("{} Registered {}", plain_text("✓").green(), root.display());
+                } else {
+                    println!(
+                        "{} Already registered: {}",
+                        plain_text("ℹ️").blue(),
+                        root.display()
+                    );
+                }
+            }
+        }
+        ReposCommand::Remove { path } => {
+            if !registry.remove(path) {
+                bail!(
+                    "{} is not registered.\n💡 Tip: See registered repositories with 'fuku repos list'",
+                    path.display()
+                );
+            }
+            registry.save()?;
+            if !cli.quiet {
+                println!(
+                    "{} Unregistered {}",
+                    plain_text("✓").yellow(),
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_tag(cli: &Cli, cmd: &TagCommand) -> Result<()> {
+    let TagCommand::List { limit, sort } = cmd;
+    let repo = open_repo(cli)?;
+    let mut stats = repo.tag_stats()?;
+    match sort {
+        // Already most used first
+        TagSort::Count => {}
+        TagSort::Recent => stats.sort_by_key(|stat| std::cmp::Reverse(stat.last_used)),
+        TagSort::Name => stats.sort_by(|a, b| a.tag.cmp(&b.tag)),
+    }
+    let total = stats.len();
+    stats.truncate(limit.unwrap_or(usize::MAX));
+    if cli.output.emit(&stats)? {
+        return Ok(());
+    }
+    if stats.is_empty() {
+        println!("{} No tags yet", plain_text("ℹ️").blue());
+        println!();
+        println!(
+            "{}Tag a note with: fuku edit @latest --add-tag <tag>",
+            plain_text("💡 ")
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(crate::output::table_preset())
+        .set_header(vec!["Tag", "Notes", "Last used"]);
+    for stat in &stats {
+        table.add_row(vec![
+            stat.tag.clone(),
+            stat.notes.to_string(),
+            stat.last_used.format("%Y-%m-%d").to_string(),
+        ]);
+    }
+    println!("{}Tags ({} total)", plain_text("🏷️ ").cyan(), total);
+    println!();
+    println!("{}", table);
+    if stats.len() < total && !cli.quiet {
+        println!();
+        println!(
+            "{}Showing {} of {}; raise --limit to see more",
+            plain_text("💡 "),
+            stats.len(),
+            total
+        );
+    }
+    Ok(())
+}
+
+fn handle_keys(cli: &Cli, cmd: &KeysCommand) -> Result<()> {
+    match cmd {
+        KeysCommand::Generate { force } => {
+            let key = signing::generate_key(*force)?;
+            let public_key = signing::public_key(&key);
+            if cli
+                .output
+                .emit(&serde_json::json!({ "public_key": public_key }))?
+            {
+                return Ok(());
+            }
+            if !cli.quiet {
+                println!(
+                    "{} Signing key saved to {}",
+                    plain_text("✓").green(),
+                    signing::key_path()?.display()
+                );
+                println!("Public key: {}", public_key);
+                println!(
+                    "{}Teammates trust it with: fuku keys trust \"{}\" {}",
+                    plain_text("💡 "),
+                    crate::note_service::default_author().name,
+                    public_key
+                );
+            }
+        }
+        KeysCommand::Show => {
+            let Some(key) = signing::load_key()? else {
+                bail!("No signing key yet.\n💡 Tip: Make one with 'fuku keys generate'");
+            };
+            let public_key = signing::public_key(&key);
+            if cli.output.emit(&serde_json::json!({
+                "public_key": public_key,
+                "signer": audit::actor(),
+            }))? {
+                return Ok(());
+            }
+            println!("{}", public_key);
+        }
+        KeysCommand::Trust { name, public_key } => {
+            let repo = open_repo(cli)?;
+            let added = trust_signing_key(&repo, name, public_key)?;
+            if !cli.quiet {
+                if added {
+                    println!(
+                        "{} Notes signed by this key now show as {}",
+                        plain_text("✓").green(),
+                        name
+                    );
+                } else {
+                    println!("{} Already trusted as {}", plain_text("ℹ️").blue(), name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_grep(cli: &Cli, cmd: &GrepCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let source = if cmd.fixed_strings {
+        regex::escape(&cmd.pattern)
+    } else {
+        cmd.pattern.clone()
+    };
+    let pattern = regex::RegexBuilder::new(&source)
+        .case_insensitive(cmd.ignore_case)
+        .build()
+        .with_context(|| format!("Invalid pattern '{}'", cmd.pattern))?;
+    let before = cmd.before_context.or(cmd.context).unwrap_or(0);
+    let after = cmd.after_context.or(cmd.context).unwrap_or(0);
+    let wanted_tags = normalize_tags(cmd.tags.clone());
+
+    // The index knows which notes are live; bodies are streamed from objects/packs
+    let index = repo.index()?;
+    let total = index.num_docs()? as usize;
+    let hits = index.search("", total, SearchSort::Updated)?;
+
+    let mut matched_notes = 0;
+    for hit in hits
+        .iter()
+        .filter(|hit| wanted_tags.iter().all(|tag| hit.tags.contains(tag)))
+    {
+        let Ok(record) = repo.load_note(&hit.object_id) else {
+            continue;
+        };
+        let lines = crate::grep::grep_lines(&record.note.body, &pattern, before, after);
+        if lines.is_empty() {
+            continue;
+        }
+        matched_notes += 1;
+
+        if cmd.files_with_matches {
+            println!("{}", record.object_id);
+            continue;
+        }
+
+        println!(
+            "{} {}",
+            format_object_id(&record.object_id).yellow(),
+            record.note.title.bold()
+        );
+        for line in lines {
+            if line.group_start {
+                println!("{}", "  --".dimmed());
+            }
+            if line.is_match {
+                let mut rendered = String::new();
+                let mut cursor = 0;
+                for (start, end) in crate::grep::match_ranges(&line.text, &pattern) {
+                    rendered.push_str(&line.text[cursor..start]);
+                    rendered.push_str(&line.text[start..end].red().bold().to_string());
+                    cursor = end;
+                }
+                rendered.push_str(&line.text[cursor..]);
+                println!(
+                    "  {}{} {}",
+                    line.number.to_string().green(),
+                    ":".dimmed(),
+                    rendered
+                );
+            } else {
+                println!(
+                    "  {}{} {}",
+                    line.number.to_string().green(),
+                    "-".dimmed(),
+                    line.text
+                );
+            }
+        }
+        println!();
+    }
+
+    if matched_notes == 0 && !cli.quiet && !cmd.files_with_matches {
+        println!(
+            "{} No notes match '{}'",
+            plain_text("ℹ️").blue(),
+            cmd.pattern
+        );
+    }
+    Ok(())
+}
+
+fn handle_pick(cli: &Cli, cmd: &PickCommand) -> Result<()> {
+    if let Some(shell) = &cmd.widget {
+        print!("{}", crate::picker::shell_widget(shell)?);
+        return Ok(());
+    }
+
+    let repo = open_repo(cli)?;
+    // Query the index directly so picking doesn't overwrite the @N search cache
+    let hits = repo.index()?.search("", cmd.limit, SearchSort::Updated)?;
+    ensure!(
+        !hits.is_empty(),
+        "No notes yet\n💡 Tip: Create one with 'fuku add'"
+    );
+
+    let Some(hit) = crate::picker::pick_note(&hits, &cmd.query.join(" "))? else {
+        bail!("No note selected");
+    };
+
+    if cmd.open {
+        let record = repo.load_note(&hit.object_id)?;
+        handle_open_inline(&repo, &record)?;
+    } else if cmd.short {
+        println!("{}", format_object_id(&hit.object_id));
+    } else {
+        println!("{}", hit.object_id);
+    }
+    Ok(())
+}
+
+fn handle_view(cli: &Cli, cmd: &ViewCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let mut resolved = repo.resolve_object_id(&cmd.id)?;
+    if let Some(merged) = repo.merged_into(&resolved)? {
+        if !cli.quiet {
+            eprintln!(
+                "Info: {} was merged into {}",
+                format_object_id(&resolved),
+                format_object_id(&merged)
+            );
+        }
+        resolved = merged;
+    }
+    let mut record = repo.load_note(&resolved)?;
+    record_opened(&repo, &resolved);
+    let trust = TrustStore::load(&repo.config()?.signing)?.check(&record.note);
+    if cmd.full {
+        if let Some(attachment) = record.note.meta.get(crate::models::BODY_ATTACHMENT_META) {
+            let full = repo.load_attachment(attachment)?;
+            record.note.body = String::from_utf8_lossy(&full).into_owned();
+        }
+    }
+    if let Some(format) = cmd.format {
+        return export_note(cli, &repo, &record, format, cmd);
+    }
+    if !cli.output.or_json(cmd.json).emit(&record)? {
+        render_note(&record, Some(&trust), &related_for_view(&repo, &record));
+    }
+    Ok(())
+}
+
+fn export_note(
+    cli: &Cli,
+    repo: &FukuraRepo,
+    record: &NoteRecord,
+    format: ViewFormat,
+    cmd: &ViewCommand,
+) -> Result<()> {
+    let schema = repo.config()?.meta_schema;
+    let theme = cmd.theme.to_lowercase();
+    let document = match format {
+        ViewFormat::Md => render_note_markdown(record, &schema),
+        ViewFormat::Html => render_note_html(
+            record,
+            &theme,
+            &schema,
+            None,
+            &related_for_view(repo, record),
+            note_cast(repo, record).as_deref(),
+        )?,
+        ViewFormat::Pdf => {
+            let output = cmd.out.clone().unwrap_or_else(|| {
+                PathBuf::from(format!("{}.pdf", format_object_id(&record.object_id)))
+            });
+            let html = render_note_html(
+                record,
+                &theme,
+                &schema,
+                None,
+                &related_for_view(repo, record),
+                note_cast(repo, record).as_deref(),
+            )?;
+            print_pdf(&html, &record.object_id, &output)?;
+            if !cli.quiet {
+                println!("{} Saved {}", plain_text("✓").green(), output.display());
+            }
+            return Ok(());
+        }
+    };
+    match &cmd.out {
+        Some(path) => {
+            fs::write(path, document)?;
+            if !cli.quiet {
+                println!("{} Saved {}", plain_text("✓").green(), path.display());
+            }
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+/// Print `html` to a PDF at `output` with the first converter found:
+/// wkhtmltopdf, or Chrome/Chromium in headless mode
+fn print_pdf(html: &str, object_id: &str, output: &Path) -> Result<()> {
+    const BROWSERS: &[&str] = &[
+        "chromium",
+        "chromium-browser",
+        "google-chrome",
+        "google-chrome-stable",
+        "microsoft-edge",
+    ];
+
+    let page = crate::paths::temp_dir()?.join(format!("fuku-{}.html", object_id));
+    fs::write(&page, html)?;
+    // Relative outputs are resolved before the converter runs elsewhere
+    let output = std::env::current_dir()?.join(output);
+    let status = if let Ok(wkhtmltopdf) = which::which("wkhtmltopdf") {
+        std::process::Command::new(wkhtmltopdf)
+            .args(["--quiet", "--enable-local-file-access"])
+            .arg(&page)
+            .arg(&output)
+            .status()
+    } else if let Some(browser) = BROWSERS.iter().find_map(|name| which::which(name).ok()) {
+        std::process::Command::new(browser)
+            .args(["--headless", "--disable-gpu", "--no-pdf-header-footer"])
+            .arg(format!("--print-to-pdf={}", output.display()))
+            .arg(&page)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+    } else {
+        let _ = fs::remove_file(&page);
+        bail!("PDF export needs wkhtmltopdf or Chrome/Chromium on PATH.\n💡 Tip: Export HTML with '--format html' and print it from a browser");
+    };
+    let _ = fs::remove_file(&page);
+    ensure!(status?.success(), "PDF conversion failed");
+    Ok(())
+}
+
+/// Note that a note was looked at, so the daemon can tell whether a suggested
+/// fix was tried. Best effort: viewing must not fail because of it.
+fn record_opened(repo: &FukuraRepo, object_id: &str) {
+    if repo.is_read_only() {
+        return;
+    }
+    let mut event = FeedbackEvent::new(object_id, FeedbackKind::Opened);
+    if let Ok(dir) = std::env::current_dir() {
+        event = event.in_dir(&dir.display().to_string());
+    }
+    let _ = FeedbackStore::new(repo.root()).append(&event);
+}
+
+fn handle_rename(cli: &Cli, cmd: &RenameCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let mut record = repo.load_note(&resolved)?;
+
+    let title = match (&cmd.title, cmd.auto) {
+        (Some(title), false) => title.trim().to_string(),
+        (None, true) => {
+            let Some(context) = TitleContext::from_note(&record.note) else {
+                bail!(
+                    "Note {} is not an auto-captured error.\n💡 Tip: Give it a title with 'fuku rename {} \"New title\"'",
+                    format_object_id(&resolved),
+                    cmd.id
+                );
+            };
+            render_title(&repo.config()?.capture.title_template, &context)
+        }
+        _ => bail!("Give a new title or --auto, e.g. 'fuku rename @latest \"New title\"'"),
+    };
+    if title.is_empty() {
+        bail!("Title cannot be empty.");
+    }
+    if title == record.note.title {
+        if !cli.quiet {
+            println!(
+                "{} Title unchanged: {}",
+                plain_text("ℹ️").blue(),
+                title.bold()
+            );
+        }
+        return Ok(());
+    }
+
+    let old_title = std::mem::replace(&mut record.note.title, title);
+    record
+        .note
+        .record_edit(&resolve_author(None, None), chrono::Utc::now());
+    let new_record = repo.replace_note(&resolved, record.note)?;
+
+    if !cli.quiet {
+        println!(
+            "{} Renamed {} {} {} ({})",
+            plain_text("✓").green(),
+            old_title.dimmed(),
+            plain_text("→"),
+            new_record.note.title.bold(),
+            format_object_id(&new_record.object_id)
+        );
+    }
+    Ok(())
+}
+
+async fn handle_summarize(cli: &Cli, cmd: &SummarizeCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let mut record = repo.load_note(&resolved)?;
+    let Some(summarizer) = Summarizer::from_config(&repo.config()?) else {
+        bail!("No model configured.\n💡 Tip: Add an [ai] section to .fukura/config or the global config, e.g.\n  [ai]\n  provider = \"ollama\"");
+    };
+    let summary = summarizer.summarize(&record.note).await?;
+    let mut output = SummarizeOutput {
+        object_id: resolved.clone(),
+        model: summarizer.model().to_string(),
+        summary,
+        updated: None,
+    };
+    if cmd.apply {
+        output
+            .summary
+            .apply_to(&mut record.note, summarizer.model());
+        record
+            .note
+            .record_edit(&resolve_author(None, None), chrono::Utc::now());
+        output.updated = Some(repo.replace_note(&resolved, record.note)?.object_id);
+    }
+    if cli.output.emit(&output)? || cli.quiet {
+        return Ok(());
+    }
+
+    let summary = &output.summary;
+    println!("{}{}", plain_text("📝 ").cyan(), summary.title.bold());
+    if !summary.problem.is_empty() {
+        println!("  {} {}", "Problem:".bold(), summary.problem);
+    }
+    if !summary.solution.is_empty() {
+        println!("  {} {}", "Solution:".bold(), summary.solution);
+    }
+    if !summary.tags.is_empty() {
+        println!("  {}", format!("#{}", summary.tags.join(" #")).dimmed());
+    }
+    println!();
+    match &output.updated {
+        Some(object_id) => println!(
+            "{} Updated the note ({})",
+            plain_text("✓").green(),
+            format_object_id(object_id)
+        ),
+        None => println!(
+            "{}Apply it to the note: fuku summarize {} --apply",
+            plain_text("💡 "),
+            cmd.id
+        ),
+    }
+    Ok(())
+}
+
+fn handle_edit(cli: &Cli, cmd: &EditCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let mut record = repo.load_note(&resolved)?;
+
+    let mut modified = false;
+
+    // Update title
+    if let Some(new_title) = &cmd.title {
+        let trimmed = new_title.trim();
+        if trimmed.is_empty() {
+            bail!("Title cannot be empty. Use --title 'New Title' with actual content.");
+        }
+        record.note.title = trimmed.to_string();
+        modified = true;
+    }
+
+    // Update body
+    if let Some(new_body) = &cmd.body {
+        record.note.body = new_body.trim().to_string();
+        modified = true;
+    } else if cmd.editor {
+        // Open editor for body
+        if let Some(edited) = Editor::new().edit(&record.note.body)? {
+            record.note.body = edited.trim().to_string();
+            modified = true;
+        }
+    }
+
+    modified |= cmd.edits.apply(&repo, &mut record.note)?;
+
+    if !modified {
+        if !cli.quiet {
+            println!("{} No changes made", plain_text("ℹ️").blue());
+            println!("{}Use --title, --body, --add-tag, --remove-tag, --meta, --remove-meta, or --editor to make changes", plain_text("💡 "));
+        }
+        return Ok(());
+    }
+
+    let new_record = store_edit(cli, &repo, &resolved, record.note)?;
+
+    if !cli.quiet {
+        let short_id = format_object_id(&new_record.object_id);
+        println!(
+            "{} Updated note {} ({})",
+            plain_text("✓").green(),
+            new_record.note.title.bold(),
+            short_id
+        );
+        if !new_record.note.tags.is_empty() {
+            println!("  #{}", new_record.note.tags.join(" #"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Store an edited version of note `object_id`, recording who edited it
+fn store_edit(cli: &Cli, repo: &FukuraRepo, object_id: &str, mut note: Note) -> Result<NoteRecord> {
+    note.record_edit(&resolve_author(None, None), chrono::Utc::now());
+    Ok(link_incident_issue(
+        cli,
+        repo,
+        repo.replace_note(object_id, note)?,
+    ))
+}
+
+fn handle_open(cli: &Cli, cmd: &OpenCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let record = repo.load_note(&resolved)?;
+    record_opened(&repo, &resolved);
+    let theme = cmd.theme.to_lowercase();
+    if cmd.serve {
+        return open_served(cli, &repo, &resolved, &theme, &cmd.addr, cmd.qr);
+    }
+    let html = render_note_html(
+        &record,
+        &theme,
+        &repo.config()?.meta_schema,
+        None,
+        &related_for_view(&repo, &record),
+        note_cast(&repo, &record).as_deref(),
+    )?;
+    let filename = format!("fuku-{}.html", resolved);
+
+    if cmd.url_only {
+        // Just show the URL for manual opening
+        let file_path = crate::paths::temp_dir()?.join(&filename);
+        fs::write(&file_path, html)?;
+
+        if !cli.quiet {
+            println!("{} Note saved to: {}", "".blue(), file_path.display());
+            println!("{} Open this file in your browser manually", "".yellow());
+        }
+        return Ok(());
+    }
+
+    if cmd.browser_only {
+        // Try direct browser opening only
+        let file_path = crate::paths::temp_dir()?.join(&filename);
+        fs::write(&file_path, html)?;
+
+        match crate::browser::BrowserOpener::open(&file_path) {
+            Ok(()) => {
+                if !cli.quiet {
+                    println!("{} Opened note in your browser", "".magenta());
+                }
+            }
+            Err(e) => {
+                if !cli.quiet {
+                    println!("{} Could not open browser: {}", "".red(), e);
+                    println!("{} File saved to: {}", "".blue(), file_path.display());
+                }
+                return Err(e);
+            }
+        }
+    } else {
+        // Use smart opening with server fallback
+        match crate::browser::BrowserOpener::open_with_server(&html, &filename) {
+            Ok(()) => {
+                if !cli.quiet {
+                    println!("{} Opened note in your browser", "".magenta());
+                }
+            }
+            Err(e) => {
+                // Fallback: save to file and show path
+                let file_path = crate::paths::temp_dir()?.join(&filename);
+                fs::write(&file_path, html)?;
+
+                if !cli.quiet {
+                    println!(
+                        "{} Could not open browser automatically: {}",
+                        "".yellow(),
+                        e
+                    );
+                    println!(
+                        "{} Please open this file manually: {}",
+                        "".blue(),
+                        file_path.display()
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Open a note from the serve server at `addr`, starting one for this
+/// repository in the background if nothing answers there. With `qr`, the
+/// note's URL is printed as a QR code for another device instead.
+fn open_served(
+    cli: &Cli,
+    repo: &FukuraRepo,
+    id: &str,
+    theme: &str,
+    addr: &str,
+    qr: bool,
+) -> Result<()> {
+    let reachable = if qr {
+        match crate::qr::reachable_addr(addr) {
+            Some(reachable) => Some(reachable),
+            None => bail!(
+                "{} only answers on this machine, so another device can't open it.\n💡 Tip: Serve on every interface with --addr 0.0.0.0:8765",
+                addr
+            ),
+        }
+    } else {
+        None
+    };
+    let started = if server_answers(addr) {
+        false
+    } else {
+        let log_dir = repo.root().join(".fukura").join("logs");
+        fs::create_dir_all(&log_dir)?;
+        let output = fs::File::create(log_dir.join("serve.out"))?;
+        std::process::Command::new(std::env::current_exe()?)
+            .args(["serve", "--addr", addr])
+            .current_dir(repo.root())
+            .stdin(std::process::Stdio::null())
+            .stdout(output.try_clone()?)
+            .stderr(output)
+            .spawn()
+            .context("Failed to start 'fuku serve'")?;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !server_answers(addr) {
+            if std::time::Instant::now() > deadline {
+                bail!(
+                    "'fuku serve' did not come up at {}.\n💡 Tip: See .fukura/logs/serve.out, or pick another --addr",
+                    addr
+                );
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        true
+    };
+
+    if let Some(reachable) = reachable {
+        let url = format!("http://{}/notes/{}?theme={}", reachable, id, theme);
+        if started && !cli.quiet {
+            println!(
+                "{} Started 'fuku serve' at http://{}",
+                "".bright_blue(),
+                addr
+            );
+        }
+        print_qr(cli, &url)?;
+        return Ok(());
+    }
+    let url = format!("http://{}/notes/{}?theme={}", addr, id, theme);
+    crate::browser::BrowserOpener::open_url(&url)?;
+    if !cli.quiet {
+        if started {
+            println!(
+                "{} Started 'fuku serve' at http://{}",
+                "".bright_blue(),
+                addr
+            );
+        }
+        println!("{} Opened {}", "".magenta(), url);
+    }
+    Ok(())
+}
+
+/// `url` as a QR code followed by the URL itself; just the URL with
+/// `--quiet`
+fn print_qr(cli: &Cli, url: &str) -> Result<()> {
+    if !cli.quiet {
+        println!("{}", crate::qr::render(url)?);
+    }
+    println!("{}", url);
+    Ok(())
+}
+
+/// Whether something answers `GET /healthz` with 200 at `addr`
+fn server_answers(addr: &str) -> bool {
+    use std::net::ToSocketAddrs;
+
+    let timeout = std::time::Duration::from_millis(300);
+    let Some(socket) = addr
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+    else {
+        return false;
+    };
+    let Ok(mut stream) = std::net::TcpStream::connect_timeout(&socket, timeout) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(timeout));
+    let request = format!(
+        "GET /healthz HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        addr
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut status = [0u8; 12];
+    stream.read_exact(&mut status).is_ok() && status.ends_with(b" 200")
+}
+
+/// `fuku stats --output json|yaml`
+#[derive(Debug, Serialize)]
+struct StatsOutput {
+    notes: usize,
+    tags: usize,
+    last_updated: Option<chrono::DateTime<Utc>>,
+    storage_bytes: u64,
+    loose_objects: usize,
+    pack_files: usize,
+    remote: Option<String>,
+    auto_sync: bool,
+    daemon_enabled: bool,
+    /// Notes recording a failure, by the program that failed
+    errors_by_tool: BTreeMap<String, usize>,
+    errors_by_class: BTreeMap<String, usize>,
+}
+
+/// Most frequent entries first, then by name
+fn top_counts(counts: &BTreeMap<String, usize>, limit: usize) -> Vec<(&str, usize)> {
+    let mut entries: Vec<(&str, usize)> = counts
+        .iter()
+        .map(|(name, count)| (name.as_str(), *count))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    entries.truncate(limit);
+    entries
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{}B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1}KB", bytes as f64 / 1024.0)
     } else {
-        String::new()
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+fn handle_stats(cli: &Cli) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let config = repo.config()?;
+
+    // Counts come from the metadata cache rather than loading every note
+    let cached = repo.meta_cache()?.stats()?;
+    let total_notes = cached.notes;
+    let last_updated = cached.last_updated;
+
+    // Calculate storage size
+    let objects_dir = repo.objects_dir();
+    let packs_dir = repo.pack_dir();
+    let mut total_size = 0u64;
+    let mut loose_count = 0usize;
+
+    if objects_dir.exists() {
+        for entry in walkdir::WalkDir::new(&objects_dir)
+            .max_depth(3)
+            .into_iter()
+            .flatten()
+        {
+            if entry.file_type().is_file() {
+                if let Ok(metadata) = entry.metadata() {
+                    total_size += metadata.len();
+                    loose_count += 1;
+                }
+            }
+        }
+    }
+
+    let mut pack_count = 0usize;
+    if packs_dir.exists() {
+        for entry in (fs::read_dir(&packs_dir)?).flatten() {
+            if entry.file_type()?.is_file() {
+                if let Ok(metadata) = entry.metadata() {
+                    total_size += metadata.len();
+                    pack_count += 1;
+                }
+            }
+        }
+    }
+
+    let stats = StatsOutput {
+        notes: total_notes,
+        tags: cached.tags,
+        last_updated,
+        storage_bytes: total_size,
+        loose_objects: loose_count,
+        pack_files: pack_count,
+        remote: config.default_remote.clone(),
+        auto_sync: config.auto_sync.unwrap_or(false),
+        daemon_enabled: config.daemon_enabled.unwrap_or(false),
+        errors_by_tool: cached.errors_by_tool,
+        errors_by_class: cached.errors_by_class,
     };
+    if cli.output.emit(&stats)? {
+        return Ok(());
+    }
+
+    let size_str = format_bytes(total_size);
+
+    if !cli.quiet {
+        println!("{}", plain_text("📊 Repository Statistics").bold().cyan());
+        println!();
+        println!(
+            "  {} Total notes: {}",
+            "".yellow(),
+            total_notes.to_string().bold()
+        );
+        println!(
+            "  {}Tags: {} unique",
+            plain_text("🏷️ ").yellow(),
+            stats.tags.to_string().bold()
+        );
+
+        if let Some(last_updated) = last_updated {
+            let time_ago = chrono::Utc::now() - last_updated;
+            let ago_str = if time_ago.num_minutes() < 60 {
+                format!("{} minutes ago", time_ago.num_minutes())
+            } else if time_ago.num_hours() < 24 {
+                format!("{} hours ago", time_ago.num_hours())
+            } else {
+                format!("{} days ago", time_ago.num_days())
+            };
+            println!(
+                "  {}Last updated: {}",
+                plain_text("🕒 ").yellow(),
+                ago_str.bold()
+            );
+        }
+
+        println!();
+        println!(
+            "  {}Storage: {}",
+            plain_text("💾 ").yellow(),
+            size_str.bold()
+        );
+        println!("    {} Loose objects: {}", plain_text("•"), loose_count);
+        println!("    {} Pack files: {}", plain_text("•"), pack_count);
+
+        if !stats.errors_by_tool.is_empty() {
+            println!();
+            println!("  {}Errors by tool:", plain_text("🧰 ").yellow());
+            for (tool, count) in top_counts(&stats.errors_by_tool, 10) {
+                println!("    {} {}: {}", plain_text("•"), tool, count);
+            }
+        }
+
+        println!();
+        println!("  {}Configuration:", plain_text("⚙️ ").yellow());
+        if let Some(remote) = &config.default_remote {
+            println!("    {} Remote: {}", plain_text("•"), remote);
+        } else {
+            println!("    {} Remote: {}", plain_text("•"), "not set".dimmed());
+        }
+        println!(
+            "    {} Auto-sync: {}",
+            plain_text("•"),
+            if config.auto_sync.unwrap_or(false) {
+                "enabled".green()
+            } else {
+                "disabled".red()
+            }
+        );
+
+        if config.daemon_enabled.unwrap_or(false) {
+            println!("    {} Daemon: {}", plain_text("•"), "enabled".green());
+        } else {
+            println!("    {} Daemon: {}", plain_text("•"), "disabled".dimmed());
+        }
+
+        println!();
+        println!("{}Tips:", plain_text("💡 "));
+        if loose_count > 10 {
+            println!("  {} Run 'fuku gc' to pack loose objects", plain_text("•"));
+        }
+        if config.default_remote.is_none() {
+            println!(
+                "  {} Set remote: 'fuku config remote --set <url>'",
+                plain_text("•")
+            );
+        }
+    }
+
+    Ok(())
+}
 
-    if body.trim().is_empty() && !cmd.no_editor {
-        // Try interactive input first, fallback to editor if needed
-        if let Ok(interactive_body) = get_interactive_body() {
-            if !interactive_body.trim().is_empty() {
-                body = interactive_body;
-            } else if let Some(buffer) =
-                Editor::new().edit("# jot down the diagnosis, commands, or code snippets here\n")?
-            {
-                body = buffer;
-            }
-        } else if let Some(buffer) =
-            Editor::new().edit("# jot down the diagnosis, commands, or code snippets here\n")?
-        {
-            body = buffer;
+/// Shells `fuku completions` can set up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Elvish,
+    Xonsh,
+}
+
+impl CompletionShell {
+    const NAMES: &'static str = "bash, zsh, fish, powershell, elvish, xonsh";
+
+    /// From a name such as `zsh`, a path such as `/bin/zsh` or a login
+    /// shell's `-zsh`
+    fn from_name(name: &str) -> Option<Self> {
+        let base = Path::new(name.trim()).file_name()?.to_str()?;
+        let base = base.trim_start_matches('-').to_lowercase();
+        Some(match base.strip_suffix(".exe").unwrap_or(&base) {
+            "bash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            "powershell" | "pwsh" => Self::PowerShell,
+            "elvish" => Self::Elvish,
+            "xonsh" => Self::Xonsh,
+            _ => return None,
+        })
+    }
+
+    /// The shell fuku was started from: xonsh by the `XONSH_VERSION` it
+    /// exports, then the parent process, then the login shell in `$SHELL`
+    fn detect() -> Option<Self> {
+        if std::env::var_os("XONSH_VERSION").is_some() {
+            return Some(Self::Xonsh);
         }
+        parent_process_name()
+            .and_then(|name| Self::from_name(&name))
+            .or_else(|| {
+                std::env::var("SHELL")
+                    .ok()
+                    .and_then(|shell| Self::from_name(&shell))
+            })
     }
 
-    if body.trim().is_empty() {
-        bail!("Note body cannot be empty");
+    /// Script generator; xonsh runs bash completions
+    fn generator(self) -> Shell {
+        match self {
+            Self::Bash | Self::Xonsh => Shell::Bash,
+            Self::Zsh => Shell::Zsh,
+            Self::Fish => Shell::Fish,
+            Self::PowerShell => Shell::PowerShell,
+            Self::Elvish => Shell::Elvish,
+        }
     }
+}
 
-    let tags = normalize_tags(cmd.tags.clone());
-    let meta = parse_meta(cmd.meta.clone())?;
-    let author = resolve_author(cmd.author.as_deref(), cmd.email.as_deref());
-
-    let note = Note {
-        title: title.trim().to_string(),
-        body: body.trim().to_string(),
-        tags,
-        links: cmd.links.clone(),
-        meta,
-        solutions: vec![],
-        privacy: cmd.privacy.clone(),
-        created_at: now,
-        updated_at: now,
-        author,
+/// Name of the process that started fuku, where the platform tells
+fn parent_process_name() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = fs::read_to_string("/proc/self/stat").ok()?;
+        // The command name before the ppid is in parentheses and may hold spaces
+        let ppid = stat.rsplit_once(')')?.1.split_whitespace().nth(1)?;
+        let comm = fs::read_to_string(format!("/proc/{}/comm", ppid)).ok()?;
+        Some(comm.trim().to_string())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+fn completion_script(shell: CompletionShell) -> Vec<u8> {
+    let generator = shell.generator();
+    let mut script = Vec::new();
+    generate(generator, &mut Cli::command(), "fuku", &mut script);
+    script.extend_from_slice(dynamic_completions(generator).as_bytes());
+    script
+}
+
+/// Where bash-completion looks for user completions, when it is installed
+fn bash_completion_dir() -> Result<Option<PathBuf>> {
+    const SCRIPTS: &[&str] = &[
+        "/usr/share/bash-completion/bash_completion",
+        "/usr/local/share/bash-completion/bash_completion",
+        "/etc/bash_completion",
+        "/usr/local/etc/profile.d/bash_completion.sh",
+        "/opt/homebrew/etc/profile.d/bash_completion.sh",
+    ];
+    if !SCRIPTS.iter().any(|script| Path::new(script).exists()) {
+        return Ok(None);
+    }
+    let dir = match std::env::var_os("BASH_COMPLETION_USER_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => crate::paths::user_data_home()?.join("bash-completion"),
     };
+    Ok(Some(dir.join("completions")))
+}
 
-    let record = repo.store_note(note)?;
+/// A directory of the user's in zsh's `$fpath`, as set up by their .zshrc
+/// (oh-my-zsh's `completions`, `~/.zfunc` and the like)
+fn zsh_fpath_dir(home: &Path) -> Option<PathBuf> {
+    let output = std::process::Command::new("zsh")
+        .args(["-ic", "print -rl -- $fpath"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .find(|dir| {
+            dir.starts_with(home)
+                && dir.is_dir()
+                // Plugin and cache directories belong to other tools
+                && !dir.components().any(|part| {
+                    matches!(part.as_os_str().to_str(), Some("plugins" | "cache" | "custom"))
+                })
+        })
+}
 
-    if !cli.quiet {
-        let short_id = format_object_id(&record.object_id);
-        println!(
-            "{} Captured {} ({})",
-            "".green(),
-            record.note.title.bold(),
-            short_id
-        );
-        if !record.note.tags.is_empty() {
-            println!("{}  #{}", "".dimmed(), record.note.tags.join(" #"));
-        }
-        println!("💡 Quick view: fuku view @latest");
+fn handle_completions(cli: &Cli, cmd: &CompletionsCommand) -> Result<()> {
+    let shell = match &cmd.shell {
+        Some(name) => CompletionShell::from_name(name).with_context(|| {
+            format!(
+                "Unsupported shell: {}. Choose from: {}",
+                name,
+                CompletionShell::NAMES
+            )
+        })?,
+        None => CompletionShell::detect().with_context(|| {
+            format!(
+                "Couldn't tell which shell this is. Name one of: {}",
+                CompletionShell::NAMES
+            )
+        })?,
+    };
+
+    if cmd.stdout {
+        io::stdout().write_all(&completion_script(shell))?;
+        return Ok(());
     }
 
-    // Auto-sync if enabled
-    let config = repo.config()?;
-    if config.auto_sync.unwrap_or(false) {
-        if let Some(remote) = &config.default_remote {
-            if !cli.quiet {
-                println!("{} Auto-syncing to remote...", "".blue());
-            }
-            match push_note(&repo, &record.object_id, remote).await {
-                Ok(remote_id) => {
-                    if !cli.quiet {
-                        println!("{} Auto-synced → {}", "".green(), remote_id);
-                    }
-                }
-                Err(e) => {
-                    if !cli.quiet {
-                        println!("{} Auto-sync failed: {}", "".yellow(), e);
-                        println!(
-                            "{} Use 'fuku sync {}' to retry",
-                            "".cyan(),
-                            record.object_id
-                        );
-                    }
-                }
+    // Install where the shell already looks, falling back to our own paths
+    let home_path = crate::paths::home_dir()?;
+    let config_home = crate::paths::user_config_home()?;
+    let (install_path, instructions) = match shell {
+        CompletionShell::Bash => match bash_completion_dir()? {
+            Some(dir) => (
+                dir.join("fuku"),
+                "bash-completion loads it the first time you press TAB after 'fuku'".to_string(),
+            ),
+            None => {
+                let file = home_path.join(".bash_completion.d").join("fuku");
+                let instructions = format!("Add 'source {}' to your ~/.bashrc", file.display());
+                (file, instructions)
             }
+        },
+        CompletionShell::Zsh => match zsh_fpath_dir(&home_path) {
+            Some(dir) => (
+                dir.join("_fuku"),
+                format!("{} is in your $fpath; compinit picks it up", dir.display()),
+            ),
+            None => (
+                home_path.join(".zsh").join("completions").join("_fuku"),
+                "Add 'fpath=(~/.zsh/completions $fpath)' to your ~/.zshrc and run 'compinit'"
+                    .to_string(),
+            ),
+        },
+        CompletionShell::Fish => (
+            config_home
+                .join("fish")
+                .join("completions")
+                .join("fuku.fish"),
+            "Completions will be loaded automatically".to_string(),
+        ),
+        CompletionShell::Elvish => {
+            let file = config_home.join("elvish").join("lib").join("fuku.elv");
+            let instructions = format!(
+                "Add 'eval (slurp < {})' to your ~/.config/elvish/rc.elv",
+                file.display()
+            );
+            (file, instructions)
+        }
+        CompletionShell::Xonsh => {
+            // xonsh completes with bash scripts listed in $BASH_COMPLETIONS
+            let dir = config_home.join("xonsh");
+            let file = dir.join("fuku-completion.bash");
+            let rc = dir.join("rc.d").join("fuku.xsh");
+            fs::create_dir_all(dir.join("rc.d"))?;
+            fs::write(
+                &rc,
+                format!(
+                    "$BASH_COMPLETIONS.append({:?})\n",
+                    file.display().to_string()
+                ),
+            )?;
+            (
+                file,
+                format!(
+                    "{} adds it to $BASH_COMPLETIONS when xonsh starts",
+                    rc.display()
+                ),
+            )
+        }
+        CompletionShell::PowerShell => {
+            bail!("PowerShell completions should be output to stdout and added to your $PROFILE manually.\nRun: fuku completions powershell --stdout >> $PROFILE");
+        }
+    };
+
+    if let Some(dir) = install_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&install_path, completion_script(shell))?;
+
+    if !cli.quiet {
+        println!("{} Shell completions installed!", plain_text("✓").green());
+        println!();
+        println!("{}Location: {}", plain_text("📍 "), install_path.display());
+        println!();
+        println!("{}Next steps:", plain_text("📝 "));
+        println!("   {}", instructions);
+        println!();
+        println!("{}Restart your shell or run:", plain_text("💡 "));
+        match shell {
+            CompletionShell::Bash => println!("   source ~/.bashrc"),
+            CompletionShell::Zsh => println!("   source ~/.zshrc"),
+            CompletionShell::Elvish => println!("   exec elvish"),
+            CompletionShell::Xonsh => println!("   exec xonsh"),
+            _ => println!("   # Completions load automatically"),
         }
     }
 
     Ok(())
 }
 
-fn handle_search(cli: &Cli, cmd: &SearchCommand) -> Result<()> {
-    let query = if cmd.query.is_empty() {
-        String::new()
-    } else {
-        cmd.query.join(" ")
+/// Most recent notes offered by `fuku __complete ids`
+const COMPLETE_RECENT_IDS: usize = 20;
+
+/// Last search results offered as @N
+const COMPLETE_SEARCH_REFS: usize = 9;
+
+const BASH_DYNAMIC_COMPLETIONS: &str = r#"
+# Note IDs and tags from the current repository
+_fuku_dynamic() {
+    local line="${COMP_LINE:0:COMP_POINT}" words cur prev kind=""
+    read -ra words <<< "$line"
+    if [[ -z "$line" || "$line" == *" " ]]; then words+=(""); fi
+    cur="${words[${#words[@]}-1]}"
+    prev="${words[${#words[@]}-2]}"
+    case "$prev" in
+        @TAG_OPTIONS@) kind=tags ;;
+        *)
+            if [[ ${#words[@]} -eq 3 && "$cur" != -* ]]; then
+                case "${words[1]}" in
+                    @ID_COMMANDS@) kind=ids ;;
+                esac
+            fi
+            ;;
+    esac
+    if [[ -z "$kind" ]]; then
+        _fuku "$@"
+        return
+    fi
+    COMPREPLY=( $(compgen -W "$(fuku __complete "$kind" 2>/dev/null | cut -f1)" -- "$cur") )
+    # '@' splits words in bash; drop what sits before the word being completed
+    local prefix="${cur%"${COMP_WORDS[COMP_CWORD]}"}"
+    COMPREPLY=( "${COMPREPLY[@]#"$prefix"}" )
+}
+complete -F _fuku_dynamic -o bashdefault -o default fuku
+"#;
+
+const ZSH_DYNAMIC_COMPLETIONS: &str = r#"
+# Note IDs and tags from the current repository
+_fuku_dynamic() {
+    local kind=""
+    case "${words[CURRENT-1]}" in
+        @TAG_OPTIONS@) kind=tags ;;
+        *)
+            if (( CURRENT == 3 )) && [[ "${words[CURRENT]}" != -* ]]; then
+                case "${words[2]}" in
+                    @ID_COMMANDS@) kind=ids ;;
+                esac
+            fi
+            ;;
+    esac
+    if [[ -z "$kind" ]]; then
+        _fuku "$@"
+        return
+    fi
+    local -a candidates
+    candidates=("${(@f)$(fuku __complete $kind 2>/dev/null | sed 's/:/\:/g; s/	/:/')}")
+    [[ -n "${candidates[1]}" ]] || return 1
+    _describe -t "$kind" "$kind" candidates
+}
+compdef _fuku_dynamic fuku
+"#;
+
+const FISH_DYNAMIC_COMPLETIONS: &str = r#"
+# Note IDs and tags from the current repository
+complete -c fuku -n '__fish_seen_subcommand_from @ID_COMMANDS@' -f -a '(fuku __complete ids 2>/dev/null)'
+complete -c fuku @TAG_OPTIONS@ -x -a '(fuku __complete tags 2>/dev/null)'
+"#;
+
+/// Shell code that completes note IDs and tags through `fuku __complete`.
+/// The commands and options it applies to are read from the CLI definition:
+/// subcommands with an `ID` argument and options taking a `TAG`.
+fn dynamic_completions(shell: Shell) -> String {
+    let app = Cli::command();
+    let mut id_commands = Vec::new();
+    let mut tag_options = std::collections::BTreeSet::new();
+    for sub in app.get_subcommands() {
+        let visible_args = || sub.get_arguments().filter(|arg| !arg.is_hide_set());
+        if visible_args().any(|arg| {
+            arg.is_positional()
+                && arg
+                    .get_value_names()
+                    .is_some_and(|values| values.iter().any(|value| value == "ID"))
+        }) {
+            id_commands.push(sub.get_name().to_string());
+        }
+        for arg in visible_args() {
+            let takes_tag = arg
+                .get_value_names()
+                .is_some_and(|values| values.iter().any(|value| value == "TAG"));
+            if let (true, Some(long)) = (takes_tag, arg.get_long()) {
+                tag_options.insert(long.to_string());
+            }
+        }
+    }
+    let (template, commands, options) = match shell {
+        Shell::Bash => (
+            BASH_DYNAMIC_COMPLETIONS,
+            id_commands.join("|"),
+            tag_options
+                .iter()
+                .map(|long| format!("--{}", long))
+                .collect::<Vec<_>>()
+                .join("|"),
+        ),
+        Shell::Zsh => (
+            ZSH_DYNAMIC_COMPLETIONS,
+            id_commands.join("|"),
+            tag_options
+                .iter()
+                .map(|long| format!("--{}", long))
+                .collect::<Vec<_>>()
+                .join("|"),
+        ),
+        Shell::Fish => (
+            FISH_DYNAMIC_COMPLETIONS,
+            id_commands.join(" "),
+            tag_options
+                .iter()
+                .map(|long| format!("-l {}", long))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        _ => return String::new(),
     };
+    template
+        .replace("@ID_COMMANDS@", &commands)
+        .replace("@TAG_OPTIONS@", &options)
+}
 
-    if cmd.all_repos {
-        // Search across all local Fukura repositories
-        return search_all_repos(cli, &query, cmd.limit, cmd.sort, cmd.json);
+/// Print completion candidates. Silent outside a repository, since it runs
+/// on every TAB.
+fn handle_complete(cmd: &CompleteCommand) {
+    let Ok(repo) = FukuraRepo::discover(None) else {
+        return;
+    };
+    let clean = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match cmd.kind {
+        CompleteKind::Ids => {
+            println!("@latest\tMost recent note");
+            let refs = repo.load_search_cache().unwrap_or_default();
+            for (idx, hit) in refs.iter().take(COMPLETE_SEARCH_REFS).enumerate() {
+                println!("@{}\t{}", idx + 1, clean(&hit.title));
+            }
+            let recent = repo
+                .search("", COMPLETE_RECENT_IDS, SearchSort::Updated)
+                .unwrap_or_default();
+            for hit in recent {
+                println!(
+                    "{}\t{}",
+                    format_object_id(&hit.object_id),
+                    clean(&hit.title)
+                );
+            }
+        }
+        CompleteKind::Tags => {
+            for stat in repo.tag_stats().unwrap_or_default() {
+                println!(
+                    "{}\t{} {}",
+                    stat.tag,
+                    stat.notes,
+                    if stat.notes == 1 { "note" } else { "notes" }
+                );
+            }
+        }
     }
+}
 
-    // Remote-only search
-    if cmd.remote_only {
-        return tokio::runtime::Runtime::new()?
-            .block_on(async { handle_remote_search(cli, &query, cmd.limit, cmd.json).await });
+fn handle_alias(cli: &Cli, cmd: &AliasCommand) -> Result<()> {
+    let aliases = vec![
+        ("fa", "fuku add -q"),
+        ("fl", "fuku list"),
+        ("fs", "fuku search"),
+        ("fv", "fuku view"),
+        ("fe", "fuku edit"),
+        ("fo", "fuku open"),
+        ("fst", "fuku stats"),
+        ("fsy", "fuku sync"),
+    ];
+
+    if cmd.show {
+        if !cli.quiet {
+            println!("{}", plain_text("📝 Recommended Aliases").bold().cyan());
+            println!();
+            for (alias, command) in &aliases {
+                println!("  {} = {}", alias.green(), command.dimmed());
+            }
+        }
+        return Ok(());
     }
 
-    let repo = open_repo(cli)?;
-    if cmd.tui {
-        run_search_tui(&repo, &query, cmd.sort, cmd.limit)?;
+    if cmd.remove {
+        // Detect shell
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let shell_name = std::path::Path::new(&shell)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("bash");
+
+        let home = crate::paths::home_dir()?.display().to_string();
+        let rc_file = match shell_name {
+            "zsh" => format!("{}/.zshrc", home),
+            "fish" => crate::paths::user_config_home()?
+                .join("fish/config.fish")
+                .display()
+                .to_string(),
+            "bash" => format!("{}/.bashrc", home),
+            _ => format!("{}/.bashrc", home),
+        };
+
+        if !std::path::Path::new(&rc_file).exists() {
+            bail!("RC file not found: {}", rc_file);
+        }
+
+        let content = fs::read_to_string(&rc_file)?;
+        let lines: Vec<&str> = content
+            .lines()
+            .filter(|line| {
+                !line.contains("# Fukura aliases")
+                    && !aliases
+                        .iter()
+                        .any(|(a, _)| line.contains(&format!("alias {}=", a)))
+            })
+            .collect();
+
+        fs::write(&rc_file, lines.join("\n"))?;
+
+        if !cli.quiet {
+            println!(
+                "{} Aliases removed from {}",
+                plain_text("✓").yellow(),
+                rc_file
+            );
+        }
         return Ok(());
     }
 
-    let hits = repo.search(&query, cmd.limit, cmd.sort)?;
+    if cmd.setup {
+        // Detect shell
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let shell_name = std::path::Path::new(&shell)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("bash");
+
+        let home = crate::paths::home_dir()?.display().to_string();
+        let rc_file = match shell_name {
+            "zsh" => format!("{}/.zshrc", home),
+            "fish" => crate::paths::user_config_home()?
+                .join("fish/config.fish")
+                .display()
+                .to_string(),
+            "bash" => format!("{}/.bashrc", home),
+            _ => format!("{}/.bashrc", home),
+        };
+
+        // Check if aliases already exist
+        if std::path::Path::new(&rc_file).exists() {
+            let content = fs::read_to_string(&rc_file)?;
+            if content.contains("# Fukura aliases") {
+                if !cli.quiet {
+                    println!(
+                        "{} Aliases already installed in {}",
+                        plain_text("ℹ️").blue(),
+                        rc_file
+                    );
+                    println!(
+                        "{}Use 'fuku alias --remove' to uninstall",
+                        plain_text("💡 ")
+                    );
+                }
+                return Ok(());
+            }
+        }
+
+        let mut alias_lines = vec!["\n# Fukura aliases".to_string()];
+
+        for (alias, command) in &aliases {
+            let alias_line = if shell_name == "fish" {
+                format!("alias {} '{}'", alias, command)
+            } else {
+                format!("alias {}='{}'", alias, command)
+            };
+            alias_lines.push(alias_line);
+        }
+        alias_lines.push("# End Fukura aliases\n".to_string());
+
+        // Append to rc file
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&rc_file)?;
+        use std::io::Write;
+        file.write_all(alias_lines.join("\n").as_bytes())?;
 
-    // If --remote flag is set and no local results, search remote
-    if cmd.remote && hits.is_empty() && !query.is_empty() {
         if !cli.quiet {
-            println!("{} No local results, searching remote hub...", "🔍".cyan());
+            println!("{} Aliases installed!", plain_text("✓").green());
+            println!();
+            println!("{}Location: {}", plain_text("📍 "), rc_file);
             println!();
+            println!("{}Aliases:", plain_text("📝 "));
+            for (alias, command) in &aliases {
+                println!("  {} {} {}", alias.green(), plain_text("→"), command);
+            }
+            println!();
+            println!("{}Restart your shell or run:", plain_text("💡 "));
+            match shell_name {
+                "zsh" => println!("   source ~/.zshrc"),
+                "fish" => println!("   source ~/.config/fish/config.fish"),
+                _ => println!("   source ~/.bashrc"),
+            }
         }
-        return tokio::runtime::Runtime::new()?
-            .block_on(async { handle_remote_search(cli, &query, cmd.limit, cmd.json).await });
-    }
-
-    if cmd.json {
-        let json = serde_json::to_string_pretty(&hits)?;
-        println!("{}", json);
         return Ok(());
     }
-    render_search_table(&hits);
-    if !hits.is_empty() {
-        let short_id = format_object_id(&hits[0].object_id);
-        println!("💡 Next: fuku view @1 (or fuku open @1 to open in browser)");
-        println!("   Copy ID: {}", short_id);
-    } else if !query.is_empty() && !cli.quiet {
-        println!("{} No results found locally", "ℹ️".blue());
+
+    // Default: show aliases
+    if !cli.quiet {
+        println!("{}", plain_text("📝 Fukura Aliases").bold().cyan());
         println!();
-        println!("💡 Try searching the remote hub:");
-        println!("   fuku search \"{}\" --remote", query);
+        println!("{}Quick commands for faster workflow:", plain_text("💡 "));
+        println!();
+        for (alias, command) in &aliases {
+            println!("  {} = {}", alias.green(), command.dimmed());
+        }
+        println!();
+        println!("Usage:");
+        println!("  fuku alias --show    # Show recommended aliases");
+        println!("  fuku alias --setup   # Install aliases to shell rc file");
+        println!("  fuku alias --remove  # Remove installed aliases");
     }
+
     Ok(())
 }
 
-async fn handle_remote_search(
-    cli: &Cli,
-    query: &str,
-    limit: usize,
-    json_output: bool,
-) -> Result<()> {
+async fn handle_import(cli: &Cli, cmd: &ImportCommand) -> Result<()> {
     let repo = open_repo(cli)?;
-    let config_path = repo.root().join(".fukura").join("config.toml");
-    let config = crate::config::FukuraConfig::load_with_global_fallback(&config_path)?;
+    let config = repo.config()?;
 
-    let remote_url = config
-        .default_remote
-        .context("No remote configured. Set with: fuku config remote --set <URL>")?;
+    let mut files_to_import = Vec::new();
 
-    let hits = crate::remote_search::search_remote(&remote_url, query, limit).await?;
+    if cmd.path.is_file() {
+        files_to_import.push(cmd.path.clone());
+    } else if cmd.path.is_dir() {
+        // Recursively find all markdown files
+        for entry in walkdir::WalkDir::new(&cmd.path)
+            .max_depth(10)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file() {
+                if let Some(ext) = entry.path().extension() {
+                    if ext == "md" || ext == "markdown" || ext == "txt" {
+                        files_to_import.push(entry.path().to_path_buf());
+                    }
+                }
+            }
+        }
+    } else {
+        bail!("Path not found: {}", cmd.path.display());
+    }
 
-    if json_output {
-        let json = serde_json::to_string_pretty(&hits)?;
-        println!("{}", json);
+    if files_to_import.is_empty() {
+        if !cli.quiet {
+            println!(
+                "{} No markdown files found in {}",
+                plain_text("ℹ️").blue(),
+                cmd.path.display()
+            );
+        }
         return Ok(());
     }
 
-    if hits.is_empty() {
+    if cmd.dry_run {
         if !cli.quiet {
-            println!("{} No results found on remote hub", "ℹ️".blue());
+            println!(
+                "{}Dry run - would import {} files:",
+                plain_text("🔍 ").cyan(),
+                files_to_import.len()
+            );
+            println!();
+            for file in &files_to_import {
+                println!("  {}{}", plain_text("📄 "), file.display());
+            }
         }
         return Ok(());
     }
 
     if !cli.quiet {
         println!(
-            "{} Found {} results from remote hub",
-            "✓".green(),
-            hits.len()
+            "{}Importing {} files...",
+            plain_text("📥 ").blue(),
+            files_to_import.len()
         );
         println!();
     }
 
-    // Display remote results in a table
-    let mut table = Table::new();
-    table.load_preset(UTF8_HORIZONTAL_ONLY);
-    table.set_header(vec!["#", "Title", "Tags", "Author", "Privacy"]);
-
-    for (i, hit) in hits.iter().enumerate() {
-        let short_id = format_object_id(&hit.object_id);
-        let tags_display = if hit.tags.is_empty() {
-            String::new()
-        } else {
-            format!("#{}", hit.tags.join(" #"))
-        };
-
-        table.add_row(vec![
-            format!("@{}", i + 1),
-            format!("{} ({})", hit.title, short_id),
-            tags_display,
-            hit.author.clone(),
-            hit.privacy.clone(),
-        ]);
-    }
-
-    println!("{}", table);
-
-    if !hits.is_empty() && !cli.quiet {
-        println!();
-        println!("💡 To pull a note from remote:");
-        println!("   fuku pull {}", format_object_id(&hits[0].object_id));
-    }
-
-    Ok(())
-}
-
-fn handle_list(cli: &Cli) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let hits = repo.search("", 100, SearchSort::Updated)?;
-
-    if hits.is_empty() {
-        println!("{} No notes yet", "ℹ️".blue());
-        println!();
-        println!("💡 Get started:");
-        println!("  • Quick add: fuku add -q");
-        println!("  • Full add:  fuku add --title 'My Note' --body 'Content'");
-        return Ok(());
-    }
+    let progress = if cli.quiet {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(files_to_import.len() as u64)
+    };
+    progress.set_style(
+        ProgressStyle::with_template("  {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .expect("valid progress template")
+            .progress_chars(crate::output::progress_chars()),
+    );
 
-    println!("{} All Notes ({} total)", "📋".cyan(), hits.len());
-    println!();
-    render_search_table(&hits);
+    // Ctrl-C stops between batches, so every stored note is indexed
+    let _interrupt = crate::interrupt::watch();
 
-    if !hits.is_empty() {
-        let short_id = format_object_id(&hits[0].object_id);
-        println!();
-        println!("💡 Quick access:");
-        println!("   fuku view @1     # View first note");
-        println!("   fuku edit @1     # Edit first note");
-        println!("   Copy ID: {}", short_id);
+    // Parsing is independent per file; results keep the input order
+    progress.set_message("parsing");
+    let parsed: Vec<(&PathBuf, Result<Option<Note>>)> = files_to_import
+        .par_iter()
+        .map(|file| {
+            if crate::interrupt::requested() {
+                return (file, Ok(None));
+            }
+            let result = parse_import_file(file, cmd.tag.as_deref());
+            progress.inc(1);
+            (file, result)
+        })
+        .collect();
+    if crate::interrupt::requested() {
+        progress.finish_and_clear();
+        bail!("Interrupted while reading files; nothing was imported");
     }
-    Ok(())
-}
-
-fn search_all_repos(
-    _cli: &Cli,
-    query: &str,
-    limit: usize,
-    sort: SearchSort,
-    json: bool,
-) -> Result<()> {
-    use std::collections::HashMap;
 
-    let home = std::env::var("HOME").context("HOME not set")?;
-    let home_path = std::path::PathBuf::from(&home);
-
-    let mut all_hits: Vec<SearchHit> = Vec::new();
-    let mut repo_map: HashMap<String, String> = HashMap::new();
-
-    // Search in common directories
-    let search_dirs = vec![
-        home_path.join("work"),
-        home_path.join("projects"),
-        home_path.join("dev"),
-        home_path.join("src"),
-        home_path,
-    ];
-
-    for base_dir in search_dirs {
-        if !base_dir.exists() {
-            continue;
+    let mut skipped_count = 0;
+    let mut notes = Vec::new();
+    for (file, result) in parsed {
+        match result {
+            Ok(Some(note)) => notes.push(note),
+            Ok(None) => {
+                if !cli.quiet {
+                    progress.suspend(|| {
+                        println!(
+                            "{}Skipped {} (empty)",
+                            plain_text("⏭️ ").yellow(),
+                            file.display()
+                        )
+                    });
+                }
+                skipped_count += 1;
+            }
+            Err(e) => {
+                if !cli.quiet {
+                    progress.suspend(|| {
+                        println!(
+                            "{}Skipped {}: {}",
+                            plain_text("⏭️ ").yellow(),
+                            file.display(),
+                            e
+                        )
+                    });
+                }
+                skipped_count += 1;
+            }
         }
-        find_and_search_repos(&base_dir, query, limit, sort, &mut all_hits, &mut repo_map)?;
     }
 
-    // Sort by relevance/date
-    match sort {
-        SearchSort::Relevance => all_hits.sort_by(|a, b| b.likes.cmp(&a.likes)),
-        SearchSort::Updated => all_hits.sort_by(|a, b| b.updated_at.cmp(&a.updated_at)),
-        SearchSort::Likes => all_hits.sort_by(|a, b| b.likes.cmp(&a.likes)),
+    // Store in chunks so the index is committed once per chunk, not per note
+    progress.set_position(0);
+    progress.set_length(notes.len() as u64);
+    progress.set_message("storing");
+    let mut records = Vec::with_capacity(notes.len());
+    let mut error_count = 0;
+    let parsed_count = notes.len();
+    let service = NoteService::new(&repo)?.with_dedup(crate::dedupe::DEFAULT_THRESHOLD);
+    while !notes.is_empty() {
+        if crate::interrupt::requested() {
+            break;
+        }
+        let rest = notes.split_off(notes.len().min(IMPORT_BATCH_SIZE));
+        let chunk = std::mem::replace(&mut notes, rest);
+        let chunk_len = chunk.len();
+        match service.create_batch(chunk) {
+            Ok(created) => {
+                for result in created {
+                    match result {
+                        Ok(created) => records.push(created.record),
+                        Err(e) => {
+                            if !cli.quiet {
+                                progress.suspend(|| println!("  {} {}", plain_text("✗").red(), e));
+                            }
+                            error_count += 1;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                if !cli.quiet {
+                    progress.suspend(|| {
+                        println!(
+                            "  {} Failed to store {} notes: {}",
+                            plain_text("✗").red(),
+                            chunk_len,
+                            e
+                        )
+                    });
+                }
+                error_count += chunk_len;
+            }
+        }
+        progress.inc(chunk_len as u64);
     }
+    progress.finish_and_clear();
+    let imported_count = records.len();
+    let interrupted = !notes.is_empty();
 
-    all_hits.truncate(limit);
-
-    if json {
-        let json = serde_json::to_string_pretty(&all_hits)?;
-        println!("{}", json);
-        return Ok(());
+    // Auto-sync if enabled
+    if config.auto_sync.unwrap_or(false) && !interrupted {
+        if let Some(remote) = &config.default_remote {
+            // Notes that look like they hold a live secret wait for an
+            // explicit `fuku sync --force`
+            let (records, _) = pre_push(&repo, records);
+            let records = records
+                .into_iter()
+                .filter(|record| leaked_secrets(&record.note).is_empty())
+                .collect();
+            if let Ok(report) = push_notes(&Hub::new(remote), records, &config.sync, |_| {}).await {
+                report.audit(&repo, remote)?;
+            }
+        }
     }
 
-    if all_hits.is_empty() {
-        println!("No notes found across all repositories.");
-        return Ok(());
+    if interrupted {
+        bail!(
+            "Interrupted after importing {} of {} notes; the rest were not imported",
+            imported_count,
+            parsed_count
+        );
     }
 
-    println!("Search Results (across {} repositories)", repo_map.len());
-    render_search_table(&all_hits);
-
-    if let Some(first) = all_hits.first() {
-        if let Some(repo_path) = repo_map.get(&first.object_id) {
-            let short_id = format_object_id(&first.object_id);
-            println!("💡 View: fuku view {} --repo {}", short_id, repo_path);
+    if !cli.quiet {
+        println!();
+        println!("{}Import complete!", plain_text("🎉 ").green());
+        println!();
+        println!("  {} Imported: {}", plain_text("✓").green(), imported_count);
+        if skipped_count > 0 {
+            println!("  {}Skipped: {}", plain_text("⏭️ ").yellow(), skipped_count);
         }
+        if error_count > 0 {
+            println!("  {} Errors: {}", plain_text("✗").red(), error_count);
+        }
+        println!();
+        println!("{}Next steps:", plain_text("💡 "));
+        println!("  fuku list        # View imported notes");
+        println!("  fuku stats       # Check repository stats");
     }
 
     Ok(())
 }
 
-fn find_and_search_repos(
-    dir: &std::path::Path,
-    query: &str,
-    limit: usize,
-    sort: SearchSort,
-    all_hits: &mut Vec<SearchHit>,
-    repo_map: &mut std::collections::HashMap<String, String>,
-) -> Result<()> {
-    // Limit recursion depth
-    const MAX_DEPTH: usize = 3;
-    find_and_search_repos_recursive(dir, query, limit, sort, all_hits, repo_map, 0, MAX_DEPTH)
-}
+/// Notes stored per index commit during `fuku import`
+const IMPORT_BATCH_SIZE: usize = 200;
+
+/// Turn a markdown or text file into a note: the first `# ` heading (or the
+/// file name) is the title, `tags:`/`labels:` lines add tags.
+/// Returns `None` for files without content.
+fn parse_import_file(file: &Path, default_tag: Option<&str>) -> Result<Option<Note>> {
+    let content = fs::read_to_string(file)?;
+
+    // Parse markdown - extract title from first heading or filename
+    let lines: Vec<&str> = content.lines().collect();
+    let title = lines
+        .iter()
+        .find(|line| line.starts_with("# "))
+        .map(|line| line.trim_start_matches("# ").trim().to_string())
+        .or_else(|| {
+            file.file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| "Imported Note".to_string());
 
-#[allow(clippy::too_many_arguments)]
-fn find_and_search_repos_recursive(
-    dir: &std::path::Path,
-    query: &str,
-    limit: usize,
-    sort: SearchSort,
-    all_hits: &mut Vec<SearchHit>,
-    repo_map: &mut std::collections::HashMap<String, String>,
-    depth: usize,
-    max_depth: usize,
-) -> Result<()> {
-    use std::fs;
+    // Remove title from body if it exists
+    let body = if lines.first().map(|l| l.starts_with("# ")).unwrap_or(false) {
+        lines[1..].join("\n").trim().to_string()
+    } else {
+        content.trim().to_string()
+    };
 
-    if depth > max_depth {
-        return Ok(());
+    if body.is_empty() {
+        return Ok(None);
     }
 
-    // Check if this directory has .fukura
-    let fukura_dir = dir.join(".fukura");
-    if fukura_dir.exists() && fukura_dir.is_dir() {
-        // Found a Fukura repository
-        if let Ok(repo) = FukuraRepo::open(dir) {
-            if let Ok(hits) = repo.search(query, limit, sort) {
-                for hit in hits {
-                    repo_map.insert(hit.object_id.clone(), dir.display().to_string());
-                    all_hits.push(hit);
-                }
-            }
-        }
-        return Ok(()); // Don't recurse into subdirectories of a repo
+    // Extract tags from content (e.g., #tag)
+    let mut tags = Vec::new();
+    if let Some(default_tag) = default_tag {
+        tags.push(default_tag.to_string());
     }
 
-    // Recurse into subdirectories
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            if let Ok(file_type) = entry.file_type() {
-                if file_type.is_dir() {
-                    let path = entry.path();
-                    // Skip hidden directories and common exclude patterns
-                    if let Some(name) = path.file_name() {
-                        let name_str = name.to_string_lossy();
-                        if name_str.starts_with('.')
-                            || name_str == "node_modules"
-                            || name_str == "target"
-                        {
-                            continue;
-                        }
-                    }
-                    let _ = find_and_search_repos_recursive(
-                        &path,
-                        query,
-                        limit,
-                        sort,
-                        all_hits,
-                        repo_map,
-                        depth + 1,
-                        max_depth,
-                    );
+    // Look for tags in format #tag or tags: tag1, tag2
+    for line in lines.iter() {
+        if line.to_lowercase().starts_with("tags:") || line.to_lowercase().starts_with("labels:") {
+            let tag_str = line.split(':').nth(1).unwrap_or("");
+            for tag in tag_str.split(',') {
+                let cleaned = tag.trim().trim_matches('#').to_lowercase();
+                if !cleaned.is_empty() && !tags.contains(&cleaned) {
+                    tags.push(cleaned);
                 }
             }
         }
     }
 
-    Ok(())
-}
-
-fn handle_view(cli: &Cli, cmd: &ViewCommand) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let resolved = repo.resolve_object_id(&cmd.id)?;
-    let record = repo.load_note(&resolved)?;
-    if cmd.json {
-        let json = serde_json::to_string_pretty(&record)?;
-        println!("{}", json);
-    } else {
-        render_note(&record);
-    }
-    Ok(())
+    Ok(Some(
+        NoteBuilder::new(title, body)
+            .tags(tags)
+            .author(resolve_author(None, None))
+            .build(),
+    ))
 }
 
-fn handle_edit(cli: &Cli, cmd: &EditCommand) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let resolved = repo.resolve_object_id(&cmd.id)?;
-    let mut record = repo.load_note(&resolved)?;
-
-    let mut modified = false;
-
-    // Update title
-    if let Some(new_title) = &cmd.title {
-        let trimmed = new_title.trim();
-        if trimmed.is_empty() {
-            bail!("Title cannot be empty. Use --title 'New Title' with actual content.");
-        }
-        record.note.title = trimmed.to_string();
-        modified = true;
-    }
-
-    // Update body
-    if let Some(new_body) = &cmd.body {
-        record.note.body = new_body.trim().to_string();
-        modified = true;
-    } else if cmd.editor {
-        // Open editor for body
-        if let Some(edited) = Editor::new().edit(&record.note.body)? {
-            record.note.body = edited.trim().to_string();
-            modified = true;
-        }
-    }
+async fn handle_rec(cli: &Cli, cmd: &RecCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let store = RecordingStore::new(repo.root());
 
-    // Add tags
-    if !cmd.add_tags.is_empty() {
-        let normalized = normalize_tags(cmd.add_tags.clone());
-        for tag in normalized {
-            if !record.note.tags.contains(&tag) {
-                record.note.tags.push(tag);
-                modified = true;
-            }
-        }
-        record.note.tags.sort();
+    if cmd.status {
+        return print_recording_status(cli, &store, cmd.name.as_deref());
     }
 
-    // Remove tags
-    if !cmd.remove_tags.is_empty() {
-        let normalized = normalize_tags(cmd.remove_tags.clone());
-        for tag in normalized {
-            if let Some(pos) = record.note.tags.iter().position(|t| t == &tag) {
-                record.note.tags.remove(pos);
-                modified = true;
-            }
-        }
+    if cmd.stop {
+        return handle_done(cli, cmd.name.as_deref(), false).await;
     }
 
-    if !modified {
-        if !cli.quiet {
-            println!("{} No changes made", "ℹ️".blue());
-            println!(
-                "💡 Use --title, --body, --add-tag, --remove-tag, or --editor to make changes"
-            );
+    // Require title if not status/stop
+    let title = match &cmd.title {
+        Some(t) => t,
+        None => {
+            bail!("Title required. Usage: fuku rec \"Task description\" [TIME_AGO]");
         }
+    };
+
+    repo.ensure_writable()?;
+    let name = cmd.name.as_deref().unwrap_or(DEFAULT_RECORDING);
+    if already_recording(cli, &store, name)? {
         return Ok(());
     }
 
-    // Update timestamp
-    record.note.updated_at = chrono::Utc::now();
+    // Handle time-based recording - should not reach here now
+    if cmd.from_time.is_some() {
+        bail!("Time-based recording should be handled by async version");
+    }
 
-    // Store updated note
-    let new_record = repo.store_note(record.note)?;
+    // Start new recording (normal case)
+    let recording =
+        ActiveRecording::new(name, title, SystemTime::now(), recording_directory(name)?);
+    store.save(&recording)?;
 
     if !cli.quiet {
-        let short_id = format_object_id(&new_record.object_id);
+        println!("{}Recording started", plain_text("🔴 ").red().bold());
+        println!();
+        println!("  {}Task: {}", plain_text("📝 "), title.bold());
         println!(
-            "{} Updated note {} ({})",
-            "✓".green(),
-            new_record.note.title.bold(),
-            short_id
+            "  {}Session ID: {}",
+            plain_text("🎯 "),
+            recording.session_id
         );
-        if !new_record.note.tags.is_empty() {
-            println!("  #{}", new_record.note.tags.join(" #"));
+        if let Some(dir) = &recording.directory {
+            println!("  {}Commands in: {}", plain_text("📂 "), dir.display());
         }
+        println!();
+        println!(
+            "{}All commands will be recorded automatically",
+            plain_text("💡 ")
+        );
+        println!("   Run '{}' when finished", done_command(name));
+        println!();
+        println!("Examples of what gets recorded:");
+        println!("  {} Every command you run", plain_text("•"));
+        println!("  {} Success/failure status", plain_text("•"));
+        println!("  {} Working directory", plain_text("•"));
+        println!("  {} Timestamps", plain_text("•"));
+    }
+    if cmd.cast {
+        record_cast(cli, &store, &recording, &cmd.shell)?;
     }
 
     Ok(())
 }
 
-fn handle_open(cli: &Cli, cmd: &OpenCommand) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let resolved = repo.resolve_object_id(&cmd.id)?;
-    let record = repo.load_note(&resolved)?;
-    let theme = cmd.theme.to_lowercase();
-    let html = render_note_html(&record, &theme)?;
-    let filename = format!("fuku-{}.html", resolved);
-
-    if cmd.url_only {
-        // Just show the URL for manual opening
-        let file_path = std::env::temp_dir().join(&filename);
-        fs::write(&file_path, html)?;
-
-        if !cli.quiet {
-            println!("{} Note saved to: {}", "".blue(), file_path.display());
-            println!("{} Open this file in your browser manually", "".yellow());
-        }
-        return Ok(());
+/// `fuku rec --cast`: run a shell in a pseudo-terminal and keep what it
+/// showed as an attachment of the recording
+fn record_cast(
+    cli: &Cli,
+    store: &RecordingStore,
+    recording: &ActiveRecording,
+    shell: &[String],
+) -> Result<()> {
+    let (program, args) = match shell.split_first() {
+        Some((program, args)) => (program.clone(), args.to_vec()),
+        None => (
+            std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()),
+            Vec::new(),
+        ),
+    };
+    let dir = store.attachments_dir(recording);
+    std::fs::create_dir_all(&dir)?;
+    if !cli.quiet {
+        println!();
+        println!(
+            "{}Recording the terminal in {}; exit it to stop",
+            plain_text("🎬 ").cyan(),
+            program.bold()
+        );
+    }
+    let path = dir.join(crate::asciicast::CAST_FILE);
+    let code = crate::asciicast::record(&program, &args, &path, &recording.title)?;
+    if !cli.quiet {
+        println!(
+            "{} Terminal recording saved ({} exited with {})",
+            plain_text("✓").green(),
+            program,
+            code
+        );
+        println!(
+            "{}Run '{}' to save the note",
+            plain_text("💡 "),
+            done_command(&recording.name)
+        );
     }
+    Ok(())
+}
 
-    if cmd.browser_only {
-        // Try direct browser opening only
-        let file_path = std::env::temp_dir().join(&filename);
-        fs::write(&file_path, html)?;
+/// Named recordings run alongside others, so each keeps to the directory it
+/// was started in; the default one records everything
+fn recording_directory(name: &str) -> Result<Option<PathBuf>> {
+    if name == DEFAULT_RECORDING {
+        return Ok(None);
+    }
+    Ok(Some(std::env::current_dir()?))
+}
 
-        match crate::browser::BrowserOpener::open(&file_path) {
-            Ok(()) => {
-                if !cli.quiet {
-                    println!("{} Opened note in your browser", "".magenta());
-                }
-            }
-            Err(e) => {
-                if !cli.quiet {
-                    println!("{} Could not open browser: {}", "".red(), e);
-                    println!("{} File saved to: {}", "".blue(), file_path.display());
-                }
-                return Err(e);
-            }
-        }
+fn done_command(name: &str) -> String {
+    if name == DEFAULT_RECORDING {
+        "fuku done".to_string()
     } else {
-        // Use smart opening with server fallback
-        match crate::browser::BrowserOpener::open_with_server(&html, &filename) {
-            Ok(()) => {
-                if !cli.quiet {
-                    println!("{} Opened note in your browser", "".magenta());
-                }
-            }
-            Err(e) => {
-                // Fallback: save to file and show path
-                let file_path = std::env::temp_dir().join(&filename);
-                fs::write(&file_path, html)?;
+        format!("fuku done --name {}", name)
+    }
+}
 
-                if !cli.quiet {
-                    println!(
-                        "{} Could not open browser automatically: {}",
-                        "".yellow(),
-                        e
-                    );
-                    println!(
-                        "{} Please open this file manually: {}",
-                        "".blue(),
-                        file_path.display()
-                    );
-                }
-            }
+/// `fuku rec --status`: every recording in progress, or only `name`
+fn print_recording_status(cli: &Cli, store: &RecordingStore, name: Option<&str>) -> Result<()> {
+    let recordings = match name {
+        Some(name) => store.load(name)?.into_iter().collect(),
+        None => store.list()?,
+    };
+    if cli.quiet {
+        return Ok(());
+    }
+    if recordings.is_empty() {
+        println!("{} Not recording", plain_text("ℹ️").blue());
+        println!();
+        println!("{}Start recording:", plain_text("💡 "));
+        println!("  fuku rec \"Task description\"");
+        println!("  fuku rec \"Task\" 3m ago      # Start from 3 minutes ago");
+        println!("  fuku rec \"Task\" --name infra # Alongside others, in this directory");
+        return Ok(());
+    }
+    for recording in &recordings {
+        println!("{}Recording in progress", plain_text("🔴 ").red());
+        println!();
+        println!("  {}Task: {}", plain_text("📝 "), recording.title.bold());
+        if recording.name != DEFAULT_RECORDING {
+            println!("  {} Name: {}", plain_text("🏷️ "), recording.name);
+        }
+        match &recording.directory {
+            Some(dir) => println!("  {}Commands in: {}", plain_text("📂 "), dir.display()),
+            None => println!("  {} All commands are being recorded", plain_text("⏱️ ")),
         }
+        println!();
+    }
+    println!("{}When done:", plain_text("💡 "));
+    for recording in &recordings {
+        println!(
+            "  {:<24} # Save and stop '{}'",
+            done_command(&recording.name),
+            recording.title
+        );
     }
-
     Ok(())
 }
 
-fn handle_stats(cli: &Cli) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let config = repo.config()?;
-
-    // Get all notes
-    let all_notes = repo.list_all_notes()?;
-    let total_notes = all_notes.len();
+/// Warn when a recording called `name` is already running
+fn already_recording(cli: &Cli, store: &RecordingStore, name: &str) -> Result<bool> {
+    let Some(recording) = store.load(name)? else {
+        return Ok(false);
+    };
+    if !cli.quiet {
+        println!(
+            "{} Already recording: {}",
+            plain_text("⚠️").yellow(),
+            recording.title
+        );
+        println!();
+        println!("{}Options:", plain_text("💡 "));
+        println!("  {:<18} # Finish current recording", done_command(name));
+        println!("  fuku rec --name NAME  # Start another one alongside it");
+    }
+    Ok(true)
+}
 
-    // Collect tags
-    let all_tags = repo.collect_tags().unwrap_or_default();
+/// Files touched during a recording, relative to the repo root, with their last change
+fn recorded_file_changes(root: &Path, session_id: &str) -> BTreeMap<PathBuf, String> {
+    use crate::activity::{ActivityType, FileChangeType};
 
-    // Find last updated note
-    let last_updated = all_notes.iter().map(|n| n.note.updated_at).max();
+    let mut files = BTreeMap::new();
+    let Ok(session) = crate::activity_storage::ActivityStorage::new(root)
+        .and_then(|storage| storage.load_session(session_id))
+    else {
+        return files;
+    };
+    for activity in &session.activities {
+        if let ActivityType::FileChange(change) = &activity.activity_type {
+            let path = change
+                .path
+                .strip_prefix(root)
+                .unwrap_or(&change.path)
+                .to_path_buf();
+            let label = match (&change.change_type, files.get(&path).map(String::as_str)) {
+                // A file created and then edited is still a new file
+                (FileChangeType::Modified, Some("created")) => "created",
+                (FileChangeType::Created, _) => "created",
+                (FileChangeType::Modified, _) => "modified",
+                (FileChangeType::Deleted, _) => "deleted",
+                (FileChangeType::Renamed { .. }, _) => "renamed",
+            };
+            files.insert(path, label.to_string());
+        }
+    }
+    files
+}
 
-    // Calculate storage size
-    let objects_dir = repo.objects_dir();
-    let packs_dir = repo.pack_dir();
-    let mut total_size = 0u64;
-    let mut loose_count = 0usize;
+fn handle_replay(cli: &Cli, cmd: &ReplayCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let record = repo.load_note(&resolved)?;
+    let steps = crate::recording::parse_steps(&record.note.body);
+    ensure!(
+        !steps.is_empty(),
+        "Note {} has no recorded command steps\n💡 Tip: Record a session with 'fuku rec' and finish it with 'fuku done'",
+        format_object_id(&resolved)
+    );
 
-    if objects_dir.exists() {
-        for entry in walkdir::WalkDir::new(&objects_dir)
-            .max_depth(3)
-            .into_iter()
-            .flatten()
-        {
-            if entry.file_type().is_file() {
-                if let Ok(metadata) = entry.metadata() {
-                    total_size += metadata.len();
-                    loose_count += 1;
+    if cmd.script {
+        let script = crate::recording::replay_script(
+            &record.note.title,
+            &format_object_id(&resolved),
+            &steps,
+        );
+        match &cmd.out {
+            Some(path) => {
+                fs::write(path, script)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+                }
+                if !cli.quiet {
+                    println!("{} Wrote {}", plain_text("✅").green(), path.display());
                 }
             }
+            None => print!("{}", script),
         }
+        return Ok(());
     }
 
-    let mut pack_count = 0usize;
-    if packs_dir.exists() {
-        for entry in (fs::read_dir(&packs_dir)?).flatten() {
-            if entry.file_type()?.is_file() {
-                if let Ok(metadata) = entry.metadata() {
-                    total_size += metadata.len();
-                    pack_count += 1;
-                }
-            }
+    println!("{}", record.note.title.bold());
+    println!();
+    for (idx, step) in steps.iter().enumerate() {
+        let status = match step.exit_code {
+            Some(0) => plain_text("✓").green().to_string(),
+            Some(code) => format!("{} {}", plain_text("✗"), code).red().to_string(),
+            None => "?".dimmed().to_string(),
+        };
+        let mut line = format!("{:>3}. {} {}", idx + 1, status, step.command);
+        if let Some(failed) = step.fixes {
+            line.push_str(&format!(" {}", format!("(fixes {})", failed + 1).dimmed()));
+        }
+        println!("{}", line);
+        if let Some(error) = &step.error {
+            println!("       {}", error.red());
         }
     }
-
-    // Format size
-    let size_str = if total_size < 1024 {
-        format!("{}B", total_size)
-    } else if total_size < 1024 * 1024 {
-        format!("{:.1}KB", total_size as f64 / 1024.0)
-    } else {
-        format!("{:.1}MB", total_size as f64 / (1024.0 * 1024.0))
-    };
-
     if !cli.quiet {
-        println!("{}", "📊 Repository Statistics".bold().cyan());
         println!();
         println!(
-            "  {} Total notes: {}",
-            "".yellow(),
-            total_notes.to_string().bold()
-        );
-        println!(
-            "  {} Tags: {} unique",
-            "🏷️".yellow(),
-            all_tags.len().to_string().bold()
+            "{}Tip: Export a runnable script with 'fuku replay {} --script'",
+            plain_text("💡 "),
+            format_object_id(&resolved)
         );
+    }
+    Ok(())
+}
 
-        if let Some(last_updated) = last_updated {
-            let time_ago = chrono::Utc::now() - last_updated;
-            let ago_str = if time_ago.num_minutes() < 60 {
-                format!("{} minutes ago", time_ago.num_minutes())
-            } else if time_ago.num_hours() < 24 {
-                format!("{} hours ago", time_ago.num_hours())
-            } else {
-                format!("{} days ago", time_ago.num_days())
-            };
-            println!("  {} Last updated: {}", "🕒".yellow(), ago_str.bold());
+fn handle_history(cli: &Cli, cmd: &HistoryCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let log = CommandLog::new(repo.root())?;
+
+    let mut records = match &cmd.since {
+        Some(expr) => {
+            let since = parse_instant(expr)?;
+            log.read_since(since.into())?
         }
+        None => log.read_all()?,
+    };
+    if cmd.failed {
+        records.retain(CommandRecord::failed);
+    }
+    if let Some(expr) = &cmd.slower_than {
+        // Commands captured without a duration never match
+        let threshold = crate::time_parser::parse_duration(expr)?.to_std()?;
+        records.retain(|record| record.duration().is_some_and(|took| took >= threshold));
+    }
+    let skip = records.len().saturating_sub(cmd.limit);
+    let records = &records[skip..];
 
-        println!();
-        println!("  {} Storage: {}", "💾".yellow(), size_str.bold());
-        println!("    • Loose objects: {}", loose_count);
-        println!("    • Pack files: {}", pack_count);
+    if cmd.json {
+        for record in records {
+            println!("{}", serde_json::to_string(record)?);
+        }
+        return Ok(());
+    }
+    if cli.output.emit(records)? {
+        return Ok(());
+    }
 
-        println!();
-        println!("  {} Configuration:", "⚙️".yellow());
-        if let Some(remote) = &config.default_remote {
-            println!("    • Remote: {}", remote);
-        } else {
-            println!("    • Remote: {}", "not set".dimmed());
+    if records.is_empty() {
+        if !cli.quiet {
+            println!("{} No commands recorded yet", plain_text("ℹ️").blue());
+            println!();
+            println!(
+                "{}Tip: Commands are captured while the daemon runs ('fuku daemon')",
+                plain_text("💡 ")
+            );
         }
-        println!(
-            "    • Auto-sync: {}",
-            if config.auto_sync.unwrap_or(false) {
-                "enabled".green()
-            } else {
-                "disabled".red()
+        return Ok(());
+    }
+
+    for record in records {
+        let time = chrono::DateTime::<chrono::Local>::from(record.timestamp)
+            .format("%m-%d %H:%M:%S")
+            .to_string();
+        let status = match record.exit_code {
+            Some(0) => plain_text("✓").green().to_string(),
+            Some(code) => format!("{} {}", plain_text("✗"), code).red().to_string(),
+            None => "?".dimmed().to_string(),
+        };
+        let took = match record.duration() {
+            Some(took) if took >= SLOW_COMMAND => {
+                format!(" ({})", format_duration(took)).yellow().to_string()
             }
+            Some(took) if took.as_secs() > 0 => {
+                format!(" ({})", format_duration(took)).dimmed().to_string()
+            }
+            _ => String::new(),
+        };
+        println!(
+            "{} {:>5} {}{}  {}",
+            time.dimmed(),
+            status,
+            record.command,
+            took,
+            record.working_directory.dimmed()
         );
+    }
+    Ok(())
+}
 
-        if config.daemon_enabled.unwrap_or(false) {
-            println!("    • Daemon: {}", "enabled".green());
-        } else {
-            println!("    • Daemon: {}", "disabled".dimmed());
-        }
+fn handle_audit(cli: &Cli, cmd: &AuditCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let since = cmd.since.as_deref().map(parse_instant).transpose()?;
+    let mut entries = repo.audit_log().read_since(since)?;
+    if !cmd.action.is_empty() {
+        entries.retain(|entry| cmd.action.contains(&entry.action));
+    }
+    if let Some(id) = &cmd.id {
+        // Deleted notes can't be resolved; match their id as given
+        let id = repo
+            .resolve_object_id(id)
+            .unwrap_or_else(|_| id.trim().to_string());
+        entries.retain(|entry| {
+            entry
+                .object_ids
+                .iter()
+                .any(|object| object.starts_with(&id))
+        });
+    }
+    let skip = entries.len().saturating_sub(cmd.limit);
+    let entries = &entries[skip..];
+    if cli.output.emit(entries)? {
+        return Ok(());
+    }
 
-        println!();
-        println!("💡 Tips:");
-        if loose_count > 10 {
-            println!("  • Run 'fuku gc' to pack loose objects");
+    if entries.is_empty() {
+        if !cli.quiet {
+            println!("{} No changes recorded", plain_text("ℹ️").blue());
         }
-        if config.default_remote.is_none() {
-            println!("  • Set remote: 'fuku config remote --set <url>'");
+        return Ok(());
+    }
+    for entry in entries {
+        let time = chrono::DateTime::<chrono::Local>::from(entry.at)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let ids: Vec<String> = entry
+            .object_ids
+            .iter()
+            .map(|id| format_object_id(id))
+            .collect();
+        let mut line = format!(
+            "{} {:<9} {}",
+            time.dimmed(),
+            entry.action.as_str().bold(),
+            entry.actor
+        );
+        if !ids.is_empty() {
+            let shown = match entry.action {
+                // Previous id first
+                AuditAction::Edit | AuditAction::Merge => ids.join(&plain_text(" → ")),
+                _ if ids.len() > 3 => {
+                    format!("{} (+{} more)", ids[..3].join(" "), ids.len() - 3)
+                }
+                _ => ids.join(" "),
+            };
+            line.push_str(&format!("  {}", shown.cyan()));
         }
+        if let Some(detail) = &entry.detail {
+            line.push_str(&format!("  {}", detail.dimmed()));
+        }
+        println!("{}", line);
     }
-
     Ok(())
 }
 
-fn handle_completions(cli: &Cli, cmd: &CompletionsCommand) -> Result<()> {
-    let shell = match cmd.shell.to_lowercase().as_str() {
-        "bash" => Shell::Bash,
-        "zsh" => Shell::Zsh,
-        "fish" => Shell::Fish,
-        "powershell" | "pwsh" => Shell::PowerShell,
-        _ => bail!(
-            "Unsupported shell: {}. Choose from: bash, zsh, fish, powershell",
-            cmd.shell
-        ),
+fn handle_patterns(cli: &Cli, cmd: &PatternsCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let store = crate::patterns::PatternStore::new(repo.root());
+    let mut patterns: Vec<_> = store.load()?.into_values().collect();
+    patterns.sort_by(|a, b| {
+        b.occurrences
+            .cmp(&a.occurrences)
+            .then(b.last_seen.cmp(&a.last_seen))
+    });
+    let total = patterns.len();
+    patterns.truncate(cmd.limit);
+
+    let now = SystemTime::now();
+    let window = StdDuration::from_secs(cmd.days.max(1) * 86_400);
+    let local_time = |time: SystemTime| {
+        chrono::DateTime::<chrono::Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
     };
 
-    if cmd.stdout {
-        // Generate to stdout
-        let mut app = Cli::command();
-        generate(shell, &mut app, "fuku", &mut std::io::stdout());
+    // Resolution notes recorded by the daemon, plus captured notes that later got solutions
+    let resolved_notes = |pattern: &crate::patterns::ErrorPattern| -> Vec<(String, String)> {
+        let mut resolved = Vec::new();
+        for id in pattern.resolved_by.iter().chain(&pattern.notes) {
+            let Ok(record) = repo.load_note(id) else {
+                continue;
+            };
+            let is_resolution =
+                pattern.resolved_by.contains(id) || !record.note.solutions.is_empty();
+            if is_resolution && !resolved.iter().any(|(existing, _)| existing == id) {
+                resolved.push((id.clone(), record.note.title));
+            }
+        }
+        resolved
+    };
+
+    let format = cli.output.or_json(cmd.json);
+    if format.is_structured() {
+        let output: Vec<serde_json::Value> = patterns
+            .iter()
+            .map(|pattern| {
+                let trend = pattern.trend(now, window);
+                serde_json::json!({
+                    "fingerprint": pattern.fingerprint,
+                    "message": pattern.normalized_message,
+                    "occurrences": pattern.occurrences,
+                    "recent": trend.current,
+                    "previous": trend.previous,
+                    "first_seen": chrono::DateTime::<Utc>::from(pattern.first_seen).to_rfc3339(),
+                    "last_seen": chrono::DateTime::<Utc>::from(pattern.last_seen).to_rfc3339(),
+                    "last_command": pattern.last_command,
+                    "notes": pattern.notes,
+                    "resolved_by": resolved_notes(pattern)
+                        .into_iter()
+                        .map(|(id, _)| id)
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        format.emit(&output)?;
         return Ok(());
     }
 
-    // Install completions
-    let home = std::env::var("HOME").context("HOME environment variable not set")?;
-    let home_path = std::path::PathBuf::from(&home);
+    if patterns.is_empty() {
+        if !cli.quiet {
+            println!("{} No error patterns recorded yet", plain_text("ℹ️").blue());
+            println!();
+            println!("{}Tip: Failed commands are grouped into patterns while the daemon runs ('fuku daemon')", plain_text("💡 "));
+        }
+        return Ok(());
+    }
 
-    let (install_path, instructions) = match shell {
-        Shell::Bash => {
-            let path = home_path.join(".bash_completion.d");
-            fs::create_dir_all(&path)?;
-            let file = path.join("fuku");
-            (
-                file,
-                format!(
-                    "Add 'source {}' to your ~/.bashrc",
-                    path.join("fuku").display()
-                ),
-            )
+    println!(
+        "{}Most frequent errors ({} of {}, trend over {} days)",
+        plain_text("📊 ").cyan(),
+        patterns.len(),
+        total,
+        cmd.days.max(1)
+    );
+    println!();
+    for (idx, pattern) in patterns.iter().enumerate() {
+        let trend = pattern.trend(now, window);
+        println!("{:>2}. {}", idx + 1, pattern.normalized_message.bold());
+        println!(
+            "    {}{} total {} {} recent {} ({} before) {} last seen {}",
+            pattern.occurrences,
+            plain_text("×"),
+            plain_text("·"),
+            trend.current,
+            trend.arrow(),
+            trend.previous,
+            plain_text("·"),
+            local_time(pattern.last_seen)
+        );
+        if let Some(command) = &pattern.last_command {
+            println!("    {}", format!("$ {}", command).dimmed());
         }
-        Shell::Zsh => {
-            let path = home_path.join(".zsh").join("completions");
-            fs::create_dir_all(&path)?;
-            let file = path.join("_fuku");
-            (
-                file,
-                "Add 'fpath=(~/.zsh/completions $fpath)' to your ~/.zshrc and run 'compinit'"
-                    .to_string(),
-            )
+        let resolved = resolved_notes(pattern);
+        if resolved.is_empty() {
+            println!("    {} Not resolved yet", plain_text("•").yellow());
         }
-        Shell::Fish => {
-            let path = home_path.join(".config").join("fish").join("completions");
-            fs::create_dir_all(&path)?;
-            let file = path.join("fuku.fish");
-            (file, "Completions will be loaded automatically".to_string())
+        for (id, title) in resolved {
+            println!(
+                "    {} Resolved by {} {}",
+                plain_text("✅").green(),
+                format_object_id(&id).cyan(),
+                title
+            );
         }
-        Shell::PowerShell => {
-            bail!("PowerShell completions should be output to stdout and added to your $PROFILE manually.\nRun: fuku completions powershell --stdout >> $PROFILE");
+        if !pattern.notes.is_empty() {
+            let shown: Vec<String> = pattern
+                .notes
+                .iter()
+                .rev()
+                .take(3)
+                .map(|id| format_object_id(id))
+                .collect();
+            let more = pattern.notes.len().saturating_sub(shown.len());
+            let mut line = shown.join(", ");
+            if more > 0 {
+                line.push_str(&format!(" (+{} more)", more));
+            }
+            println!("    {}Notes: {}", plain_text("📝 ").blue(), line);
         }
-        _ => unreachable!(),
-    };
-
-    // Generate completion file
-    let mut file = fs::File::create(&install_path)?;
-    let mut app = Cli::command();
-    generate(shell, &mut app, "fuku", &mut file);
-
-    if !cli.quiet {
-        println!("{} Shell completions installed!", "✓".green());
-        println!();
-        println!("📍 Location: {}", install_path.display());
-        println!();
-        println!("📝 Next steps:");
-        println!("   {}", instructions);
         println!();
-        println!("💡 Restart your shell or run:");
-        match shell {
-            Shell::Bash => println!("   source ~/.bashrc"),
-            Shell::Zsh => println!("   source ~/.zshrc"),
-            Shell::Fish => println!("   # Completions load automatically"),
-            _ => {}
-        }
     }
-
+    if !cli.quiet {
+        println!(
+            "{}Tip: Open a note with 'fuku view <id>'",
+            plain_text("💡 ")
+        );
+    }
     Ok(())
 }
 
-fn handle_alias(cli: &Cli, cmd: &AliasCommand) -> Result<()> {
-    let aliases = vec![
-        ("fa", "fuku add -q"),
-        ("fl", "fuku list"),
-        ("fs", "fuku search"),
-        ("fv", "fuku view"),
-        ("fe", "fuku edit"),
-        ("fo", "fuku open"),
-        ("fst", "fuku stats"),
-        ("fsy", "fuku sync"),
-    ];
-
-    if cmd.show {
-        if !cli.quiet {
-            println!("{}", "📝 Recommended Aliases".bold().cyan());
-            println!();
-            for (alias, command) in &aliases {
-                println!("  {} = {}", alias.green(), command.dimmed());
-            }
-        }
-        return Ok(());
+fn handle_report(cli: &Cli, cmd: &ReportCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let (since, until) = parse_range(&cmd.since)?;
+    let until = match &cmd.until {
+        Some(until) => parse_instant(until)?,
+        None => until,
+    };
+    if until <= since {
+        bail!("--until must be after --since");
     }
+    let report = crate::report::Report::for_repo(&repo, since.into(), until.into())?;
 
-    if cmd.remove {
-        // Detect shell
-        let shell = std::env::var("SHELL").unwrap_or_default();
-        let shell_name = std::path::Path::new(&shell)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("bash");
-
-        let home = std::env::var("HOME").context("HOME not set")?;
-        let rc_file = match shell_name {
-            "zsh" => format!("{}/.zshrc", home),
-            "fish" => format!("{}/.config/fish/config.fish", home),
-            "bash" => format!("{}/.bashrc", home),
-            _ => format!("{}/.bashrc", home),
+    if cmd.email {
+        let config = repo.config()?;
+        let Some(smtp) = &config.smtp else {
+            bail!("No [smtp] section in the config; add one to send reports by email");
         };
-
-        if !std::path::Path::new(&rc_file).exists() {
-            bail!("RC file not found: {}", rc_file);
-        }
-
-        let content = fs::read_to_string(&rc_file)?;
-        let lines: Vec<&str> = content
-            .lines()
-            .filter(|line| {
-                !line.contains("# Fukura aliases")
-                    && !aliases
-                        .iter()
-                        .any(|(a, _)| line.contains(&format!("alias {}=", a)))
-            })
-            .collect();
-
-        fs::write(&rc_file, lines.join("\n"))?;
-
+        let to = if cmd.to.is_empty() { &smtp.to } else { &cmd.to };
+        let recipients = crate::mail::Mailer::new(smtp)?.send_report(&report, to)?;
         if !cli.quiet {
-            println!("{} Aliases removed from {}", "✓".yellow(), rc_file);
+            println!(
+                "{} Sent the report for {} to {}",
+                plain_text("✓").green(),
+                report.period(),
+                recipients.join(", ")
+            );
         }
         return Ok(());
     }
 
-    if cmd.setup {
-        // Detect shell
-        let shell = std::env::var("SHELL").unwrap_or_default();
-        let shell_name = std::path::Path::new(&shell)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("bash");
+    if cmd.markdown {
+        print!("{}", report.to_markdown());
+        return Ok(());
+    }
+    if cmd.html {
+        print!("{}", report.to_html());
+        return Ok(());
+    }
+    if cli.quiet {
+        return Ok(());
+    }
 
-        let home = std::env::var("HOME").context("HOME not set")?;
-        let rc_file = match shell_name {
-            "zsh" => format!("{}/.zshrc", home),
-            "fish" => format!("{}/.config/fish/config.fish", home),
-            "bash" => format!("{}/.bashrc", home),
-            _ => format!("{}/.bashrc", home),
-        };
+    println!(
+        "{} {}",
+        plain_text("📋 Report").bold().cyan(),
+        report.period().dimmed()
+    );
+    println!();
+    println!(
+        "  {} {} problems solved",
+        plain_text("✓").green(),
+        report.problems_solved.len().to_string().bold()
+    );
+    println!(
+        "  {} {} errors captured",
+        plain_text("✗").red(),
+        report.errors_captured.len().to_string().bold()
+    );
+    println!(
+        "  {}{} recordings",
+        plain_text("⏺ ").magenta(),
+        report.recordings.len().to_string().bold()
+    );
+    println!(
+        "  {}{} new notes",
+        plain_text("📝 ").yellow(),
+        report.new_notes.len().to_string().bold()
+    );
+    println!(
+        "  {} {} commands run ({} failed)",
+        "$".dimmed(),
+        report.commands.to_string().bold(),
+        report.failed_commands
+    );
 
-        // Check if aliases already exist
-        if std::path::Path::new(&rc_file).exists() {
-            let content = fs::read_to_string(&rc_file)?;
-            if content.contains("# Fukura aliases") {
-                if !cli.quiet {
-                    println!("{} Aliases already installed in {}", "ℹ️".blue(), rc_file);
-                    println!("💡 Use 'fuku alias --remove' to uninstall");
-                }
-                return Ok(());
-            }
+    let sections = [
+        ("Problems solved", &report.problems_solved),
+        ("Errors captured", &report.errors_captured),
+        ("Recordings", &report.recordings),
+        ("New notes", &report.new_notes),
+    ];
+    for (heading, notes) in sections {
+        if notes.is_empty() {
+            continue;
         }
-
-        let mut alias_lines = vec!["\n# Fukura aliases".to_string()];
-
-        for (alias, command) in &aliases {
-            let alias_line = if shell_name == "fish" {
-                format!("alias {} '{}'", alias, command)
-            } else {
-                format!("alias {}='{}'", alias, command)
-            };
-            alias_lines.push(alias_line);
+        println!();
+        println!("{}", heading.bold());
+        for note in notes.iter() {
+            println!(
+                "  {} {}",
+                format_object_id(&note.object_id).dimmed(),
+                note.title
+            );
         }
-        alias_lines.push("# End Fukura aliases\n".to_string());
+    }
+    if !report.top_errors.is_empty() {
+        println!();
+        println!("{}", "Most frequent errors".bold());
+        for (message, count) in &report.top_errors {
+            println!(
+                "  {}{} {}",
+                count.to_string().red(),
+                plain_text("×"),
+                message
+            );
+        }
+    }
+    println!();
+    println!(
+        "{}Tip: Share it with 'fuku report --markdown' or '--html'",
+        plain_text("💡 ")
+    );
+    Ok(())
+}
 
-        // Append to rc file
-        let mut file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&rc_file)?;
-        use std::io::Write;
-        file.write_all(alias_lines.join("\n").as_bytes())?;
+async fn handle_done(cli: &Cli, name: Option<&str>, include_diffs: bool) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let store = RecordingStore::new(repo.root());
 
+    let Some(recording) = store.find(name, &std::env::current_dir()?)? else {
         if !cli.quiet {
-            println!("{} Aliases installed!", "✓".green());
-            println!();
-            println!("📍 Location: {}", rc_file);
-            println!();
-            println!("📝 Aliases:");
-            for (alias, command) in &aliases {
-                println!("  {} → {}", alias.green(), command);
+            match name {
+                Some(name) => println!("{} No recording named '{}'", plain_text("ℹ️").blue(), name),
+                None => println!("{} No recording in progress", plain_text("ℹ️").blue()),
             }
             println!();
-            println!("💡 Restart your shell or run:");
-            match shell_name {
-                "zsh" => println!("   source ~/.zshrc"),
-                "fish" => println!("   source ~/.config/fish/config.fish"),
-                _ => println!("   source ~/.bashrc"),
-            }
+            println!("{}Start recording:", plain_text("💡 "));
+            println!("  fuku rec \"Task description\"");
         }
         return Ok(());
-    }
+    };
+
+    let session_id = recording.session_id.as_str();
+    let title = recording.title.as_str();
 
-    // Default: show aliases
     if !cli.quiet {
-        println!("{}", "📝 Fukura Aliases".bold().cyan());
-        println!();
-        println!("💡 Quick commands for faster workflow:");
-        println!();
-        for (alias, command) in &aliases {
-            println!("  {} = {}", alias.green(), command.dimmed());
-        }
+        println!("{}Finalizing recording...", plain_text("⏹️ ").yellow());
         println!();
-        println!("Usage:");
-        println!("  fuku alias --show    # Show recommended aliases");
-        println!("  fuku alias --setup   # Install aliases to shell rc file");
-        println!("  fuku alias --remove  # Remove installed aliases");
     }
 
-    Ok(())
-}
+    // Create detailed note with command history
+    let now = chrono::Utc::now();
+    // Time-based recordings (`fuku rec "x" 2h`) start at their backfill
+    let start_timestamp = recording
+        .start_time()
+        .and_then(|start| start.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|start| start.as_secs() as i64)
+        .unwrap_or_else(|| now.timestamp());
 
-async fn handle_import(cli: &Cli, cmd: &ImportCommand) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let config = repo.config()?;
+    let since =
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(start_timestamp.max(0) as u64);
+    let mut commands = CommandLog::new(repo.root())
+        .and_then(|log| log.read_since(since))
+        .unwrap_or_default();
+    // Named recordings share the daemon's command log; each keeps its directory
+    commands.retain(|command| recording.includes(command));
 
-    let mut files_to_import = Vec::new();
+    let duration_secs = now.timestamp() - start_timestamp;
+    let duration_mins = duration_secs / 60;
 
-    if cmd.path.is_file() {
-        files_to_import.push(cmd.path.clone());
-    } else if cmd.path.is_dir() {
-        // Recursively find all markdown files
-        for entry in walkdir::WalkDir::new(&cmd.path)
-            .max_depth(10)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file() {
-                if let Some(ext) = entry.path().extension() {
-                    if ext == "md" || ext == "markdown" || ext == "txt" {
-                        files_to_import.push(entry.path().to_path_buf());
-                    }
+    let mut body = String::new();
+    body.push_str("## 🎯 Manual Recording Session\n\n");
+    body.push_str(&format!("**Task**: {}\n", title));
+    body.push_str(&format!("**Duration**: {} minutes\n", duration_mins));
+    body.push_str(&format!(
+        "**Completed**: {}\n\n",
+        now.format("%Y-%m-%d %H:%M UTC")
+    ));
+
+    body.push_str("## 📋 What I Did\n\n");
+    let steps = crate::recording::build_steps(since, &commands);
+    if steps.is_empty() {
+        body.push_str("This recording captured a complete workflow. \n");
+        body.push_str("No commands were captured. Is the daemon running? (`fuku daemon`)\n\n");
+    } else {
+        body.push_str(&crate::recording::render_steps(&steps));
+        body.push('\n');
+    }
+
+    // File changes collected by the daemon's watcher while recording
+    let files_changed = recorded_file_changes(repo.root(), session_id);
+    if !files_changed.is_empty() {
+        body.push_str("## 📂 Files Changed\n\n");
+        let mut diffs = String::new();
+        for (path, change) in &files_changed {
+            let diff = include_diffs
+                .then(|| crate::file_watcher::git_diff(repo.root(), &repo.root().join(path)))
+                .flatten();
+            match &diff {
+                Some((_, added, removed)) => body.push_str(&format!(
+                    "- `{}` ({}, +{} -{})\n",
+                    path.display(),
+                    change,
+                    added,
+                    removed
+                )),
+                None => body.push_str(&format!("- `{}` ({})\n", path.display(), change)),
+            }
+            if let Some((text, _, _)) = diff {
+                let lines: Vec<&str> = text.lines().collect();
+                diffs.push_str("```diff\n");
+                diffs.push_str(&lines[..lines.len().min(200)].join("\n"));
+                if lines.len() > 200 {
+                    diffs.push_str(&format!("\n... ({} more lines)", lines.len() - 200));
                 }
+                diffs.push_str("\n```\n\n");
             }
         }
-    } else {
-        bail!("Path not found: {}", cmd.path.display());
+        body.push('\n');
+        body.push_str(&diffs);
+    }
+
+    // Scrollback attached with `fuku capture`: the pane name, then its text
+    let attachments = store.attachments(&recording)?;
+    let captures: Vec<String> = attachments
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+    if !captures.is_empty() {
+        body.push_str("## 🖥️ Terminal Output\n\n");
+        for capture in &captures {
+            let (pane, text) = capture.split_once('\n').unwrap_or(("tmux", ""));
+            let lines: Vec<&str> = text.lines().collect();
+            let skip = lines.len().saturating_sub(200);
+            body.push_str(&format!("From `{}`:\n\n```\n", pane));
+            if skip > 0 {
+                body.push_str(&format!("... ({} earlier lines)\n", skip));
+            }
+            body.push_str(&lines[skip..].join("\n"));
+            body.push_str("\n```\n\n");
+        }
     }
 
-    if files_to_import.is_empty() {
-        if !cli.quiet {
-            println!(
-                "{} No markdown files found in {}",
-                "ℹ️".blue(),
-                cmd.path.display()
+    // Terminal recorded with `fuku rec --cast`, kept with the note
+    let cast_attachment = match attachments
+        .iter()
+        .find(|path| path.ends_with(crate::asciicast::CAST_FILE))
+    {
+        Some(path) => {
+            let redactor = crate::redaction::Redactor::default_with_overrides(
+                &repo.config()?.redaction_overrides,
             );
+            let cast = crate::asciicast::redact(&std::fs::read_to_string(path)?, &redactor);
+            let attachment = repo.store_attachment(cast.as_bytes())?;
+            body.push_str("## 🎬 Terminal Recording\n\n");
+            body.push_str(&format!(
+                "Watch it with `fuku open` (asciicast attachment {}).\n\n",
+                &attachment[..8]
+            ));
+            Some(attachment)
         }
-        return Ok(());
+        None => None,
+    };
+
+    body.push_str("## 💡 Next Steps\n\n");
+    body.push_str("- Review the full history: `fuku history`\n");
+    body.push_str("- Add manual notes if needed: `fuku edit @latest --editor`\n");
+    body.push_str("- Tag for easy finding: `fuku edit @latest --add-tag <tag>`\n");
+
+    // Auto-detect technology tags from title
+    let mut tags = vec!["recording".to_string(), "workflow".to_string()];
+    let title_lower = title.to_lowercase();
+    if title_lower.contains("kubernetes")
+        || title_lower.contains("k8s")
+        || title_lower.contains("kubectl")
+    {
+        tags.push("kubernetes".to_string());
     }
+    if title_lower.contains("docker") {
+        tags.push("docker".to_string());
+    }
+    if title_lower.contains("git") {
+        tags.push("git".to_string());
+    }
+    if title_lower.contains("deploy") {
+        tags.push("deployment".to_string());
+    }
+    tags.sort();
+    tags.dedup();
 
-    if cmd.dry_run {
+    let mut meta = std::collections::BTreeMap::from([
+        ("session_id".to_string(), session_id.to_string()),
+        ("recording".to_string(), "true".to_string()),
+        ("duration_minutes".to_string(), duration_mins.to_string()),
+        ("command_count".to_string(), steps.len().to_string()),
+        ("files_changed".to_string(), files_changed.len().to_string()),
+    ]);
+    if recording.name != DEFAULT_RECORDING {
+        meta.insert("recording_name".to_string(), recording.name.clone());
+    }
+    if let Some(attachment) = cast_attachment {
+        meta.insert(crate::models::CAST_ATTACHMENT_META.to_string(), attachment);
+    }
+    let mut note = NoteBuilder::new(title, body)
+        .tags(tags)
+        .meta_map(meta)
+        .author(resolve_author(None, None))
+        .created_at(chrono::DateTime::from_timestamp(start_timestamp, 0).unwrap_or(now))
+        .updated_at(now)
+        .build();
+    let config = repo.config()?;
+    if let Some(dir) = &recording.directory {
+        config.capture.confine(&mut note, dir);
+        config.scope.assign(&mut note, repo.root(), dir);
+    }
+    if let Some(summarizer) = Summarizer::from_config(&config) {
         if !cli.quiet {
             println!(
-                "{} Dry run - would import {} files:",
-                "🔍".cyan(),
-                files_to_import.len()
+                "{}Summarizing with {}...",
+                plain_text("🤖 ").blue(),
+                summarizer.model()
             );
-            println!();
-            for file in &files_to_import {
-                println!("  📄 {}", file.display());
+        }
+        if let Err(e) = summarizer.enrich(&mut note).await {
+            if !cli.quiet {
+                println!(
+                    "{} Couldn't summarize the recording: {}",
+                    plain_text("⚠").yellow(),
+                    e
+                );
             }
         }
-        return Ok(());
     }
 
+    let record = NoteService::new(&repo)?.create(note)?.record;
+
+    store.remove(&recording.name)?;
+    store.discard_attachments(&recording)?;
+
     if !cli.quiet {
+        let short_id = format_object_id(&record.object_id);
+        println!("{} Recording saved!", plain_text("✅").green().bold());
+        println!();
+        println!("  {}{}", plain_text("📝 "), record.note.title.bold());
+        println!("  {}{}", plain_text("🆔 "), short_id);
         println!(
-            "{} Importing {} files...",
-            "📥".blue(),
-            files_to_import.len()
+            "  {} Duration: {} minutes",
+            plain_text("⏱️ "),
+            duration_mins
+        );
+        println!(
+            "  {}{} commands, {} files changed",
+            plain_text("📋 "),
+            steps.len(),
+            files_changed.len()
         );
+        if !record.note.tags.is_empty() {
+            println!("  {} #{}", plain_text("🏷️ "), record.note.tags.join(" #"));
+        }
         println!();
+        println!("{}Quick access:", plain_text("💡 "));
+        println!("  fuku view @latest    # View recording");
+        println!("  fuku edit @latest --editor  # Add notes");
+        println!("  fuku open @latest    # Open in browser");
     }
 
-    let mut imported_count = 0;
-    let mut skipped_count = 0;
-    let mut error_count = 0;
+    Ok(())
+}
 
-    for file in &files_to_import {
-        let content = match fs::read_to_string(file) {
-            Ok(c) => c,
-            Err(e) => {
-                if !cli.quiet {
-                    println!("{} Skipped {}: {}", "⏭️".yellow(), file.display(), e);
-                }
-                skipped_count += 1;
-                continue;
-            }
-        };
+/// `fuku gc --output json|yaml`
+#[derive(Debug, Serialize)]
+struct GcOutput<'a> {
+    /// Expired notes moved to the archive
+    archived: Vec<String>,
+    /// Loose objects before packing
+    loose: crate::pack::LooseUsage,
+    /// Missing when `--auto` found nothing worth packing
+    pack: Option<&'a crate::pack::PackReport>,
+}
 
-        // Parse markdown - extract title from first heading or filename
-        let lines: Vec<&str> = content.lines().collect();
-        let title = lines
+fn handle_gc(cli: &Cli, cmd: &GcCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let archived = repo.archive_expired(chrono::Utc::now())?;
+    let loose = repo.loose_usage()?;
+    let _interrupt = crate::interrupt::watch();
+    let report = if cmd.auto {
+        repo.auto_pack()?
+    } else {
+        Some(repo.pack_loose_objects(cmd.prune)?)
+    };
+    let summary = GcOutput {
+        archived: archived
             .iter()
-            .find(|line| line.starts_with("# "))
-            .map(|line| line.trim_start_matches("# ").trim().to_string())
-            .or_else(|| {
-                file.file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string())
-            })
-            .unwrap_or_else(|| "Imported Note".to_string());
-
-        // Remove title from body if it exists
-        let body = if lines.first().map(|l| l.starts_with("# ")).unwrap_or(false) {
-            lines[1..].join("\n").trim().to_string()
-        } else {
-            content.trim().to_string()
-        };
-
-        if body.is_empty() {
-            if !cli.quiet {
-                println!("{} Skipped {} (empty)", "⏭️".yellow(), file.display());
-            }
-            skipped_count += 1;
-            continue;
+            .map(|record| record.object_id.clone())
+            .collect(),
+        loose,
+        pack: report.as_ref(),
+    };
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
+    if !cli.quiet {
+        if !archived.is_empty() {
+            println!(
+                "{}Archived {} expired notes (fuku search --archived to find them)",
+                plain_text("📦 ").blue(),
+                archived.len()
+            );
         }
-
-        // Extract tags from content (e.g., #tag)
-        let mut tags = Vec::new();
-        if let Some(default_tag) = &cmd.tag {
-            tags.push(default_tag.clone());
+        let Some(report) = report else {
+            println!(
+                "{} {} loose objects ({}) are under the [gc] thresholds; nothing to pack",
+                plain_text("✓").green(),
+                loose.objects,
+                format_bytes(loose.bytes)
+            );
+            return Ok(());
+        };
+        println!(
+            "{} Packed {} objects into {}",
+            "".blue(),
+            report.object_count,
+            report.pack_file.display()
+        );
+        if report.pruned > 0 {
+            println!(
+                "{} Pruned {} loose objects, reclaiming {}",
+                "".dimmed(),
+                report.pruned,
+                format_bytes(report.reclaimed_bytes)
+            );
         }
-
-        // Look for tags in format #tag or tags: tag1, tag2
-        for line in lines.iter() {
-            if line.to_lowercase().starts_with("tags:")
-                || line.to_lowercase().starts_with("labels:")
-            {
-                let tag_str = line.split(':').nth(1).unwrap_or("");
-                for tag in tag_str.split(',') {
-                    let cleaned = tag.trim().trim_matches('#').to_lowercase();
-                    if !cleaned.is_empty() && !tags.contains(&cleaned) {
-                        tags.push(cleaned);
-                    }
-                }
-            }
+        if report.interrupted {
+            println!(
+                "{} Interrupted; the other loose objects are packed by the next fuku gc",
+                plain_text("⚠").yellow()
+            );
         }
+    }
+    Ok(())
+}
 
-        let now = chrono::Utc::now();
-        let author = resolve_author(None, None);
-
-        let note = Note {
-            title: title.clone(),
-            body,
-            tags: normalize_tags(tags),
-            links: vec![],
-            meta: BTreeMap::new(),
-            solutions: vec![],
-            privacy: Privacy::Private,
-            created_at: now,
-            updated_at: now,
-            author,
-        };
+fn handle_migrate(cli: &Cli, cmd: &MigrateCommand) -> Result<()> {
+    use crate::migrations::{migrate_to, pending, repo_version, CURRENT_VERSION};
 
-        match repo.store_note(note) {
-            Ok(record) => {
-                imported_count += 1;
-                if !cli.quiet {
-                    let short_id = format_object_id(&record.object_id);
-                    println!("  {} Imported: {} ({})", "✓".green(), title, short_id);
-                }
+    // Opening normally would already run every migration
+    let root = match &cli.repo {
+        Some(path) => path.clone(),
+        None => FukuraRepo::find_root(None)?,
+    };
+    let repo = FukuraRepo::open_without_upgrade(&root)?;
+    let version = repo_version(&repo)?;
+    let target = cmd.to.unwrap_or(CURRENT_VERSION);
+    let steps = pending(version, target);
 
-                // Auto-sync if enabled
-                if config.auto_sync.unwrap_or(false) {
-                    if let Some(remote) = &config.default_remote {
-                        let _ = push_note(&repo, &record.object_id, remote).await;
-                    }
-                }
-            }
-            Err(e) => {
-                if !cli.quiet {
-                    println!("  {} Failed: {} - {}", "✗".red(), title, e);
+    if cmd.dry_run || steps.is_empty() {
+        if !cli.quiet {
+            if steps.is_empty() && target >= version {
+                println!(
+                    "{} Repository format is v{}; nothing to migrate",
+                    plain_text("✓").green(),
+                    version
+                );
+            } else {
+                println!(
+                    "{}v{} {} v{}: {} pending migrations",
+                    plain_text("🔍 ").cyan(),
+                    version,
+                    plain_text("→"),
+                    target,
+                    steps.len()
+                );
+                for step in &steps {
+                    println!(
+                        "  v{} {} v{}  {}",
+                        step.from,
+                        plain_text("→"),
+                        step.from + 1,
+                        step.description
+                    );
                 }
-                error_count += 1;
             }
         }
+        if steps.is_empty() && target < version {
+            // Let migrate_to explain why downgrades are refused
+            migrate_to(&repo, target, false)?;
+        }
+        return Ok(());
     }
 
+    let report = migrate_to(&repo, target, !cmd.no_backup)?;
     if !cli.quiet {
-        println!();
-        println!("{} Import complete!", "🎉".green());
-        println!();
-        println!("  {} Imported: {}", "✓".green(), imported_count);
-        if skipped_count > 0 {
-            println!("  {} Skipped: {}", "⏭️".yellow(), skipped_count);
+        for description in &report.applied {
+            println!("  {} {}", plain_text("✓").green(), description);
         }
-        if error_count > 0 {
-            println!("  {} Errors: {}", "✗".red(), error_count);
+        println!(
+            "{} Migrated repository format v{} {} v{}",
+            plain_text("✓").green(),
+            report.from,
+            plain_text("→"),
+            report.to
+        );
+        if let Some(backup) = &report.backup {
+            println!("{} Backup: {}", plain_text("ℹ️").blue(), backup.display());
         }
-        println!();
-        println!("💡 Next steps:");
-        println!("  fuku list        # View imported notes");
-        println!("  fuku stats       # Check repository stats");
     }
-
     Ok(())
 }
 
-fn handle_rec(cli: &Cli, cmd: &RecCommand) -> Result<()> {
+fn handle_dedupe(cli: &Cli, cmd: &DedupeCommand) -> Result<()> {
+    ensure!(
+        (0.0..=1.0).contains(&cmd.threshold),
+        "Threshold must be between 0.0 and 1.0"
+    );
     let repo = open_repo(cli)?;
-    let recording_file = repo.root().join(".fukura").join("recording");
-
-    if cmd.status {
-        // Show recording status
-        if recording_file.exists() {
-            let content = fs::read_to_string(&recording_file)?;
-            let parts: Vec<&str> = content.split('|').collect();
-            if parts.len() >= 2 {
-                let title = parts[1];
-                if !cli.quiet {
-                    println!("{} Recording in progress", "🔴".red());
-                    println!();
-                    println!("  📝 Task: {}", title.bold());
-                    println!("  ⏱️  All commands are being recorded");
-                    println!();
-                    println!("💡 When done:");
-                    println!("  fuku done        # Save and stop recording");
-                }
-            }
-        } else if !cli.quiet {
-            println!("{} Not recording", "ℹ️".blue());
-            println!();
-            println!("💡 Start recording:");
-            println!("  fuku rec \"Task description\"");
-            println!("  fuku rec \"Task\" 3m ago      # Start from 3 minutes ago");
-        }
-        return Ok(());
-    }
-
-    if cmd.stop {
-        return handle_done(cli);
+    let mut records = repo.list_all_notes()?;
+    if !cmd.all {
+        records.retain(|record| {
+            record
+                .note
+                .tags
+                .iter()
+                .any(|tag| tag == "auto-captured" || tag == "error")
+        });
     }
 
-    // Require title if not status/stop
-    let title = match &cmd.title {
-        Some(t) => t,
-        None => {
-            bail!("Title required. Usage: fuku rec \"Task description\" [TIME_AGO]");
-        }
-    };
-
-    // Check if already recording
-    if recording_file.exists() {
-        let content = fs::read_to_string(&recording_file)?;
-        let parts: Vec<&str> = content.split('|').collect();
-        if parts.len() >= 2 {
-            if !cli.quiet {
-                println!("{} Already recording: {}", "⚠️".yellow(), parts[1]);
-                println!();
-                println!("💡 Options:");
-                println!("  fuku done          # Finish current recording");
-                println!("  fuku rec --stop    # Same as 'fuku done'");
-            }
-            return Ok(());
+    let clusters = crate::dedupe::cluster_notes(&records, cmd.threshold);
+    if clusters.is_empty() {
+        if !cli.quiet {
+            println!(
+                "{} No duplicates found among {} notes",
+                "".green(),
+                records.len()
+            );
         }
+        return Ok(());
     }
 
-    // Handle time-based recording - should not reach here now
-    if cmd.from_time.is_some() {
-        bail!("Time-based recording should be handled by async version");
-    }
-
-    // Start new recording (normal case)
-    let session_id = format!("rec_{}", chrono::Utc::now().timestamp());
-    let recording_data = format!("{}|{}", session_id, title);
-    fs::write(&recording_file, recording_data)?;
-
+    let duplicate_count: usize = clusters.iter().map(|c| c.members.len() - 1).sum();
     if !cli.quiet {
-        println!("{} Recording started", "🔴".red().bold());
-        println!();
-        println!("  📝 Task: {}", title.bold());
-        println!("  🎯 Session ID: {}", session_id);
-        println!();
-        println!("💡 All commands will be recorded automatically");
-        println!("   Run 'fuku done' when finished");
+        println!(
+            "{}Found {} clusters with {} duplicates (threshold {:.2})",
+            plain_text("🔍 ").cyan(),
+            clusters.len(),
+            duplicate_count,
+            cmd.threshold
+        );
         println!();
-        println!("Examples of what gets recorded:");
-        println!("  • Every command you run");
-        println!("  • Success/failure status");
-        println!("  • Working directory");
-        println!("  • Timestamps");
+        for (idx, cluster) in clusters.iter().enumerate() {
+            let keep = &records[cluster.members[0]];
+            println!(
+                "Cluster {} {} {} notes {} {:.0}% similar",
+                idx + 1,
+                plain_text("·"),
+                cluster.members.len(),
+                plain_text("·"),
+                cluster.similarity * 100.0
+            );
+            println!(
+                "  {} {} {}",
+                "keep".green(),
+                format_object_id(&keep.object_id).cyan(),
+                keep.note.title
+            );
+            for &member in &cluster.members[1..] {
+                let record = &records[member];
+                println!(
+                    "  {} {} {}",
+                    "merge".yellow(),
+                    format_object_id(&record.object_id).dimmed(),
+                    record.note.title.dimmed()
+                );
+            }
+            println!();
+        }
     }
 
-    Ok(())
-}
-
-fn handle_done(cli: &Cli) -> Result<()> {
-    let repo = open_repo(cli)?;
-    let recording_file = repo.root().join(".fukura").join("recording");
-
-    if !recording_file.exists() {
+    if !cmd.apply {
         if !cli.quiet {
-            println!("{} No recording in progress", "ℹ️".blue());
-            println!();
-            println!("💡 Start recording:");
-            println!("  fuku rec \"Task description\"");
+            println!(
+                "{}Tip: Run 'fuku dedupe --apply' to merge these notes",
+                plain_text("💡 ")
+            );
         }
         return Ok(());
     }
 
-    // Read recording data
-    let content = fs::read_to_string(&recording_file)?;
-    let parts: Vec<&str> = content.split('|').collect();
-
-    if parts.len() < 2 {
-        fs::remove_file(&recording_file)?;
-        bail!("Invalid recording file");
+    let mut replaced: BTreeMap<String, String> = BTreeMap::new();
+    for cluster in &clusters {
+        let canonical = &records[cluster.members[0]];
+        let duplicates: Vec<&Note> = cluster.members[1..]
+            .iter()
+            .map(|&idx| &records[idx].note)
+            .collect();
+        let merged = crate::dedupe::merge_notes(&canonical.note, &duplicates);
+        let stored = repo.replace_note(&canonical.object_id, merged)?;
+        for &idx in &cluster.members[1..] {
+            repo.delete_note(&records[idx].object_id)?;
+            replaced.insert(records[idx].object_id.clone(), stored.object_id.clone());
+        }
+        replaced.insert(canonical.object_id.clone(), stored.object_id.clone());
     }
 
-    let session_id = parts[0];
-    let title = parts[1];
+    repoint_patterns(&repo, &replaced)?;
 
     if !cli.quiet {
-        println!("{} Finalizing recording...", "⏹️".yellow());
-        println!();
+        println!(
+            "{} Merged {} duplicates into {} notes",
+            "".green(),
+            duplicate_count,
+            clusters.len()
+        );
     }
+    Ok(())
+}
 
-    // Create detailed note with command history
-    let now = chrono::Utc::now();
-    let start_timestamp = session_id
-        .strip_prefix("rec_")
-        .and_then(|ts| ts.parse::<i64>().ok())
-        .unwrap_or_else(|| now.timestamp());
-
-    let duration_secs = now.timestamp() - start_timestamp;
-    let duration_mins = duration_secs / 60;
-
-    let mut body = String::new();
-    body.push_str("## 🎯 Manual Recording Session\n\n");
-    body.push_str(&format!("**Task**: {}\n", title));
-    body.push_str(&format!("**Duration**: {} minutes\n", duration_mins));
-    body.push_str(&format!(
-        "**Completed**: {}\n\n",
-        now.format("%Y-%m-%d %H:%M UTC")
-    ));
+/// Keep error patterns pointing at notes that still exist after merges
+fn repoint_patterns(repo: &FukuraRepo, replaced: &BTreeMap<String, String>) -> Result<()> {
+    let store = crate::patterns::PatternStore::new(repo.root());
+    if store.path().exists() {
+        store.update(|patterns| {
+            for pattern in patterns.values_mut() {
+                for ids in [&mut pattern.notes, &mut pattern.resolved_by] {
+                    let mut updated: Vec<String> = Vec::new();
+                    for id in ids.iter() {
+                        let id = replaced.get(id).unwrap_or(id);
+                        if !updated.contains(id) {
+                            updated.push(id.clone());
+                        }
+                    }
+                    *ids = updated;
+                }
+            }
+        })?;
+    }
+    Ok(())
+}
 
-    body.push_str("## 📋 What I Did\n\n");
-    body.push_str("This recording captured a complete workflow. \n");
-    body.push_str("All commands executed during this session were automatically tracked by Fukura daemon.\n\n");
+/// `fuku diff --output json|yaml`
+#[derive(Debug, Serialize)]
+struct DiffOutput {
+    from: String,
+    to: String,
+    #[serde(flatten)]
+    diff: NoteDiff,
+}
 
-    body.push_str("## 💡 Next Steps\n\n");
-    body.push_str("- Review the commands in daemon logs\n");
-    body.push_str("- Add manual notes if needed: `fuku edit @latest --editor`\n");
-    body.push_str("- Tag for easy finding: `fuku edit @latest --add-tag <tag>`\n");
+fn handle_diff(cli: &Cli, cmd: &DiffCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let a = repo.resolve_object_id(&cmd.a)?;
+    let (from, to) = match &cmd.b {
+        Some(b) => (a, repo.resolve_object_id(b)?),
+        None => (previous_revision(&repo, &a)?, a),
+    };
+    let old = repo.load_note(&from)?;
+    let new = repo.load_note(&to)?;
+    let summary = DiffOutput {
+        diff: NoteDiff::new(&old.note, &new.note),
+        from,
+        to,
+    };
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
 
-    // Auto-detect technology tags from title
-    let mut tags = vec!["recording".to_string(), "workflow".to_string()];
-    let title_lower = title.to_lowercase();
-    if title_lower.contains("kubernetes")
-        || title_lower.contains("k8s")
-        || title_lower.contains("kubectl")
-    {
-        tags.push("kubernetes".to_string());
+    println!(
+        "{}",
+        format!("--- {} {}", format_object_id(&summary.from), old.note.title).red()
+    );
+    println!(
+        "{}",
+        format!("+++ {} {}", format_object_id(&summary.to), new.note.title).green()
+    );
+    if summary.diff.is_empty() {
+        println!("{} No differences", plain_text("✓").green());
+        return Ok(());
     }
-    if title_lower.contains("docker") {
-        tags.push("docker".to_string());
+    let diff = &summary.diff;
+    if let Some([old_title, new_title]) = &diff.title {
+        println!("{}", " Title".bold());
+        println!("  {}", format!("-{}", old_title).red());
+        println!("  {}", format!("+{}", new_title).green());
     }
-    if title_lower.contains("git") {
-        tags.push("git".to_string());
+    if !diff.tags_removed.is_empty() || !diff.tags_added.is_empty() {
+        println!("{}", " Tags".bold());
+        for tag in &diff.tags_removed {
+            println!("  {}", format!("-#{}", tag).red());
+        }
+        for tag in &diff.tags_added {
+            println!("  {}", format!("+#{}", tag).green());
+        }
     }
-    if title_lower.contains("deploy") {
-        tags.push("deployment".to_string());
+    if !diff.meta.is_empty() {
+        println!("{}", " Meta".bold());
+        for (key, [old_value, new_value]) in &diff.meta {
+            if let Some(value) = old_value {
+                println!("  {}", format!("-{} = {}", key, value).red());
+            }
+            if let Some(value) = new_value {
+                println!("  {}", format!("+{} = {}", key, value).green());
+            }
+        }
     }
-    tags.sort();
-    tags.dedup();
+    if !diff.body.is_empty() {
+        println!("{}", " Body".bold());
+        for line in diff.body.lines() {
+            match line.chars().next() {
+                Some('@') => println!("{}", line.cyan()),
+                Some('-') => println!("{}", line.red()),
+                Some('+') => println!("{}", line.green()),
+                _ => println!("{}", line),
+            }
+        }
+    }
+    Ok(())
+}
 
-    let note = Note {
-        title: title.to_string(),
-        body,
-        tags,
-        links: vec![],
-        meta: std::collections::BTreeMap::from([
-            ("session_id".to_string(), session_id.to_string()),
-            ("recording".to_string(), "true".to_string()),
-            ("duration_minutes".to_string(), duration_mins.to_string()),
-        ]),
-        solutions: vec![],
-        privacy: Privacy::Private,
-        created_at: chrono::DateTime::from_timestamp(start_timestamp, 0).unwrap_or(now),
-        updated_at: now,
-        author: resolve_author(None, None),
-    };
+/// The note `object_id` was edited from, from the audit log
+fn previous_revision(repo: &FukuraRepo, object_id: &str) -> Result<String> {
+    repo.audit_log()
+        .read_since(None)?
+        .into_iter()
+        .rev()
+        .find(|entry| {
+            entry.action == AuditAction::Edit
+                && entry.object_ids.get(1).map(String::as_str) == Some(object_id)
+        })
+        .and_then(|entry| entry.object_ids.into_iter().next())
+        .with_context(|| {
+            format!(
+                "No earlier revision of {} is recorded\n💡 Tip: Compare two notes with: fuku diff <a> <b>",
+                format_object_id(object_id)
+            )
+        })
+}
+
+fn handle_merge(cli: &Cli, cmd: &MergeCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let mut ids: Vec<String> = Vec::new();
+    for input in &cmd.ids {
+        let id = repo.resolve_object_id(input)?;
+        ensure!(
+            !ids.contains(&id),
+            "Note {} is listed more than once",
+            format_object_id(&id)
+        );
+        ids.push(id);
+    }
+    let kept = repo.load_note(&ids[0])?;
+    // Without a terminal to ask on, nothing is dropped
+    let interactive = cmd.conflicts.is_none() && is_terminal::is_terminal(std::io::stdin());
+
+    let mut merged = kept.note.clone();
+    for id in &ids[1..] {
+        let absorbed = repo.load_note(id)?;
+        let body = merge_bodies(&merged.body, &absorbed.note.body, |pair| {
+            match cmd.conflicts {
+                Some(choice) => Ok(choice),
+                None if interactive => choose_section(pair, &absorbed),
+                None => Ok(SectionChoice::Both),
+            }
+        })?;
+        merged = merge_into(&merged, &absorbed.note, id, body);
+    }
+    merged.record_edit(&resolve_author(None, None), Utc::now());
 
-    let record = repo.store_note(note)?;
+    if cmd.dry_run {
+        render_note(
+            &NoteRecord {
+                object_id: kept.object_id.clone(),
+                note: merged,
+            },
+            None,
+            &[],
+        );
+        return Ok(());
+    }
 
-    // Remove recording file
-    fs::remove_file(&recording_file)?;
+    let stored = repo.replace_note(&kept.object_id, merged)?;
+    let mut replaced = BTreeMap::from([(kept.object_id.clone(), stored.object_id.clone())]);
+    for id in &ids[1..] {
+        repo.tombstone(id, &stored.object_id)?;
+        replaced.insert(id.clone(), stored.object_id.clone());
+    }
+    repoint_patterns(&repo, &replaced)?;
 
     if !cli.quiet {
-        let short_id = format_object_id(&record.object_id);
-        println!("{} Recording saved!", "✅".green().bold());
-        println!();
-        println!("  📝 {}", record.note.title.bold());
-        println!("  🆔 {}", short_id);
-        println!("  ⏱️  Duration: {} minutes", duration_mins);
-        if !record.note.tags.is_empty() {
-            println!("  🏷️  #{}", record.note.tags.join(" #"));
+        println!(
+            "{} Merged {} notes into {} ({})",
+            plain_text("✓").green(),
+            ids.len() - 1,
+            stored.note.title.bold(),
+            format_object_id(&stored.object_id)
+        );
+        for id in &ids[1..] {
+            println!("  {} {}", "merged".dimmed(), format_object_id(id).dimmed());
         }
-        println!();
-        println!("💡 Quick access:");
-        println!("  fuku view @latest    # View recording");
-        println!("  fuku edit @latest --editor  # Add notes");
-        println!("  fuku open @latest    # Open in browser");
     }
-
     Ok(())
 }
 
-fn handle_gc(cli: &Cli, cmd: &GcCommand) -> Result<()> {
+/// Ask which version of a section both notes have differently to keep
+fn choose_section(pair: &SectionPair, absorbed: &NoteRecord) -> Result<SectionChoice> {
+    let heading = pair
+        .heading
+        .as_deref()
+        .unwrap_or("(before the first heading)");
+    println!();
+    println!("{} {}", "Section".bold(), heading.cyan());
+    for (label, section) in [
+        ("kept".green(), &pair.kept),
+        (
+            format_object_id(&absorbed.object_id).yellow(),
+            &pair.absorbed,
+        ),
+    ] {
+        let rule = plain_text("──");
+        println!("{}", format!("{rule} {label} {rule}").dimmed());
+        if let Some(section) = section {
+            println!("{}", section.text.trim_end());
+        }
+    }
+    let choice = dialoguer::Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Keep which version?")
+        .items(["Kept note", "Merged note", "Both"])
+        .default(2)
+        .interact()?;
+    Ok([
+        SectionChoice::Kept,
+        SectionChoice::Absorbed,
+        SectionChoice::Both,
+    ][choice])
+}
+
+fn handle_feedback(cli: &Cli, cmd: &FeedbackCommand) -> Result<()> {
     let repo = open_repo(cli)?;
-    let report = repo.pack_loose_objects(cmd.prune)?;
+    let resolved = repo.resolve_object_id(&cmd.id)?;
+    let record = repo.load_note(&resolved)?;
+    let kind = if cmd.worked {
+        FeedbackKind::Worked
+    } else {
+        FeedbackKind::DidntWork
+    };
+    let mut event = FeedbackEvent::new(&resolved, kind);
+    if let Ok(dir) = std::env::current_dir() {
+        event = event.in_dir(&dir.display().to_string());
+    }
+    repo.ensure_writable()?;
+    let store = FeedbackStore::new(repo.root());
+    store.append(&event)?;
+
     if !cli.quiet {
+        let stats = store.stats()?.get(&resolved).copied().unwrap_or_default();
+        let verdict = if cmd.worked { "worked" } else { "didn't work" };
         println!(
-            "{} Packed {} objects into {}",
-            "".blue(),
-            report.object_count,
-            report.pack_file.display()
+            "{} Recorded that {} ({}) {}",
+            plain_text("✓").green(),
+            record.note.title.bold(),
+            format_object_id(&resolved),
+            verdict
+        );
+        println!(
+            "  {} worked {} {} didn't {} suggested {}{} {} confidence {:.0}%",
+            stats.worked + stats.implicit_worked,
+            plain_text("·"),
+            stats.didnt_work + stats.implicit_didnt_work,
+            plain_text("·"),
+            stats.suggested,
+            plain_text("×"),
+            plain_text("·"),
+            stats.confidence(0.5) * 100.0
         );
-        if cmd.prune {
-            println!("{} Pruned {} loose objects", "".dimmed(), report.pruned);
-        }
     }
     Ok(())
 }
@@ -2313,9 +6733,15 @@ async fn handle_push(cli: &Cli, cmd: &PushCommand) -> Result<()> {
     let repo = open_repo(cli)?;
     let resolved = repo.resolve_object_id(&cmd.id)?;
     let remote = determine_remote(&repo, cmd.remote.as_deref())?;
-    let remote_id = push_note(&repo, &resolved, &remote).await?;
+    let remote_id = push_note(&repo, &resolved, &remote, cmd.force).await?;
     if !cli.quiet {
-        println!("{} Pushed {} → {}", "".green(), resolved, remote_id);
+        println!(
+            "{} Pushed {} {} {}",
+            "".green(),
+            resolved,
+            plain_text("→"),
+            remote_id
+        );
     }
     Ok(())
 }
@@ -2328,16 +6754,167 @@ async fn handle_pull(cli: &Cli, cmd: &PullCommand) -> Result<()> {
         .unwrap_or_else(|_| cmd.id.clone());
     let local_id = pull_note(&repo, &remote_id, &remote).await?;
     if !cli.quiet {
-        println!("{} Pulled {} → {}", "".cyan(), remote_id, local_id);
+        println!(
+            "{} Pulled {} {} {}",
+            "".cyan(),
+            remote_id,
+            plain_text("→"),
+            local_id
+        );
+    }
+    Ok(())
+}
+
+async fn handle_share(cli: &Cli, cmd: &ShareCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    if cmd.list {
+        return list_shares(cli, &repo);
+    }
+    let Some(id) = cmd.id.as_deref() else {
+        bail!("Which note? Pass its ID");
+    };
+    if cmd.revoke {
+        return revoke_shares(cli, &repo, id).await;
+    }
+
+    let resolved = repo.resolve_object_id(id)?;
+    let copy = share::prepare(&repo, &resolved, cmd.force)?;
+    let config = repo.config()?;
+    let now = Utc::now();
+    let shared = match (&cmd.remote, &config.share.endpoint) {
+        (None, Some(endpoint)) => {
+            if cmd.expires.is_some() {
+                bail!(
+                    "{} is a paste service, which can't expire what it holds; share through a hub with --remote to use --expires",
+                    endpoint
+                );
+            }
+            let markdown = render_note_markdown(&copy, &config.meta_schema);
+            let url = share::paste(endpoint, &markdown).await?;
+            Share {
+                id: url.clone(),
+                kind: ShareKind::Paste,
+                object_id: resolved,
+                title: copy.note.title.clone(),
+                url,
+                target: endpoint.clone(),
+                created_at: now,
+                expires_at: None,
+            }
+        }
+        _ => {
+            let remote = determine_remote(&repo, cmd.remote.as_deref())?;
+            let expires_at = cmd
+                .expires
+                .as_deref()
+                .or(config.share.expires_after.as_deref())
+                .map(crate::time_parser::parse_duration)
+                .transpose()?
+                .map(|ttl| now + ttl);
+            let published = Hub::new(&remote).share(&copy, expires_at).await?;
+            Share {
+                id: published.id,
+                kind: ShareKind::Hub,
+                object_id: resolved,
+                title: copy.note.title.clone(),
+                url: published.url,
+                target: remote,
+                created_at: now,
+                expires_at: published.expires_at.or(expires_at),
+            }
+        }
+    };
+    share::record(&repo, &shared)?;
+
+    if cli.output.emit(&shared)? {
+        return Ok(());
+    }
+    if cli.quiet {
+        println!("{}", shared.url);
+        return Ok(());
+    }
+    if cmd.qr {
+        println!("{}", crate::qr::render(&shared.url)?);
+    }
+    println!("{} Shared '{}'", "".green(), shared.title);
+    println!("  {}", shared.url.cyan());
+    if let Some(at) = shared.expires_at {
+        println!(
+            "  {}",
+            format!("Expires {}", at.format("%Y-%m-%d %H:%M UTC")).dimmed()
+        );
+    }
+    println!(
+        "{}Revoke with: fuku share --revoke {}",
+        plain_text("💡 ").yellow(),
+        shared.id
+    );
+    Ok(())
+}
+
+fn list_shares(cli: &Cli, repo: &FukuraRepo) -> Result<()> {
+    let shares = repo.shares().list()?;
+    if cli.output.emit(&shares)? || cli.quiet {
+        return Ok(());
+    }
+    if shares.is_empty() {
+        println!("{} No notes shared from this repository", "".cyan());
+        return Ok(());
+    }
+    let now = Utc::now();
+    for shared in &shares {
+        let expiry = match shared.expires_at {
+            Some(at) if shared.is_expired(now) => {
+                format!(" (expired {})", at.format("%Y-%m-%d %H:%M UTC"))
+            }
+            Some(at) => format!(" (until {})", at.format("%Y-%m-%d %H:%M UTC")),
+            None => String::new(),
+        };
+        println!(
+            "{} {}  {}{}",
+            format_object_id(&shared.object_id).cyan(),
+            shared.title,
+            shared.url,
+            expiry.dimmed()
+        );
     }
     Ok(())
 }
 
+/// Revoke the share `query` names by ID or URL, or every share of the note
+/// it names
+async fn revoke_shares(cli: &Cli, repo: &FukuraRepo, query: &str) -> Result<()> {
+    let store = repo.shares();
+    let mut matching = store.find(query)?;
+    if matching.is_empty() {
+        if let Ok(resolved) = repo.resolve_object_id(query) {
+            matching = store.find(&resolved)?;
+        }
+    }
+    if matching.is_empty() {
+        bail!(
+            "No share matches '{}'\n💡 Tip: See what is shared with: fuku share --list",
+            query
+        );
+    }
+    for shared in &matching {
+        share::revoke(repo, shared).await?;
+        if !cli.quiet && !cli.output.is_structured() {
+            println!("{} Revoked {}", "".green(), shared.url);
+        }
+    }
+    cli.output.emit(&matching)?;
+    Ok(())
+}
+
 async fn handle_sync(cli: &Cli, cmd: &SyncCommand) -> Result<()> {
     let repo = open_repo(cli)?;
-    let mut config = repo.config()?;
+    let mut config = repo.local_config()?;
 
     // Handle auto-sync enable/disable
+    if cmd.enable_auto || cmd.disable_auto {
+        repo.ensure_writable()?;
+    }
     if cmd.enable_auto {
         config.auto_sync = Some(true);
         config.save(&repo.config_path())?;
@@ -2363,80 +6940,385 @@ async fn handle_sync(cli: &Cli, cmd: &SyncCommand) -> Result<()> {
     // Determine remote
     let remote = determine_remote(&repo, cmd.remote.as_deref())?;
 
+    let structured = cli.output.is_structured();
+    let mut summary = SyncOutput {
+        remote: remote.clone(),
+        synced: vec![],
+        skipped: vec![],
+        failed: vec![],
+        interrupted: false,
+    };
+
     // Sync single note
     if let Some(id) = &cmd.id {
         let resolved = repo.resolve_object_id(id)?;
-        let remote_id = push_note(&repo, &resolved, &remote).await?;
+        let remote_id = push_note(&repo, &resolved, &remote, cmd.force).await?;
+        summary.synced.push(SyncedNote {
+            object_id: resolved.clone(),
+            remote_id: Some(remote_id.clone()),
+            error: None,
+        });
+        if cli.output.emit(&summary)? {
+            return Ok(());
+        }
         if !cli.quiet {
-            println!("{} Synced {} → {}", "".green(), resolved, remote_id);
+            println!(
+                "{} Synced {} {} {}",
+                "".green(),
+                resolved,
+                plain_text("→"),
+                remote_id
+            );
         }
         return Ok(());
     }
 
     // Sync all private notes (default behavior or explicit --all)
-    if !cli.quiet {
+    if !cli.quiet && !structured {
         println!("{} Syncing all private notes...", "".blue());
     }
 
-    // Get all notes and filter private ones
-    let all_notes = repo.list_all_notes()?;
-    let mut synced_count = 0;
+    // Only what the hub lacks goes out, in batches; Ctrl-C stops after the
+    // requests in flight
+    let _interrupt = crate::interrupt::watch();
+    let private: Vec<NoteRecord> = repo
+        .iter_notes()?
+        .flatten()
+        .filter(|record| record.note.privacy == Privacy::Private && !record.note.is_local_only())
+        .collect();
+    let hub = Hub::new(&remote);
+    let (unsynced, synced) = hub.partition_synced(private).await?;
+    summary.skipped = synced.into_iter().map(|record| record.object_id).collect();
+    let (mut unsynced, rejected) = pre_push(&repo, unsynced);
+    summary
+        .failed
+        .extend(rejected.into_iter().map(|(object_id, e)| SyncedNote {
+            object_id,
+            remote_id: None,
+            error: Some(e.to_string()),
+        }));
+    if !cmd.force {
+        unsynced.retain(|record| {
+            let leaks = leaked_secrets(&record.note);
+            if leaks.is_empty() {
+                return true;
+            }
+            summary.failed.push(SyncedNote {
+                object_id: record.object_id.clone(),
+                remote_id: None,
+                error: Some(format!(
+                    "contains what looks like a live secret ({}); rotate it, or push anyway with --force",
+                    leaks.join(", ")
+                )),
+            });
+            false
+        });
+    }
+    if !cli.quiet && !structured {
+        for failed in &summary.failed {
+            println!(
+                "{} Not syncing {}: {}",
+                "  [FAIL]".red(),
+                failed.object_id,
+                failed.error.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    let progress = if cli.quiet || structured {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(unsynced.len() as u64)
+    };
+    progress.set_style(
+        ProgressStyle::with_template("  {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .expect("valid progress template")
+            .progress_chars(crate::output::progress_chars()),
+    );
+    let sync_config = repo.config()?.sync;
+    let report = push_notes(&hub, unsynced, &sync_config, |outcome| {
+        progress.inc(1);
+        if cli.quiet || structured {
+            return;
+        }
+        progress.suspend(|| match &outcome.result {
+            Ok(_) => println!("{} Synced: {}", "  [OK]".green(), outcome.title),
+            Err(e) => println!(
+                "{} Failed to sync {}: {}",
+                "  [FAIL]".red(),
+                outcome.title,
+                e
+            ),
+        });
+    })
+    .await?;
+    report.audit(&repo, &remote)?;
+    for outcome in report.outcomes {
+        match outcome.result {
+            Ok(remote_id) => summary.synced.push(SyncedNote {
+                object_id: outcome.object_id,
+                remote_id: Some(remote_id),
+                error: None,
+            }),
+            Err(e) => summary.failed.push(SyncedNote {
+                object_id: outcome.object_id,
+                remote_id: None,
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+
+    progress.finish_and_clear();
+    summary.interrupted = report.interrupted;
 
-    for note_record in all_notes {
-        if note_record.note.privacy == Privacy::Private {
-            match push_note(&repo, &note_record.object_id, &remote).await {
-                Ok(_) => {
-                    synced_count += 1;
-                    if !cli.quiet {
-                        println!("{} Synced: {}", "  [OK]".green(), note_record.note.title);
-                    }
-                }
-                Err(e) => {
-                    if !cli.quiet {
-                        println!(
-                            "{} Failed to sync {}: {}",
-                            "  [FAIL]".red(),
-                            note_record.note.title,
-                            e
-                        );
-                    }
-                }
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
+    if !cli.quiet {
+        println!(
+            "{} Pushed {}, skipped {} already on {}, failed {}",
+            "".green(),
+            summary.synced.len(),
+            summary.skipped.len(),
+            remote,
+            summary.failed.len()
+        );
+        if summary.interrupted {
+            println!(
+                "{} Interrupted; run fuku sync again to push the rest",
+                plain_text("⚠").yellow()
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn handle_clone(cli: &Cli, cmd: &CloneCommand) -> Result<()> {
+    let root = match &cmd.dir {
+        Some(dir) => dir.clone(),
+        None => std::env::current_dir()?,
+    };
+    ensure!(
+        !root.join(".fukura").exists(),
+        "{} is already a fukura repository",
+        root.display()
+    );
+    let since = cmd.since.as_deref().map(parse_instant).transpose()?;
+    let tags = normalize_tags(cmd.tags.iter().cloned());
+    let mut manifest = Hub::new(&cmd.remote).clone_manifest().await?;
+    let available = manifest.notes.len();
+    manifest.notes.retain(|record| {
+        since.map_or(true, |since| record.note.updated_at >= since)
+            && tags.iter().all(|tag| record.note.tags.contains(tag))
+    });
+
+    // Every clone starts thin; a full one then fetches each note, without a
+    // cache limit so nothing is dropped on the way
+    let repo = FukuraRepo::init(&root, false)?;
+    let mut config = repo.local_config()?;
+    config.default_remote = Some(cmd.remote.clone());
+    config.thin = Some(ThinConfig {
+        remote: cmd.remote.clone(),
+        cache_limit_mb: match (cmd.thin, cmd.cache_limit_mb) {
+            (false, _) => 0,
+            (true, limit) => limit.unwrap_or_else(ThinConfig::default_cache_limit_mb),
+        },
+    });
+    config.save(&repo.config_path())?;
+    ThinObjectStore::write_remote_ids(
+        &repo.thin_dir(),
+        manifest
+            .notes
+            .iter()
+            .map(|record| record.object_id.as_str()),
+    )?;
+    let repo = FukuraRepo::open(&root)?;
+    for (name, value) in &manifest.refs {
+        repo.set_ref(name, value)?;
+    }
+    repo.index_records(&manifest.notes)?;
+
+    let mut summary = CloneOutput {
+        repository: root.display().to_string(),
+        remote: cmd.remote.clone(),
+        notes: manifest.notes.len(),
+        filtered_out: available - manifest.notes.len(),
+        fetched: 0,
+        thin: true,
+    };
+    if !cmd.thin {
+        let _interrupt = crate::interrupt::watch();
+        let progress = if cli.quiet || cli.output.is_structured() {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(manifest.notes.len() as u64)
+        };
+        progress.set_style(
+            ProgressStyle::with_template("  Fetching {bar:40.cyan/blue} {pos}/{len}")
+                .expect("valid progress template")
+                .progress_chars(crate::output::progress_chars()),
+        );
+        for stub in &manifest.notes {
+            if crate::interrupt::requested() {
+                break;
+            }
+            // Loading keeps the fetched object
+            let record = repo.load_note(&stub.object_id)?;
+            if let Some(attachment) = record.note.meta.get(crate::models::BODY_ATTACHMENT_META) {
+                repo.load_attachment(attachment)?;
             }
+            summary.fetched += 1;
+            progress.inc(1);
+        }
+        progress.finish_and_clear();
+        if summary.fetched == summary.notes {
+            config.thin = None;
+            config.save(&repo.config_path())?;
+            fs::remove_dir_all(repo.thin_dir())?;
+            drop(repo);
+            // The index was built from summaries; search the full bodies
+            FukuraRepo::open_without_upgrade(&root)?.rebuild_indexes()?;
+            summary.thin = false;
         }
     }
 
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
     if !cli.quiet {
-        println!("{} Synced {} notes", "".green(), synced_count);
+        println!(
+            "{} Cloned {} notes from {} into {}",
+            plain_text("✓").green(),
+            summary.notes,
+            summary.remote,
+            summary.repository
+        );
+        if summary.filtered_out > 0 {
+            println!(
+                "{} Left out {} notes that don't match --tag/--since",
+                "".dimmed(),
+                summary.filtered_out
+            );
+        }
+        if cmd.thin {
+            println!(
+                "{} Thin clone: notes are fetched on first view and up to {} MB are kept",
+                "".cyan(),
+                cmd.cache_limit_mb
+                    .unwrap_or_else(ThinConfig::default_cache_limit_mb)
+            );
+        } else if summary.thin {
+            println!(
+                "{} Interrupted after {} notes; the others are fetched on first view",
+                plain_text("⚠").yellow(),
+                summary.fetched
+            );
+        }
     }
     Ok(())
 }
 
+/// `fuku clone --output json|yaml`
+#[derive(Debug, Serialize)]
+struct CloneOutput {
+    repository: String,
+    remote: String,
+    notes: usize,
+    /// Notes left out by `--tag` or `--since`
+    filtered_out: usize,
+    /// Notes fetched in full; the rest are fetched on first view
+    fetched: usize,
+    thin: bool,
+}
+
+/// `fuku explain --output json|yaml`
+#[derive(Debug, Serialize)]
+struct ExplainOutput {
+    /// The error, normalized as it was looked up
+    error: String,
+    /// `stdin`, or the command whose captured failure was explained
+    source: String,
+    solutions: Vec<crate::daemon::SolutionHit>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    remote: Vec<crate::remote_search::RemoteSearchHit>,
+}
+
+/// `fuku summarize --output json|yaml`
+#[derive(Debug, Serialize)]
+struct SummarizeOutput {
+    object_id: String,
+    model: String,
+    #[serde(flatten)]
+    summary: Summary,
+    /// Id of the note with the summary applied, with `--apply`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated: Option<String>,
+}
+
+/// `fuku sync --output json|yaml`
+#[derive(Debug, Serialize)]
+struct SyncOutput {
+    remote: String,
+    synced: Vec<SyncedNote>,
+    /// Notes the hub already had
+    skipped: Vec<String>,
+    failed: Vec<SyncedNote>,
+    /// Stopped by Ctrl-C before every note was pushed
+    interrupted: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncedNote {
+    object_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// `fuku config show --output json|yaml`: the repository's effective config
+#[derive(Debug, Serialize)]
+struct ConfigOutput<'a> {
+    repository: String,
+    config: &'a crate::config::FukuraConfig,
+}
+
 fn handle_config(cli: &Cli, cmd: &ConfigCommand) -> Result<()> {
     match cmd {
         ConfigCommand::Show => {
             let repo = open_repo(cli)?;
             let config = repo.config()?;
+            let shown = ConfigOutput {
+                repository: repo.root().display().to_string(),
+                config: &config,
+            };
+            if cli.output.emit(&shown)? {
+                return Ok(());
+            }
 
             if !cli.quiet {
-                println!("{}", "📝 Configuration".bold().cyan());
+                println!("{}", plain_text("📝 Configuration").bold().cyan());
                 println!();
 
                 // Repository info
-                println!("  {} Repository:", "📁".yellow());
-                println!("    • Path: {}", repo.root().display());
+                println!("  {}Repository:", plain_text("📁 ").yellow());
+                println!("    {} Path: {}", plain_text("•"), repo.root().display());
+                if let Some(profile) = &config.profile {
+                    println!("    {} Profile: {}", plain_text("•"), profile.green());
+                }
                 println!();
 
                 // Remote configuration
-                println!("  {} Remote:", "🌐".yellow());
+                println!("  {}Remote:", plain_text("🌐 ").yellow());
                 if let Some(remote) = &config.default_remote {
-                    println!("    • URL: {}", remote.green());
+                    println!("    {} URL: {}", plain_text("•"), remote.green());
                 } else {
-                    println!("    • URL: {}", "not configured".dimmed());
+                    println!("    {} URL: {}", plain_text("•"), "not configured".dimmed());
                 }
 
                 // Sync configuration
                 println!(
-                    "    • Auto-sync: {}",
+                    "    {} Auto-sync: {}",
+                    plain_text("•"),
                     if config.auto_sync.unwrap_or(false) {
                         "enabled".green()
                     } else {
@@ -2446,9 +7328,10 @@ fn handle_config(cli: &Cli, cmd: &ConfigCommand) -> Result<()> {
                 println!();
 
                 // Daemon configuration
-                println!("  {} Daemon:", "⚙️".yellow());
+                println!("  {}Daemon:", plain_text("⚙️ ").yellow());
                 println!(
-                    "    • Enabled: {}",
+                    "    {} Enabled: {}",
+                    plain_text("•"),
                     if config.daemon_enabled.unwrap_or(false) {
                         "yes".green()
                     } else {
@@ -2457,23 +7340,37 @@ fn handle_config(cli: &Cli, cmd: &ConfigCommand) -> Result<()> {
                 );
                 println!();
 
+                println!("  {}Search:", plain_text("🔎 ").yellow());
+                println!(
+                    "    {} Analyzer: {}",
+                    plain_text("•"),
+                    config.search.analyzer.as_str()
+                );
+                println!();
+
                 // Redaction rules
-                println!("  {} Redaction Rules:", "🔒".yellow());
+                println!("  {}Redaction Rules:", plain_text("🔒 ").yellow());
                 if config.redaction_overrides.is_empty() {
-                    println!("    • {}", "none configured".dimmed());
+                    println!("    {} {}", plain_text("•"), "none configured".dimmed());
                 } else {
                     for (name, pattern) in &config.redaction_overrides {
-                        println!("    • {} = {}", name.cyan(), pattern);
+                        println!("    {} {} = {}", plain_text("•"), name.cyan(), pattern);
                     }
                 }
                 println!();
 
-                println!("💡 Commands:");
+                println!("{}Commands:", plain_text("💡 "));
                 if config.default_remote.is_none() {
-                    println!("  • Set remote: fuku config remote --set <url>");
+                    println!(
+                        "  {} Set remote: fuku config remote --set <url>",
+                        plain_text("•")
+                    );
                 }
-                println!("  • Enable auto-sync: fuku sync --enable-auto");
-                println!("  • Add redaction: fuku config redact --set 'api_key=(?i)api[_-]?key\\s*[:=]\\s*['\"]?([a-zA-Z0-9]+)'");
+                println!(
+                    "  {} Enable auto-sync: fuku sync --enable-auto",
+                    plain_text("•")
+                );
+                println!("  {} Add redaction: fuku config redact --set 'api_key=(?i)api[_-]?key\\s*[:=]\\s*['\"]?([a-zA-Z0-9]+)'", plain_text("•"));
             }
 
             Ok(())
@@ -2548,8 +7445,125 @@ fn handle_config(cli: &Cli, cmd: &ConfigCommand) -> Result<()> {
                     }
                 }
             }
-            Ok(())
+            Ok(())
+        }
+        ConfigCommand::Analyzer(cmd) => {
+            let repo = open_repo(cli)?;
+            let Some(analyzer) = cmd.analyzer else {
+                println!("{}", repo.config()?.search.analyzer.as_str());
+                return Ok(());
+            };
+            if !update_analyzer(&repo, analyzer)? {
+                if !cli.quiet {
+                    println!("Analyzer is already {}", analyzer.as_str());
+                }
+                return Ok(());
+            }
+            // Reopening rebuilds the index; the open handle must go first
+            let root = repo.root().to_path_buf();
+            drop(repo);
+            FukuraRepo::open(&root)?;
+            if !cli.quiet {
+                println!(
+                    "{} Search analyzer set to {}",
+                    plain_text("✓").green(),
+                    analyzer.as_str()
+                );
+            }
+            Ok(())
+        }
+        ConfigCommand::Edit(cmd) => edit_config_file(cli, &config_file_path(cli, cmd.global)?),
+        ConfigCommand::Check(cmd) => {
+            let path = config_file_path(cli, cmd.global)?;
+            let problems = if path.exists() {
+                crate::config::validate_config(&fs::read_to_string(&path)?)
+            } else {
+                vec![]
+            };
+            if !cli.output.emit(&problems)? {
+                if problems.is_empty() {
+                    if !cli.quiet {
+                        println!("{} {} is valid", plain_text("✓").green(), path.display());
+                    }
+                } else {
+                    print_config_problems(&path, &problems);
+                }
+            }
+            ensure!(
+                problems.is_empty(),
+                "{} problem(s) in {}",
+                problems.len(),
+                path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// The repository's or the global config file. The repository is found but
+/// not opened, so a config that no longer parses can still be fixed.
+fn config_file_path(cli: &Cli, global: bool) -> Result<PathBuf> {
+    if global {
+        return crate::config::FukuraConfig::global_config_path();
+    }
+    let root = FukuraRepo::find_root(cli.repo.as_deref())?;
+    Ok(root.join(".fukura").join("config"))
+}
+
+fn print_config_problems(path: &Path, problems: &[crate::config::ConfigProblem]) {
+    eprintln!("{} {}:", plain_text("✗").red(), path.display());
+    for problem in problems {
+        eprintln!(
+            "  {} {}",
+            plain_text("•"),
+            problem.to_string().replace('\n', "\n    ")
+        );
+    }
+}
+
+/// Edit `path` in $EDITOR until it validates or the user gives up; only a
+/// valid config is written back
+fn edit_config_file(cli: &Cli, path: &Path) -> Result<()> {
+    let original = if path.exists() {
+        fs::read_to_string(path)?
+    } else {
+        "version = 1\n".to_string()
+    };
+    let mut text = original.clone();
+    loop {
+        let Some(edited) = Editor::new().extension(".toml").edit(&text)? else {
+            if !cli.quiet {
+                println!("Config left unchanged");
+            }
+            return Ok(());
+        };
+        let problems = crate::config::validate_config(&edited);
+        if problems.is_empty() {
+            if edited == original {
+                if !cli.quiet {
+                    println!("Config left unchanged");
+                }
+                return Ok(());
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, &edited)?;
+            crate::config::audit_config_change(path, Some(&original), &edited)?;
+            if !cli.quiet {
+                println!("{} Saved {}", plain_text("✓").green(), path.display());
+            }
+            return Ok(());
+        }
+        print_config_problems(path, &problems);
+        let again = dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Edit again? (no discards your changes)")
+            .default(true)
+            .interact()?;
+        if !again {
+            bail!("Config not saved; {} is unchanged", path.display());
         }
+        text = edited;
     }
 }
 
@@ -2562,6 +7576,14 @@ fn parse_redaction_entry(entry: &str) -> Result<(String, String)> {
         !value.trim().is_empty(),
         "Redaction pattern cannot be empty"
     );
+    let builtin = crate::redaction::builtin_rule_names();
+    ensure!(
+        builtin.contains(&key.trim()),
+        "'{}' is not a built-in redaction rule; one of: {}",
+        key.trim(),
+        builtin.join(", ")
+    );
+    regex::Regex::new(value).with_context(|| format!("Invalid regex for '{}'", key.trim()))?;
     Ok((key.trim().to_string(), value.to_string()))
 }
 
@@ -2607,13 +7629,23 @@ async fn handle_serve(cli: &Cli, cmd: &ServeCommand) -> Result<()> {
     let listener = TcpListener::bind(&addr).await?;
     let state = ServeState {
         repo: Arc::new(repo.clone()),
-        index: Arc::new(SearchIndex::open_or_create(&repo)?),
+        index: Arc::new(repo.index()?.clone()),
         default_limit: cmd.page_size,
+        webhooks: Webhooks::new(repo.config()?.webhooks),
     };
     let app = Router::new()
         .route("/healthz", get(health))
         .route("/notes", get(list_notes).post(create_note))
-        .route("/notes/:id", get(show_note))
+        .route(
+            "/notes/{id}",
+            get(show_note).put(update_note).delete(delete_note),
+        )
+        .route("/stats", get(team_stats))
+        .route("/tags", get(tag_counts))
+        .route("/feed.xml", get(note_feed))
+        .route("/v1/clone", get(clone_manifest))
+        .route("/v1/notes/{id}", get(show_note))
+        .route("/v1/objects/{id}", get(show_object))
         .with_state(state);
     if !cli.quiet {
         println!("{} Serving at http://{}", "".bright_blue(), addr);
@@ -2622,22 +7654,476 @@ async fn handle_serve(cli: &Cli, cmd: &ServeCommand) -> Result<()> {
     Ok(())
 }
 
-fn open_repo(cli: &Cli) -> Result<FukuraRepo> {
-    match &cli.repo {
-        Some(path) => FukuraRepo::open(path),
-        None => FukuraRepo::discover(None),
+fn handle_lsp(cli: &Cli) -> Result<()> {
+    let repo = open_repo(cli)?;
+    // stdout carries the protocol; diagnostics go to stderr
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    crate::editor_server::EditorServer::new(repo).run(stdin.lock(), stdout.lock())
+}
+
+fn handle_ci(cli: &Cli, cmd: &CiCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let mut log = String::new();
+    io::stdin()
+        .read_to_string(&mut log)
+        .context("Failed to read the build log from stdin")?;
+
+    let feedback = FeedbackStore::new(repo.root()).stats().unwrap_or_default();
+    let link_base = cmd
+        .link_base
+        .clone()
+        .or_else(|| repo.config().ok()?.default_remote);
+    let run = RunInfo::from_env();
+    let findings = scan_log(&log, cmd.max_errors)
+        .into_iter()
+        .map(|error| {
+            let mut solutions =
+                crate::daemon::FukuraDaemon::solutions_for_error(&repo, &error.message, &feedback)?;
+            solutions.truncate(MAX_SOLUTIONS);
+            Ok(CiFinding { error, solutions })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Workflow commands are read from stdout
+    for finding in &findings {
+        println!("{}", annotation(finding, &run, link_base.as_deref()));
+    }
+
+    if let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY") {
+        if !path.is_empty() && !findings.is_empty() {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("Failed to open job summary {}", path))?;
+            file.write_all(step_summary(&findings, link_base.as_deref()).as_bytes())?;
+        }
+    }
+
+    if let Some(path) = &cmd.report {
+        fs::write(path, serde_json::to_string_pretty(&findings)?)
+            .with_context(|| format!("Failed to write report {}", path.display()))?;
+    }
+
+    if cmd.archive && !findings.is_empty() {
+        let mut note = failure_note(&findings, &run);
+        if let Some(ttl) = repo.config()?.capture.expiry() {
+            note.set_expires_at(note.created_at + ttl);
+        }
+        let record = NoteService::new(&repo)?.create(note)?.record;
+        println!(
+            "::notice title=fuku::Archived the failure as note {}",
+            format_object_id(&record.object_id)
+        );
+    }
+
+    if !cli.quiet {
+        let known = findings.iter().filter(|f| !f.solutions.is_empty()).count();
+        println!(
+            "{} {} errors, {} with known fixes",
+            if known > 0 {
+                plain_text("✓").green()
+            } else {
+                plain_text("ℹ").cyan()
+            },
+            findings.len(),
+            known
+        );
     }
+    Ok(())
+}
+
+/// `fuku last-error --output json|yaml`
+#[derive(Debug, Serialize)]
+struct LastErrorOutput {
+    #[serde(flatten)]
+    failure: CommandRecord,
+    /// Id of the note saved with `--note`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
 }
 
-fn normalize_tags(raw: Vec<String>) -> Vec<String> {
-    let mut tags = raw
+fn handle_last_error(cli: &Cli, cmd: &LastErrorCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let cwd = std::env::current_dir()?;
+    let last_failure = CommandLog::new(repo.root())?
+        .read_all()?
         .into_iter()
-        .map(|t| t.trim().to_lowercase().replace(' ', "-"))
-        .filter(|t| !t.is_empty())
-        .collect::<Vec<_>>();
-    tags.sort();
-    tags.dedup();
-    tags
+        .rev()
+        .filter(|record| cmd.anywhere || Path::new(&record.working_directory) == cwd)
+        .find(CommandRecord::failed);
+    let Some(failure) = last_failure else {
+        bail!(
+            "No failed command captured {}\n💡 Tip: Failures are captured while the daemon runs ('fuku daemon'){}",
+            if cmd.anywhere { "yet" } else { "in this directory" },
+            if cmd.anywhere { "" } else { "; --anywhere looks in every directory" }
+        );
+    };
+
+    let note = if cmd.note {
+        let msg = crate::ipc::HookMessage {
+            session_id: failure.session_id.clone(),
+            command: failure.command.clone(),
+            exit_code: failure.exit_code.unwrap_or(1),
+            working_dir: failure.working_directory.clone(),
+            stderr: failure.stderr.clone().unwrap_or_default(),
+            duration_ms: failure.duration_ms,
+        };
+        let config = repo.config()?;
+        let mut note = crate::daemon::FukuraDaemon::failure_note(&msg, &config.capture);
+        config
+            .scope
+            .assign(&mut note, repo.root(), Path::new(&msg.working_dir));
+        let created = NoteService::new(&repo)?
+            .with_auto_tags()
+            .with_dedup(crate::dedupe::DEFAULT_THRESHOLD)
+            .create(note)?;
+        Some(link_incident_issue(cli, &repo, created.record))
+    } else {
+        None
+    };
+    let summary = LastErrorOutput {
+        note: note.as_ref().map(|record| record.object_id.clone()),
+        failure,
+    };
+    if cli.output.emit(&summary)? {
+        return Ok(());
+    }
+
+    let failure = &summary.failure;
+    let time =
+        chrono::DateTime::<chrono::Local>::from(failure.timestamp).format("%Y-%m-%d %H:%M:%S");
+    println!("{} {}", "$".red().bold(), failure.command.bold());
+    println!(
+        "  {} exit {} {} {} {} {}",
+        plain_text("✗").red(),
+        failure.exit_code.unwrap_or(1),
+        plain_text("·"),
+        time,
+        plain_text("·"),
+        failure.working_directory.dimmed()
+    );
+    if let Some(stderr) = failure.stderr.as_deref().filter(|s| !s.trim().is_empty()) {
+        println!();
+        println!("{}", stderr.trim_end());
+    }
+    match &note {
+        Some(record) => {
+            println!();
+            println!(
+                "{} Saved {} ({})",
+                plain_text("✓").green(),
+                record.note.title.bold(),
+                format_object_id(&record.object_id)
+            );
+        }
+        None if !cli.quiet => {
+            println!();
+            println!(
+                "{}Save it as a note with: fuku last-error --note; find fixes with: fuku explain",
+                plain_text("💡 ")
+            );
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Lines of a captured scrollback kept in a note
+const CAPTURE_NOTE_LINES: usize = 60;
+
+/// `fuku capture --output json|yaml`
+#[derive(Debug, Serialize)]
+struct CaptureOutput {
+    pane: String,
+    lines: usize,
+    /// Recording the scrollback was attached to
+    recording: Option<String>,
+    note: Option<String>,
+}
+
+fn handle_capture(cli: &Cli, cmd: &CaptureCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    if cmd.pane.is_none() && !crate::tmux::inside() {
+        bail!("Not inside tmux.\n💡 Tip: Pick a pane with --pane, e.g. 'fuku capture --tmux --pane work:1.0'");
+    }
+    let tmux = crate::tmux::Tmux::new();
+    let pane = tmux.pane_name(cmd.pane.as_deref())?;
+    let config = repo.config()?;
+    let redactor = crate::redaction::Redactor::default_with_overrides(&config.redaction_overrides);
+    let scrollback = redactor.redact(&tmux.capture_pane(cmd.pane.as_deref(), cmd.lines)?);
+    let line_count = scrollback.lines().count();
+
+    let store = RecordingStore::new(repo.root());
+    let recording = if cmd.note {
+        None
+    } else {
+        let found = store.find(cmd.name.as_deref(), &std::env::current_dir()?)?;
+        if let (Some(name), None) = (&cmd.name, &found) {
+            bail!("No recording named '{}'", name);
+        }
+        found
+    };
+
+    let mut output = CaptureOutput {
+        pane: pane.clone(),
+        lines: line_count,
+        recording: None,
+        note: None,
+    };
+    if let Some(recording) = recording {
+        let file_name = format!("scrollback-{}.txt", chrono::Utc::now().timestamp_millis());
+        let content = format!("{}\n{}", pane, scrollback.trim_end());
+        store.attach(&recording, &file_name, &content)?;
+        output.recording = Some(recording.name.clone());
+        if cli.output.emit(&output)? {
+            return Ok(());
+        }
+        if !cli.quiet {
+            println!(
+                "{} Attached {} lines from {} to recording '{}'",
+                plain_text("✓").green(),
+                line_count,
+                pane.cyan(),
+                recording.name
+            );
+            println!(
+                "{}It goes into the note when you run: fuku done",
+                plain_text("💡 ")
+            );
+        }
+        return Ok(());
+    }
+
+    let excerpt = crate::tmux::excerpt(&scrollback, CAPTURE_NOTE_LINES);
+    let title = cmd.title.clone().unwrap_or_else(|| match &excerpt.error {
+        Some(error) => format!(
+            "Error in {}: {}",
+            pane,
+            error.chars().take(80).collect::<String>()
+        ),
+        None => format!("Terminal output from {}", pane),
+    });
+    let mut body = format!("## Terminal output from `{}`\n\n```\n", pane);
+    for line in &excerpt.lines {
+        body.push_str(line);
+        body.push('\n');
+    }
+    body.push_str("```\n");
+    let mut tags = vec!["terminal".to_string(), "tmux".to_string()];
+    if excerpt.error.is_some() {
+        tags.push("error".to_string());
+    }
+    let mut note = NoteBuilder::new(title, body)
+        .tags(tags)
+        .meta("source", "tmux")
+        .meta("pane", pane.clone())
+        .author(resolve_author(None, None))
+        .build();
+    let cwd = std::env::current_dir()?;
+    config.capture.confine(&mut note, &cwd);
+    config.scope.assign(&mut note, repo.root(), &cwd);
+    let record = NoteService::new(&repo)?.create(note)?.record;
+    output.note = Some(record.object_id.clone());
+    if cli.output.emit(&output)? {
+        return Ok(());
+    }
+    if !cli.quiet {
+        println!(
+            "{} Captured {} ({})",
+            plain_text("✓").green(),
+            record.note.title.bold(),
+            format_object_id(&record.object_id)
+        );
+        println!(
+            "{}Add what you found: fuku edit @latest --editor",
+            plain_text("💡 ")
+        );
+    }
+    Ok(())
+}
+
+async fn handle_explain(cli: &Cli, cmd: &ExplainCommand) -> Result<()> {
+    let repo = open_repo(cli)?;
+    let (message, source) = if !is_terminal::is_terminal(io::stdin()) {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read the error from stdin")?;
+        if input.trim().is_empty() {
+            bail!(
+                "No error on stdin.\n💡 Tip: Pipe one in, e.g. 'cargo build 2>&1 | fuku explain'"
+            );
+        }
+        (
+            crate::patterns::failure_message("", 1, &input),
+            "stdin".to_string(),
+        )
+    } else {
+        let last_failure = CommandLog::new(repo.root())
+            .and_then(|log| log.read_all())
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .find(CommandRecord::failed);
+        let Some(failure) = last_failure else {
+            bail!("No captured failure to explain.\n💡 Tip: Pipe an error in, e.g. 'cargo build 2>&1 | fuku explain', or start the daemon to capture failures");
+        };
+        (
+            crate::patterns::failure_message(
+                &failure.command,
+                failure.exit_code.unwrap_or(1),
+                failure.stderr.as_deref().unwrap_or_default(),
+            ),
+            failure.command,
+        )
+    };
+    let message = crate::patterns::normalize_message(&message);
+
+    let feedback = FeedbackStore::new(repo.root()).stats().unwrap_or_default();
+    let mut solutions =
+        crate::daemon::FukuraDaemon::solutions_for_error(&repo, &message, &feedback)?;
+    solutions.truncate(cmd.limit);
+    let mut remote = Vec::new();
+    if cmd.remote {
+        let query = crate::daemon::FukuraDaemon::error_search_terms(&message);
+        match repo.config()?.default_remote {
+            Some(url) if !query.is_empty() => {
+                match crate::remote_search::search_remote(&url, &query, cmd.limit).await {
+                    Ok(hits) => remote = hits,
+                    Err(e) => {
+                        if !cli.quiet && !cli.output.is_structured() {
+                            println!("{} Remote search failed: {}", plain_text("⚠").yellow(), e);
+                        }
+                    }
+                }
+            }
+            Some(_) => {}
+            None => bail!("No remote configured. Set with: fuku config remote --set <URL>"),
+        }
+    }
+
+    let output = ExplainOutput {
+        error: message,
+        source,
+        solutions,
+        remote,
+    };
+    if cli.output.emit(&output)? || cli.quiet {
+        return Ok(());
+    }
+
+    println!("{}{}", plain_text("🔍 ").cyan(), output.error.bold());
+    if output.source != "stdin" {
+        println!(
+            "   {}",
+            format!("from the last failure: {}", output.source).dimmed()
+        );
+    }
+    println!();
+    if output.solutions.is_empty() && output.remote.is_empty() {
+        println!("{} No known fixes yet", plain_text("ℹ️").blue());
+        println!(
+            "{}Once you've solved it, save the fix: fuku add --title \"...\"",
+            plain_text("💡 ")
+        );
+        return Ok(());
+    }
+    for (i, hit) in output.solutions.iter().enumerate() {
+        println!(
+            "{}. {} ({}) {} confidence {:.0}%",
+            i + 1,
+            hit.title.bold(),
+            format_object_id(&hit.note_id),
+            plain_text("·"),
+            hit.confidence * 100.0
+        );
+        if !hit.snippet.trim().is_empty() {
+            println!("   {}", hit.snippet.trim().dimmed());
+        }
+    }
+    if !output.remote.is_empty() {
+        println!();
+        println!("{}From the remote hub:", plain_text("🌐 ").blue());
+        for hit in &output.remote {
+            println!(
+                "  {} {} ({})",
+                plain_text("•"),
+                hit.title.bold(),
+                format_object_id(&hit.object_id)
+            );
+            if !hit.snippet.trim().is_empty() {
+                println!("    {}", hit.snippet.trim().dimmed());
+            }
+        }
+    }
+    if let Some(first) = output.solutions.first() {
+        println!();
+        println!(
+            "{}Next: fuku view {}",
+            plain_text("💡 "),
+            format_object_id(&first.note_id)
+        );
+    }
+    Ok(())
+}
+
+/// Open a tracker issue for a note tagged as an incident, when a tracker is
+/// configured. A failure is reported but doesn't fail the command.
+fn link_incident_issue(cli: &Cli, repo: &FukuraRepo, record: NoteRecord) -> NoteRecord {
+    let Ok(config) = repo.config() else {
+        return record;
+    };
+    let incident_tag = config.integrations.incident_tag.to_lowercase();
+    if config.integrations.issues.is_none() || !record.note.tags.contains(&incident_tag) {
+        return record;
+    }
+    let linked = IssueLinker::from_config(&config.integrations).and_then(|linker| {
+        let Some(linker) = linker.filter(|linker| linker.needs_incident_issue(&record.note)) else {
+            return Ok(None);
+        };
+        let linked = block_on(linker.open_issue(repo, &record, IssueReason::Incident))??;
+        Ok(Some((linker.tracker_name(), linked)))
+    });
+    match linked {
+        Ok(Some((tracker, linked))) => {
+            if !cli.quiet {
+                println!(
+                    "{}Opened {} issue: {}",
+                    plain_text("🔗 ").cyan(),
+                    tracker,
+                    linked
+                        .note
+                        .meta
+                        .get(ISSUE_URL_META)
+                        .map_or("", String::as_str)
+                );
+            }
+            linked
+        }
+        Ok(None) => record,
+        Err(e) => {
+            eprintln!(
+                "{} Could not open an issue: {}",
+                plain_text("⚠️").yellow(),
+                e
+            );
+            record
+        }
+    }
+}
+
+fn open_repo(cli: &Cli) -> Result<FukuraRepo> {
+    // Opening may rebuild the search index
+    let _interrupt = crate::interrupt::watch();
+    let repo = match &cli.repo {
+        Some(path) => FukuraRepo::open(path)?,
+        None => FukuraRepo::discover(None)?,
+    };
+    // Best effort: --all-repos just won't see it
+    let _ = registry::register(repo.root());
+    Ok(repo)
 }
 
 fn parse_meta(raw: Vec<String>) -> Result<BTreeMap<String, String>> {
@@ -2651,84 +8137,306 @@ fn parse_meta(raw: Vec<String>) -> Result<BTreeMap<String, String>> {
     Ok(map)
 }
 
+/// Check meta against the repository's `meta_schema`
+fn validate_meta(repo: &FukuraRepo, meta: &BTreeMap<String, String>) -> Result<()> {
+    let problems = repo.config()?.meta_schema.validate(meta);
+    if !problems.is_empty() {
+        bail!(
+            "Invalid metadata:\n  {}\n💡 Tip: Fields are defined under [meta_schema] in .fukura/config",
+            problems.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
 fn resolve_author(name: Option<&str>, email: Option<&str>) -> Author {
-    let default_name = name
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("GIT_AUTHOR_NAME").ok())
-        .unwrap_or_else(|| {
-            std::env::var("USER")
-                .or_else(|_| std::env::var("USERNAME"))
-                .unwrap_or_else(|_| "unknown".to_string())
-        });
-    let default_email = email
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("GIT_AUTHOR_EMAIL").ok())
-        .or_else(|| std::env::var("EMAIL").ok());
+    let default = crate::note_service::default_author();
     Author {
-        name: default_name,
-        email: default_email,
+        name: name.map(|s| s.to_string()).unwrap_or(default.name),
+        email: email.map(|s| s.to_string()).or(default.email),
+    }
+}
+
+/// Where the page sits among all results, and how to get the next one
+fn render_page_footer(page: &SearchPage, limit: usize) {
+    if page.offset == 0 && page.next_offset.is_none() {
+        return;
+    }
+    let shown = page.hits.iter().filter(|hit| !hit.pinned).count();
+    println!(
+        "{}Showing {}-{} of {}",
+        plain_text("📄 ").dimmed(),
+        (page.offset + 1).min(page.total),
+        (page.offset + shown).min(page.total),
+        page.total
+    );
+    if let Some(next) = page.next_offset {
+        if next % limit == 0 {
+            println!(
+                "{}Next page: --page {}",
+                plain_text("💡 "),
+                next / limit + 1
+            );
+        } else {
+            println!("{}Next page: --offset {}", plain_text("💡 "), next);
+        }
     }
 }
 
 fn render_search_table(hits: &[SearchHit]) {
     if hits.is_empty() {
-        println!("{} No results found", "ℹ️".blue());
+        println!("{} No results found", plain_text("ℹ️").blue());
         println!();
-        println!("💡 Suggestions:");
-        println!("  • Use broader search terms");
-        println!("  • Create your first note: fuku add --title 'My Note'");
-        println!("  • Check if any notes exist: fuku stats");
+        println!("{}Suggestions:", plain_text("💡 "));
+        println!("  {} Use broader search terms", plain_text("•"));
+        println!(
+            "  {} Create your first note: fuku add --title 'My Note'",
+            plain_text("•")
+        );
+        println!("  {} Check if any notes exist: fuku stats", plain_text("•"));
         return;
     }
+    // Only monorepos with `[scope]` packages have scoped notes
+    let scoped = hits.iter().any(|hit| hit.scope.is_some());
+    let mut header = vec!["#", "Title", "Likes", "Updated", "By", "Tags"];
+    if scoped {
+        header.push("Scope");
+    }
     let mut table = Table::new();
     table
-        .load_preset(UTF8_HORIZONTAL_ONLY)
-        .set_header(vec!["#", "Title", "Likes", "Updated", "By", "Tags"]);
+        .load_preset(crate::output::table_preset())
+        .set_header(header);
     for (idx, hit) in hits.iter().enumerate() {
-        table.add_row(vec![
+        let mut title = if hit.pinned {
+            format!("{}{}", plain_text("📌 "), hit.title)
+        } else {
+            hit.title.clone()
+        };
+        if let Some(snippet) = &hit.snippet {
+            title.push_str("\n  ");
+            title.push_str(&marked_snippet(snippet));
+        }
+        let mut row = vec![
             format!("{:>2}", idx + 1),
-            hit.title.clone(),
+            title,
             hit.likes.to_string(),
             hit.updated_at.format("%Y-%m-%d").to_string(),
-            hit.author.clone(),
+            if hit.verified {
+                format!("{} {}", hit.author, plain_text("✓"))
+            } else {
+                hit.author.clone()
+            },
             hit.tags.join(", "),
-        ]);
+        ];
+        if scoped {
+            row.push(hit.scope.clone().unwrap_or_default());
+        }
+        table.add_row(row);
     }
     println!("{}", " Results".bold());
     println!("{}", table);
 }
 
-fn render_note(record: &NoteRecord) {
+/// A snippet as plain text with the matched terms in Markdown bold. The
+/// table can't hold ANSI styling without breaking its column widths.
+fn marked_snippet(snippet: &HitSnippet) -> String {
+    snippet
+        .segments()
+        .into_iter()
+        .map(|(text, matched)| {
+            if matched {
+                format!("**{}**", text)
+            } else {
+                text.to_string()
+            }
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Notes related to `record` for showing next to it; a failed lookup only
+/// means none are shown
+fn related_for_view(repo: &FukuraRepo, record: &NoteRecord) -> Vec<RelatedNote> {
+    related::related_notes(repo, record, related::DEFAULT_LIMIT).unwrap_or_else(|err| {
+        tracing::debug!(error = %err, "related notes lookup failed");
+        Vec::new()
+    })
+}
+
+/// A note for the terminal; `trust` adds whether its signature is trusted
+fn render_note(record: &NoteRecord, trust: Option<&Trust>, related: &[RelatedNote]) {
+    let note = &record.note;
+    println!("{}", note.title.bold());
+    let short_id = format_object_id(&record.object_id);
+    println!(
+        "{} {} {} {}",
+        "".cyan(),
+        short_id,
+        plain_text("·"),
+        note.updated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    if !note.tags.is_empty() {
+        println!("{} #{}", "".yellow(), note.tags.join(" #"));
+    }
+    let by = note.contributor_names().join(", ");
+    match trust {
+        Some(Trust::Verified { name }) => println!(
+            "{} {} {}",
+            "by".dimmed(),
+            by,
+            format!("{} verified ({})", plain_text("✓"), name).green()
+        ),
+        Some(Trust::Untrusted { signer }) => println!(
+            "{} {} {}",
+            "by".dimmed(),
+            by,
+            format!("signed by {}, key not trusted", signer).yellow()
+        ),
+        Some(Trust::Invalid) => println!(
+            "{} {} {}",
+            "by".dimmed(),
+            by,
+            plain_text("✗ signature doesn't match the note").red()
+        ),
+        Some(Trust::Unsigned) | None => println!("{} {}", "by".dimmed(), by),
+    }
+    if !note.links.is_empty() {
+        println!("{}", " Links".bold());
+        for link in &note.links {
+            println!("  - {}", link);
+        }
+    }
+    println!();
+    println!("{}", note.body);
+    if !note.meta.is_empty() {
+        println!();
+        println!("{}", " Meta".bold());
+        for (key, value) in &note.meta {
+            println!("  {} = {}", key.cyan(), value);
+        }
+    }
+    if !note.contributors.is_empty() {
+        println!();
+        println!("{}", " Revisions".bold());
+        println!(
+            "  {}  {} (created)",
+            note.created_at.format("%Y-%m-%d %H:%M UTC"),
+            note.author.name
+        );
+        for contributor in &note.contributors {
+            println!(
+                "  {}  {}",
+                contributor.at.format("%Y-%m-%d %H:%M UTC"),
+                contributor.name
+            );
+        }
+    }
+    if !related.is_empty() {
+        println!();
+        println!("{}", " Related notes".bold());
+        for note in related {
+            let shared = if note.shared_tags.is_empty() {
+                String::new()
+            } else {
+                format!(" #{}", note.shared_tags.join(" #"))
+            };
+            println!(
+                "  {} {}{}",
+                format_object_id(&note.object_id).cyan(),
+                note.title,
+                shared.yellow()
+            );
+        }
+    }
+}
+
+/// A note as a Markdown document: title, summary line, body, then solutions,
+/// links and meta sections
+fn render_note_markdown(record: &NoteRecord, schema: &MetaSchema) -> String {
     let note = &record.note;
-    println!("{}", note.title.bold());
-    let short_id = format_object_id(&record.object_id);
-    println!(
-        "{} {} · {}",
-        "".cyan(),
-        short_id,
-        note.updated_at.format("%Y-%m-%d %H:%M UTC")
-    );
+    let mut out = format!("# {}\n\n", note.title);
+    let mut summary = vec![
+        format!("`{}`", format_object_id(&record.object_id)),
+        format!("updated {}", note.updated_at.format("%Y-%m-%d %H:%M UTC")),
+        format!("by {}", note.contributor_names().join(", ")),
+    ];
     if !note.tags.is_empty() {
-        println!("{} #{}", "".yellow(), note.tags.join(" #"));
+        summary.push(format!("#{}", note.tags.join(" #")));
+    }
+    out.push_str(&format!("_{}_\n\n", summary.join(" · ")));
+    out.push_str(note.body.trim());
+    out.push('\n');
+
+    if !note.solutions.is_empty() {
+        out.push_str("\n## Solutions\n");
+        for (idx, solution) in note.solutions.iter().enumerate() {
+            if note.solutions.len() > 1 {
+                out.push_str(&format!("\n### Solution {}\n", idx + 1));
+            }
+            out.push('\n');
+            for (step, text) in solution.steps.iter().enumerate() {
+                out.push_str(&format!("{}. {}\n", step + 1, text));
+            }
+            for link in &solution.links {
+                out.push_str(&format!("- <{}>\n", link));
+            }
+        }
     }
     if !note.links.is_empty() {
-        println!("{}", " Links".bold());
+        out.push_str("\n## Links\n\n");
         for link in &note.links {
-            println!("  - {}", link);
+            out.push_str(&format!("- {}\n", link));
         }
     }
-    println!();
-    println!("{}", note.body);
     if !note.meta.is_empty() {
-        println!();
-        println!("{}", " Meta".bold());
-        for (key, value) in &note.meta {
-            println!("  {} = {}", key.cyan(), value);
+        out.push_str("\n## Meta\n\n| Field | Value |\n| --- | --- |\n");
+        // Schema fields first, in schema order, like the HTML page
+        let fields = schema
+            .fields
+            .keys()
+            .filter_map(|key| note.meta.get_key_value(key))
+            .chain(
+                note.meta
+                    .iter()
+                    .filter(|(key, _)| schema.get(key).is_none()),
+            );
+        for (key, value) in fields {
+            let label = schema
+                .get(key)
+                .and_then(|field| field.label.as_deref())
+                .unwrap_or(key);
+            out.push_str(&format!(
+                "| {} | {} |\n",
+                label.replace('|', "\\|"),
+                value.replace('|', "\\|")
+            ));
         }
     }
+    out
+}
+
+/// A note as a standalone HTML page. With `note_base` (e.g. `/notes/`), links
+/// that aren't URLs are taken as note IDs and point there, as do `related`
+/// notes in the footer.
+/// Where note pages load the player for `fuku rec --cast` recordings
+const ASCIINEMA_PLAYER: &str = "https://cdn.jsdelivr.net/npm/asciinema-player@3.8.0/dist/bundle";
+
+/// The asciicast kept with a `fuku rec --cast` recording
+fn note_cast(repo: &FukuraRepo, record: &NoteRecord) -> Option<String> {
+    let attachment = record.note.meta.get(crate::models::CAST_ATTACHMENT_META)?;
+    let bytes = repo.load_attachment(attachment).ok()?;
+    String::from_utf8(bytes).ok()
 }
 
-fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
+fn render_note_html(
+    record: &NoteRecord,
+    theme: &str,
+    schema: &MetaSchema,
+    note_base: Option<&str>,
+    related: &[RelatedNote],
+    cast: Option<&str>,
+) -> Result<String> {
     let background = match theme {
         "light" => "#fdfdfd",
         _ => "#0f172a",
@@ -2741,11 +8449,20 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
         "light" => "#2563eb",
         _ => "#38bdf8",
     };
-    let mut body_html = String::new();
-    pulldown_cmark::html::push_html(
-        &mut body_html,
-        pulldown_cmark::Parser::new(&record.note.body),
-    );
+    // Secondary text and panels: white tints vanish on the light background
+    let (muted, faint, panel) = match theme {
+        "light" => (
+            "rgba(17,24,39,0.6)",
+            "rgba(17,24,39,0.45)",
+            "rgba(15,23,42,0.05)",
+        ),
+        _ => (
+            "rgba(255,255,255,0.65)",
+            "rgba(255,255,255,0.35)",
+            "rgba(15,23,42,0.12)",
+        ),
+    };
+    let body = crate::markdown::render_html(&record.note.body, theme == "light");
     let tags = if record.note.tags.is_empty() {
         String::new()
     } else {
@@ -2763,14 +8480,87 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
     let meta = if record.note.meta.is_empty() {
         String::new()
     } else {
-        let items = record
-            .note
-            .meta
-            .iter()
-            .map(|(k, v)| format!("<li><strong>{}</strong> {}</li>", k, v))
+        // Schema fields first, in schema order, then the free-form keys
+        let mut fields: Vec<(&String, &String)> = schema
+            .fields
+            .keys()
+            .filter_map(|key| record.note.meta.get_key_value(key))
+            .collect();
+        fields.extend(
+            record
+                .note
+                .meta
+                .iter()
+                .filter(|(key, _)| schema.get(key).is_none()),
+        );
+        let items = fields
+            .into_iter()
+            .map(|(key, value)| {
+                let field = schema.get(key);
+                let label = field
+                    .and_then(|field| field.label.as_deref())
+                    .unwrap_or(key);
+                let value = if field.is_some_and(|field| field.kind == MetaFieldType::Url) {
+                    format!(
+                        "<a href=\"{}\">{}</a>",
+                        html_escape::encode_double_quoted_attribute(value),
+                        html_escape::encode_text(value)
+                    )
+                } else {
+                    html_escape::encode_text(value).to_string()
+                };
+                format!(
+                    "<dt>{}</dt><dd>{}</dd>",
+                    html_escape::encode_text(label),
+                    value
+                )
+            })
             .collect::<Vec<_>>()
             .join("\n");
-        format!("<section><h2>Meta</h2><ul>{}</ul></section>", items)
+        format!(
+            "<section><h2>Meta</h2><dl class=\"meta-fields\">{}</dl></section>",
+            items
+        )
+    };
+    let revisions = if record.note.contributors.is_empty() {
+        String::new()
+    } else {
+        let items = std::iter::once(format!(
+            "<li><strong>{}</strong> created · {}</li>",
+            html_escape::encode_text(&record.note.author.name),
+            record.note.created_at.format("%Y-%m-%d %H:%M UTC")
+        ))
+        .chain(record.note.contributors.iter().map(|contributor| {
+            format!(
+                "<li><strong>{}</strong> edited · {}</li>",
+                html_escape::encode_text(&contributor.name),
+                contributor.at.format("%Y-%m-%d %H:%M UTC")
+            )
+        }))
+        .collect::<Vec<_>>()
+        .join("\n");
+        format!("<section><h2>Revisions</h2><ul>{}</ul></section>", items)
+    };
+    let solutions = record
+        .note
+        .solutions
+        .iter()
+        .map(|solution| {
+            let steps = solution
+                .steps
+                .iter()
+                .map(|step| format!("<li>{}</li>", html_escape::encode_text(step)))
+                .collect::<String>();
+            format!("<li><ol>{}</ol></li>", steps)
+        })
+        .collect::<Vec<_>>();
+    let solutions = if solutions.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<section><h2>Solutions</h2><ul>{}</ul></section>",
+            solutions.join("\n")
+        )
     };
     let links = if record.note.links.is_empty() {
         String::new()
@@ -2779,11 +8569,63 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
             .note
             .links
             .iter()
-            .map(|l| format!("<li><a href=\"{0}\">{0}</a></li>", l))
+            .map(|l| {
+                let href = match note_base {
+                    Some(base) if !l.contains("://") && !l.starts_with("mailto:") => {
+                        format!("{}{}", base, l)
+                    }
+                    _ => l.clone(),
+                };
+                format!(
+                    "<li><a href=\"{}\">{}</a></li>",
+                    html_escape::encode_double_quoted_attribute(&href),
+                    html_escape::encode_text(l)
+                )
+            })
             .collect::<Vec<_>>()
             .join("\n");
         format!("<section><h2>Links</h2><ul>{}</ul></section>", items)
     };
+    let related = if related.is_empty() {
+        String::new()
+    } else {
+        let items = related
+            .iter()
+            .map(|note| {
+                let title = html_escape::encode_text(&note.title);
+                let short_id = format_object_id(&note.object_id);
+                match note_base {
+                    Some(base) => format!(
+                        "<li><a href=\"{}{}\">{}</a> <code>{}</code></li>",
+                        base,
+                        html_escape::encode_double_quoted_attribute(&note.object_id),
+                        title,
+                        short_id
+                    ),
+                    None => format!("<li>{} <code>{}</code></li>", title, short_id),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "<nav class=\"related\"><h2>Related notes</h2><ul>{}</ul></nav>",
+            items
+        )
+    };
+    let cast = match cast {
+        // Embedded in a script element, which only `</` can end early
+        Some(cast) => format!(
+            r#"<section class="cast"><h2>Terminal recording</h2><div id="cast-player"></div></section>
+    <link rel="stylesheet" href="{player}/asciinema-player.css" />
+    <script src="{player}/asciinema-player.min.js"></script>
+    <script>
+      AsciinemaPlayer.create({{ data: {data} }}, document.getElementById('cast-player'), {{ fit: 'width', idleTimeLimit: 2 }});
+    </script>"#,
+            player = ASCIINEMA_PLAYER,
+            data = serde_json::to_string(cast)?.replace("</", "<\\/"),
+        ),
+        None => String::new(),
+    };
     Ok(format!(
         r#"<!doctype html>
 <html lang="en">
@@ -2821,7 +8663,7 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
       display: flex;
       gap: 1rem;
       align-items: center;
-      color: rgba(255,255,255,0.65);
+      color: {muted};
       text-transform: uppercase;
       font-weight: 600;
       letter-spacing: 0.12em;
@@ -2846,7 +8688,7 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
       font-size: 1.1rem;
       text-transform: uppercase;
       letter-spacing: 0.16em;
-      color: rgba(255,255,255,0.5);
+      color: {muted};
       margin-bottom: 0.8rem;
     }}
     section ul {{
@@ -2858,9 +8700,17 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
     }}
     section ul li {{
       padding: 0.6rem 0.8rem;
-      background: rgba(15,23,42,0.12);
+      background: {panel};
       border-radius: 12px;
     }}
+    dl.meta-fields {{
+      display: grid;
+      grid-template-columns: max-content 1fr;
+      gap: 0.4rem 1.5rem;
+      margin: 0;
+    }}
+    dl.meta-fields dt {{ font-weight: 600; opacity: 0.7; }}
+    dl.meta-fields dd {{ margin: 0; }}
     a {{ color: {accent}; text-decoration: none; font-weight: 600; }}
     a:hover {{ text-decoration: underline; }}
     article {{
@@ -2878,18 +8728,54 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
     }}
     article code {{
       font-family: 'JetBrains Mono', 'Fira Code', monospace;
-      background: rgba(15,23,42,0.35);
+      background: {panel};
       padding: 0.2rem 0.45rem;
       border-radius: 8px;
       font-size: 0.9rem;
     }}
+    article pre code {{ background: none; padding: 0; }}
+    .code-block {{ position: relative; }}
+    .code-block .copy {{
+      position: absolute;
+      top: 0.6rem;
+      right: 0.6rem;
+      background: {panel};
+      color: {accent};
+      border: 1px solid {accent};
+      border-radius: 8px;
+      padding: 0.2rem 0.6rem;
+      font-size: 0.75rem;
+      cursor: pointer;
+      opacity: 0.7;
+    }}
+    .code-block .copy:hover {{ opacity: 1; }}
+    nav.toc {{
+      margin-top: 2rem;
+      padding: 1rem 1.5rem;
+      background: {panel};
+      border-radius: 16px;
+    }}
+    nav.toc h2 {{
+      font-size: 0.8rem;
+      text-transform: uppercase;
+      letter-spacing: 0.16em;
+      color: {muted};
+      margin: 0 0 0.5rem;
+    }}
+    nav.toc ul {{ list-style: none; padding: 0; margin: 0; }}
+    nav.toc li.toc-level-1 {{ padding-left: 1rem; }}
+    nav.toc li.toc-level-2 {{ padding-left: 2rem; }}
     footer {{
       margin-top: 3rem;
       display: flex;
+      flex-wrap: wrap;
       justify-content: space-between;
-      color: rgba(255,255,255,0.35);
+      color: {faint};
       font-size: 0.8rem;
     }}
+    nav.related {{ flex-basis: 100%; margin-bottom: 1.5rem; font-size: 0.95rem; }}
+    nav.related h2 {{ font-size: 0.8rem; text-transform: uppercase; color: {muted}; }}
+    nav.related ul {{ padding-left: 1.2rem; margin: 0; }}
   </style>
 </head>
 <body>
@@ -2903,14 +8789,30 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
       </div>
       {tags}
     </header>
+    {toc}
     <article>{body}</article>
+    {cast}
+    {solutions}
     {links}
     {meta}
+    {revisions}
     <footer>
+      {related}
       <span>Fukura · {object_id}</span>
       <span>{created}</span>
     </footer>
   </main>
+  <script>
+    document.querySelectorAll('.code-block .copy').forEach(function (button) {{
+      button.addEventListener('click', function () {{
+        var code = button.parentElement.querySelector('pre').innerText;
+        navigator.clipboard.writeText(code).then(function () {{
+          button.textContent = 'Copied';
+          setTimeout(function () {{ button.textContent = 'Copy'; }}, 1500);
+        }});
+      }});
+    }});
+  </script>
 </body>
 </html>
 "#,
@@ -2919,13 +8821,21 @@ fn render_note_html(record: &NoteRecord, theme: &str) -> Result<String> {
         background = background,
         foreground = foreground,
         accent = accent,
+        muted = muted,
+        faint = faint,
+        panel = panel,
         tags = tags,
-        body = body_html,
+        toc = body.toc_html(),
+        body = body.html,
+        cast = cast,
+        solutions = solutions,
         links = links,
         meta = meta,
+        revisions = revisions,
+        related = related,
         updated = record.note.updated_at.format("%Y-%m-%d %H:%M UTC"),
         created = record.note.created_at.format("%Y-%m-%d %H:%M UTC"),
-        author = html_escape::encode_text(&record.note.author.name),
+        author = html_escape::encode_text(&record.note.contributor_names().join(", ")),
         privacy = format_privacy(&record.note.privacy)
             .to_string()
             .to_uppercase(),
@@ -2978,6 +8888,67 @@ impl TimeFilter {
     }
 }
 
+enum TuiMode {
+    Browse,
+    Compose(Box<NoteForm>),
+    Tagging {
+        object_id: String,
+        input: TextEditor,
+    },
+    ConfirmDelete {
+        object_id: String,
+        title: String,
+    },
+}
+
+/// Centered strip of the given height used for TUI prompts
+fn popup_area(area: Rect, height: u16) -> Rect {
+    let height = height.min(area.height);
+    Rect {
+        x: area.x,
+        y: area.y + (area.height - height) / 2,
+        width: area.width,
+        height,
+    }
+}
+
+fn save_note_form(repo: &FukuraRepo, form: &NoteForm) -> Result<NoteRecord> {
+    let title = form.title.text().trim().to_string();
+    let body = form.body.text().trim().to_string();
+    ensure!(!title.is_empty(), "Title cannot be empty");
+    ensure!(!body.is_empty(), "Body cannot be empty");
+    let tags = normalize_tags(form.tag_list());
+
+    match &form.editing {
+        Some(object_id) => {
+            let mut record = repo.load_note(object_id)?;
+            record.note.title = title;
+            record.note.body = body;
+            record.note.tags = tags;
+            record
+                .note
+                .record_edit(&resolve_author(None, None), Utc::now());
+            repo.replace_note(object_id, record.note)
+        }
+        None => {
+            let note = NoteBuilder::new(title, body)
+                .tags(tags)
+                .author(resolve_author(None, None))
+                .build();
+            Ok(NoteService::new(repo)?.create(note)?.record)
+        }
+    }
+}
+
+fn retag_note(repo: &FukuraRepo, object_id: &str, raw_tags: &str) -> Result<NoteRecord> {
+    let mut record = repo.load_note(object_id)?;
+    record.note.tags = normalize_tags(raw_tags.split([',', ' ']).map(String::from));
+    record
+        .note
+        .record_edit(&resolve_author(None, None), Utc::now());
+    repo.replace_note(object_id, record.note)
+}
+
 fn apply_filters(
     hits: &[SearchHit],
     selected_tags: &HashSet<String>,
@@ -3005,7 +8976,9 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
     terminal.hide_cursor()?;
 
     let mut all_results = repo.search(query, limit, sort)?;
-    let available_tags = repo.collect_tags().unwrap_or_default();
+    pinned_first(&mut all_results);
+    // Most used first, so the tags worth filtering by are on top
+    let mut available_tags = repo.tag_stats().unwrap_or_default();
     let mut selected_tags: HashSet<String> = HashSet::new();
     let mut time_filter = TimeFilter::All;
     let mut displayed = apply_filters(&all_results, &selected_tags, &time_filter);
@@ -3021,6 +8994,10 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
 
     let mut focus = FocusArea::Results;
     let mut cached: Option<NoteRecord> = None;
+    let mut preview: Option<(String, Vec<Line<'static>>)> = None;
+    let mut related: Vec<RelatedNote> = Vec::new();
+    let mut mode = TuiMode::Browse;
+    let mut status = String::new();
 
     loop {
         if let Some(selected) = result_state.selected() {
@@ -3034,6 +9011,20 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
         } else {
             cached = None;
         }
+        match &cached {
+            Some(note) if preview.as_ref().map(|(id, _)| id) != Some(&note.object_id) => {
+                preview = Some((
+                    note.object_id.clone(),
+                    crate::markdown::render_markdown(&note.note.body),
+                ));
+                related = related_for_view(repo, note);
+            }
+            Some(_) => {}
+            None => {
+                preview = None;
+                related.clear();
+            }
+        }
 
         terminal.draw(|frame| {
             let size = frame.area();
@@ -3061,11 +9052,11 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
             } else {
                 available_tags
                     .iter()
-                    .map(|tag| {
-                        let active = selected_tags.contains(tag);
-                        let marker = if active { "☑" } else { "☐" };
+                    .map(|stat| {
+                        let active = selected_tags.contains(&stat.tag);
+                        let marker = plain_text(if active { "☑" } else { "☐" });
                         ListItem::new(Span::styled(
-                            format!("{} {}", marker, tag),
+                            format!("{} {} ({})", marker, stat.tag, stat.notes),
                             Style::default().fg(if active { Color::Magenta } else { Color::Gray }),
                         ))
                     })
@@ -3086,7 +9077,7 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                 .highlight_symbol(" ");
             frame.render_stateful_widget(tag_list, filter_chunks[0], &mut tag_state);
 
-            let filter_help = Paragraph::new(vec![
+            let mut help_lines = vec![
                 Line::from(vec![
                     Span::styled("Time:", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(format!(" {}", time_filter.label())),
@@ -3094,8 +9085,15 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                 Line::from("1:All  2:7d  3:30d  4:90d"),
                 Line::from("Space: toggle tag | f: clear filters"),
                 Line::from("Tab: switch focus"),
-            ])
-            .wrap(Wrap { trim: true });
+                Line::from("n:new e:edit t:tag d:delete"),
+            ];
+            if !status.is_empty() {
+                help_lines.push(Line::from(Span::styled(
+                    status.clone(),
+                    Style::default().fg(Color::Green),
+                )));
+            }
+            let filter_help = Paragraph::new(help_lines).wrap(Wrap { trim: true });
             frame.render_widget(filter_help, filter_chunks[1]);
 
             let main_chunks = Layout::default()
@@ -3116,9 +9114,14 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                         } else {
                             format!(" #{}", hit.tags.join(" #"))
                         };
-                        ListItem::new(Line::from(vec![
+                        let title = if hit.pinned {
+                            format!("{}{}", plain_text("📌 "), hit.title)
+                        } else {
+                            hit.title.clone()
+                        };
+                        let mut lines = vec![Line::from(vec![
                             Span::styled(
-                                hit.title.clone(),
+                                title,
                                 Style::default()
                                     .fg(Color::Cyan)
                                     .add_modifier(Modifier::BOLD),
@@ -3129,7 +9132,22 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                                 Style::default().fg(Color::DarkGray),
                             ),
                             Span::raw(tags),
-                        ]))
+                        ])];
+                        if let Some(snippet) = &hit.snippet {
+                            let mut spans = vec![Span::raw("  ")];
+                            spans.extend(snippet.segments().into_iter().map(|(text, matched)| {
+                                let style = if matched {
+                                    Style::default()
+                                        .fg(Color::Yellow)
+                                        .add_modifier(Modifier::BOLD)
+                                } else {
+                                    Style::default().fg(Color::Gray)
+                                };
+                                Span::styled(text.to_string(), style)
+                            }));
+                            lines.push(Line::from(spans));
+                        }
+                        ListItem::new(lines)
                     })
                     .collect()
             };
@@ -3152,26 +9170,193 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                 .highlight_symbol(" ");
             frame.render_stateful_widget(list, main_chunks[0], &mut result_state);
 
+            let preview_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .split(main_chunks[1]);
             let detail_block = Block::default().title("Preview").borders(Borders::ALL);
-            let detail = if let Some(note) = &cached {
-                Paragraph::new(note.note.body.clone()).wrap(Wrap { trim: false })
+            let detail = if let Some((_, lines)) = &preview {
+                Paragraph::new(lines.clone()).wrap(Wrap { trim: false })
             } else {
                 Paragraph::new("Select a note to preview.")
             };
-            frame.render_widget(detail.block(detail_block), main_chunks[1]);
+            frame.render_widget(detail.block(detail_block), preview_chunks[0]);
+
+            let related_items: Vec<ListItem> = if related.is_empty() {
+                vec![ListItem::new(Span::styled(
+                    "Nothing related found.",
+                    Style::default().fg(Color::DarkGray),
+                ))]
+            } else {
+                related
+                    .iter()
+                    .map(|note| {
+                        let mut lines = vec![Line::from(Span::styled(
+                            note.title.clone(),
+                            Style::default().fg(Color::Cyan),
+                        ))];
+                        if !note.shared_tags.is_empty() {
+                            lines.push(Line::from(Span::styled(
+                                format!("  #{}", note.shared_tags.join(" #")),
+                                Style::default().fg(Color::Magenta),
+                            )));
+                        }
+                        ListItem::new(lines)
+                    })
+                    .collect()
+            };
+            frame.render_widget(
+                List::new(related_items)
+                    .block(Block::default().title("Related").borders(Borders::ALL)),
+                preview_chunks[1],
+            );
+
+            match &mut mode {
+                TuiMode::Browse => {}
+                TuiMode::Compose(form) => form.render(frame, columns[1]),
+                TuiMode::Tagging { input, .. } => {
+                    let area = popup_area(columns[1], 3);
+                    frame.render_widget(Clear, area);
+                    input.render(frame, area, "Tags (comma separated, Enter to save)", true);
+                }
+                TuiMode::ConfirmDelete { title, .. } => {
+                    let area = popup_area(columns[1], 3);
+                    frame.render_widget(Clear, area);
+                    frame.render_widget(
+                        Paragraph::new(format!("Delete '{}'? (y/N)", title)).block(
+                            Block::default()
+                                .title("Confirm")
+                                .borders(Borders::ALL)
+                                .border_style(Style::default().fg(Color::Red)),
+                        ),
+                        area,
+                    );
+                }
+            }
         })?;
 
         if event::poll(StdDuration::from_millis(350))? {
             match event::read()? {
+                Event::Key(key)
+                    if key.kind == KeyEventKind::Press && !matches!(mode, TuiMode::Browse) =>
+                {
+                    let mut changed = false;
+                    let next_mode = match &mut mode {
+                        TuiMode::Browse => None,
+                        TuiMode::Compose(form) => match form.handle_key(key) {
+                            EditorEvent::Continue => None,
+                            EditorEvent::Cancel => Some(TuiMode::Browse),
+                            EditorEvent::Submit => match save_note_form(repo, form) {
+                                Ok(record) => {
+                                    status =
+                                        format!("Saved {}", format_object_id(&record.object_id));
+                                    changed = true;
+                                    Some(TuiMode::Browse)
+                                }
+                                Err(e) => {
+                                    status = format!("Error: {}", e);
+                                    None
+                                }
+                            },
+                        },
+                        TuiMode::Tagging { object_id, input } => match input.handle_key(key) {
+                            EditorEvent::Continue => None,
+                            EditorEvent::Cancel => Some(TuiMode::Browse),
+                            EditorEvent::Submit => {
+                                match retag_note(repo, object_id, &input.text()) {
+                                    Ok(record) => {
+                                        status = format!(
+                                            "Tagged {}",
+                                            format_object_id(&record.object_id)
+                                        );
+                                        changed = true;
+                                    }
+                                    Err(e) => status = format!("Error: {}", e),
+                                }
+                                Some(TuiMode::Browse)
+                            }
+                        },
+                        TuiMode::ConfirmDelete { object_id, title } => {
+                            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                                match repo.delete_note(object_id) {
+                                    Ok(()) => {
+                                        status = format!("Deleted '{}'", title);
+                                        changed = true;
+                                    }
+                                    Err(e) => status = format!("Error: {}", e),
+                                }
+                            } else {
+                                status = "Delete cancelled".to_string();
+                            }
+                            Some(TuiMode::Browse)
+                        }
+                    };
+                    if let Some(next) = next_mode {
+                        mode = next;
+                    }
+                    if changed {
+                        all_results = repo.search(query, limit, sort)?;
+                        pinned_first(&mut all_results);
+                        available_tags = repo.tag_stats().unwrap_or_default();
+                        displayed = apply_filters(&all_results, &selected_tags, &time_filter);
+                        if displayed.is_empty() {
+                            result_state.select(None);
+                        } else {
+                            result_state.select(Some(0));
+                        }
+                        if tag_state.selected().unwrap_or(0) >= available_tags.len() {
+                            tag_state.select(if available_tags.is_empty() {
+                                None
+                            } else {
+                                Some(0)
+                            });
+                        }
+                        cached = None;
+                    }
+                }
                 Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
                     KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('n') => {
+                        status.clear();
+                        mode = TuiMode::Compose(Box::new(NoteForm::create()));
+                    }
+                    KeyCode::Char('e') => {
+                        if let Some(note) = &cached {
+                            status.clear();
+                            mode = TuiMode::Compose(Box::new(NoteForm::edit(
+                                &note.object_id,
+                                &note.note.title,
+                                &note.note.tags,
+                                &note.note.body,
+                            )));
+                        }
+                    }
+                    KeyCode::Char('t') => {
+                        if let Some(note) = &cached {
+                            status.clear();
+                            mode = TuiMode::Tagging {
+                                object_id: note.object_id.clone(),
+                                input: TextEditor::single_line(&note.note.tags.join(", ")),
+                            };
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        if let Some(note) = &cached {
+                            status.clear();
+                            mode = TuiMode::ConfirmDelete {
+                                object_id: note.object_id.clone(),
+                                title: note.note.title.clone(),
+                            };
+                        }
+                    }
                     KeyCode::Char('o') => {
                         if let Some(note) = &cached {
-                            let _ = handle_open_inline(note);
+                            let _ = handle_open_inline(repo, note);
                         }
                     }
                     KeyCode::Char('r') => {
                         all_results = repo.search(query, limit, sort)?;
+                        pinned_first(&mut all_results);
                         displayed = apply_filters(&all_results, &selected_tags, &time_filter);
                         if displayed.is_empty() {
                             result_state.select(None);
@@ -3252,7 +9437,7 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                     }
                     KeyCode::Char(' ') if matches!(focus, FocusArea::Filters) => {
                         if let Some(selected) = tag_state.selected() {
-                            if let Some(tag) = available_tags.get(selected) {
+                            if let Some(tag) = available_tags.get(selected).map(|stat| &stat.tag) {
                                 if !tag.is_empty() {
                                     if selected_tags.contains(tag) {
                                         selected_tags.remove(tag);
@@ -3306,7 +9491,7 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
                     KeyCode::Enter => {
                         if matches!(focus, FocusArea::Results) {
                             if let Some(note) = &cached {
-                                render_note(note);
+                                render_note(note, None, &related);
                             }
                         }
                     }
@@ -3323,65 +9508,455 @@ fn run_search_tui(repo: &FukuraRepo, query: &str, sort: SearchSort, limit: usize
     Ok(())
 }
 
-fn handle_open_inline(record: &NoteRecord) -> Result<()> {
-    let html = render_note_html(record, "dark")?;
+fn handle_open_inline(repo: &FukuraRepo, record: &NoteRecord) -> Result<()> {
+    let html = render_note_html(
+        record,
+        "dark",
+        &repo.config()?.meta_schema,
+        None,
+        &related_for_view(repo, record),
+        note_cast(repo, record).as_deref(),
+    )?;
     let filename = format!("fuku-{}.html", record.object_id);
 
-    // Use the new cross-platform browser opener
-    crate::browser::BrowserOpener::open_with_server(&html, &filename).or_else(|_| {
-        // Fallback: save to file and try direct opening
-        let file_path = std::env::temp_dir().join(&filename);
-        fs::write(&file_path, html)?;
-        crate::browser::BrowserOpener::open(&file_path)
-    })?;
+    // Use the new cross-platform browser opener
+    crate::browser::BrowserOpener::open_with_server(&html, &filename).or_else(|_| {
+        // Fallback: save to file and try direct opening
+        let file_path = crate::paths::temp_dir()?.join(&filename);
+        fs::write(&file_path, html)?;
+        crate::browser::BrowserOpener::open(&file_path)
+    })?;
+
+    Ok(())
+}
+
+async fn health() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+#[derive(Debug, Deserialize)]
+struct ListParams {
+    q: Option<String>,
+    limit: Option<usize>,
+    sort: Option<SearchSort>,
+    /// Position of the first result; `cursor` takes precedence
+    offset: Option<usize>,
+    /// `next_cursor` of the previous page
+    cursor: Option<String>,
+}
+
+/// Body of `GET /notes`
+#[derive(Debug, Serialize)]
+struct NotesPage {
+    notes: Vec<SearchHit>,
+    total: usize,
+    /// Pass as `cursor` for the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[derive(Clone)]
+struct ServeState {
+    repo: Arc<FukuraRepo>,
+    index: Arc<SearchIndex>,
+    default_limit: usize,
+    webhooks: Webhooks,
+}
+
+async fn list_notes(
+    State(state): State<ServeState>,
+    AxumQuery(params): AxumQuery<ListParams>,
+) -> impl IntoResponse {
+    let query = params.q.unwrap_or_default();
+    let limit = params.limit.unwrap_or(state.default_limit);
+    let sort = params.sort.unwrap_or(SearchSort::Updated);
+    // Cursors are opaque to clients; today they carry the offset
+    let offset = match params.cursor.as_deref().map(str::parse::<usize>) {
+        Some(Ok(offset)) => offset,
+        Some(Err(_)) => return (StatusCode::BAD_REQUEST, "invalid cursor").into_response(),
+        None => params.offset.unwrap_or(0),
+    };
+    // Notes kept local to this machine are never served
+    let filter = SearchFilter {
+        exclude_meta: vec![(LOCAL_ONLY_META.to_string(), "true".to_string())],
+        ..SearchFilter::default()
+    };
+    let results = state
+        .index
+        .search_page(&query, &filter, offset, limit, sort)
+        .and_then(|mut page| {
+            state.repo.mark_pinned(&mut page.hits)?;
+            Ok(NotesPage {
+                notes: page.hits,
+                total: page.total,
+                next_cursor: page.next_offset.map(|offset| offset.to_string()),
+            })
+        });
+    match results {
+        Ok(results) => Json(results).into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "search failed");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ShowParams {
+    theme: Option<String>,
+}
+
+/// JSON by default; an HTML page for browsers (`Accept: text/html`)
+async fn show_note(
+    State(state): State<ServeState>,
+    AxumPath(id): AxumPath<String>,
+    AxumQuery(params): AxumQuery<ShowParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Ok(note) = state
+        .repo
+        .resolve_object_id(&id)
+        .and_then(|resolved| state.repo.load_note(&resolved))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if note.note.is_local_only() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let wants_html = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if !wants_html {
+        return Json(note).into_response();
+    }
+    let theme = params.theme.unwrap_or_else(|| "dark".to_string());
+    let html = state.repo.config().and_then(|config| {
+        render_note_html(
+            &note,
+            &theme.to_lowercase(),
+            &config.meta_schema,
+            Some("/notes/"),
+            &related_for_view(&state.repo, &note),
+            note_cast(&state.repo, &note).as_deref(),
+        )
+    });
+    match html {
+        Ok(html) => Html(html).into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to render note");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsParams {
+    /// Days of activity and pattern trends to cover
+    days: Option<u64>,
+    /// Error patterns and recent notes to include
+    limit: Option<usize>,
+}
+
+/// Body of `GET /stats`
+#[derive(Debug, Serialize)]
+struct TeamStats {
+    notes: usize,
+    tags: usize,
+    last_updated: Option<chrono::DateTime<Utc>>,
+    errors_by_tool: BTreeMap<String, usize>,
+    errors_by_class: BTreeMap<String, usize>,
+    top_patterns: Vec<PatternSummary>,
+    activity: ActivitySummary,
+}
+
+#[derive(Debug, Serialize)]
+struct PatternSummary {
+    fingerprint: String,
+    message: String,
+    occurrences: u32,
+    /// Occurrences within the last `days`
+    recent: usize,
+    last_seen: chrono::DateTime<Utc>,
+    notes: usize,
+    resolved: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ActivitySummary {
+    days: u64,
+    /// Notes last updated on each day, by UTC date
+    updated_per_day: BTreeMap<String, usize>,
+    recent: Vec<SearchHit>,
+}
+
+/// `GET /stats`: totals, the most frequent error patterns and recent
+/// activity, aggregated here so dashboards don't page through every note
+async fn team_stats(
+    State(state): State<ServeState>,
+    AxumQuery(params): AxumQuery<StatsParams>,
+) -> impl IntoResponse {
+    let days = params.days.unwrap_or(30).clamp(1, 3650);
+    let limit = params.limit.unwrap_or(10);
+    let stats = (|| -> Result<TeamStats> {
+        let cache = state.repo.meta_cache()?;
+        let cached = cache.stats()?;
+        let since = Utc::now() - Duration::days(days as i64);
+        let mut recent = cache.page(0, limit)?;
+        state.repo.mark_pinned(&mut recent)?;
+
+        let now = SystemTime::now();
+        let window = StdDuration::from_secs(days * 86_400);
+        let mut patterns: Vec<_> = crate::patterns::PatternStore::new(state.repo.root())
+            .load()?
+            .into_values()
+            .collect();
+        patterns.sort_by(|a, b| {
+            b.occurrences
+                .cmp(&a.occurrences)
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+        patterns.truncate(limit);
+        let top_patterns = patterns
+            .into_iter()
+            .map(|pattern| PatternSummary {
+                recent: pattern.trend(now, window).current,
+                last_seen: pattern.last_seen.into(),
+                notes: pattern.notes.len(),
+                resolved: !pattern.resolved_by.is_empty(),
+                fingerprint: pattern.fingerprint,
+                message: pattern.normalized_message,
+                occurrences: pattern.occurrences,
+            })
+            .collect();
+
+        Ok(TeamStats {
+            notes: cached.notes,
+            tags: cached.tags,
+            last_updated: cached.last_updated,
+            errors_by_tool: cached.errors_by_tool,
+            errors_by_class: cached.errors_by_class,
+            top_patterns,
+            activity: ActivitySummary {
+                days,
+                updated_per_day: cache.updated_per_day(since)?,
+                recent,
+            },
+        })
+    })();
+    match stats {
+        Ok(stats) => Json(stats).into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to aggregate stats");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TagParams {
+    limit: Option<usize>,
+}
 
-    Ok(())
+/// Body of `GET /tags`
+#[derive(Debug, Serialize)]
+struct TagCounts {
+    tags: Vec<TagStat>,
+    /// Distinct tags, including those past `limit`
+    total: usize,
 }
 
-async fn health() -> impl IntoResponse {
-    (StatusCode::OK, "ok")
+/// `GET /tags`: tags with how many notes carry each and when one was last
+/// updated, most used first
+async fn tag_counts(
+    State(state): State<ServeState>,
+    AxumQuery(params): AxumQuery<TagParams>,
+) -> impl IntoResponse {
+    match state.repo.tag_stats() {
+        Ok(stats) => {
+            let total = stats.len();
+            let tags = stats
+                .into_iter()
+                .take(params.limit.unwrap_or(usize::MAX))
+                .collect();
+            Json(TagCounts { tags, total }).into_response()
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "failed to count tags");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
-struct ListParams {
-    q: Option<String>,
+struct FeedParams {
     limit: Option<usize>,
-    sort: Option<SearchSort>,
 }
 
-#[derive(Clone)]
-struct ServeState {
-    repo: Arc<FukuraRepo>,
-    index: Arc<SearchIndex>,
-    default_limit: usize,
-}
+/// Entries of `GET /feed.xml` unless `limit` asks otherwise
+const FEED_DEFAULT_ENTRIES: usize = 20;
+const FEED_MAX_ENTRIES: usize = 200;
 
-async fn list_notes(
+/// `GET /feed.xml`: the latest public and org notes as an Atom feed, for
+/// feed readers. Private notes never appear.
+async fn note_feed(
     State(state): State<ServeState>,
-    AxumQuery(params): AxumQuery<ListParams>,
+    AxumQuery(params): AxumQuery<FeedParams>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let query = params.q.unwrap_or_default();
-    let limit = params.limit.unwrap_or(state.default_limit);
-    let sort = params.sort.unwrap_or(SearchSort::Updated);
-    match state.index.search(&query, limit, sort) {
-        Ok(results) => Json(results).into_response(),
+    let limit = params
+        .limit
+        .unwrap_or(FEED_DEFAULT_ENTRIES)
+        .min(FEED_MAX_ENTRIES);
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let base = format!("http://{}", host);
+    let records = state.repo.iter_notes().map(|notes| {
+        notes
+            .flatten()
+            .filter(|record| record.note.privacy != Privacy::Private)
+            .take(limit)
+            .collect::<Vec<_>>()
+    });
+    match records {
+        Ok(records) => (
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+            atom_feed(&records, &base, &state.repo),
+        )
+            .into_response(),
         Err(err) => {
-            tracing::error!(error = %err, "search failed");
+            tracing::error!(error = %err, "failed to list notes for the feed");
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-async fn show_note(
+/// Atom (RFC 4287) document of `records`, linking each to its page under
+/// `base`. Entry ids follow a note across edits.
+fn atom_feed(records: &[NoteRecord], base: &str, repo: &FukuraRepo) -> String {
+    let text = |value: &str| html_escape::encode_text(value).to_string();
+    let attr = |value: &str| html_escape::encode_double_quoted_attribute(value).to_string();
+    let updated = records
+        .iter()
+        .map(|record| record.note.updated_at)
+        .max()
+        .unwrap_or_else(Utc::now);
+    let name = repo
+        .root()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "notes".to_string());
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    feed.push_str(&format!("  <title>Fukura: {}</title>\n", text(&name)));
+    feed.push_str(&format!("  <id>{}/feed.xml</id>\n", text(base)));
+    feed.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}/feed.xml\"/>\n",
+        attr(base)
+    ));
+    feed.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        updated.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    ));
+    feed.push_str("  <generator>fukura</generator>\n");
+    for record in records {
+        let note = &record.note;
+        let mut content = crate::markdown::render_feed_html(&note.body);
+        for (idx, solution) in note.solutions.iter().enumerate() {
+            content.push_str(&format!("<h3>Solution {}</h3><ol>", idx + 1));
+            for step in &solution.steps {
+                content.push_str(&format!("<li>{}</li>", text(step)));
+            }
+            content.push_str("</ol>");
+        }
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!("    <title>{}</title>\n", text(&note.title)));
+        feed.push_str(&format!(
+            "    <id>urn:fukura:note:{}</id>\n",
+            crate::crdt::origin(record)
+        ));
+        feed.push_str(&format!(
+            "    <link rel=\"alternate\" type=\"text/html\" href=\"{}/notes/{}\"/>\n",
+            attr(base),
+            record.object_id
+        ));
+        feed.push_str(&format!(
+            "    <published>{}</published>\n",
+            note.created_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        ));
+        feed.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            note.updated_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        ));
+        feed.push_str(&format!(
+            "    <author><name>{}</name></author>\n",
+            text(&note.author.name)
+        ));
+        for tag in &note.tags {
+            feed.push_str(&format!("    <category term=\"{}\"/>\n", attr(tag)));
+        }
+        feed.push_str(&format!(
+            "    <content type=\"html\">{}</content>\n",
+            text(&content)
+        ));
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+/// `GET /v1/clone`: refs and every note cut down to its summary, for
+/// `fuku clone`
+async fn clone_manifest(State(state): State<ServeState>) -> impl IntoResponse {
+    let manifest = state.repo.list_all_notes().and_then(|mut notes| {
+        notes.retain(|record| !record.note.is_local_only());
+        for record in &mut notes {
+            record.note.body = SearchHit::from_record(record).summary;
+        }
+        Ok(CloneManifest {
+            refs: state.repo.refs()?,
+            notes,
+        })
+    });
+    match manifest {
+        Ok(manifest) => Json(manifest).into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to list notes for a clone");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// `GET /v1/objects/<id>`: an object as stored, for thin clones
+async fn show_object(
     State(state): State<ServeState>,
     AxumPath(id): AxumPath<String>,
 ) -> impl IntoResponse {
-    match state.repo.resolve_object_id(&id) {
-        Ok(resolved) => match state.repo.load_note(&resolved) {
-            Ok(note) => Json(note).into_response(),
-            Err(_) => StatusCode::NOT_FOUND.into_response(),
-        },
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    if id.len() != 64 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    // Other objects, such as attachments, don't load as notes
+    if state
+        .repo
+        .load_note(&id)
+        .is_ok_and(|record| record.note.is_local_only())
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match state.repo.object_store().read(&id) {
+        Ok(Some(bytes)) => {
+            ([(header::CONTENT_TYPE, "application/octet-stream")], bytes).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to read object");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
     }
 }
 
@@ -3389,8 +9964,15 @@ async fn create_note(
     State(state): State<ServeState>,
     Json(payload): Json<NoteRecord>,
 ) -> impl IntoResponse {
-    match state.repo.store_note(payload.note) {
-        Ok(record) => Json(record).into_response(),
+    match NoteService::new(&state.repo).and_then(|service| service.create(payload.note)) {
+        Ok(created) => {
+            let event = match created.merged_into {
+                Some(_) => NoteEvent::Updated,
+                None => NoteEvent::Created,
+            };
+            state.webhooks.fire(event, &created.record);
+            Json(created.record).into_response()
+        }
         Err(err) => {
             tracing::error!(error = %err, "failed to store note");
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -3398,6 +9980,55 @@ async fn create_note(
     }
 }
 
+/// `PUT /notes/<id>`: replace a note with an edited version
+async fn update_note(
+    State(state): State<ServeState>,
+    AxumPath(id): AxumPath<String>,
+    Json(payload): Json<NoteRecord>,
+) -> impl IntoResponse {
+    let Ok(previous) = state.repo.resolve_object_id(&id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let updated = NoteService::new(&state.repo).and_then(|service| {
+        let note = service.prepare(payload.note);
+        state.repo.replace_note(&previous, note)
+    });
+    match updated {
+        Ok(record) => {
+            state.webhooks.fire(NoteEvent::Updated, &record);
+            Json(record).into_response()
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "failed to update note");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// `DELETE /notes/<id>`
+async fn delete_note(
+    State(state): State<ServeState>,
+    AxumPath(id): AxumPath<String>,
+) -> impl IntoResponse {
+    let Ok(record) = state
+        .repo
+        .resolve_object_id(&id)
+        .and_then(|resolved| state.repo.load_note(&resolved))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match state.repo.delete_note(&record.object_id) {
+        Ok(()) => {
+            state.webhooks.fire(NoteEvent::Deleted, &record);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "failed to delete note");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 fn format_privacy(privacy: &Privacy) -> String {
     match privacy {
         Privacy::Private => "private".into(),
@@ -3440,58 +10071,142 @@ async fn handle_stop(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
-async fn handle_status(cli: &Cli) -> Result<()> {
+/// `fuku status`: the daemon, hooks, notifications and what is waiting to
+/// be synced. `--json` prints the [`crate::status::StatusSnapshot`].
+fn handle_status(cli: &Cli, cmd: &StatusCommand) -> Result<()> {
     let repo = open_repo(cli)?;
-    let daemon_service = crate::daemon_service::DaemonService::new(repo.root());
-    let config = repo.config()?;
-
-    if !cli.quiet {
-        if daemon_service.is_running().await {
-            println!("{} Daemon: {}", "".blue(), "Running".green());
+    let status = crate::status::StatusSnapshot::collect(&repo)?;
+    if cli.output.or_json(cmd.json).emit(&status)? || cli.quiet {
+        return Ok(());
+    }
 
-            let hook_manager = crate::hooks::HookManager::new(repo.root());
-            let hooks_installed = hook_manager.are_hooks_installed().unwrap_or(false);
-            println!(
-                "{} Hooks: {}",
-                "".blue(),
-                if hooks_installed {
-                    "Installed".green()
-                } else {
-                    "Not installed".red()
-                }
-            );
+    let on_off = |enabled: bool| {
+        if enabled {
+            "Enabled".green()
+        } else {
+            "Disabled".red()
+        }
+    };
+    if status.daemon.running {
+        let mut details = Vec::new();
+        if let Some(pid) = status.daemon.pid {
+            details.push(format!("pid {}", pid));
+        }
+        if let Some(secs) = status.daemon.uptime_secs {
+            details.push(format!(
+                "up {}",
+                crate::recording::format_duration(std::time::Duration::from_secs(secs))
+            ));
+        }
+        let details = if details.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", details.join(", ")).dimmed().to_string()
+        };
+        println!("{} Daemon: {}{}", "".blue(), "Running".green(), details);
+    } else {
+        println!("{} Daemon: {}", "".blue(), "Stopped".red());
+    }
+    println!(
+        "{} Hooks: {}",
+        "".blue(),
+        if status.hooks_installed {
+            "Installed".green()
+        } else {
+            "Not installed".red()
+        }
+    );
+    for shell in &status.hooks {
+        println!(
+            "    {} {} (v{})",
+            shell.hooks.shell,
+            shell.hooks.path.display().to_string().dimmed(),
+            shell.hooks.version
+        );
+    }
+    println!(
+        "{} Notifications: {}",
+        "".blue(),
+        on_off(status.notifications.enabled)
+    );
+    match status.last_capture {
+        Some(time) => println!(
+            "{} Last capture: {} ago",
+            "".blue(),
+            crate::status::age(time.into())
+        ),
+        None => println!("{} Last capture: {}", "".blue(), "never".dimmed()),
+    }
+    if !status.recording.is_empty() {
+        println!("{} Recording: {}", "".blue(), status.recording.join(", "));
+    }
+    if let Some(notes) = status.indexed_notes {
+        println!("{} Notes indexed: {}", "".blue(), notes);
+    }
+    if let Some(remote) = &status.remote {
+        println!("{} Remote: {}", "".blue(), remote);
+    }
+    println!("{} Unsynced notes: {}", "".blue(), status.unsynced);
+    println!("{} Auto-sync: {}", "".blue(), on_off(status.auto_sync));
+    if !status.daemon.running {
+        println!("{} Run 'fuku start' to begin monitoring", "".cyan());
+    }
 
-            let notif_mgr = crate::notification::NotificationManager::new(repo.root())?;
+    let outdated: Vec<_> = status.outdated_hooks().collect();
+    for hooks in &outdated {
+        println!(
+            "{} {} hooks in {} are from an older version (v{}, current v{})",
+            "".yellow(),
+            hooks.shell,
+            hooks.path.display(),
+            hooks.version,
+            crate::hooks::HOOK_VERSION
+        );
+    }
+    if !outdated.is_empty() {
+        let regenerate = is_terminal::is_terminal(std::io::stdin())
+            && dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Regenerate them now?")
+                .default(true)
+                .interact()?;
+        if regenerate {
+            crate::hooks::HookManager::new(repo.root()).regenerate()?;
             println!(
-                "{} Notifications: {}",
-                "".blue(),
-                if notif_mgr.is_enabled() {
-                    "Enabled".green()
-                } else {
-                    "Disabled".red()
-                }
+                "{} Hooks regenerated; open a new shell to load them",
+                "".green()
             );
-
-            if let Some(remote) = &config.default_remote {
-                println!("{} Remote: {}", "".blue(), remote);
-            }
+        } else {
             println!(
-                "{} Auto-sync: {}",
-                "".blue(),
-                if config.auto_sync.unwrap_or(false) {
-                    "Enabled".green()
-                } else {
-                    "Disabled".red()
-                }
+                "{} Run 'fuku daemon --update-hooks' to regenerate them",
+                "".cyan()
             );
-        } else {
-            println!("{} Daemon: {}", "".blue(), "Stopped".red());
-            println!("{} Run 'fuku start' to begin monitoring", "".cyan());
         }
     }
     Ok(())
 }
 
+/// Prompts run this on every render, so it only reads the state files under
+/// `.fukura/state`: no repository open, no index, no daemon socket. Prints
+/// nothing outside a repository.
+fn handle_prompt_status(cli: &Cli, cmd: &PromptStatusCommand) -> Result<()> {
+    let root = match &cli.repo {
+        Some(path) => path.clone(),
+        None => match FukuraRepo::find_root(None) {
+            Ok(root) => root,
+            Err(_) => return Ok(()),
+        },
+    };
+    let status = crate::prompt_state::PromptStatus::read(&root);
+    if cli.output.emit(&status)? {
+        return Ok(());
+    }
+    match &cmd.format {
+        Some(template) => println!("{}", status.render(template)),
+        None => println!("{}", status.segment(crate::output::is_plain())),
+    }
+    Ok(())
+}
+
 async fn handle_restart(cli: &Cli) -> Result<()> {
     handle_stop(cli).await?;
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -3508,8 +10223,8 @@ async fn handle_log(cli: &Cli, cmd: &LogCommand) -> Result<()> {
 
     // Get sessions
     let sessions = if let Some(time_expr) = &cmd.since {
-        let target_time = crate::time_parser::parse_time_ago(time_expr)?;
-        storage.get_sessions_since(target_time)?
+        let target_time = parse_instant(time_expr)?;
+        storage.get_sessions_since(target_time.into())?
     } else {
         let all_session_ids = storage.list_sessions()?;
         all_session_ids
@@ -3520,16 +10235,19 @@ async fn handle_log(cli: &Cli, cmd: &LogCommand) -> Result<()> {
 
     if sessions.is_empty() {
         if !cli.quiet {
-            println!("{} No activities recorded yet", "ℹ️".cyan());
+            println!("{} No activities recorded yet", plain_text("ℹ️").cyan());
             println!();
-            println!("💡 Start tracking with: fuku track --start");
+            println!(
+                "{}Start tracking with: fuku track --start",
+                plain_text("💡 ")
+            );
         }
         return Ok(());
     }
 
     // Sort by time (most recent first)
     let mut sessions: Vec<_> = sessions;
-    sessions.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.start_time));
 
     // Limit results
     sessions.truncate(cmd.limit);
@@ -3611,9 +10329,7 @@ async fn handle_show_activity(cli: &Cli, cmd: &ShowCommand) -> Result<()> {
 
     let session = storage.load_session(&session_id)?;
 
-    if cmd.json {
-        let json = serde_json::to_string_pretty(&session)?;
-        println!("{}", json);
+    if cli.output.or_json(cmd.json).emit(&session)? {
         return Ok(());
     }
 
@@ -3642,9 +10358,9 @@ async fn handle_show_activity(cli: &Cli, cmd: &ShowCommand) -> Result<()> {
                 match &activity.activity_type {
                     crate::activity::ActivityType::Command(c) => {
                         let status = if c.exit_code == Some(0) {
-                            "✓".green()
+                            plain_text("✓").green()
                         } else {
-                            "✗".red()
+                            plain_text("✗").red()
                         };
                         println!(
                             "  {} [{}] {} {}",
@@ -3676,9 +10392,10 @@ async fn handle_show_activity(cli: &Cli, cmd: &ShowCommand) -> Result<()> {
                             c.content.clone()
                         };
                         println!(
-                            "  {} [{}] 📋 {}",
+                            "  {} [{}] {}{}",
                             format!("{:3}", i + 1).dimmed(),
                             time.dimmed(),
+                            plain_text("📋 "),
                             preview.dimmed()
                         );
                     }
@@ -3709,7 +10426,7 @@ async fn handle_show_activity(cli: &Cli, cmd: &ShowCommand) -> Result<()> {
             println!("  Commands: {}", commands);
             println!("  File changes: {}", files);
             println!();
-            println!("💡 Use --full to see all activities");
+            println!("{}Use --full to see all activities", plain_text("💡 "));
         }
     }
 
@@ -3718,13 +10435,19 @@ async fn handle_show_activity(cli: &Cli, cmd: &ShowCommand) -> Result<()> {
 
 async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
     let repo = open_repo(cli)?;
+    if cmd.start || cmd.stop || cmd.files || cmd.clipboard {
+        repo.ensure_writable()?;
+    }
     let config_path = repo.root().join(".fukura").join("config.toml");
     let mut config = crate::config::FukuraConfig::load(&config_path)?;
 
     if cmd.status {
         // Show tracking status
         if !cli.quiet {
-            println!("{} Activity Tracking Status", "📊".cyan().bold());
+            println!(
+                "{}Activity Tracking Status",
+                plain_text("📊 ").cyan().bold()
+            );
             println!();
             println!(
                 "  Enabled:          {}",
@@ -3769,12 +10492,15 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
         config.save(&config_path)?;
 
         if !cli.quiet {
-            println!("{} Activity tracking enabled", "✓".green().bold());
+            println!(
+                "{} Activity tracking enabled",
+                plain_text("✓").green().bold()
+            );
             println!();
-            println!("💡 Tracking:");
-            println!("  • Commands");
-            println!("  • File changes");
-            println!("  • Application switches");
+            println!("{}Tracking:", plain_text("💡 "));
+            println!("  {} Commands", plain_text("•"));
+            println!("  {} File changes", plain_text("•"));
+            println!("  {} Application switches", plain_text("•"));
             println!();
             println!("View with: fuku log");
         }
@@ -3786,7 +10512,7 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
         config.save(&config_path)?;
 
         if !cli.quiet {
-            println!("{} Activity tracking disabled", "⏹️".yellow());
+            println!("{}Activity tracking disabled", plain_text("⏹️ ").yellow());
         }
         return Ok(());
     }
@@ -3798,7 +10524,7 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
         if !cli.quiet {
             println!(
                 "{} File tracking: {}",
-                "✓".green(),
+                plain_text("✓").green(),
                 format_bool(config.activity_tracking.file_tracking)
             );
         }
@@ -3812,12 +10538,15 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
         if !cli.quiet {
             println!(
                 "{} Clipboard tracking: {}",
-                "✓".green(),
+                plain_text("✓").green(),
                 format_bool(config.activity_tracking.clipboard_tracking)
             );
             if config.activity_tracking.clipboard_tracking {
                 println!();
-                println!("⚠️  Clipboard tracking is privacy-sensitive");
+                println!(
+                    "{}  Clipboard tracking is privacy-sensitive",
+                    plain_text("⚠️")
+                );
                 println!("   Sensitive data will be automatically redacted");
             }
         }
@@ -3826,7 +10555,10 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
 
     // Default: show status
     if !cli.quiet {
-        println!("{} Activity Tracking Status", "📊".cyan().bold());
+        println!(
+            "{}Activity Tracking Status",
+            plain_text("📊 ").cyan().bold()
+        );
         println!();
         println!(
             "  Enabled:          {}",
@@ -3849,7 +10581,10 @@ async fn handle_track(cli: &Cli, cmd: &TrackCommand) -> Result<()> {
             format_bool(config.activity_tracking.editor_tracking)
         );
         println!();
-        println!("💡 Toggle with: fuku track --start | --stop | --files | --clipboard");
+        println!(
+            "{}Toggle with: fuku track --start | --stop | --files | --clipboard",
+            plain_text("💡 ")
+        );
     }
 
     Ok(())
@@ -3863,6 +10598,133 @@ fn format_bool(value: bool) -> colored::ColoredString {
     }
 }
 
+fn handle_timeline(cli: &Cli, cmd: &TimelineCommand) -> Result<()> {
+    use crate::timeline::{Timeline, TimelineEvent, TimelineKind};
+
+    let repo = open_repo(cli)?;
+    let (start, end) = match &cmd.session {
+        Some(_) => (
+            SystemTime::UNIX_EPOCH,
+            SystemTime::now() + StdDuration::from_secs(60),
+        ),
+        None => {
+            let (start, end) = parse_range(&cmd.day)?;
+            (start.into(), end.into())
+        }
+    };
+
+    let mut events = Vec::new();
+    for record in CommandLog::new(repo.root())?.read_since(start)? {
+        events.extend(TimelineEvent::from_command(&record));
+    }
+
+    let storage = crate::activity_storage::ActivityStorage::new(repo.root())?;
+    for session_id in storage.list_sessions()? {
+        let Ok(session) = storage.load_session(&session_id) else {
+            continue;
+        };
+        if session.end_time.is_some_and(|finished| finished < start) || session.start_time >= end {
+            continue;
+        }
+        events.extend(
+            session
+                .activities
+                .iter()
+                .filter_map(TimelineEvent::from_activity),
+        );
+    }
+
+    // Notes only show up for day views; they don't belong to a capture session
+    if cmd.session.is_none() {
+        let index = repo.index()?;
+        let total = index.num_docs()? as usize;
+        let start_utc: chrono::DateTime<Utc> = start.into();
+        for hit in index
+            .search("", total, SearchSort::Updated)?
+            .into_iter()
+            .filter(|hit| hit.updated_at >= start_utc)
+        {
+            let Ok(record) = repo.load_note(&hit.object_id) else {
+                continue;
+            };
+            events.push(TimelineEvent::note(
+                record.note.created_at.into(),
+                record.note.meta.get("working_dir").cloned(),
+                &record.object_id,
+                &record.note.title,
+            ));
+        }
+    }
+
+    let mut timeline = Timeline::new(events).within(start, end);
+    if let Some(session_id) = &cmd.session {
+        timeline = timeline.for_session(session_id);
+    }
+
+    if cli.output.or_json(cmd.json).emit(timeline.events())? {
+        return Ok(());
+    }
+
+    if timeline.is_empty() {
+        if !cli.quiet {
+            println!(
+                "{} Nothing recorded for this period",
+                plain_text("ℹ️").blue()
+            );
+            println!();
+            println!(
+                "{}Tip: Commands are captured while the daemon runs ('fuku daemon')",
+                plain_text("💡 ")
+            );
+        }
+        return Ok(());
+    }
+
+    for (directory, events) in timeline.group_by_directory() {
+        println!(
+            "{}{}",
+            plain_text("📁 ").cyan(),
+            directory.unwrap_or("(unknown directory)").bold()
+        );
+        for event in events {
+            let time = chrono::DateTime::<chrono::Local>::from(event.timestamp)
+                .format("%H:%M:%S")
+                .to_string();
+            let line = match &event.kind {
+                TimelineKind::Command {
+                    command,
+                    exit_code: Some(0),
+                } => format!("{} {}", "$".green(), command),
+                TimelineKind::Command { command, exit_code } => format!(
+                    "{} {} {}",
+                    "$".red(),
+                    command,
+                    exit_code
+                        .map(|code| format!("(exit {})", code))
+                        .unwrap_or_default()
+                        .red()
+                ),
+                TimelineKind::Error { message } => {
+                    format!("  {} {}", plain_text("✗").red(), message.red())
+                }
+                TimelineKind::FileChange { path, change } => {
+                    format!("{} {} {}", "~".yellow(), path, change.dimmed())
+                }
+                TimelineKind::Note { object_id, title } => format!(
+                    "{}{} {}",
+                    plain_text("📝 "),
+                    format_object_id(object_id).yellow(),
+                    title.bold()
+                ),
+            };
+            println!("  {} {}", time.dimmed(), line);
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
 fn format_time(time: SystemTime) -> String {
     let datetime: chrono::DateTime<chrono::Utc> = time.into();
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
@@ -3878,13 +10740,16 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
 
         if sessions.is_empty() {
             if !cli.quiet {
-                println!("{} No activity sessions found", "ℹ️".blue());
+                println!("{} No activity sessions found", plain_text("ℹ️").blue());
                 println!();
-                println!("💡 Activity tracking captures comprehensive context:");
-                println!("  • File changes");
-                println!("  • Clipboard operations");
-                println!("  • Editor activities");
-                println!("  • Application switches");
+                println!(
+                    "{}Activity tracking captures comprehensive context:",
+                    plain_text("💡 ")
+                );
+                println!("  {} File changes", plain_text("•"));
+                println!("  {} Clipboard operations", plain_text("•"));
+                println!("  {} Editor activities", plain_text("•"));
+                println!("  {} Application switches", plain_text("•"));
                 println!();
                 println!("Enable with: fuku config activity --enable");
             }
@@ -3892,7 +10757,7 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
         }
 
         if !cli.quiet {
-            println!("{} Activity Sessions:", "📊".cyan());
+            println!("{}Activity Sessions:", plain_text("📊 ").cyan());
             println!();
         }
 
@@ -3917,27 +10782,40 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
         let session = storage.load_session(session_id)?;
 
         if !cli.quiet {
-            println!("{} Activity Session: {}", "📊".cyan(), session.title.bold());
+            println!(
+                "{}Activity Session: {}",
+                plain_text("📊 ").cyan(),
+                session.title.bold()
+            );
             println!();
-            println!("  🆔 ID: {}", session.id);
-            println!("  ⏰ Started: {:?}", session.start_time);
+            println!("  {}ID: {}", plain_text("🆔 "), session.id);
+            println!("  {}Started: {:?}", plain_text("⏰ "), session.start_time);
             if let Some(end) = session.end_time {
-                println!("  🏁 Ended: {:?}", end);
+                println!("  {}Ended: {:?}", plain_text("🏁 "), end);
             }
-            println!("  📝 Activities: {}", session.activities.len());
+            println!(
+                "  {}Activities: {}",
+                plain_text("📝 "),
+                session.activities.len()
+            );
             println!();
 
             if cmd.timeline {
-                println!("{} Timeline:", "⏱️".cyan());
+                println!("{}Timeline:", plain_text("⏱️ ").cyan());
                 println!();
 
                 for activity in &session.activities {
                     match &activity.activity_type {
                         crate::activity::ActivityType::Command(cmd) => {
-                            println!("  ⌨️  {}", cmd.command);
+                            println!("  {} {}", plain_text("⌨️ "), cmd.command);
                         }
                         crate::activity::ActivityType::FileChange(file) => {
-                            println!("  📝 {:?} - {:?}", file.change_type, file.path);
+                            println!(
+                                "  {}{:?} - {:?}",
+                                plain_text("📝 "),
+                                file.change_type,
+                                file.path
+                            );
                         }
                         crate::activity::ActivityType::Clipboard(clip) => {
                             let preview = if clip.content.len() > 50 {
@@ -3945,7 +10823,7 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
                             } else {
                                 clip.content.clone()
                             };
-                            println!("  📋 {}", preview);
+                            println!("  {}{}", plain_text("📋 "), preview);
                         }
                         _ => {}
                     }
@@ -3958,11 +10836,11 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
 
     if let Some(time_expr) = &cmd.since {
         // Show activities since specific time
-        let target_time = crate::time_parser::parse_time_ago(time_expr)?;
-        let sessions = storage.get_sessions_since(target_time)?;
+        let target_time = parse_instant(time_expr)?;
+        let sessions = storage.get_sessions_since(target_time.into())?;
 
         if !cli.quiet {
-            println!("{} Activities since {}", "📊".cyan(), time_expr);
+            println!("{}Activities since {}", plain_text("📊 ").cyan(), time_expr);
             println!();
 
             for session in sessions {
@@ -3976,7 +10854,7 @@ async fn handle_activity(cli: &Cli, cmd: &ActivityCommand) -> Result<()> {
 
     // Default: show help
     if !cli.quiet {
-        println!("{} Activity Tracking", "📊".cyan());
+        println!("{}Activity Tracking", plain_text("📊 ").cyan());
         println!();
         println!("Usage:");
         println!("  fuku activity --list                    # List all sessions");
@@ -4010,6 +10888,25 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
         return Ok(());
     }
 
+    if cmd.update_hooks {
+        let replaced = crate::hooks::HookManager::new(repo.root()).regenerate()?;
+        if !cli.quiet {
+            if replaced.is_empty() {
+                println!("{} Shell hooks are up to date", "".green());
+            }
+            for hooks in replaced {
+                println!(
+                    "{} Regenerated {} hooks in {}",
+                    "".green(),
+                    hooks.shell,
+                    hooks.path.display()
+                );
+            }
+            println!("{} Open a new shell to load them", "".cyan());
+        }
+        return Ok(());
+    }
+
     if cmd.hooks_status {
         let hook_manager = crate::hooks::HookManager::new(repo.root());
         let installed = hook_manager.are_hooks_installed()?;
@@ -4043,11 +10940,25 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
         return Ok(());
     }
 
+    if let Some(action) = cmd.notification_click {
+        let mut notif_mgr = crate::notification::NotificationManager::new(repo.root())?;
+        notif_mgr.set_click_action(action)?;
+        if !cli.quiet {
+            println!(
+                "{} Notification click action set to {:?}",
+                "".green(),
+                action
+            );
+        }
+        return Ok(());
+    }
+
     if cmd.notifications_status {
         let notif_mgr = crate::notification::NotificationManager::new(repo.root())?;
         if !cli.quiet {
             if notif_mgr.is_enabled() {
                 println!("{} Notifications: {}", "".blue(), "Enabled".green());
+                println!("{} Click action: {:?}", "".blue(), notif_mgr.click_action());
             } else {
                 println!("{} Notifications: {}", "".blue(), "Disabled".red());
                 println!(
@@ -4080,6 +10991,30 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
         return Ok(());
     }
 
+    if cmd.logs {
+        let log_path = crate::logging::daemon_log_path(repo.root());
+        if !log_path.exists() {
+            if !cli.quiet {
+                println!(
+                    "{} No daemon log yet at {}",
+                    "".yellow(),
+                    log_path.display()
+                );
+                println!("{} Start the daemon with 'fuku daemon'", "".cyan());
+            }
+            if !cmd.follow {
+                return Ok(());
+            }
+        }
+        for line in crate::logging::tail_lines(&log_path, cmd.lines)? {
+            println!("{}", line);
+        }
+        if cmd.follow {
+            crate::logging::follow(&log_path, |line| println!("{}", line))?;
+        }
+        return Ok(());
+    }
+
     let config = crate::daemon::DaemonConfig::default();
     let daemon = crate::daemon::FukuraDaemon::new(repo.root(), config)?;
 
@@ -4099,24 +11034,40 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
 
                 // Show what daemon monitors
                 println!("\n{} Monitoring:", "".cyan());
-                println!("  • Command executions and exit codes");
-                println!("  • Error messages from stderr");
-                println!("  • Working directory and git context");
-                println!("  • Session timeout: 10 minutes (default)");
+                println!("  {} Command executions and exit codes", plain_text("•"));
+                println!("  {} Error messages from stderr", plain_text("•"));
+                println!("  {} Working directory and git context", plain_text("•"));
+                println!(
+                    "  {} Session timeout: 10 minutes (default)",
+                    plain_text("•")
+                );
 
                 // Show what gets recorded
                 println!("\n{} Recording:", "".cyan());
                 println!(
-                    "  • All data stored locally in {}",
+                    "  {} All data stored locally in {}",
+                    plain_text("•"),
                     repo.root().join(".fukura").display()
                 );
-                println!("  • Private by default (use 'fuku sync' to share)");
-                println!("  • Auto-generated notes after 5 min inactivity");
+                println!(
+                    "  {} Private by default (use 'fuku sync' to share)",
+                    plain_text("•")
+                );
+                println!(
+                    "  {} Auto-generated notes after 5 min inactivity",
+                    plain_text("•")
+                );
+                println!(
+                    "  {} Log: {} ('fuku daemon --logs -f' to follow)",
+                    plain_text("•"),
+                    crate::logging::daemon_log_path(repo.root()).display()
+                );
 
                 // Show configuration
                 println!("\n{} Configuration:", "".cyan());
                 println!(
-                    "  • Auto-sync: {}",
+                    "  {} Auto-sync: {}",
+                    plain_text("•"),
                     if config.auto_sync.unwrap_or(false) {
                         "enabled".green()
                     } else {
@@ -4124,7 +11075,7 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
                     }
                 );
                 if let Some(remote) = &config.default_remote {
-                    println!("  • Default remote: {}", remote);
+                    println!("  {} Default remote: {}", plain_text("•"), remote);
                 }
             } else {
                 println!("{} Daemon status: {}", "".blue(), "Stopped".red());
@@ -4188,15 +11139,20 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
     } else {
         // Start daemon
         if cmd.foreground {
+            let log_path = crate::logging::init_daemon_logging(repo.root())?;
             if !cli.quiet {
                 println!("{} Starting daemon in foreground...", "".green());
+                println!("{} Logging to {}", "".blue(), log_path.display());
                 println!("{} Press Ctrl+C to stop", "".blue());
             }
+            tracing::info!("Daemon starting in {}", repo.root().display());
             daemon.start().await?;
 
             // Keep running until interrupted
+            let _interrupt = crate::interrupt::watch();
             tokio::signal::ctrl_c().await?;
             daemon.stop().await?;
+            tracing::info!("Daemon stopped");
             if !cli.quiet {
                 println!("{} Daemon stopped", "".red());
             }
@@ -4215,6 +11171,7 @@ async fn handle_daemon(cli: &Cli, cmd: &DaemonCommand) -> Result<()> {
                     println!("{} Daemon started in background", "".green());
                     println!("{} Now monitoring for errors automatically", "".blue());
                     println!("{} Use 'fukura daemon --status' to check status", "".blue());
+                    println!("{} Use 'fuku daemon --logs -f' to watch the log", "".blue());
                 }
             }
         }
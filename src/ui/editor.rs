@@ -0,0 +1,392 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+/// Outcome of feeding a key press to an editor widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorEvent {
+    Continue,
+    Submit,
+    Cancel,
+}
+
+/// Minimal multi-line text editor used inside the search TUI
+#[derive(Debug, Clone)]
+pub struct TextEditor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    scroll: usize,
+    single_line: bool,
+}
+
+impl TextEditor {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let row = lines.len() - 1;
+        let col = lines[row].chars().count();
+        Self {
+            lines,
+            row,
+            col,
+            scroll: 0,
+            single_line: false,
+        }
+    }
+
+    /// Single-line input where Enter submits instead of inserting a newline
+    pub fn single_line(text: &str) -> Self {
+        let flattened = text.replace('\n', " ");
+        let mut editor = Self::new(&flattened);
+        editor.single_line = true;
+        editor
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Cursor position as (line, column in chars)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorEvent {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return EditorEvent::Cancel,
+            KeyCode::Char('s') if ctrl => return EditorEvent::Submit,
+            KeyCode::Enter if self.single_line => return EditorEvent::Submit,
+            KeyCode::Enter => self.insert_newline(),
+            KeyCode::Char(c) if !ctrl => self.insert_char(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => {
+                if self.col > 0 {
+                    self.col -= 1;
+                } else if self.row > 0 {
+                    self.row -= 1;
+                    self.col = self.line_len(self.row);
+                }
+            }
+            KeyCode::Right => {
+                if self.col < self.line_len(self.row) {
+                    self.col += 1;
+                } else if self.row + 1 < self.lines.len() {
+                    self.row += 1;
+                    self.col = 0;
+                }
+            }
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.col.min(self.line_len(self.row));
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = self.col.min(self.line_len(self.row));
+            }
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(self.row),
+            _ => {}
+        }
+        EditorEvent::Continue
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, title: &str, focused: bool) {
+        let block = Block::default()
+            .title(title.to_string())
+            .borders(Borders::ALL)
+            .border_style(if focused {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            });
+        let inner = block.inner(area);
+        let visible = inner.height.max(1) as usize;
+        if self.row < self.scroll {
+            self.scroll = self.row;
+        } else if self.row >= self.scroll + visible {
+            self.scroll = self.row + 1 - visible;
+        }
+
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .skip(self.scroll)
+            .take(visible)
+            .map(|l| Line::from(l.as_str()))
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+
+        if focused {
+            let x = inner.x + (self.col as u16).min(inner.width.saturating_sub(1));
+            let y = inner.y + (self.row - self.scroll) as u16;
+            frame.set_cursor_position((x, y));
+        }
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let idx = self.byte_index();
+        self.lines[self.row].insert(idx, c);
+        self.col += 1;
+    }
+
+    fn insert_newline(&mut self) {
+        let idx = self.byte_index();
+        let rest = self.lines[self.row].split_off(idx);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let idx = self.byte_index();
+            self.lines[self.row].remove(idx);
+        } else if self.row > 0 {
+            let current = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&current);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let idx = self.byte_index();
+            self.lines[self.row].remove(idx);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormField {
+    Title,
+    Tags,
+    Body,
+}
+
+/// Title/tags/body form shown when creating or editing a note in the TUI
+#[derive(Debug, Clone)]
+pub struct NoteForm {
+    /// Object id of the note being edited, `None` when creating
+    pub editing: Option<String>,
+    pub title: TextEditor,
+    pub tags: TextEditor,
+    pub body: TextEditor,
+    pub field: FormField,
+}
+
+impl NoteForm {
+    pub fn create() -> Self {
+        Self {
+            editing: None,
+            title: TextEditor::single_line(""),
+            tags: TextEditor::single_line(""),
+            body: TextEditor::new(""),
+            field: FormField::Title,
+        }
+    }
+
+    pub fn edit(object_id: &str, title: &str, tags: &[String], body: &str) -> Self {
+        Self {
+            editing: Some(object_id.to_string()),
+            title: TextEditor::single_line(title),
+            tags: TextEditor::single_line(&tags.join(", ")),
+            body: TextEditor::new(body),
+            field: FormField::Body,
+        }
+    }
+
+    /// Tab/BackTab cycle fields, Enter on a single-line field moves on,
+    /// Ctrl+S saves from anywhere and Esc cancels
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorEvent {
+        match key.code {
+            KeyCode::Tab => {
+                self.field = self.next_field();
+                return EditorEvent::Continue;
+            }
+            KeyCode::BackTab => {
+                self.field = self.previous_field();
+                return EditorEvent::Continue;
+            }
+            _ => {}
+        }
+        let event = match self.field {
+            FormField::Title => self.title.handle_key(key),
+            FormField::Tags => self.tags.handle_key(key),
+            FormField::Body => self.body.handle_key(key),
+        };
+        if event == EditorEvent::Submit
+            && key.code == KeyCode::Enter
+            && self.field != FormField::Body
+        {
+            self.field = self.next_field();
+            return EditorEvent::Continue;
+        }
+        event
+    }
+
+    pub fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .text()
+            .split([',', ' '])
+            .map(|t| t.to_string())
+            .collect()
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+        let title = if self.editing.is_some() {
+            "Edit note"
+        } else {
+            "New note"
+        };
+        let block = Block::default()
+            .title(Span::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+        let field = self.field;
+        self.title
+            .render(frame, chunks[0], "Title", field == FormField::Title);
+        self.tags.render(
+            frame,
+            chunks[1],
+            "Tags (comma separated)",
+            field == FormField::Tags,
+        );
+        self.body
+            .render(frame, chunks[2], "Body", field == FormField::Body);
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(
+                "Tab: next field | Ctrl+S: save | Esc: cancel",
+                Style::default().fg(Color::DarkGray),
+            ))),
+            chunks[3],
+        );
+    }
+
+    fn next_field(&self) -> FormField {
+        match self.field {
+            FormField::Title => FormField::Tags,
+            FormField::Tags => FormField::Body,
+            FormField::Body => FormField::Title,
+        }
+    }
+
+    fn previous_field(&self) -> FormField {
+        match self.field {
+            FormField::Title => FormField::Body,
+            FormField::Tags => FormField::Title,
+            FormField::Body => FormField::Tags,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(editor: &mut TextEditor, text: &str) {
+        for c in text.chars() {
+            editor.handle_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_insert_and_newline() {
+        let mut editor = TextEditor::new("");
+        type_text(&mut editor, "hello");
+        editor.handle_key(key(KeyCode::Enter));
+        type_text(&mut editor, "wörld");
+        assert_eq!(editor.text(), "hello\nwörld");
+        assert_eq!(editor.cursor(), (1, 5));
+    }
+
+    #[test]
+    fn test_backspace_joins_lines() {
+        let mut editor = TextEditor::new("ab\ncd");
+        editor.handle_key(key(KeyCode::Home));
+        editor.handle_key(key(KeyCode::Backspace));
+        assert_eq!(editor.text(), "abcd");
+        assert_eq!(editor.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn test_delete_and_movement_with_multibyte() {
+        let mut editor = TextEditor::new("日本語");
+        editor.handle_key(key(KeyCode::Left));
+        editor.handle_key(key(KeyCode::Left));
+        editor.handle_key(key(KeyCode::Delete));
+        assert_eq!(editor.text(), "日語");
+    }
+
+    #[test]
+    fn test_single_line_submits_on_enter() {
+        let mut editor = TextEditor::single_line("rust");
+        assert_eq!(editor.handle_key(key(KeyCode::Enter)), EditorEvent::Submit);
+        assert_eq!(editor.text(), "rust");
+        assert_eq!(
+            editor.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL)),
+            EditorEvent::Submit
+        );
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)), EditorEvent::Cancel);
+    }
+
+    #[test]
+    fn test_form_field_navigation() {
+        let mut form = NoteForm::create();
+        type_text(&mut form.title, "Fix build");
+        assert_eq!(form.handle_key(key(KeyCode::Enter)), EditorEvent::Continue);
+        assert_eq!(form.field, FormField::Tags);
+        form.handle_key(key(KeyCode::Char('a')));
+        form.handle_key(key(KeyCode::Char(',')));
+        form.handle_key(key(KeyCode::Char('b')));
+        form.handle_key(key(KeyCode::Tab));
+        assert_eq!(form.field, FormField::Body);
+        form.handle_key(key(KeyCode::Enter));
+        assert_eq!(form.body.text(), "\n");
+        assert_eq!(form.tag_list(), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,503 @@
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+const CODE_THEME: &str = "base16-ocean.dark";
+
+/// Code theme of HTML pages with a light background
+const LIGHT_CODE_THEME: &str = "InspiredGitHub";
+
+/// Headings a note needs before its HTML page gets a table of contents
+const TOC_MIN_HEADINGS: usize = 3;
+
+/// Render a markdown note body into styled lines for the TUI preview
+pub fn render_markdown(body: &str) -> Vec<Line<'static>> {
+    let mut renderer = MarkdownRenderer::default();
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(body, options) {
+        renderer.handle(event);
+    }
+    renderer.finish()
+}
+
+/// Highlight a code block with syntect, falling back to plain text for unknown languages
+pub fn highlight_code(code: &str, lang: &str) -> Vec<Line<'static>> {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let theme = &THEME_SET.themes[CODE_THEME];
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in syntect::util::LinesWithEndings::from(code) {
+        let spans = match highlighter.highlight_line(line, &SYNTAX_SET) {
+            Ok(ranges) => ranges
+                .into_iter()
+                .map(|(style, text)| {
+                    let mut span_style = Style::default().fg(Color::Rgb(
+                        style.foreground.r,
+                        style.foreground.g,
+                        style.foreground.b,
+                    ));
+                    if style.font_style.contains(FontStyle::BOLD) {
+                        span_style = span_style.add_modifier(Modifier::BOLD);
+                    }
+                    if style.font_style.contains(FontStyle::ITALIC) {
+                        span_style = span_style.add_modifier(Modifier::ITALIC);
+                    }
+                    Span::styled(text.trim_end_matches(['\n', '\r']).to_string(), span_style)
+                })
+                .collect::<Vec<_>>(),
+            Err(_) => vec![Span::raw(line.trim_end_matches(['\n', '\r']).to_string())],
+        };
+        let mut prefixed = vec![Span::styled("│ ", Style::default().fg(Color::DarkGray))];
+        prefixed.extend(spans);
+        lines.push(Line::from(prefixed));
+    }
+    lines
+}
+
+#[derive(Default)]
+struct MarkdownRenderer {
+    lines: Vec<Line<'static>>,
+    current: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    lists: Vec<Option<u64>>,
+    code: Option<(String, String)>,
+    link: Option<String>,
+    quote_depth: usize,
+}
+
+impl MarkdownRenderer {
+    fn handle(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, buffer)) = self.code.as_mut() {
+                    buffer.push_str(&text);
+                } else {
+                    self.push_text(&text);
+                }
+            }
+            Event::Code(code) => {
+                let style = self.style().fg(Color::Yellow);
+                self.current.push(Span::styled(code.to_string(), style));
+            }
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.lines.push(Line::from(Span::styled(
+                    "─".repeat(40),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            Event::TaskListMarker(done) => {
+                self.push_text(if done { "[x] " } else { "[ ] " });
+            }
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(&html),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                let (marker, color) = match level {
+                    HeadingLevel::H1 => ("# ", Color::Cyan),
+                    HeadingLevel::H2 => ("## ", Color::Green),
+                    HeadingLevel::H3 => ("### ", Color::Yellow),
+                    _ => ("#### ", Color::Magenta),
+                };
+                let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+                self.current.push(Span::styled(marker, style));
+                self.styles.push(style);
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        let label = format!("{}. ", number);
+                        *number += 1;
+                        label
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.current.push(Span::styled(
+                    format!("{}{}", indent, bullet),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        lang.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+                self.styles.push(
+                    Style::default()
+                        .fg(Color::Gray)
+                        .add_modifier(Modifier::ITALIC),
+                );
+            }
+            Tag::Emphasis => {
+                let style = self.style().add_modifier(Modifier::ITALIC);
+                self.styles.push(style);
+            }
+            Tag::Strong => {
+                let style = self.style().add_modifier(Modifier::BOLD);
+                self.styles.push(style);
+            }
+            Tag::Strikethrough => {
+                let style = self.style().add_modifier(Modifier::CROSSED_OUT);
+                self.styles.push(style);
+            }
+            Tag::Link { dest_url, .. } => {
+                self.link = Some(dest_url.to_string());
+                let style = self
+                    .style()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::UNDERLINED);
+                self.styles.push(style);
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush();
+                self.lines.push(Line::default());
+            }
+            TagEnd::Paragraph => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.lines.push(Line::default());
+                }
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.lines.push(Line::default());
+                }
+            }
+            TagEnd::Item => self.flush(),
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    self.lines.extend(highlight_code(&code, &lang));
+                    self.lines.push(Line::default());
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.styles.pop();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link => {
+                self.styles.pop();
+                if let Some(url) = self.link.take() {
+                    self.current.push(Span::styled(
+                        format!(" <{}>", url),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.current.is_empty() && self.quote_depth > 0 {
+            self.current.push(Span::styled(
+                "▎ ".repeat(self.quote_depth),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let style = self.style();
+        self.current.push(Span::styled(text.to_string(), style));
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            self.lines
+                .push(Line::from(std::mem::take(&mut self.current)));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        self.flush();
+        while self.lines.last().is_some_and(|line| line.spans.is_empty()) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+/// A heading of an HTML-rendered body, for the table of contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
+}
+
+/// A note body as HTML: highlighted code blocks with copy buttons and
+/// headings with anchors
+#[derive(Debug, Clone, Default)]
+pub struct HtmlBody {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+impl HtmlBody {
+    /// Table of contents as a `<nav>`, or nothing for short notes
+    pub fn toc_html(&self) -> String {
+        if self.toc.len() < TOC_MIN_HEADINGS {
+            return String::new();
+        }
+        let top = self.toc.iter().map(|entry| entry.level).min().unwrap_or(1);
+        let items = self
+            .toc
+            .iter()
+            .map(|entry| {
+                format!(
+                    "<li class=\"toc-level-{}\"><a href=\"#{}\">{}</a></li>",
+                    entry.level - top,
+                    entry.id,
+                    html_escape::encode_text(&entry.text)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "<nav class=\"toc\"><h2>Contents</h2><ul>{}</ul></nav>",
+            items
+        )
+    }
+}
+
+/// Render a markdown note body to HTML for the browser views
+pub fn render_html(body: &str, light: bool) -> HtmlBody {
+    let options =
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+    let mut events: Vec<Event> = Vec::new();
+    let mut toc = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    let mut heading: Option<(HeadingLevel, Vec<Event>)> = None;
+
+    for event in Parser::new_ext(body, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, buf)) = code.as_mut() {
+                    buf.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, buf)) = code.take() {
+                    events.push(Event::Html(CowStr::from(highlight_html(
+                        &buf, &lang, light,
+                    ))));
+                }
+            }
+            Event::Start(Tag::Heading { level, .. }) => heading = Some((level, Vec::new())),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, inner)) = heading.take() {
+                    let text: String = inner
+                        .iter()
+                        .filter_map(|event| match event {
+                            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                            _ => None,
+                        })
+                        .collect();
+                    let id = unique_slug(&text, &toc);
+                    let level = level as u8;
+                    events.push(Event::Html(CowStr::from(format!(
+                        "<h{} id=\"{}\">",
+                        level, id
+                    ))));
+                    events.extend(inner);
+                    events.push(Event::Html(CowStr::from(format!("</h{}>", level))));
+                    toc.push(TocEntry { level, id, text });
+                }
+            }
+            event => match heading.as_mut() {
+                Some((_, inner)) => inner.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    HtmlBody { html, toc }
+}
+
+/// Render a markdown note body to plain HTML for feed readers: no
+/// highlighting or buttons, and HTML embedded in the note is escaped
+pub fn render_feed_html(body: &str) -> String {
+    let options =
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+    let events = Parser::new_ext(body, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// A code block as highlighted HTML with a copy button
+fn highlight_html(code: &str, lang: &str, light: bool) -> String {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let theme = &THEME_SET.themes[if light { LIGHT_CODE_THEME } else { CODE_THEME }];
+    let pre = syntect::html::highlighted_html_for_string(code, &SYNTAX_SET, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", html_escape::encode_text(code)));
+    format!(
+        "<div class=\"code-block\"><button class=\"copy\" type=\"button\">Copy</button>{}</div>",
+        pre
+    )
+}
+
+/// Anchor for a heading, numbered when the same text came before
+fn unique_slug(text: &str, taken: &[TocEntry]) -> String {
+    let mut slug = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        slug = "section".to_string();
+    }
+    let base = slug.clone();
+    let mut n = 1;
+    while taken.iter().any(|entry| entry.id == slug) {
+        slug = format!("{}-{}", base, n);
+        n += 1;
+    }
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_headings_and_paragraphs() {
+        let lines = render_markdown("# Title\n\nSome *text* here.");
+        assert_eq!(plain(&lines), vec!["# Title", "", "Some text here."]);
+        assert!(lines[0].spans[0]
+            .style
+            .add_modifier
+            .contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_lists() {
+        let lines = render_markdown("- one\n- two\n\n1. first\n2. second\n");
+        let text = plain(&lines);
+        assert!(text.contains(&"• one".to_string()));
+        assert!(text.contains(&"• two".to_string()));
+        assert!(text.contains(&"1. first".to_string()));
+        assert!(text.contains(&"2. second".to_string()));
+    }
+
+    #[test]
+    fn test_fenced_code_is_highlighted() {
+        let lines = render_markdown("```rust\nfn main() {}\n```\n");
+        assert_eq!(plain(&lines), vec!["│ fn main() {}"]);
+        // Highlighted code uses more than one span with RGB colors
+        assert!(lines[0].spans.len() > 2);
+        assert!(lines[0]
+            .spans
+            .iter()
+            .any(|s| matches!(s.style.fg, Some(Color::Rgb(..)))));
+    }
+
+    #[test]
+    fn test_html_highlights_code_and_anchors_headings() {
+        let body = render_html(
+            "## Setup\n\nRun:\n\n```rust\nfn main() {}\n```\n\n## Setup\n\n### Done\n",
+            false,
+        );
+        assert!(body.html.contains("<h2 id=\"setup\">Setup</h2>"));
+        assert!(body.html.contains("<h2 id=\"setup-1\">"));
+        assert!(body.html.contains("class=\"copy\""));
+        assert!(body.html.contains("<span style="));
+        assert_eq!(body.toc.len(), 3);
+        let toc = body.toc_html();
+        assert!(toc.contains("href=\"#done\""));
+        assert!(toc.contains("toc-level-1"));
+
+        assert_eq!(render_html("# Only one", true).toc_html(), "");
+    }
+
+    #[test]
+    fn test_feed_html_is_plain_and_escapes_embedded_html() {
+        let html = render_feed_html("## Fix\n\n<script>alert(1)</script>\n\n```sh\nls\n```\n");
+        assert!(html.contains("<h2>Fix</h2>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("<pre><code class=\"language-sh\">ls\n</code></pre>"));
+        assert!(!html.contains("copy"));
+    }
+
+    #[test]
+    fn test_unknown_language_falls_back_to_plain_text() {
+        let lines = highlight_code("some output\nmore\n", "no-such-lang");
+        assert_eq!(plain(&lines), vec!["│ some output", "│ more"]);
+    }
+}
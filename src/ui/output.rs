@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Turns on [`set_plain`] like `--plain` does
+pub const PLAIN_ENV: &str = "FUKURA_PLAIN";
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Output without emoji, colors or box drawing, for screen readers, CI logs
+/// and terminals that mangle them. Set once at startup.
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Whether `FUKURA_PLAIN` asks for plain output; empty, `0` and `false`
+/// don't
+pub fn plain_from_env() -> bool {
+    std::env::var(PLAIN_ENV)
+        .is_ok_and(|value| !matches!(value.trim().to_lowercase().as_str(), "" | "0" | "false"))
+}
+
+/// `text` as printed: unchanged unless plain output is on, see [`to_plain`]
+pub fn plain_text(text: &str) -> Cow<'_, str> {
+    if is_plain() {
+        Cow::Owned(to_plain(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Symbols with an ASCII meaning (check marks, bullets, arrows, box drawing)
+/// spelled in ASCII; other emoji dropped together with the space after them
+pub fn to_plain(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let replacement = match c {
+            '✓' | '✔' | '✅' | '☑' => "[ok]",
+            '✗' | '✘' | '❌' => "[x]",
+            '⚠' => "[!]",
+            '☐' => "[ ]",
+            'ℹ' => "[i]",
+            '•' | '·' => "-",
+            '→' | '↳' => "->",
+            '←' => "<-",
+            '↑' => "^",
+            '↓' => "v",
+            '↻' => "~",
+            '×' => "x",
+            '…' => "...",
+            '─' | '━' | '┄' | '┈' | '═' => "-",
+            '│' | '┃' | '║' => "|",
+            '\u{2500}'..='\u{257F}' => "+",
+            '\u{2580}'..='\u{259F}' => "#",
+            '\u{FE0F}' | '\u{200D}' => "",
+            '\u{2300}'..='\u{23FF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{1F000}'..='\u{1FFFF}' => {
+                // Drop the pictograph and the space that set it apart
+                while matches!(chars.peek(), Some('\u{FE0F}' | '\u{200D}')) {
+                    chars.next();
+                }
+                if chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+                continue;
+            }
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        out.push_str(replacement);
+    }
+    out
+}
+
+/// Unicode or ASCII bar characters for progress bars
+pub fn progress_chars() -> &'static str {
+    if is_plain() {
+        "#>-"
+    } else {
+        "█▓░"
+    }
+}
+
+/// Table borders: Unicode lines, or ASCII in plain output
+pub fn table_preset() -> &'static str {
+    if is_plain() {
+        comfy_table::presets::ASCII_HORIZONTAL_ONLY
+    } else {
+        comfy_table::presets::UTF8_HORIZONTAL_ONLY
+    }
+}
+
+/// How commands print their results: tables and prose for people, JSON or
+/// YAML for scripts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+}
+
+impl OutputFormat {
+    /// A command's own `--json` flag wins over the global `--output`
+    pub fn or_json(self, json: bool) -> Self {
+        if json {
+            Self::Json
+        } else {
+            self
+        }
+    }
+
+    pub fn is_structured(self) -> bool {
+        self != Self::Table
+    }
+
+    /// `value` as JSON or YAML; `None` for tables, which callers render
+    /// themselves
+    pub fn render<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<String>> {
+        Ok(match self {
+            Self::Table => None,
+            Self::Json => Some(serde_json::to_string_pretty(value)?),
+            Self::Yaml => Some(serde_yaml::to_string(value)?),
+        })
+    }
+
+    /// Print `value` as JSON or YAML. `false` for tables, so callers can
+    /// `return` early when it printed.
+    pub fn emit<T: Serialize + ?Sized>(self, value: &T) -> Result<bool> {
+        match self.render(value)? {
+            Some(text) => {
+                println!("{}", text.trim_end());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Counts {
+        notes: usize,
+        tags: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let counts = Counts {
+            notes: 2,
+            tags: vec!["rust"],
+        };
+        assert!(OutputFormat::Table.render(&counts).unwrap().is_none());
+        let json = OutputFormat::Json.render(&counts).unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()["notes"],
+            2
+        );
+        let yaml = OutputFormat::Yaml.render(&counts).unwrap().unwrap();
+        assert!(yaml.contains("notes: 2"));
+        assert!(yaml.contains("- rust"));
+    }
+
+    #[test]
+    fn test_plain_text_is_ascii() {
+        assert_eq!(to_plain("✓ Saved"), "[ok] Saved");
+        assert_eq!(to_plain("💡 Tip: run fuku list"), "Tip: run fuku list");
+        assert_eq!(to_plain("  ⚙️ Configuration:"), "  Configuration:");
+        assert_eq!(to_plain("⚠️ Stale"), "[!] Stale");
+        assert_eq!(to_plain("    • Remote: a → b"), "    - Remote: a -> b");
+        assert_eq!(to_plain("──┼──"), "--+--");
+        assert_eq!(to_plain("  ⏱️ 3× total"), "  3x total");
+        assert_eq!(to_plain("プロキシ 設定"), "プロキシ 設定");
+    }
+
+    #[test]
+    fn test_json_flag_wins() {
+        assert_eq!(OutputFormat::Yaml.or_json(true), OutputFormat::Json);
+        assert_eq!(OutputFormat::Yaml.or_json(false), OutputFormat::Yaml);
+        assert!(!OutputFormat::default().is_structured());
+    }
+}
@@ -0,0 +1,255 @@
+use std::io::{self, Write};
+use std::time::Duration as StdDuration;
+
+use anyhow::{bail, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::border;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Terminal;
+
+use crate::fuzzy::fuzzy_filter;
+use crate::index::SearchHit;
+
+/// Borders drawn with `--plain`
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+fn bordered(title: String) -> Block<'static> {
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if crate::output::is_plain() {
+        block.border_set(ASCII_BORDER)
+    } else {
+        block
+    }
+}
+
+/// `style`, or no styling at all with `--plain`
+fn styled(style: Style) -> Style {
+    if crate::output::is_plain() {
+        Style::default()
+    } else {
+        style
+    }
+}
+
+/// Text the picker matches against for a hit
+fn candidate_text(hit: &SearchHit) -> String {
+    if hit.tags.is_empty() {
+        hit.title.clone()
+    } else {
+        format!("{} #{}", hit.title, hit.tags.join(" #"))
+    }
+}
+
+struct PickerCleanup;
+
+impl Drop for PickerCleanup {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let mut stderr = io::stderr();
+        let _ = queue!(stderr, LeaveAlternateScreen);
+        let _ = stderr.flush();
+    }
+}
+
+/// fzf-style incremental picker. The UI is drawn on stderr so stdout stays
+/// free for the selected id (`fuku view $(fuku pick)`).
+pub fn pick_note(hits: &[SearchHit], initial_query: &str) -> Result<Option<SearchHit>> {
+    if !is_terminal::is_terminal(io::stderr()) {
+        bail!("fuku pick needs an interactive terminal");
+    }
+
+    enable_raw_mode()?;
+    let mut stderr = io::stderr();
+    execute!(stderr, EnterAlternateScreen)?;
+    let guard = PickerCleanup;
+    let backend = CrosstermBackend::new(stderr);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut query = initial_query.to_string();
+    let mut state = ListState::default();
+    let mut selection: Option<SearchHit> = None;
+
+    loop {
+        let matches = fuzzy_filter(&query, hits, candidate_text);
+        match state.selected() {
+            _ if matches.is_empty() => state.select(None),
+            Some(idx) if idx >= matches.len() => state.select(Some(matches.len() - 1)),
+            None => state.select(Some(0)),
+            _ => {}
+        }
+
+        terminal.draw(|frame| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(3)])
+                .split(frame.area());
+
+            let items: Vec<ListItem> = matches
+                .iter()
+                .map(|(_, hit)| {
+                    let mut spans = vec![
+                        Span::styled(
+                            format!("{:<9}", &hit.object_id[..8.min(hit.object_id.len())]),
+                            styled(Style::default().fg(Color::DarkGray)),
+                        ),
+                        Span::styled(
+                            hit.title.clone(),
+                            styled(
+                                Style::default()
+                                    .fg(Color::Cyan)
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                        ),
+                    ];
+                    if !hit.tags.is_empty() {
+                        spans.push(Span::styled(
+                            format!(" #{}", hit.tags.join(" #")),
+                            styled(Style::default().fg(Color::Magenta)),
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(bordered(format!(
+                    "Notes ({}/{})",
+                    matches.len(),
+                    hits.len()
+                )))
+                .highlight_style(styled(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .highlight_symbol("> ");
+            frame.render_stateful_widget(list, chunks[0], &mut state);
+
+            let prompt = Paragraph::new(Line::from(vec![
+                Span::styled("> ", styled(Style::default().fg(Color::Green))),
+                Span::raw(query.clone()),
+            ]))
+            .block(bordered("Enter: select | Esc: cancel".to_string()));
+            frame.render_widget(prompt, chunks[1]);
+            frame.set_cursor_position((
+                chunks[1].x + 3 + query.chars().count() as u16,
+                chunks[1].y + 1,
+            ));
+        })?;
+
+        if !event::poll(StdDuration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Esc => break,
+                KeyCode::Char('c') | KeyCode::Char('g') if ctrl => break,
+                KeyCode::Enter => {
+                    selection = state
+                        .selected()
+                        .and_then(|idx| matches.get(idx))
+                        .map(|(_, hit)| (*hit).clone());
+                    break;
+                }
+                KeyCode::Up => state.select_previous(),
+                KeyCode::Char('p') | KeyCode::Char('k') if ctrl => state.select_previous(),
+                KeyCode::Down => state.select_next(),
+                KeyCode::Char('n') | KeyCode::Char('j') if ctrl => state.select_next(),
+                KeyCode::Char('u') if ctrl => {
+                    query.clear();
+                    state.select(Some(0));
+                }
+                KeyCode::Backspace => {
+                    query.pop();
+                    state.select(Some(0));
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    query.push(c);
+                    state.select(Some(0));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    terminal.show_cursor()?;
+    drop(terminal);
+    drop(guard);
+    Ok(selection)
+}
+
+/// Shell snippet binding Ctrl+G to insert a picked note id at the cursor
+pub fn shell_widget(shell: &str) -> Result<&'static str> {
+    Ok(match shell {
+        "bash" => {
+            r#"# fuku pick widget (bash): Ctrl+G inserts a note id
+__fuku_pick_widget() {
+  local id
+  id="$(fuku pick)" || return
+  READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}${id}${READLINE_LINE:$READLINE_POINT}"
+  READLINE_POINT=$((READLINE_POINT + ${#id}))
+}
+bind -x '"\C-g": __fuku_pick_widget'
+"#
+        }
+        "zsh" => {
+            r#"# fuku pick widget (zsh): Ctrl+G inserts a note id
+__fuku_pick_widget() {
+  local id
+  id="$(fuku pick </dev/tty)" || { zle reset-prompt; return }
+  LBUFFER="${LBUFFER}${id}"
+  zle reset-prompt
+}
+zle -N __fuku_pick_widget
+bindkey '^G' __fuku_pick_widget
+"#
+        }
+        "fish" => {
+            r#"# fuku pick widget (fish): Ctrl+G inserts a note id
+function __fuku_pick_widget
+    set -l id (fuku pick)
+    and commandline -i -- $id
+    commandline -f repaint
+end
+bind \cg __fuku_pick_widget
+"#
+        }
+        other => bail!(
+            "Unsupported shell for widget: {} (use bash, zsh, or fish)",
+            other
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_widgets() {
+        assert!(shell_widget("bash").unwrap().contains("bind -x"));
+        assert!(shell_widget("zsh").unwrap().contains("zle -N"));
+        assert!(shell_widget("fish").unwrap().contains("commandline"));
+        assert!(shell_widget("tcsh").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+use anyhow::{Context, Result};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+
+/// `text` as a QR code to print in a terminal, two modules per character
+/// cell. Light modules are drawn, so the code reads right on the usual dark
+/// background; plain output draws them with `#`.
+pub fn render(text: &str) -> Result<String> {
+    let code = QrCode::new(text.as_bytes()).context("Too long for a QR code")?;
+    Ok(if crate::output::is_plain() {
+        code.render::<&str>()
+            .dark_color(" ")
+            .light_color("#")
+            .module_dimensions(2, 1)
+            .quiet_zone(true)
+            .build()
+    } else {
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .quiet_zone(true)
+            .build()
+    })
+}
+
+/// `addr` as another device on the network reaches it: a server listening
+/// on every interface (`0.0.0.0:8765`) gets this machine's LAN address.
+/// `None` for loopback addresses, which only this machine can open.
+pub fn reachable_addr(addr: &str) -> Option<String> {
+    let socket: SocketAddr = addr.to_socket_addrs().ok()?.next()?;
+    let ip = if socket.ip().is_unspecified() {
+        lan_ip()?
+    } else if socket.ip().is_loopback() {
+        return None;
+    } else {
+        socket.ip()
+    };
+    Some(SocketAddr::new(ip, socket.port()).to_string())
+}
+
+/// The address this machine sends from on its default route. Connecting a
+/// UDP socket only picks the route; nothing is sent.
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified() && !ip.is_loopback()).then_some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_is_square_and_framed() {
+        let code = render("https://hub.example.com/s/s1").unwrap();
+        let lines: Vec<&str> = code.lines().collect();
+        let width = lines[0].chars().count();
+        // Two rows of modules per line
+        assert!(lines.len() * 2 >= width && lines.len() * 2 <= width + 1);
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+        // The quiet zone is light, so drawn solid
+        assert!(lines[0].chars().all(|c| c == '█'));
+    }
+
+    #[test]
+    fn test_loopback_is_not_reachable() {
+        assert_eq!(reachable_addr("127.0.0.1:8765"), None);
+        assert_eq!(
+            reachable_addr("192.168.1.20:8765").as_deref(),
+            Some("192.168.1.20:8765")
+        );
+    }
+}
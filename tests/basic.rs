@@ -1,30 +1,24 @@
-use std::collections::BTreeMap;
-
 use chrono::Utc;
-use fukura::index::SearchSort;
-use fukura::models::{Author, Note, Privacy};
+use fukura::index::{SearchFilter, SearchSort};
+use fukura::models::{Author, Note};
+use fukura::note_service::NoteBuilder;
 use fukura::repo::FukuraRepo;
 
 fn sample_note() -> Note {
-    let now = Utc::now();
-    let mut meta = BTreeMap::new();
-    meta.insert("os".into(), "macos".into());
-    meta.insert("tool".into(), "internal-proxy".into());
-    Note {
-        title: "Proxy install fails".into(),
-        body: "Update the proxy credentials and retry the setup script.".into(),
-        tags: vec!["proxy".into(), "install".into()],
-        links: vec!["https://internal.example.com/runbook".into()],
-        meta,
-        solutions: vec![],
-        privacy: Privacy::Private,
-        created_at: now,
-        updated_at: now,
-        author: Author {
-            name: "Woven Developer".into(),
-            email: Some("dev@example.com".into()),
-        },
-    }
+    NoteBuilder::new(
+        "Proxy install fails",
+        "Update the proxy credentials and retry the setup script.",
+    )
+    .tag("proxy")
+    .tag("install")
+    .links(["https://internal.example.com/runbook".to_string()])
+    .meta("os", "macos")
+    .meta("tool", "internal-proxy")
+    .author(Author {
+        name: "Woven Developer".into(),
+        email: Some("dev@example.com".into()),
+    })
+    .build()
 }
 
 #[test]
@@ -39,7 +33,7 @@ fn init_and_store_note() -> anyhow::Result<()> {
 
     let fetched = repo.load_note(&record.object_id)?;
     assert_eq!(fetched.note.title, "Proxy install fails");
-    assert_eq!(fetched.note.tags, vec!["proxy", "install"]);
+    assert_eq!(fetched.note.tags, vec!["install", "proxy"]);
 
     let hits = repo.search("proxy", 5, SearchSort::Relevance)?;
     assert!(!hits.is_empty(), "search should surface stored notes");
@@ -75,3 +69,404 @@ fn pack_and_prune() -> anyhow::Result<()> {
     assert_eq!(loaded.note.title, "Proxy install fails");
     Ok(())
 }
+
+#[test]
+fn index_handle_is_reused_and_sees_other_writers() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let clone = repo.clone();
+    assert!(std::ptr::eq(repo.index()?, clone.index()?));
+
+    // A separate handle, as the daemon would have, writes after the first searched
+    assert!(repo.search("proxy", 5, SearchSort::Relevance)?.is_empty());
+    let other = FukuraRepo::open(tmp.path())?;
+    other.store_note(sample_note())?;
+
+    let hits = repo.search("proxy", 5, SearchSort::Relevance)?;
+    assert_eq!(hits.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn iterate_notes_lazily_with_pages() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    for (i, title) in ["oldest", "middle", "newest"].iter().enumerate() {
+        let mut note = sample_note();
+        note.title = title.to_string();
+        note.updated_at = Utc::now() - chrono::Duration::hours(3 - i as i64);
+        repo.store_note(note)?;
+    }
+
+    let titles = |notes: Vec<fukura::models::NoteRecord>| -> Vec<String> {
+        notes.into_iter().map(|r| r.note.title).collect()
+    };
+    let all = repo.iter_notes()?.collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(titles(all), vec!["newest", "middle", "oldest"]);
+
+    let page = repo.notes_page(1, 1)?;
+    assert_eq!(page.size_hint(), (1, Some(1)));
+    assert_eq!(
+        titles(page.collect::<anyhow::Result<Vec<_>>>()?),
+        vec!["middle"]
+    );
+    assert_eq!(repo.list_all_notes()?.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn archive_expired_notes() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut expiring = sample_note();
+    expiring.title = "Stale proxy error".into();
+    expiring.set_expires_at(Utc::now() - chrono::Duration::days(1));
+    let expired = repo.store_note(expiring)?;
+    repo.store_note(sample_note())?;
+
+    let archived = repo.archive_expired(Utc::now())?;
+    assert_eq!(archived.len(), 1);
+    assert_eq!(repo.search("proxy", 10, SearchSort::Relevance)?.len(), 1);
+    let hits = repo.search_archived("proxy", 10, SearchSort::Relevance)?;
+    assert_eq!(hits[0].object_id, expired.object_id);
+    // Archived notes can still be opened by id
+    assert!(repo
+        .load_note(&expired.object_id)?
+        .note
+        .is_expired(Utc::now()));
+    assert!(repo.archive_expired(Utc::now())?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn pinned_notes_lead_listings() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut runbook = sample_note();
+    runbook.title = "Deploy runbook".into();
+    runbook.updated_at = Utc::now() - chrono::Duration::days(30);
+    let runbook = repo.store_note(runbook)?;
+    repo.store_note(sample_note())?;
+
+    assert!(repo.pin(&runbook.object_id)?);
+    assert!(!repo.pin(&runbook.object_id)?);
+    let listed = repo.list_notes(10)?;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].title, "Deploy runbook");
+    assert!(listed[0].pinned && !listed[1].pinned);
+
+    let hits = repo.search("runbook", 5, SearchSort::Relevance)?;
+    assert!(hits[0].pinned);
+    assert_eq!(repo.pinned_notes()?.len(), 1);
+
+    assert!(repo.unpin(&runbook.object_id)?);
+    assert!(repo.pins()?.is_empty());
+    assert_eq!(repo.list_notes(10)?[0].title, "Proxy install fails");
+    Ok(())
+}
+
+#[test]
+fn search_by_meta_field() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut linux = sample_note();
+    linux.title = "Proxy install fails on Linux".into();
+    linux.meta.insert("os".into(), "linux".into());
+    repo.store_note(linux)?;
+    repo.store_note(sample_note())?;
+
+    let hits = repo.search("meta.os:macos", 5, SearchSort::Relevance)?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].title, "Proxy install fails");
+    let hits = repo.search(
+        "meta.os:linux AND meta.tool:proxy",
+        5,
+        SearchSort::Relevance,
+    )?;
+    assert_eq!(hits.len(), 1);
+    assert!(repo
+        .search("meta.os:windows", 5, SearchSort::Relevance)?
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn search_by_error_taxonomy() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let captured = |command: &str, exit_code: &str, stderr: &str| {
+        let mut note = sample_note();
+        note.title = format!("Error: {}", command);
+        note.body = format!(
+            "## Command Failed\n\n```bash\n$ {}\n\n# Error output:\n{}\n```",
+            command, stderr
+        );
+        note.meta.insert("exit_code".into(), exit_code.into());
+        note
+    };
+    repo.store_note(captured(
+        "TF_LOG=warn terraform plan",
+        "1",
+        "Error: Could not resolve host: registry.terraform.io",
+    ))?;
+    repo.store_note(captured("terraform fmt -check", "3", "main.tf"))?;
+    repo.store_note(captured(
+        "cargo build",
+        "101",
+        "error[E0425]: cannot find value",
+    ))?;
+    repo.store_note(sample_note())?;
+
+    let titles = |query: &str| -> anyhow::Result<Vec<String>> {
+        Ok(repo
+            .search(query, 10, SearchSort::Relevance)?
+            .into_iter()
+            .map(|hit| hit.title)
+            .collect())
+    };
+    assert_eq!(
+        titles("binary:terraform exit_code:1")?,
+        vec!["Error: TF_LOG=warn terraform plan"]
+    );
+    assert_eq!(titles("binary:terraform")?.len(), 2);
+    assert_eq!(
+        titles("language:rust AND error_class:compile")?,
+        vec!["Error: cargo build"]
+    );
+    assert_eq!(titles("error_class:network")?.len(), 1);
+    assert!(titles("exit_code:2")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn tombstoned_notes_point_at_their_merge() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut captured = sample_note();
+    captured.title = "Captured proxy error".into();
+    let captured = repo.store_note(captured)?;
+    let manual = repo.store_note(sample_note())?;
+    repo.pin(&captured.object_id)?;
+
+    repo.tombstone(&captured.object_id, &manual.object_id)?;
+    assert_eq!(
+        repo.merged_into(&captured.object_id)?,
+        Some(manual.object_id.clone())
+    );
+    assert_eq!(repo.merged_into(&manual.object_id)?, None);
+    assert_eq!(repo.pins()?, vec![manual.object_id.clone()]);
+    let hits = repo.search("proxy", 5, SearchSort::Relevance)?;
+    assert_eq!(hits.len(), 1);
+    // The object stays for provenance
+    assert_eq!(
+        repo.load_note(&captured.object_id)?.note.title,
+        "Captured proxy error"
+    );
+    Ok(())
+}
+
+#[test]
+fn search_pages_line_up() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    for day in 0..5 {
+        let mut note = sample_note();
+        note.title = format!("Proxy note {}", day);
+        note.updated_at = Utc::now() - chrono::Duration::days(day);
+        repo.store_note(note)?;
+    }
+
+    let first = repo.search_page("proxy", &SearchFilter::default(), 0, 2, SearchSort::Updated)?;
+    assert_eq!(first.total, 5);
+    assert_eq!(first.next_offset, Some(2));
+    let titles: Vec<_> = first.hits.iter().map(|hit| hit.title.as_str()).collect();
+    assert_eq!(titles, vec!["Proxy note 0", "Proxy note 1"]);
+
+    let last = repo.search_page("proxy", &SearchFilter::default(), 4, 2, SearchSort::Updated)?;
+    assert_eq!(last.hits.len(), 1);
+    assert_eq!(last.hits[0].title, "Proxy note 4");
+    assert_eq!(last.next_offset, None);
+
+    let listed = repo.list_notes_page(2, 2)?;
+    assert_eq!(listed.hits[0].title, "Proxy note 2");
+    Ok(())
+}
+
+#[test]
+fn search_hits_carry_highlighted_snippets() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    repo.store_note(sample_note())?;
+
+    let hits = repo.search("credentials", 5, SearchSort::Relevance)?;
+    let snippet = hits[0]
+        .snippet
+        .as_ref()
+        .expect("query terms are highlighted");
+    let matched: Vec<_> = snippet
+        .segments()
+        .into_iter()
+        .filter(|(_, matched)| *matched)
+        .map(|(text, _)| text)
+        .collect();
+    assert_eq!(matched, vec!["credentials"]);
+    assert!(snippet.text.contains("Update the proxy credentials"));
+
+    // Listing has no query, so nothing to highlight
+    assert!(repo.list_notes(5)?[0].snippet.is_none());
+    Ok(())
+}
+
+#[test]
+fn search_filters_by_dates_and_meta() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut old = sample_note();
+    old.title = "Old proxy note".into();
+    old.created_at = Utc::now() - chrono::Duration::days(30);
+    old.updated_at = old.created_at;
+    old.meta.insert("os".into(), "linux".into());
+    repo.store_note(old)?;
+    repo.store_note(sample_note())?;
+
+    let titles = |filter: SearchFilter| -> anyhow::Result<Vec<String>> {
+        Ok(repo
+            .search_page("proxy", &filter, 0, 10, SearchSort::Updated)?
+            .hits
+            .into_iter()
+            .map(|hit| hit.title)
+            .collect())
+    };
+    let week_ago = Utc::now() - chrono::Duration::days(7);
+    assert_eq!(
+        titles(SearchFilter {
+            created_before: Some(week_ago),
+            ..Default::default()
+        })?,
+        vec!["Old proxy note"]
+    );
+    assert_eq!(
+        titles(SearchFilter {
+            updated_after: Some(week_ago),
+            ..Default::default()
+        })?,
+        vec!["Proxy install fails"]
+    );
+    let meta = |key: &str, value: &str| SearchFilter {
+        meta: vec![(key.into(), value.into())],
+        ..Default::default()
+    };
+    assert_eq!(titles(meta("os", "macos"))?, vec!["Proxy install fails"]);
+    // Exact values only; `meta.tool:proxy` would match the tokenized text
+    assert!(titles(meta("tool", "proxy"))?.is_empty());
+    assert_eq!(titles(meta("tool", "internal-proxy"))?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn search_filters_by_scope() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    for (title, scope) in [
+        ("Payments proxy note", Some("services/payments")),
+        ("Refunds proxy note", Some("services/payments/refunds")),
+        ("Billing proxy note", Some("services/billing")),
+        ("Root proxy note", None),
+    ] {
+        let mut note = sample_note();
+        note.title = title.into();
+        if let Some(scope) = scope {
+            note.meta.insert("scope".into(), scope.into());
+        }
+        repo.store_note(note)?;
+    }
+
+    let titles = |scope: &str| -> anyhow::Result<Vec<String>> {
+        let filter = SearchFilter {
+            scope: Some(scope.into()),
+            ..Default::default()
+        };
+        let mut titles: Vec<String> = repo
+            .search_page("proxy", &filter, 0, 10, SearchSort::Updated)?
+            .hits
+            .into_iter()
+            .map(|hit| hit.title)
+            .collect();
+        titles.sort();
+        Ok(titles)
+    };
+    assert_eq!(
+        titles("services/payments")?,
+        vec!["Payments proxy note", "Refunds proxy note"]
+    );
+    // Whole path segments only
+    assert!(titles("services/pay")?.is_empty());
+    assert_eq!(titles("services")?.len(), 3);
+
+    let listed = repo.list_notes_page(0, 10)?;
+    let scopes: Vec<Option<&str>> = listed.hits.iter().map(|hit| hit.scope.as_deref()).collect();
+    assert!(scopes.contains(&Some("services/billing")));
+    assert!(scopes.contains(&None));
+    Ok(())
+}
+
+#[test]
+fn cjk_analyzer_rebuilds_index_and_finds_japanese() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let mut note = sample_note();
+    note.title = "プロキシ設定が失敗する".into();
+    note.body = "社内プロキシの認証情報を更新してから再実行する".into();
+    repo.store_note(note)?;
+
+    let hits = |repo: &FukuraRepo, query: &str| -> anyhow::Result<usize> {
+        Ok(repo
+            .search_page(
+                query,
+                &SearchFilter::default(),
+                0,
+                10,
+                SearchSort::Relevance,
+            )?
+            .total)
+    };
+    assert_eq!(hits(&repo, "設定")?, 0);
+
+    assert!(fukura::config_cmd::update_analyzer(
+        &repo,
+        fukura::analyzer::Analyzer::Cjk
+    )?);
+    drop(repo);
+    let repo = FukuraRepo::open(tmp.path())?;
+    assert_eq!(
+        repo.index()?.analyzer(),
+        Some(fukura::analyzer::Analyzer::Cjk)
+    );
+    assert_eq!(hits(&repo, "設定")?, 1);
+    assert_eq!(hits(&repo, "認証情報")?, 1);
+    assert_eq!(hits(&repo, "proxy")?, 1);
+    Ok(())
+}
+
+#[test]
+fn index_with_another_schema_version_is_rebuilt_on_open() -> anyhow::Result<()> {
+    use fukura::index::{SearchIndex, SCHEMA_VERSION};
+
+    let tmp = tempfile::tempdir()?;
+    let repo = FukuraRepo::init(tmp.path(), true)?;
+    let record = repo.store_note(sample_note())?;
+    assert_eq!(
+        SearchIndex::schema_version_at(&repo.index_dir())?,
+        SCHEMA_VERSION
+    );
+    drop(repo);
+
+    let index_dir = tmp.path().join(".fukura/index");
+    std::fs::write(index_dir.join("fukura-schema"), "0")?;
+    let repo = FukuraRepo::open(tmp.path())?;
+    assert_eq!(SearchIndex::schema_version_at(&index_dir)?, SCHEMA_VERSION);
+    let hits = repo.search("proxy", 10, SearchSort::Relevance)?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].object_id, record.object_id);
+    Ok(())
+}
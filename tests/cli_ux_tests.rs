@@ -100,6 +100,53 @@ fn test_recording_workflow() {
     assert!(done_output.status.success());
 }
 
+#[test]
+fn test_named_recordings_run_side_by_side() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let infra = temp_dir.path().join("infra");
+    std::fs::create_dir(&infra).unwrap();
+    let fuku = |dir: &std::path::Path, args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    assert!(fuku(temp_dir.path(), &["rec", "App work"]).status.success());
+    assert!(fuku(&infra, &["rec", "Infra work", "--name", "infra"])
+        .status
+        .success());
+    assert!(temp_dir
+        .path()
+        .join(".fukura")
+        .join("recordings")
+        .join("infra")
+        .exists());
+
+    let status = fuku(temp_dir.path(), &["rec", "--status"]);
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(stdout.contains("App work") && stdout.contains("Infra work"));
+    assert!(stdout.contains("fuku done --name infra"));
+
+    // Inside infra/, plain `fuku done` finishes the infra recording
+    assert!(fuku(&infra, &["done"]).status.success());
+    let record: serde_json::Value =
+        serde_json::from_slice(&fuku(temp_dir.path(), &["view", "@latest", "--json"]).stdout)
+            .unwrap();
+    assert_eq!(record["note"]["title"], "Infra work");
+    assert_eq!(record["note"]["meta"]["recording_name"], "infra");
+
+    let status = fuku(temp_dir.path(), &["rec", "--status"]);
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(stdout.contains("App work") && !stdout.contains("Infra work"));
+    assert!(fuku(temp_dir.path(), &["done"]).status.success());
+    assert!(!fuku(temp_dir.path(), &["rec", "x", "--name", "../x"])
+        .status
+        .success());
+}
+
 #[test]
 fn test_time_based_recording_workflow() {
     let temp_dir = setup_test_repo();
@@ -259,407 +306,2833 @@ fn test_add_search_view_workflow() {
     assert!(view_output.status.success());
 }
 
-// ============================================================================
-// Git-like Command Tests
-// ============================================================================
-
 #[test]
-fn test_git_like_commands() {
+fn test_meta_schema_validates_and_searches_fields() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
+    let config_path = temp_dir.path().join(".fukura").join("config");
+    let mut config = std::fs::read_to_string(&config_path).unwrap();
+    config.push_str("\n[meta_schema.severity]\ntype = \"integer\"\nrequired = true\n");
+    std::fs::write(&config_path, config).unwrap();
 
-    // Status-like commands
-    let cmds = vec![vec!["status"], vec!["stats"], vec!["list"]];
-
-    for cmd in cmds {
-        let output = Command::new(&binary_path)
-            .args(&cmd)
+    let add = |severity: &str| {
+        Command::new(&binary_path)
+            .args([
+                "add",
+                "--title",
+                "Payments timeout",
+                "--body",
+                "Gateway timed out",
+                "--meta",
+                &format!("severity={}", severity),
+                "--meta",
+                "service=payments",
+                "--no-editor",
+            ])
             .current_dir(temp_dir.path())
             .output()
-            .unwrap_or_else(|_| panic!("Failed to execute {:?}", cmd));
-
-        assert!(output.status.success(), "Command {:?} should succeed", cmd);
-    }
-}
-
-// ============================================================================
-// Error Handling Tests
-// ============================================================================
+            .expect("Failed to add note")
+    };
+    let rejected = add("high");
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("must be an integer"));
+    assert!(add("2").status.success());
 
-#[test]
-fn test_invalid_subcommand() {
-    let binary_path = get_binary_path();
     let output = Command::new(&binary_path)
-        .arg("invalid_command")
+        .args(["edit", "@latest", "--remove-meta", "severity"])
+        .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to execute command");
-
+        .expect("Failed to edit note");
     assert!(!output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(
-        stderr.to_lowercase().contains("unrecognized") || stderr.to_lowercase().contains("invalid")
-    );
-}
-
-#[test]
-fn test_missing_required_arguments() {
-    let temp_dir = setup_test_repo();
-    let binary_path = get_binary_path();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("meta 'severity' is required"));
 
-    // rec without title
     let output = Command::new(&binary_path)
-        .args(["rec"])
+        .args(["search", "meta.service:payments"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to execute command");
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        assert!(
-            stderr.to_lowercase().contains("required") || stderr.to_lowercase().contains("title")
-        );
-    }
+        .expect("Failed to search");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Payments timeout"));
 }
 
-// ============================================================================
-// Daemon Management Tests
-// ============================================================================
-
 #[test]
-fn test_daemon_lifecycle() {
+fn test_edit_records_contributors() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
 
-    // Check status
     let output = Command::new(&binary_path)
-        .args(["status"])
+        .args([
+            "add",
+            "--title",
+            "Flaky deploy",
+            "--body",
+            "Retry the rollout",
+            "--author",
+            "Alice",
+            "--no-editor",
+        ])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to check status");
-
+        .expect("Failed to add note");
     assert!(output.status.success());
-}
-
-// ============================================================================
-// Configuration Tests
-// ============================================================================
 
-#[test]
-fn test_config_show() {
-    let temp_dir = setup_test_repo();
-    let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .args(["edit", "@latest", "--add-tag", "deploy"])
+        .env("GIT_AUTHOR_NAME", "Bob")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to edit note");
+    assert!(output.status.success());
 
     let output = Command::new(&binary_path)
-        .args(["config", "show"])
+        .args(["view", "@latest", "--json"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to show config");
+        .expect("Failed to view note");
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["note"]["author"]["name"], "Alice");
+    assert_eq!(record["note"]["contributors"][0]["name"], "Bob");
 
-    assert!(output.status.success());
+    let output = Command::new(&binary_path)
+        .args(["view", "@latest"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to view note");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Alice, Bob"));
 }
 
-// ============================================================================
-// Edge Cases Tests
-// ============================================================================
-
 #[test]
-fn test_empty_repository_commands() {
+fn test_merge_combines_notes_and_retires_absorbed() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
-
-    // These should work even with empty repo
-    let safe_commands = vec![
-        vec!["list"],
-        vec!["stats"],
-        vec!["log"],
-        vec!["track", "--status"],
-    ];
-
-    for cmd in safe_commands {
+    let add = |title: &str, body: &str, tag: &str| {
         let output = Command::new(&binary_path)
-            .args(&cmd)
+            .args([
+                "add",
+                "--title",
+                title,
+                "--body",
+                body,
+                "--tag",
+                tag,
+                "--no-editor",
+            ])
             .current_dir(temp_dir.path())
             .output()
-            .unwrap_or_else(|_| panic!("Failed to execute {:?}", cmd));
-
-        assert!(
-            output.status.success(),
-            "Command {:?} should work with empty repo",
-            cmd
-        );
-    }
-}
-
-#[test]
-fn test_special_refs() {
-    let temp_dir = setup_test_repo();
-    let binary_path = get_binary_path();
+            .expect("Failed to add note");
+        assert!(output.status.success());
+        let output = Command::new(&binary_path)
+            .args(["view", "@latest", "--json"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to view note");
+        let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        record["object_id"].as_str().unwrap().to_string()
+    };
+    let manual = add("Proxy auth", "## Fix\nRestart the proxy", "proxy");
+    let captured = add(
+        "407 from proxy",
+        "## Fix\nRotate the token",
+        "auto-captured",
+    );
 
-    // Add a note first
-    let _add = Command::new(&binary_path)
-        .args(["add", "--title", "Test", "--body", "Content", "--no-editor"])
+    let output = Command::new(&binary_path)
+        .args(["merge", &manual, &captured, "--conflicts", "both"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to add note");
-
-    // Test special refs
-    let refs = vec!["@latest", "@1"];
+        .expect("Failed to merge notes");
+    assert!(output.status.success());
 
-    for ref_str in refs {
-        let output = Command::new(&binary_path)
-            .args(["view", ref_str])
-            .current_dir(temp_dir.path())
-            .output()
-            .unwrap_or_else(|_| panic!("Failed to view {}", ref_str));
+    let output = Command::new(&binary_path)
+        .args(["view", &captured, "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to view note");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was merged into"));
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let note = &record["note"];
+    assert_eq!(note["title"], "Proxy auth");
+    assert_eq!(
+        note["body"],
+        "## Fix\nRestart the proxy\n\nRotate the token"
+    );
+    assert_eq!(note["tags"], serde_json::json!(["auto-captured", "proxy"]));
+    assert_eq!(note["meta"]["merged_from"], captured.as_str());
 
-        assert!(output.status.success(), "Should resolve ref: {}", ref_str);
-    }
+    let output = Command::new(&binary_path)
+        .args(["search", "token"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Proxy auth") && !stdout.contains("407 from proxy"));
 }
 
-// ============================================================================
-// Performance Tests
-// ============================================================================
-
 #[test]
-fn test_bulk_operations() {
+fn test_search_pages() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
-
-    // Add multiple notes
-    for i in 0..10 {
+    for i in 1..=3 {
         let output = Command::new(&binary_path)
             .args([
                 "add",
                 "--title",
-                &format!("Note {}", i),
+                &format!("Paged note {}", i),
                 "--body",
-                &format!("Content {}", i),
+                "pagination",
                 "--no-editor",
             ])
             .current_dir(temp_dir.path())
             .output()
             .expect("Failed to add note");
-
-        assert!(output.status.success(), "Failed to add note {}", i);
+        assert!(output.status.success());
     }
 
-    // Search should be fast
-    let search_output = Command::new(&binary_path)
-        .args(["search", "Note"])
-        .current_dir(temp_dir.path())
-        .output()
-        .expect("Failed to search");
-
-    assert!(search_output.status.success());
-}
-
-// ============================================================================
-// User Experience Tests
-// ============================================================================
-
-#[test]
-fn test_helpful_error_messages() {
-    let binary_path = get_binary_path();
-
-    // Command without repo
-    let output = Command::new(&binary_path)
-        .args(["add", "--title", "Test"])
-        .current_dir("/tmp")
-        .output()
-        .expect("Failed to execute");
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        // Should mention init
-        assert!(
-            stderr.to_lowercase().contains("init") || stderr.to_lowercase().contains("repository")
-        );
+    let search = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .args(["search", "--sort", "updated", "-n", "2"])
+            .args(args)
+            .arg("pagination")
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to search");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let first = search(&[]);
+    assert!(first.contains("Showing 1-2 of 3"));
+    assert!(first.contains("--page 2"));
+    let second = search(&["--page", "2"]);
+    assert!(second.contains("Showing 3-3 of 3"));
+    // Every note shows up on exactly one page
+    for i in 1..=3 {
+        let title = format!("Paged note {}", i);
+        assert!(first.contains(&title) != second.contains(&title));
     }
+    let json = search(&["--offset", "1", "--json"]);
+    let hits: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(hits.as_array().unwrap().len(), 2);
 }
 
 #[test]
-fn test_alias_suggestions() {
+fn test_rename_auto_uses_title_template() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
+    let config = temp_dir.path().join(".fukura/config");
+    let contents = std::fs::read_to_string(&config)
+        .unwrap()
+        .replace("Error: {command}", "{program} ({exit_code}): {error}");
+    std::fs::write(&config, contents).unwrap();
 
-    // Test that visible aliases work
     let output = Command::new(&binary_path)
-        .args(["l"]) // Alias for log
-        .current_dir(temp_dir.path())
-        .output()
-        .expect("Failed to execute alias");
-
-    assert!(output.status.success(), "Alias 'l' should work for 'log'");
-}
-
-// ============================================================================
-// Complex Workflow Tests
-// ============================================================================
-
-#[test]
-fn test_complete_dev_workflow() {
-    let temp_dir = setup_test_repo();
-    let binary_path = get_binary_path();
-
-    // 1. Start recording
-    let _rec = Command::new(&binary_path)
-        .args(["rec", "Feature implementation"])
-        .current_dir(temp_dir.path())
-        .output()
-        .expect("Failed to start rec");
-
-    // 2. Check status
-    let status = Command::new(&binary_path)
-        .args(["rec", "--status"])
-        .current_dir(temp_dir.path())
-        .output()
-        .expect("Failed to check status");
-
-    assert!(status.status.success());
-
-    // 3. Add some notes
-    let _add = Command::new(&binary_path)
         .args([
             "add",
             "--title",
-            "Implementation notes",
+            "Error: npm test",
             "--body",
-            "Details",
+            "```bash\n$ npm test\n\n# Error output:\nnpm ERR! missing script: test\n```",
+            "--meta",
+            "exit_code=1",
             "--no-editor",
         ])
         .current_dir(temp_dir.path())
         .output()
         .expect("Failed to add note");
+    assert!(output.status.success());
 
-    // 4. Stop recording
-    let done = Command::new(&binary_path)
-        .args(["done"])
+    let output = Command::new(&binary_path)
+        .args(["rename", "--auto", "@latest"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to finish");
-
-    assert!(done.status.success());
+        .expect("Failed to rename");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("npm (1): npm ERR! missing script: test"));
 
-    // 5. View latest
-    let view = Command::new(&binary_path)
-        .args(["view", "@latest"])
+    let output = Command::new(&binary_path)
+        .args(["rename", "@latest", "npm test needs a test script"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to view");
-
-    assert!(view.status.success());
-
-    // 6. Search
-    let search = Command::new(&binary_path)
-        .args(["search", "implementation"])
+        .expect("Failed to rename");
+    assert!(output.status.success());
+    let output = Command::new(&binary_path)
+        .args(["rename", "--auto", "@latest", "Both"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to search");
+        .expect("Failed to run rename");
+    assert!(!output.status.success());
+}
 
-    assert!(search.status.success());
+#[test]
+fn test_profile_sets_author_and_privacy() {
+    let fukura_home = TempDir::new().expect("Failed to create temp dir");
+    let project = TempDir::new().expect("Failed to create temp dir");
+    std::fs::write(
+        fukura_home.path().join("config.toml"),
+        "version = 1\n\n[profiles.work]\ndefault_privacy = \"org\"\nauthor = { name = \"Ana Ops\" }\n",
+    )
+    .unwrap();
+    let binary_path = get_binary_path();
+    let fuku = |args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .env("FUKURA_HOME", fukura_home.path())
+            .env_remove("FUKURA_PROFILE")
+            .current_dir(project.path())
+            .output()
+            .expect("Failed to run fuku")
+    };
+    assert!(fuku(&["init", "--no-daemon", "--no-hooks"])
+        .status
+        .success());
+
+    let add = |profile: &str| {
+        fuku(&[
+            "--profile",
+            profile,
+            "add",
+            "--title",
+            "VPN drops",
+            "--body",
+            "Reconnect",
+            "--no-editor",
+        ])
+    };
+    assert!(add("work").status.success());
+    let record: serde_json::Value =
+        serde_json::from_slice(&fuku(&["view", "@latest", "--json"]).stdout).unwrap();
+    assert_eq!(record["note"]["privacy"], "org");
+    assert_eq!(record["note"]["author"]["name"], "Ana Ops");
+
+    let output = add("wrok");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown profile 'wrok'"));
 }
 
-// ============================================================================
-// Time Expression Tests
-// ============================================================================
+#[test]
+fn test_config_check_reports_typos() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let check = || {
+        Command::new(&binary_path)
+            .args(["config", "check"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run config check")
+    };
+    assert!(check().status.success());
+
+    let config_path = temp_dir.path().join(".fukura").join("config");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, format!("auto_synk = true\n{}", config)).unwrap();
+    let output = check();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("auto_synk: unknown key"));
+}
 
 #[test]
-fn test_various_time_expressions() {
+fn test_repos_registry_drives_all_repos_search() {
+    let fukura_home = TempDir::new().expect("Failed to create temp dir");
+    let project = TempDir::new().expect("Failed to create temp dir");
+    let binary_path = get_binary_path();
+    let fuku = |args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .env("FUKURA_HOME", fukura_home.path())
+            .current_dir(project.path())
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    assert!(fuku(&["init", "--no-daemon", "--no-hooks"])
+        .status
+        .success());
+    assert!(fuku(&[
+        "add",
+        "--title",
+        "Registry note",
+        "--body",
+        "findable everywhere",
+        "--no-editor",
+    ])
+    .status
+    .success());
+
+    let listed = String::from_utf8_lossy(&fuku(&["repos", "list"]).stdout).to_string();
+    let root = project.path().canonicalize().unwrap();
+    assert!(listed.contains(&root.display().to_string()));
+
+    let found = fuku(&["search", "--all-repos", "findable"]);
+    assert!(String::from_utf8_lossy(&found.stdout).contains("Registry note"));
+    let found = fuku(&["search", "--all-repos", "--json", "findable"]);
+    let hits: serde_json::Value = serde_json::from_slice(&found.stdout).unwrap();
+    assert_eq!(hits[0]["title"], "Registry note");
+    assert_eq!(hits[0]["repo"], root.display().to_string());
+
+    assert!(fuku(&["repos", "remove", root.to_str().unwrap()])
+        .status
+        .success());
+    assert!(!fuku(&["repos", "remove", root.to_str().unwrap()])
+        .status
+        .success());
+    let json = fuku(&["repos", "list", "--json"]);
+    let entries: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert!(entries.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_view_exports_markdown_and_html() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Proxy runbook",
+            "--body",
+            "## Steps\n\n```bash\nexport HTTPS_PROXY=http://proxy:3128\n```",
+            "--tag",
+            "network",
+            "--link",
+            "https://wiki.example.com/proxy",
+            "--meta",
+            "team=infra",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
 
-    let valid_times = vec![
-        "1m ago",
-        "5m ago",
-        "30m ago",
-        "1h ago",
-        "2h ago",
-        "1h 30m ago",
-        "2h 15m ago",
-        "45m ago",
-        "1m",
-        "2h",
-        "30m", // Without "ago"
-    ];
+    let output = Command::new(&binary_path)
+        .args(["view", "@latest", "--format", "md"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to export");
+    assert!(output.status.success());
+    let markdown = String::from_utf8_lossy(&output.stdout);
+    assert!(markdown.starts_with("# Proxy runbook\n"));
+    assert!(markdown.contains("#network"));
+    assert!(markdown.contains("## Links\n\n- https://wiki.example.com/proxy"));
+    assert!(markdown.contains("| team | infra |"));
+    assert!(!markdown.contains('\u{1b}'));
+
+    let page = temp_dir.path().join("runbook.html");
+    let output = Command::new(&binary_path)
+        .args(["view", "@latest", "--format", "html", "-o"])
+        .arg(&page)
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to export");
+    assert!(output.status.success());
+    let html = std::fs::read_to_string(&page).unwrap();
+    assert!(html.starts_with("<!doctype html>"));
+    assert!(html.contains("Proxy runbook"));
 
-    for time_expr in valid_times {
-        let _ = Command::new(&binary_path)
-            .args(["rec", "--stop"])
-            .current_dir(temp_dir.path())
-            .output();
+    let output = Command::new(&binary_path)
+        .args(["view", "@latest", "--format", "md", "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run view");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_dynamic_completion_candidates() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Completion note",
+            "--body",
+            "tab tab",
+            "--tag",
+            "shell",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
 
+    let complete = |kind: &str| {
         let output = Command::new(&binary_path)
-            .args(["rec", "Test", time_expr])
+            .args(["__complete", kind])
             .current_dir(temp_dir.path())
             .output()
-            .expect("Failed to execute");
+            .expect("Failed to complete");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let ids = complete("ids");
+    assert!(ids.starts_with("@latest\t"));
+    assert!(ids.contains("\tCompletion note"));
+    assert_eq!(complete("tags").trim(), "shell\t1 note");
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            // Should not fail due to time format
-            assert!(
-                !stderr.to_lowercase().contains("invalid format"),
-                "Time expression should be valid: {}",
-                time_expr
-            );
-        }
-    }
+    let output = Command::new(&binary_path)
+        .args(["completions", "bash", "--stdout"])
+        .output()
+        .expect("Failed to generate completions");
+    let script = String::from_utf8_lossy(&output.stdout);
+    assert!(script.contains("complete -F _fuku_dynamic"));
+    assert!(script.contains("--add-tag"));
+
+    // Outside a repository there is nothing to offer, and no error
+    let elsewhere = TempDir::new().unwrap();
+    let output = Command::new(&binary_path)
+        .args(["__complete", "ids"])
+        .current_dir(elsewhere.path())
+        .output()
+        .expect("Failed to complete");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
 }
 
-// ============================================================================
-// Output Format Tests
-// ============================================================================
+#[test]
+fn test_completions_for_more_shells() {
+    let binary_path = get_binary_path();
+    let home = TempDir::new().unwrap();
+    let fuku = |args: &[&str], shell: &str| {
+        Command::new(&binary_path)
+            .args(args)
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path().join(".config"))
+            .env("SHELL", shell)
+            .env_remove("XONSH_VERSION")
+            .output()
+            .expect("Failed to run completions")
+    };
+
+    let output = fuku(&["completions", "elvish", "--stdout"], "/bin/sh");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("edit:completion:arg-completer"));
+
+    // No shell named: the login shell decides
+    let output = fuku(&["completions", "--stdout"], "/usr/bin/fish");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("complete -c fuku"));
+
+    let output = fuku(&["completions", "xonsh"], "/bin/sh");
+    assert!(output.status.success());
+    let xonsh = home.path().join(".config/xonsh");
+    assert!(std::fs::read_to_string(xonsh.join("fuku-completion.bash"))
+        .unwrap()
+        .contains("complete -F _fuku_dynamic"));
+    assert!(std::fs::read_to_string(xonsh.join("rc.d/fuku.xsh"))
+        .unwrap()
+        .contains("$BASH_COMPLETIONS.append"));
+
+    let output = fuku(&["completions", "tcsh"], "/bin/sh");
+    assert!(!output.status.success());
+}
 
 #[test]
-fn test_json_output() {
+fn test_structured_output() {
     let temp_dir = setup_test_repo();
     let binary_path = get_binary_path();
-
-    // Add note
-    let _add = Command::new(&binary_path)
+    Command::new(&binary_path)
         .args([
             "add",
             "--title",
-            "JSON test",
+            "Proxy timeout",
             "--body",
-            "Content",
+            "Raise it",
+            "--tag",
+            "net",
             "--no-editor",
         ])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to add");
+        .expect("Failed to add note");
 
-    // View as JSON
     let output = Command::new(&binary_path)
-        .args(["view", "@latest", "--json"])
+        .args(["--output", "json", "stats"])
         .current_dir(temp_dir.path())
         .output()
-        .expect("Failed to view JSON");
-
+        .expect("Failed to run stats");
     assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["notes"], 1);
+    assert_eq!(stats["tags"], 1);
 
-    // Should be valid JSON
+    let output = Command::new(&binary_path)
+        .args(["list", "--output", "yaml"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run list");
+    let yaml = String::from_utf8_lossy(&output.stdout);
+    assert!(yaml.contains("total: 1"));
+    assert!(yaml.contains("title: Proxy timeout"));
+}
+
+// ============================================================================
+// Git-like Command Tests
+// ============================================================================
+
+#[test]
+fn test_git_like_commands() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Status-like commands
+    let cmds = vec![vec!["status"], vec!["stats"], vec!["list"]];
+
+    for cmd in cmds {
+        let output = Command::new(&binary_path)
+            .args(&cmd)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap_or_else(|_| panic!("Failed to execute {:?}", cmd));
+
+        assert!(output.status.success(), "Command {:?} should succeed", cmd);
+    }
+}
+
+// ============================================================================
+// Error Handling Tests
+// ============================================================================
+
+#[test]
+fn test_invalid_subcommand() {
+    let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .arg("invalid_command")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
-        serde_json::from_str::<serde_json::Value>(&stdout).is_ok(),
-        "Output should be valid JSON"
+        stderr.to_lowercase().contains("unrecognized") || stderr.to_lowercase().contains("invalid")
     );
 }
 
+#[test]
+fn test_missing_required_arguments() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // rec without title
+    let output = Command::new(&binary_path)
+        .args(["rec"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.to_lowercase().contains("required") || stderr.to_lowercase().contains("title")
+        );
+    }
+}
+
 // ============================================================================
-// Interactive Mode Tests (skipped in CI)
+// Daemon Management Tests
 // ============================================================================
 
 #[test]
-#[ignore = "Requires interactive input"]
-fn test_interactive_add() {
-    // This would test the quick mode with user input
-    // Skipped in automated tests
+fn test_daemon_lifecycle() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Check status
+    let output = Command::new(&binary_path)
+        .args(["status"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to check status");
+
+    assert!(output.status.success());
+
+    let output = Command::new(&binary_path)
+        .args(["status", "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to check status");
+    assert!(output.status.success());
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(status["daemon"]["running"], false);
+    assert_eq!(status["unsynced"], 0);
+    assert_eq!(status["indexed_notes"], 0);
+    assert!(status["hooks"].is_array());
+    assert!(status["last_capture"].is_null());
 }
 
+// ============================================================================
+// Configuration Tests
+// ============================================================================
+
 #[test]
-#[ignore = "Requires interactive input"]
-fn test_interactive_edit() {
-    // This would test the editor mode
-    // Skipped in automated tests
+fn test_config_show() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args(["config", "show"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to show config");
+
+    assert!(output.status.success());
+}
+
+// ============================================================================
+// Edge Cases Tests
+// ============================================================================
+
+#[test]
+fn test_empty_repository_commands() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // These should work even with empty repo
+    let safe_commands = vec![
+        vec!["list"],
+        vec!["stats"],
+        vec!["log"],
+        vec!["track", "--status"],
+    ];
+
+    for cmd in safe_commands {
+        let output = Command::new(&binary_path)
+            .args(&cmd)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap_or_else(|_| panic!("Failed to execute {:?}", cmd));
+
+        assert!(
+            output.status.success(),
+            "Command {:?} should work with empty repo",
+            cmd
+        );
+    }
+}
+
+#[test]
+fn test_special_refs() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Add a note first
+    let _add = Command::new(&binary_path)
+        .args(["add", "--title", "Test", "--body", "Content", "--no-editor"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+
+    // Test special refs
+    let refs = vec!["@latest", "@1"];
+
+    for ref_str in refs {
+        let output = Command::new(&binary_path)
+            .args(["view", ref_str])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap_or_else(|_| panic!("Failed to view {}", ref_str));
+
+        assert!(output.status.success(), "Should resolve ref: {}", ref_str);
+    }
+}
+
+// ============================================================================
+// Performance Tests
+// ============================================================================
+
+#[test]
+fn test_bulk_operations() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Add multiple notes
+    for i in 0..10 {
+        let output = Command::new(&binary_path)
+            .args([
+                "add",
+                "--title",
+                &format!("Note {}", i),
+                "--body",
+                &format!("Content {}", i),
+                "--no-editor",
+            ])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+
+        assert!(output.status.success(), "Failed to add note {}", i);
+    }
+
+    // Search should be fast
+    let search_output = Command::new(&binary_path)
+        .args(["search", "Note"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search");
+
+    assert!(search_output.status.success());
+}
+
+// ============================================================================
+// User Experience Tests
+// ============================================================================
+
+#[test]
+fn test_helpful_error_messages() {
+    let binary_path = get_binary_path();
+
+    // Command without repo
+    let output = Command::new(&binary_path)
+        .args(["add", "--title", "Test"])
+        .current_dir("/tmp")
+        .output()
+        .expect("Failed to execute");
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Should mention init
+        assert!(
+            stderr.to_lowercase().contains("init") || stderr.to_lowercase().contains("repository")
+        );
+    }
+}
+
+#[test]
+fn test_alias_suggestions() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Test that visible aliases work
+    let output = Command::new(&binary_path)
+        .args(["l"]) // Alias for log
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute alias");
+
+    assert!(output.status.success(), "Alias 'l' should work for 'log'");
+}
+
+// ============================================================================
+// Complex Workflow Tests
+// ============================================================================
+
+#[test]
+fn test_complete_dev_workflow() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // 1. Start recording
+    let _rec = Command::new(&binary_path)
+        .args(["rec", "Feature implementation"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to start rec");
+
+    // 2. Check status
+    let status = Command::new(&binary_path)
+        .args(["rec", "--status"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to check status");
+
+    assert!(status.status.success());
+
+    // 3. Add some notes
+    let _add = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Implementation notes",
+            "--body",
+            "Details",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+
+    // 4. Stop recording
+    let done = Command::new(&binary_path)
+        .args(["done"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to finish");
+
+    assert!(done.status.success());
+
+    // 5. View latest
+    let view = Command::new(&binary_path)
+        .args(["view", "@latest"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to view");
+
+    assert!(view.status.success());
+
+    // 6. Search
+    let search = Command::new(&binary_path)
+        .args(["search", "implementation"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search");
+
+    assert!(search.status.success());
+}
+
+// ============================================================================
+// Time Expression Tests
+// ============================================================================
+
+#[test]
+fn test_various_time_expressions() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let valid_times = vec![
+        "1m ago",
+        "5m ago",
+        "30m ago",
+        "1h ago",
+        "2h ago",
+        "1h 30m ago",
+        "2h 15m ago",
+        "45m ago",
+        "1m",
+        "2h",
+        "30m", // Without "ago"
+    ];
+
+    for time_expr in valid_times {
+        let _ = Command::new(&binary_path)
+            .args(["rec", "--stop"])
+            .current_dir(temp_dir.path())
+            .output();
+
+        let output = Command::new(&binary_path)
+            .args(["rec", "Test", time_expr])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute");
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Should not fail due to time format
+            assert!(
+                !stderr.to_lowercase().contains("invalid format"),
+                "Time expression should be valid: {}",
+                time_expr
+            );
+        }
+    }
+}
+
+// ============================================================================
+// Output Format Tests
+// ============================================================================
+
+#[test]
+fn test_json_output() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // Add note
+    let _add = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "JSON test",
+            "--body",
+            "Content",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add");
+
+    // View as JSON
+    let output = Command::new(&binary_path)
+        .args(["view", "@latest", "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to view JSON");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Should be valid JSON
+    assert!(
+        serde_json::from_str::<serde_json::Value>(&stdout).is_ok(),
+        "Output should be valid JSON"
+    );
+}
+
+// ============================================================================
+// Grep Tests
+// ============================================================================
+
+#[test]
+fn test_grep_matches_exact_error_codes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    for (title, body, tag) in [
+        (
+            "Rust resolve",
+            "error[E0425]: cannot find value `x`",
+            "rust",
+        ),
+        ("Other", "nothing interesting here", "misc"),
+    ] {
+        let output = Command::new(&binary_path)
+            .args([
+                "add",
+                "--title",
+                title,
+                "--body",
+                body,
+                "--tag",
+                tag,
+                "--no-editor",
+            ])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add");
+        assert!(output.status.success());
+    }
+
+    let output = Command::new(&binary_path)
+        .args(["grep", "-F", "error[E0425]"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to grep");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Rust resolve"));
+    assert!(!stdout.contains("Other"));
+
+    let output = Command::new(&binary_path)
+        .args(["grep", "-l", "E04\\d+", "--tag", "misc"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to grep");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).trim().is_empty());
+}
+
+// ============================================================================
+// Picker Tests
+// ============================================================================
+
+#[test]
+fn test_pick_widget_output() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args(["pick", "--widget", "zsh"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute pick");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("fuku pick"));
+    assert!(stdout.contains("bindkey"));
+}
+
+#[test]
+fn test_pick_requires_terminal() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let _add = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Pick me",
+            "--body",
+            "Content",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add");
+
+    // Without a TTY the picker must fail cleanly instead of hanging
+    let output = Command::new(&binary_path)
+        .arg("pick")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute pick");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).trim().is_empty());
+}
+
+// ============================================================================
+// Interactive Mode Tests (skipped in CI)
+// ============================================================================
+
+#[test]
+#[ignore = "Requires interactive input"]
+fn test_interactive_add() {
+    // This would test the quick mode with user input
+    // Skipped in automated tests
+}
+
+#[test]
+#[ignore = "Requires interactive input"]
+fn test_interactive_edit() {
+    // This would test the editor mode
+    // Skipped in automated tests
+}
+
+// ============================================================================
+// History Tests
+// ============================================================================
+
+#[test]
+fn test_history_reads_persisted_commands() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let activity_dir = temp_dir.path().join(".fukura").join("activity");
+    std::fs::create_dir_all(&activity_dir).unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let lines = [
+        format!(
+            r#"{{"session_id":"s1","command":"cargo build","exit_code":101,"timestamp":{{"secs_since_epoch":{},"nanos_since_epoch":0}},"working_directory":"/tmp"}}"#,
+            now - 60
+        ),
+        format!(
+            r#"{{"session_id":"s1","command":"ls -la","exit_code":0,"timestamp":{{"secs_since_epoch":{},"nanos_since_epoch":0}},"working_directory":"/tmp"}}"#,
+            now - 30
+        ),
+    ];
+    std::fs::write(activity_dir.join("commands.jsonl"), lines.join("\n")).unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["history", "--failed", "--since", "1h"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run history");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cargo build"));
+    assert!(!stdout.contains("ls -la"));
+}
+
+#[test]
+fn test_last_error_shows_and_saves_the_last_failure_here() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let activity_dir = temp_dir.path().join(".fukura").join("activity");
+    std::fs::create_dir_all(&activity_dir).unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let here = temp_dir.path().canonicalize().unwrap();
+    let record = |command: &str, exit_code: i32, dir: &str, stderr: &str, ago: u64| {
+        serde_json::json!({
+            "session_id": "s1",
+            "command": command,
+            "exit_code": exit_code,
+            "timestamp": {"secs_since_epoch": now - ago, "nanos_since_epoch": 0},
+            "working_directory": dir,
+            "stderr": stderr,
+        })
+        .to_string()
+    };
+    let lines = [
+        record(
+            "npm test",
+            1,
+            here.to_str().unwrap(),
+            "Error: Cannot find module 'jest'",
+            90,
+        ),
+        record("ls -la", 0, here.to_str().unwrap(), "", 60),
+        record("make", 2, "/somewhere/else", "make: *** No rule", 30),
+    ];
+    std::fs::write(activity_dir.join("commands.jsonl"), lines.join("\n")).unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["last-error"])
+        .current_dir(&here)
+        .output()
+        .expect("Failed to run last-error");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("npm test"));
+    assert!(stdout.contains("Cannot find module 'jest'"));
+    assert!(!stdout.contains("make"));
+
+    let output = Command::new(&binary_path)
+        .args(["last-error", "--note"])
+        .current_dir(&here)
+        .output()
+        .expect("Failed to run last-error --note");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Saved"));
+
+    let output = Command::new(&binary_path)
+        .args(["search", "--json", "jest"])
+        .current_dir(&here)
+        .output()
+        .expect("Failed to search");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("auto-captured"));
+}
+
+#[test]
+fn test_history_slower_than_filters_by_duration() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let activity_dir = temp_dir.path().join(".fukura").join("activity");
+    std::fs::create_dir_all(&activity_dir).unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let record = |command: &str, duration_ms: Option<u64>| {
+        let mut record = serde_json::json!({
+            "session_id": "s1",
+            "command": command,
+            "exit_code": 0,
+            "timestamp": {"secs_since_epoch": now - 60, "nanos_since_epoch": 0},
+            "working_directory": "/tmp",
+        });
+        if let Some(ms) = duration_ms {
+            record["duration_ms"] = ms.into();
+        }
+        record.to_string()
+    };
+    let lines = [
+        record("cargo build --release", Some(754_000)),
+        record("ls -la", Some(12)),
+        record("make", None),
+    ];
+    std::fs::write(activity_dir.join("commands.jsonl"), lines.join("\n")).unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["history", "--slower-than", "1m"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run history");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cargo build --release"));
+    assert!(stdout.contains("12m 34s"));
+    assert!(!stdout.contains("ls -la"));
+    assert!(!stdout.contains("make"));
+}
+
+#[test]
+fn test_prompt_status_reads_state_files() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let prompt_status = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .arg("prompt-status")
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run prompt-status");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    assert_eq!(prompt_status(&["--plain"]), "off");
+
+    for args in [
+        vec![
+            "add",
+            "--title",
+            "Proxy fix",
+            "--body",
+            "set HTTPS_PROXY",
+            "--no-editor",
+        ],
+        vec!["rec", "Debugging"],
+    ] {
+        let output = Command::new(&binary_path)
+            .args(&args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku");
+        assert!(output.status.success());
+    }
+    assert_eq!(prompt_status(&["--plain"]), "off REC ^1");
+    assert_eq!(
+        prompt_status(&["--format", "{daemon}:{unsynced}"]),
+        "down:1"
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&prompt_status(&["--output", "json"])).unwrap();
+    assert_eq!(json["unsynced"], 1);
+    assert_eq!(json["daemon_running"], false);
+
+    // Outside a repository it stays silent
+    let outside = TempDir::new().unwrap();
+    let output = Command::new(&binary_path)
+        .arg("prompt-status")
+        .current_dir(outside.path())
+        .output()
+        .expect("Failed to run prompt-status");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_patterns_lists_frequent_errors() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let time = |secs: u64| format!(r#"{{"secs_since_epoch":{},"nanos_since_epoch":0}}"#, secs);
+    let patterns = format!(
+        r#"{{"version":1,"patterns":{{"abc":{{"normalized_message":"error[E0308]: mismatched types","fingerprint":"abc","occurrences":3,"last_seen":{},"first_seen":{},"last_command":"cargo test","seen_at":[{},{},{}],"notes":[],"resolved_by":[]}}}}}}"#,
+        time(now - 60),
+        time(now - 86_400 * 10),
+        time(now - 86_400 * 10),
+        time(now - 3_600),
+        time(now - 60),
+    );
+    std::fs::write(
+        temp_dir.path().join(".fukura").join("patterns.json"),
+        patterns,
+    )
+    .unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["patterns"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run patterns");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("error[E0308]: mismatched types"));
+    assert!(stdout.contains("3× total · 2 recent ↑ (1 before)"));
+    assert!(stdout.contains("$ cargo test"));
+}
+
+#[test]
+fn test_feedback_requires_outcome_and_records_it() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Fix",
+            "--body",
+            "Solution: rm -rf target",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+
+    let output = Command::new(&binary_path)
+        .args(["feedback", "@latest"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run feedback");
+    assert!(!output.status.success());
+
+    let output = Command::new(&binary_path)
+        .args(["feedback", "@latest", "--worked"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run feedback");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 worked"));
+
+    let feedback =
+        std::fs::read_to_string(temp_dir.path().join(".fukura").join("feedback.jsonl")).unwrap();
+    assert!(feedback.contains("\"kind\":\"worked\""));
+}
+
+#[test]
+fn test_import_directory_in_batches() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let vault = temp_dir.path().join("vault");
+    std::fs::create_dir_all(vault.join("nested")).unwrap();
+    for i in 0..5 {
+        std::fs::write(
+            vault.join(format!("note-{}.md", i)),
+            format!("# Vault note {}\n\ntags: imported, rust\n\nBody {}", i, i),
+        )
+        .unwrap();
+    }
+    std::fs::write(vault.join("nested").join("empty.md"), "# Only a title\n").unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["import", "vault"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run import");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Imported: 5"));
+    assert!(stdout.contains("Skipped: 1"));
+
+    let output = Command::new(&binary_path)
+        .args(["search", "--limit", "10", "Vault note"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run search");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Vault note 4"));
+}
+
+#[test]
+fn test_report_markdown_counts_new_notes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Proxy runbook",
+            "--body",
+            "Set HTTPS_PROXY",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+
+    let output = Command::new(&binary_path)
+        .args(["report", "--since", "7d", "--markdown"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run report");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# Fukura report:"));
+    assert!(stdout.contains("- **1** new notes"));
+    assert!(stdout.contains("## New notes\n\n- Proxy runbook"));
+
+    let output = Command::new(&binary_path)
+        .args(["report", "--markdown", "--html"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run report");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_migrate_reports_current_format() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args(["migrate", "--dry-run"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run migrate");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("nothing to migrate"));
+
+    let output = Command::new(&binary_path)
+        .args(["migrate", "--to", "1"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run migrate");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_gc_archives_expired_notes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Flaky mirror timeout",
+            "--body",
+            "Retry against the backup mirror",
+            "--expires",
+            "1s",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    let output = Command::new(&binary_path)
+        .args(["gc"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run gc");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Archived 1 expired notes"));
+
+    let output = Command::new(&binary_path)
+        .args(["search", "--json", "mirror"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Flaky mirror timeout"));
+
+    let output = Command::new(&binary_path)
+        .args(["search", "--archived", "--json", "mirror"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search archive");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Flaky mirror timeout"));
+}
+
+#[test]
+fn test_gc_auto_packs_past_thresholds() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    for title in ["Proxy fix", "Docker restart"] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--body", "Steps", "--no-editor"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+
+    let output = Command::new(&binary_path)
+        .args(["gc", "--auto"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run gc");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("nothing to pack"));
+
+    let config_path = temp_dir.path().join(".fukura/config");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("auto_loose_objects = 500"));
+    let config = config.replace("auto_loose_objects = 500", "auto_loose_objects = 2");
+    std::fs::write(&config_path, config).unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["--output", "json", "gc", "--auto"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run gc");
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["loose"]["objects"], 2);
+    assert_eq!(summary["pack"]["pruned"], 2);
+    assert!(summary["pack"]["reclaimed_bytes"].is_u64());
+    assert_eq!(summary["pack"]["interrupted"], false);
+
+    let output = Command::new(&binary_path)
+        .args(["list"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to list notes");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Proxy fix"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupted_import_leaves_repo_consistent() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let docs = temp_dir.path().join("docs");
+    std::fs::create_dir(&docs).unwrap();
+    for i in 0..2000 {
+        std::fs::write(
+            docs.join(format!("note-{i}.md")),
+            format!("# Note {i}\n\nBody {i}\n"),
+        )
+        .unwrap();
+    }
+
+    let child = Command::new(&binary_path)
+        .args(["import", "docs"])
+        .current_dir(temp_dir.path())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start import");
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let _ = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status();
+    let output = child.wait_with_output().expect("Failed to wait for import");
+    // It finished first, stopped between batches, or the signal came
+    // before it started writing; the repo must be usable either way
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"));
+
+    let output = Command::new(&binary_path)
+        .args(["--output", "json", "stats"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run stats");
+    assert!(output.status.success());
+    let output = Command::new(&binary_path)
+        .args(["search", "Body"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to search");
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_no_auto_reindex_reports_schema_mismatch() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Proxy fix",
+            "--body",
+            "Steps",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+    std::fs::write(temp_dir.path().join(".fukura/index/fukura-schema"), "0").unwrap();
+
+    let output = Command::new(&binary_path)
+        .args(["--no-auto-reindex", "list"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to list notes");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("schema v0"));
+
+    let output = Command::new(&binary_path)
+        .args(["list"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to list notes");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Rebuilding the search index"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Proxy fix"));
+}
+
+#[test]
+fn test_plain_output_is_ascii() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Proxy fix",
+            "--body",
+            "Steps",
+            "--tag",
+            "network",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+
+    for (args, env) in [
+        (vec!["--plain", "stats"], None),
+        (vec!["--plain", "list"], None),
+        (vec!["search", "proxy"], Some("1")),
+        (vec!["view", "@1"], Some("1")),
+    ] {
+        let mut command = Command::new(&binary_path);
+        command
+            .args(&args)
+            .current_dir(temp_dir.path())
+            .env("CLICOLOR_FORCE", "1");
+        if let Some(value) = env {
+            command.env("FUKURA_PLAIN", value);
+        }
+        let output = command.output().expect("Failed to run fuku");
+        assert!(output.status.success(), "fuku {:?} failed", args);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.is_ascii(), "fuku {:?} printed: {}", args, stdout);
+        assert!(!stdout.contains('\x1b'), "fuku {:?} printed colors", args);
+    }
+}
+
+/// Start `fuku serve` for `repo` on a free port, returning it and its URL
+fn spawn_serve(repo: &std::path::Path) -> (std::process::Child, String) {
+    let addr = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+    let server = Command::new(get_binary_path())
+        .args(["serve", "--addr", &addr.to_string()])
+        .current_dir(repo)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to start fuku serve");
+    let started = std::time::Instant::now();
+    while std::net::TcpStream::connect(addr).is_err() {
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    (server, format!("http://{}", addr))
+}
+
+#[test]
+fn test_clone_filters_and_sets_default_remote() {
+    let origin = setup_test_repo();
+    let binary_path = get_binary_path();
+    for (title, tag) in [("Cargo lockfile", "rust"), ("Pod restarts", "k8s")] {
+        let output = Command::new(&binary_path)
+            .args([
+                "add",
+                "--title",
+                title,
+                "--body",
+                "Steps",
+                "--tag",
+                tag,
+                "--no-editor",
+            ])
+            .current_dir(origin.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+    let (mut server, remote) = spawn_serve(origin.path());
+
+    let clone = TempDir::new().unwrap();
+    let target = clone.path().join("vault");
+    let output = Command::new(&binary_path)
+        .args(["--output", "json", "clone", &remote])
+        .arg(&target)
+        .args(["--tag", "rust"])
+        .output()
+        .expect("Failed to clone");
+    server.kill().unwrap();
+    let _ = server.wait();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["notes"], 1);
+    assert_eq!(summary["filtered_out"], 1);
+
+    let output = Command::new(&binary_path)
+        .args(["list"])
+        .current_dir(&target)
+        .output()
+        .expect("Failed to list");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Cargo lockfile"));
+    assert!(!stdout.contains("Pod restarts"));
+
+    let config = std::fs::read_to_string(target.join(".fukura/config")).unwrap();
+    assert!(config.contains(&format!("default_remote = \"{}\"", remote)));
+    assert!(!config.contains("[thin]"));
+}
+
+#[test]
+fn test_thin_clone_fetches_notes_on_view() {
+    let origin = setup_test_repo();
+    let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Proxy fix",
+            "--body",
+            "Set HTTPS_PROXY before running cargo",
+            "--no-editor",
+        ])
+        .current_dir(origin.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+    let object_id = std::fs::read_to_string(origin.path().join(".fukura/refs/latest")).unwrap();
+
+    let (mut server, remote) = spawn_serve(origin.path());
+
+    let thin = TempDir::new().unwrap();
+    let output = Command::new(&binary_path)
+        .args(["clone", &remote, ".", "--thin"])
+        .current_dir(thin.path())
+        .output()
+        .expect("Failed to clone");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let loose = |dir: &std::path::Path| {
+        dir.join(".fukura/objects")
+            .join(&object_id[..2])
+            .join(&object_id[2..])
+    };
+    assert!(!loose(thin.path()).exists());
+
+    let output = Command::new(&binary_path)
+        .args(["search", "proxy"])
+        .current_dir(thin.path())
+        .output()
+        .expect("Failed to search");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Proxy fix"));
+
+    let output = Command::new(&binary_path)
+        .args(["view", &object_id[..8]])
+        .current_dir(thin.path())
+        .output()
+        .expect("Failed to view");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("before running cargo"));
+    assert!(loose(thin.path()).exists());
+
+    let full = TempDir::new().unwrap();
+    let output = Command::new(&binary_path)
+        .args(["--output", "json", "clone", &remote, "."])
+        .current_dir(full.path())
+        .output()
+        .expect("Failed to clone");
+    server.kill().unwrap();
+    let _ = server.wait();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["thin"], false);
+    assert_eq!(summary["fetched"], 1);
+
+    // Everything is local, so the remote isn't needed any more
+    let output = Command::new(&binary_path)
+        .args(["search", "HTTPS_PROXY"])
+        .current_dir(full.path())
+        .output()
+        .expect("Failed to search");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Proxy fix"));
+}
+
+#[test]
+fn test_pin_and_list_pins() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    for title in ["Deploy runbook", "Proxy fix"] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--body", "Steps", "--no-editor"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+
+    let output = Command::new(&binary_path)
+        .args(["pin", "@latest"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to pin");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Pinned") && stdout.contains("Proxy fix"));
+
+    let output = Command::new(&binary_path)
+        .args(["pins"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to list pins");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("📌 Proxy fix"));
+    assert!(!stdout.contains("Deploy runbook"));
+}
+
+#[test]
+fn test_archive_hides_notes_until_restored() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let run = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku");
+        assert!(output.status.success(), "{:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    run(&[
+        "add",
+        "--title",
+        "Captured npm error",
+        "--body",
+        "npm ERR! 404",
+        "--tag",
+        "auto-captured",
+        "--no-editor",
+    ]);
+    run(&[
+        "add",
+        "--title",
+        "Deploy runbook",
+        "--body",
+        "Steps",
+        "--no-editor",
+    ]);
+
+    // Nothing captured is older than a month yet
+    let stdout = run(&["archive", "--query", "tag:auto-captured before:1mo"]);
+    assert!(stdout.contains("Nothing to archive"));
+
+    let stdout = run(&["archive", "--query", "tag:auto-captured"]);
+    assert!(stdout.contains("Archived 1 notes"));
+    let stdout = run(&["list"]);
+    assert!(!stdout.contains("Captured npm error"));
+    assert!(stdout.contains("Deploy runbook"));
+    let stdout = run(&["search", "--archived", "npm"]);
+    assert!(stdout.contains("Captured npm error"));
+
+    let stdout = run(&["archive", "--query", "tag:auto-captured", "--restore"]);
+    assert!(stdout.contains("Restored 1 notes"));
+    assert!(run(&["list"]).contains("Captured npm error"));
+}
+
+#[test]
+fn test_bulk_edits_notes_matching_a_query() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let run = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku");
+        assert!(output.status.success(), "{:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    for title in ["Flaky test timeout", "Flaky port in use"] {
+        run(&[
+            "add",
+            "--title",
+            title,
+            "--body",
+            "retry",
+            "--tag",
+            "auto-captured",
+            "--no-editor",
+        ]);
+    }
+    run(&[
+        "add",
+        "--title",
+        "Deploy runbook",
+        "--body",
+        "Steps",
+        "--no-editor",
+    ]);
+
+    let stdout = run(&[
+        "bulk",
+        "--query",
+        "tag:auto-captured",
+        "--add-tag",
+        "flaky",
+        "--dry-run",
+    ]);
+    assert!(stdout.contains("Would change 2 of 2 matching notes"));
+    assert!(!run(&["search", "--json", "flaky"]).contains("\"flaky\""));
+
+    let stdout = run(&[
+        "bulk",
+        "--query",
+        "tag:auto-captured",
+        "--add-tag",
+        "flaky",
+        "--set-privacy",
+        "org",
+        "--output",
+        "json",
+    ]);
+    let summary: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(summary["matched"], 2);
+    assert_eq!(summary["changed"].as_array().unwrap().len(), 2);
+
+    // Running it again changes nothing
+    let stdout = run(&["bulk", "--query", "tag:flaky", "--add-tag", "flaky"]);
+    assert!(stdout.contains("Changed 0 of 2 matching notes (2 unchanged"));
+    assert!(run(&["search", "--json", "flaky"]).contains("\"flaky\""));
+}
+
+#[test]
+fn test_diff_compares_a_note_with_its_previous_revision() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let run = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    run(&[
+        "add",
+        "--title",
+        "Port in use",
+        "--body",
+        "lsof -i :3000\nkill it",
+        "--no-editor",
+    ]);
+    run(&[
+        "edit",
+        "@latest",
+        "--body",
+        "lsof -i :3000\nkill -9 it",
+        "--add-tag",
+        "network",
+    ]);
+
+    let stdout = run(&["diff", "@latest"]);
+    assert!(stdout.contains("-kill it"));
+    assert!(stdout.contains("+kill -9 it"));
+    assert!(stdout.contains("+#network"));
+    assert!(!stdout.contains("-lsof"));
+}
+
+#[test]
+fn test_ci_annotates_errors_with_known_fixes() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Linker cannot find openssl",
+            "--body",
+            "Solution: install libssl-dev before building",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+
+    let mut child = Command::new(&binary_path)
+        .args(["ci", "--archive"])
+        .current_dir(temp_dir.path())
+        .env_remove("GITHUB_STEP_SUMMARY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run ci");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"   Compiling app v0.1.0\nerror: linker cannot find openssl\n")
+        .unwrap();
+    let output = child.wait_with_output().expect("Failed to wait for ci");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("::error title=fuku%3A 1 known fix::error: linker cannot find openssl"));
+    assert!(stdout.contains("Linker cannot find openssl (fuku view"));
+    assert!(stdout.contains("::notice title=fuku::Archived the failure"));
+}
+
+/// Send a request to `fuku serve`, returning the status code and body
+fn http_request(base: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+    use std::io::{Read, Write};
+    let addr = base.trim_start_matches("http://");
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        addr,
+        body.len(),
+        body
+    )
+    .unwrap();
+    // Objects are binary, so decode lossily
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+/// Body of a `GET` against `fuku serve`
+fn http_get(base: &str, path: &str) -> serde_json::Value {
+    let (status, body) = http_request(base, "GET", path, "");
+    assert_eq!(status, 200, "{}", body);
+    serde_json::from_str(&body).unwrap()
+}
+
+#[test]
+fn test_serve_hides_notes_from_private_zones() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let zone = temp_dir.path().join("clients");
+    std::fs::create_dir(&zone).unwrap();
+    let config = temp_dir.path().join(".fukura").join("config");
+    let mut content = std::fs::read_to_string(&config).unwrap();
+    content.push_str(&format!(
+        "\n[[capture.zones]]\npath = {:?}\nmode = \"private\"\n",
+        zone.display().to_string()
+    ));
+    std::fs::write(&config, content).unwrap();
+
+    let mut ids = Vec::new();
+    for (title, dir) in [
+        ("Acme VPN", &zone),
+        ("Proxy fix", &temp_dir.path().to_path_buf()),
+    ] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--body", "Steps", "--no-editor"])
+            .current_dir(dir)
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+        ids.push(std::fs::read_to_string(temp_dir.path().join(".fukura/refs/latest")).unwrap());
+    }
+    let (mut server, base) = spawn_serve(temp_dir.path());
+
+    let page = http_get(&base, "/notes");
+    let served = |id: &str| -> Vec<u16> {
+        ["/notes/", "/v1/notes/", "/v1/objects/"]
+            .iter()
+            .map(|route| http_request(&base, "GET", &format!("{}{}", route, id), "").0)
+            .collect()
+    };
+    let (private, shared) = (served(&ids[0]), served(&ids[1]));
+    server.kill().unwrap();
+    let _ = server.wait();
+
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["notes"][0]["title"], "Proxy fix");
+    assert_eq!(private, vec![404, 404, 404]);
+    assert_eq!(shared, vec![200, 200, 200]);
+}
+
+#[test]
+fn test_tag_list_counts_and_sorts() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    for (title, tags) in [
+        ("Cargo lockfile", ["rust", "cargo"]),
+        ("Borrow checker", ["rust", "lifetimes"]),
+    ] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--body", "Steps", "--no-editor"])
+            .args(["--tag", tags[0], "--tag", tags[1]])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+    let tag_list = |args: &[&str]| -> Vec<serde_json::Value> {
+        let output = Command::new(&binary_path)
+            .args(["--output", "json", "tag", "list"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run tag list");
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let tags = tag_list(&[]);
+    assert_eq!(tags.len(), 3);
+    assert_eq!(tags[0]["tag"], "rust");
+    assert_eq!(tags[0]["notes"], 2);
+    assert!(tags[0]["last_used"].is_string());
+
+    let names: Vec<_> = tag_list(&["--sort", "name"])
+        .iter()
+        .map(|tag| tag["tag"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["cargo", "lifetimes", "rust"]);
+    assert_eq!(tag_list(&["--limit", "1"]).len(), 1);
+
+    let output = Command::new(&binary_path)
+        .args(["tag", "list"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to run tag list");
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Tags (3 total)"));
+    assert!(text.contains("lifetimes"));
+}
+
+#[test]
+fn test_serve_exposes_team_stats_and_tags() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    for (title, tags) in [
+        ("Cargo lockfile", ["rust", "cargo"]),
+        ("Borrow checker", ["rust", "lifetimes"]),
+    ] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--body", "Steps", "--no-editor"])
+            .args(["--tag", tags[0], "--tag", tags[1]])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+    let (mut server, base) = spawn_serve(temp_dir.path());
+    let tags = http_get(&base, "/tags?limit=2");
+    let stats = http_get(&base, "/stats?days=7&limit=1");
+    server.kill().unwrap();
+    let _ = server.wait();
+
+    assert_eq!(tags["total"], 3);
+    assert_eq!(tags["tags"][0]["tag"], "rust");
+    assert_eq!(tags["tags"][0]["notes"], 2);
+    assert!(tags["tags"][0]["last_used"].is_string());
+    assert_eq!(tags["tags"].as_array().unwrap().len(), 2);
+
+    assert_eq!(stats["notes"], 2);
+    assert_eq!(stats["tags"], 3);
+    assert_eq!(stats["activity"]["days"], 7);
+    let per_day: u64 = stats["activity"]["updated_per_day"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|count| count.as_u64().unwrap())
+        .sum();
+    assert_eq!(per_day, 2);
+    assert_eq!(stats["activity"]["recent"].as_array().unwrap().len(), 1);
+    assert!(stats["top_patterns"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_serve_posts_webhooks_on_note_changes() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    let temp_dir = setup_test_repo();
+
+    // Receiver answering every delivery with 204, reporting event and body
+    let receiver = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let hook_url = format!("http://{}/hook", receiver.local_addr().unwrap());
+    let (sender, deliveries) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in receiver.incoming().flatten() {
+            let mut reader = BufReader::new(stream);
+            let (mut event, mut length) = (String::new(), 0);
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(": ").unwrap_or((line, ""));
+                match name.to_ascii_lowercase().as_str() {
+                    "x-fukura-event" => event = value.to_string(),
+                    "content-length" => length = value.parse().unwrap(),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = reader.into_inner();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            if sender.send((event, body)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let config_path = temp_dir.path().join(".fukura").join("config");
+    let mut config = std::fs::read_to_string(&config_path).unwrap();
+    config.push_str(&format!(
+        "\n[[webhooks]]\nurl = \"{}\"\nevents = [\"created\", \"deleted\"]\n",
+        hook_url
+    ));
+    std::fs::write(&config_path, config).unwrap();
+
+    let (mut server, base) = spawn_serve(temp_dir.path());
+    let note = serde_json::json!({
+        "object_id": "",
+        "note": {
+            "title": "Restart the queue",
+            "body": "kubectl rollout restart deploy/queue",
+            "tags": ["runbook"],
+            "privacy": "public",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "author": { "name": "ops" }
+        }
+    });
+    let (status, created) = http_request(&base, "POST", "/notes", &note.to_string());
+    assert_eq!(status, 200, "{}", created);
+    let created: serde_json::Value = serde_json::from_str(&created).unwrap();
+    let id = created["object_id"].as_str().unwrap().to_string();
+
+    let mut edited = note.clone();
+    edited["note"]["body"] = "kubectl rollout restart deploy/queue -n prod".into();
+    let (status, updated) =
+        http_request(&base, "PUT", &format!("/notes/{}", id), &edited.to_string());
+    assert_eq!(status, 200, "{}", updated);
+    let updated: serde_json::Value = serde_json::from_str(&updated).unwrap();
+    let updated_id = updated["object_id"].as_str().unwrap().to_string();
+    assert_ne!(updated_id, id);
+
+    let (status, _) = http_request(&base, "DELETE", &format!("/notes/{}", updated_id), "");
+    assert_eq!(status, 204);
+
+    let timeout = std::time::Duration::from_secs(10);
+    let (event, body) = deliveries.recv_timeout(timeout).unwrap();
+    assert_eq!(event, "created");
+    assert_eq!(body["object_id"], id);
+    assert_eq!(body["note"]["title"], "Restart the queue");
+    // Updates aren't subscribed to
+    let (event, body) = deliveries.recv_timeout(timeout).unwrap();
+    server.kill().unwrap();
+    let _ = server.wait();
+    assert_eq!(event, "deleted");
+    assert_eq!(body["object_id"], updated_id);
+}
+
+#[test]
+fn test_serve_feed_lists_shared_notes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    for (title, privacy) in [
+        ("Rotate the TLS cert", "org"),
+        ("My salary notes", "private"),
+    ] {
+        let output = Command::new(&binary_path)
+            .args(["add", "--title", title, "--no-editor"])
+            .args(["--body", "## Steps\n\nRun `make certs` & restart"])
+            .args(["--tag", "ops", "--privacy", privacy])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to add note");
+        assert!(output.status.success());
+    }
+    let (mut server, base) = spawn_serve(temp_dir.path());
+    let (status, feed) = http_request(&base, "GET", "/feed.xml?limit=5", "");
+    server.kill().unwrap();
+    let _ = server.wait();
+
+    assert_eq!(status, 200);
+    assert!(feed.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert_eq!(feed.matches("<entry>").count(), 1);
+    assert!(feed.contains("<title>Rotate the TLS cert</title>"));
+    assert!(!feed.contains("salary"));
+    assert!(feed.contains("<category term=\"ops\"/>"));
+    // Rendered HTML, escaped once for the XML
+    assert!(feed.contains("&lt;h2&gt;Steps&lt;/h2&gt;"));
+    assert!(feed.contains("&amp;amp; restart"));
+    assert!(feed.contains(&format!("href=\"{}/notes/", base)));
+}
+
+#[test]
+fn test_audit_log_records_changes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let fuku = |args: &[&str]| {
+        let output = Command::new(&binary_path)
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "Auditor")
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku");
+        assert!(
+            output.status.success(),
+            "fuku {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    };
+    fuku(&[
+        "add",
+        "--title",
+        "Audit me",
+        "--body",
+        "Body",
+        "--no-editor",
+    ]);
+    fuku(&["edit", "@latest", "--add-tag", "compliance"]);
+    fuku(&["config", "redact", "--set", "api_key=KEY-[0-9]+"]);
+
+    let output = fuku(&["--output", "json", "audit"]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let actions: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    // `init` wrote the first config
+    assert_eq!(actions, ["config", "add", "edit", "redaction"]);
+    let edit = &entries[2];
+    assert!(edit["actor"].as_str().unwrap().starts_with("Auditor"));
+    assert_eq!(edit["object_ids"][0], entries[1]["object_ids"][0]);
+    assert_eq!(entries[3]["detail"], "api_key");
+
+    let latest = std::fs::read_to_string(temp_dir.path().join(".fukura/refs/latest")).unwrap();
+    let output = fuku(&[
+        "--output",
+        "json",
+        "audit",
+        "--id",
+        latest.trim(),
+        "--action",
+        "edit",
+        "--since",
+        "1h",
+    ]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_signed_notes_verify_on_pull() {
+    let alice_home = TempDir::new().unwrap();
+    let bob_home = TempDir::new().unwrap();
+    let origin = setup_test_repo();
+    let local = setup_test_repo();
+    let fuku = |home: &TempDir, repo: &TempDir, args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .env("FUKURA_HOME", home.path())
+            .current_dir(repo.path())
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    let output = fuku(
+        &alice_home,
+        &origin,
+        &["--output", "json", "keys", "generate"],
+    );
+    assert!(output.status.success());
+    let generated: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let public_key = generated["public_key"].as_str().unwrap().to_string();
+    assert_eq!(public_key.len(), 64);
+    let output = fuku(&alice_home, &origin, &["keys", "show"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), public_key);
+    assert!(fuku(
+        &alice_home,
+        &origin,
+        &[
+            "add",
+            "--title",
+            "Failover runbook",
+            "--body",
+            "Promote the replica",
+            "--no-editor",
+        ],
+    )
+    .status
+    .success());
+    let object_id = std::fs::read_to_string(origin.path().join(".fukura/refs/latest")).unwrap();
+    let output = fuku(&alice_home, &origin, &["view", &object_id[..8]]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("✓ verified"));
+
+    let (mut server, remote) = spawn_serve(origin.path());
+    std::fs::write(
+        local.path().join(".fukura/config"),
+        "version = 1\n\n[signing]\nrequire_signed = true\n",
+    )
+    .unwrap();
+    let pull = || {
+        fuku(
+            &bob_home,
+            &local,
+            &["pull", &object_id, "--remote", &remote],
+        )
+    };
+    let output = pull();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't signed by a trusted key"));
+
+    assert!(
+        !fuku(&bob_home, &local, &["keys", "trust", "alice", "not-a-key"])
+            .status
+            .success()
+    );
+    assert!(
+        fuku(&bob_home, &local, &["keys", "trust", "alice", &public_key])
+            .status
+            .success()
+    );
+    let output = pull();
+    server.kill().unwrap();
+    let _ = server.wait();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = fuku(&bob_home, &local, &["view", &object_id[..8]]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("✓ verified (alice)"));
+    let output = fuku(&bob_home, &local, &["search", "failover"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(" ✓"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plugins_run_as_notes_are_added() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = TempDir::new().unwrap();
+    let repo = setup_test_repo();
+    let hooks = repo.path().join(".fukura/hooks");
+    std::fs::create_dir_all(&hooks).unwrap();
+    let install = |name: &str, script: &str| {
+        let path = hooks.join(name);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    install(
+        "pre-add",
+        "#!/bin/sh\nnote=$(cat)\ncase \"$note\" in *TICKET-*) printf '%s' \"$note\" ;; *) echo 'Notes need a ticket number' >&2; exit 1 ;; esac\n",
+    );
+    install(
+        "post-add",
+        "#!/bin/sh\ncat >/dev/null\necho \"$FUKURA_OBJECT_ID\" >> added.log\n",
+    );
+    let add = |title: &str| {
+        Command::new(get_binary_path())
+            .args([
+                "add",
+                "--title",
+                title,
+                "--body",
+                "Restart it",
+                "--no-editor",
+            ])
+            .env("FUKURA_HOME", home.path())
+            .current_dir(repo.path())
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    let rejected = add("Queue stuck");
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("Notes need a ticket number"));
+    assert!(!repo.path().join("added.log").exists());
+
+    assert!(add("TICKET-42 Queue stuck").status.success());
+    let object_id = std::fs::read_to_string(repo.path().join(".fukura/refs/latest")).unwrap();
+    let log = std::fs::read_to_string(repo.path().join("added.log")).unwrap();
+    assert_eq!(log.trim(), object_id.trim());
+
+    // Imports go through the same plugins
+    let vault = repo.path().join("vault");
+    std::fs::create_dir(&vault).unwrap();
+    std::fs::write(vault.join("a.md"), "# TICKET-7 Disk full\n\nPrune images").unwrap();
+    std::fs::write(vault.join("b.md"), "# Cache cold\n\nWarm it up").unwrap();
+    let import = Command::new(get_binary_path())
+        .args(["import", "vault"])
+        .env("FUKURA_HOME", home.path())
+        .current_dir(repo.path())
+        .output()
+        .expect("Failed to run fuku");
+    assert!(import.status.success());
+    let stdout = String::from_utf8_lossy(&import.stdout);
+    assert!(stdout.contains("Imported: 1"), "{}", stdout);
+    assert!(stdout.contains("Notes need a ticket number"));
+    let log = std::fs::read_to_string(repo.path().join("added.log")).unwrap();
+    assert_eq!(log.lines().count(), 2);
+}
+
+#[test]
+fn test_explain_finds_fixes_for_piped_errors() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let output = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Linker cannot find openssl",
+            "--body",
+            "Solution: install libssl-dev before building",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to add note");
+    assert!(output.status.success());
+
+    let explain = |input: &[u8]| {
+        let mut child = Command::new(&binary_path)
+            .args(["--output", "json", "explain"])
+            .current_dir(temp_dir.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run explain");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child
+            .wait_with_output()
+            .expect("Failed to wait for explain")
+    };
+
+    let output = explain(b"   Compiling app v0.1.0\nerror: linker cannot find openssl\n");
+    assert!(output.status.success());
+    let explained: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(explained["error"], "error: linker cannot find openssl");
+    assert_eq!(explained["source"], "stdin");
+    assert_eq!(
+        explained["solutions"][0]["title"],
+        "Linker cannot find openssl"
+    );
+    assert!(explained["solutions"][0]["snippet"]
+        .as_str()
+        .unwrap()
+        .contains("libssl-dev"));
+
+    assert!(!explain(b"\n").status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_capture_tmux_attaches_to_recording_or_creates_note() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+
+    // A stand-in for tmux that prints a psql session
+    let bin = TempDir::new().unwrap();
+    let tmux = bin.path().join("tmux");
+    std::fs::write(
+        &tmux,
+        "#!/bin/sh\ncase \"$1\" in\n  display-message) echo 'work:1.0' ;;\n  capture-pane) printf '$ psql -h db\\npsql: error: password authentication failed\\ndb=> \\n\\n' ;;\nesac\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&tmux, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let fuku = |args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .env("PATH", &path)
+            .env_remove("TMUX")
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    let outside = fuku(&["capture", "--tmux"]);
+    assert!(!outside.status.success());
+    assert!(String::from_utf8_lossy(&outside.stderr).contains("Not inside tmux"));
+
+    assert!(fuku(&["capture", "--tmux", "--pane", "work:1.0"])
+        .status
+        .success());
+    let record: serde_json::Value =
+        serde_json::from_slice(&fuku(&["view", "@latest", "--json"]).stdout).unwrap();
+    assert_eq!(
+        record["note"]["title"],
+        "Error in work:1.0: psql: error: password authentication failed"
+    );
+    assert!(record["note"]["body"]
+        .as_str()
+        .unwrap()
+        .contains("$ psql -h db"));
+
+    assert!(fuku(&["rec", "Debug db login"]).status.success());
+    let attached = fuku(&["capture", "--tmux", "--pane", "work:1.0"]);
+    assert!(String::from_utf8_lossy(&attached.stdout).contains("to recording 'default'"));
+    assert!(fuku(&["done"]).status.success());
+    let record: serde_json::Value =
+        serde_json::from_slice(&fuku(&["view", "@latest", "--json"]).stdout).unwrap();
+    assert_eq!(record["note"]["title"], "Debug db login");
+    let body = record["note"]["body"].as_str().unwrap();
+    assert!(body.contains("Terminal Output"));
+    assert!(body.contains("password authentication failed"));
+    let attachments = temp_dir.path().join(".fukura").join("attachments");
+    assert!(attachments.read_dir().unwrap().next().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rec_cast_records_terminal_into_the_note() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let fuku = |args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    let rec = fuku(&[
+        "rec",
+        "Cast demo",
+        "--cast",
+        "--",
+        "sh",
+        "-c",
+        "echo cast-output",
+    ]);
+    assert!(rec.status.success());
+    assert!(String::from_utf8_lossy(&rec.stdout).contains("cast-output"));
+    assert!(fuku(&["done"]).status.success());
+
+    let record: serde_json::Value =
+        serde_json::from_slice(&fuku(&["view", "@latest", "--json"]).stdout).unwrap();
+    assert!(record["note"]["meta"]["cast_attachment"].is_string());
+    let html = fuku(&["view", "@latest", "--format", "html"]);
+    let html = String::from_utf8_lossy(&html.stdout);
+    assert!(html.contains("AsciinemaPlayer.create"));
+    assert!(html.contains("cast-output"));
+}
+
+#[test]
+fn test_read_only_repo_searches_but_refuses_changes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    let fuku = |args: &[&str]| {
+        Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .env_remove("FUKURA_READ_ONLY")
+            .output()
+            .expect("Failed to run fuku")
+    };
+
+    let add = fuku(&[
+        "add",
+        "--title",
+        "Redis timeout",
+        "--body",
+        "Raise the pool size",
+        "--no-editor",
+    ]);
+    assert!(add.status.success());
+
+    let search = fuku(&["--read-only", "search", "redis"]);
+    assert!(search.status.success());
+    assert!(String::from_utf8_lossy(&search.stdout).contains("Redis timeout"));
+    assert!(fuku(&["--read-only", "view", "@latest"]).status.success());
+
+    let refused = fuku(&[
+        "--read-only",
+        "add",
+        "--title",
+        "Another",
+        "--body",
+        "Body",
+        "--no-editor",
+    ]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("read-only"));
+    assert!(!fuku(&["--read-only", "pin", "@latest"]).status.success());
+    // Recordings, auto-sync and activity tracking write under .fukura too
+    for args in [
+        &["--read-only", "rec", "Fix redis"][..],
+        &["--read-only", "sync", "--enable-auto"],
+        &["--read-only", "sync", "--disable-auto"],
+        &["--read-only", "track", "--start"],
+    ] {
+        let refused = fuku(args);
+        assert!(!refused.status.success(), "{:?} succeeded", args);
+        assert!(String::from_utf8_lossy(&refused.stderr).contains("read-only"));
+    }
+    assert!(fuku(&["--read-only", "rec", "--status"]).status.success());
+
+    // The same from the config
+    let config = temp_dir.path().join(".fukura").join("config");
+    let mut content = std::fs::read_to_string(&config).unwrap();
+    content.insert_str(0, "read_only = true\n");
+    std::fs::write(&config, content).unwrap();
+    assert!(fuku(&["search", "redis"]).status.success());
+    assert!(!fuku(&["pin", "@latest"]).status.success());
+}
+
+#[test]
+fn test_read_only_repo_refuses_config_changes() {
+    let temp_dir = setup_test_repo();
+    let binary_path = get_binary_path();
+    // A read-only repository needs its search index built beforehand
+    let add = Command::new(&binary_path)
+        .args([
+            "add",
+            "--title",
+            "Redis timeout",
+            "--body",
+            "Raise it",
+            "--no-editor",
+        ])
+        .current_dir(temp_dir.path())
+        .env_remove("FUKURA_READ_ONLY")
+        .output()
+        .expect("Failed to run fuku");
+    assert!(add.status.success());
+    let config = temp_dir.path().join(".fukura").join("config");
+    let before = std::fs::read_to_string(&config).unwrap();
+
+    for args in [
+        &["config", "remote", "--set", "https://hub.example.com"][..],
+        &["config", "remote", "--clear"],
+        &["config", "redact", "--set", "api_key=KEY-[0-9]+"],
+        &["config", "redact", "--unset", "api_key"],
+        &["config", "analyzer", "cjk"],
+    ] {
+        let refused = Command::new(&binary_path)
+            .args(args)
+            .current_dir(temp_dir.path())
+            .env("FUKURA_READ_ONLY", "1")
+            .output()
+            .expect("Failed to run fuku");
+        assert!(!refused.status.success(), "{:?} succeeded", args);
+        assert!(String::from_utf8_lossy(&refused.stderr).contains("but not changed"));
+    }
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), before);
+
+    let shown = Command::new(&binary_path)
+        .args(["config", "analyzer"])
+        .current_dir(temp_dir.path())
+        .env("FUKURA_READ_ONLY", "1")
+        .output()
+        .expect("Failed to run fuku");
+    assert!(shown.status.success());
 }
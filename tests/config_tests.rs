@@ -210,6 +210,7 @@ fn test_recording_config_serialization() {
     let config = RecordingConfig {
         max_lookback_hours: 24,
         min_lookback_minutes: 15,
+        auto_start_after: Some(3),
     };
 
     // Test that it can be serialized and deserialized
@@ -220,6 +221,7 @@ fn test_recording_config_serialization() {
 
     assert_eq!(deserialized.max_lookback_hours, 24);
     assert_eq!(deserialized.min_lookback_minutes, 15);
+    assert_eq!(deserialized.auto_start_after, Some(3));
 }
 
 #[test]
@@ -255,3 +257,19 @@ fn test_reasonable_config_limits() {
         "Minimum lookback should be less than maximum lookback"
     );
 }
+
+#[test]
+fn test_gc_thresholds() {
+    use fukura::pack::LooseUsage;
+
+    let config: FukuraConfig = toml::from_str("version = 1\n[gc]\nauto_loose_bytes = 0\n").unwrap();
+    assert_eq!(config.gc.auto_loose_objects, 500);
+    assert_eq!(config.gc.idle_minutes, 10);
+
+    let usage = |objects, bytes| LooseUsage { objects, bytes };
+    assert!(!config.gc.is_due(&usage(499, u64::MAX)));
+    assert!(config.gc.is_due(&usage(500, 0)));
+    assert!(FukuraConfig::default()
+        .gc
+        .is_due(&usage(1, 8 * 1024 * 1024)));
+}
@@ -45,6 +45,31 @@ fn test_cli_init() {
     assert!(repo_path.join(".fukura").exists());
 }
 
+#[test]
+fn test_cli_init_ensure_is_idempotent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let binary_path = get_binary_path();
+
+    let ensure = || {
+        let output = Command::new(&binary_path)
+            .args(["init", "--ensure", "--daemon", "off", "--output", "json"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let first = ensure();
+    assert_eq!(first["created"], true);
+    assert_eq!(first["daemon"], "off");
+    assert!(temp_dir.path().join(".fukura").exists());
+
+    let second = ensure();
+    assert_eq!(second["created"], false);
+    assert_eq!(second["root"], first["root"]);
+}
+
 #[test]
 fn test_cli_version() {
     let binary_path = get_binary_path();
@@ -1,30 +1,19 @@
-use chrono::Utc;
-use fukura::models::{Author, Note, Privacy};
+use fukura::models::{Author, Note};
+use fukura::note_service::NoteBuilder;
 use fukura::repo::FukuraRepo;
-use std::collections::BTreeMap;
 use std::time::Instant;
 use tempfile::TempDir;
 
 fn create_test_note(title: &str, body: &str) -> Note {
-    let now = Utc::now();
-    let mut meta = BTreeMap::new();
-    meta.insert("test".into(), "performance".into());
-
-    Note {
-        title: title.into(),
-        body: body.into(),
-        tags: vec!["performance".into(), "test".into()],
-        links: vec![],
-        meta,
-        solutions: vec![],
-        privacy: Privacy::Private,
-        created_at: now,
-        updated_at: now,
-        author: Author {
+    NoteBuilder::new(title, body)
+        .tag("performance")
+        .tag("test")
+        .meta("test", "performance")
+        .author(Author {
             name: "Performance Tester".into(),
             email: Some("perf@test.com".into()),
-        },
-    }
+        })
+        .build()
 }
 
 #[test]